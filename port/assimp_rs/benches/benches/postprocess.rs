@@ -0,0 +1,66 @@
+//! The main post-processing steps, one at a time on imported scenes
+
+use assimp_rs::postprocess::{AiPostProcessSteps as Steps, pipeline::PostProcessPipeline};
+use assimp_rs_bench::{SCENES, import_x};
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+
+const STEPS: &[(&str, Steps)] = &[
+    ("ValidateDataStructure", Steps::ValidateDataStructure),
+    ("MakeLeftHanded", Steps::MakeLeftHanded),
+    ("FlipUVs", Steps::FlipUVs),
+    ("FlipWindingOrder", Steps::FlipWindingOrder),
+    ("PopulateArmatureData", Steps::PopulateArmatureData),
+    (
+        "GenNormals",
+        Steps::GenNormals.union(Steps::ForceGenNormals),
+    ),
+    (
+        "GenSmoothNormals",
+        Steps::GenSmoothNormals.union(Steps::ForceGenNormals),
+    ),
+    ("ImproveCacheLocality", Steps::ImproveCacheLocality),
+    ("GenBoundingBoxes", Steps::GenBoundingBoxes),
+];
+
+fn steps(c: &mut Criterion) {
+    let scenes: Vec<_> = SCENES.iter().map(|&name| (name, import_x(name))).collect();
+    for &(step, flags) in STEPS {
+        let mut group = c.benchmark_group(step);
+        let pipeline = PostProcessPipeline::new(flags);
+        for (name, scene) in &scenes {
+            group.bench_with_input(BenchmarkId::from_parameter(name), scene, |b, scene| {
+                b.iter_batched(
+                    || scene.clone(),
+                    |mut scene| {
+                        pipeline.run(&mut scene);
+                        scene
+                    },
+                    BatchSize::LargeInput,
+                );
+            });
+        }
+        group.finish();
+    }
+}
+
+fn preset(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Preset_TargetRealtime_MaxQuality");
+    let pipeline = PostProcessPipeline::new(Steps::Preset_TargetRealtime_MaxQuality);
+    for &name in SCENES {
+        let scene = import_x(name);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &scene, |b, scene| {
+            b.iter_batched(
+                || scene.clone(),
+                |mut scene| {
+                    pipeline.run(&mut scene);
+                    scene
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, steps, preset);
+criterion_main!(benches);
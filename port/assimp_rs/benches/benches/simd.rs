@@ -0,0 +1,40 @@
+//! The vertex kernels of `core::simd` on a million vertices, run with and
+//! without `--features assimp_rs/simd` to compare, single precision only
+
+use assimp_rs::{core::simd, math::Vec3};
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+
+const COUNT: usize = 1_000_000;
+
+fn vertices() -> Vec<Vec3> {
+    (0..COUNT)
+        .map(|i| {
+            let t = i as f32 * 0.001;
+            Vec3::new(t.sin(), t.cos(), t * 0.5)
+        })
+        .collect()
+}
+
+fn kernels(c: &mut Criterion) {
+    let vertices = vertices();
+    let mut group = c.benchmark_group(if simd::ENABLED { "simd" } else { "scalar" });
+    let kernels: &[(&str, fn(&mut [Vec3]))] = &[
+        ("flip_z", simd::flip_z),
+        ("flip_v", simd::flip_v),
+        ("normalize", simd::normalize),
+    ];
+    for &(name, kernel) in kernels {
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || vertices.clone(),
+                |v| kernel(v),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.bench_function("bounds", |b| b.iter(|| simd::bounds(black_box(&vertices))));
+    group.finish();
+}
+
+criterion_group!(benches, kernels);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//! Parsing and scene conversion of X files in all encodings
+
+use std::hint::black_box;
+
+use assimp_rs::{
+    formats::x::{importer::Importer, parser::Parser},
+    structs::scene::AiScene,
+    traits::importer::trait_define::InternalImporter,
+};
+use assimp_rs_bench::{X_BINARY, X_COMPRESSED, X_TEXT, compress_x, read_model};
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+/// The assets of all encodings, named by their encoding and file name
+fn assets() -> Vec<(String, Vec<u8>)> {
+    let files = [
+        ("text", X_TEXT),
+        ("binary", X_BINARY),
+        ("compressed", X_COMPRESSED),
+    ];
+    let mut assets: Vec<_> = files
+        .into_iter()
+        .flat_map(|(encoding, names)| {
+            names
+                .iter()
+                .map(move |name| (format!("{encoding}/{name}"), read_model("X", name)))
+        })
+        .collect();
+    assets.push((
+        "compressed/anim_test.x".to_owned(),
+        compress_x("anim_test.x"),
+    ));
+    assets
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("x_parse");
+    for (name, data) in assets() {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            b.iter(|| Parser::parse(black_box(data)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn convert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("x_convert");
+    for (name, data) in assets() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            b.iter_batched(
+                || Parser::parse(data).unwrap().scene,
+                |scene| {
+                    let mut ai_scene = AiScene::default();
+                    Importer::to_ai_scene(scene, &mut ai_scene).unwrap();
+                    ai_scene
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn import(c: &mut Criterion) {
+    let mut group = c.benchmark_group("x_import");
+    for (name, data) in assets() {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            b.iter(|| {
+                let mut scene = AiScene::default();
+                Importer::import_from_buf(black_box(data), &mut scene).unwrap();
+                scene
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, convert, import);
+criterion_main!(benches);
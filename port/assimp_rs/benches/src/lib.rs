@@ -0,0 +1,55 @@
+//! Assets shared by the benchmarks, run them with
+//!
+//! ```text
+//! cargo bench --manifest-path benches/Cargo.toml
+//! ```
+
+use std::fs;
+
+use assimp_rs::{
+    formats::x::{errors::XFileImportError, exporter::Exporter, importer::Importer},
+    get_model_path,
+    structs::{exporter::ExportProperties, scene::AiScene},
+    traits::importer::trait_define::InternalImporter,
+};
+
+/// Text files of the X test models, from small to large
+pub const X_TEXT: &[&str] = &[
+    "test_cube_text.x",
+    "kwxport_test_cubewithvcolors.x",
+    "BCN_Epileptic.X",
+    "anim_test.x",
+];
+
+/// Binary files of the X test models, from small to large
+pub const X_BINARY: &[&str] = &["test_cube_binary.x", "fromtruespace_bin32.x"];
+
+/// Compressed files of the X test models, see [`compress_x`] for larger ones
+pub const X_COMPRESSED: &[&str] = &["test_cube_compressed.x"];
+
+/// Scenes for the post-processing steps, from small to large
+pub const SCENES: &[&str] = &["kwxport_test_cubewithvcolors.x", "anim_test.x"];
+
+pub fn read_model(format: &str, name: &str) -> Vec<u8> {
+    let path = get_model_path(format, name);
+    fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()))
+}
+
+pub fn import_x(name: &str) -> AiScene {
+    let mut scene = AiScene::default();
+    Importer::import_from_buf(&read_model("X", name), &mut scene)
+        .unwrap_or_else(|e: XFileImportError| panic!("Failed to import {name}: {e}"));
+    scene
+}
+
+/// The scene of an X test model exported as compressed text, there is no
+/// large compressed test model
+pub fn compress_x(name: &str) -> Vec<u8> {
+    let scene = import_x(name);
+    let properties = ExportProperties::default();
+    let mut data = Vec::new();
+    Exporter::new(&scene, &properties)
+        .write_compressed(&mut data)
+        .unwrap_or_else(|e| panic!("Failed to compress {name}: {e}"));
+    data
+}
@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    assimp_rs::fuzzing::fuzz_import::<assimp_rs::formats::m3d::importer::Importer, _>(data);
+});
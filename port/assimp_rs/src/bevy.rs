@@ -0,0 +1,414 @@
+//! Conversions of imported scenes to Bevy meshes, materials and entities,
+//! to load X and the other formats of the build at runtime without going
+//! through glTF. Like Bevy, assimp_rs uses right-handed, y-up coordinates,
+//! so the scenes are used as they are imported.
+//!
+//! [`AssimpPlugin`] registers a [`SceneLoader`] for each format, which
+//! loads files as [`Scene`] assets with the meshes and materials as labeled
+//! assets `Mesh{i}` and `Material{i}`:
+//!
+//! ```ignore
+//! app.add_plugins((DefaultPlugins, AssimpPlugin::default()));
+//!
+//! fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+//!     commands.spawn(SceneRoot(asset_server.load("dwarf.x")));
+//! }
+//! ```
+//!
+//! Scenes imported otherwise are spawned with [`spawn_scene`].
+
+// the casts convert from `AiReal`, which is `f32` unless the library is
+// built with `double_precision`
+#![allow(clippy::unnecessary_cast)]
+
+use core::marker::PhantomData;
+use std::path::Path;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use thiserror::Error;
+
+#[cfg(feature = "m3d")]
+use crate::formats::m3d;
+#[cfg(feature = "usd")]
+use crate::formats::usd;
+#[cfg(feature = "vrml")]
+use crate::formats::vrml;
+#[cfg(feature = "x_file")]
+use crate::formats::x;
+use crate::{
+    AiReal, decompose,
+    structs::{
+        importer::ImportProperties,
+        importer_desc::ImporterDesc,
+        material::{AI_MATKEY_TWOSIDED, AiMaterial, TextureSlot},
+        mesh::AiMesh,
+        nodes::Index,
+        scene::{AiNode, AiScene},
+        vertex_layout::IndexBuffer,
+    },
+    traits::importer::trait_define::Importer,
+};
+
+/// Converts a vector of assimp_rs, which uses its own version of glam
+fn vec3(v: impl Into<[AiReal; 3]>) -> [f32; 3] {
+    v.into().map(|c| c as f32)
+}
+
+/// Converts the triangles of `mesh` with its positions, normals, tangents,
+/// first texture coordinates and first vertex colors. Polygons are
+/// triangulated as fans, points and lines are dropped.
+pub fn mesh(mesh: &AiMesh) -> Mesh {
+    let mut out = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        mesh.vertices.iter().map(|&v| vec3(v)).collect::<Vec<_>>(),
+    );
+    if mesh.has_normals() {
+        out.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            mesh.normals.iter().map(|&v| vec3(v)).collect::<Vec<_>>(),
+        );
+    }
+    if mesh.has_normals() && mesh.has_tangents_and_bitangents() {
+        // bevy stores the handedness of the bitangent in w
+        let tangents = (mesh.tangents.iter().zip(&mesh.bitangents))
+            .zip(&mesh.normals)
+            .map(|((&t, &b), &n)| {
+                let w = if n.cross(t).dot(b) < 0.0 { -1.0 } else { 1.0 };
+                let [x, y, z] = vec3(t);
+                [x, y, z, w]
+            })
+            .collect::<Vec<_>>();
+        out.insert_attribute(Mesh::ATTRIBUTE_TANGENT, tangents);
+    }
+    if mesh.has_texture_coords(0) {
+        // the origin of texture coordinates is at the top left in bevy
+        let uvs = mesh.texture_coords[0]
+            .iter()
+            .map(|uv| [uv.x as f32, 1.0 - uv.y as f32])
+            .collect::<Vec<_>>();
+        out.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    if mesh.has_vertex_colors(0) {
+        let colors = mesh.colors[0]
+            .iter()
+            .map(|c| c.to_array())
+            .collect::<Vec<_>>();
+        out.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+    out.insert_indices(match mesh.index_buffer() {
+        IndexBuffer::U16(indices) => Indices::U16(indices),
+        IndexBuffer::U32(indices) => Indices::U32(indices),
+    });
+    out
+}
+
+/// Converts the metallic-roughness view of `material`, see
+/// [`AiMaterial::to_pbr`]. `load_texture` provides the images of the
+/// texture slots, e.g. with `AssetServer::load` for external files.
+pub fn material(
+    material: &AiMaterial,
+    mut load_texture: impl FnMut(&TextureSlot) -> Option<Handle<Image>>,
+) -> StandardMaterial {
+    let pbr = material.to_pbr();
+    let mut texture = |slot: &Option<TextureSlot>| slot.as_ref().and_then(&mut load_texture);
+    let [r, g, b, a] = pbr.base_color.to_array().map(|c| c as f32);
+    let [er, eg, eb] = vec3(pbr.emissive * pbr.emissive_intensity);
+    let double_sided = material.get_int(AI_MATKEY_TWOSIDED).is_some_and(|v| v != 0);
+    StandardMaterial {
+        base_color: Color::linear_rgba(r, g, b, a),
+        base_color_texture: texture(&pbr.base_color_texture),
+        metallic: pbr.metallic as f32,
+        perceptual_roughness: pbr.roughness as f32,
+        metallic_roughness_texture: texture(&pbr.metallic_roughness_texture),
+        emissive: LinearRgba::rgb(er, eg, eb),
+        emissive_texture: texture(&pbr.emissive_texture),
+        normal_map_texture: texture(&pbr.normal_texture),
+        occlusion_texture: texture(&pbr.occlusion_texture),
+        clearcoat: pbr.clearcoat as f32,
+        clearcoat_perceptual_roughness: pbr.clearcoat_roughness as f32,
+        specular_transmission: pbr.transmission as f32,
+        alpha_mode: if a < 1.0 {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        },
+        double_sided,
+        cull_mode: if double_sided {
+            None
+        } else {
+            StandardMaterial::default().cull_mode
+        },
+        ..default()
+    }
+}
+
+/// The local transform of `node`
+pub fn transform(node: &AiNode) -> Transform {
+    let (translation, rotation, scale) = decompose(node.transformation);
+    Transform {
+        translation: Vec3::from_array(vec3(translation)),
+        rotation: Quat::from_xyzw(
+            rotation.x as f32,
+            rotation.y as f32,
+            rotation.z as f32,
+            rotation.w as f32,
+        ),
+        scale: Vec3::from_array(vec3(scale)),
+    }
+}
+
+/// Spawns the node hierarchy of `scene`, one entity per node with a child
+/// entity per mesh, and returns the entity of the root node. Meshes and
+/// materials are added to the assets once, also when used by several
+/// nodes.
+pub fn spawn_scene(
+    commands: &mut Commands,
+    scene: &AiScene,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    mut load_texture: impl FnMut(&TextureSlot) -> Option<Handle<Image>>,
+) -> Entity {
+    let mesh_handles: Vec<_> = scene.meshes.iter().map(|m| meshes.add(mesh(m))).collect();
+    let material_handles: Vec<_> = scene
+        .materials
+        .iter()
+        .map(|m| materials.add(material(m, &mut load_texture)))
+        .collect();
+    spawn_nodes(commands, scene, &mesh_handles, &material_handles)
+}
+
+fn spawn_nodes(
+    commands: &mut Commands,
+    scene: &AiScene,
+    meshes: &[Handle<Mesh>],
+    materials: &[Handle<StandardMaterial>],
+) -> Entity {
+    let Some(root) = scene.root else {
+        return commands
+            .spawn((Name::new("Scene"), Transform::default()))
+            .id();
+    };
+    let mut stack = vec![(root, None)];
+    let mut root_entity = None;
+    while let Some((node, parent)) = stack.pop() {
+        let entity = spawn_node(commands, scene, node, meshes, materials);
+        match parent {
+            Some(parent) => {
+                commands.entity(parent).add_child(entity);
+            }
+            None => root_entity = Some(entity),
+        }
+        let children = &scene.nodes[node.value()].children;
+        stack.extend(children.iter().rev().map(|&child| (child, Some(entity))));
+    }
+    root_entity.unwrap()
+}
+
+fn spawn_node(
+    commands: &mut Commands,
+    scene: &AiScene,
+    node: Index<AiNode>,
+    meshes: &[Handle<Mesh>],
+    materials: &[Handle<StandardMaterial>],
+) -> Entity {
+    let node = &scene.nodes[node.value()];
+    let entity = commands
+        .spawn((Name::new(node.name.clone()), transform(node)))
+        .id();
+    for index in node.meshes.clone() {
+        let Some(mesh) = meshes.get(index as usize) else {
+            continue;
+        };
+        let mut child = commands.spawn((
+            Name::new(scene.meshes[index as usize].name.clone()),
+            Transform::default(),
+            Mesh3d(mesh.clone()),
+        ));
+        let material = scene.meshes[index as usize].material_index as usize;
+        if let Some(material) = materials.get(material) {
+            child.insert(MeshMaterial3d(material.clone()));
+        }
+        let child = child.id();
+        commands.entity(entity).add_child(child);
+    }
+    entity
+}
+
+#[derive(Debug, Error)]
+pub enum SceneLoaderError<E> {
+    #[error("Failed to read the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Import(E),
+}
+
+/// Loads the files of the importer `I` as [`Scene`] assets. External
+/// textures are loaded relative to the file, embedded ones are skipped.
+pub struct SceneLoader<I, E> {
+    extensions: Vec<&'static str>,
+    properties: ImportProperties,
+    importer: PhantomData<fn() -> (I, E)>,
+}
+
+impl<I, E> SceneLoader<I, E> {
+    /// Loads the extensions of `desc`, the description of `I`
+    pub fn new(desc: &'static ImporterDesc, properties: ImportProperties) -> Self {
+        Self {
+            extensions: desc.extensions().collect(),
+            properties,
+            importer: PhantomData,
+        }
+    }
+}
+
+impl<I, E> AssetLoader for SceneLoader<I, E>
+where
+    I: Importer<E> + 'static,
+    E: core::error::Error + Send + Sync + 'static,
+{
+    type Asset = Scene;
+    type Settings = ();
+    type Error = SceneLoaderError<E>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Scene, Self::Error> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await?;
+        let (scene, _) =
+            I::read_with_report(&buf, &self.properties).map_err(SceneLoaderError::Import)?;
+
+        let directory = load_context
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let meshes: Vec<_> = (scene.meshes.iter().enumerate())
+            .map(|(i, m)| load_context.add_labeled_asset(format!("Mesh{i}"), mesh(m)))
+            .collect();
+        let materials: Vec<_> = (scene.materials.iter().enumerate())
+            .map(|(i, m)| {
+                let material = material(m, |slot| {
+                    (!slot.path.starts_with('*'))
+                        .then(|| load_context.load(directory.join(&slot.path)))
+                });
+                load_context.add_labeled_asset(format!("Material{i}"), material)
+            })
+            .collect();
+
+        let mut world = World::default();
+        spawn_nodes(&mut world.commands(), &scene, &meshes, &materials);
+        world.flush();
+        Ok(Scene::new(world))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+/// Registers a [`SceneLoader`] for each format of the build
+#[derive(Default)]
+pub struct AssimpPlugin {
+    /// The properties of all imports
+    pub properties: ImportProperties,
+}
+
+impl Plugin for AssimpPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "x_file")]
+        app.register_asset_loader(SceneLoader::<
+            x::importer::Importer,
+            x::errors::XFileImportError,
+        >::new(&x::importer::DESC, self.properties.clone()));
+        #[cfg(feature = "vrml")]
+        app.register_asset_loader(SceneLoader::<
+            vrml::importer::Importer,
+            vrml::errors::VrmlImportError,
+        >::new(
+            &vrml::importer::DESC, self.properties.clone()
+        ));
+        #[cfg(feature = "usd")]
+        app.register_asset_loader(SceneLoader::<
+            usd::importer::Importer,
+            usd::errors::UsdImportError,
+        >::new(&usd::importer::DESC, self.properties.clone()));
+        #[cfg(feature = "m3d")]
+        app.register_asset_loader(SceneLoader::<
+            m3d::importer::Importer,
+            m3d::errors::M3dImportError,
+        >::new(&m3d::importer::DESC, self.properties.clone()));
+    }
+}
+
+#[cfg(all(test, feature = "x_file"))]
+#[allow(unused)]
+mod test {
+    use bevy::asset::{AssetMetaCheck, LoadState};
+
+    use super::*;
+    use crate::get_model_path;
+
+    #[test]
+    fn test_load_scene() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: get_model_path("X", "").to_string_lossy().into_owned(),
+                meta_check: AssetMetaCheck::Never,
+                ..default()
+            },
+            AssimpPlugin::default(),
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .init_asset::<Image>()
+        .init_asset::<Scene>();
+
+        let handle: Handle<Scene> = app
+            .world()
+            .resource::<AssetServer>()
+            .load("test_cube_text.x");
+        for _ in 0..1000 {
+            app.update();
+            match app.world().resource::<AssetServer>().load_state(&handle) {
+                LoadState::Loaded | LoadState::Failed(_) => break,
+                _ => std::thread::sleep(std::time::Duration::from_millis(1)),
+            }
+        }
+        assert!(matches!(
+            app.world().resource::<AssetServer>().load_state(&handle),
+            LoadState::Loaded
+        ));
+
+        let world = app.world_mut();
+        let mut scene = world
+            .resource_mut::<Assets<Scene>>()
+            .remove(&handle)
+            .unwrap();
+        let mut meshes = scene
+            .world
+            .query::<(&Mesh3d, &MeshMaterial3d<StandardMaterial>)>();
+        assert_eq!(meshes.iter(&scene.world).count(), 1);
+        let (mesh, _) = meshes.single(&scene.world);
+        let mesh = world.resource::<Assets<Mesh>>().get(&mesh.0).unwrap();
+        assert_eq!(mesh.indices().unwrap().len(), 36);
+        let mut roots = scene.world.query_filtered::<&Name, Without<Parent>>();
+        assert_eq!(roots.iter(&scene.world).count(), 1);
+    }
+}
@@ -0,0 +1,242 @@
+//! Command line front end of the library, modeled after the `assimp`
+//! utility of the C++ library.
+//!
+//! ```text
+//! assimp_rs-convert info <file> [post-process options]
+//! assimp_rs-convert convert <input> <output> [--compress] [post-process options]
+//! assimp_rs-convert validate <file>
+//! ```
+
+use std::{env, process::ExitCode};
+
+use assimp_rs::{
+    core::{export_file, import_file, registry},
+    postprocess::{
+        AiPostProcessSteps as Steps,
+        pipeline::PostProcessPipeline,
+        validate::{ValidateDSProcess, ValidationReport},
+    },
+    structs::{
+        exporter::{AI_CONFIG_EXPORT_M3D_COMPRESS, AI_CONFIG_EXPORT_X_COMPRESS, ExportProperties},
+        importer::ImportProperties,
+        scene::AiScene,
+    },
+};
+
+const USAGE: &str = "\
+Usage:
+    assimp_rs-convert info <file> [post-process options]
+        Print statistics about the scene in <file>
+    assimp_rs-convert convert <input> <output> [--compress] [post-process options]
+        Convert <input> to the format given by the extension of <output>,
+        --compress writes compressed files if the format supports them
+    assimp_rs-convert validate <file>
+        Check the scene in <file> for consistency and whether its meshes
+        are closed manifolds
+
+Post-process options:
+    -cfast, -cdefault, -cfull   presets for real-time rendering";
+
+/// Short and long names of the post-processing steps, the same as `assimp`
+const STEPS: &[(&str, &str, Steps)] = &[
+    ("-cts", "--calc-tangent-space", Steps::CalcTangentSpace),
+    (
+        "-jiv",
+        "--join-identical-vertices",
+        Steps::JoinIdenticalVertices,
+    ),
+    ("-lh", "--make-left-handed", Steps::MakeLeftHanded),
+    ("-tri", "--triangulate", Steps::Triangulate),
+    ("-gn", "--gen-normals", Steps::GenNormals),
+    ("-gsn", "--gen-smooth-normals", Steps::GenSmoothNormals),
+    ("-slm", "--split-large-meshes", Steps::SplitLargeMeshes),
+    (
+        "-ptv",
+        "--pretransform-vertices",
+        Steps::PreTransformVertices,
+    ),
+    ("-lbw", "--limit-bone-weights", Steps::LimitBoneWeights),
+    (
+        "-vds",
+        "--validate-data-structure",
+        Steps::ValidateDataStructure,
+    ),
+    (
+        "-icl",
+        "--improve-cache-locality",
+        Steps::ImproveCacheLocality,
+    ),
+    (
+        "-rrm",
+        "--remove-redundant-materials",
+        Steps::RemoveRedundantMaterials,
+    ),
+    ("-fixn", "--fix-normals", Steps::FixInfacingNormals),
+    (
+        "-pad",
+        "--populate-armature-data",
+        Steps::PopulateArmatureData,
+    ),
+    ("-sbpt", "--sort-by-ptype", Steps::SortByPType),
+    ("-fd", "--find-degenerates", Steps::FindDegenerates),
+    ("-fiv", "--find-invalid-data", Steps::FindInvalidData),
+    ("-guv", "--gen-uvcoords", Steps::GenUVCoords),
+    ("-tuv", "--transform-uv-coords", Steps::TransformUVCoords),
+    ("-fi", "--find-instances", Steps::FindInstances),
+    ("-om", "--optimize-meshes", Steps::OptimizeMeshes),
+    ("-og", "--optimize-graph", Steps::OptimizeGraph),
+    ("-fuv", "--flip-uv", Steps::FlipUVs),
+    ("-fwo", "--flip-winding-order", Steps::FlipWindingOrder),
+    ("-sbc", "--split-by-bone-count", Steps::SplitByBoneCount),
+    ("-db", "--debone", Steps::Debone),
+    ("-gs", "--global-scale", Steps::GlobalScale),
+    ("-embtex", "--embed-textures", Steps::EmbedTextures),
+    ("-fgn", "--force-gen-normals", Steps::ForceGenNormals),
+    ("-dn", "--drop-normals", Steps::DropNormals),
+    ("-gbb", "--gen-bounding-boxes", Steps::GenBoundingBoxes),
+    ("-cfast", "--config-fast", Steps::Preset_TargetRealtime_Fast),
+    (
+        "-cdefault",
+        "--config-default",
+        Steps::Preset_TargetRealtime_Quality,
+    ),
+    (
+        "-cfull",
+        "--config-full",
+        Steps::Preset_TargetRealtime_MaxQuality,
+    ),
+];
+
+/// Options shared by all commands
+struct Options {
+    files: Vec<String>,
+    steps: Steps,
+    compress: bool,
+}
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            files: Vec::new(),
+            steps: Steps::empty(),
+            compress: false,
+        };
+        for arg in args {
+            if arg == "--compress" {
+                options.compress = true;
+            } else if let Some(&(_, _, step)) = STEPS
+                .iter()
+                .find(|(short, long, _)| arg == *short || arg == *long)
+            {
+                options.steps |= step;
+            } else if arg.starts_with('-') {
+                return Err(format!("Unknown option {arg}"));
+            } else {
+                options.files.push(arg);
+            }
+        }
+        Ok(options)
+    }
+
+    fn expect_files(&self, count: usize) -> Result<(), String> {
+        if self.files.len() != count {
+            return Err(format!(
+                "Expected {count} file names but got {}",
+                self.files.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn print_formats() {
+    let registry = registry();
+    println!("Import formats:");
+    for desc in &registry.importers {
+        println!("    {:<10}{}", desc.file_extensions, desc.name);
+    }
+    println!("Export formats:");
+    for desc in &registry.exporters {
+        println!("    {:<10}{}", desc.file_extension, desc.description);
+    }
+}
+
+fn import(file_name: &str, steps: Steps) -> Result<Box<AiScene>, String> {
+    let (mut scene, _) = import_file(file_name, &ImportProperties::default())
+        .map_err(|e| format!("Failed to import {file_name}: {e}"))?;
+    PostProcessPipeline::new(steps).run(&mut scene);
+    Ok(scene)
+}
+
+fn info(options: &Options) -> Result<ExitCode, String> {
+    options.expect_files(1)?;
+    let scene = import(&options.files[0], options.steps)?;
+    println!("{}", scene.stats());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn convert(options: &Options) -> Result<ExitCode, String> {
+    options.expect_files(2)?;
+    let (input, output) = (&options.files[0], &options.files[1]);
+    let scene = import(input, options.steps)?;
+    let mut properties = ExportProperties::default();
+    properties.set_bool(AI_CONFIG_EXPORT_X_COMPRESS, options.compress);
+    properties.set_bool(AI_CONFIG_EXPORT_M3D_COMPRESS, options.compress);
+    export_file(&scene, &properties, output)
+        .map_err(|e| format!("Failed to export {output}: {e}"))?;
+    println!("Converted {input} to {output}");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn validate(options: &Options) -> Result<ExitCode, String> {
+    options.expect_files(1)?;
+    let scene = import(&options.files[0], options.steps)?;
+    let ValidationReport { errors, warnings } = ValidateDSProcess::validate(&scene);
+    for warning in &warnings {
+        println!("warning: {warning}");
+    }
+    for error in &errors {
+        println!("error: {error}");
+    }
+    for (i, mesh) in scene.meshes.iter().enumerate() {
+        println!("mesh {i} ({}): {}", mesh.name, mesh.topology().report());
+    }
+    println!(
+        "{}: {} errors, {} warnings",
+        options.files[0],
+        errors.len(),
+        warnings.len()
+    );
+    Ok(if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let Some(command) = args.next() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let result = Options::parse(args).and_then(|options| match command.as_str() {
+        "info" => info(&options),
+        "convert" => convert(&options),
+        "validate" => validate(&options),
+        "help" | "-h" | "--help" => {
+            println!("{USAGE}");
+            for (short, long, _) in STEPS {
+                println!("    {short:<10}{long}");
+            }
+            println!();
+            print_formats();
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => Err(format!("Unknown command {command}\n\n{USAGE}")),
+    });
+    result.unwrap_or_else(|error| {
+        eprintln!("{error}");
+        ExitCode::FAILURE
+    })
+}
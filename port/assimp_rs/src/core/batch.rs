@@ -0,0 +1,239 @@
+//! Importing many files at once, like the `BatchLoader` of assimp.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use crate::{
+    postprocess::embed_textures::{EmbedTexturesProcess, TexturePathConfig},
+    structs::{
+        importer::{
+            ImportProperties,
+            report::{ImportReport, IssueAction, IssueKind},
+        },
+        scene::AiScene,
+    },
+    traits::{
+        importer::{error::ImportError, trait_define::Importer},
+        io_system::{CachingIoSystem, DefaultIoSystem, IoSystem},
+    },
+};
+
+/// The outcome of importing one file of a batch
+#[derive(Debug)]
+pub struct BatchResult<E> {
+    /// Position of the file in the list passed to [`BatchLoader::load`]
+    pub index: usize,
+    pub path: PathBuf,
+    pub result: Result<(Box<AiScene>, ImportReport), E>,
+    /// The external textures of the materials by their path in the
+    /// material. Textures referenced by several files of the batch share
+    /// their data.
+    pub textures: Vec<(String, Arc<[u8]>)>,
+}
+
+/// Imports many files on a bounded number of threads. External files
+/// referenced by the models are read through a [`CachingIoSystem`], so
+/// files shared by several models are read once.
+#[derive(Debug)]
+pub struct BatchLoader<S = DefaultIoSystem> {
+    io_system: CachingIoSystem<S>,
+    properties: ImportProperties,
+    parallelism: usize,
+}
+
+impl Default for BatchLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchLoader {
+    pub fn new() -> Self {
+        Self::with_io_system(DefaultIoSystem)
+    }
+}
+
+impl<S: IoSystem + Sync> BatchLoader<S> {
+    pub fn with_io_system(io_system: S) -> Self {
+        Self {
+            io_system: CachingIoSystem::new(io_system),
+            properties: ImportProperties::default(),
+            parallelism: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// The properties used for every file
+    pub fn properties(mut self, properties: ImportProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    /// The maximal number of files imported at the same time, at least 1
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// The io system holding the external files read so far
+    pub fn io_system(&self) -> &CachingIoSystem<S> {
+        &self.io_system
+    }
+
+    /// Imports all `files` with the importer `I`, the results are in the
+    /// order the imports finished
+    pub fn load<I, E>(&self, files: &[impl AsRef<Path> + Sync]) -> Vec<BatchResult<E>>
+    where
+        I: Importer<E>,
+        E: From<ImportError> + Send,
+    {
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for _ in 0..self.parallelism.min(files.len()) {
+                let sender = sender.clone();
+                let next = &next;
+                scope.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(index) else {
+                            break;
+                        };
+                        let result = self.load_one::<I, E>(index, path.as_ref());
+                        if sender.send(result).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        drop(sender);
+        receiver.into_iter().collect()
+    }
+
+    fn load_one<I, E>(&self, index: usize, path: &Path) -> BatchResult<E>
+    where
+        I: Importer<E>,
+        E: From<ImportError>,
+    {
+        let mut textures = Vec::new();
+        // models are read once, only the files they share are cached
+        let result = self
+            .io_system
+            .inner()
+            .read(path)
+            .map_err(|e| E::from(e.into()))
+            .and_then(|buf| I::read_with_report(&buf, &self.properties))
+            .map(|(scene, mut report)| {
+                textures = self.load_textures(path, &scene, &mut report);
+                (scene, report)
+            });
+        BatchResult {
+            index,
+            path: path.to_path_buf(),
+            result,
+            textures,
+        }
+    }
+
+    /// Reads the external textures of `scene`, missing ones are reported
+    fn load_textures(
+        &self,
+        path: &Path,
+        scene: &AiScene,
+        report: &mut ImportReport,
+    ) -> Vec<(String, Arc<[u8]>)> {
+        let root = path.parent().unwrap_or(Path::new(""));
+        let config = TexturePathConfig {
+            root_path: Some(root),
+            make_relative: false,
+            io_system: Some(&self.io_system),
+            embed: false,
+        };
+        let mut seen = HashSet::new();
+        let mut textures = Vec::new();
+        let texture_paths = scene
+            .materials
+            .iter()
+            .flat_map(|material| material.properties.iter())
+            .filter_map(|p| p.texture_path())
+            .filter(|p| !p.starts_with('*'));
+        for texture in texture_paths {
+            if !seen.insert(texture) {
+                continue;
+            }
+            let (resolved, found) = EmbedTexturesProcess::resolve_path(texture, &config);
+            let file = if found && !Path::new(&resolved).is_absolute() {
+                let in_root = root.join(&resolved);
+                if self.io_system.exists(&in_root) {
+                    in_root
+                } else {
+                    PathBuf::from(resolved)
+                }
+            } else {
+                PathBuf::from(resolved)
+            };
+            match self.io_system.read_shared(&file) {
+                Ok(data) => textures.push((texture.to_owned(), data)),
+                Err(e) => report.push(
+                    IssueKind::MissingFile,
+                    IssueAction::Warned,
+                    None,
+                    format!("Texture {texture} could not be read: {e}"),
+                ),
+            }
+        }
+        textures
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use super::BatchLoader;
+    use crate::{
+        formats::x::{errors::XFileImportError, importer::Importer},
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_batch_loader() {
+        use std::sync::Arc;
+
+        let files = [
+            get_model_path("X", "kwxport_test_cubewithvcolors.x"),
+            get_model_path("X", "test_cube_text.x"),
+            get_model_path("X", "kwxport_test_cubewithvcolors.x"),
+            get_model_path("X", "missing.x"),
+        ];
+        let loader = BatchLoader::new().parallelism(2);
+        let mut results = loader.load::<Importer, XFileImportError>(&files);
+        assert_eq!(results.len(), files.len());
+        results.sort_by_key(|r| r.index);
+        assert!(results[3].result.is_err());
+        let (scene, report) = results[1].result.as_ref().unwrap();
+        assert!(!scene.meshes.is_empty());
+        assert_eq!(report.format.as_deref(), Some("DirectX X"));
+        assert!(results[1].textures.is_empty());
+
+        // both copies of the model share the textures
+        let first = &results[0].textures;
+        let second = &results[2].textures;
+        assert_eq!(first.len(), 3);
+        assert_eq!(first.len(), second.len());
+        for ((path_a, data_a), (path_b, data_b)) in first.iter().zip(second) {
+            assert_eq!(path_a, path_b);
+            assert!(Arc::ptr_eq(data_a, data_b));
+        }
+        // files read by two threads at once count as misses for both
+        assert_eq!(loader.io_system().len(), 3);
+        assert!(loader.io_system().hits() <= 3);
+    }
+}
@@ -1 +1,12 @@
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod profiler;
+pub mod registry;
+pub mod simd;
+pub mod spatial;
+pub mod topology;
 
+pub use profiler::{ProfileScope, Profiler};
+#[cfg(feature = "std")]
+pub use registry::{export_file, import_file};
+pub use registry::{exporter_for_extension, importer_for_data, importer_for_extension, registry};
@@ -0,0 +1,238 @@
+//! Wall-clock timing of the import phases and post-processing steps, e.g.
+//! to find out which part of a slow import takes the time.
+
+use core::{fmt, time::Duration};
+use std::time::Instant;
+
+/// Part of the work a [`ProfileEntry`] belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProfileScope {
+    /// A phase of an importer, like `header` or `parse`
+    Import,
+    /// A post-processing step, named like its flag
+    PostProcess,
+}
+
+/// The duration of one phase or step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub scope: ProfileScope,
+    pub name: &'static str,
+    pub duration: Duration,
+}
+
+/// Records the durations of the phases of an import and of the
+/// post-processing steps, in the order they finished.
+///
+/// A disabled profiler records nothing and does not read the clock, the
+/// functions without profiler use one.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    enabled: bool,
+    entries: Vec<ProfileEntry>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Runs `f` and records its duration
+    pub fn measure<T>(
+        &mut self,
+        scope: ProfileScope,
+        name: &'static str,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let start = self.start();
+        let result = f();
+        self.finish(scope, name, start);
+        result
+    }
+
+    /// The start of a phase which can not be wrapped in a closure, pass it
+    /// to [`Self::finish`] at its end
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    pub fn finish(&mut self, scope: ProfileScope, name: &'static str, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.record(scope, name, start.elapsed());
+        }
+    }
+
+    pub fn record(&mut self, scope: ProfileScope, name: &'static str, duration: Duration) {
+        if self.enabled {
+            self.entries.push(ProfileEntry {
+                scope,
+                name,
+                duration,
+            });
+        }
+    }
+
+    pub fn entries(&self) -> &[ProfileEntry] {
+        &self.entries
+    }
+
+    /// The sum of the durations of `name`, phases like `parse` may be
+    /// recorded several times
+    pub fn duration(&self, scope: ProfileScope, name: &str) -> Option<Duration> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.scope == scope && entry.name == name)
+            .map(|entry| entry.duration)
+            .reduce(|a, b| a + b)
+    }
+
+    pub fn total(&self) -> Duration {
+        self.entries.iter().map(|entry| entry.duration).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// The recorded durations as a table, see the [`fmt::Display`]
+    /// implementation
+    pub fn report(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Profiler {
+    /// One line per entry with its duration in milliseconds and its share of
+    /// the total
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        let width = self
+            .entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+        for entry in &self.entries {
+            let scope = match entry.scope {
+                ProfileScope::Import => "import",
+                ProfileScope::PostProcess => "postprocess",
+            };
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                entry.duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            writeln!(
+                f,
+                "{scope:<11} {:<width$} {:>10.3} ms {share:>5.1}%",
+                entry.name,
+                entry.duration.as_secs_f64() * 1000.0,
+            )?;
+        }
+        write!(
+            f,
+            "{:<11} {:<width$} {:>10.3} ms",
+            "",
+            "total",
+            total.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use crate::{
+        core::{ProfileScope, Profiler},
+        formats::x::importer::Importer,
+        postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+        structs::{importer::ImportProperties, scene::AiScene},
+        traits::importer::trait_define::InternalImporter,
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_profiler() {
+        let phases = |file_name| {
+            let source = fs::read(get_model_path("X", file_name)).unwrap();
+            let mut scene = AiScene::default();
+            let mut profiler = Profiler::new();
+            Importer::import_from_buf_with_profiler(
+                &source,
+                &mut scene,
+                &ImportProperties::default(),
+                &mut profiler,
+            )
+            .unwrap();
+            PostProcessPipeline::new(
+                AiPostProcessSteps::ValidateDataStructure | AiPostProcessSteps::GenBoundingBoxes,
+            )
+            .run_with_profiler(&mut scene, &mut profiler);
+            let report = profiler.report();
+            assert!(report.contains("GenBoundingBoxes"), "{report}");
+            assert!(report.ends_with(" ms"), "{report}");
+            assert_eq!(
+                profiler.total(),
+                profiler.entries().iter().map(|e| e.duration).sum()
+            );
+            profiler
+                .entries()
+                .iter()
+                .map(|entry| (entry.scope, entry.name))
+                .collect::<Vec<_>>()
+        };
+        let import = |name| (ProfileScope::Import, name);
+        let post_process = |name| (ProfileScope::PostProcess, name);
+        assert_eq!(
+            phases("test_cube_text.x"),
+            [
+                import("header"),
+                import("parse"),
+                import("conversion"),
+                post_process("ValidateDataStructure"),
+                post_process("GenBoundingBoxes"),
+            ]
+        );
+        #[cfg(feature = "compression")]
+        assert_eq!(
+            phases("test_cube_compressed.x")[..3],
+            [import("header"), import("decompression"), import("parse")]
+        );
+
+        // nothing is recorded without profiling
+        let mut profiler = Profiler::disabled();
+        let mut scene = AiScene::default();
+        Importer::import_from_buf_with_profiler(
+            b"xof 0303txt 0032 Frame Root {}",
+            &mut scene,
+            &ImportProperties::default(),
+            &mut profiler,
+        )
+        .unwrap();
+        assert!(profiler.entries().is_empty());
+        assert_eq!(profiler.report().trim(), "total      0.000 ms");
+    }
+}
@@ -0,0 +1,315 @@
+//! Runtime query of the formats compiled into the library, e.g. to build
+//! the filters of a file dialog, and import and export by file name.
+
+#[cfg(feature = "std")]
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+#[cfg(feature = "std")]
+use thiserror::Error;
+
+#[cfg(feature = "m3d")]
+use crate::formats::m3d;
+#[cfg(feature = "usd")]
+use crate::formats::usd;
+#[cfg(feature = "vrml")]
+use crate::formats::vrml;
+#[cfg(feature = "x_file")]
+use crate::formats::x;
+use crate::structs::{exporter_desc::ExporterDesc, importer_desc::ImporterDesc};
+#[cfg(any(feature = "x_file", feature = "vrml", feature = "usd", feature = "m3d"))]
+use crate::traits::importer::trait_define::FormatValidator;
+#[cfg(all(feature = "std", feature = "compression"))]
+use crate::traits::importer::trait_define::unpack;
+#[cfg(feature = "std")]
+use crate::{
+    structs::{
+        exporter::ExportProperties,
+        importer::{ImportProperties, report::ImportReport},
+        scene::AiScene,
+    },
+    traits::{
+        exporter::trait_define::Exporter,
+        importer::{error::ImportError, trait_define::Importer},
+    },
+};
+
+/// Errors of [`import_file`] and [`export_file`]
+#[cfg(feature = "std")]
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    #[error("no importer for {0}")]
+    NoImporter(String),
+    #[error("no exporter for {0}")]
+    NoExporter(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+    #[error(transparent)]
+    ImportError(#[from] ImportError),
+    /// The error of the importer or exporter of the format
+    #[error("{format}: {source}")]
+    FormatError {
+        format: &'static str,
+        source: Box<dyn Error>,
+    },
+}
+
+#[cfg(feature = "std")]
+type ReadFn = fn(&[u8], &ImportProperties) -> Result<(Box<AiScene>, ImportReport), Box<dyn Error>>;
+
+#[cfg(feature = "std")]
+type WriteFn = fn(&AiScene, &ExportProperties, &mut dyn Write) -> Result<(), Box<dyn Error>>;
+
+/// [`Importer::read_with_report`] of `I` with a boxed error
+#[cfg(feature = "std")]
+#[allow(unused)]
+fn read_with<I: Importer<E>, E: Error + 'static>(
+    buf: &[u8],
+    properties: &ImportProperties,
+) -> Result<(Box<AiScene>, ImportReport), Box<dyn Error>> {
+    Ok(I::read_with_report(buf, properties)?)
+}
+
+/// [`Exporter::export_to_writer`] of `X` with a boxed error
+#[cfg(feature = "std")]
+#[allow(unused)]
+fn write_with<X: Exporter<E>, E: Error + 'static>(
+    scene: &AiScene,
+    properties: &ExportProperties,
+    mut writer: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    Ok(X::export_to_writer(scene, properties, &mut writer)?)
+}
+
+struct ImporterEntry {
+    desc: &'static ImporterDesc,
+    /// Checks the magic words at the start of a file
+    can_read: fn(&[u8]) -> bool,
+    #[cfg(feature = "std")]
+    read: ReadFn,
+}
+
+static IMPORTERS: &[ImporterEntry] = &[
+    #[cfg(feature = "x_file")]
+    ImporterEntry {
+        desc: &x::importer::DESC,
+        can_read: x::importer::Importer::can_read_from_buf,
+        #[cfg(feature = "std")]
+        read: read_with::<x::importer::Importer, x::errors::XFileImportError>,
+    },
+    #[cfg(feature = "vrml")]
+    ImporterEntry {
+        desc: &vrml::importer::DESC,
+        can_read: vrml::importer::Importer::can_read_from_buf,
+        #[cfg(feature = "std")]
+        read: read_with::<vrml::importer::Importer, vrml::errors::VrmlImportError>,
+    },
+    #[cfg(feature = "usd")]
+    ImporterEntry {
+        desc: &usd::importer::DESC,
+        can_read: usd::importer::Importer::can_read_from_buf,
+        #[cfg(feature = "std")]
+        read: read_with::<usd::importer::Importer, usd::errors::UsdImportError>,
+    },
+    #[cfg(feature = "m3d")]
+    ImporterEntry {
+        desc: &m3d::importer::DESC,
+        can_read: m3d::importer::Importer::can_read_from_buf,
+        #[cfg(feature = "std")]
+        read: read_with::<m3d::importer::Importer, m3d::errors::M3dImportError>,
+    },
+];
+
+struct ExporterEntry {
+    desc: &'static ExporterDesc,
+    #[cfg(feature = "std")]
+    write: WriteFn,
+}
+
+static EXPORTERS: &[ExporterEntry] = &[
+    #[cfg(feature = "x_file")]
+    ExporterEntry {
+        desc: &x::exporter::DESC,
+        #[cfg(feature = "std")]
+        write: write_with::<x::exporter::Exporter<'static>, x::errors::XFileExportError>,
+    },
+    #[cfg(feature = "m3d")]
+    ExporterEntry {
+        desc: &m3d::exporter::DESC,
+        #[cfg(feature = "std")]
+        write: write_with::<m3d::exporter::Exporter<'static>, m3d::errors::M3dExportError>,
+    },
+];
+
+/// Descriptions of all importers and exporters in this build
+#[derive(Debug, Clone)]
+pub struct Registry {
+    pub importers: Vec<&'static ImporterDesc>,
+    pub exporters: Vec<&'static ExporterDesc>,
+}
+
+impl Registry {
+    /// All extensions which can be imported, without duplicates
+    pub fn import_extensions(&self) -> Vec<&'static str> {
+        let mut extensions = Vec::new();
+        for extension in self.importers.iter().flat_map(|desc| desc.extensions()) {
+            if !extensions.contains(&extension) {
+                extensions.push(extension);
+            }
+        }
+        extensions
+    }
+}
+
+pub fn registry() -> Registry {
+    Registry {
+        importers: IMPORTERS.iter().map(|entry| entry.desc).collect(),
+        exporters: EXPORTERS.iter().map(|entry| entry.desc).collect(),
+    }
+}
+
+/// Normalizes `"X"`, `".x"` and `"x"` to `"x"`
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_ascii_lowercase()
+}
+
+fn importer_entry_for_extension(extension: &str) -> Option<&'static ImporterEntry> {
+    let extension = normalize_extension(extension);
+    IMPORTERS
+        .iter()
+        .find(|entry| entry.desc.extensions().any(|e| e == extension))
+}
+
+fn importer_entry_for_data(data: &[u8]) -> Option<&'static ImporterEntry> {
+    IMPORTERS.iter().find(|entry| (entry.can_read)(data))
+}
+
+fn exporter_entry_for_extension(extension: &str) -> Option<&'static ExporterEntry> {
+    let extension = normalize_extension(extension);
+    EXPORTERS
+        .iter()
+        .find(|entry| entry.desc.file_extension == extension)
+}
+
+/// The first importer handling files with the given extension, which may
+/// have a leading dot and is matched case insensitively.
+pub fn importer_for_extension(extension: &str) -> Option<&'static ImporterDesc> {
+    importer_entry_for_extension(extension).map(|entry| entry.desc)
+}
+
+/// The first importer recognizing the magic words at the start of `data`,
+/// independent of the file name.
+pub fn importer_for_data(data: &[u8]) -> Option<&'static ImporterDesc> {
+    importer_entry_for_data(data).map(|entry| entry.desc)
+}
+
+/// The first exporter writing files with the given extension, see
+/// [`importer_for_extension`].
+pub fn exporter_for_extension(extension: &str) -> Option<&'static ExporterDesc> {
+    exporter_entry_for_extension(extension).map(|entry| entry.desc)
+}
+
+/// Imports a file with the importer of its extension, or the importer
+/// recognizing its content if no importer handles the extension. With the
+/// `compression` feature gzip files and zip archives holding a single file
+/// are decompressed first, like [`Importer::read_file_with_report`] does.
+#[cfg(feature = "std")]
+pub fn import_file(
+    file_name: &str,
+    properties: &ImportProperties,
+) -> Result<(Box<AiScene>, ImportReport), RegistryError> {
+    let buf = std::fs::read(file_name)?;
+    #[cfg(feature = "compression")]
+    let buf = unpack(&buf, properties)?.unwrap_or(buf);
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy())
+        .unwrap_or_default();
+    let entry = importer_entry_for_extension(&extension)
+        .or_else(|| importer_entry_for_data(&buf))
+        .ok_or_else(|| RegistryError::NoImporter(file_name.to_owned()))?;
+    (entry.read)(&buf, properties).map_err(|source| RegistryError::FormatError {
+        format: entry.desc.name,
+        source,
+    })
+}
+
+/// Exports the scene with the exporter of the extension of `file_name`
+#[cfg(feature = "std")]
+pub fn export_file(
+    scene: &AiScene,
+    properties: &ExportProperties,
+    file_name: &str,
+) -> Result<(), RegistryError> {
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy())
+        .unwrap_or_default();
+    let entry = exporter_entry_for_extension(&extension)
+        .ok_or_else(|| RegistryError::NoExporter(file_name.to_owned()))?;
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    (entry.write)(scene, properties, &mut writer).map_err(|source| RegistryError::FormatError {
+        format: entry.desc.description,
+        source,
+    })?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(all(feature = "std", feature = "x_file"))]
+#[allow(unused)]
+mod test {
+    use super::*;
+    use crate::{get_model_path, structs::exporter::AI_CONFIG_EXPORT_X_COMPRESS};
+
+    #[test]
+    fn test_registry() {
+        let registry = registry();
+        assert!(registry.import_extensions().contains(&"x"));
+        assert!(registry.exporters.iter().any(|desc| desc.id == "x"));
+
+        let desc = importer_for_extension(".X").unwrap();
+        assert_eq!(desc.name, "Direct3D XFile Importer");
+        assert!(importer_for_extension("gltf").is_none());
+        assert_eq!(exporter_for_extension("x").unwrap().id, "x");
+
+        let source = std::fs::read(get_model_path("X", "test.x")).unwrap();
+        assert_eq!(importer_for_data(&source).unwrap().name, desc.name);
+        assert!(importer_for_data(b"glTF").is_none());
+    }
+
+    #[test]
+    fn test_import_export_file() {
+        let path = get_model_path("X", "test_cube_text.x.gz");
+        let (scene, report) =
+            import_file(path.to_str().unwrap(), &ImportProperties::default()).unwrap();
+        assert_eq!(scene.meshes.len(), 1);
+        assert_eq!(report.format.as_deref(), Some("DirectX X"));
+
+        // the extension picks the exporter, the content the importer
+        let mut properties = ExportProperties::default();
+        properties.set_bool(AI_CONFIG_EXPORT_X_COMPRESS, true);
+        let output = std::env::temp_dir().join("assimp_rs_export_file.x");
+        let output = output.to_str().unwrap();
+        export_file(&scene, &properties, output).unwrap();
+        assert!(std::fs::read(output).unwrap().starts_with(b"xof 0303tzip"));
+        let renamed = std::env::temp_dir().join("assimp_rs_export_file.bin");
+        std::fs::rename(output, &renamed).unwrap();
+        let (reimported, _) =
+            import_file(renamed.to_str().unwrap(), &ImportProperties::default()).unwrap();
+        assert_eq!(reimported.meshes.len(), 1);
+
+        assert!(matches!(
+            export_file(&scene, &properties, "cube.gltf"),
+            Err(RegistryError::NoExporter(_))
+        ));
+        std::fs::write(&renamed, b"no model").unwrap();
+        let result = import_file(renamed.to_str().unwrap(), &ImportProperties::default());
+        std::fs::remove_file(&renamed).unwrap();
+        assert!(matches!(result, Err(RegistryError::NoImporter(_))));
+    }
+}
@@ -0,0 +1,216 @@
+//! Kernels for the hot loops of the post-processing steps over whole vertex
+//! streams.
+//!
+//! With the `simd` feature, single precision builds process four vectors at
+//! once in three `Vec4` registers (`xyzx yzxy zxyz`), which glam maps to
+//! SSE2, NEON or WASM SIMD depending on the target. Other builds, and
+//! builds with the `paranoid` feature, use plain loops.
+
+use crate::{structs::aabb::AABB, utils::float_precision::Vec3};
+
+#[cfg(all(
+    feature = "simd",
+    not(feature = "double_precision"),
+    not(feature = "paranoid")
+))]
+mod wide {
+    use glam::{Vec3, Vec3A, Vec4};
+
+    /// Four vectors take three `Vec4`
+    const LANES: usize = 12;
+
+    fn as_floats(v: &[Vec3]) -> &[f32] {
+        bytemuck::cast_slice(v)
+    }
+
+    fn as_floats_mut(v: &mut [Vec3]) -> &mut [f32] {
+        bytemuck::cast_slice_mut(v)
+    }
+
+    /// Applies `v * mul + add` to the lanes of all groups of four vectors,
+    /// returns the vectors left over
+    fn mul_add_lanes(v: &mut [Vec3], mul: [Vec4; 3], add: [Vec4; 3]) -> &mut [Vec3] {
+        let split = v.len() / 4 * 4;
+        let (groups, rest) = v.split_at_mut(split);
+        for chunk in as_floats_mut(groups).chunks_exact_mut(LANES) {
+            for (i, lane) in chunk.chunks_exact_mut(4).enumerate() {
+                (Vec4::from_slice(lane) * mul[i] + add[i]).write_to_slice(lane);
+            }
+        }
+        rest
+    }
+
+    pub fn flip_z(v: &mut [Vec3]) {
+        let mul = [
+            Vec4::new(1.0, 1.0, -1.0, 1.0),
+            Vec4::new(1.0, -1.0, 1.0, 1.0),
+            Vec4::new(-1.0, 1.0, 1.0, -1.0),
+        ];
+        for v in mul_add_lanes(v, mul, [Vec4::ZERO; 3]) {
+            v.z = -v.z;
+        }
+    }
+
+    pub fn flip_v(v: &mut [Vec3]) {
+        let mul = [
+            Vec4::new(1.0, -1.0, 1.0, 1.0),
+            Vec4::new(-1.0, 1.0, 1.0, -1.0),
+            Vec4::new(1.0, 1.0, -1.0, 1.0),
+        ];
+        let add = [
+            Vec4::new(0.0, 1.0, 0.0, 0.0),
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, 1.0, 0.0),
+        ];
+        for v in mul_add_lanes(v, mul, add) {
+            v.y = 1.0 - v.y;
+        }
+    }
+
+    pub fn normalize(v: &mut [Vec3]) {
+        for v in v.iter_mut() {
+            *v = Vec3A::from(*v).normalize_or_zero().into();
+        }
+    }
+
+    pub fn bounds(v: &[Vec3]) -> (Vec3, Vec3) {
+        let split = v.len() / 4 * 4;
+        let mut min = [Vec4::INFINITY; 3];
+        let mut max = [Vec4::NEG_INFINITY; 3];
+        for chunk in as_floats(&v[..split]).chunks_exact(LANES) {
+            for (i, lane) in chunk.chunks_exact(4).enumerate() {
+                let lane = Vec4::from_slice(lane);
+                // the accumulator is the second operand, so it is kept if
+                // the lane is NaN
+                min[i] = lane.min(min[i]);
+                max[i] = lane.max(max[i]);
+            }
+        }
+        // the lanes of x, y and z of the four vectors
+        let gather = |l: [Vec4; 3], f: fn(Vec4) -> f32| {
+            Vec3::new(
+                f(Vec4::new(l[0].x, l[0].w, l[1].z, l[2].y)),
+                f(Vec4::new(l[0].y, l[1].x, l[1].w, l[2].z)),
+                f(Vec4::new(l[0].z, l[1].y, l[2].x, l[2].w)),
+            )
+        };
+        let mut lo = gather(min, Vec4::min_element);
+        let mut hi = gather(max, Vec4::max_element);
+        for &p in &v[split..] {
+            lo = p.min(lo);
+            hi = p.max(hi);
+        }
+        (lo, hi)
+    }
+}
+
+#[cfg(not(all(
+    feature = "simd",
+    not(feature = "double_precision"),
+    not(feature = "paranoid")
+)))]
+mod wide {
+    use crate::utils::float_precision::Vec3;
+
+    pub fn flip_z(v: &mut [Vec3]) {
+        for v in v.iter_mut() {
+            v.z = -v.z;
+        }
+    }
+
+    pub fn flip_v(v: &mut [Vec3]) {
+        for v in v.iter_mut() {
+            v.y = 1.0 - v.y;
+        }
+    }
+
+    pub fn normalize(v: &mut [Vec3]) {
+        for v in v.iter_mut() {
+            *v = v.normalize_or_zero();
+        }
+    }
+
+    pub fn bounds(v: &[Vec3]) -> (Vec3, Vec3) {
+        // the accumulator is the second operand, so it is kept if the point
+        // is NaN
+        v.iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(lo, hi), &p| {
+                (p.min(lo), p.max(hi))
+            })
+    }
+}
+
+/// Whether the kernels use SIMD registers in this build
+pub const ENABLED: bool = cfg!(all(
+    feature = "simd",
+    not(feature = "double_precision"),
+    not(feature = "paranoid")
+));
+
+/// Mirrors the vectors along the z axis
+pub fn flip_z(v: &mut [Vec3]) {
+    wide::flip_z(v);
+}
+
+/// Flips texture coordinates vertically, `v` becomes `1 - v`
+pub fn flip_v(v: &mut [Vec3]) {
+    wide::flip_v(v);
+}
+
+/// Scales the vectors to unit length, zero vectors stay zero
+pub fn normalize(v: &mut [Vec3]) {
+    wide::normalize(v);
+}
+
+/// The smallest box containing all points, [`AABB::EMPTY`] for none. NaN
+/// coordinates are ignored.
+pub fn bounds(v: &[Vec3]) -> AABB {
+    let (min, max) = wide::bounds(v);
+    AABB::new(min, max)
+}
+
+#[allow(unused)]
+mod test {
+    use crate::{AiReal, core::simd, structs::aabb::AABB, utils::float_precision::Vec3};
+
+    #[test]
+    fn test_simd_kernels() {
+        // not a multiple of four, so the kernels have a remainder
+        let points: Vec<_> = (0..11)
+            .map(|i| {
+                let t = i as AiReal;
+                Vec3::new(t - 5.0, 2.0 * t, 0.25 - t)
+            })
+            .collect();
+
+        let mut flipped = points.clone();
+        simd::flip_z(&mut flipped);
+        let expected: Vec<_> = points.iter().map(|p| Vec3::new(p.x, p.y, -p.z)).collect();
+        assert_eq!(flipped, expected);
+
+        let mut flipped = points.clone();
+        simd::flip_v(&mut flipped);
+        let expected: Vec<_> = points
+            .iter()
+            .map(|p| Vec3::new(p.x, 1.0 - p.y, p.z))
+            .collect();
+        assert_eq!(flipped, expected);
+
+        let mut normalized = points.clone();
+        normalized.push(Vec3::ZERO);
+        simd::normalize(&mut normalized);
+        for (n, p) in normalized.iter().zip(&points) {
+            assert!(n.abs_diff_eq(p.normalize(), 1e-6), "{n} {p}");
+        }
+        assert_eq!(normalized[11], Vec3::ZERO);
+
+        let aabb = simd::bounds(&points);
+        assert_eq!(aabb.min, Vec3::new(-5.0, 0.0, -9.75));
+        assert_eq!(aabb.max, Vec3::new(5.0, 20.0, 0.25));
+        let mut with_nan = points.clone();
+        with_nan[6].y = AiReal::NAN;
+        with_nan[3].x = AiReal::NAN;
+        assert_eq!(simd::bounds(&with_nan), aabb);
+        assert_eq!(simd::bounds(&[]), AABB::EMPTY);
+    }
+}
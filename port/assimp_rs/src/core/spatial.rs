@@ -0,0 +1,462 @@
+//! Bounding volume hierarchies to find vertices and faces of meshes by
+//! position, shared by the raycasting and the post-processing steps.
+
+use crate::{
+    AiReal,
+    structs::{
+        aabb::AABB,
+        mesh::AiMesh,
+        ray::{AiRay, TriangleHit},
+        scene::AiScene,
+    },
+    utils::float_precision::Vec3,
+};
+
+/// Maximum number of primitives in a leaf
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    aabb: AABB,
+    /// Index of the first primitive of a leaf, or of the right child of an
+    /// inner node. The left child directly follows its parent.
+    first: u32,
+    /// Number of primitives of a leaf, 0 for inner nodes
+    count: u32,
+}
+
+enum Visit<'a> {
+    Node(&'a AABB),
+    Primitive(u32),
+}
+
+/// Bounding volume hierarchy over primitives given by their bounding boxes
+#[derive(Debug, Clone, Default)]
+struct Bvh {
+    nodes: Vec<BvhNode>,
+    primitives: Vec<u32>,
+}
+
+impl Bvh {
+    fn new(bounds: &[AABB]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(bounds.len().div_ceil(LEAF_SIZE) * 2),
+            primitives: (0..bounds.len() as u32).collect(),
+        };
+        if !bounds.is_empty() {
+            let centroids = bounds.iter().map(AABB::center).collect::<Vec<_>>();
+            bvh.build(bounds, &centroids, 0, bounds.len());
+        }
+        bvh
+    }
+
+    /// Splits the primitives at the median of the longest axis, so the
+    /// depth stays logarithmic.
+    fn build(&mut self, bounds: &[AABB], centroids: &[Vec3], start: usize, end: usize) -> u32 {
+        let index = self.nodes.len();
+        let primitives = &mut self.primitives[start..end];
+        let aabb = primitives
+            .iter()
+            .fold(AABB::EMPTY, |aabb, &p| aabb.union(&bounds[p as usize]));
+        self.nodes.push(BvhNode {
+            aabb,
+            first: start as u32,
+            count: (end - start) as u32,
+        });
+        if end - start <= LEAF_SIZE {
+            return index as u32;
+        }
+
+        let extent = primitives
+            .iter()
+            .fold(AABB::EMPTY, |aabb, &p| aabb.extended(centroids[p as usize]))
+            .extent();
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let mid = (start + end) / 2;
+        primitives.select_nth_unstable_by(mid - start, |&a, &b| {
+            centroids[a as usize][axis].total_cmp(&centroids[b as usize][axis])
+        });
+        self.build(bounds, centroids, start, mid);
+        let right = self.build(bounds, centroids, mid, end);
+        let node = &mut self.nodes[index];
+        node.first = right;
+        node.count = 0;
+        index as u32
+    }
+
+    fn bounds(&self) -> AABB {
+        self.nodes.first().map_or(AABB::EMPTY, |node| node.aabb)
+    }
+
+    /// Walks the hierarchy depth first. Nodes are only entered if `visit`
+    /// returns `true` for their box, the return value for primitives is
+    /// ignored.
+    fn traverse(&self, mut visit: impl FnMut(Visit) -> bool) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !visit(Visit::Node(&node.aabb)) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(index + 1);
+                continue;
+            }
+            let first = node.first as usize;
+            for &primitive in &self.primitives[first..first + node.count as usize] {
+                visit(Visit::Primitive(primitive));
+            }
+        }
+    }
+}
+
+/// Squared distance of `point` to the closest point of the box
+fn distance_squared_to(aabb: &AABB, point: Vec3) -> AiReal {
+    point.clamp(aabb.min, aabb.max).distance_squared(point)
+}
+
+/// Barycentric weights of the point of the triangle closest to `p`, see
+/// "Real-Time Collision Detection" by Christer Ericson, 5.1.5.
+fn closest_point_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let (ab, ac) = (b - a, c - a);
+    let ap = p - a;
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return Vec3::X;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return Vec3::Y;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return Vec3::new(1.0 - v, v, 0.0);
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return Vec3::Z;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return Vec3::new(1.0 - w, 0.0, w);
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 >= d3 && d5 >= d6 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return Vec3::new(0.0, 1.0 - w, w);
+    }
+    let denom = 1.0 / (va + vb + vc);
+    let (v, w) = (vb * denom, vc * denom);
+    Vec3::new(1.0 - v - w, v, w)
+}
+
+/// Vertex positions sorted into a hierarchy, to find equal or close
+/// vertices without comparing all pairs.
+#[derive(Debug, Clone, Default)]
+pub struct PointBvh {
+    bvh: Bvh,
+    points: Vec<Vec3>,
+}
+
+impl PointBvh {
+    pub fn new(points: &[Vec3]) -> Self {
+        let bounds = points
+            .iter()
+            .map(|&point| AABB::new(point, point))
+            .collect::<Vec<_>>();
+        Self {
+            bvh: Bvh::new(&bounds),
+            points: points.to_vec(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn bounds(&self) -> AABB {
+        self.bvh.bounds()
+    }
+
+    /// Index of the point closest to `position`, `None` if there are no
+    /// points
+    pub fn nearest(&self, position: Vec3) -> Option<u32> {
+        let mut best: Option<(u32, AiReal)> = None;
+        let limit = |best: &Option<(u32, AiReal)>| best.map_or(AiReal::INFINITY, |b| b.1);
+        self.bvh.traverse(|visit| match visit {
+            Visit::Node(aabb) => distance_squared_to(aabb, position) < limit(&best),
+            Visit::Primitive(i) => {
+                let distance = self.points[i as usize].distance_squared(position);
+                if distance < limit(&best) {
+                    best = Some((i, distance));
+                }
+                true
+            }
+        });
+        best.map(|(i, _)| i)
+    }
+
+    /// Collects the indices of all points at most `radius` away from
+    /// `position` in `out`, in ascending order.
+    pub fn find_within(&self, position: Vec3, radius: AiReal, out: &mut Vec<u32>) {
+        out.clear();
+        let radius_sq = radius * radius;
+        self.bvh.traverse(|visit| match visit {
+            Visit::Node(aabb) => distance_squared_to(aabb, position) <= radius_sq,
+            Visit::Primitive(i) => {
+                if self.points[i as usize].distance_squared(position) <= radius_sq {
+                    out.push(i);
+                }
+                true
+            }
+        });
+        out.sort_unstable();
+    }
+}
+
+/// The point of a mesh surface closest to a query point, see
+/// [`TriangleBvh::nearest_point`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfacePoint {
+    pub face: u32,
+    /// The vertices of the closest triangle, polygons are split into a fan
+    /// around their first vertex
+    pub indices: [u32; 3],
+    /// Weights of the three vertices at the closest point
+    pub barycentrics: Vec3,
+    pub point: Vec3,
+    pub distance: AiReal,
+}
+
+#[derive(Debug, Clone)]
+struct Triangle {
+    face: u32,
+    indices: [u32; 3],
+    vertices: [Vec3; 3],
+}
+
+/// The triangles of a mesh sorted into a hierarchy, for ray and
+/// closest-point queries in the space of the mesh.
+///
+/// The positions are copied, so the hierarchy stays valid while the mesh
+/// is modified but does not reflect the modifications.
+#[derive(Debug, Clone, Default)]
+pub struct TriangleBvh {
+    bvh: Bvh,
+    triangles: Vec<Triangle>,
+}
+
+impl TriangleBvh {
+    /// Builds the hierarchy from the polygons of `mesh`. Points, lines and
+    /// faces with invalid indices are skipped.
+    pub fn new(mesh: &AiMesh) -> Self {
+        let vertex = |i: u32| mesh.vertices.get(i as usize).copied();
+        let mut triangles = Vec::new();
+        for (f, face) in mesh.faces.iter().enumerate() {
+            let Some((&first, rest)) = face.indices.split_first() else {
+                continue;
+            };
+            for pair in rest.windows(2) {
+                let indices = [first, pair[0], pair[1]];
+                if let (Some(a), Some(b), Some(c)) =
+                    (vertex(indices[0]), vertex(indices[1]), vertex(indices[2]))
+                {
+                    triangles.push(Triangle {
+                        face: f as u32,
+                        indices,
+                        vertices: [a, b, c],
+                    });
+                }
+            }
+        }
+        let bounds = triangles
+            .iter()
+            .map(|t| AABB::from_points(&t.vertices))
+            .collect::<Vec<_>>();
+        Self {
+            bvh: Bvh::new(&bounds),
+            triangles,
+        }
+    }
+
+    /// Number of triangles
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    pub fn bounds(&self) -> AABB {
+        self.bvh.bounds()
+    }
+
+    /// Closest intersection of the ray with both sides of the triangles
+    pub fn raycast(&self, ray: &AiRay) -> Option<TriangleHit> {
+        let mut best: Option<TriangleHit> = None;
+        let limit = |best: &Option<TriangleHit>| best.map_or(AiReal::INFINITY, |b| b.t);
+        self.bvh.traverse(|visit| match visit {
+            Visit::Node(aabb) => ray.intersect_aabb(aabb).is_some_and(|t| t < limit(&best)),
+            Visit::Primitive(i) => {
+                let triangle = &self.triangles[i as usize];
+                let [a, b, c] = triangle.vertices;
+                if let Some((t, u, v)) = ray.intersect_triangle(a, b, c)
+                    && t < limit(&best)
+                {
+                    best = Some(TriangleHit {
+                        face: triangle.face,
+                        indices: triangle.indices,
+                        barycentrics: Vec3::new(1.0 - u - v, u, v),
+                        t,
+                    });
+                }
+                true
+            }
+        });
+        best
+    }
+
+    /// The point on the triangles closest to `position`, `None` if there
+    /// are no triangles
+    pub fn nearest_point(&self, position: Vec3) -> Option<SurfacePoint> {
+        let mut best: Option<SurfacePoint> = None;
+        let limit = |best: &Option<SurfacePoint>| {
+            best.map_or(AiReal::INFINITY, |b| b.distance * b.distance)
+        };
+        self.bvh.traverse(|visit| match visit {
+            Visit::Node(aabb) => distance_squared_to(aabb, position) < limit(&best),
+            Visit::Primitive(i) => {
+                let triangle = &self.triangles[i as usize];
+                let [a, b, c] = triangle.vertices;
+                let barycentrics = closest_point_on_triangle(position, a, b, c);
+                let point = a * barycentrics.x + b * barycentrics.y + c * barycentrics.z;
+                let distance_sq = point.distance_squared(position);
+                if distance_sq < limit(&best) {
+                    best = Some(SurfacePoint {
+                        face: triangle.face,
+                        indices: triangle.indices,
+                        barycentrics,
+                        point,
+                        distance: distance_sq.sqrt(),
+                    });
+                }
+                true
+            }
+        });
+        best
+    }
+}
+
+/// A [`TriangleBvh`] for every mesh of a scene, to cast many rays at the
+/// same scene with [`AiScene::raycast_cached`].
+#[derive(Debug, Clone, Default)]
+pub struct SceneBvh {
+    meshes: Vec<TriangleBvh>,
+}
+
+impl SceneBvh {
+    pub fn new(scene: &AiScene) -> Self {
+        Self {
+            meshes: scene.meshes.iter().map(TriangleBvh::new).collect(),
+        }
+    }
+
+    pub fn mesh(&self, index: u32) -> Option<&TriangleBvh> {
+        self.meshes.get(index as usize)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{PointBvh, SceneBvh, TriangleBvh};
+    use crate::{
+        AiReal,
+        structs::{
+            face::AiFace, material::AiMaterial, mesh::AiMesh, ray::AiRay,
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Vec3},
+    };
+
+    #[test]
+    fn test_spatial() {
+        // a 16 x 16 grid of quads in the xy plane, with a bump in the middle
+        let n = 16;
+        let mut vertices = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                let z = if (x, y) == (n / 2, n / 2) { 1.0 } else { 0.0 };
+                vertices.push(Vec3::new(x as AiReal, y as AiReal, z));
+            }
+        }
+        let faces = (0..n)
+            .flat_map(|y| (0..n).map(move |x| y * (n + 1) + x))
+            .map(|i| AiFace {
+                indices: Box::new([i, i + 1, i + n + 2, i + n + 1]),
+            })
+            .collect::<Vec<_>>();
+        let mesh = AiMesh {
+            vertices,
+            faces,
+            ..Default::default()
+        };
+
+        let points = PointBvh::new(&mesh.vertices);
+        let center = Vec3::new(3.2, 4.9, 0.0);
+        let mut found = Vec::new();
+        points.find_within(center, 1.5, &mut found);
+        let expected = (0..mesh.vertices.len() as u32)
+            .filter(|&i| mesh.vertices[i as usize].distance(center) <= 1.5)
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected);
+        assert_eq!(points.nearest(center), Some(5 * (n + 1) + 3));
+
+        let triangles = TriangleBvh::new(&mesh);
+        assert_eq!(triangles.len(), 2 * (n * n) as usize);
+        let nearest = triangles.nearest_point(Vec3::new(2.25, 3.5, 2.0)).unwrap();
+        assert_eq!(nearest.face, 3 * n + 2);
+        assert!(nearest.point.abs_diff_eq(Vec3::new(2.25, 3.5, 0.0), 1e-6));
+        assert!((nearest.distance - 2.0).abs() < 1e-6);
+        let below = triangles.nearest_point(Vec3::new(-1.0, -1.0, 0.0)).unwrap();
+        assert_eq!(below.point, Vec3::ZERO);
+
+        // the hierarchy finds the same hits as testing every triangle
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", Mat4::from_rotation_x(0.3), None);
+        let mesh = builder.add_mesh(mesh);
+        builder.attach_mesh(root, mesh);
+        let scene = builder.build().unwrap();
+        let bvh = SceneBvh::new(&scene);
+        for i in 0..64 {
+            let target = Vec3::new((i % 8) as AiReal * 2.1, (i / 8) as AiReal * 2.1, 0.0);
+            let origin = Vec3::new(8.0, 8.0, 10.0);
+            let ray = AiRay::new(origin, target - origin);
+            let expected = scene.raycast(&ray);
+            let hit = scene.raycast_cached(&ray, &bvh);
+            assert_eq!(
+                hit.map(|h| (h.face, h.indices, h.t)),
+                expected.map(|h| (h.face, h.indices, h.t))
+            );
+        }
+    }
+}
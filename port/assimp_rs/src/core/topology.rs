@@ -0,0 +1,289 @@
+//! Half-edge adjacency of the faces of a mesh, to check whether it is a
+//! closed manifold as required by 3D printing formats.
+
+use std::{collections::HashMap, fmt};
+
+use crate::structs::mesh::AiMesh;
+
+/// One side of an edge of a face, pointing from `origin` to the origin of
+/// `next`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalfEdge {
+    /// Vertex the half-edge starts at, the first vertex of the mesh at its
+    /// position
+    pub origin: u32,
+    pub face: u32,
+    /// The following half-edge of the same face
+    pub next: u32,
+    /// The half-edge of the neighboring face in the opposite direction,
+    /// `None` at borders and at non-manifold or inconsistent edges
+    pub twin: Option<u32>,
+}
+
+/// Half-edge adjacency of the polygons of a mesh. Vertices at the same
+/// position are treated as one, so meshes split at UV seams or hard edges
+/// are still closed. Points, lines and edges between vertices at the same
+/// position are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    half_edges: Vec<HalfEdge>,
+    boundary_edges: Vec<[u32; 2]>,
+    non_manifold_edges: Vec<[u32; 2]>,
+    inconsistent_edges: Vec<[u32; 2]>,
+    component_of_face: Vec<Option<u32>>,
+    component_count: usize,
+}
+
+/// Summary of a [`Topology`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TopologyReport {
+    /// Edges with a face on one side only
+    pub boundary_edges: usize,
+    /// Edges shared by more than two faces
+    pub non_manifold_edges: usize,
+    /// Edges whose two faces have opposite winding orders
+    pub inconsistent_edges: usize,
+    /// Groups of faces connected by edges
+    pub components: usize,
+}
+
+impl TopologyReport {
+    /// Whether the mesh is a closed, consistently oriented manifold
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges == 0 && self.non_manifold_edges == 0 && self.inconsistent_edges == 0
+    }
+}
+
+impl fmt::Display for TopologyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} components, {} boundary edges, {} non-manifold edges, {} inconsistent edges{}",
+            self.components,
+            self.boundary_edges,
+            self.non_manifold_edges,
+            self.inconsistent_edges,
+            if self.is_watertight() {
+                ", watertight"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+fn find(parent: &mut [u32], mut i: u32) -> u32 {
+    while parent[i as usize] != i {
+        parent[i as usize] = parent[parent[i as usize] as usize];
+        i = parent[i as usize];
+    }
+    i
+}
+
+impl Topology {
+    pub fn new(mesh: &AiMesh) -> Self {
+        let mut positions = HashMap::new();
+        let welded = mesh
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                *positions
+                    .entry(v.to_array().map(|c| c.to_bits()))
+                    .or_insert(i as u32)
+            })
+            .collect::<Vec<_>>();
+
+        let mut topology = Self::default();
+        let mut edges = HashMap::<[u32; 2], Vec<u32>>::new();
+        for (f, face) in mesh.faces.iter().enumerate() {
+            if face.indices.len() < 3 || face.indices.iter().any(|&i| i as usize >= welded.len()) {
+                continue;
+            }
+            let first = topology.half_edges.len() as u32;
+            let count = face.indices.len() as u32;
+            for (k, &i) in face.indices.iter().enumerate() {
+                let k = k as u32;
+                let origin = welded[i as usize];
+                let target = welded[face.indices[((k + 1) % count) as usize] as usize];
+                let index = topology.half_edges.len() as u32;
+                topology.half_edges.push(HalfEdge {
+                    origin,
+                    face: f as u32,
+                    next: first + (k + 1) % count,
+                    twin: None,
+                });
+                if origin != target {
+                    edges
+                        .entry([origin.min(target), origin.max(target)])
+                        .or_default()
+                        .push(index);
+                }
+            }
+        }
+
+        let mut parent = (0..mesh.faces.len() as u32).collect::<Vec<_>>();
+        let mut sorted = edges.into_iter().collect::<Vec<_>>();
+        sorted.sort_unstable_by_key(|(edge, _)| *edge);
+        for (edge, half_edges) in sorted {
+            for pair in half_edges.windows(2) {
+                let [a, b] = [pair[0], pair[1]].map(|h| topology.half_edges[h as usize].face);
+                let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+                parent[a as usize] = b;
+            }
+            match *half_edges {
+                [_] => topology.boundary_edges.push(edge),
+                [a, b] => {
+                    if topology.half_edges[a as usize].origin
+                        == topology.half_edges[b as usize].origin
+                    {
+                        topology.inconsistent_edges.push(edge);
+                    } else {
+                        topology.half_edges[a as usize].twin = Some(b);
+                        topology.half_edges[b as usize].twin = Some(a);
+                    }
+                }
+                _ => topology.non_manifold_edges.push(edge),
+            }
+        }
+
+        let mut component_of_root = HashMap::new();
+        topology.component_of_face = vec![None; mesh.faces.len()];
+        for half_edge in &topology.half_edges {
+            let face = half_edge.face as usize;
+            if topology.component_of_face[face].is_none() {
+                let root = find(&mut parent, half_edge.face);
+                let count = component_of_root.len() as u32;
+                topology.component_of_face[face] =
+                    Some(*component_of_root.entry(root).or_insert(count));
+            }
+        }
+        topology.component_count = component_of_root.len();
+        topology
+    }
+
+    pub fn half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    /// Edges with a face on one side only, as pairs of vertices
+    pub fn boundary_edges(&self) -> &[[u32; 2]] {
+        &self.boundary_edges
+    }
+
+    /// Edges shared by more than two faces, as pairs of vertices
+    pub fn non_manifold_edges(&self) -> &[[u32; 2]] {
+        &self.non_manifold_edges
+    }
+
+    /// Edges whose two faces have opposite winding orders, as pairs of
+    /// vertices
+    pub fn inconsistent_edges(&self) -> &[[u32; 2]] {
+        &self.inconsistent_edges
+    }
+
+    /// The connected component of each face, `None` for points, lines and
+    /// invalid faces
+    pub fn components(&self) -> &[Option<u32>] {
+        &self.component_of_face
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.component_count
+    }
+
+    pub fn report(&self) -> TopologyReport {
+        TopologyReport {
+            boundary_edges: self.boundary_edges.len(),
+            non_manifold_edges: self.non_manifold_edges.len(),
+            inconsistent_edges: self.inconsistent_edges.len(),
+            components: self.component_count,
+        }
+    }
+}
+
+impl AiMesh {
+    /// Builds the half-edge adjacency of the faces, see [`Topology`]
+    pub fn topology(&self) -> Topology {
+        Topology::new(self)
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::TopologyReport;
+    use crate::{
+        formats::x::importer::Importer,
+        get_model_path,
+        structs::{face::AiFace, scene::AiScene},
+        traits::importer::trait_define::InternalImporter,
+        utils::float_precision::Vec3,
+    };
+
+    #[test]
+    fn test_topology() {
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let mut scene = AiScene::default();
+        Importer::import_from_buf(&source, &mut scene).unwrap();
+        let cube = &scene.meshes[0];
+        let topology = cube.topology();
+        assert_eq!(
+            topology.report(),
+            TopologyReport {
+                components: 1,
+                ..Default::default()
+            }
+        );
+        assert!(topology.report().is_watertight());
+        for (i, half_edge) in topology.half_edges().iter().enumerate() {
+            let twin = topology.half_edges()[half_edge.twin.unwrap() as usize];
+            assert_eq!(twin.twin, Some(i as u32));
+            let next = topology.half_edges()[half_edge.next as usize];
+            assert_eq!(twin.origin, next.origin);
+        }
+
+        // an open box
+        let mut open = cube.clone();
+        let removed = open.faces.pop().unwrap();
+        let report = open.topology().report();
+        assert_eq!(report.boundary_edges, removed.indices.len());
+        assert!(!report.is_watertight());
+
+        // a fin on an edge of the cube and a flipped face
+        let mut broken = cube.clone();
+        let [a, b, ..] = *cube.faces[0].indices else {
+            unreachable!()
+        };
+        broken.vertices.push(Vec3::splat(5.0));
+        let fin = broken.vertices.len() as u32 - 1;
+        broken.faces.push(AiFace {
+            indices: Box::new([a, b, fin]),
+        });
+        broken.faces[1].indices.reverse();
+        let topology = broken.topology();
+        assert_eq!(topology.non_manifold_edges().len(), 1);
+        assert_eq!(topology.boundary_edges().len(), 2);
+        assert_eq!(
+            topology.inconsistent_edges().len(),
+            cube.faces[1].indices.len()
+        );
+
+        // two separate boxes
+        let mut boxes = cube.clone();
+        let offset = boxes.vertices.len() as u32;
+        boxes
+            .vertices
+            .extend(cube.vertices.iter().map(|v| *v + Vec3::X * 10.0));
+        boxes.faces.extend(cube.faces.iter().map(|f| AiFace {
+            indices: f.indices.iter().map(|i| i + offset).collect(),
+        }));
+        let topology = boxes.topology();
+        assert_eq!(topology.component_count(), 2);
+        assert!(topology.report().is_watertight());
+        assert_eq!(topology.components()[0], Some(0));
+        assert_eq!(topology.components()[cube.faces.len()], Some(1));
+    }
+}
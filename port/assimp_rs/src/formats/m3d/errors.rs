@@ -0,0 +1,63 @@
+use thiserror::Error;
+
+use crate::{
+    structs::scene_builder::SceneBuildError, traits::importer::error::ImportError,
+    utils::compression::error::CompressionError,
+};
+
+/// Model 3D specific import errors
+#[derive(Debug, Error)]
+pub enum M3dImportError {
+    #[error("Not a binary Model 3D file")]
+    InvalidFormat,
+
+    #[error("ASCII Model 3D files are not supported, only binary ones")]
+    UnsupportedAsciiFormat,
+
+    #[error("The header gives a length of {expected} bytes, but the file has {actual}")]
+    LengthMismatch { expected: usize, actual: usize },
+
+    #[error("Vertex and string indices are at most 4 bytes large")]
+    InvalidIndexSize,
+
+    #[error("Unexpected end of the {0} chunk")]
+    UnexpectedEnd(&'static str),
+
+    #[error("String offset {0} is outside of the string table")]
+    InvalidString(u32),
+
+    #[error("{kind} {index} does not exist")]
+    InvalidIndex { kind: &'static str, index: u32 },
+
+    #[error("Import error: {0}")]
+    ImportError(#[from] ImportError),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Build error: {0}")]
+    BuildError(#[from] SceneBuildError),
+}
+
+impl From<CompressionError> for M3dImportError {
+    fn from(error: CompressionError) -> Self {
+        Self::ImportError(ImportError::CompressionError(error))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum M3dExportError {
+    #[error("Model 3D files are binary, they can't be exported to a string")]
+    BinaryFormat,
+
+    #[error(
+        "Mesh {mesh:?} has a face with {count} indices, only triangles are supported, run the Triangulate step first"
+    )]
+    NotTriangulated { mesh: String, count: usize },
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Compression error: {0}")]
+    CompressionError(#[from] CompressionError),
+}
@@ -0,0 +1,555 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    errors::M3dExportError,
+    importer::{AI_METADATA_M3D_AUTHOR, AI_METADATA_M3D_DESCRIPTION, DEFAULT_MATERIAL_NAME},
+    model::{
+        Action, Asset, Bone, Face, Frame, MAP_PROPERTIES, Material, Model, PROP_IL, PropertyFormat,
+        PropertyValue, SCALAR_PROPERTIES, Skin, Transform, UNDEF, Vertex, from_color,
+    },
+};
+use crate::{
+    AiReal,
+    structs::{
+        anim::{AiAnimation, skinning::SkinningPalette},
+        coordinate_system::CoordinateSystem,
+        exporter::{AI_CONFIG_EXPORT_M3D_COMPRESS, ExportProperties},
+        exporter_desc::ExporterDesc,
+        material::{AI_MATKEY_SHADING_MODEL, AiMaterial, AiShadingMode, GetProperty},
+        mesh::AiMesh,
+        meta::{AI_METADATA_SOURCE_COPYRIGHT, MetadataExt},
+        names::NameRules,
+        nodes::Index,
+        scene::{AiNode, AiScene},
+        skinning::MAX_BONES_PER_VERTEX,
+    },
+    traits::exporter::trait_define,
+    utils::float_precision::{Mat3, Mat4, Vec4, compose, decompose, to_f32},
+};
+
+pub(crate) static DESC: ExporterDesc = ExporterDesc {
+    id: "m3d",
+    description: "Model 3D",
+    file_extension: "m3d",
+};
+
+pub struct Exporter<'source> {
+    properties: &'source ExportProperties,
+    scene: &'source AiScene,
+}
+
+impl<'source> Exporter<'source> {
+    pub fn new(scene: &'source AiScene, properties: &'source ExportProperties) -> Self {
+        Self { scene, properties }
+    }
+
+    pub fn get_info(&self) -> &ExporterDesc {
+        &DESC
+    }
+
+    /// Writes the scene as binary file. Meshes are transformed into the
+    /// space of the root node, bones without animation channels or weights
+    /// are only written if they are ancestors of bones.
+    pub fn write_to_vec(&self) -> Result<Vec<u8>, M3dExportError> {
+        let model = ModelBuilder::new(self.scene).build()?;
+        let compress = self
+            .properties
+            .get_bool_or(AI_CONFIG_EXPORT_M3D_COMPRESS, true);
+        Ok(model.to_bytes(compress)?)
+    }
+}
+
+impl trait_define::Exporter<M3dExportError> for Exporter<'_> {
+    fn desc() -> &'static ExporterDesc {
+        &DESC
+    }
+
+    #[cfg(feature = "std")]
+    fn export_to_writer<W: std::io::Write>(
+        scene: &AiScene,
+        properties: &ExportProperties,
+        writer: &mut W,
+    ) -> Result<(), M3dExportError> {
+        let bytes = Exporter::new(scene, properties).write_to_vec()?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn export_to_string(
+        _scene: &AiScene,
+        _properties: &ExportProperties,
+    ) -> Result<String, M3dExportError> {
+        Err(M3dExportError::BinaryFormat)
+    }
+}
+
+/// Builds a [`Model`] from a scene, the vertices, texture coordinates and
+/// skins are shared by all meshes
+struct ModelBuilder<'a> {
+    scene: &'a AiScene,
+    model: Model,
+    /// Globals of the nodes in the current pose
+    palette: SkinningPalette,
+    /// Index into [`Model::vertices`] by the bits of the position, color
+    /// and skin
+    vertices: HashMap<([u32; 4], u32, u32), u32>,
+    texture_coords: HashMap<[u32; 2], u32>,
+    /// Index into [`Model::skins`] by the quantized influences
+    skins: HashMap<Vec<(u32, u8)>, u32>,
+    /// The node of every bone
+    bone_nodes: Vec<Index<AiNode>>,
+    /// Index into [`Model::bones`] by node name
+    bone_by_name: HashMap<&'a str, u32>,
+    /// The pose of every bone as vertex indices, in the bind pose
+    bind_pose: Vec<(u32, u32)>,
+}
+
+impl<'a> ModelBuilder<'a> {
+    fn new(scene: &'a AiScene) -> Self {
+        Self {
+            scene,
+            model: Model::default(),
+            palette: SkinningPalette::new(scene),
+            vertices: HashMap::new(),
+            texture_coords: HashMap::new(),
+            skins: HashMap::new(),
+            bone_nodes: Vec::new(),
+            bone_by_name: HashMap::new(),
+            bind_pose: Vec::new(),
+        }
+    }
+
+    fn build(mut self) -> Result<Model, M3dExportError> {
+        let scene = self.scene;
+        let root = scene.root.and_then(|root| scene.get_node_by_index(root));
+        self.model.name = root.map_or_else(String::new, |root| root.name.clone());
+        let metadata = &scene.metadata;
+        for (text, key) in [
+            (&mut self.model.license, AI_METADATA_SOURCE_COPYRIGHT),
+            (&mut self.model.author, AI_METADATA_M3D_AUTHOR),
+            (&mut self.model.description, AI_METADATA_M3D_DESCRIPTION),
+        ] {
+            *text = metadata.get_str(key).unwrap_or_default().to_owned();
+        }
+        self.model.scale = CoordinateSystem::from_metadata(metadata).map_or(1.0, |system| {
+            to_f32(system.unit_scale_factor / CoordinateSystem::METERS)
+        });
+
+        self.add_skeleton();
+        let materials = self.add_materials();
+        for (index, node) in scene.nodes.iter().enumerate() {
+            let global = self
+                .palette
+                .global_transform(Index::new(index as u32))
+                .unwrap_or(Mat4::IDENTITY);
+            for mesh in node.meshes.clone() {
+                let Some(ai_mesh) = scene.meshes.get(mesh as usize) else {
+                    continue;
+                };
+                let material = materials
+                    .get(ai_mesh.material_index as usize)
+                    .copied()
+                    .unwrap_or(UNDEF);
+                self.add_mesh(ai_mesh, global, material)?;
+            }
+        }
+        for animation in &scene.animations {
+            self.add_animation(animation);
+        }
+        self.model.has_colors |= self
+            .model
+            .materials
+            .iter()
+            .flat_map(|material| &material.properties)
+            .any(|(_, value)| matches!(value, PropertyValue::Color(_)));
+        Ok(self.model)
+    }
+
+    fn vertex(&mut self, position: Vec4, color: u32, skin: u32) -> u32 {
+        let bits = position.to_array().map(|c| to_f32(c).to_bits());
+        let vertices = &mut self.model.vertices;
+        *self.vertices.entry((bits, color, skin)).or_insert_with(|| {
+            vertices.push(Vertex {
+                position,
+                color,
+                skin,
+            });
+            (vertices.len() - 1) as u32
+        })
+    }
+
+    /// Adds the translation and rotation of a bone relative to its parent
+    /// as two vertices, the scale is lost
+    fn pose(&mut self, local: Mat4) -> (u32, u32) {
+        let (translation, rotation, _) = decompose(local);
+        let position = self.vertex(translation.extend(1.0), 0, UNDEF);
+        let orientation = self.vertex(Vec4::from(rotation.normalize()), 0, UNDEF);
+        (position, orientation)
+    }
+
+    /// Adds the nodes of bones and their ancestors below the root node as
+    /// bones, parents before their children. Top level bones get their
+    /// global transformation in the bind pose.
+    fn add_skeleton(&mut self) {
+        let scene = self.scene;
+        let Some(root) = scene.root else {
+            return;
+        };
+        let names = scene
+            .meshes
+            .iter()
+            .flat_map(|mesh| &mesh.bones)
+            .map(|bone| bone.name.as_str())
+            .collect::<HashSet<_>>();
+        let mut is_bone = vec![false; scene.nodes.len()];
+        for name in names {
+            let mut node = self.palette.node_index(name);
+            while let Some(index) = node {
+                if std::mem::replace(&mut is_bone[index.value()], true) {
+                    break;
+                }
+                node = scene.nodes[index.value()].parent.filter(|&p| p != root);
+            }
+        }
+
+        let locals = scene.bind_pose();
+        let mut stack = vec![(root, UNDEF, Mat4::IDENTITY)];
+        let mut visited = vec![false; scene.nodes.len()];
+        while let Some((index, parent, parent_global)) = stack.pop() {
+            let Some(node) = scene.get_node_by_index(index) else {
+                continue;
+            };
+            if std::mem::replace(&mut visited[index.value()], true) {
+                continue;
+            }
+            let global = locals[index.value()] * parent_global;
+            let mut bone = parent;
+            if is_bone[index.value()] {
+                let local = match parent {
+                    UNDEF => global,
+                    _ => locals[index.value()],
+                };
+                let (position, orientation) = self.pose(local);
+                bone = self.model.bones.len() as u32;
+                self.model.bones.push(Bone {
+                    parent,
+                    name: node.name.clone(),
+                    position,
+                    orientation,
+                });
+                self.bone_nodes.push(index);
+                self.bone_by_name.entry(&node.name).or_insert(bone);
+                self.bind_pose.push((position, orientation));
+            }
+            // reversed, so the children are visited in order
+            stack.extend(node.children.iter().rev().map(|&c| (c, bone, global)));
+        }
+    }
+
+    /// Adds the materials, returns the index into [`Model::materials`] of
+    /// every material of the scene. The default material of the importer
+    /// is not written.
+    fn add_materials(&mut self) -> Vec<u32> {
+        let mut names = HashSet::new();
+        let mut indices = Vec::with_capacity(self.scene.materials.len());
+        for material in &self.scene.materials {
+            let name = material.name().unwrap_or_default();
+            if name == DEFAULT_MATERIAL_NAME {
+                indices.push(UNDEF);
+                continue;
+            }
+            let name = NameRules::AI_STRING.sanitize(name);
+            let base = match name.as_ref() {
+                "" => "Material",
+                name => name,
+            };
+            let mut name = base.to_owned();
+            let mut suffix = 1;
+            while !names.insert(name.clone()) {
+                name = format!("{base}.{suffix:03}");
+                suffix += 1;
+            }
+            let properties = self.material_properties(material);
+            indices.push(self.model.materials.len() as u32);
+            self.model.materials.push(Material { name, properties });
+        }
+        indices
+    }
+
+    fn material_properties(&mut self, material: &AiMaterial) -> Vec<(u8, PropertyValue)> {
+        let mut properties = Vec::new();
+        for &(kind, key) in &SCALAR_PROPERTIES {
+            let value = match PropertyFormat::of(kind) {
+                Some(PropertyFormat::Color) => material
+                    .get_color(key)
+                    .map(|color| PropertyValue::Color(from_color(color))),
+                _ => material
+                    .get_float(key)
+                    .map(|number| PropertyValue::Float(to_f32(number))),
+            };
+            properties.extend(value.map(|value| (kind, value)));
+        }
+        let shading: Option<&AiShadingMode> = material.get_property(AI_MATKEY_SHADING_MODEL, 0);
+        if let Some(&shading) = shading {
+            let model = match shading {
+                AiShadingMode::NoShading => 0,
+                AiShadingMode::Phong | AiShadingMode::Blinn => 2,
+                _ => 1,
+            };
+            properties.push((PROP_IL, PropertyValue::Integer(model)));
+        }
+        for &(kind, texture_type) in &MAP_PROPERTIES {
+            let Some(slot) = material.get_texture(texture_type, 0) else {
+                continue;
+            };
+            if let Some(name) = self.texture_name(&slot.path) {
+                properties.push((kind, PropertyValue::Map(name)));
+            }
+        }
+        properties
+    }
+
+    /// The name of a texture, the file name without directory and
+    /// extension. Embedded PNG textures are inlined as assets, other
+    /// embedded textures are skipped.
+    fn texture_name(&mut self, path: &str) -> Option<String> {
+        let stem = |path: &str| {
+            let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+            match name.rsplit_once('.') {
+                Some((stem, _)) if !stem.is_empty() => stem.to_owned(),
+                _ => name.to_owned(),
+            }
+        };
+        let Some(index) = path.strip_prefix('*') else {
+            return Some(stem(path)).filter(|name| !name.is_empty());
+        };
+        let texture = index
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| self.scene.textures.get(index))?;
+        let data = match texture.compressed_data() {
+            Some(data) if texture.format_hint().eq_ignore_ascii_case("png") => data,
+            _ => {
+                log::warn!("Skipping embedded texture {path}, only PNG files can be inlined");
+                return None;
+            }
+        };
+        let name = match stem(&texture.filename) {
+            name if name.is_empty() => format!("texture{index}"),
+            name => name,
+        };
+        if !self.model.assets.iter().any(|asset| asset.name == name) {
+            self.model.assets.push(Asset {
+                name: name.clone(),
+                data,
+            });
+        }
+        Some(name)
+    }
+
+    /// The skin of every vertex of a mesh, [`UNDEF`] for vertices without
+    /// influences. Only the strongest influences are kept.
+    fn add_skins(&mut self, mesh: &AiMesh) -> Vec<u32> {
+        let mut influences = vec![Vec::new(); mesh.vertices.len()];
+        for bone in &mesh.bones {
+            let Some(&index) = self.bone_by_name.get(bone.name.as_str()) else {
+                continue;
+            };
+            for weight in &bone.weights {
+                if let Some(vertex) = influences.get_mut(weight.vertex_id as usize)
+                    && weight.weight > 0.0
+                {
+                    vertex.push((index, weight.weight));
+                }
+            }
+        }
+        influences
+            .into_iter()
+            .map(|mut influences| {
+                if influences.is_empty() {
+                    return UNDEF;
+                }
+                influences.sort_by(|a, b| b.1.total_cmp(&a.1));
+                influences.truncate(MAX_BONES_PER_VERTEX);
+                let sum = influences.iter().map(|(_, weight)| weight).sum::<f32>();
+                let mut quantized = influences
+                    .iter()
+                    .map(|&(bone, weight)| (bone, (weight / sum * 255.0).round() as u8))
+                    .filter(|&(_, weight)| weight != 0)
+                    .collect::<Vec<_>>();
+                if quantized.is_empty() {
+                    quantized.push((influences[0].0, u8::MAX));
+                }
+                let skins = &mut self.model.skins;
+                *self.skins.entry(quantized).or_insert_with_key(|quantized| {
+                    skins.push(Skin {
+                        influences: quantized
+                            .iter()
+                            .map(|&(bone, weight)| (bone, weight as f32 / 255.0))
+                            .collect(),
+                    });
+                    (skins.len() - 1) as u32
+                })
+            })
+            .collect()
+    }
+
+    /// Adds the triangles of a mesh, transformed by the global
+    /// transformation of its node
+    fn add_mesh(
+        &mut self,
+        mesh: &AiMesh,
+        global: Mat4,
+        material: u32,
+    ) -> Result<(), M3dExportError> {
+        // row layout, so the transposed matrix transforms column vectors
+        let transform = global.transpose();
+        let normal_transform = Mat3::from_mat4(transform.inverse()).transpose();
+        let skins = self.add_skins(mesh);
+        let colors = &mesh.colors[0];
+        self.model.has_colors |= !colors.is_empty();
+
+        let mut vertices = Vec::with_capacity(mesh.vertices.len());
+        for (i, position) in mesh.vertices.iter().enumerate() {
+            let position = transform.transform_point3(*position).extend(1.0);
+            let color = colors.get(i).map_or(0, |color| {
+                from_color(Vec4::from_array(color.to_array().map(|c| c as AiReal)))
+            });
+            vertices.push(self.vertex(position, color, skins[i]));
+        }
+        let normals = mesh
+            .normals
+            .iter()
+            .map(|normal| {
+                let normal = (normal_transform * *normal).normalize_or_zero();
+                self.vertex(normal.extend(1.0), 0, UNDEF)
+            })
+            .collect::<Vec<_>>();
+        let texture_coords = mesh.texture_coords[0]
+            .iter()
+            .map(|uv| {
+                let bits = [to_f32(uv.x).to_bits(), to_f32(uv.y).to_bits()];
+                let texture_coords = &mut self.model.texture_coords;
+                *self.texture_coords.entry(bits).or_insert_with(|| {
+                    texture_coords.push([uv.x, uv.y]);
+                    (texture_coords.len() - 1) as u32
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for face in &mesh.faces {
+            let indices = match &*face.indices {
+                &[a, b, c] => [a, b, c].map(|i| i as usize),
+                indices if indices.len() < 3 => continue,
+                indices => {
+                    return Err(M3dExportError::NotTriangulated {
+                        mesh: mesh.name.clone(),
+                        count: indices.len(),
+                    });
+                }
+            };
+            let Some(face_vertices) = indices
+                .iter()
+                .map(|&i| vertices.get(i).copied())
+                .collect::<Option<Vec<_>>>()
+            else {
+                log::warn!(
+                    "Skipping a face of mesh {:?} with invalid indices",
+                    mesh.name
+                );
+                continue;
+            };
+            let lookup = |indices: [usize; 3], list: &[u32]| {
+                indices.map(|i| list.get(i).copied().unwrap_or(UNDEF))
+            };
+            self.model.faces.push(Face {
+                material,
+                vertices: [face_vertices[0], face_vertices[1], face_vertices[2]],
+                texture_coords: lookup(indices, &texture_coords),
+                normals: lookup(indices, &normals),
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds an action with a frame at every key of the channels of bones.
+    /// Frames only transform bones whose pose changes, top level bones are
+    /// moved into the space of the root node.
+    fn add_animation(&mut self, animation: &AiAnimation) {
+        let scene = self.scene;
+        let channels = animation
+            .channels
+            .iter()
+            .filter_map(|channel| {
+                let bone = *self.bone_by_name.get(&*channel.node_name)?;
+                Some((bone, channel))
+            })
+            .collect::<Vec<_>>();
+        let mut times = channels
+            .iter()
+            .flat_map(|(_, channel)| {
+                let positions = channel.position_keys.iter().map(|key| key.time);
+                let rotations = channel.rotation_keys.iter().map(|key| key.time);
+                let scalings = channel.scaling_keys.iter().map(|key| key.time);
+                positions.chain(rotations).chain(scalings)
+            })
+            .collect::<Vec<_>>();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+
+        let ticks_per_second = animation.ticks_per_second_or_default();
+        let msec = |ticks: f64| (ticks / ticks_per_second * 1000.0).round().max(0.0) as u32;
+        let mut poses = self.bind_pose.clone();
+        let mut action = Action {
+            name: animation.name.clone(),
+            duration: msec(animation.duration),
+            frames: Vec::new(),
+        };
+        for ticks in times {
+            let time = msec(ticks);
+            if action.frames.last().is_some_and(|frame| frame.time == time) {
+                continue;
+            }
+            let mut frame = Frame {
+                time,
+                transforms: Vec::new(),
+            };
+            for &(bone, channel) in &channels {
+                let index = bone as usize;
+                let node = &scene.nodes[self.bone_nodes[index].value()];
+                let (translation, rotation, scale) = decompose(node.transformation);
+                let (position, orientation, scaling) = channel.sample(ticks);
+                let mut local = compose(
+                    position.unwrap_or(translation),
+                    orientation.unwrap_or(rotation),
+                    scaling.unwrap_or(scale),
+                );
+                if self.model.bones[index].parent == UNDEF {
+                    let parent_global = node
+                        .parent
+                        .and_then(|parent| self.palette.global_transform(parent))
+                        .unwrap_or(Mat4::IDENTITY);
+                    local *= parent_global;
+                }
+                let pose = self.pose(local);
+                if poses[index] != pose {
+                    poses[index] = pose;
+                    frame.transforms.push(Transform {
+                        bone,
+                        position: pose.0,
+                        orientation: pose.1,
+                    });
+                }
+            }
+            action.frames.push(frame);
+        }
+        if action.frames.is_empty() {
+            log::warn!(
+                "Skipping animation {:?}, it does not animate bones",
+                animation.name
+            );
+            return;
+        }
+        self.model.actions.push(action);
+    }
+}
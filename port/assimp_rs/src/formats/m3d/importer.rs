@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use super::{
+    errors::M3dImportError,
+    model::{
+        Action, Face, MAGIC, MAP_PROPERTIES, Material, Model, PROP_IL, PropertyValue,
+        SCALAR_PROPERTIES, UNDEF, Vertex, to_color,
+    },
+};
+use crate::{
+    AiReal,
+    core::profiler::{ProfileScope, Profiler},
+    structs::{
+        aabb::AABB,
+        anim::{AiAnimation, anim::AiNodeAnim},
+        bone::AiBone,
+        coordinate_system::CoordinateSystem,
+        face::AiFace,
+        importer::ImportProperties,
+        importer_desc::{ImporterDesc, ImporterFlags},
+        key::{AiQuatKey, AiVectorKey},
+        material::{
+            AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_NAME, AI_MATKEY_SHADING_MODEL, AddProperty,
+            AiMaterial, AiShadingMode, TextureSlot,
+        },
+        mesh::{AiMesh, AiVertexWeight, PrimitiveTypeFlags},
+        meta::{AI_METADATA_SOURCE_COPYRIGHT, AI_METADATA_SOURCE_FORMAT, MetadataExt},
+        nodes::Index,
+        scene::{AiNode, AiScene},
+        scene_builder::SceneBuilder,
+        texture::AiTexture,
+    },
+    traits::importer::trait_define::{FormatHeader, InternalImporter},
+    utils::float_precision::{Mat4, Quat, Vec3, Vec4, compose},
+};
+
+/// Value of the [`AI_METADATA_SOURCE_FORMAT`] metadata of scenes imported
+/// from Model 3D files
+pub const SOURCE_FORMAT: &str = "M3D";
+
+/// Name of the root node of models without a name
+pub const ROOT_NODE_NAME: &str = "M3DRoot";
+
+/// Scene metadata key of the author of a model, its license is stored as
+/// [`AI_METADATA_SOURCE_COPYRIGHT`]
+pub const AI_METADATA_M3D_AUTHOR: &str = "M3D.Author";
+/// Scene metadata key of the description of a model
+pub const AI_METADATA_M3D_DESCRIPTION: &str = "M3D.Description";
+
+/// Ticks per second of the animations. Frames start at milliseconds, the
+/// ticks are centiseconds like in assimp.
+pub const TICKS_PER_SECOND: f64 = 100.0;
+
+/// Name of the material of faces without material, which is always the
+/// first material of the scene
+pub const DEFAULT_MATERIAL_NAME: &str = "DefaultMaterial";
+
+pub(crate) static DESC: ImporterDesc = ImporterDesc {
+    name: "Model 3D Importer",
+    author: "",
+    maintainer: "",
+    comments: "binary files with meshes, materials, skeletons and actions, no ASCII files, shapes or voxels",
+    flags: ImporterFlags::SUPPORT_BINARY_FLAVOUR.bits()
+        | ImporterFlags::SUPPORT_COMPRESSED_FLAVOUR.bits()
+        | ImporterFlags::LIMITED_SUPPORT.bits(),
+    min_major: 0,
+    min_minor: 0,
+    max_major: 0,
+    max_minor: 0,
+    file_extensions: "m3d",
+};
+
+pub struct Importer;
+
+impl Importer {
+    pub fn get_info(&self) -> &ImporterDesc {
+        &DESC
+    }
+
+    fn import(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        profiler: &mut Profiler,
+    ) -> Result<(), M3dImportError> {
+        let model = profiler.measure(ProfileScope::Import, "parsing", || Model::parse(buf))?;
+        *ai_scene = profiler.measure(ProfileScope::Import, "conversion", || {
+            Converter::new(&model).convert()
+        })?;
+        let metadata = &mut ai_scene.metadata;
+        metadata.set(AI_METADATA_SOURCE_FORMAT, SOURCE_FORMAT);
+        for (key, text) in [
+            (AI_METADATA_SOURCE_COPYRIGHT, &model.license),
+            (AI_METADATA_M3D_AUTHOR, &model.author),
+            (AI_METADATA_M3D_DESCRIPTION, &model.description),
+        ] {
+            if !text.is_empty() {
+                metadata.set(key, text.clone());
+            }
+        }
+        CoordinateSystem::Y_UP_RIGHT_HANDED
+            .with_unit_scale_factor(model.scale as AiReal * CoordinateSystem::METERS)
+            .write_metadata(metadata);
+        Ok(())
+    }
+}
+
+impl FormatHeader<4> for Importer {
+    const HEADER: [u8; 4] = MAGIC;
+}
+
+impl InternalImporter<M3dImportError> for Importer {
+    #[cfg(feature = "std")]
+    fn import_from_file(file_name: &str, ai_scene: &mut AiScene) -> Result<(), M3dImportError> {
+        let buf = std::fs::read(file_name)?;
+        Self::import_from_buf(&buf, ai_scene)
+    }
+
+    fn import_from_buf(buf: &[u8], ai_scene: &mut AiScene) -> Result<(), M3dImportError> {
+        Self::import(buf, ai_scene, &mut Profiler::disabled())
+    }
+
+    fn import_from_buf_with_profiler(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        _properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<(), M3dImportError> {
+        Self::import(buf, ai_scene, profiler)
+    }
+}
+
+/// Converts a model to an [`AiScene`]. Bones become nodes below the root
+/// node, the meshes are attached to the root node.
+struct Converter<'a> {
+    model: &'a Model,
+    builder: SceneBuilder,
+    /// The embedded textures by name of the inlined asset
+    textures: HashMap<&'a str, u32>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(model: &'a Model) -> Self {
+        Self {
+            model,
+            builder: SceneBuilder::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    fn convert(mut self) -> Result<AiScene, M3dImportError> {
+        let model = self.model;
+        let name = match model.name.as_str() {
+            "" => ROOT_NODE_NAME,
+            name => name,
+        };
+        let root = self.builder.add_node(name, Mat4::IDENTITY, None);
+        let globals = self.convert_bones(root)?;
+
+        let mut default = AiMaterial::default();
+        default.add_property(AI_MATKEY_NAME, DEFAULT_MATERIAL_NAME.to_owned(), 0);
+        default.add_property(AI_MATKEY_COLOR_DIFFUSE, Vec4::new(0.6, 0.6, 0.6, 1.0), 0);
+        self.builder.add_material(default);
+        for material in &model.materials {
+            let material = self.convert_material(material);
+            self.builder.add_material(material);
+        }
+
+        // faces are grouped by material, the groups are in the order the
+        // materials are used first. Material 0 is the default material.
+        let mut groups: Vec<(u32, Vec<&Face>)> = Vec::new();
+        for face in &model.faces {
+            let material = match face.material {
+                m if (m as usize) < model.materials.len() => m + 1,
+                _ => 0,
+            };
+            match groups.iter_mut().find(|(m, _)| *m == material) {
+                Some((_, faces)) => faces.push(face),
+                None => groups.push((material, vec![face])),
+            }
+        }
+        for (material, faces) in groups {
+            let mesh = self.convert_mesh(material, &faces, &globals)?;
+            let mesh = self.builder.add_mesh(mesh);
+            self.builder.attach_mesh(root, mesh);
+        }
+
+        for action in &model.actions {
+            let animation = self.convert_action(action)?;
+            self.builder.add_animation(animation);
+        }
+        Ok(self.builder.build()?)
+    }
+
+    fn vertex(&self, index: u32) -> Result<&'a Vertex, M3dImportError> {
+        self.model
+            .vertices
+            .get(index as usize)
+            .ok_or(M3dImportError::InvalidIndex {
+                kind: "Vertex",
+                index,
+            })
+    }
+
+    /// The translation and rotation stored in two vertices, of a bone
+    /// relative to its parent
+    fn pose(&self, position: u32, orientation: u32) -> Result<(Vec3, Quat), M3dImportError> {
+        let position = self.vertex(position)?.position.truncate();
+        let [x, y, z, w] = self.vertex(orientation)?.position.to_array();
+        let rotation = Quat::from_xyzw(x, y, z, w);
+        let rotation = match rotation.length_squared() > 0.0 {
+            true => rotation.normalize(),
+            false => Quat::IDENTITY,
+        };
+        Ok((position, rotation))
+    }
+
+    /// Adds a node for every bone, returns the global transformations of
+    /// the bones
+    fn convert_bones(&mut self, root: Index<AiNode>) -> Result<Vec<Mat4>, M3dImportError> {
+        let bones = &self.model.bones;
+        let mut nodes = Vec::with_capacity(bones.len());
+        let mut globals = Vec::with_capacity(bones.len());
+        for (i, bone) in bones.iter().enumerate() {
+            let (position, rotation) = self.pose(bone.position, bone.orientation)?;
+            let local = compose(position, rotation, Vec3::ONE);
+            let parent = bone.parent as usize;
+            let (parent, parent_global) = if parent < i {
+                (nodes[parent], globals[parent])
+            } else {
+                if bone.parent != UNDEF {
+                    log::warn!("Bone {:?} has an invalid parent {parent}", bone.name);
+                }
+                (root, Mat4::IDENTITY)
+            };
+            nodes.push(self.builder.add_node(&bone.name, local, Some(parent)));
+            globals.push(local * parent_global);
+        }
+        Ok(globals)
+    }
+
+    fn convert_material(&mut self, material: &'a Material) -> AiMaterial {
+        let mut ai_material = AiMaterial::default();
+        ai_material.add_property(AI_MATKEY_NAME, material.name.clone(), 0);
+        for (kind, value) in &material.properties {
+            if let PropertyValue::Map(texture) = value {
+                match MAP_PROPERTIES.iter().find(|(k, _)| k == kind) {
+                    Some(&(_, texture_type)) => {
+                        let path = self.texture(texture);
+                        ai_material.set_texture(texture_type, 0, TextureSlot::new(path));
+                    }
+                    None => log::debug!("Skipping texture property {kind}"),
+                }
+                continue;
+            }
+            if *kind == PROP_IL {
+                let mode = match value {
+                    PropertyValue::Integer(0) => AiShadingMode::NoShading,
+                    PropertyValue::Integer(2) => AiShadingMode::Phong,
+                    _ => AiShadingMode::Gouraud,
+                };
+                ai_material.add_property(AI_MATKEY_SHADING_MODEL, mode, 0);
+                continue;
+            }
+            let Some(&(_, key)) = SCALAR_PROPERTIES.iter().find(|(k, _)| k == kind) else {
+                log::debug!("Skipping property {kind}");
+                continue;
+            };
+            match *value {
+                PropertyValue::Color(color) => {
+                    let color = to_color(color).to_array().map(|c| c as AiReal);
+                    ai_material.add_property(key, Vec4::from_array(color), 0);
+                }
+                PropertyValue::Float(number) => {
+                    ai_material.add_property(key, number as AiReal, 0);
+                }
+                PropertyValue::Integer(number) => {
+                    ai_material.add_property(key, number as AiReal, 0);
+                }
+                PropertyValue::Map(_) => {}
+            }
+        }
+        ai_material
+    }
+
+    /// The path of a texture, `.png` files next to the model or inlined
+    /// assets, which are embedded
+    fn texture(&mut self, name: &'a str) -> String {
+        if let Some(index) = self.textures.get(name) {
+            return format!("*{index}");
+        }
+        let file_name = format!("{name}.png");
+        let Some(asset) = self.model.assets.iter().find(|asset| asset.name == name) else {
+            return file_name;
+        };
+        let mut texture = AiTexture::from_compressed(&asset.data, "png");
+        texture.filename = file_name.into();
+        let index = self.builder.add_texture(texture);
+        self.textures.insert(name, index);
+        format!("*{index}")
+    }
+
+    /// Converts the faces of a material, every corner gets its own vertex.
+    /// If any vertex is skinned, the mesh gets all bones.
+    fn convert_mesh(
+        &self,
+        material: u32,
+        faces: &[&Face],
+        globals: &[Mat4],
+    ) -> Result<AiMesh, M3dImportError> {
+        let model = self.model;
+        let name = match material {
+            0 => DEFAULT_MATERIAL_NAME,
+            m => &model.materials[m as usize - 1].name,
+        };
+        let mut mesh = AiMesh {
+            name: name.to_owned(),
+            primitive_type: PrimitiveTypeFlags::TRIANGLE,
+            material_index: material,
+            ..Default::default()
+        };
+        let mut colors = Vec::new();
+        let mut texture_coords = Vec::new();
+        let mut normals = Vec::new();
+        let mut weights = vec![Vec::new(); model.bones.len()];
+        for face in faces {
+            let start = mesh.vertices.len() as u32;
+            for corner in 0..3 {
+                let vertex_id = mesh.vertices.len() as u32;
+                let vertex = self.vertex(face.vertices[corner])?;
+                mesh.vertices.push(vertex.position.truncate());
+                colors.push(vertex.color);
+                texture_coords.push(
+                    model
+                        .texture_coords
+                        .get(face.texture_coords[corner] as usize)
+                        .map(|&[u, v]| Vec3::new(u, v, 0.0)),
+                );
+                normals.push(
+                    model
+                        .vertices
+                        .get(face.normals[corner] as usize)
+                        .map(|normal| normal.position.truncate()),
+                );
+                let influences = model
+                    .skins
+                    .get(vertex.skin as usize)
+                    .map_or(&[][..], |skin| &skin.influences);
+                for &(bone, weight) in influences {
+                    if let Some(weights) = weights.get_mut(bone as usize) {
+                        weights.push(AiVertexWeight { vertex_id, weight });
+                    }
+                }
+            }
+            mesh.faces.push(AiFace {
+                indices: (start..start + 3).collect(),
+            });
+        }
+
+        // colors of 0 are no colors, there are no per vertex flags
+        if model.has_colors && colors.iter().any(|&color| color != 0) {
+            mesh.colors[0] = colors.into_iter().map(to_color).collect();
+        }
+        match texture_coords.iter().copied().collect::<Option<Vec<_>>>() {
+            Some(texture_coords) => {
+                mesh.texture_coords[0] = texture_coords;
+                mesh.num_of_uv_components[0] = 2;
+            }
+            None if texture_coords.iter().any(Option::is_some) => {
+                log::warn!("Only some vertices of mesh {name:?} have texture coordinates");
+            }
+            None => {}
+        }
+        match normals.iter().copied().collect::<Option<Vec<_>>>() {
+            Some(normals) => mesh.normals = normals,
+            None if normals.iter().any(Option::is_some) => {
+                log::warn!("Only some vertices of mesh {name:?} have normals");
+            }
+            None => {}
+        }
+        if weights.iter().any(|weights| !weights.is_empty()) {
+            mesh.bones = model
+                .bones
+                .iter()
+                .zip(weights)
+                .zip(globals)
+                .map(|((bone, weights), global)| AiBone {
+                    name: bone.name.clone(),
+                    weights,
+                    offset_matrix: global.inverse(),
+                    ..Default::default()
+                })
+                .collect();
+        }
+        mesh.aabb = AABB::from_points(&mesh.vertices);
+        Ok(mesh)
+    }
+
+    /// Converts an action to an animation with a channel for every bone it
+    /// transforms. The channels have keys at every frame.
+    fn convert_action(&self, action: &Action) -> Result<AiAnimation, M3dImportError> {
+        let bones = &self.model.bones;
+        let mut poses = bones
+            .iter()
+            .map(|bone| self.pose(bone.position, bone.orientation))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut channels = vec![None; bones.len()];
+        for transform in action.frames.iter().flat_map(|frame| &frame.transforms) {
+            let channel =
+                channels
+                    .get_mut(transform.bone as usize)
+                    .ok_or(M3dImportError::InvalidIndex {
+                        kind: "Bone",
+                        index: transform.bone,
+                    })?;
+            channel.get_or_insert_with(|| AiNodeAnim {
+                node_name: bones[transform.bone as usize].name.as_str().into(),
+                scaling_keys: vec![AiVectorKey::new(0.0, Vec3::ONE)],
+                ..Default::default()
+            });
+        }
+        for frame in &action.frames {
+            let time = frame.time as f64 / 1000.0 * TICKS_PER_SECOND;
+            for transform in &frame.transforms {
+                poses[transform.bone as usize] =
+                    self.pose(transform.position, transform.orientation)?;
+            }
+            for (channel, &(position, rotation)) in channels.iter_mut().zip(&poses) {
+                if let Some(channel) = channel {
+                    channel.position_keys.push(AiVectorKey::new(time, position));
+                    channel.rotation_keys.push(AiQuatKey::new(time, rotation));
+                }
+            }
+        }
+        Ok(AiAnimation {
+            name: action.name.clone(),
+            duration: action.duration as f64 / 1000.0 * TICKS_PER_SECOND,
+            ticks_per_second: TICKS_PER_SECOND,
+            channels: channels.into_iter().flatten().collect(),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,354 @@
+pub mod errors;
+pub mod exporter;
+pub mod importer;
+mod model;
+
+#[allow(unused)]
+mod test {
+    use std::fs;
+
+    use super::{
+        errors::{M3dExportError, M3dImportError},
+        exporter::Exporter,
+        importer::{AI_METADATA_M3D_AUTHOR, DEFAULT_MATERIAL_NAME, Importer, SOURCE_FORMAT},
+        model::Model,
+    };
+    use crate::{
+        core::{exporter_for_extension, importer_for_data, importer_for_extension},
+        decompose, get_model_path,
+        structs::{
+            anim::{AiAnimation, anim::AiNodeAnim},
+            bone::AiBone,
+            coordinate_system::CoordinateSystem,
+            exporter::ExportProperties,
+            face::AiFace,
+            key::{AiQuatKey, AiVectorKey},
+            material::{
+                AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_NAME, AI_MATKEY_SHADING_MODEL,
+                AI_MATKEY_SHININESS, AddProperty, AiMaterial, AiShadingMode, AiTextureType,
+                GetProperty, TextureSlot,
+            },
+            mesh::{AiMesh, AiVertexWeight, PrimitiveTypeFlags},
+            meta::{AI_METADATA_SOURCE_COPYRIGHT, AI_METADATA_SOURCE_FORMAT, MetadataExt},
+            scene::AiScene,
+            scene_builder::SceneBuilder,
+            texture::AiTexture,
+        },
+        traits::{
+            exporter::trait_define::Exporter as _,
+            importer::trait_define::{FormatValidator, InternalImporter},
+        },
+        utils::float_precision::{Mat4, Quat, Vec3, Vec4, compose},
+    };
+
+    fn import(source: &[u8]) -> AiScene {
+        let mut scene = AiScene::default();
+        Importer::import_from_buf(source, &mut scene).unwrap();
+        scene
+    }
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(get_model_path("M3D", name)).unwrap()
+    }
+
+    #[test]
+    fn test_import_cubes() {
+        let scene = import(&read("cube_normals.m3d"));
+        assert_eq!(scene.nodes[0].name, "cube.obj");
+        assert_eq!(scene.meshes.len(), 1);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.name, DEFAULT_MATERIAL_NAME);
+        assert_eq!(mesh.faces.len(), 12);
+        assert_eq!(mesh.primitive_type, PrimitiveTypeFlags::TRIANGLE);
+        assert_eq!(mesh.normals.len(), 36);
+        assert!(mesh.normals.iter().all(|n| (n.length() - 1.0).abs() < 1e-3));
+        assert!(mesh.colors[0].is_empty());
+
+        // faces are grouped by material in the order of their first use
+        let scene = import(&read("cube_usemtl.m3d"));
+        let names = scene
+            .meshes
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["mtl3", "mtl", "mtl2"]);
+        assert_eq!(scene.nodes[0].meshes, 0..3);
+        let material = &scene.materials[scene.meshes[1].material_index as usize];
+        assert_eq!(material.name(), Some("mtl"));
+        assert_eq!(material.get_color(AI_MATKEY_COLOR_DIFFUSE), Some(Vec4::ONE));
+        assert_eq!(material.get_float(AI_MATKEY_SHININESS), Some(200.0));
+        assert_eq!(
+            material.get_property(AI_MATKEY_SHADING_MODEL, 0),
+            Some(&AiShadingMode::Gouraud)
+        );
+
+        let scene = import(&read("cube_with_vertexcolors.m3d"));
+        assert_eq!(scene.meshes[0].colors[0].len(), 36);
+    }
+
+    #[test]
+    fn test_import_suzanne() {
+        let scene = import(&read("suzanne.m3d"));
+        assert_eq!(scene.nodes[0].name, "Suzanne");
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.faces.len(), 968);
+        assert_eq!(mesh.texture_coords[0].len(), mesh.vertices.len());
+        assert_eq!(mesh.num_of_uv_components[0], 2);
+
+        let metadata = &scene.metadata;
+        assert_eq!(
+            metadata.get_str(AI_METADATA_SOURCE_FORMAT),
+            Some(SOURCE_FORMAT)
+        );
+        assert_eq!(metadata.get_str(AI_METADATA_SOURCE_COPYRIGHT), Some("GPL"));
+        assert_eq!(metadata.get_str(AI_METADATA_M3D_AUTHOR), Some("Blender"));
+        assert_eq!(
+            CoordinateSystem::from_metadata(metadata).unwrap(),
+            CoordinateSystem::Y_UP_RIGHT_HANDED.with_unit_scale_factor(CoordinateSystem::METERS)
+        );
+    }
+
+    #[test]
+    fn test_import_coordinate_sizes() {
+        // the same model with 8, 16 and 32 bit coordinates
+        let scenes = ["WusonBlitz0.m3d", "WusonBlitz1.m3d", "WusonBlitz2.m3d"]
+            .map(|name| import(&read(name)));
+        let precise = &scenes[2].meshes[0];
+        for scene in &scenes[..2] {
+            let mesh = &scene.meshes[0];
+            assert_eq!(mesh.faces.len(), precise.faces.len());
+            let extent = precise.aabb.max - precise.aabb.min;
+            assert!(
+                (mesh.aabb.min - precise.aabb.min).abs().max_element()
+                    < extent.max_element() / 50.0
+            );
+            assert!(
+                (mesh.aabb.max - precise.aabb.max).abs().max_element()
+                    < extent.max_element() / 50.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let mut scene = AiScene::default();
+        let error =
+            Importer::import_from_buf(&read("cube_with_vertexcolors.a3d"), &mut scene).unwrap_err();
+        assert!(matches!(error, M3dImportError::UnsupportedAsciiFormat));
+        let error = Importer::import_from_buf(b"not a model", &mut scene).unwrap_err();
+        assert!(matches!(error, M3dImportError::InvalidFormat));
+
+        let mut source = read("cube_normals.m3d");
+        source.truncate(source.len() / 2);
+        assert!(Importer::import_from_buf(&source, &mut scene).is_err());
+    }
+
+    #[test]
+    fn test_model_round_trip() {
+        for name in [
+            "cube_usemtl.m3d",
+            "cube_with_vertexcolors.m3d",
+            "suzanne.m3d",
+            "WusonBlitz0.m3d",
+            "WusonBlitz1.m3d",
+            "WusonBlitz2.m3d",
+        ] {
+            // coordinates are written with the size they were read with, so
+            // they are the same in single and double precision builds
+            let model = Model::parse(&read(name)).unwrap();
+            for compress in [false, true] {
+                let bytes = model.to_bytes(compress).unwrap();
+                assert_eq!(Model::parse(&bytes).unwrap(), model, "{name}");
+            }
+        }
+    }
+
+    /// A quad skinned to a hip and a knee, the knee bends in the animation
+    fn skinned_scene() -> AiScene {
+        let mut builder = SceneBuilder::new();
+        let root = builder.add_node(
+            "Armature",
+            compose(Vec3::Y, Quat::IDENTITY, Vec3::ONE),
+            None,
+        );
+        let hip_local = compose(Vec3::new(0.0, 0.5, 0.0), Quat::IDENTITY, Vec3::ONE);
+        let hip = builder.add_node("Hip", hip_local, Some(root));
+        let knee_local = compose(Vec3::Y, Quat::IDENTITY, Vec3::ONE);
+        builder.add_node("Knee", knee_local, Some(hip));
+        builder
+            .metadata_mut()
+            .set(AI_METADATA_M3D_AUTHOR, "Tester".to_owned());
+
+        let mut material = AiMaterial::default();
+        material.add_property(AI_MATKEY_NAME, "Red".to_owned(), 0);
+        material.add_property(AI_MATKEY_COLOR_DIFFUSE, Vec4::new(1.0, 0.0, 0.0, 1.0), 0);
+        material.add_property(AI_MATKEY_SHADING_MODEL, AiShadingMode::Phong, 0);
+        material.set_texture(AiTextureType::Diffuse, 0, TextureSlot::new("*0"));
+        let material = builder.add_material(material);
+        let mut texture = AiTexture::from_compressed(b"\x89PNG\r\n\x1a\n", "png");
+        texture.filename = "textures/albedo.png".into();
+        builder.add_texture(texture);
+
+        // offsets are in the space of the root node, which has the mesh
+        let hip_bind = compose(Vec3::new(0.0, 0.5, 0.0), Quat::IDENTITY, Vec3::ONE);
+        let knee_bind = compose(Vec3::new(0.0, 1.5, 0.0), Quat::IDENTITY, Vec3::ONE);
+        let weights = |vertices: [u32; 2]| {
+            vertices
+                .map(|vertex_id| AiVertexWeight {
+                    vertex_id,
+                    weight: 1.0,
+                })
+                .to_vec()
+        };
+        let mut mesh = AiMesh {
+            name: "Quad".to_owned(),
+            primitive_type: PrimitiveTypeFlags::TRIANGLE,
+            material_index: material,
+            vertices: vec![
+                Vec3::ZERO,
+                Vec3::X,
+                Vec3::new(1.0, 2.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+            ],
+            normals: vec![Vec3::Z; 4],
+            faces: vec![
+                AiFace {
+                    indices: [0, 1, 2].into(),
+                },
+                AiFace {
+                    indices: [0, 2, 3].into(),
+                },
+            ],
+            bones: vec![
+                AiBone {
+                    name: "Hip".to_owned(),
+                    weights: weights([0, 1]),
+                    offset_matrix: hip_bind.inverse(),
+                    ..Default::default()
+                },
+                AiBone {
+                    name: "Knee".to_owned(),
+                    weights: weights([2, 3]),
+                    offset_matrix: knee_bind.inverse(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        mesh.texture_coords[0] = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::Y];
+        mesh.num_of_uv_components[0] = 2;
+        let mesh = builder.add_mesh(mesh);
+        builder.attach_mesh(root, mesh);
+
+        let bent = Quat::from_rotation_z(core::f64::consts::FRAC_PI_2 as _);
+        builder.add_animation(AiAnimation {
+            name: "Bend".to_owned(),
+            duration: 50.0,
+            ticks_per_second: 100.0,
+            channels: vec![AiNodeAnim {
+                node_name: "Knee".into(),
+                position_keys: vec![AiVectorKey::new(0.0, Vec3::Y)],
+                rotation_keys: vec![
+                    AiQuatKey::new(0.0, Quat::IDENTITY),
+                    AiQuatKey::new(50.0, bent),
+                ],
+                scaling_keys: vec![AiVectorKey::new(0.0, Vec3::ONE)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let source = skinned_scene();
+        let properties = ExportProperties::default();
+        let mut data = Vec::new();
+        Exporter::export_to_writer(&source, &properties, &mut data).unwrap();
+        assert!(Importer::can_read_from_buf(&data));
+        let scene = import(&data);
+
+        let names = scene
+            .nodes
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Armature", "Hip", "Knee"]);
+        // the top bone is in the space of the root node
+        let (translation, _, _) = decompose(scene.nodes[1].transformation);
+        assert!((translation - Vec3::new(0.0, 1.5, 0.0)).length() < 1e-5);
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_M3D_AUTHOR),
+            Some("Tester")
+        );
+
+        // the mesh is baked into the space of the root node
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.vertices[2], Vec3::new(1.0, 3.0, 0.0));
+        assert_eq!(mesh.normals[0], Vec3::Z);
+        assert_eq!(mesh.texture_coords[0][2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.bones.len(), 2);
+        let knee = &mesh.bones[1];
+        assert_eq!(knee.name, "Knee");
+        assert_eq!(knee.weights.len(), 3);
+        let bind = knee.offset_matrix.inverse();
+        assert!((bind.row(3).truncate() - Vec3::new(0.0, 2.5, 0.0)).length() < 1e-5);
+
+        let material = &scene.materials[mesh.material_index as usize];
+        assert_eq!(material.name(), Some("Red"));
+        assert_eq!(
+            material.get_color(AI_MATKEY_COLOR_DIFFUSE),
+            Some(Vec4::new(1.0, 0.0, 0.0, 1.0))
+        );
+        assert_eq!(
+            material.get_property(AI_MATKEY_SHADING_MODEL, 0),
+            Some(&AiShadingMode::Phong)
+        );
+        let texture = material.get_texture(AiTextureType::Diffuse, 0).unwrap();
+        assert_eq!(texture.path, "*0");
+        assert_eq!(
+            scene.textures[0].compressed_data().unwrap(),
+            b"\x89PNG\r\n\x1a\n"
+        );
+
+        let animation = &scene.animations[0];
+        assert_eq!(animation.name, "Bend");
+        assert_eq!(animation.duration, 50.0);
+        assert_eq!(animation.channels.len(), 1);
+        let channel = &animation.channels[0];
+        assert_eq!(&*channel.node_name, "Knee");
+        assert_eq!(channel.rotation_keys.len(), 2);
+        let key = &channel.rotation_keys[1];
+        assert_eq!(key.time, 50.0);
+        let x = key.value * Vec3::X;
+        assert!((x - Vec3::Y).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_export_errors() {
+        let mut scene = skinned_scene();
+        let properties = ExportProperties::default();
+        let error = Exporter::export_to_string(&scene, &properties).unwrap_err();
+        assert!(matches!(error, M3dExportError::BinaryFormat));
+
+        scene.meshes[0].faces = vec![AiFace {
+            indices: [0, 1, 2, 3].into(),
+        }];
+        let error = Exporter::export_to_writer(&scene, &properties, &mut Vec::new()).unwrap_err();
+        assert!(matches!(
+            error,
+            M3dExportError::NotTriangulated { count: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn test_registry() {
+        let desc = importer_for_extension(".M3D").unwrap();
+        assert_eq!(desc.name, "Model 3D Importer");
+        let source = read("suzanne.m3d");
+        assert_eq!(importer_for_data(&source).unwrap().name, desc.name);
+        assert_eq!(exporter_for_extension("m3d").unwrap().id, "m3d");
+    }
+}
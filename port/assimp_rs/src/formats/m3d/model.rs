@@ -0,0 +1,1079 @@
+//! The chunks of binary Model 3D files as they are stored, see [`Model`].
+//! The importer converts a parsed model to a scene, the exporter builds one
+//! from a scene and writes it.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use zlib_rs::MAX_WBITS;
+
+use super::errors::M3dImportError;
+use crate::{
+    AiReal,
+    structs::{
+        color::Color4D,
+        material::{
+            AI_MATKEY_BUMPSCALING, AI_MATKEY_COLOR_AMBIENT, AI_MATKEY_COLOR_DIFFUSE,
+            AI_MATKEY_COLOR_EMISSIVE, AI_MATKEY_COLOR_REFLECTIVE, AI_MATKEY_COLOR_SPECULAR,
+            AI_MATKEY_METALLIC_FACTOR, AI_MATKEY_OPACITY, AI_MATKEY_REFRACTI,
+            AI_MATKEY_ROUGHNESS_FACTOR, AI_MATKEY_SHININESS, AiTextureType,
+        },
+    },
+    utils::{
+        compression::{Compression, DEFAULT_COMPRESSION_LEVEL, compress, error::CompressionError},
+        float_precision::{Vec4, to_f32},
+    },
+};
+
+/// Magic word of binary files
+pub const MAGIC: [u8; 4] = *b"3DMO";
+
+/// Magic word of ASCII files, which start with `3dmodel`
+pub const ASCII_MAGIC: [u8; 4] = *b"3dmo";
+
+const HEADER_MAGIC: [u8; 4] = *b"HEAD";
+const END_MAGIC: [u8; 4] = *b"OMD3";
+
+/// Size of the fixed part of the header: magic, length, scale and types
+const HEADER_SIZE: usize = 16;
+
+/// Index of nothing, e.g. the parent of root bones
+pub const UNDEF: u32 = u32::MAX;
+
+pub const PROP_KD: u8 = 0;
+pub const PROP_KA: u8 = 1;
+pub const PROP_KS: u8 = 2;
+pub const PROP_NS: u8 = 3;
+pub const PROP_KE: u8 = 4;
+pub const PROP_TF: u8 = 5;
+pub const PROP_KM: u8 = 6;
+pub const PROP_D: u8 = 7;
+/// Illumination model
+pub const PROP_IL: u8 = 8;
+pub const PROP_PR: u8 = 64;
+pub const PROP_PM: u8 = 65;
+pub const PROP_PS: u8 = 66;
+pub const PROP_NI: u8 = 67;
+pub const PROP_NT: u8 = 68;
+/// Properties from this one on are textures, `map_Kd` is the texture of
+/// [`PROP_KD`] and so on
+pub const PROP_MAP: u8 = 128;
+
+/// Scalar properties and the material keys they map to, like the
+/// `M3DMaterials.h` table of assimp. Roughness and metalness are PBR
+/// factors here.
+pub const SCALAR_PROPERTIES: [(u8, &str); 11] = [
+    (PROP_KD, AI_MATKEY_COLOR_DIFFUSE),
+    (PROP_KA, AI_MATKEY_COLOR_AMBIENT),
+    (PROP_KS, AI_MATKEY_COLOR_SPECULAR),
+    (PROP_NS, AI_MATKEY_SHININESS),
+    (PROP_KE, AI_MATKEY_COLOR_EMISSIVE),
+    (PROP_TF, AI_MATKEY_COLOR_REFLECTIVE),
+    (PROP_KM, AI_MATKEY_BUMPSCALING),
+    (PROP_D, AI_MATKEY_OPACITY),
+    (PROP_PR, AI_MATKEY_ROUGHNESS_FACTOR),
+    (PROP_PM, AI_MATKEY_METALLIC_FACTOR),
+    (PROP_NI, AI_MATKEY_REFRACTI),
+];
+
+/// Texture properties and the texture types they map to
+pub const MAP_PROPERTIES: [(u8, AiTextureType); 11] = [
+    (PROP_MAP + PROP_KD, AiTextureType::Diffuse),
+    (PROP_MAP + PROP_KA, AiTextureType::AmbientOcclusion),
+    (PROP_MAP + PROP_KS, AiTextureType::Specular),
+    (PROP_MAP + PROP_NS, AiTextureType::Shininess),
+    (PROP_MAP + PROP_KE, AiTextureType::Emissive),
+    (PROP_MAP + PROP_KM, AiTextureType::Height),
+    (PROP_MAP + PROP_D, AiTextureType::Opacity),
+    (PROP_MAP + PROP_IL, AiTextureType::Normals),
+    (PROP_MAP + PROP_PR, AiTextureType::DiffuseRoughness),
+    (PROP_MAP + PROP_PM, AiTextureType::Metalness),
+    (PROP_MAP + PROP_NI, AiTextureType::Reflection),
+];
+
+/// How the value of a scalar property is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PropertyFormat {
+    Color,
+    Uint8,
+    Float,
+}
+
+impl PropertyFormat {
+    pub(super) fn of(kind: u8) -> Option<Self> {
+        match kind {
+            PROP_KD | PROP_KA | PROP_KS | PROP_KE | PROP_TF => Some(Self::Color),
+            PROP_NS | PROP_KM | PROP_D | PROP_PR | PROP_PM | PROP_PS | PROP_NI | PROP_NT => {
+                Some(Self::Float)
+            }
+            PROP_IL => Some(Self::Uint8),
+            _ => None,
+        }
+    }
+}
+
+/// A vertex of the vertex list. Besides positions it holds normals, the
+/// positions of bones and their orientations as quaternions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vertex {
+    pub position: Vec4,
+    /// RGBA, red in the lowest byte
+    pub color: u32,
+    /// Index into [`Model::skins`]
+    pub skin: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bone {
+    pub parent: u32,
+    pub name: String,
+    /// Vertex of the translation relative to the parent
+    pub position: u32,
+    /// Vertex of the rotation relative to the parent
+    pub orientation: u32,
+}
+
+/// The bones influencing a vertex with their weights, which sum up to 1
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Skin {
+    pub influences: Vec<(u32, f32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Color(u32),
+    Integer(u32),
+    Float(f32),
+    /// Name of a texture, the file has the extension `.png`
+    Map(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub properties: Vec<(u8, PropertyValue)>,
+}
+
+/// A triangle, missing texture coordinates and normals are [`UNDEF`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Face {
+    /// Index into [`Model::materials`]
+    pub material: u32,
+    pub vertices: [u32; 3],
+    /// Indices into [`Model::texture_coords`]
+    pub texture_coords: [u32; 3],
+    /// Indices into [`Model::vertices`]
+    pub normals: [u32; 3],
+}
+
+/// The pose of a bone from a frame on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub bone: u32,
+    pub position: u32,
+    pub orientation: u32,
+}
+
+/// A key frame, bones which are not transformed keep their pose of the
+/// previous frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    /// Start of the frame in milliseconds
+    pub time: u32,
+    pub transforms: Vec<Transform>,
+}
+
+/// A skeletal animation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Action {
+    pub name: String,
+    /// Length in milliseconds
+    pub duration: u32,
+    pub frames: Vec<Frame>,
+}
+
+/// A file inlined into the model, e.g. a PNG texture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asset {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// The contents of a binary file. Shapes, labels, procedural surfaces and
+/// previews are skipped when reading.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Model {
+    pub name: String,
+    pub license: String,
+    pub author: String,
+    pub description: String,
+    /// Size of a unit in meters
+    pub scale: f32,
+    /// Whether vertices and materials have colors
+    pub has_colors: bool,
+    /// Bytes per coordinate the model was read with, 1 and 2 byte
+    /// coordinates are normalized integers. Models are written with the
+    /// same size, 0 writes 32 bit floats.
+    pub coordinate_size: usize,
+    pub texture_coords: Vec<[AiReal; 2]>,
+    pub vertices: Vec<Vertex>,
+    pub bones: Vec<Bone>,
+    pub skins: Vec<Skin>,
+    pub materials: Vec<Material>,
+    pub faces: Vec<Face>,
+    pub actions: Vec<Action>,
+    pub assets: Vec<Asset>,
+}
+
+/// Converts a packed RGBA color
+pub fn to_color(color: u32) -> Color4D {
+    let [r, g, b, a] = color.to_le_bytes().map(|c| c as f32 / 255.0);
+    Color4D::new(r, g, b, a)
+}
+
+/// Packs a color as RGBA, components are clamped to `[0, 1]`
+pub fn from_color(color: Vec4) -> u32 {
+    let channel = |c: AiReal| (to_f32(c).clamp(0.0, 1.0) * 255.0).round() as u8;
+    u32::from_le_bytes([
+        channel(color.x),
+        channel(color.y),
+        channel(color.z),
+        channel(color.w),
+    ])
+}
+
+/// Byte sizes of the numbers and indices, packed into the `types` field of
+/// the header by their binary logarithm. Optional indices have size 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sizes {
+    coordinate: usize,
+    vertex: usize,
+    string: usize,
+    color: usize,
+    texture_coord: usize,
+    bone: usize,
+    bones_per_vertex: usize,
+    skin: usize,
+    frame: usize,
+}
+
+impl Sizes {
+    fn from_types(types: u32) -> Result<Self, M3dImportError> {
+        let size = |shift: u32| 1usize << ((types >> shift) & 3);
+        let optional = |shift: u32| match size(shift) {
+            8 => 0,
+            size => size,
+        };
+        let sizes = Self {
+            coordinate: size(0),
+            vertex: size(2),
+            string: size(4),
+            color: optional(6),
+            texture_coord: optional(8),
+            bone: optional(10),
+            bones_per_vertex: size(12),
+            skin: optional(14),
+            frame: optional(16),
+        };
+        if sizes.vertex > 4 || sizes.string > 4 {
+            return Err(M3dImportError::InvalidIndexSize);
+        }
+        Ok(sizes)
+    }
+
+    fn to_types(self) -> u32 {
+        let bits = |size: usize| match size {
+            1 => 0,
+            2 => 1,
+            4 => 2,
+            _ => 3,
+        };
+        bits(self.coordinate)
+            | bits(self.vertex) << 2
+            | bits(self.string) << 4
+            | bits(self.color) << 6
+            | bits(self.texture_coord) << 8
+            | bits(self.bone) << 10
+            | bits(self.bones_per_vertex) << 12
+            | bits(self.skin) << 14
+            | bits(self.frame) << 16
+            // neither shapes nor faces of shapes are written
+            | 3 << 18
+            | 3 << 20
+    }
+}
+
+/// Smallest index size for `count` values. The largest values of a size
+/// stand for negative indices.
+fn index_size(count: usize) -> usize {
+    match count {
+        0..254 => 1,
+        254..65534 => 2,
+        _ => 4,
+    }
+}
+
+/// Reads the numbers of a chunk, `chunk` names it in errors
+struct Reader<'a> {
+    data: &'a [u8],
+    chunk: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], chunk: &'static str) -> Self {
+        Self { data, chunk }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], M3dImportError> {
+        if count > self.data.len() {
+            return Err(M3dImportError::UnexpectedEnd(self.chunk));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], M3dImportError> {
+        Ok(self.bytes(N)?.try_into().expect("N bytes were taken"))
+    }
+
+    fn u8(&mut self) -> Result<u8, M3dImportError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, M3dImportError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, M3dImportError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    /// An index of `size` bytes, the two largest values of 1 and 2 byte
+    /// indices are negative. Absent indices of size 0 are [`UNDEF`].
+    fn index(&mut self, size: usize) -> Result<u32, M3dImportError> {
+        Ok(match size {
+            0 => UNDEF,
+            1 => match self.u8()? {
+                index @ 254.. => index as i8 as u32,
+                index => index as u32,
+            },
+            2 => match self.u16()? {
+                index @ 65534.. => index as i16 as u32,
+                index => index as u32,
+            },
+            _ => self.u32()?,
+        })
+    }
+
+    /// A count stored like an index, 0 if it is absent
+    fn count(&mut self, size: usize) -> Result<usize, M3dImportError> {
+        match size {
+            0 => Ok(0),
+            size => self.index(size).map(|count| count as usize),
+        }
+    }
+
+    /// A coordinate, integers are normalized to `[-1, 1]`
+    fn coordinate(&mut self, size: usize) -> Result<AiReal, M3dImportError> {
+        Ok(match size {
+            1 => self.u8()? as i8 as AiReal / 127.0,
+            2 => self.u16()? as i16 as AiReal / 32767.0,
+            4 => f32::from_le_bytes(self.array()?) as AiReal,
+            _ => f64::from_le_bytes(self.array()?) as AiReal,
+        })
+    }
+
+    /// A texture coordinate. 16 bit values are divided by 65535 like the
+    /// reference loader does.
+    fn texture_coord(&mut self, size: usize) -> Result<AiReal, M3dImportError> {
+        Ok(match size {
+            1 => self.u8()? as AiReal / 255.0,
+            2 => self.u16()? as i16 as AiReal / 65535.0,
+            size => self.coordinate(size)?,
+        })
+    }
+
+    /// A color, indices into the color map or RGBA
+    fn color(&mut self, size: usize, color_map: &[u32]) -> Result<u32, M3dImportError> {
+        let index = match size {
+            0 => return Ok(0),
+            1 => self.u8()? as usize,
+            2 => self.u16()? as usize,
+            _ => return self.u32(),
+        };
+        Ok(color_map.get(index).copied().unwrap_or(0))
+    }
+}
+
+/// The zero terminated strings after the fixed part of the header, which
+/// other chunks reference by their offset
+struct Strings<'a> {
+    data: &'a [u8],
+    size: usize,
+}
+
+impl Strings<'_> {
+    /// The string at `offset`, offset 0 is no string
+    fn get(&self, offset: u32) -> Result<Option<String>, M3dImportError> {
+        if offset == 0 {
+            return Ok(None);
+        }
+        let bytes = self
+            .data
+            .get(offset as usize..)
+            .filter(|bytes| !bytes.is_empty())
+            .ok_or(M3dImportError::InvalidString(offset))?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(Some(String::from_utf8_lossy(&bytes[..end]).into_owned()))
+    }
+
+    fn read(&self, reader: &mut Reader) -> Result<Option<String>, M3dImportError> {
+        let offset = reader.index(self.size)?;
+        self.get(offset)
+    }
+}
+
+impl Model {
+    /// Parses a binary file, compressed or not
+    pub fn parse(data: &[u8]) -> Result<Self, M3dImportError> {
+        if data.starts_with(&ASCII_MAGIC) {
+            return Err(M3dImportError::UnsupportedAsciiFormat);
+        }
+        if !data.starts_with(&MAGIC) {
+            return Err(M3dImportError::InvalidFormat);
+        }
+        let mut reader = Reader::new(&data[4..], "file header");
+        let length = reader.u32()? as usize;
+        if length < 8 || length > data.len() {
+            return Err(M3dImportError::LengthMismatch {
+                expected: length,
+                actual: data.len(),
+            });
+        }
+        let body = &data[8..length];
+        let body = if body.starts_with(&HEADER_MAGIC) {
+            Cow::Borrowed(body)
+        } else {
+            let mut inflated = Vec::new();
+            Compression::builder()
+                .open()?
+                .decompress(body, &mut inflated)?;
+            Cow::Owned(inflated)
+        };
+        Self::parse_chunks(&body)
+    }
+
+    fn parse_chunks(body: &[u8]) -> Result<Self, M3dImportError> {
+        let mut reader = Reader::new(body, "HEAD");
+        if reader.array()? != HEADER_MAGIC {
+            return Err(M3dImportError::InvalidFormat);
+        }
+        let header_length = reader.u32()? as usize;
+        let scale = f32::from_le_bytes(reader.array()?);
+        let sizes = Sizes::from_types(reader.u32()?)?;
+        if header_length < HEADER_SIZE || header_length > body.len() {
+            return Err(M3dImportError::UnexpectedEnd("HEAD"));
+        }
+        let strings = Strings {
+            data: &body[HEADER_SIZE..header_length],
+            size: sizes.string,
+        };
+        // name, license, author and description follow each other
+        let mut texts = strings
+            .data
+            .split(|&b| b == 0)
+            .map(|text| String::from_utf8_lossy(text).into_owned());
+        let mut model = Model {
+            name: texts.next().unwrap_or_default(),
+            license: texts.next().unwrap_or_default(),
+            author: texts.next().unwrap_or_default(),
+            description: texts.next().unwrap_or_default(),
+            scale: if scale > 0.0 { scale } else { 1.0 },
+            has_colors: sizes.color != 0,
+            coordinate_size: sizes.coordinate,
+            ..Default::default()
+        };
+
+        let mut color_map = Vec::new();
+        let mut position = header_length;
+        while let Some(magic) = body.get(position..position + 4) {
+            if magic == END_MAGIC {
+                break;
+            }
+            let Some(length) = body
+                .get(position + 4..position + 8)
+                .map(|length| u32::from_le_bytes(length.try_into().unwrap()) as usize)
+                .filter(|&length| length >= 8 && length <= body.len() - position)
+            else {
+                log::warn!("Invalid chunk size, the rest of the file is skipped");
+                break;
+            };
+            let data = &body[position + 8..position + length];
+            position += length;
+            match magic {
+                b"CMAP" => {
+                    color_map = data
+                        .chunks_exact(4)
+                        .map(|color| u32::from_le_bytes(color.try_into().unwrap()))
+                        .collect();
+                }
+                b"TMAP" => model.parse_texture_map(data, sizes)?,
+                b"VRTS" => model.parse_vertices(data, sizes, &color_map)?,
+                b"BONE" => model.parse_bones(data, sizes, &strings)?,
+                b"MTRL" => model.parse_material(data, sizes, &strings, &color_map)?,
+                b"MESH" => {
+                    if !model.parse_mesh(data, sizes, &strings)? {
+                        break;
+                    }
+                }
+                b"ACTN" => model.parse_action(data, sizes, &strings)?,
+                b"ASET" => {
+                    let mut reader = Reader::new(data, "ASET");
+                    let name = strings.read(&mut reader)?.unwrap_or_default();
+                    model.assets.push(Asset {
+                        name,
+                        data: reader.data.to_vec(),
+                    });
+                }
+                b"PRVW" => {}
+                other => log::warn!(
+                    "Skipping the unsupported {:?} chunk",
+                    String::from_utf8_lossy(other)
+                ),
+            }
+        }
+        Ok(model)
+    }
+
+    fn parse_texture_map(&mut self, data: &[u8], sizes: Sizes) -> Result<(), M3dImportError> {
+        if sizes.texture_coord == 0 {
+            log::warn!("Texture map without texture coordinate indices");
+            return Ok(());
+        }
+        let mut reader = Reader::new(data, "TMAP");
+        while reader.data.len() >= 2 * sizes.coordinate {
+            let u = reader.texture_coord(sizes.coordinate)?;
+            let v = reader.texture_coord(sizes.coordinate)?;
+            self.texture_coords.push([u, v]);
+        }
+        Ok(())
+    }
+
+    fn parse_vertices(
+        &mut self,
+        data: &[u8],
+        sizes: Sizes,
+        color_map: &[u32],
+    ) -> Result<(), M3dImportError> {
+        let mut reader = Reader::new(data, "VRTS");
+        let record = 4 * sizes.coordinate + sizes.color + sizes.skin;
+        while reader.data.len() >= record {
+            let mut position = [0.0; 4];
+            for c in position.iter_mut() {
+                *c = reader.coordinate(sizes.coordinate)?;
+            }
+            self.vertices.push(Vertex {
+                position: Vec4::from_array(position),
+                color: reader.color(sizes.color, color_map)?,
+                skin: reader.index(sizes.skin)?,
+            });
+        }
+        Ok(())
+    }
+
+    fn parse_bones(
+        &mut self,
+        data: &[u8],
+        sizes: Sizes,
+        strings: &Strings,
+    ) -> Result<(), M3dImportError> {
+        if sizes.bone == 0 {
+            log::warn!("Skeleton without bone indices");
+            return Ok(());
+        }
+        let mut reader = Reader::new(data, "BONE");
+        let num_bones = reader.count(sizes.bone)?;
+        let num_skins = reader.count(sizes.skin)?;
+        for _ in 0..num_bones {
+            self.bones.push(Bone {
+                parent: reader.index(sizes.bone)?,
+                name: strings.read(&mut reader)?.unwrap_or_default(),
+                position: reader.index(sizes.vertex)?,
+                orientation: reader.index(sizes.vertex)?,
+            });
+        }
+        while self.skins.len() < num_skins && !reader.is_empty() {
+            let weights = match sizes.bones_per_vertex {
+                1 => vec![u8::MAX],
+                count => reader.bytes(count)?.to_vec(),
+            };
+            let mut skin = Skin::default();
+            for weight in weights.into_iter().filter(|&weight| weight != 0) {
+                let bone = reader.index(sizes.bone)?;
+                skin.influences.push((bone, weight as f32 / 255.0));
+            }
+            let sum = skin
+                .influences
+                .iter()
+                .map(|(_, weight)| weight)
+                .sum::<f32>();
+            for (_, weight) in skin.influences.iter_mut() {
+                *weight /= sum;
+            }
+            self.skins.push(skin);
+        }
+        Ok(())
+    }
+
+    fn parse_material(
+        &mut self,
+        data: &[u8],
+        sizes: Sizes,
+        strings: &Strings,
+        color_map: &[u32],
+    ) -> Result<(), M3dImportError> {
+        let mut reader = Reader::new(data, "MTRL");
+        let name = strings.read(&mut reader)?.unwrap_or_default();
+        if self.materials.iter().any(|material| material.name == name) {
+            log::warn!("Skipping the second definition of material {name:?}");
+            return Ok(());
+        }
+        let mut properties = Vec::new();
+        while !reader.is_empty() {
+            let kind = reader.u8()?;
+            let value = match PropertyFormat::of(kind) {
+                _ if kind >= PROP_MAP => match strings.read(&mut reader)? {
+                    Some(texture) => PropertyValue::Map(texture),
+                    None => continue,
+                },
+                Some(PropertyFormat::Color) => {
+                    PropertyValue::Color(reader.color(sizes.color, color_map)?)
+                }
+                Some(PropertyFormat::Uint8) => PropertyValue::Integer(reader.u8()? as u32),
+                Some(PropertyFormat::Float) => {
+                    PropertyValue::Float(f32::from_le_bytes(reader.array()?))
+                }
+                None => {
+                    log::warn!("Unknown property {kind} of material {name:?}");
+                    break;
+                }
+            };
+            properties.push((kind, value));
+        }
+        self.materials.push(Material { name, properties });
+        Ok(())
+    }
+
+    /// Reads the faces of a mesh chunk, returns `false` if the rest of the
+    /// file can't be read because of an unsupported polygon
+    fn parse_mesh(
+        &mut self,
+        data: &[u8],
+        sizes: Sizes,
+        strings: &Strings,
+    ) -> Result<bool, M3dImportError> {
+        let mut reader = Reader::new(data, "MESH");
+        let mut material = UNDEF;
+        while !reader.is_empty() {
+            let record = reader.u8()?;
+            match record >> 4 {
+                0 => {
+                    material = UNDEF;
+                    if let Some(name) = strings.read(&mut reader)? {
+                        match self.materials.iter().position(|m| m.name == name) {
+                            Some(index) => material = index as u32,
+                            None => log::warn!("Material {name:?} is not defined"),
+                        }
+                    }
+                }
+                3 => {
+                    let mut face = Face {
+                        material,
+                        vertices: [UNDEF; 3],
+                        texture_coords: [UNDEF; 3],
+                        normals: [UNDEF; 3],
+                    };
+                    for corner in 0..3 {
+                        face.vertices[corner] = reader.index(sizes.vertex)?;
+                        if record & 1 != 0 {
+                            face.texture_coords[corner] = reader.index(sizes.texture_coord)?;
+                        }
+                        if record & 2 != 0 {
+                            face.normals[corner] = reader.index(sizes.vertex)?;
+                        }
+                    }
+                    self.faces.push(face);
+                }
+                count => {
+                    log::warn!(
+                        "Only triangles are supported, found a polygon with {count} vertices. The rest of the file is skipped"
+                    );
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn parse_action(
+        &mut self,
+        data: &[u8],
+        sizes: Sizes,
+        strings: &Strings,
+    ) -> Result<(), M3dImportError> {
+        let mut reader = Reader::new(data, "ACTN");
+        let name = strings.read(&mut reader)?.unwrap_or_default();
+        let num_frames = reader.u16()? as usize;
+        if num_frames == 0 {
+            return Ok(());
+        }
+        let mut action = Action {
+            name,
+            duration: reader.u32()?,
+            frames: Vec::new(),
+        };
+        while action.frames.len() < num_frames && !reader.is_empty() {
+            let mut frame = Frame {
+                time: reader.u32()?,
+                transforms: Vec::new(),
+            };
+            for _ in 0..reader.count(sizes.frame)? {
+                frame.transforms.push(Transform {
+                    bone: reader.index(sizes.bone)?,
+                    position: reader.index(sizes.vertex)?,
+                    orientation: reader.index(sizes.vertex)?,
+                });
+            }
+            action.frames.push(frame);
+        }
+        self.actions.push(action);
+        Ok(())
+    }
+
+    /// Writes the model as binary file, the chunks are compressed if
+    /// `compress_chunks` is set and it makes the file smaller. Coordinates
+    /// are written with [`Self::coordinate_size`].
+    pub fn to_bytes(&self, compress_chunks: bool) -> Result<Vec<u8>, CompressionError> {
+        let mut writer = Writer::new(self);
+        let body = writer.write(self);
+        let mut file = Vec::with_capacity(body.len() + 8);
+        file.extend_from_slice(&MAGIC);
+        file.extend_from_slice(&0u32.to_le_bytes());
+        let mut compressed = Vec::new();
+        if compress_chunks
+            && compress(&body, DEFAULT_COMPRESSION_LEVEL, MAX_WBITS, &mut compressed)? < body.len()
+        {
+            file.extend_from_slice(&compressed);
+        } else {
+            file.extend_from_slice(&body);
+        }
+        let length = file.len() as u32;
+        file[4..8].copy_from_slice(&length.to_le_bytes());
+        Ok(file)
+    }
+}
+
+/// Writes the chunks of a model with the smallest index sizes
+struct Writer {
+    sizes: Sizes,
+    /// Offsets of the strings in the string table
+    offsets: HashMap<String, u32>,
+    /// The name, license, author, description and string table
+    strings: Vec<u8>,
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn new(model: &Model) -> Self {
+        let mut strings = Vec::new();
+        for text in [
+            &model.name,
+            &model.license,
+            &model.author,
+            &model.description,
+        ] {
+            strings.extend_from_slice(text.as_bytes());
+            strings.push(0);
+        }
+        let mut writer = Self {
+            sizes: Sizes {
+                coordinate: match model.coordinate_size {
+                    size @ (1 | 2 | 8) => size,
+                    _ => 4,
+                },
+                vertex: index_size(model.vertices.len()),
+                string: 1,
+                color: if model.has_colors { 4 } else { 0 },
+                texture_coord: match model.texture_coords.len() {
+                    0 => 0,
+                    count => index_size(count),
+                },
+                bone: match model.bones.len() {
+                    0 => 0,
+                    count => index_size(count),
+                },
+                bones_per_vertex: match model.skins.iter().map(|s| s.influences.len()).max() {
+                    None | Some(0..=1) => 1,
+                    Some(2) => 2,
+                    Some(3..=4) => 4,
+                    Some(_) => 8,
+                },
+                skin: match model.skins.len() {
+                    0 => 0,
+                    count => index_size(count),
+                },
+                frame: model
+                    .actions
+                    .iter()
+                    .flat_map(|action| &action.frames)
+                    .map(|frame| frame.transforms.len())
+                    .max()
+                    .map_or(0, index_size),
+            },
+            offsets: HashMap::new(),
+            strings,
+            out: Vec::new(),
+        };
+        let names = model
+            .bones
+            .iter()
+            .map(|bone| &bone.name)
+            .chain(model.materials.iter().flat_map(|material| {
+                let maps = material
+                    .properties
+                    .iter()
+                    .filter_map(|(_, value)| match value {
+                        PropertyValue::Map(texture) => Some(texture),
+                        _ => None,
+                    });
+                [&material.name].into_iter().chain(maps)
+            }))
+            .chain(model.actions.iter().map(|action| &action.name))
+            .chain(model.assets.iter().map(|asset| &asset.name));
+        for name in names {
+            writer.add_string(name);
+        }
+        writer.sizes.string = index_size(writer.strings.len());
+        writer
+    }
+
+    fn add_string(&mut self, text: &str) {
+        if text.is_empty() || self.offsets.contains_key(text) {
+            return;
+        }
+        self.offsets
+            .insert(text.to_owned(), self.strings.len() as u32);
+        self.strings.extend_from_slice(text.as_bytes());
+        self.strings.push(0);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn index(&mut self, size: usize, index: u32) {
+        match size {
+            0 => {}
+            1 => self.out.push(index as u8),
+            2 => self.out.extend_from_slice(&(index as u16).to_le_bytes()),
+            _ => self.u32(index),
+        }
+    }
+
+    /// Writes a coordinate like [`Reader::coordinate`] reads it, so read
+    /// values are written unchanged
+    // the cast is a no-op if double_precision is enabled
+    #[allow(clippy::unnecessary_cast)]
+    fn coordinate(&mut self, value: AiReal) {
+        match self.sizes.coordinate {
+            1 => self.out.push(((value * 127.0).round() as i8) as u8),
+            2 => self
+                .out
+                .extend_from_slice(&((value * 32767.0).round() as i16).to_le_bytes()),
+            8 => self.out.extend_from_slice(&(value as f64).to_le_bytes()),
+            _ => self.out.extend_from_slice(&to_f32(value).to_le_bytes()),
+        }
+    }
+
+    fn texture_coord(&mut self, value: AiReal) {
+        match self.sizes.coordinate {
+            1 => self.out.push((value * 255.0).round() as u8),
+            2 => self
+                .out
+                .extend_from_slice(&((value * 65535.0).round() as i16).to_le_bytes()),
+            _ => self.coordinate(value),
+        }
+    }
+
+    fn string(&mut self, text: &str) {
+        let offset = self.offsets.get(text).copied().unwrap_or(0);
+        self.index(self.sizes.string, offset);
+    }
+
+    /// Starts a chunk, returns its start for [`Self::end_chunk`]
+    fn begin_chunk(&mut self, magic: &[u8; 4]) -> usize {
+        let start = self.out.len();
+        self.out.extend_from_slice(magic);
+        self.u32(0);
+        start
+    }
+
+    fn end_chunk(&mut self, start: usize) {
+        let length = (self.out.len() - start) as u32;
+        self.out[start + 4..start + 8].copy_from_slice(&length.to_le_bytes());
+    }
+
+    fn write(&mut self, model: &Model) -> Vec<u8> {
+        let sizes = self.sizes;
+        self.out.extend_from_slice(&HEADER_MAGIC);
+        self.u32((HEADER_SIZE + self.strings.len()) as u32);
+        self.out.extend_from_slice(&model.scale.to_le_bytes());
+        self.u32(sizes.to_types());
+        let strings = std::mem::take(&mut self.strings);
+        self.out.extend_from_slice(&strings);
+
+        if !model.texture_coords.is_empty() {
+            let start = self.begin_chunk(b"TMAP");
+            for &[u, v] in &model.texture_coords {
+                self.texture_coord(u);
+                self.texture_coord(v);
+            }
+            self.end_chunk(start);
+        }
+        if !model.vertices.is_empty() {
+            let start = self.begin_chunk(b"VRTS");
+            for vertex in &model.vertices {
+                for c in vertex.position.to_array() {
+                    self.coordinate(c);
+                }
+                if sizes.color != 0 {
+                    self.u32(vertex.color);
+                }
+                self.index(sizes.skin, vertex.skin);
+            }
+            self.end_chunk(start);
+        }
+        if !model.bones.is_empty() {
+            let start = self.begin_chunk(b"BONE");
+            self.index(sizes.bone, model.bones.len() as u32);
+            self.index(sizes.skin, model.skins.len() as u32);
+            for bone in &model.bones {
+                self.index(sizes.bone, bone.parent);
+                self.string(&bone.name);
+                self.index(sizes.vertex, bone.position);
+                self.index(sizes.vertex, bone.orientation);
+            }
+            for skin in &model.skins {
+                let weights = skin
+                    .influences
+                    .iter()
+                    .map(|&(bone, weight)| (bone, (weight.clamp(0.0, 1.0) * 255.0).round() as u8))
+                    .filter(|&(_, weight)| weight != 0)
+                    .take(sizes.bones_per_vertex)
+                    .collect::<Vec<_>>();
+                if sizes.bones_per_vertex > 1 {
+                    for slot in 0..sizes.bones_per_vertex {
+                        self.out
+                            .push(weights.get(slot).map_or(0, |&(_, weight)| weight));
+                    }
+                }
+                for (bone, _) in weights {
+                    self.index(sizes.bone, bone);
+                }
+            }
+            self.end_chunk(start);
+        }
+        for material in &model.materials {
+            let start = self.begin_chunk(b"MTRL");
+            self.string(&material.name);
+            for (kind, value) in &material.properties {
+                let format = PropertyFormat::of(*kind);
+                match value {
+                    PropertyValue::Color(color) if format == Some(PropertyFormat::Color) => {
+                        if sizes.color == 0 {
+                            continue;
+                        }
+                        self.out.push(*kind);
+                        self.u32(*color);
+                    }
+                    PropertyValue::Integer(number) if format == Some(PropertyFormat::Uint8) => {
+                        self.out.push(*kind);
+                        self.out.push(*number as u8);
+                    }
+                    PropertyValue::Float(number) if format == Some(PropertyFormat::Float) => {
+                        self.out.push(*kind);
+                        self.out.extend_from_slice(&number.to_le_bytes());
+                    }
+                    PropertyValue::Map(texture) if *kind >= PROP_MAP => {
+                        self.out.push(*kind);
+                        self.string(texture);
+                    }
+                    _ => log::warn!(
+                        "Property {kind} of material {:?} has the wrong type",
+                        material.name
+                    ),
+                }
+            }
+            self.end_chunk(start);
+        }
+        if !model.faces.is_empty() {
+            let start = self.begin_chunk(b"MESH");
+            let mut material = UNDEF;
+            for face in &model.faces {
+                if face.material != material {
+                    material = face.material;
+                    self.out.push(0);
+                    let name = model
+                        .materials
+                        .get(material as usize)
+                        .map_or("", |m| m.name.as_str());
+                    self.string(name);
+                }
+                let has_texture_coords = !face.texture_coords.contains(&UNDEF);
+                let has_normals = !face.normals.contains(&UNDEF);
+                self.out
+                    .push(3 << 4 | has_texture_coords as u8 | (has_normals as u8) << 1);
+                for corner in 0..3 {
+                    self.index(sizes.vertex, face.vertices[corner]);
+                    if has_texture_coords {
+                        self.index(sizes.texture_coord, face.texture_coords[corner]);
+                    }
+                    if has_normals {
+                        self.index(sizes.vertex, face.normals[corner]);
+                    }
+                }
+            }
+            self.end_chunk(start);
+        }
+        for action in model.actions.iter().filter(|a| !a.frames.is_empty()) {
+            let start = self.begin_chunk(b"ACTN");
+            self.string(&action.name);
+            let num_frames = action.frames.len().min(u16::MAX as usize);
+            self.out
+                .extend_from_slice(&(num_frames as u16).to_le_bytes());
+            self.u32(action.duration);
+            for frame in &action.frames[..num_frames] {
+                self.u32(frame.time);
+                self.index(sizes.frame, frame.transforms.len() as u32);
+                for transform in &frame.transforms {
+                    self.index(sizes.bone, transform.bone);
+                    self.index(sizes.vertex, transform.position);
+                    self.index(sizes.vertex, transform.orientation);
+                }
+            }
+            self.end_chunk(start);
+        }
+        for asset in &model.assets {
+            let start = self.begin_chunk(b"ASET");
+            self.string(&asset.name);
+            self.out.extend_from_slice(&asset.data);
+            self.end_chunk(start);
+        }
+        self.out.extend_from_slice(&END_MAGIC);
+        std::mem::take(&mut self.out)
+    }
+}
@@ -1,3 +1,9 @@
+#[cfg(feature = "m3d")]
+pub mod m3d;
+#[cfg(feature = "usd")]
+pub mod usd;
+#[cfg(feature = "vrml")]
+pub mod vrml;
 #[cfg(feature = "x_file")]
 pub mod x;
 
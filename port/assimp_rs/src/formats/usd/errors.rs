@@ -0,0 +1,65 @@
+use thiserror::Error;
+
+use crate::{
+    structs::scene_builder::SceneBuildError,
+    traits::importer::error::{EncodingError, ImportError},
+    utils::compression::error::CompressionError,
+};
+
+/// USD specific import errors
+#[derive(Debug, Error)]
+pub enum UsdImportError {
+    #[error("Neither a usda layer nor a usdz package")]
+    InvalidFormat,
+
+    #[error("Binary usdc layers are not supported, only usda text layers")]
+    UnsupportedCrateFormat,
+
+    #[error("Unsupported version {0:?}, only usda 1.0 is supported")]
+    UnsupportedVersion(String),
+
+    #[error("The usdz package contains no usda layer")]
+    MissingLayer,
+
+    #[error("Prims are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("Line {line}: {error}")]
+    ParseError { line: usize, error: UsdParseError },
+
+    #[error("Import error: {0}")]
+    ImportError(#[from] ImportError),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Build error: {0}")]
+    BuildError(#[from] SceneBuildError),
+}
+
+impl From<EncodingError> for UsdImportError {
+    fn from(error: EncodingError) -> Self {
+        Self::ImportError(ImportError::EncodingError(error))
+    }
+}
+
+impl From<CompressionError> for UsdImportError {
+    fn from(error: CompressionError) -> Self {
+        Self::ImportError(ImportError::CompressionError(error))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum UsdParseError {
+    #[error("Unexpected end of file")]
+    UnexpectedEnd,
+
+    #[error("Unexpected {0:?}")]
+    UnexpectedToken(String),
+
+    #[error("Unterminated {0}")]
+    Unterminated(&'static str),
+
+    #[error("Values are nested deeper than {0} levels")]
+    TooDeep(usize),
+}
@@ -0,0 +1,865 @@
+use core::cell::Cell;
+use std::{borrow::Cow, collections::HashMap};
+
+use super::{
+    errors::UsdImportError,
+    parser,
+    stage::{Specifier, Stage, Value, as_bool},
+};
+use crate::{
+    AiReal,
+    core::profiler::{ProfileScope, Profiler},
+    structs::{
+        aabb::AABB,
+        color::Color4D,
+        coordinate_system::CoordinateSystem,
+        face::AiFace,
+        importer::ImportProperties,
+        importer_desc::{ImporterDesc, ImporterFlags},
+        material::{
+            AI_MATKEY_NAME, AI_MATKEY_OPACITY, AI_MATKEY_TWOSIDED, AddProperty, AiMaterial,
+            AiTextureMapMode, AiTextureType, TextureSlot,
+        },
+        mesh::{AiMesh, PrimitiveTypeFlags},
+        meta::{AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, MetadataExt},
+        nodes::Index,
+        pbr::PbrMaterial,
+        scene::{AiNode, AiScene},
+        scene_builder::SceneBuilder,
+        texture::AiTexture,
+    },
+    traits::importer::{
+        error::EncodingError,
+        trait_define::{FormatHeader, InternalImporter},
+    },
+    utils::{
+        alloc_budget::AllocBudget,
+        compression::{
+            error::CompressionError,
+            unpack_budget,
+            zip::{self, ZipArchive},
+        },
+        float_precision::{Mat4, Quat, Vec3, Vec4, to_f32},
+    },
+};
+
+/// Value of the [`AI_METADATA_SOURCE_FORMAT`] metadata of scenes imported
+/// from USD files
+pub const SOURCE_FORMAT: &str = "USD";
+
+/// Name of the node holding the prims at the top level of a layer
+pub const ROOT_NODE_NAME: &str = "USDRoot";
+
+/// Magic word of binary usdc layers, the crate format
+const CRATE_MAGIC: &[u8] = b"PXR-USDC";
+
+/// Extensions of layers, the first layer of a usdz package is imported
+const LAYER_EXTENSIONS: [&str; 3] = [".usda", ".usdc", ".usd"];
+
+/// Connections are followed at most this many times to find the value or
+/// texture of a shader input
+const MAX_CONNECTIONS: usize = 16;
+
+pub(crate) static DESC: ImporterDesc = ImporterDesc {
+    name: "Universal Scene Description Importer",
+    author: "",
+    maintainer: "",
+    comments: "usda layers and usdz packages with meshes and UsdPreviewSurface materials, no composition",
+    flags: ImporterFlags::SUPPORT_TEXT_FLAVOUR.bits()
+        | ImporterFlags::SUPPORT_COMPRESSED_FLAVOUR.bits()
+        | ImporterFlags::LIMITED_SUPPORT.bits(),
+    min_major: 1,
+    min_minor: 0,
+    max_major: 1,
+    max_minor: 0,
+    file_extensions: "usd usda usdz",
+};
+
+pub struct Importer;
+
+impl Importer {
+    pub fn get_info(&self) -> &ImporterDesc {
+        &DESC
+    }
+
+    /// Parses a usda layer, along with its version
+    fn parse(buf: &[u8]) -> Result<(Stage, String), UsdImportError> {
+        if buf.starts_with(CRATE_MAGIC) {
+            return Err(UsdImportError::UnsupportedCrateFormat);
+        }
+        let buf = match buf {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => rest,
+            _ => buf,
+        };
+        let text = core::str::from_utf8(buf).map_err(|_| EncodingError::NotValidUtf8)?;
+        let header = text
+            .strip_prefix("#usda")
+            .ok_or(UsdImportError::InvalidFormat)?;
+        let version = header.lines().next().unwrap_or_default().trim();
+        if !version.starts_with("1.") {
+            return Err(UsdImportError::UnsupportedVersion(version.to_owned()));
+        }
+        Ok((parser::parse(text)?, version.to_owned()))
+    }
+
+    fn import(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<(), UsdImportError> {
+        let package = if zip::is_zip(buf) {
+            Some(Package::open(buf, properties)?)
+        } else {
+            None
+        };
+        let layer = match &package {
+            Some(package) => Cow::Owned(package.read_layer()?),
+            None => Cow::Borrowed(buf),
+        };
+        let (stage, version) =
+            profiler.measure(ProfileScope::Import, "parsing", || Self::parse(&layer))?;
+        *ai_scene = profiler.measure(ProfileScope::Import, "conversion", || {
+            Converter::new(&stage, package.as_ref()).convert()
+        })?;
+        let metadata = &mut ai_scene.metadata;
+        metadata.set(AI_METADATA_SOURCE_FORMAT, SOURCE_FORMAT);
+        metadata.set(AI_METADATA_SOURCE_FORMAT_VERSION, version);
+        let system = match stage.layer_metadata("upAxis") {
+            Some(Value::String(axis)) if axis == "Z" => CoordinateSystem::Z_UP_RIGHT_HANDED,
+            _ => CoordinateSystem::Y_UP_RIGHT_HANDED,
+        };
+        // centimeters unless the layer says otherwise
+        let meters_per_unit = match stage.layer_metadata("metersPerUnit") {
+            Some(&Value::Number(meters)) if meters > 0.0 => meters as AiReal,
+            _ => 0.01,
+        };
+        system
+            .with_unit_scale_factor(meters_per_unit * CoordinateSystem::METERS)
+            .write_metadata(metadata);
+        Ok(())
+    }
+}
+
+impl FormatHeader<5> for Importer {
+    const HEADER: [u8; 5] = *b"#usda";
+
+    /// Also accepts usdz packages
+    fn check_header(buf: &[u8]) -> bool {
+        let buf = match buf {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => rest,
+            _ => buf,
+        };
+        buf.starts_with(&Self::HEADER) || is_usdz(buf)
+    }
+}
+
+impl InternalImporter<UsdImportError> for Importer {
+    #[cfg(feature = "std")]
+    fn import_from_file(file_name: &str, ai_scene: &mut AiScene) -> Result<(), UsdImportError> {
+        let buf = std::fs::read(file_name)?;
+        Self::import_from_buf(&buf, ai_scene)
+    }
+
+    fn import_from_buf(buf: &[u8], ai_scene: &mut AiScene) -> Result<(), UsdImportError> {
+        Self::import(
+            buf,
+            ai_scene,
+            &ImportProperties::default(),
+            &mut Profiler::disabled(),
+        )
+    }
+
+    fn import_from_buf_with_profiler(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<(), UsdImportError> {
+        Self::import(buf, ai_scene, properties, profiler)
+    }
+}
+
+fn is_layer_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    LAYER_EXTENSIONS.iter().any(|e| name.ends_with(e))
+}
+
+/// Whether the data is a usdz package, a zip archive whose first file is a
+/// layer. Reads the local header of the first file only.
+pub(crate) fn is_usdz(buf: &[u8]) -> bool {
+    let Some(&[low, high]) = buf.get(26..28) else {
+        return false;
+    };
+    let name_len = u16::from_le_bytes([low, high]) as usize;
+    zip::is_zip(buf)
+        && buf
+            .get(30..30 + name_len)
+            .and_then(|name| core::str::from_utf8(name).ok())
+            .is_some_and(is_layer_name)
+}
+
+/// A usdz package, a zip archive with a layer and the files it uses
+struct Package<'a> {
+    archive: ZipArchive<'a>,
+    /// Path of the imported layer inside the archive
+    layer: String,
+    /// Shared by all files read from the archive
+    budget: Cell<AllocBudget>,
+}
+
+impl<'a> Package<'a> {
+    /// Opens the package, the files read from it share the budget of
+    /// [`unpack_budget`]
+    fn open(buf: &'a [u8], properties: &ImportProperties) -> Result<Self, UsdImportError> {
+        let archive = ZipArchive::new(buf)?;
+        let layer = archive
+            .files()
+            .find(|entry| is_layer_name(&entry.name))
+            .ok_or(UsdImportError::MissingLayer)?
+            .name
+            .clone();
+        Ok(Self {
+            archive,
+            layer,
+            budget: Cell::new(unpack_budget(properties)),
+        })
+    }
+
+    /// Decompresses a file of the archive
+    fn read(&self, path: &str) -> Result<Vec<u8>, CompressionError> {
+        let mut budget = self.budget.get();
+        let result = self.archive.read_by_name(path, &mut budget);
+        self.budget.set(budget);
+        result
+    }
+
+    fn read_layer(&self) -> Result<Vec<u8>, UsdImportError> {
+        Ok(self.read(&self.layer)?)
+    }
+
+    /// Resolves an asset path relative to the layer, returns the path of
+    /// the file inside the archive if it is there
+    fn resolve(&self, asset: &str) -> Option<String> {
+        let directory = self.layer.rfind('/').map_or("", |i| &self.layer[..=i]);
+        let path = format!("{directory}{}", asset.replace('\\', "/"));
+        let mut parts = Vec::new();
+        for part in path.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop();
+                }
+                part => parts.push(part),
+            }
+        }
+        let path = parts.join("/");
+        self.archive.find(&path).map(|_| path)
+    }
+}
+
+/// The interpolation of a primvar, how its values map to the mesh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interpolation {
+    /// One value for the whole mesh
+    Constant,
+    /// One value per face
+    Uniform,
+    /// One value per point, `varying` is the same for polygonal meshes
+    Vertex,
+    /// One value per corner of a face
+    FaceVarying,
+}
+
+impl Interpolation {
+    fn parse(name: Option<&str>, default: Self) -> Self {
+        match name {
+            Some("constant") => Self::Constant,
+            Some("uniform") => Self::Uniform,
+            Some("vertex" | "varying") => Self::Vertex,
+            Some("faceVarying") => Self::FaceVarying,
+            _ => default,
+        }
+    }
+}
+
+/// The values of a primvar like `primvars:st`, with its optional index
+/// list `primvars:st:indices`
+struct Primvar<const N: usize> {
+    values: Vec<[AiReal; N]>,
+    indices: Vec<f64>,
+    interpolation: Interpolation,
+}
+
+impl<const N: usize> Primvar<N> {
+    fn read(stage: &Stage, prim: usize, name: &str, default: Interpolation) -> Option<Self> {
+        let property = stage.property(prim, name)?;
+        let values = vectors::<N>(stage, prim, name);
+        if values.is_empty() {
+            return None;
+        }
+        Some(Self {
+            values,
+            indices: stage.numbers(prim, &format!("{name}:indices")),
+            interpolation: Interpolation::parse(property.interpolation.as_deref(), default),
+        })
+    }
+
+    /// The value of the corner `corner` of face `face` at point `point`
+    fn get(&self, face: usize, point: usize, corner: usize) -> Option<[AiReal; N]> {
+        let i = match self.interpolation {
+            Interpolation::Constant => 0,
+            Interpolation::Uniform => face,
+            Interpolation::Vertex => point,
+            Interpolation::FaceVarying => corner,
+        };
+        let i = match self.indices.is_empty() {
+            true => i,
+            false => index(&self.indices, i)?,
+        };
+        self.values.get(i).copied()
+    }
+}
+
+/// The value of a shader input, found by following its connections
+enum Input {
+    Value(Value),
+    /// A `UsdUVTexture` shader
+    Texture(usize),
+}
+
+/// Converts the prims to an [`AiScene`]. Xformable prims become nodes, the
+/// meshes of mesh prims are attached to their nodes.
+struct Converter<'a> {
+    stage: &'a Stage,
+    package: Option<&'a Package<'a>>,
+    builder: SceneBuilder,
+    /// The materials by material prim and whether the mesh is two-sided
+    materials: HashMap<(Option<usize>, bool), u32>,
+    /// The embedded textures by path inside the package
+    textures: HashMap<String, u32>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(stage: &'a Stage, package: Option<&'a Package<'a>>) -> Self {
+        Self {
+            stage,
+            package,
+            builder: SceneBuilder::new(),
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    fn convert(mut self) -> Result<AiScene, UsdImportError> {
+        let root = self.builder.add_node(ROOT_NODE_NAME, Mat4::IDENTITY, None);
+        let stage = self.stage;
+        for &prim in &stage.roots {
+            self.convert_prim(prim, root, None);
+        }
+        Ok(self.builder.build()?)
+    }
+
+    /// Converts a prim and its descendants. `binding` is the material bound
+    /// to an ancestor, which the prim inherits.
+    fn convert_prim(&mut self, prim: usize, parent: Index<AiNode>, binding: Option<usize>) {
+        let stage = self.stage;
+        let p = stage.prim(prim);
+        // classes and overs describe no prims without composition
+        if p.specifier != Specifier::Def
+            || stage.metadata(prim, "active").and_then(as_bool) == Some(false)
+        {
+            return;
+        }
+        match p.type_name.as_str() {
+            "Material" | "Shader" | "NodeGraph" | "GeomSubset" | "Skeleton" | "SkelAnimation"
+            | "BlendShape" => return,
+            _ => {}
+        }
+        for arc in ["references", "payload", "inherits", "specializes"] {
+            if stage.metadata(prim, arc).is_some() {
+                log::warn!("The {arc} of prim {:?} are not resolved", p.name);
+            }
+        }
+        let binding = stage
+            .target(prim, "material:binding")
+            .and_then(|path| stage.find(path))
+            .or(binding);
+        let node = self
+            .builder
+            .add_node(&p.name, transformation(stage, prim), Some(parent));
+        match p.type_name.as_str() {
+            "Mesh" => {
+                for mesh in self.convert_mesh(prim, binding) {
+                    self.builder.attach_mesh(node, mesh);
+                }
+            }
+            "" | "Xform" | "Scope" | "SkelRoot" => {}
+            // cameras, lights and implicit surfaces like `Sphere`
+            other => log::debug!("Skipping {other} prim {:?}", p.name),
+        }
+        for &child in &p.children {
+            self.convert_prim(child, node, binding);
+        }
+    }
+
+    /// Converts a mesh, it is split by the materials bound to its subsets
+    fn convert_mesh(&mut self, prim: usize, binding: Option<usize>) -> Vec<u32> {
+        let stage = self.stage;
+        let name = &stage.prim(prim).name;
+        let counts = stage.numbers(prim, "faceVertexCounts");
+        let face_vertex_indices = stage.numbers(prim, "faceVertexIndices");
+        let points = vectors::<3>(stage, prim, "points");
+
+        // the corners of each face, as positions in the index list
+        let mut faces = Vec::with_capacity(counts.len());
+        let mut start = 0;
+        for &count in &counts {
+            let count = count.max(0.0) as usize;
+            if count > face_vertex_indices.len() - start {
+                log::warn!("Mesh {name:?} has less indices than its faces need");
+                break;
+            }
+            faces.push(start..start + count);
+            start += count;
+        }
+
+        // faces in subsets with a bound material get their own meshes
+        let mut groups = vec![(name.clone(), binding, Vec::new())];
+        let mut group_of_face = vec![0; faces.len()];
+        for &subset in &stage.prim(prim).children {
+            if stage.prim(subset).type_name != "GeomSubset"
+                || stage
+                    .token(subset, "elementType")
+                    .is_some_and(|t| t != "face")
+            {
+                continue;
+            }
+            let Some(material) = stage
+                .target(subset, "material:binding")
+                .and_then(|path| stage.find(path))
+            else {
+                continue;
+            };
+            let group = groups.len();
+            let indices = stage.numbers(subset, "indices");
+            for face in (0..indices.len()).filter_map(|k| index(&indices, k)) {
+                if let Some(g) = group_of_face.get_mut(face) {
+                    *g = group;
+                }
+            }
+            let subset_name = format!("{name}_{}", stage.prim(subset).name);
+            groups.push((subset_name, Some(material), Vec::new()));
+        }
+        for (face, &group) in group_of_face.iter().enumerate() {
+            groups[group].2.push(face);
+        }
+
+        let normals = Primvar::<3>::read(stage, prim, "primvars:normals", Interpolation::Vertex)
+            .or_else(|| Primvar::read(stage, prim, "normals", Interpolation::Vertex));
+        let tex_coords = tex_coords_name(stage, prim).and_then(|uv| {
+            Primvar::<2>::read(stage, prim, uv, Interpolation::Constant)
+                .map(|primvar| (uv, primvar))
+        });
+        let colors = Primvar::<3>::read(
+            stage,
+            prim,
+            "primvars:displayColor",
+            Interpolation::Constant,
+        );
+        let opacities = Primvar::<1>::read(
+            stage,
+            prim,
+            "primvars:displayOpacity",
+            Interpolation::Constant,
+        );
+        let left_handed = stage.token(prim, "orientation") == Some("leftHanded");
+        let two_sided = stage.bool(prim, "doubleSided") == Some(true);
+
+        let mut meshes = Vec::new();
+        for (mesh_name, material, group) in groups {
+            let mut mesh = AiMesh {
+                name: mesh_name,
+                ..Default::default()
+            };
+            if let Some((uv, _)) = &tex_coords {
+                mesh.num_of_uv_components[0] = 2;
+                mesh.set_texture_coords_name(0, uv.strip_prefix("primvars:").unwrap_or(uv));
+            }
+            // every corner gets its own vertex, like face-varying primvars
+            // need, JoinIdenticalVertices joins them again
+            for f in group {
+                let mut corners = faces[f].clone().collect::<Vec<_>>();
+                if corners.len() < 3 {
+                    continue;
+                }
+                if left_handed {
+                    corners.reverse();
+                }
+                let Some(positions) = corners
+                    .iter()
+                    .map(|&k| {
+                        index(&face_vertex_indices, k).and_then(|i| Some((i, points.get(i)?)))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    log::warn!("Skipping face {f} of mesh {name:?} with invalid indices");
+                    continue;
+                };
+                let start = mesh.vertices.len() as u32;
+                mesh.primitive_type |= PrimitiveTypeFlags::for_index_count(corners.len());
+                for (&k, (point, &[x, y, z])) in corners.iter().zip(positions) {
+                    mesh.vertices.push(Vec3::new(x, y, z));
+                    if let Some(normals) = &normals {
+                        let [x, y, z] = normals.get(f, point, k).unwrap_or_default();
+                        mesh.normals.push(Vec3::new(x, y, z));
+                    }
+                    if let Some((_, tex_coords)) = &tex_coords {
+                        let [u, v] = tex_coords.get(f, point, k).unwrap_or_default();
+                        mesh.texture_coords[0].push(Vec3::new(u, v, 0.0));
+                    }
+                    if colors.is_some() || opacities.is_some() {
+                        let [r, g, b] = colors
+                            .as_ref()
+                            .and_then(|colors| colors.get(f, point, k))
+                            .unwrap_or([1.0; 3]);
+                        let [a] = opacities
+                            .as_ref()
+                            .and_then(|opacities| opacities.get(f, point, k))
+                            .unwrap_or([1.0]);
+                        mesh.colors[0].push(Color4D::new(
+                            to_f32(r),
+                            to_f32(g),
+                            to_f32(b),
+                            to_f32(a),
+                        ));
+                    }
+                }
+                mesh.faces.push(AiFace {
+                    indices: (start..mesh.vertices.len() as u32).collect(),
+                });
+            }
+            if mesh.faces.is_empty() {
+                continue;
+            }
+            mesh.aabb = AABB::from_points(&mesh.vertices);
+            mesh.material_index = self.material(material, two_sided);
+            meshes.push(self.builder.add_mesh(mesh));
+        }
+        meshes
+    }
+
+    /// The material of a material prim, meshes bound to the same material
+    /// share it
+    fn material(&mut self, material: Option<usize>, two_sided: bool) -> u32 {
+        if let Some(&index) = self.materials.get(&(material, two_sided)) {
+            return index;
+        }
+        let stage = self.stage;
+        // the fallbacks of UsdPreviewSurface
+        let mut pbr = PbrMaterial {
+            base_color: Vec4::new(0.18, 0.18, 0.18, 1.0),
+            metallic: 0.0,
+            roughness: 0.5,
+            clearcoat_roughness: 0.01,
+            ..Default::default()
+        };
+        let mut extra_textures = Vec::new();
+        if let Some(shader) = material.and_then(|material| self.surface_shader(material)) {
+            let color = |value: &Value| {
+                let mut numbers = Vec::new();
+                if let Value::List(values) = value {
+                    numbers.extend(values.iter().filter_map(|v| match v {
+                        Value::Number(n) => Some(*n as AiReal),
+                        _ => None,
+                    }));
+                }
+                <[AiReal; 3]>::try_from(numbers).ok().map(Vec3::from_array)
+            };
+            let number = |value: &Value| match value {
+                Value::Number(n) => Some(*n as AiReal),
+                _ => None,
+            };
+            match self.input(shader, "inputs:diffuseColor") {
+                Some(Input::Value(value)) => {
+                    if let Some(rgb) = color(&value) {
+                        pbr.base_color = rgb.extend(pbr.base_color.w);
+                    }
+                }
+                Some(Input::Texture(texture)) => {
+                    pbr.base_color = Vec4::ONE;
+                    pbr.base_color_texture = self.texture(texture);
+                }
+                None => {}
+            }
+            match self.input(shader, "inputs:opacity") {
+                Some(Input::Value(value)) => {
+                    pbr.base_color.w = number(&value).unwrap_or(1.0);
+                }
+                Some(Input::Texture(texture)) => {
+                    extra_textures.push((AiTextureType::Opacity, self.texture(texture)));
+                }
+                None => {}
+            }
+            match self.input(shader, "inputs:emissiveColor") {
+                Some(Input::Value(value)) => pbr.emissive = color(&value).unwrap_or(Vec3::ZERO),
+                Some(Input::Texture(texture)) => {
+                    pbr.emissive = Vec3::ONE;
+                    pbr.emissive_texture = self.texture(texture);
+                }
+                None => {}
+            }
+            let mut factor =
+                |input, texture_type, factor: &mut AiReal| match self.input(shader, input) {
+                    Some(Input::Value(value)) => *factor = number(&value).unwrap_or(*factor),
+                    Some(Input::Texture(texture)) => {
+                        *factor = 1.0;
+                        extra_textures.push((texture_type, self.texture(texture)));
+                    }
+                    None => {}
+                };
+            factor(
+                "inputs:metallic",
+                AiTextureType::Metalness,
+                &mut pbr.metallic,
+            );
+            factor(
+                "inputs:roughness",
+                AiTextureType::DiffuseRoughness,
+                &mut pbr.roughness,
+            );
+            factor(
+                "inputs:clearcoat",
+                AiTextureType::Clearcoat,
+                &mut pbr.clearcoat,
+            );
+            if let Some(Input::Value(value)) = self.input(shader, "inputs:clearcoatRoughness") {
+                pbr.clearcoat_roughness = number(&value).unwrap_or(pbr.clearcoat_roughness);
+            }
+            if let Some(Input::Texture(texture)) = self.input(shader, "inputs:normal") {
+                pbr.normal_texture = self.texture(texture);
+            }
+            if let Some(Input::Texture(texture)) = self.input(shader, "inputs:occlusion") {
+                pbr.occlusion_texture = self.texture(texture);
+            }
+        }
+
+        let mut ai_material = AiMaterial::default();
+        let name = material.map_or("DefaultMaterial", |material| &stage.prim(material).name);
+        ai_material.add_property(AI_MATKEY_NAME, name.to_owned(), 0);
+        pbr.apply_to(&mut ai_material);
+        ai_material.add_property(AI_MATKEY_OPACITY, pbr.base_color.w, 0);
+        for (texture_type, slot) in extra_textures {
+            if let Some(slot) = slot {
+                ai_material.set_texture(texture_type, 0, slot);
+            }
+        }
+        if two_sided {
+            ai_material.add_property(AI_MATKEY_TWOSIDED, 1, 0);
+        }
+        let index = self.builder.add_material(ai_material);
+        self.materials.insert((material, two_sided), index);
+        index
+    }
+
+    /// The `UsdPreviewSurface` shader of a material, other shaders are not
+    /// supported
+    fn surface_shader(&self, material: usize) -> Option<usize> {
+        let stage = self.stage;
+        let is_preview_surface =
+            |shader: usize| stage.token(shader, "info:id") == Some("UsdPreviewSurface");
+        let connected = stage
+            .target(material, "outputs:surface.connect")
+            .and_then(|path| stage.find(path))
+            .filter(|&shader| is_preview_surface(shader));
+        let shader = connected.or_else(|| {
+            stage
+                .prim(material)
+                .children
+                .iter()
+                .copied()
+                .find(|&shader| is_preview_surface(shader))
+        });
+        if shader.is_none() {
+            log::warn!(
+                "Material {:?} has no UsdPreviewSurface shader",
+                stage.prim(material).name
+            );
+        }
+        shader
+    }
+
+    /// The value of a shader input, following connections to the inputs of
+    /// materials and node graphs
+    fn input(&self, prim: usize, input: &str) -> Option<Input> {
+        let stage = self.stage;
+        let (mut prim, mut input) = (prim, input.to_owned());
+        for _ in 0..MAX_CONNECTIONS {
+            let Some(path) = stage.target(prim, &format!("{input}.connect")) else {
+                return stage.value(prim, &input).cloned().map(Input::Value);
+            };
+            let source = stage.find(path)?;
+            if stage
+                .token(source, "info:id")
+                .is_some_and(|id| id.starts_with("UsdUVTexture"))
+            {
+                return Some(Input::Texture(source));
+            }
+            // an input of a material or node graph, or an output of a
+            // node graph, which is connected further
+            let (_, property) = path.split_once('.')?;
+            (prim, input) = (source, property.to_owned());
+        }
+        None
+    }
+
+    /// The texture slot of a `UsdUVTexture` shader. Textures in a usdz
+    /// package are embedded.
+    fn texture(&mut self, shader: usize) -> Option<TextureSlot> {
+        let stage = self.stage;
+        let file = match self.input(shader, "inputs:file")? {
+            Input::Value(Value::Asset(file)) => file,
+            _ => return None,
+        };
+        let map_mode = |input| match stage.token(shader, input) {
+            Some("clamp") => AiTextureMapMode::Clamp,
+            Some("mirror") => AiTextureMapMode::Mirror,
+            Some("black") => AiTextureMapMode::Decal,
+            _ => AiTextureMapMode::Wrap,
+        };
+        let path = match self.package.and_then(|package| package.resolve(&file)) {
+            Some(path) => self.embed(&path).unwrap_or(file),
+            None => file,
+        };
+        let mut slot = TextureSlot::new(path);
+        slot.map_mode = [map_mode("inputs:wrapS"), map_mode("inputs:wrapT")];
+        Some(slot)
+    }
+
+    /// Embeds a file of the package as compressed texture, returns its
+    /// `*n` path
+    fn embed(&mut self, path: &str) -> Option<String> {
+        if let Some(index) = self.textures.get(path) {
+            return Some(format!("*{index}"));
+        }
+        let bytes = match self.package?.read(path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                log::warn!("Texture {path:?} of the package is not embedded: {error}");
+                return None;
+            }
+        };
+        let hint = path
+            .rsplit_once('.')
+            .map_or("", |(_, e)| e)
+            .to_ascii_lowercase();
+        let mut texture = AiTexture::from_compressed(&bytes, &hint);
+        texture.filename = path.into();
+        let index = self.builder.add_texture(texture);
+        self.textures.insert(path.to_owned(), index);
+        Some(format!("*{index}"))
+    }
+}
+
+/// The name of the texture coordinates of a mesh, `primvars:st` or the
+/// first primvar of texture coordinates
+fn tex_coords_name(stage: &Stage, prim: usize) -> Option<&str> {
+    if stage.property(prim, "primvars:st").is_some() {
+        return Some("primvars:st");
+    }
+    stage
+        .prim(prim)
+        .properties
+        .iter()
+        .find_map(|(name, property)| {
+            (name.starts_with("primvars:")
+                && !name.ends_with(":indices")
+                && property.type_name.starts_with("texCoord2"))
+            .then_some(name.as_str())
+        })
+}
+
+/// The local transformation of a prim from its `xformOpOrder`, stored row
+/// by row
+fn transformation(stage: &Stage, prim: usize) -> Mat4 {
+    let mut matrix = Mat4::IDENTITY;
+    for op in stage.tokens(prim, "xformOpOrder") {
+        if op == "!resetXformStack!" {
+            log::debug!("Ignoring the reset of the transformation stack");
+            continue;
+        }
+        let (inverse, name) = match op.strip_prefix("!invert!") {
+            Some(name) => (true, name),
+            None => (false, op),
+        };
+        let kind = name
+            .strip_prefix("xformOp:")
+            .and_then(|kind| kind.split(':').next())
+            .unwrap_or_default();
+        let numbers = stage
+            .numbers(prim, name)
+            .into_iter()
+            .map(|n| n as AiReal)
+            .collect::<Vec<_>>();
+        let vector = |default| match numbers[..] {
+            [x, y, z, ..] => Vec3::new(x, y, z),
+            _ => default,
+        };
+        let angle = numbers.first().copied().unwrap_or_default().to_radians();
+        let transform = match kind {
+            "translate" => Mat4::from_translation(vector(Vec3::ZERO)),
+            "scale" => Mat4::from_scale(vector(Vec3::ONE)),
+            "rotateX" => Mat4::from_rotation_x(angle),
+            "rotateY" => Mat4::from_rotation_y(angle),
+            "rotateZ" => Mat4::from_rotation_z(angle),
+            // the angles are in degrees and ordered by axis, the first axis
+            // of the name is rotated about first
+            "rotateXYZ" | "rotateXZY" | "rotateYXZ" | "rotateYZX" | "rotateZXY" | "rotateZYX" => {
+                let angles = vector(Vec3::ZERO);
+                kind[6..].chars().fold(Mat4::IDENTITY, |rotation, axis| {
+                    let rotate = match axis {
+                        'X' => Mat4::from_rotation_x(angles.x.to_radians()),
+                        'Y' => Mat4::from_rotation_y(angles.y.to_radians()),
+                        _ => Mat4::from_rotation_z(angles.z.to_radians()),
+                    };
+                    rotate * rotation
+                })
+            }
+            // a quaternion with the real part first
+            "orient" => match numbers[..] {
+                [w, x, y, z, ..] => Mat4::from_quat(Quat::from_xyzw(x, y, z, w).normalize()),
+                _ => Mat4::IDENTITY,
+            },
+            // rows of a matrix which transforms row vectors, so the columns
+            // of one which transforms column vectors
+            "transform" => match <[AiReal; 16]>::try_from(numbers) {
+                Ok(values) => Mat4::from_cols_array(&values),
+                Err(_) => Mat4::IDENTITY,
+            },
+            _ => {
+                log::warn!("Unsupported transformation operation {op:?}");
+                Mat4::IDENTITY
+            }
+        };
+        matrix *= if inverse {
+            transform.inverse()
+        } else {
+            transform
+        };
+    }
+    matrix.transpose()
+}
+
+/// The element at position `k` of an index list
+fn index(indices: &[f64], k: usize) -> Option<usize> {
+    indices.get(k).filter(|&&i| i >= 0.0).map(|&i| i as usize)
+}
+
+/// The values of an attribute in groups of `N`
+fn vectors<const N: usize>(stage: &Stage, prim: usize, name: &str) -> Vec<[AiReal; N]> {
+    stage
+        .numbers(prim, name)
+        .chunks_exact(N)
+        .map(|chunk| core::array::from_fn(|i| chunk[i] as AiReal))
+        .collect()
+}
@@ -0,0 +1,303 @@
+pub mod errors;
+pub mod importer;
+mod parser;
+mod stage;
+
+#[allow(unused)]
+mod test {
+    use std::fs;
+
+    use super::{
+        errors::{UsdImportError, UsdParseError},
+        importer::{Importer, ROOT_NODE_NAME, SOURCE_FORMAT},
+    };
+    use crate::{
+        core::{importer_for_data, importer_for_extension},
+        decompose, get_model_path,
+        structs::{
+            coordinate_system::CoordinateSystem,
+            material::{
+                AI_MATKEY_BASE_COLOR, AI_MATKEY_METALLIC_FACTOR, AI_MATKEY_ROUGHNESS_FACTOR,
+                AI_MATKEY_TWOSIDED, AiTextureMapMode, AiTextureType, GetProperty,
+            },
+            mesh::PrimitiveTypeFlags,
+            meta::{AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, MetadataExt},
+            scene::AiScene,
+        },
+        traits::importer::trait_define::{FormatValidator, InternalImporter},
+        utils::float_precision::{Vec3, Vec4},
+    };
+
+    fn import(source: &[u8]) -> AiScene {
+        let mut scene = AiScene::default();
+        Importer::import_from_buf(source, &mut scene).unwrap();
+        scene
+    }
+
+    const USDA: &str = r#"#usda 1.0
+(
+    defaultPrim = "Root"
+    metersPerUnit = 1
+    upAxis = "Z"
+)
+
+def Xform "Root" (
+    kind = "component"
+)
+{
+    double3 xformOp:translate = (0, 0, 2)
+    float xformOp:rotateZ.timeSamples = {
+        10: 180,
+        0: 90,
+    }
+    uniform token[] xformOpOrder = ["xformOp:translate", "xformOp:rotateZ"]
+    rel material:binding = </Root/Looks/Red>
+
+    # a quad and a triangle, the triangle has its own material
+    def Mesh "Pyramid"
+    {
+        uniform bool doubleSided = 1
+        int[] faceVertexCounts = [4, 3]
+        int[] faceVertexIndices = [0, 1, 2, 3, 0, 1, 4]
+        point3f[] points = [(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0), (0.5, 0.5, 1)]
+        normal3f[] normals = [(0, 0, 1)] (
+            interpolation = "constant"
+        )
+        texCoord2f[] primvars:st = [(0, 0), (1, 0), (1, 1), (0, 1)] (
+            interpolation = "faceVarying"
+        )
+        int[] primvars:st:indices = [0, 1, 2, 3, 0, 1, 2]
+
+        def GeomSubset "Tip"
+        {
+            uniform token elementType = "face"
+            uniform token familyName = "materialBind"
+            int[] indices = [1]
+            rel material:binding = </Root/Looks/Textured>
+        }
+    }
+
+    def Scope "Looks"
+    {
+        def Material "Red"
+        {
+            token outputs:surface.connect = </Root/Looks/Red/Surface.outputs:surface>
+
+            def Shader "Surface"
+            {
+                uniform token info:id = "UsdPreviewSurface"
+                color3f inputs:diffuseColor = (1, 0, 0)
+                float inputs:metallic = 1
+                float inputs:roughness = 0.25
+                token outputs:surface
+            }
+        }
+
+        def Material "Textured"
+        {
+            asset inputs:albedo = @textures/albedo.png@
+            token outputs:surface.connect = </Root/Looks/Textured/Surface.outputs:surface>
+
+            def Shader "Surface"
+            {
+                uniform token info:id = "UsdPreviewSurface"
+                color3f inputs:diffuseColor.connect = </Root/Looks/Textured/Albedo.outputs:rgb>
+                token outputs:surface
+            }
+
+            def Shader "Albedo"
+            {
+                uniform token info:id = "UsdUVTexture"
+                asset inputs:file.connect = </Root/Looks/Textured.inputs:albedo>
+                token inputs:wrapS = "clamp"
+                float3 outputs:rgb
+            }
+        }
+    }
+}
+
+class "Template"
+{
+}
+"#;
+
+    /// A zip archive with stored files, like usdz packages are
+    fn usdz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        for (name, content) in files {
+            let crc = zlib_rs::crc32(0, content);
+            let offset = data.len() as u32;
+            let size = (content.len() as u32).to_le_bytes();
+            let name_len = (name.len() as u16).to_le_bytes();
+            data.extend_from_slice(b"PK\x03\x04\x14\x00\x00\x00\x00\x00\x00\x00\x00\x00");
+            data.extend_from_slice(&crc.to_le_bytes());
+            data.extend_from_slice(&size);
+            data.extend_from_slice(&size);
+            data.extend_from_slice(&name_len);
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(content);
+
+            directory
+                .extend_from_slice(b"PK\x01\x02\x14\x00\x14\x00\x00\x00\x00\x00\x00\x00\x00\x00");
+            directory.extend_from_slice(&crc.to_le_bytes());
+            directory.extend_from_slice(&size);
+            directory.extend_from_slice(&size);
+            directory.extend_from_slice(&name_len);
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+        let offset = data.len() as u32;
+        let count = (files.len() as u16).to_le_bytes();
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(b"PK\x05\x06\x00\x00\x00\x00");
+        data.extend_from_slice(&count);
+        data.extend_from_slice(&count);
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_import_usda() {
+        let scene = import(USDA.as_bytes());
+        let names = scene
+            .nodes
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        // materials and classes are no nodes
+        assert_eq!(names, [ROOT_NODE_NAME, "Root", "Pyramid", "Looks"]);
+        let (translation, rotation, _) = decompose(scene.nodes[1].transformation);
+        assert_eq!(translation, Vec3::new(0.0, 0.0, 2.0));
+        // the earliest time sample is used
+        let x = rotation * Vec3::X;
+        assert!((x - Vec3::Y).length() < 1e-5);
+
+        // the mesh is split by the material of its subset
+        assert_eq!(scene.meshes.len(), 2);
+        assert_eq!(scene.nodes[2].meshes, 0..2);
+        let quad = &scene.meshes[0];
+        assert_eq!(quad.name, "Pyramid");
+        assert_eq!(quad.faces.len(), 1);
+        assert_eq!(quad.vertices.len(), 4);
+        assert_eq!(quad.primitive_type, PrimitiveTypeFlags::POLYGON);
+        assert_eq!(quad.normals[3], Vec3::Z);
+        assert_eq!(quad.texture_coords[0][2], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(quad.get_texture_coords_name(0), Some("st"));
+        let tip = &scene.meshes[1];
+        assert_eq!(tip.name, "Pyramid_Tip");
+        assert_eq!(tip.primitive_type, PrimitiveTypeFlags::TRIANGLE);
+        assert_eq!(tip.vertices[2], Vec3::new(0.5, 0.5, 1.0));
+
+        let red = &scene.materials[quad.material_index as usize];
+        assert_eq!(red.name(), Some("Red"));
+        assert_eq!(
+            red.get_property(AI_MATKEY_BASE_COLOR, 0),
+            Some(&Vec4::new(1.0, 0.0, 0.0, 1.0))
+        );
+        assert_eq!(red.get_float(AI_MATKEY_METALLIC_FACTOR), Some(1.0));
+        assert_eq!(red.get_float(AI_MATKEY_ROUGHNESS_FACTOR), Some(0.25));
+        assert_eq!(red.get_int(AI_MATKEY_TWOSIDED), Some(1));
+        let textured = &scene.materials[tip.material_index as usize];
+        assert_eq!(textured.name(), Some("Textured"));
+        // the file is an input of the material
+        let texture = textured.get_texture(AiTextureType::BaseColor, 0).unwrap();
+        assert_eq!(texture.path, "textures/albedo.png");
+        assert_eq!(
+            texture.map_mode,
+            [AiTextureMapMode::Clamp, AiTextureMapMode::Wrap]
+        );
+
+        let metadata = &scene.metadata;
+        assert_eq!(
+            metadata.get_str(AI_METADATA_SOURCE_FORMAT),
+            Some(SOURCE_FORMAT)
+        );
+        assert_eq!(
+            metadata.get_str(AI_METADATA_SOURCE_FORMAT_VERSION),
+            Some("1.0")
+        );
+        let system = CoordinateSystem::from_metadata(metadata).unwrap();
+        assert_eq!(
+            system,
+            CoordinateSystem::Z_UP_RIGHT_HANDED.with_unit_scale_factor(CoordinateSystem::METERS)
+        );
+    }
+
+    #[test]
+    fn test_import_usdz() {
+        let png = b"\x89PNG\r\n\x1a\n";
+        let package = usdz(&[
+            ("scene.usda", USDA.as_bytes()),
+            ("textures/albedo.png", png),
+        ]);
+        assert!(Importer::can_read_from_buf(&package));
+        let scene = import(&package);
+        assert_eq!(scene.meshes.len(), 2);
+        // textures of the package are embedded
+        assert_eq!(scene.textures.len(), 1);
+        assert_eq!(scene.textures[0].compressed_data().unwrap(), png);
+        assert_eq!(scene.textures[0].format_hint(), "png");
+        let textured = &scene.materials[scene.meshes[1].material_index as usize];
+        let texture = textured.get_texture(AiTextureType::Diffuse, 0).unwrap();
+        assert_eq!(texture.path, "*0");
+    }
+
+    #[test]
+    fn test_import_left_handed() {
+        let source = br#"#usda 1.0
+def Mesh "Triangle"
+{
+    uniform token orientation = "leftHanded"
+    int[] faceVertexCounts = [3]
+    int[] faceVertexIndices = [0, 1, 2]
+    point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0)]
+    color3f[] primvars:displayColor = [(0, 1, 0)]
+}
+"#;
+        let scene = import(source);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.vertices[0], Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.colors[0][2].y, 1.0);
+        assert_eq!(scene.materials[0].name(), Some("DefaultMaterial"));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let mut scene = AiScene::default();
+        let source = fs::read(get_model_path("USD", "MotionCaptureROM.usdc")).unwrap();
+        let error = Importer::import_from_buf(&source, &mut scene).unwrap_err();
+        assert!(matches!(error, UsdImportError::UnsupportedCrateFormat));
+
+        let error = Importer::import_from_buf(b"#usda 2.0\n", &mut scene).unwrap_err();
+        assert!(matches!(error, UsdImportError::UnsupportedVersion(v) if v == "2.0"));
+
+        let source = b"#usda 1.0\ndef Xform \"Root\"\n{\n    float3 xformOp:scale = (1, 2\n}\n";
+        let error = Importer::import_from_buf(source, &mut scene).unwrap_err();
+        assert!(matches!(
+            error,
+            UsdImportError::ParseError {
+                line: 5,
+                error: UsdParseError::UnexpectedToken(_)
+            }
+        ));
+
+        let package = usdz(&[("texture.png", b"\x89PNG\r\n\x1a\n")]);
+        let error = Importer::import_from_buf(&package, &mut scene).unwrap_err();
+        assert!(matches!(error, UsdImportError::MissingLayer));
+    }
+
+    #[test]
+    fn test_registry() {
+        let desc = importer_for_extension("usdz").unwrap();
+        assert_eq!(desc.name, "Universal Scene Description Importer");
+        assert_eq!(importer_for_extension(".USDA").unwrap().name, desc.name);
+        assert_eq!(importer_for_data(USDA.as_bytes()).unwrap().name, desc.name);
+        let package = usdz(&[("scene.usda", USDA.as_bytes())]);
+        assert_eq!(importer_for_data(&package).unwrap().name, desc.name);
+    }
+}
@@ -0,0 +1,529 @@
+//! Parser of usda layers, the text encoding of USD, e.g.
+//!
+//! ```text
+//! #usda 1.0
+//! (
+//!     upAxis = "Y"
+//! )
+//!
+//! def Xform "Root"
+//! {
+//!     double3 xformOp:translate = (0, 1, 0)
+//!     uniform token[] xformOpOrder = ["xformOp:translate"]
+//!
+//!     def Mesh "Triangle"
+//!     {
+//!         int[] faceVertexCounts = [3]
+//!         int[] faceVertexIndices = [0, 1, 2]
+//!         point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0)]
+//!     }
+//! }
+//! ```
+//!
+//! Prims are parsed without knowing their schema, the type of a value
+//! follows from its tokens. Variant sets and dictionaries are skipped.
+
+use super::{
+    errors::{UsdImportError, UsdParseError},
+    stage::{MAX_DEPTH, Prim, Property, Specifier, Stage, Value},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    /// Keywords, names like `xformOp:translate`, types and numbers
+    Word(&'a str),
+    String(String),
+    Asset(&'a str),
+    Path(&'a str),
+    Punct(u8),
+}
+
+impl Token<'_> {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(w) => w.to_string(),
+            Token::String(s) => format!("\"{s}\""),
+            Token::Asset(a) => format!("@{a}@"),
+            Token::Path(p) => format!("<{p}>"),
+            Token::Punct(c) => char::from(*c).to_string(),
+        }
+    }
+}
+
+fn is_word_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b'-' | b'+')
+}
+
+fn unterminated(line: usize, what: &'static str) -> UsdImportError {
+    UsdImportError::ParseError {
+        line,
+        error: UsdParseError::Unterminated(what),
+    }
+}
+
+/// Splits the text into tokens with their line numbers. `#` and `//` start
+/// comments, so the header line is one.
+fn lex(text: &str) -> Result<Vec<(Token<'_>, usize)>, UsdImportError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut pos = 0;
+    while let Some(&c) = bytes.get(pos) {
+        let rest = &bytes[pos..];
+        let start = line;
+        let token = match c {
+            b'\n' => {
+                line += 1;
+                pos += 1;
+                continue;
+            }
+            c if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'#' | b'/' if c == b'#' || rest.starts_with(b"//") => {
+                pos += rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+                continue;
+            }
+            b'/' if rest.starts_with(b"/*") => {
+                let len = rest
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .ok_or_else(|| unterminated(start, "comment"))?;
+                line += rest[..len].iter().filter(|&&c| c == b'\n').count();
+                pos += len + 2;
+                continue;
+            }
+            b'"' | b'\'' => {
+                let (s, len) = lex_string(&text[pos..], &mut line)
+                    .ok_or_else(|| unterminated(start, "string"))?;
+                tokens.push((Token::String(s), start));
+                pos += len;
+                continue;
+            }
+            // `@path@`, or `@@@path@@@` for paths containing `@`
+            b'@' => {
+                let delimiter: &[u8] = if rest.starts_with(b"@@@") {
+                    b"@@@"
+                } else {
+                    b"@"
+                };
+                let content = &rest[delimiter.len()..];
+                let len = content
+                    .windows(delimiter.len())
+                    .position(|w| w == delimiter)
+                    .ok_or_else(|| unterminated(start, "asset path"))?;
+                let begin = pos + delimiter.len();
+                tokens.push((Token::Asset(&text[begin..begin + len]), start));
+                pos = begin + len + delimiter.len();
+                continue;
+            }
+            b'<' => {
+                let len = rest
+                    .iter()
+                    .position(|&c| c == b'>' || c == b'\n')
+                    .filter(|&len| rest[len] == b'>')
+                    .ok_or_else(|| unterminated(start, "path"))?;
+                tokens.push((Token::Path(&text[pos + 1..pos + len]), start));
+                pos += len + 1;
+                continue;
+            }
+            b'(' | b')' | b'[' | b']' | b'{' | b'}' | b'=' | b',' | b':' | b';' => Token::Punct(c),
+            c if is_word_byte(c) => {
+                // namespaced names like `primvars:st:indices` keep their
+                // colons, the colon after a time code like `0: (1, 2, 3)`
+                // is a separator
+                let numeric = c.is_ascii_digit() || matches!(c, b'-' | b'+' | b'.');
+                let mut len = 0;
+                let namespace = |len: usize| {
+                    !numeric
+                        && rest
+                            .get(len + 1)
+                            .is_some_and(|&c| c.is_ascii_alphabetic() || c == b'_')
+                };
+                while let Some(&c) = rest.get(len) {
+                    if !(is_word_byte(c) || c == b':' && namespace(len)) {
+                        break;
+                    }
+                    len += 1;
+                }
+                tokens.push((Token::Word(&text[pos..pos + len]), start));
+                pos += len;
+                continue;
+            }
+            _ => {
+                let c = text[pos..].chars().next().unwrap_or_default();
+                return Err(UsdImportError::ParseError {
+                    line,
+                    error: UsdParseError::UnexpectedToken(c.to_string()),
+                });
+            }
+        };
+        tokens.push((token, start));
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+/// Lexes a string quoted with `"`, `'` or their triple versions, returns
+/// the unescaped content and the length of the quoted string
+fn lex_string(text: &str, line: &mut usize) -> Option<(String, usize)> {
+    let quote = &text[..1];
+    let triple = quote.repeat(3);
+    let delimiter = if text.starts_with(&triple) {
+        triple.as_str()
+    } else {
+        quote
+    };
+    let mut s = String::new();
+    let mut chars = text[delimiter.len()..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if text[delimiter.len() + i..].starts_with(delimiter) => {
+                return Some((s, 2 * delimiter.len() + i));
+            }
+            '\\' => match chars.next()?.1 {
+                'n' => s.push('\n'),
+                't' => s.push('\t'),
+                'r' => s.push('\r'),
+                c => s.push(c),
+            },
+            '\n' if delimiter.len() == 1 => return None,
+            c => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                s.push(c);
+            }
+        }
+    }
+    None
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    pos: usize,
+    stage: Stage,
+}
+
+/// Parses a whole layer, the header line is a comment
+pub(super) fn parse(text: &str) -> Result<Stage, UsdImportError> {
+    let mut parser = Parser {
+        tokens: lex(text)?,
+        pos: 0,
+        stage: Stage::default(),
+    };
+    if parser.peek() == Some(&Token::Punct(b'(')) {
+        parser.stage.metadata = parser.parse_metadata(0)?;
+    }
+    while parser.peek().is_some() {
+        let prim = parser.parse_statement(0)?;
+        parser.stage.roots.push(prim);
+    }
+    Ok(parser.stage)
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn error(&self, error: UsdParseError) -> UsdImportError {
+        let line = match self
+            .tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+        {
+            Some(&(_, line)) => line,
+            None => 1,
+        };
+        UsdImportError::ParseError { line, error }
+    }
+
+    fn unexpected(&self) -> UsdImportError {
+        self.error(match self.peek() {
+            Some(token) => UsdParseError::UnexpectedToken(token.describe()),
+            None => UsdParseError::UnexpectedEnd,
+        })
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, UsdImportError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|(token, _)| token.clone())
+            .ok_or_else(|| self.error(UsdParseError::UnexpectedEnd))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn word(&mut self) -> Result<&'a str, UsdImportError> {
+        match self.peek() {
+            Some(&Token::Word(word)) => {
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), UsdImportError> {
+        if self.peek() != Some(&Token::Punct(c)) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Consumes the punctuation `c` if it is next
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(&Token::Punct(c));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Skips a token and everything up to the matching closing bracket if it
+    /// opens one
+    fn skip_balanced(&mut self) -> Result<(), UsdImportError> {
+        let mut depth = 0usize;
+        loop {
+            match self.next()? {
+                Token::Punct(b'(' | b'[' | b'{') => depth += 1,
+                Token::Punct(b')' | b']' | b'}') => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parses a prim statement `def`, `over` or `class`, returns the prim
+    fn parse_statement(&mut self, depth: usize) -> Result<usize, UsdImportError> {
+        let specifier = match self.word()? {
+            "def" => Specifier::Def,
+            "over" => Specifier::Over,
+            "class" => Specifier::Class,
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected());
+            }
+        };
+        self.parse_prim(specifier, depth)
+    }
+
+    /// Parses `def Type "name" (metadata) { body }` after the specifier
+    fn parse_prim(&mut self, specifier: Specifier, depth: usize) -> Result<usize, UsdImportError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(UsdParseError::TooDeep(MAX_DEPTH)));
+        }
+        let type_name = match self.peek() {
+            Some(Token::Word(_)) => self.word()?.to_owned(),
+            _ => String::new(),
+        };
+        let name = match self.next()? {
+            Token::String(name) => name,
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected());
+            }
+        };
+        let metadata = if self.peek() == Some(&Token::Punct(b'(')) {
+            self.parse_metadata(depth)?
+        } else {
+            Vec::new()
+        };
+        self.expect(b'{')?;
+        let mut properties = Vec::new();
+        let mut children = Vec::new();
+        while !self.eat(b'}') {
+            if self.eat(b';') {
+                continue;
+            }
+            match self.peek() {
+                Some(Token::Word("def" | "over" | "class")) => {
+                    children.push(self.parse_statement(depth + 1)?);
+                }
+                // variantSet "name" = { "variant" { body } ... }
+                Some(Token::Word("variantSet")) => {
+                    log::debug!("Skipping variant set of prim {name:?}");
+                    self.pos += 2;
+                    self.expect(b'=')?;
+                    self.skip_balanced()?;
+                }
+                // reorder nameChildren = ["a", "b"]
+                Some(Token::Word("reorder")) => {
+                    self.pos += 2;
+                    self.expect(b'=')?;
+                    self.skip_balanced()?;
+                }
+                _ => {
+                    if let Some(property) = self.parse_property(depth)? {
+                        properties.push(property);
+                    }
+                }
+            }
+        }
+        let index = self.stage.prims.len();
+        self.stage.prims.push(Prim {
+            specifier,
+            type_name,
+            name,
+            metadata,
+            properties,
+            children,
+        });
+        Ok(index)
+    }
+
+    /// Parses `(key = value ...)`, a lone string is the `doc` of the prim.
+    /// Operations on list values like `prepend` are ignored, the list is
+    /// taken as is.
+    fn parse_metadata(&mut self, depth: usize) -> Result<Vec<(String, Value)>, UsdImportError> {
+        self.expect(b'(')?;
+        let mut metadata = Vec::new();
+        while !self.eat(b')') {
+            if self.eat(b';') {
+                continue;
+            }
+            if let Some(Token::String(_)) = self.peek() {
+                let Token::String(doc) = self.next()? else {
+                    unreachable!()
+                };
+                metadata.push(("doc".to_owned(), Value::String(doc)));
+                continue;
+            }
+            let mut key = self.word()?;
+            if matches!(key, "prepend" | "append" | "add" | "delete" | "reorder") {
+                key = self.word()?;
+            }
+            // `string key = value` entries of layer level dictionaries
+            if let Some(Token::Word(_)) = self.peek() {
+                key = self.word()?;
+            }
+            self.expect(b'=')?;
+            let value = self.parse_value(depth)?;
+            metadata.push((key.to_owned(), value));
+        }
+        Ok(metadata)
+    }
+
+    /// Parses an attribute `uniform type name = value (metadata)` or a
+    /// relationship `rel name = <target>`. Returns `None` for deletions.
+    fn parse_property(
+        &mut self,
+        depth: usize,
+    ) -> Result<Option<(String, Property)>, UsdImportError> {
+        let mut deleted = false;
+        let mut word = self.word()?;
+        loop {
+            match word {
+                "delete" => deleted = true,
+                "prepend" | "append" | "add" | "custom" | "uniform" | "varying" | "config" => {}
+                _ => break,
+            }
+            word = self.word()?;
+        }
+        let mut type_name = word.to_owned();
+        if self.peek() == Some(&Token::Punct(b'[')) {
+            self.expect(b'[')?;
+            self.expect(b']')?;
+            type_name += "[]";
+        }
+        let name = self.word()?;
+        let mut property = Property {
+            type_name,
+            ..Default::default()
+        };
+        if self.eat(b'=') {
+            property.value = Some(match name.strip_suffix(".timeSamples") {
+                Some(_) => self.parse_time_samples(depth)?,
+                None => self.parse_value(depth)?,
+            });
+        }
+        if self.peek() == Some(&Token::Punct(b'(')) {
+            let metadata = self.parse_metadata(depth)?;
+            property.interpolation =
+                metadata
+                    .into_iter()
+                    .rev()
+                    .find_map(|(key, value)| match (key.as_str(), value) {
+                        ("interpolation", Value::String(s)) => Some(s),
+                        _ => None,
+                    });
+        }
+        if deleted {
+            return Ok(None);
+        }
+        // the earliest sample stands for animated attributes
+        let name = name.strip_suffix(".timeSamples").unwrap_or(name);
+        Ok(Some((name.to_owned(), property)))
+    }
+
+    /// Parses `{ time: value, ... }`, returns the value of the earliest time
+    fn parse_time_samples(&mut self, depth: usize) -> Result<Value, UsdImportError> {
+        self.expect(b'{')?;
+        let mut earliest: Option<(f64, Value)> = None;
+        while !self.eat(b'}') {
+            let time = self.word()?;
+            let time = time
+                .parse::<f64>()
+                .map_err(|_| self.error(UsdParseError::UnexpectedToken(time.to_owned())))?;
+            self.expect(b':')?;
+            let value = self.parse_value(depth)?;
+            if earliest.as_ref().is_none_or(|(t, _)| time < *t) {
+                earliest = Some((time, value));
+            }
+            self.eat(b',');
+        }
+        Ok(earliest.map_or(Value::None, |(_, value)| value))
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, UsdImportError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(UsdParseError::TooDeep(MAX_DEPTH)));
+        }
+        let value = match self.next()? {
+            Token::Word("None") => Value::None,
+            Token::Word(word) => match word.parse::<f64>() {
+                Ok(number) => Value::Number(number),
+                Err(_) => Value::String(word.to_owned()),
+            },
+            Token::String(s) => Value::String(s),
+            Token::Asset(asset) => {
+                // the prim and the layer offset of references and payloads
+                if let Some(Token::Path(_)) = self.peek() {
+                    self.pos += 1;
+                }
+                if self.peek() == Some(&Token::Punct(b'(')) {
+                    self.skip_balanced()?;
+                }
+                Value::Asset(asset.to_owned())
+            }
+            Token::Path(path) => Value::Path(path.to_owned()),
+            Token::Punct(open @ (b'(' | b'[')) => {
+                let close = if open == b'(' { b')' } else { b']' };
+                let mut values = Vec::new();
+                while !self.eat(close) {
+                    values.push(self.parse_value(depth + 1)?);
+                    if !self.eat(b',') {
+                        self.expect(close)?;
+                        break;
+                    }
+                }
+                Value::List(values)
+            }
+            Token::Punct(b'{') => {
+                self.pos -= 1;
+                self.skip_balanced()?;
+                Value::None
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected());
+            }
+        };
+        Ok(value)
+    }
+}
@@ -0,0 +1,185 @@
+//! The prims of a usda layer. Attributes are kept untyped, the importer
+//! reads them with the type it expects. Layers are not composed: references,
+//! payloads, inherits and variants are not resolved.
+
+/// Prims and values are nested at most this deep, to bound the recursion of
+/// parsing and converting them
+pub(super) const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Number(f64),
+    /// Strings, tokens and words like `true`
+    String(String),
+    /// An asset path like `@textures/albedo.png@`
+    Asset(String),
+    /// A prim or property path like `</Root/Mesh.points>`
+    Path(String),
+    /// Tuples like `(1, 2, 3)` and arrays like `[1, 2, 3]`
+    List(Vec<Value>),
+    /// `None` and dictionaries, which are not read
+    None,
+}
+
+impl Value {
+    /// Appends the numbers of the value and of nested lists
+    fn flatten(&self, numbers: &mut Vec<f64>) {
+        match self {
+            Value::Number(n) => numbers.push(*n),
+            Value::List(values) => values.iter().for_each(|v| v.flatten(numbers)),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Specifier {
+    Def,
+    Over,
+    Class,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Property {
+    /// The value type like `point3f[]`, `rel` for relationships
+    pub type_name: String,
+    /// The default value, or the earliest time sample of animated attributes
+    pub value: Option<Value>,
+    /// The `interpolation` metadata of primvars
+    pub interpolation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Prim {
+    pub specifier: Specifier,
+    /// The schema like `Xform` or `Mesh`, empty for typeless prims
+    pub type_name: String,
+    pub name: String,
+    pub metadata: Vec<(String, Value)>,
+    /// Properties by name, `.connect` is part of the name of connections
+    pub properties: Vec<(String, Property)>,
+    pub children: Vec<usize>,
+}
+
+/// All prims of a layer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Stage {
+    /// The metadata of the layer like `upAxis`
+    pub metadata: Vec<(String, Value)>,
+    pub prims: Vec<Prim>,
+    /// The prims at the top level of the layer
+    pub roots: Vec<usize>,
+}
+
+fn find<'a>(entries: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+    entries
+        .iter()
+        .rev()
+        .find_map(|(name, value)| (name == key).then_some(value))
+}
+
+impl Stage {
+    pub fn prim(&self, prim: usize) -> &Prim {
+        &self.prims[prim]
+    }
+
+    pub fn layer_metadata(&self, key: &str) -> Option<&Value> {
+        find(&self.metadata, key)
+    }
+
+    pub fn metadata(&self, prim: usize, key: &str) -> Option<&Value> {
+        find(&self.prims[prim].metadata, key)
+    }
+
+    pub fn property(&self, prim: usize, name: &str) -> Option<&Property> {
+        self.prims[prim]
+            .properties
+            .iter()
+            .rev()
+            .find_map(|(n, property)| (n == name).then_some(property))
+    }
+
+    pub fn value(&self, prim: usize, name: &str) -> Option<&Value> {
+        self.property(prim, name)?.value.as_ref()
+    }
+
+    /// The numbers of an attribute, tuples are flattened. Empty if the
+    /// attribute is missing or holds no numbers.
+    pub fn numbers(&self, prim: usize, name: &str) -> Vec<f64> {
+        let mut numbers = Vec::new();
+        if let Some(value) = self.value(prim, name) {
+            value.flatten(&mut numbers);
+        }
+        numbers
+    }
+
+    /// A string or token attribute
+    pub fn token(&self, prim: usize, name: &str) -> Option<&str> {
+        match self.value(prim, name)? {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The strings of an array of tokens like `xformOpOrder`
+    pub fn tokens(&self, prim: usize, name: &str) -> Vec<&str> {
+        match self.value(prim, name) {
+            Some(Value::List(values)) => values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::String(s) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn bool(&self, prim: usize, name: &str) -> Option<bool> {
+        as_bool(self.value(prim, name)?)
+    }
+
+    /// The first target of a relationship or of a connection, e.g. of
+    /// `material:binding` or `inputs:diffuseColor.connect`
+    pub fn target(&self, prim: usize, name: &str) -> Option<&str> {
+        match self.value(prim, name)? {
+            Value::Path(path) => Some(path),
+            Value::List(values) => values.iter().find_map(|value| match value {
+                Value::Path(path) => Some(path.as_str()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The prim at an absolute path like `/Root/Looks/Red`, the property part
+    /// of a property path is ignored
+    pub fn find(&self, path: &str) -> Option<usize> {
+        let path = path.split('.').next().unwrap_or_default();
+        let mut names = path.strip_prefix('/')?.split('/');
+        let first = names.next()?;
+        let mut prim = *self
+            .roots
+            .iter()
+            .find(|&&root| self.prims[root].name == first)?;
+        for name in names {
+            prim = *self.prims[prim]
+                .children
+                .iter()
+                .find(|&&child| self.prims[child].name == name)?;
+        }
+        Some(prim)
+    }
+}
+
+pub(super) fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Number(n) => Some(*n != 0.0),
+        Value::String(s) => match s.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
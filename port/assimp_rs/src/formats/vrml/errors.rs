@@ -0,0 +1,61 @@
+use thiserror::Error;
+
+use crate::{
+    structs::scene_builder::SceneBuildError,
+    traits::importer::error::{EncodingError, ImportError},
+};
+
+/// VRML and X3D specific import errors
+#[derive(Debug, Error)]
+pub enum VrmlImportError {
+    #[error("Neither a VRML 2.0 nor an X3D file")]
+    InvalidFormat,
+
+    #[error("Unsupported version {0:?}, only VRML 2.0 and X3D are supported")]
+    UnsupportedVersion(String),
+
+    #[error("The scene instantiates more than {0} nodes")]
+    TooManyNodes(usize),
+
+    #[error("Nodes are instantiated deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("Line {line}: {error}")]
+    ParseError { line: usize, error: VrmlParseError },
+
+    #[error("Import error: {0}")]
+    ImportError(#[from] ImportError),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Build error: {0}")]
+    BuildError(#[from] SceneBuildError),
+}
+
+impl From<EncodingError> for VrmlImportError {
+    fn from(error: EncodingError) -> Self {
+        Self::ImportError(ImportError::EncodingError(error))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VrmlParseError {
+    #[error("Unexpected end of file")]
+    UnexpectedEnd,
+
+    #[error("Unexpected {0:?}")]
+    UnexpectedToken(String),
+
+    #[error("Unterminated string")]
+    UnterminatedString,
+
+    #[error("USE of {0:?}, which is not defined before")]
+    UndefinedName(String),
+
+    #[error("Nodes are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("Closing tag </{found}> does not match <{expected}>")]
+    MismatchedTag { expected: String, found: String },
+}
@@ -0,0 +1,150 @@
+//! The scene graph of a file, shared by the classic VRML encoding and the
+//! XML encoding of X3D. Fields are kept untyped, the importer reads them
+//! with the type it expects.
+
+use std::borrow::Cow;
+
+/// Nodes are nested at most this deep, to bound the recursion of parsing
+/// and converting them
+pub(super) const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    Numbers(Vec<f64>),
+    Strings(Vec<String>),
+    Bool(bool),
+    /// Indices into [`Graph::nodes`], empty for `NULL`
+    Nodes(Vec<usize>),
+    /// An attribute of an X3D element, which is parsed when it is read
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Node {
+    pub type_name: String,
+    /// The name given with `DEF`
+    pub name: Option<String>,
+    pub fields: Vec<(String, Value)>,
+}
+
+/// All nodes of a file. A node used several times with `USE` is stored
+/// once. Names are only resolved to nodes defined before, so the graph has
+/// no cycles.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Graph {
+    pub nodes: Vec<Node>,
+    /// The nodes at the top level of the file
+    pub roots: Vec<usize>,
+}
+
+impl Graph {
+    pub fn type_name(&self, node: usize) -> &str {
+        &self.nodes[node].type_name
+    }
+
+    pub fn name(&self, node: usize) -> Option<&str> {
+        self.nodes[node].name.as_deref()
+    }
+
+    fn field(&self, node: usize, field: &str) -> Option<&Value> {
+        self.nodes[node]
+            .fields
+            .iter()
+            .rev()
+            .find_map(|(name, value)| (name == field).then_some(value))
+    }
+
+    /// The numbers of a field, empty if it is missing or no list of numbers
+    pub fn numbers(&self, node: usize, field: &str) -> Cow<'_, [f64]> {
+        match self.field(node, field) {
+            Some(Value::Numbers(numbers)) => Cow::Borrowed(numbers),
+            Some(Value::Text(text)) => Cow::Owned(
+                text.split(|c: char| c.is_ascii_whitespace() || c == ',')
+                    .filter(|s| !s.is_empty())
+                    .map_while(parse_number)
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(&[]),
+        }
+    }
+
+    /// The first `N` numbers of a field, `None` if there are less
+    pub fn array<const N: usize>(&self, node: usize, field: &str) -> Option<[f64; N]> {
+        self.numbers(node, field).get(..N)?.try_into().ok()
+    }
+
+    pub fn number(&self, node: usize, field: &str) -> Option<f64> {
+        self.array::<1>(node, field).map(|[n]| n)
+    }
+
+    pub fn bool(&self, node: usize, field: &str) -> Option<bool> {
+        match self.field(node, field)? {
+            Value::Bool(value) => Some(*value),
+            Value::Text(text) => match text.trim() {
+                "true" | "TRUE" => Some(true),
+                "false" | "FALSE" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn strings(&self, node: usize, field: &str) -> Cow<'_, [String]> {
+        match self.field(node, field) {
+            Some(Value::Strings(strings)) => Cow::Borrowed(strings),
+            Some(Value::Text(text)) => Cow::Owned(parse_strings(text)),
+            _ => Cow::Borrowed(&[]),
+        }
+    }
+
+    /// The nodes of a field, empty if it is missing or no node
+    pub fn children(&self, node: usize, field: &str) -> &[usize] {
+        match self.field(node, field) {
+            Some(Value::Nodes(nodes)) => nodes,
+            _ => &[],
+        }
+    }
+
+    /// The first node of a field
+    pub fn child(&self, node: usize, field: &str) -> Option<usize> {
+        self.children(node, field).first().copied()
+    }
+}
+
+/// Parses a decimal or hexadecimal (`0xFF`) number
+pub(super) fn parse_number(s: &str) -> Option<f64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        let value = u32::from_str_radix(hex, 16).ok()? as f64;
+        return Some(if negative { -value } else { value });
+    }
+    s.parse().ok()
+}
+
+/// Parses the quoted strings of an X3D `MFString` attribute like
+/// `'"a.png" "b.png"'`. Unquoted text is a single string.
+fn parse_strings(text: &str) -> Vec<String> {
+    if !text.trim_start().starts_with('"') {
+        return vec![text.to_owned()];
+    }
+    let mut strings = Vec::new();
+    let mut chars = text.chars();
+    while chars.by_ref().any(|c| c == '"') {
+        let mut s = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => s.extend(chars.next()),
+                c => s.push(c),
+            }
+        }
+        strings.push(s);
+    }
+    strings
+}
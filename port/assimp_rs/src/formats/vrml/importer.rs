@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+
+use super::{
+    errors::VrmlImportError,
+    graph::{Graph, MAX_DEPTH},
+    parser, xml,
+};
+use crate::{
+    AiReal,
+    core::profiler::{ProfileScope, Profiler},
+    structs::{
+        aabb::AABB,
+        color::Color4D,
+        coordinate_system::CoordinateSystem,
+        face::AiFace,
+        importer::ImportProperties,
+        importer_desc::{ImporterDesc, ImporterFlags},
+        material::{
+            AI_MATKEY_COLOR_AMBIENT, AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_COLOR_EMISSIVE,
+            AI_MATKEY_COLOR_SPECULAR, AI_MATKEY_NAME, AI_MATKEY_OPACITY, AI_MATKEY_SHADING_MODEL,
+            AI_MATKEY_SHININESS, AI_MATKEY_TWOSIDED, AddProperty, AiMaterial, AiShadingMode,
+            AiTextureMapMode, AiTextureType, TextureSlot,
+        },
+        mesh::{AiMesh, PrimitiveTypeFlags},
+        meta::{AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, MetadataExt},
+        nodes::Index,
+        scene::{AiNode, AiScene},
+        scene_builder::SceneBuilder,
+    },
+    traits::importer::{
+        error::EncodingError,
+        trait_define::{FormatHeader, InternalImporter},
+    },
+    utils::float_precision::{Mat4, Quat, Vec3, to_f32},
+};
+
+/// Value of the [`AI_METADATA_SOURCE_FORMAT`] metadata of scenes imported
+/// from VRML files
+pub const SOURCE_FORMAT_VRML: &str = "VRML";
+
+/// Value of the [`AI_METADATA_SOURCE_FORMAT`] metadata of scenes imported
+/// from X3D files, in the classic or the XML encoding
+pub const SOURCE_FORMAT_X3D: &str = "X3D";
+
+/// Name of the node holding the top level nodes of a file
+pub const ROOT_NODE_NAME: &str = "VRMLRoot";
+
+/// Upper bound of the nodes a scene may instantiate. `USE` of a group which
+/// uses another group several times multiplies the nodes, so a small file
+/// can describe a huge scene.
+const MAX_NODES: usize = 1 << 20;
+
+pub(crate) static DESC: ImporterDesc = ImporterDesc {
+    name: "VRML 2.0 and X3D Importer",
+    author: "",
+    maintainer: "",
+    comments: "Shapes with indexed face, triangle and line sets, no prototypes or scripts",
+    flags: ImporterFlags::SUPPORT_TEXT_FLAVOUR.bits()
+        | ImporterFlags::SUPPORT_COMPRESSED_FLAVOUR.bits()
+        | ImporterFlags::LIMITED_SUPPORT.bits(),
+    min_major: 2,
+    min_minor: 0,
+    max_major: 4,
+    max_minor: 0,
+    file_extensions: "wrl wrz x3d x3dz x3dv x3dvz",
+};
+
+pub struct Importer;
+
+impl Importer {
+    pub fn get_info(&self) -> &ImporterDesc {
+        &DESC
+    }
+
+    /// Parses the scene graph of a file, along with its format and version
+    fn parse(text: &str) -> Result<(Graph, &'static str, String), VrmlImportError> {
+        if let Some(header) = text.strip_prefix('#') {
+            let mut words = header.lines().next().unwrap_or_default().split_whitespace();
+            return match (words.next(), words.next()) {
+                (Some("VRML"), Some("V2.0")) => {
+                    Ok((parser::parse(text)?, SOURCE_FORMAT_VRML, "2.0".to_owned()))
+                }
+                (Some("X3D"), Some(version))
+                    if version.strip_prefix('V').is_some_and(is_x3d_version) =>
+                {
+                    Ok((
+                        parser::parse(text)?,
+                        SOURCE_FORMAT_X3D,
+                        version[1..].to_owned(),
+                    ))
+                }
+                (Some("VRML" | "X3D"), version) => Err(VrmlImportError::UnsupportedVersion(
+                    version.unwrap_or_default().to_owned(),
+                )),
+                _ => Err(VrmlImportError::InvalidFormat),
+            };
+        }
+        if !is_x3d_xml(text.as_bytes()) {
+            return Err(VrmlImportError::InvalidFormat);
+        }
+        let (graph, version) = xml::parse(text)?;
+        // the version attribute is required, but often missing
+        let version = version.unwrap_or_else(|| "3.0".to_owned());
+        if !is_x3d_version(&version) {
+            return Err(VrmlImportError::UnsupportedVersion(version));
+        }
+        Ok((graph, SOURCE_FORMAT_X3D, version))
+    }
+
+    fn import(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        profiler: &mut Profiler,
+    ) -> Result<(), VrmlImportError> {
+        let buf = match buf {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => rest,
+            _ => buf,
+        };
+        let text = core::str::from_utf8(buf).map_err(|_| EncodingError::NotValidUtf8)?;
+        let (graph, format, version) =
+            profiler.measure(ProfileScope::Import, "parsing", || Self::parse(text))?;
+        *ai_scene = profiler.measure(ProfileScope::Import, "conversion", || {
+            Converter::new(&graph).convert()
+        })?;
+        let metadata = &mut ai_scene.metadata;
+        metadata.set(AI_METADATA_SOURCE_FORMAT, format);
+        metadata.set(AI_METADATA_SOURCE_FORMAT_VERSION, version);
+        CoordinateSystem::Y_UP_RIGHT_HANDED
+            .with_unit_scale_factor(CoordinateSystem::METERS)
+            .write_metadata(metadata);
+        Ok(())
+    }
+}
+
+impl FormatHeader<5> for Importer {
+    const HEADER: [u8; 5] = *b"#VRML";
+
+    /// Also accepts the classic encoding of X3D and XML files with an `X3D`
+    /// element
+    fn check_header(buf: &[u8]) -> bool {
+        let buf = match buf {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => rest,
+            _ => buf,
+        };
+        buf.starts_with(&Self::HEADER) || buf.starts_with(b"#X3D") || is_x3d_xml(buf)
+    }
+}
+
+impl InternalImporter<VrmlImportError> for Importer {
+    #[cfg(feature = "std")]
+    fn import_from_file(file_name: &str, ai_scene: &mut AiScene) -> Result<(), VrmlImportError> {
+        let buf = std::fs::read(file_name)?;
+        Self::import_from_buf(&buf, ai_scene)
+    }
+
+    fn import_from_buf(buf: &[u8], ai_scene: &mut AiScene) -> Result<(), VrmlImportError> {
+        Self::import(buf, ai_scene, &mut Profiler::disabled())
+    }
+
+    fn import_from_buf_with_profiler(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        _properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<(), VrmlImportError> {
+        Self::import(buf, ai_scene, profiler)
+    }
+}
+
+/// Whether XML data has an `X3D` element near the start, after the XML
+/// declaration and the document type
+fn is_x3d_xml(buf: &[u8]) -> bool {
+    let start = &buf[..buf.len().min(1024)];
+    start.starts_with(b"<") && start.windows(4).any(|w| w == b"<X3D")
+}
+
+fn is_x3d_version(version: &str) -> bool {
+    version.starts_with("3.") || version.starts_with("4.")
+}
+
+/// Converts the scene graph to an [`AiScene`]. Grouping nodes become
+/// nodes, the meshes of shapes are attached to the node of the group they
+/// are in.
+struct Converter<'a> {
+    graph: &'a Graph,
+    builder: SceneBuilder,
+    /// The meshes of each shape, a shape used several times is converted
+    /// once
+    shapes: HashMap<usize, Vec<u32>>,
+    /// The materials by appearance and whether the geometry is two-sided
+    materials: HashMap<(Option<usize>, bool), u32>,
+    num_nodes: usize,
+}
+
+impl<'a> Converter<'a> {
+    fn new(graph: &'a Graph) -> Self {
+        Self {
+            graph,
+            builder: SceneBuilder::new(),
+            shapes: HashMap::new(),
+            materials: HashMap::new(),
+            num_nodes: 0,
+        }
+    }
+
+    fn convert(mut self) -> Result<AiScene, VrmlImportError> {
+        let root = self.builder.add_node(ROOT_NODE_NAME, Mat4::IDENTITY, None);
+        let graph = self.graph;
+        for &node in &graph.roots {
+            self.convert_node(node, root, 0)?;
+        }
+        Ok(self.builder.build()?)
+    }
+
+    fn convert_children(
+        &mut self,
+        node: usize,
+        fields: &[&str],
+        parent: Index<AiNode>,
+        depth: usize,
+    ) -> Result<(), VrmlImportError> {
+        let graph = self.graph;
+        for field in fields {
+            for &child in graph.children(node, field) {
+                self.convert_node(child, parent, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_node(
+        &mut self,
+        node: usize,
+        transformation: Mat4,
+        parent: Index<AiNode>,
+    ) -> Index<AiNode> {
+        let graph = self.graph;
+        let name = graph.name(node).unwrap_or(graph.type_name(node));
+        self.builder.add_node(name, transformation, Some(parent))
+    }
+
+    fn convert_node(
+        &mut self,
+        node: usize,
+        parent: Index<AiNode>,
+        depth: usize,
+    ) -> Result<(), VrmlImportError> {
+        // a node used several times is converted several times, nested uses
+        // can nest deeper than the file
+        if depth > MAX_DEPTH {
+            return Err(VrmlImportError::TooDeep(MAX_DEPTH));
+        }
+        self.num_nodes += 1;
+        if self.num_nodes > MAX_NODES {
+            return Err(VrmlImportError::TooManyNodes(MAX_NODES));
+        }
+        let graph = self.graph;
+        match graph.type_name(node) {
+            "Transform" | "CADPart" => {
+                let new_node = self.add_node(node, transformation(graph, node), parent);
+                self.convert_children(node, &["children"], new_node, depth)?;
+            }
+            "Group" | "StaticGroup" | "Anchor" | "Billboard" | "Collision" | "CADAssembly"
+            | "CADLayer" => {
+                let new_node = self.add_node(node, Mat4::IDENTITY, parent);
+                self.convert_children(node, &["children"], new_node, depth)?;
+            }
+            // the shape of a face of a CAD part
+            "CADFace" => {
+                let new_node = self.add_node(node, Mat4::IDENTITY, parent);
+                self.convert_children(node, &["shape"], new_node, depth)?;
+            }
+            // only the chosen child is visible, VRML calls the children
+            // `choice`
+            "Switch" => {
+                let new_node = self.add_node(node, Mat4::IDENTITY, parent);
+                let choice = graph.number(node, "whichChoice").unwrap_or(-1.0);
+                let children = match graph.children(node, "choice") {
+                    [] => graph.children(node, "children"),
+                    children => children,
+                };
+                if choice >= 0.0
+                    && let Some(&child) = children.get(choice as usize)
+                {
+                    self.convert_node(child, new_node, depth + 1)?;
+                }
+            }
+            // the first level has the most details, VRML calls the levels
+            // `level`
+            "LOD" => {
+                let new_node = self.add_node(node, Mat4::IDENTITY, parent);
+                let levels = match graph.children(node, "level") {
+                    [] => graph.children(node, "children"),
+                    levels => levels,
+                };
+                if let Some(&level) = levels.first() {
+                    self.convert_node(level, new_node, depth + 1)?;
+                }
+            }
+            "Shape" => {
+                for mesh in self.convert_shape(node) {
+                    self.builder.attach_mesh(parent, mesh);
+                }
+            }
+            "Inline" => {
+                log::warn!(
+                    "Inline file {:?} is not loaded",
+                    graph.strings(node, "url").first()
+                );
+            }
+            // lights, viewpoints, sensors, scripts and interpolators
+            other => log::debug!("Skipping {other} node"),
+        }
+        Ok(())
+    }
+
+    fn convert_shape(&mut self, shape: usize) -> Vec<u32> {
+        if let Some(meshes) = self.shapes.get(&shape) {
+            return meshes.clone();
+        }
+        let graph = self.graph;
+        let mut meshes = Vec::new();
+        if let Some(geometry) = graph.child(shape, "geometry")
+            && let Some(mut mesh) = convert_geometry(graph, geometry)
+        {
+            if mesh.name.is_empty() {
+                mesh.name = graph.name(shape).unwrap_or_default().to_owned();
+            }
+            // geometry which is not solid is seen from both sides
+            let two_sided = graph.bool(geometry, "solid") == Some(false);
+            mesh.material_index = self.material(graph.child(shape, "appearance"), two_sided);
+            meshes.push(self.builder.add_mesh(mesh));
+        }
+        self.shapes.insert(shape, meshes.clone());
+        meshes
+    }
+
+    /// The material of an appearance, appearances used by several shapes
+    /// share it
+    fn material(&mut self, appearance: Option<usize>, two_sided: bool) -> u32 {
+        if let Some(&material) = self.materials.get(&(appearance, two_sided)) {
+            return material;
+        }
+        let graph = self.graph;
+        let mut material = AiMaterial::default();
+        let node = appearance.and_then(|appearance| graph.child(appearance, "material"));
+        let name = node
+            .and_then(|node| graph.name(node))
+            .or_else(|| appearance.and_then(|appearance| graph.name(appearance)))
+            .unwrap_or("DefaultMaterial");
+        material.add_property(AI_MATKEY_NAME, name.to_owned(), 0);
+        let color = |field, default| {
+            node.and_then(|node| graph.array::<3>(node, field))
+                .map_or(default, |[r, g, b]| {
+                    Vec3::new(r as AiReal, g as AiReal, b as AiReal)
+                })
+        };
+        let diffuse = color("diffuseColor", Vec3::splat(0.8));
+        let ambient_intensity = node
+            .and_then(|node| graph.number(node, "ambientIntensity"))
+            .unwrap_or(0.2) as AiReal;
+        let shininess = node
+            .and_then(|node| graph.number(node, "shininess"))
+            .unwrap_or(0.2) as AiReal;
+        let transparency = node
+            .and_then(|node| graph.number(node, "transparency"))
+            .unwrap_or(0.0) as AiReal;
+        // shapes without material are not lit
+        let shading = if node.is_some() {
+            AiShadingMode::Phong
+        } else {
+            AiShadingMode::NoShading
+        };
+        material.add_property(AI_MATKEY_SHADING_MODEL, shading, 0);
+        material.add_property(AI_MATKEY_COLOR_DIFFUSE, diffuse, 0);
+        material.add_property(AI_MATKEY_COLOR_AMBIENT, diffuse * ambient_intensity, 0);
+        material.add_property(
+            AI_MATKEY_COLOR_SPECULAR,
+            color("specularColor", Vec3::ZERO),
+            0,
+        );
+        material.add_property(
+            AI_MATKEY_COLOR_EMISSIVE,
+            color("emissiveColor", Vec3::ZERO),
+            0,
+        );
+        // the shininess is in [0, 1], 128 is the largest exponent of OpenGL
+        material.add_property(AI_MATKEY_SHININESS, shininess * 128.0, 0);
+        material.add_property(AI_MATKEY_OPACITY, 1.0 - transparency, 0);
+        if two_sided {
+            material.add_property(AI_MATKEY_TWOSIDED, 1, 0);
+        }
+        if let Some(texture) = appearance.and_then(|appearance| graph.child(appearance, "texture"))
+            && let Some(url) = graph.strings(texture, "url").first()
+        {
+            let map_mode = |field| match graph.bool(texture, field) {
+                Some(false) => AiTextureMapMode::Clamp,
+                _ => AiTextureMapMode::Wrap,
+            };
+            let mut slot = TextureSlot::new(url.as_str());
+            slot.map_mode = [map_mode("repeatS"), map_mode("repeatT")];
+            material.set_texture(AiTextureType::Diffuse, 0, slot);
+        }
+        let index = self.builder.add_material(material);
+        self.materials.insert((appearance, two_sided), index);
+        index
+    }
+}
+
+/// The transformation of a `Transform` node, stored row by row
+fn transformation(graph: &Graph, node: usize) -> Mat4 {
+    let vector = |field, default| {
+        graph.array::<3>(node, field).map_or(default, |[x, y, z]| {
+            Vec3::new(x as AiReal, y as AiReal, z as AiReal)
+        })
+    };
+    // rotations are given as axis and angle
+    let rotation = |field| match graph.array::<4>(node, field) {
+        Some([x, y, z, angle]) => {
+            let axis = Vec3::new(x as AiReal, y as AiReal, z as AiReal);
+            if axis.length_squared() > 0.0 {
+                Quat::from_axis_angle(axis.normalize(), angle as AiReal)
+            } else {
+                Quat::IDENTITY
+            }
+        }
+        None => Quat::IDENTITY,
+    };
+    let center = vector("center", Vec3::ZERO);
+    let scale_orientation = rotation("scaleOrientation");
+    let matrix = Mat4::from_translation(vector("translation", Vec3::ZERO) + center)
+        * Mat4::from_quat(rotation("rotation"))
+        * Mat4::from_quat(scale_orientation)
+        * Mat4::from_scale(vector("scale", Vec3::ONE))
+        * Mat4::from_quat(scale_orientation.inverse())
+        * Mat4::from_translation(-center);
+    matrix.transpose()
+}
+
+/// Converts an indexed face, triangle or line set, other geometry is not
+/// supported
+fn convert_geometry(graph: &Graph, geometry: usize) -> Option<AiMesh> {
+    let type_name = graph.type_name(geometry);
+    // the faces are the number of the face or polyline in the file, for
+    // values per face, and the positions of their corners in the index list
+    let (index_field, mut faces): (_, Vec<(usize, Vec<usize>)>) = match type_name {
+        "IndexedFaceSet" => {
+            let coord_index = graph.numbers(geometry, "coordIndex");
+            let faces = polygons(&coord_index).enumerate();
+            ("coordIndex", faces.filter(|(_, f)| f.len() >= 3).collect())
+        }
+        "IndexedTriangleSet" => {
+            let index = graph.numbers(geometry, "index");
+            let triangles = (0..index.len() / 3).map(|i| (i, (3 * i..3 * i + 3).collect()));
+            ("index", triangles.collect())
+        }
+        "IndexedLineSet" => {
+            // polylines are split into their segments
+            let coord_index = graph.numbers(geometry, "coordIndex");
+            let segments = polygons(&coord_index).enumerate().flat_map(|(i, line)| {
+                line.windows(2)
+                    .map(|segment| (i, segment.to_vec()))
+                    .collect::<Vec<_>>()
+            });
+            ("coordIndex", segments.collect())
+        }
+        _ => {
+            log::warn!("{type_name} geometry is not supported");
+            return None;
+        }
+    };
+    if graph.bool(geometry, "ccw") == Some(false) {
+        faces.iter_mut().for_each(|(_, face)| face.reverse());
+    }
+    let index = graph.numbers(geometry, index_field);
+    let points = graph
+        .child(geometry, "coord")
+        .map(|coord| vectors::<3>(graph, coord, "point"))
+        .unwrap_or_default();
+    let normals = graph
+        .child(geometry, "normal")
+        .map(|normal| vectors::<3>(graph, normal, "vector"))
+        .unwrap_or_default();
+    let tex_coords = graph
+        .child(geometry, "texCoord")
+        .map(|tex_coord| vectors::<2>(graph, tex_coord, "point"))
+        .unwrap_or_default();
+    let colors = match graph.child(geometry, "color") {
+        Some(color) if graph.type_name(color) == "ColorRGBA" => vectors::<4>(graph, color, "color")
+            .into_iter()
+            .map(|[r, g, b, a]| Color4D::new(to_f32(r), to_f32(g), to_f32(b), to_f32(a)))
+            .collect(),
+        Some(color) => vectors::<3>(graph, color, "color")
+            .into_iter()
+            .map(|[r, g, b]| Color4D::new(to_f32(r), to_f32(g), to_f32(b), 1.0))
+            .collect(),
+        None => Vec::new(),
+    };
+    let normal_index = graph.numbers(geometry, "normalIndex");
+    let color_index = graph.numbers(geometry, "colorIndex");
+    let tex_coord_index = graph.numbers(geometry, "texCoordIndex");
+    let normal_per_vertex = graph.bool(geometry, "normalPerVertex") != Some(false);
+    let color_per_vertex = graph.bool(geometry, "colorPerVertex") != Some(false);
+
+    let mut mesh = AiMesh {
+        name: graph.name(geometry).unwrap_or_default().to_owned(),
+        ..Default::default()
+    };
+    if !tex_coords.is_empty() {
+        mesh.num_of_uv_components[0] = 2;
+    }
+    // every corner gets its own vertex, like normals and colors per face
+    // need, JoinIdenticalVertices joins them again
+    for (f, face) in &faces {
+        let f = *f;
+        let Some(positions) = face
+            .iter()
+            .map(|&k| corner(&index, k).and_then(|i| points.get(i)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            log::warn!("Skipping face {f} of {type_name} with invalid indices");
+            continue;
+        };
+        let start = mesh.vertices.len() as u32;
+        mesh.primitive_type |= PrimitiveTypeFlags::for_index_count(face.len());
+        for (&k, position) in face.iter().zip(positions) {
+            mesh.vertices.push(real(*position));
+            if !normals.is_empty() {
+                let i = attribute(normal_per_vertex, &normal_index, &index, f, k);
+                let normal = i.and_then(|i| normals.get(i)).copied().unwrap_or_default();
+                mesh.normals.push(real(normal));
+            }
+            if !tex_coords.is_empty() {
+                let i = attribute(true, &tex_coord_index, &index, f, k);
+                let [u, v] = i
+                    .and_then(|i| tex_coords.get(i))
+                    .copied()
+                    .unwrap_or_default();
+                mesh.texture_coords[0].push(Vec3::new(u, v, 0.0));
+            }
+            if !colors.is_empty() {
+                let i = attribute(color_per_vertex, &color_index, &index, f, k);
+                let color = i.and_then(|i| colors.get(i)).copied().unwrap_or_default();
+                mesh.colors[0].push(color);
+            }
+        }
+        mesh.faces.push(AiFace {
+            indices: (start..mesh.vertices.len() as u32).collect(),
+        });
+    }
+    if mesh.faces.is_empty() {
+        return None;
+    }
+    mesh.aabb = AABB::from_points(&mesh.vertices);
+    Some(mesh)
+}
+
+/// Splits an index list at the `-1` separators, returns the positions of
+/// the indices of each polygon
+fn polygons(index: &[f64]) -> impl Iterator<Item = Vec<usize>> + '_ {
+    let mut start = 0;
+    index
+        .iter()
+        .enumerate()
+        .filter(|&(_, &i)| i < 0.0)
+        .map(|(end, _)| end)
+        .chain(Some(index.len()))
+        .map(move |end| {
+            let polygon = (start..end).collect::<Vec<_>>();
+            start = end + 1;
+            polygon
+        })
+        .filter(|polygon| !polygon.is_empty())
+}
+
+/// The index at position `k` of an index list
+fn corner(index: &[f64], k: usize) -> Option<usize> {
+    index.get(k).filter(|&&i| i >= 0.0).map(|&i| i as usize)
+}
+
+/// The index of the normal, color or texture coordinate of the corner at
+/// position `k` of face `f`. Values per vertex are indexed like the
+/// positions without an own index list, values per face are in the order of
+/// the faces.
+fn attribute(per_vertex: bool, own: &[f64], index: &[f64], f: usize, k: usize) -> Option<usize> {
+    match (per_vertex, own.is_empty()) {
+        (true, true) => corner(index, k),
+        (true, false) => corner(own, k),
+        (false, true) => Some(f),
+        (false, false) => corner(own, f),
+    }
+}
+
+/// The values of a field in groups of `N`
+fn vectors<const N: usize>(graph: &Graph, node: usize, field: &str) -> Vec<[AiReal; N]> {
+    graph
+        .numbers(node, field)
+        .chunks_exact(N)
+        .map(|chunk| core::array::from_fn(|i| chunk[i] as AiReal))
+        .collect()
+}
+
+fn real([x, y, z]: [AiReal; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
@@ -0,0 +1,252 @@
+pub mod errors;
+mod graph;
+pub mod importer;
+mod parser;
+mod xml;
+
+#[allow(unused)]
+mod test {
+    use std::fs;
+
+    use super::{
+        errors::{VrmlImportError, VrmlParseError},
+        importer::{Importer, ROOT_NODE_NAME, SOURCE_FORMAT_VRML, SOURCE_FORMAT_X3D},
+    };
+    use crate::{
+        core::{importer_for_data, importer_for_extension},
+        decompose, get_model_path,
+        structs::{
+            material::{
+                AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_COLOR_EMISSIVE, AI_MATKEY_TWOSIDED,
+                AiTextureMapMode, AiTextureType, GetProperty,
+            },
+            mesh::PrimitiveTypeFlags,
+            meta::{AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, MetadataExt},
+            scene::AiScene,
+        },
+        traits::importer::trait_define::{FormatValidator, InternalImporter},
+        utils::float_precision::Vec3,
+    };
+
+    fn import(source: &[u8]) -> AiScene {
+        let mut scene = AiScene::default();
+        Importer::import_from_buf(source, &mut scene).unwrap();
+        scene
+    }
+
+    const VRML: &[u8] = br#"#VRML V2.0 utf8
+# a quad and a triangle, used by two transforms
+DEF Left Transform {
+  translation -2 0 0
+  children [
+    DEF Pyramid Shape {
+      appearance Appearance {
+        material DEF Red Material { diffuseColor 1 0 0 }
+        texture ImageTexture { url [ "red.png" ] repeatS FALSE }
+      }
+      geometry IndexedFaceSet {
+        solid FALSE
+        coord Coordinate { point [ 0 0 0, 1 0 0, 1 1 0, 0 1 0, 0.5 0.5 1 ] }
+        coordIndex [ 0 1 2 3 -1, 0 1 4 -1 ]
+        color Color { color [ 1 0 0, 0 1 0 ] }
+        colorPerVertex FALSE
+      }
+    }
+  ]
+}
+DEF Right Transform {
+  translation 2 0 0
+  children [ USE Pyramid ]
+}
+ROUTE Left.translation TO Right.translation
+"#;
+
+    const X3D: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE X3D PUBLIC "ISO//Web3D//DTD X3D 3.3//EN" "https://www.web3d.org/specifications/x3d-3.3.dtd">
+<X3D profile='Interchange' version='3.3'>
+  <head><meta name='title' content='Pyramid'/></head>
+  <Scene>
+    <!-- a quad and a triangle, used by two transforms -->
+    <Transform DEF='Left' translation='-2 0 0'>
+      <Shape DEF='Pyramid'>
+        <Appearance>
+          <Material DEF='Red' diffuseColor='1 0 0'/>
+          <ImageTexture url='"red.png"' repeatS='false'/>
+        </Appearance>
+        <IndexedFaceSet solid='false' coordIndex='0 1 2 3 -1 0 1 4 -1' colorPerVertex='false'>
+          <Coordinate point='0 0 0, 1 0 0, 1 1 0, 0 1 0, 0.5 0.5 1'/>
+          <Color color='1 0 0, 0 1 0'/>
+        </IndexedFaceSet>
+      </Shape>
+    </Transform>
+    <Transform DEF='Right' translation='2 0 0'>
+      <Shape USE='Pyramid'/>
+    </Transform>
+    <ROUTE fromNode='Left' fromField='translation' toNode='Right' toField='translation'/>
+  </Scene>
+</X3D>
+"#;
+
+    fn check_pyramids(scene: &AiScene) {
+        let names = scene
+            .nodes
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [ROOT_NODE_NAME, "Left", "Right"]);
+        let (translation, _, _) = decompose(scene.nodes[2].transformation);
+        assert_eq!(translation, Vec3::new(2.0, 0.0, 0.0));
+
+        // the shape used twice is duplicated, its material is shared
+        assert_eq!(scene.meshes.len(), 2);
+        assert_eq!(scene.nodes[1].meshes, 0..1);
+        assert_eq!(scene.nodes[2].meshes, 1..2);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.vertices, scene.meshes[1].vertices);
+        assert_eq!(mesh.name, "Pyramid");
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.vertices.len(), 7);
+        assert_eq!(
+            mesh.primitive_type,
+            PrimitiveTypeFlags::TRIANGLE | PrimitiveTypeFlags::POLYGON
+        );
+        // one color per face
+        assert_eq!(mesh.colors[0][3].x, 1.0);
+        assert_eq!(mesh.colors[0][4].y, 1.0);
+
+        assert_eq!(scene.materials.len(), 1);
+        let material = &scene.materials[0];
+        assert_eq!(material.name(), Some("Red"));
+        assert_eq!(
+            material.get_property(AI_MATKEY_COLOR_DIFFUSE, 0),
+            Some(&Vec3::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(material.get_int(AI_MATKEY_TWOSIDED), Some(1));
+        let texture = material.get_texture(AiTextureType::Diffuse, 0).unwrap();
+        assert_eq!(texture.path, "red.png");
+        assert_eq!(
+            texture.map_mode,
+            [AiTextureMapMode::Clamp, AiTextureMapMode::Wrap]
+        );
+    }
+
+    #[test]
+    fn test_import_vrml() {
+        let scene = import(VRML);
+        check_pyramids(&scene);
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT),
+            Some(SOURCE_FORMAT_VRML)
+        );
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT_VERSION),
+            Some("2.0")
+        );
+    }
+
+    #[test]
+    fn test_import_x3d() {
+        let scene = import(X3D);
+        check_pyramids(&scene);
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT),
+            Some(SOURCE_FORMAT_X3D)
+        );
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT_VERSION),
+            Some("3.3")
+        );
+        let vrml = import(VRML);
+        assert_eq!(scene.meshes[0].vertices, vrml.meshes[0].vertices);
+        assert_eq!(scene.meshes[0].faces, vrml.meshes[0].faces);
+    }
+
+    #[test]
+    fn test_import_x3d_classic() {
+        let source = String::from_utf8(VRML.to_vec())
+            .unwrap()
+            .replace("#VRML V2.0 utf8", "#X3D V3.3 utf8\nPROFILE Interchange");
+        let scene = import(source.as_bytes());
+        check_pyramids(&scene);
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT_VERSION),
+            Some("3.3")
+        );
+    }
+
+    #[test]
+    fn test_import_line_set() {
+        let source = fs::read(get_model_path("X3D", "IndexedLineSet.x3d")).unwrap();
+        let scene = import(&source);
+        assert_eq!(scene.meshes.len(), 1);
+        let mesh = &scene.meshes[0];
+        // the closed polyline is split into its four segments
+        assert_eq!(mesh.faces.len(), 4);
+        assert_eq!(mesh.primitive_type, PrimitiveTypeFlags::LINE);
+        assert_eq!(
+            scene.materials[0].get_property(AI_MATKEY_COLOR_EMISSIVE, 0),
+            Some(&Vec3::new(1.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_import_wuson() {
+        let source = fs::read(get_model_path("WRL", "Wuson.wrl")).unwrap();
+        let scene = import(&source);
+        let names = scene
+            .nodes
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"OB_Mesh"));
+        assert!(names.contains(&"ME_Mesh"));
+        assert_eq!(scene.meshes.len(), 1);
+        assert!(scene.meshes[0].faces.len() > 1000);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf(b"#VRML V1.0 ascii\n", &mut scene).unwrap_err();
+        assert!(matches!(error, VrmlImportError::UnsupportedVersion(v) if v == "V1.0"));
+
+        let source = b"#VRML V2.0 utf8\nGroup {\n  children [ USE Missing ]\n}\n";
+        let error = Importer::import_from_buf(source, &mut scene).unwrap_err();
+        assert!(matches!(
+            error,
+            VrmlImportError::ParseError {
+                line: 3,
+                error: VrmlParseError::UndefinedName(_)
+            }
+        ));
+
+        let source = b"<X3D version='3.3'>\n<Scene>\n<Group></Transform>\n</Scene>\n</X3D>";
+        let error = Importer::import_from_buf(source, &mut scene).unwrap_err();
+        assert!(matches!(
+            error,
+            VrmlImportError::ParseError {
+                line: 3,
+                error: VrmlParseError::MismatchedTag { .. }
+            }
+        ));
+
+        // every use doubles the nodes
+        let mut source = String::from("#VRML V2.0 utf8\nDEF N0 Group {}\n");
+        for i in 1..32 {
+            let n = i - 1;
+            source += &format!("DEF N{i} Group {{ children [ USE N{n} USE N{n} ] }}\n");
+        }
+        let error = Importer::import_from_buf(source.as_bytes(), &mut scene).unwrap_err();
+        assert!(matches!(error, VrmlImportError::TooManyNodes(_)));
+    }
+
+    #[test]
+    fn test_registry() {
+        let desc = importer_for_extension("wrl").unwrap();
+        assert_eq!(desc.name, "VRML 2.0 and X3D Importer");
+        assert_eq!(importer_for_extension(".x3d").unwrap().name, desc.name);
+        assert!(Importer::can_read_from_buf(VRML));
+        assert!(Importer::can_read_from_buf(X3D));
+        assert_eq!(importer_for_data(X3D).unwrap().name, desc.name);
+    }
+}
@@ -0,0 +1,400 @@
+//! Parser of the classic encoding of VRML 2.0 and X3D, e.g.
+//!
+//! ```text
+//! #VRML V2.0 utf8
+//! DEF Box Transform {
+//!   translation 0 1 0
+//!   children [ Shape { geometry IndexedFaceSet { coordIndex [ 0 1 2 -1 ] } } ]
+//! }
+//! ```
+//!
+//! Nodes are parsed without knowing their fields, the type of a value
+//! follows from its tokens. Prototypes, routes and the interface
+//! declarations of scripts are skipped.
+
+use std::collections::HashMap;
+
+use super::{
+    errors::{VrmlImportError, VrmlParseError},
+    graph::{Graph, MAX_DEPTH, Node, Value, parse_number},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    String(String),
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+}
+
+impl Token<'_> {
+    fn is_number(&self) -> bool {
+        matches!(self, Token::Word(w) if w.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '.')))
+    }
+}
+
+/// Splits the text into tokens with their line numbers. Commas are white
+/// space and `#` starts a comment.
+fn lex(text: &str) -> Result<Vec<(Token<'_>, usize)>, VrmlImportError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut pos = 0;
+    while let Some(&c) = bytes.get(pos) {
+        let token = match c {
+            b'\n' => {
+                line += 1;
+                pos += 1;
+                continue;
+            }
+            c if c.is_ascii_whitespace() || c == b',' => {
+                pos += 1;
+                continue;
+            }
+            b'#' => {
+                pos += bytes[pos..]
+                    .iter()
+                    .position(|&c| c == b'\n')
+                    .unwrap_or(bytes.len() - pos);
+                continue;
+            }
+            b'{' => Token::OpenBrace,
+            b'}' => Token::CloseBrace,
+            b'[' => Token::OpenBracket,
+            b']' => Token::CloseBracket,
+            b'"' => {
+                let start = line;
+                let mut s = String::new();
+                let mut chars = text[pos + 1..].char_indices();
+                loop {
+                    let Some((i, c)) = chars.next() else {
+                        return Err(VrmlImportError::ParseError {
+                            line: start,
+                            error: VrmlParseError::UnterminatedString,
+                        });
+                    };
+                    match c {
+                        '"' => {
+                            pos += i + 2;
+                            break;
+                        }
+                        '\\' => s.extend(chars.next().map(|(_, c)| c)),
+                        '\n' => {
+                            line += 1;
+                            s.push(c);
+                        }
+                        c => s.push(c),
+                    }
+                }
+                tokens.push((Token::String(s), start));
+                continue;
+            }
+            _ => {
+                let len = bytes[pos..]
+                    .iter()
+                    .position(|&c| {
+                        c.is_ascii_whitespace()
+                            || matches!(c, b',' | b'{' | b'}' | b'[' | b']' | b'"' | b'#')
+                    })
+                    .unwrap_or(bytes.len() - pos);
+                tokens.push((Token::Word(&text[pos..pos + len]), line));
+                pos += len;
+                continue;
+            }
+        };
+        tokens.push((token, line));
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    pos: usize,
+    graph: Graph,
+    names: HashMap<&'a str, usize>,
+}
+
+/// Parses a whole file, the header line is a comment
+pub(super) fn parse(text: &str) -> Result<Graph, VrmlImportError> {
+    let mut parser = Parser {
+        tokens: lex(text)?,
+        pos: 0,
+        graph: Graph::default(),
+        names: HashMap::new(),
+    };
+    while parser.peek().is_some() {
+        if !parser.skip_statement()?
+            && let Some(node) = parser.parse_node(0)?
+        {
+            parser.graph.roots.push(node);
+        }
+    }
+    Ok(parser.graph)
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn error(&self, error: VrmlParseError) -> VrmlImportError {
+        let line = match self
+            .tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+        {
+            Some(&(_, line)) => line,
+            None => 1,
+        };
+        VrmlImportError::ParseError { line, error }
+    }
+
+    fn unexpected(&self) -> VrmlImportError {
+        self.error(match self.peek() {
+            Some(Token::Word(w)) => VrmlParseError::UnexpectedToken(w.to_string()),
+            Some(Token::String(s)) => VrmlParseError::UnexpectedToken(format!("\"{s}\"")),
+            Some(Token::OpenBrace) => VrmlParseError::UnexpectedToken("{".to_owned()),
+            Some(Token::CloseBrace) => VrmlParseError::UnexpectedToken("}".to_owned()),
+            Some(Token::OpenBracket) => VrmlParseError::UnexpectedToken("[".to_owned()),
+            Some(Token::CloseBracket) => VrmlParseError::UnexpectedToken("]".to_owned()),
+            None => VrmlParseError::UnexpectedEnd,
+        })
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, VrmlImportError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|(token, _)| token.clone())
+            .ok_or_else(|| self.error(VrmlParseError::UnexpectedEnd))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn word(&mut self) -> Result<&'a str, VrmlImportError> {
+        match self.peek() {
+            Some(&Token::Word(word)) => {
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn skip(&mut self, n: usize) -> Result<(), VrmlImportError> {
+        (0..n).try_for_each(|_| self.next().map(|_| ()))
+    }
+
+    /// Skips a token and everything up to the matching closing bracket if it
+    /// opens one
+    fn skip_balanced(&mut self) -> Result<(), VrmlImportError> {
+        let mut depth = 0usize;
+        loop {
+            match self.next()? {
+                Token::OpenBrace | Token::OpenBracket => depth += 1,
+                Token::CloseBrace | Token::CloseBracket => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips the statements which declare no nodes, returns whether there
+    /// was one
+    fn skip_statement(&mut self) -> Result<bool, VrmlImportError> {
+        let Some(&Token::Word(word)) = self.peek() else {
+            return Ok(false);
+        };
+        match word {
+            // PROTO name [ interface ] { body }, EXTERNPROTO name [ interface ] url
+            "PROTO" | "EXTERNPROTO" => {
+                self.skip(2)?;
+                self.skip_balanced()?;
+                self.skip_balanced()?;
+            }
+            // ROUTE from.event TO to.event
+            "ROUTE" => self.skip(4)?,
+            // IMPORT inline.name AS name, EXPORT name AS name
+            "IMPORT" | "EXPORT" => {
+                self.skip(2)?;
+                if self.peek() == Some(&Token::Word("AS")) {
+                    self.skip(2)?;
+                }
+            }
+            // the header statements of X3D
+            "PROFILE" | "COMPONENT" => self.skip(2)?,
+            "META" => self.skip(3)?,
+            "UNIT" => self.skip(4)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Parses `Type { fields }`, `DEF name Type { fields }`, `USE name` or
+    /// `NULL`
+    fn parse_node(&mut self, depth: usize) -> Result<Option<usize>, VrmlImportError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(VrmlParseError::TooDeep(MAX_DEPTH)));
+        }
+        let name = match self.word()? {
+            "NULL" => return Ok(None),
+            "USE" => {
+                let name = self.word()?;
+                return match self.names.get(name) {
+                    Some(&node) => Ok(Some(node)),
+                    None => Err(self.error(VrmlParseError::UndefinedName(name.to_owned()))),
+                };
+            }
+            "DEF" => Some(self.word()?),
+            _ => {
+                self.pos -= 1;
+                None
+            }
+        };
+        let type_name = self.word()?;
+        if self.next()? != Token::OpenBrace {
+            self.pos -= 1;
+            return Err(self.unexpected());
+        }
+        let mut fields = Vec::new();
+        loop {
+            if self.peek() == Some(&Token::CloseBrace) {
+                self.pos += 1;
+                break;
+            }
+            if self.skip_statement()? {
+                continue;
+            }
+            let field = self.word()?;
+            match field {
+                // interface declarations of scripts, `eventIn type name`
+                "eventIn" | "eventOut" | "inputOnly" | "outputOnly" => {
+                    self.skip(2)?;
+                    self.skip_is()?;
+                    continue;
+                }
+                // `field type name value`
+                "field" | "exposedField" | "initializeOnly" | "inputOutput" => {
+                    self.skip(1)?;
+                    let field = self.word()?;
+                    if !self.skip_is()? {
+                        let value = self.parse_value(depth)?;
+                        fields.push((field.to_owned(), value));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            if !self.skip_is()? {
+                let value = self.parse_value(depth)?;
+                fields.push((field.to_owned(), value));
+            }
+        }
+        let index = self.graph.nodes.len();
+        self.graph.nodes.push(Node {
+            type_name: type_name.to_owned(),
+            name: name.map(str::to_owned),
+            fields,
+        });
+        // names are registered after the body, so a node cannot contain
+        // itself
+        if let Some(name) = name {
+            self.names.insert(name, index);
+        }
+        Ok(Some(index))
+    }
+
+    /// Skips `IS name`, which connects a field to the interface of a
+    /// prototype
+    fn skip_is(&mut self) -> Result<bool, VrmlImportError> {
+        if self.peek() != Some(&Token::Word("IS")) {
+            return Ok(false);
+        }
+        self.skip(2)?;
+        Ok(true)
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, VrmlImportError> {
+        match self.peek() {
+            Some(Token::OpenBracket) => {
+                self.pos += 1;
+                self.parse_list(depth)
+            }
+            Some(Token::String(_)) => {
+                let Token::String(s) = self.next()? else {
+                    unreachable!()
+                };
+                Ok(Value::Strings(vec![s]))
+            }
+            Some(Token::Word("TRUE")) => {
+                self.pos += 1;
+                Ok(Value::Bool(true))
+            }
+            Some(Token::Word("FALSE")) => {
+                self.pos += 1;
+                Ok(Value::Bool(false))
+            }
+            Some(token) if token.is_number() => Ok(Value::Numbers(self.parse_numbers()?)),
+            Some(Token::Word(_)) => Ok(Value::Nodes(
+                self.parse_node(depth + 1)?.into_iter().collect(),
+            )),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_numbers(&mut self) -> Result<Vec<f64>, VrmlImportError> {
+        let mut numbers = Vec::new();
+        while let Some(&Token::Word(word)) = self.peek() {
+            if !Token::Word(word).is_number() {
+                break;
+            }
+            let number = parse_number(word)
+                .ok_or_else(|| self.error(VrmlParseError::UnexpectedToken(word.to_owned())))?;
+            numbers.push(number);
+            self.pos += 1;
+        }
+        Ok(numbers)
+    }
+
+    /// Parses the elements of `[ ... ]` after the opening bracket. The
+    /// type of the list follows from the first element.
+    fn parse_list(&mut self, depth: usize) -> Result<Value, VrmlImportError> {
+        let value = match self.peek() {
+            Some(Token::String(_)) => {
+                let mut strings = Vec::new();
+                while let Some(Token::String(_)) = self.peek() {
+                    let Token::String(s) = self.next()? else {
+                        unreachable!()
+                    };
+                    strings.push(s);
+                }
+                Value::Strings(strings)
+            }
+            Some(Token::Word("TRUE" | "FALSE")) => {
+                let mut numbers = Vec::new();
+                while let Some(&Token::Word(word @ ("TRUE" | "FALSE"))) = self.peek() {
+                    numbers.push(if word == "TRUE" { 1.0 } else { 0.0 });
+                    self.pos += 1;
+                }
+                Value::Numbers(numbers)
+            }
+            Some(token) if token.is_number() => Value::Numbers(self.parse_numbers()?),
+            _ => {
+                let mut nodes = Vec::new();
+                while let Some(Token::Word(_)) = self.peek() {
+                    nodes.extend(self.parse_node(depth + 1)?);
+                }
+                Value::Nodes(nodes)
+            }
+        };
+        if self.next()? != Token::CloseBracket {
+            self.pos -= 1;
+            return Err(self.unexpected());
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,433 @@
+//! Parser of the XML encoding of X3D, e.g.
+//!
+//! ```text
+//! <X3D version="3.3">
+//!   <Scene>
+//!     <Transform DEF="Box" translation="0 1 0">
+//!       <Shape><IndexedFaceSet coordIndex="0 1 2 -1">...</IndexedFaceSet></Shape>
+//!     </Transform>
+//!   </Scene>
+//! </X3D>
+//! ```
+//!
+//! Attributes are stored as text and parsed when the importer reads them.
+//! Child elements are stored in the field named by their `containerField`
+//! attribute, or the default field of their type. Prototypes and routes are
+//! skipped.
+
+use std::collections::HashMap;
+
+use super::{
+    errors::{VrmlImportError, VrmlParseError},
+    graph::{Graph, MAX_DEPTH, Node, Value},
+};
+
+/// Elements which only structure the file, their children are parsed as if
+/// they were at the top level
+const TRANSPARENT: &[&str] = &["X3D", "Scene"];
+
+/// Elements which are skipped with all their children
+const SKIPPED: &[&str] = &[
+    "head",
+    "ProtoDeclare",
+    "ExternProtoDeclare",
+    "ProtoInstance",
+    "ROUTE",
+    "IMPORT",
+    "EXPORT",
+];
+
+/// The field which holds a child element without `containerField`
+fn default_container_field(type_name: &str) -> &'static str {
+    match type_name {
+        "Appearance" => "appearance",
+        "Material" | "TwoSidedMaterial" | "PhysicalMaterial" => "material",
+        "ImageTexture" | "PixelTexture" | "MovieTexture" | "MultiTexture" => "texture",
+        "TextureTransform" => "textureTransform",
+        "Coordinate" | "CoordinateDouble" => "coord",
+        "Normal" => "normal",
+        "Color" | "ColorRGBA" => "color",
+        "TextureCoordinate" | "MultiTextureCoordinate" => "texCoord",
+        "IndexedFaceSet" | "IndexedTriangleSet" | "IndexedLineSet" | "TriangleSet" | "Box"
+        | "Sphere" | "Cone" | "Cylinder" | "ElevationGrid" | "Extrusion" | "PointSet" => "geometry",
+        "MetadataBoolean" | "MetadataDouble" | "MetadataFloat" | "MetadataInteger"
+        | "MetadataSet" | "MetadataString" => "metadata",
+        _ => "children",
+    }
+}
+
+struct Element<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, String)>,
+    /// `<name/>` without children
+    empty: bool,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    graph: Graph,
+    names: HashMap<String, usize>,
+    /// The `version` attribute of the `X3D` element
+    version: Option<String>,
+}
+
+/// Parses a whole X3D file, returns the scene graph and the version of X3D
+pub(super) fn parse(text: &str) -> Result<(Graph, Option<String>), VrmlImportError> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        graph: Graph::default(),
+        names: HashMap::new(),
+        version: None,
+    };
+    let mut fields = Vec::new();
+    parser.parse_children(None, 0, &mut fields)?;
+    // at the top level all nodes are roots, whatever their container
+    parser.graph.roots = fields.into_iter().flat_map(|(_, nodes)| nodes).collect();
+    Ok((parser.graph, parser.version))
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn error(&self, error: VrmlParseError) -> VrmlImportError {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        VrmlImportError::ParseError { line, error }
+    }
+
+    /// Moves behind the next occurrence of `end`
+    fn skip_past(&mut self, end: &str) -> Result<(), VrmlImportError> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.pos += i + end.len();
+                Ok(())
+            }
+            None => {
+                self.pos = self.text.len();
+                Err(self.error(VrmlParseError::UnexpectedEnd))
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> Result<&'a str, VrmlImportError> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.unexpected());
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn unexpected(&self) -> VrmlImportError {
+        match self.rest().chars().next() {
+            Some(c) => self.error(VrmlParseError::UnexpectedToken(c.to_string())),
+            None => self.error(VrmlParseError::UnexpectedEnd),
+        }
+    }
+
+    /// Parses the elements up to the closing tag of `parent`, or the end of
+    /// the file at the top level. The nodes are added to `fields` by the
+    /// field they are stored in.
+    fn parse_children(
+        &mut self,
+        parent: Option<&str>,
+        depth: usize,
+        fields: &mut Vec<(&'static str, Vec<usize>)>,
+    ) -> Result<(), VrmlImportError> {
+        loop {
+            // text between elements carries no data in X3D
+            match self.rest().find('<') {
+                Some(i) => self.pos += i,
+                None if parent.is_none() => return Ok(()),
+                None => {
+                    self.pos = self.text.len();
+                    return Err(self.error(VrmlParseError::UnexpectedEnd));
+                }
+            }
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!") {
+                // DOCTYPE, which may have an internal subset in brackets
+                match rest.find(['[', '>']) {
+                    Some(i) if rest.as_bytes()[i] == b'[' => self.skip_past("]>")?,
+                    _ => self.skip_past(">")?,
+                }
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let found = self.name()?;
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.unexpected());
+                }
+                self.pos += 1;
+                return match parent {
+                    Some(expected) if expected == found => Ok(()),
+                    Some(expected) => Err(self.error(VrmlParseError::MismatchedTag {
+                        expected: expected.to_owned(),
+                        found: found.to_owned(),
+                    })),
+                    None => Err(self.error(VrmlParseError::UnexpectedToken(format!("</{found}>")))),
+                };
+            } else {
+                self.parse_element(depth, fields)?;
+            }
+        }
+    }
+
+    /// Parses `<name attribute="value" ...>` or `<name ... />`
+    fn parse_tag(&mut self) -> Result<Element<'a>, VrmlImportError> {
+        self.pos += 1;
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(Element {
+                    name,
+                    attributes,
+                    empty: true,
+                });
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                return Ok(Element {
+                    name,
+                    attributes,
+                    empty: false,
+                });
+            }
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.unexpected());
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let Some(quote) = self
+                .rest()
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+            else {
+                return Err(self.unexpected());
+            };
+            let value = &self.rest()[1..];
+            let Some(len) = value.find(quote) else {
+                return Err(self.error(VrmlParseError::UnterminatedString));
+            };
+            attributes.push((attribute, unescape(&value[..len])));
+            self.pos += len + 2;
+        }
+    }
+
+    fn parse_element(
+        &mut self,
+        depth: usize,
+        fields: &mut Vec<(&'static str, Vec<usize>)>,
+    ) -> Result<(), VrmlImportError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(VrmlParseError::TooDeep(MAX_DEPTH)));
+        }
+        let element = self.parse_tag()?;
+        if SKIPPED.contains(&element.name) {
+            if !element.empty {
+                self.skip_element(element.name, depth)?;
+            }
+            return Ok(());
+        }
+        if TRANSPARENT.contains(&element.name) {
+            if element.name == "X3D" {
+                self.version = element
+                    .attributes
+                    .into_iter()
+                    .find_map(|(attribute, value)| (attribute == "version").then_some(value));
+            }
+            if !element.empty {
+                self.parse_children(Some(element.name), depth, fields)?;
+            }
+            return Ok(());
+        }
+
+        let mut name = None;
+        let mut used = None;
+        let mut container = None;
+        let mut node_fields = Vec::new();
+        for (attribute, value) in element.attributes {
+            match attribute {
+                "DEF" => name = Some(value),
+                "USE" => used = Some(value),
+                "containerField" => container = Some(value),
+                _ => node_fields.push((attribute.to_owned(), Value::Text(value))),
+            }
+        }
+        let mut children = Vec::new();
+        if !element.empty {
+            self.parse_children(Some(element.name), depth + 1, &mut children)?;
+        }
+
+        let index = match used {
+            Some(used) => match self.names.get(&used) {
+                Some(&node) => node,
+                None => return Err(self.error(VrmlParseError::UndefinedName(used))),
+            },
+            None => {
+                node_fields.extend(
+                    children
+                        .into_iter()
+                        .map(|(field, nodes)| (field.to_owned(), Value::Nodes(nodes))),
+                );
+                let index = self.graph.nodes.len();
+                self.graph.nodes.push(Node {
+                    type_name: element.name.to_owned(),
+                    name: name.clone(),
+                    fields: node_fields,
+                });
+                // names are registered after the children, so a node cannot
+                // contain itself
+                if let Some(name) = name {
+                    self.names.insert(name, index);
+                }
+                index
+            }
+        };
+
+        let field = match container.as_deref() {
+            Some(container) => container_field(container),
+            None => default_container_field(element.name),
+        };
+        match fields.iter_mut().find(|(name, _)| *name == field) {
+            Some((_, nodes)) => nodes.push(index),
+            None => fields.push((field, vec![index])),
+        }
+        Ok(())
+    }
+
+    /// Skips the children and the closing tag of an element, which may
+    /// contain elements of the same name
+    fn skip_element(&mut self, name: &str, depth: usize) -> Result<(), VrmlImportError> {
+        let mut ignored = Vec::new();
+        let mut depth = depth;
+        loop {
+            if depth > MAX_DEPTH {
+                return Err(self.error(VrmlParseError::TooDeep(MAX_DEPTH)));
+            }
+            let Some(i) = self.rest().find('<') else {
+                self.pos = self.text.len();
+                return Err(self.error(VrmlParseError::UnexpectedEnd));
+            };
+            self.pos += i;
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if rest.starts_with("<?") || rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let found = self.name()?;
+                self.skip_past(">")?;
+                match ignored.pop() {
+                    Some(_) => depth -= 1,
+                    None if found == name => return Ok(()),
+                    None => {
+                        return Err(self.error(VrmlParseError::MismatchedTag {
+                            expected: name.to_owned(),
+                            found: found.to_owned(),
+                        }));
+                    }
+                }
+            } else {
+                let element = self.parse_tag()?;
+                if !element.empty {
+                    ignored.push(element.name);
+                    depth += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Maps the `containerField` of an element to a field name with static
+/// lifetime, unknown fields are children
+fn container_field(container: &str) -> &'static str {
+    const FIELDS: &[&str] = &[
+        "appearance",
+        "material",
+        "texture",
+        "textureTransform",
+        "coord",
+        "normal",
+        "color",
+        "texCoord",
+        "geometry",
+        "metadata",
+        "children",
+        "backMaterial",
+    ];
+    FIELDS
+        .iter()
+        .copied()
+        .find(|&field| field == container)
+        .unwrap_or("children")
+}
+
+/// Replaces the predefined entities and character references
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut s = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        s.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                s.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                s.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    s.push_str(rest);
+    s
+}
@@ -30,10 +30,13 @@ pub enum XFileImportError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
-    #[error("{position}: {error}")]
+    /// The snippet shows the offending source line with a caret under the
+    /// position, it is only available for text files.
+    #[error("{position}: {error}{}", .snippet.as_deref().unwrap_or_default())]
     XFileParseError {
         position: String,
         error: XFileParseError,
+        snippet: Option<String>,
     },
 }
 
@@ -58,6 +61,10 @@ pub enum XFileExportError {
 
     #[error("Write error: {0}")]
     WriteError(#[from] std::fmt::Error),
+
+    #[cfg(feature = "compression")]
+    #[error("Compression error: {0}")]
+    CompressionError(#[from] CompressionError),
 }
 
 #[derive(Debug, Error)]
@@ -130,7 +137,16 @@ pub enum XFileParseError {
     #[error("Not enough data to read {0} bytes")]
     NotEnoughDataToRead(usize),
 
+    #[error("Non-standard number literal {0}")]
+    NonStandardNumberLiteral(String),
+
+    #[error("Invalid declaration of template {name}: {reason}")]
+    InvalidTemplate { name: String, reason: &'static str },
+
     // Mesh validation errors
+    #[error("Vertex index {index} out of range, the mesh has {count} vertices")]
+    VertexIndexOutOfRange { index: u32, count: u32 },
+
     #[error("Unknown data object in mesh")]
     UnknownDataObject,
 
@@ -158,6 +174,9 @@ pub enum XFileParseError {
     #[error("Per-face material index count does not match face count")]
     MaterialIndexCountMismatch,
 
+    #[error("Vertex duplication index count does not match vertex count")]
+    DuplicationIndexCountMismatch,
+
     #[error("Texture coord count does not match vertex count")]
     TextureCoordCountDoesNotMatchVertexCount,
 
@@ -167,6 +186,14 @@ pub enum XFileParseError {
     #[error("Per-face material index count does not match face count")]
     PerFaceMaterialIndexCountDoesNotMatchFaceCount,
 
+    #[error("Unknown vertex element type {0}")]
+    UnknownVertexElementType(u32),
+
+    #[error(
+        "Vertex declaration data count does not match vertex count, expected {expected} DWORDs but got {found}"
+    )]
+    VertexDeclarationDataCountMismatch { expected: usize, found: usize },
+
     #[error(
         "Invalid number of arguments for {key_type} key in animation, expected {expected} but got {found}"
     )]
@@ -226,6 +253,17 @@ impl XFileParseError {
         }
     }
 
+    /// Whether the error was caused by running out of data
+    #[inline]
+    pub fn is_end_of_file(&self) -> bool {
+        matches!(
+            self,
+            Self::UnexpectedEndOfFile { .. }
+                | Self::NotEnoughDataToRead(_)
+                | Self::TextParseError(XFileTextParseError::UnterminatedString)
+        )
+    }
+
     /// Create an InvalidAnimationKeyArgs error
     #[inline]
     pub fn invalid_animation_key_args(key_type: &'static str) -> Self {
@@ -240,6 +278,10 @@ pub enum XFileTextParseError {
 
     #[error("Not enough data to read 4 bytes")]
     ReadBinaryDwordError,
+
+    /// The file ends before the closing quote of a string
+    #[error("Unterminated string")]
+    UnterminatedString,
 }
 
 #[derive(Debug, Error)]
@@ -249,4 +291,7 @@ pub enum XFileBinaryParseError {
 
     #[error("Not enough data to read 4 bytes")]
     ReadBinaryDwordError,
+
+    #[error("Unknown token {token:#x} at offset {offset:X}")]
+    UnknownToken { token: u16, offset: usize },
 }
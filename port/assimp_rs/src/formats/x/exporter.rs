@@ -7,13 +7,87 @@ use core::{
 use crate::{
     formats::{Level, x::errors::XFileExportError},
     structs::{
-        exporter::ExportProperties,
-        material::AiStringPropertyType,
+        camera::AiCamera,
+        exporter::{
+            AI_CONFIG_EXPORT_X_COLLAPSE_ROOT, AI_CONFIG_EXPORT_X_LIGHTS_CAMERAS,
+            AI_CONFIG_EXPORT_X_NORMALS, AI_CONFIG_EXPORT_X_PATH_ENCODING,
+            AI_CONFIG_EXPORT_X_PRECISION, AI_CONFIG_EXPORT_X_TEXCOORDS,
+            AI_CONFIG_EXPORT_X_VERTEX_COLORS, AI_CONFIG_EXPORT_XFILE_64BIT, ExportProperties,
+        },
+        exporter_desc::ExporterDesc,
+        light::{AiLight, LightType},
+        material::AiTextureType,
         mesh::AiMesh,
+        names::NameRules,
         scene::{AiNode, AiScene},
     },
+    traits::exporter::trait_define,
     utils::float_precision::{Mat4, PRECISION},
 };
+#[cfg(feature = "compression")]
+use zlib_rs::MAX_WBITS;
+
+#[cfg(feature = "compression")]
+use crate::{
+    structs::exporter::AI_CONFIG_EXPORT_X_COMPRESS,
+    utils::compression::{DEFAULT_COMPRESSION_LEVEL, compress, error::CompressionError},
+};
+
+/// Uncompressed size of the blocks in compressed files
+#[cfg(feature = "compression")]
+const MSZIP_BLOCK: usize = 32768;
+
+pub(crate) static DESC: ExporterDesc = ExporterDesc {
+    id: "x",
+    description: "X Files",
+    file_extension: "x",
+};
+
+/// Encoding of the texture paths, see [`AI_CONFIG_EXPORT_X_PATH_ENCODING`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathEncoding {
+    /// ISO-8859-1, characters outside of it are replaced by `?`
+    #[default]
+    Latin1 = 0,
+    /// UTF-8, the paths are written as they are
+    Utf8 = 1,
+}
+
+impl PathEncoding {
+    fn from_properties(properties: &ExportProperties) -> Self {
+        match properties.get_int(AI_CONFIG_EXPORT_X_PATH_ENCODING) {
+            1 => Self::Utf8,
+            _ => Self::Latin1,
+        }
+    }
+}
+
+/// What is written and how, read from the [`ExportProperties`]
+struct WriteOptions {
+    /// Digits after the decimal point
+    precision: usize,
+    normals: bool,
+    texture_coords: bool,
+    vertex_colors: bool,
+    collapse_root: bool,
+    path_encoding: PathEncoding,
+    lights_cameras: bool,
+}
+
+impl WriteOptions {
+    fn new(properties: &ExportProperties) -> Self {
+        let precision = properties.get_int_or(AI_CONFIG_EXPORT_X_PRECISION, PRECISION as i32);
+        Self {
+            precision: precision.max(0) as usize,
+            normals: properties.get_bool_or(AI_CONFIG_EXPORT_X_NORMALS, true),
+            texture_coords: properties.get_bool_or(AI_CONFIG_EXPORT_X_TEXCOORDS, true),
+            vertex_colors: properties.get_bool_or(AI_CONFIG_EXPORT_X_VERTEX_COLORS, true),
+            collapse_root: properties.get_bool(AI_CONFIG_EXPORT_X_COLLAPSE_ROOT),
+            path_encoding: PathEncoding::from_properties(properties),
+            lights_cameras: properties.get_bool(AI_CONFIG_EXPORT_X_LIGHTS_CAMERAS),
+        }
+    }
+}
 
 pub struct Exporter<'source> {
     properties: &'source ExportProperties,
@@ -40,30 +114,81 @@ impl<'source> Exporter<'source> {
         Self { scene, properties }
     }
 
+    pub fn get_info(&self) -> &ExporterDesc {
+        &DESC
+    }
+
     pub fn write_to_stream(&self, stream: &mut impl Write) -> Result<(), XFileExportError> {
+        let options = WriteOptions::new(self.properties);
         self.write_header(stream)?;
-        let level = Level(1);
-        _writeln!(stream, "Frame DXCC_ROOT {{");
-        _write!(stream, "{}", XFileMat4Wrapper(&Mat4::IDENTITY, level));
-
-        _write!(
-            stream,
-            "{}",
-            XFileNodeWrapper(
-                &self.scene.root.unwrap().get(&self.scene.nodes).unwrap(),
-                &self.scene.nodes,
-                &self.scene,
-                level
-            )
+        let root = XFileNodeWrapper(
+            self.scene.root.unwrap().get(&self.scene.nodes).unwrap(),
+            self.scene,
+            &options,
+            Level(options.collapse_root as usize),
         );
-
-        _writeln!(stream, "}}");
+        if options.collapse_root {
+            _write!(stream, "{root}");
+        } else {
+            let level = Level(1);
+            _writeln!(stream, "Frame DXCC_ROOT {{");
+            _write!(
+                stream,
+                "{}",
+                XFileMat4Wrapper(&Mat4::IDENTITY, &options, level)
+            );
+            _write!(stream, "{root}");
+            _writeln!(stream, "}}");
+        }
+        if options.lights_cameras {
+            // lights and cameras without a node of the same name
+            let has_node = |name: &str| self.scene.nodes.iter().any(|node| node.name == name);
+            let level = Level(0);
+            for light in self.scene.lights.iter().filter(|l| !has_node(&l.name)) {
+                _write!(stream, "{}", XFileLightWrapper(light, &options, level));
+            }
+            for camera in self.scene.cameras.iter().filter(|c| !has_node(&c.name)) {
+                _write!(stream, "{}", XFileCameraWrapper(camera, &options, level));
+            }
+        }
         Ok(())
     }
 
+    /// Writes the scene as compressed text file (`tzip`). The text is split
+    /// into blocks which are deflated separately, the MSZIP layout the
+    /// DirectX SDK reads:
+    ///
+    /// ```text
+    /// xof 0303tzip0032
+    /// u32 size of the uncompressed file, including the header
+    /// per block:
+    ///     u16 uncompressed size
+    ///     u16 compressed size + 2
+    ///     'CK'
+    ///     raw deflate data
+    /// ```
+    ///
+    /// The text is compressed block by block while it is written, only the
+    /// compressed file is kept in memory.
+    #[cfg(feature = "compression")]
+    pub fn write_compressed(&self, output: &mut Vec<u8>) -> Result<(), XFileExportError> {
+        let mut writer = MsZipWriter {
+            start: output.len(),
+            output,
+            block: Vec::with_capacity(MSZIP_BLOCK),
+            header_written: false,
+            size: 0,
+            error: None,
+            latin1: PathEncoding::from_properties(self.properties) == PathEncoding::Latin1,
+        };
+        let result = self.write_to_stream(&mut writer);
+        writer.finish()?;
+        result
+    }
+
     /// Writes the asset header
     pub(crate) fn write_header(&self, stream: &mut impl Write) -> Result<(), XFileExportError> {
-        let is_64_bits = self.properties.get_bool("AI_CONFIG_EXPORT_XFILE_64BIT");
+        let is_64_bits = self.properties.get_bool(AI_CONFIG_EXPORT_XFILE_64BIT);
         if is_64_bits {
             _writeln!(stream, "xof 0303txt 0064");
         } else {
@@ -188,15 +313,172 @@ impl<'source> Exporter<'source> {
         _writeln!(stream, "}}");
         _writeln!(stream);
 
+        if self.properties.get_bool(AI_CONFIG_EXPORT_X_LIGHTS_CAMERAS) {
+            // not part of the DirectX SDK, laid out like D3DLIGHT9
+            _writeln!(stream, "template Light {{");
+            _writeln!(stream, "{level}<a8a8e5c0-6d1b-4c4e-9c33-6f0e1d2c7a41>");
+            _writeln!(stream, "{level}DWORD type;");
+            _writeln!(stream, "{level}ColorRGBA diffuse;");
+            _writeln!(stream, "{level}ColorRGBA specular;");
+            _writeln!(stream, "{level}ColorRGBA ambient;");
+            _writeln!(stream, "{level}Vector position;");
+            _writeln!(stream, "{level}Vector direction;");
+            _writeln!(stream, "{level}FLOAT range;");
+            _writeln!(stream, "{level}FLOAT falloff;");
+            _writeln!(stream, "{level}FLOAT attenuation0;");
+            _writeln!(stream, "{level}FLOAT attenuation1;");
+            _writeln!(stream, "{level}FLOAT attenuation2;");
+            _writeln!(stream, "{level}FLOAT theta;");
+            _writeln!(stream, "{level}FLOAT phi;");
+            _writeln!(stream, "}}");
+            _writeln!(stream);
+
+            _writeln!(stream, "template Camera {{");
+            _writeln!(stream, "{level}<b4f1b5e2-3a7d-4f0b-8e2a-1d9c6e5f4a37>");
+            _writeln!(stream, "{level}Vector position;");
+            _writeln!(stream, "{level}Vector up;");
+            _writeln!(stream, "{level}Vector lookAt;");
+            _writeln!(stream, "{level}FLOAT horizontalFov;");
+            _writeln!(stream, "{level}FLOAT nearPlane;");
+            _writeln!(stream, "{level}FLOAT farPlane;");
+            _writeln!(stream, "{level}FLOAT aspect;");
+            _writeln!(stream, "}}");
+            _writeln!(stream);
+        }
+
+        Ok(())
+    }
+}
+
+/// Compresses the text of [`Exporter::write_compressed`] block by block
+#[cfg(feature = "compression")]
+struct MsZipWriter<'a> {
+    output: &'a mut Vec<u8>,
+    /// Offset of the file in the output
+    start: usize,
+    /// The header until it is written, then the current block
+    block: Vec<u8>,
+    header_written: bool,
+    /// Size of the uncompressed file
+    size: usize,
+    error: Option<CompressionError>,
+    /// Write the text as Latin-1 instead of UTF-8
+    latin1: bool,
+}
+
+#[cfg(feature = "compression")]
+impl MsZipWriter<'_> {
+    const HEADER_SIZE: usize = 16;
+
+    fn write_block(&mut self) -> Result<(), CompressionError> {
+        let output = &mut *self.output;
+        if self.header_written {
+            output.extend_from_slice(&(self.block.len() as u16).to_le_bytes());
+            let size_offset = output.len();
+            output.extend_from_slice(&[0, 0]);
+            output.extend_from_slice(b"CK");
+            let size = compress(&self.block, DEFAULT_COMPRESSION_LEVEL, -MAX_WBITS, output)?;
+            output[size_offset..size_offset + 2].copy_from_slice(&(size as u16 + 2).to_le_bytes());
+        } else {
+            output.extend_from_slice(&self.block[..8]);
+            output.extend_from_slice(b"tzip");
+            output.extend_from_slice(&self.block[12..]);
+            // the size is known at the end
+            output.extend_from_slice(&[0; 4]);
+            self.header_written = true;
+        }
+        self.block.clear();
+        Ok(())
+    }
+
+    /// Compresses the last block and writes the size of the file
+    fn finish(mut self) -> Result<(), XFileExportError> {
+        if let Some(error) = self.error {
+            return Err(error.into());
+        }
+        if !self.block.is_empty() {
+            self.write_block()?;
+        }
+        let offset = self.start + Self::HEADER_SIZE;
+        self.output[offset..offset + 4].copy_from_slice(&(self.size as u32).to_le_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Write for MsZipWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let encoded = if self.latin1 {
+            trait_define::latin1_bytes(s)
+        } else {
+            s.as_bytes().into()
+        };
+        let mut bytes = &*encoded;
+        self.size += bytes.len();
+        while !bytes.is_empty() {
+            let limit = if self.header_written {
+                MSZIP_BLOCK
+            } else {
+                Self::HEADER_SIZE
+            };
+            let len = (limit - self.block.len()).min(bytes.len());
+            self.block.extend_from_slice(&bytes[..len]);
+            bytes = &bytes[len..];
+            if self.block.len() == limit {
+                self.write_block().map_err(|e| {
+                    self.error = Some(e);
+                    core::fmt::Error
+                })?;
+            }
+        }
         Ok(())
     }
 }
 
-struct XFileNodeWrapper<'a>(&'a AiNode, &'a Vec<AiNode>, &'a AiScene, Level);
+impl trait_define::Exporter<XFileExportError> for Exporter<'_> {
+    fn desc() -> &'static ExporterDesc {
+        &DESC
+    }
+
+    #[cfg(feature = "std")]
+    fn export_to_writer<W: std::io::Write>(
+        scene: &AiScene,
+        properties: &ExportProperties,
+        writer: &mut W,
+    ) -> Result<(), XFileExportError> {
+        #[cfg(feature = "compression")]
+        if properties.get_bool(AI_CONFIG_EXPORT_X_COMPRESS) {
+            let mut data = Vec::new();
+            Exporter::new(scene, properties).write_compressed(&mut data)?;
+            writer.write_all(&data)?;
+            return Ok(());
+        }
+        let latin1 = PathEncoding::from_properties(properties) == PathEncoding::Latin1;
+        let mut adapter = trait_define::IoAdapter::new(writer).with_latin1(latin1);
+        let result = Exporter::new(scene, properties).write_to_stream(&mut adapter);
+        // a failing writer makes the exporter fail with `fmt::Error`, its own
+        // error is more useful
+        adapter.finish()?;
+        result
+    }
+
+    /// The text is always UTF-8, [`PathEncoding::Latin1`] only restricts the
+    /// characters of the paths
+    fn export_to_string(
+        scene: &AiScene,
+        properties: &ExportProperties,
+    ) -> Result<String, XFileExportError> {
+        let mut text = String::new();
+        Exporter::new(scene, properties).write_to_stream(&mut text)?;
+        Ok(text)
+    }
+}
+
+struct XFileNodeWrapper<'a>(&'a AiNode, &'a AiScene, &'a WriteOptions, Level);
 
 impl<'a> Display for XFileNodeWrapper<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        let XFileNodeWrapper(node, nodes, scene, level) = self;
+        let XFileNodeWrapper(node, scene, options, level) = self;
         let mut level = *level;
         if node.name.is_empty() {
             writeln!(
@@ -208,10 +490,22 @@ impl<'a> Display for XFileNodeWrapper<'a> {
             writeln!(f, "{level}Frame {} {{", XFileStringWrapper(&node.name))?;
         }
         level = level.next();
-        write!(f, "{}", XFileMat4Wrapper(&node.transformation, level))?;
+        write!(
+            f,
+            "{}",
+            XFileMat4Wrapper(&node.transformation, options, level)
+        )?;
         let Range { start, end } = node.meshes;
         for mesh in &scene.meshes[start as usize..end as usize] {
-            write!(f, "{}", XFileAiMeshWrapper(scene, mesh, level))?;
+            write!(f, "{}", XFileAiMeshWrapper(scene, mesh, options, level))?;
+        }
+        if options.lights_cameras {
+            for light in scene.lights.iter().filter(|l| l.name == node.name) {
+                write!(f, "{}", XFileLightWrapper(light, options, level))?;
+            }
+            for camera in scene.cameras.iter().filter(|c| *c.name == node.name) {
+                write!(f, "{}", XFileCameraWrapper(camera, options, level))?;
+            }
         }
 
         // recursive call the Nodes
@@ -219,7 +513,7 @@ impl<'a> Display for XFileNodeWrapper<'a> {
             write!(
                 f,
                 "{}",
-                XFileNodeWrapper(i.get(nodes).unwrap(), nodes, scene, level)
+                XFileNodeWrapper(i.get(&scene.nodes).unwrap(), scene, options, level)
             )?;
         }
 
@@ -229,33 +523,34 @@ impl<'a> Display for XFileNodeWrapper<'a> {
         Ok(())
     }
 }
-struct XFileMat4Wrapper<'a>(&'a Mat4, Level);
+struct XFileMat4Wrapper<'a>(&'a Mat4, &'a WriteOptions, Level);
 
 impl<'a> Display for XFileMat4Wrapper<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        let XFileMat4Wrapper(m, level) = self;
+        let XFileMat4Wrapper(m, options, level) = self;
+        let precision = options.precision;
         let mut level = *level;
         writeln!(f, "{}FrameTransformMatrix {{", level)?;
         level = level.next();
-        write!(f, "{level}{:.*}, ", PRECISION, m.x_axis.x)?;
-        write!(f, "{:.*}, ", PRECISION, m.y_axis.x)?;
-        write!(f, "{:.*}, ", PRECISION, m.z_axis.x)?;
-        writeln!(f, "{:.*},", PRECISION, m.w_axis.x)?;
-
-        write!(f, "{level}{:.*}, ", PRECISION, m.x_axis.y)?;
-        write!(f, "{:.*}, ", PRECISION, m.y_axis.y)?;
-        write!(f, "{:.*}, ", PRECISION, m.z_axis.y)?;
-        writeln!(f, "{:.*},", PRECISION, m.w_axis.y)?;
-
-        write!(f, "{level}{:.*}, ", PRECISION, m.x_axis.z)?;
-        write!(f, "{:.*}, ", PRECISION, m.y_axis.z)?;
-        write!(f, "{:.*}, ", PRECISION, m.z_axis.z)?;
-        writeln!(f, "{:.*},", PRECISION, m.w_axis.z)?;
-
-        write!(f, "{level}{:.*}, ", PRECISION, m.x_axis.w)?;
-        write!(f, "{:.*}, ", PRECISION, m.y_axis.w)?;
-        write!(f, "{:.*}, ", PRECISION, m.z_axis.w)?;
-        writeln!(f, "{:.*};;", PRECISION, m.w_axis.w)?;
+        write!(f, "{level}{:.*}, ", precision, m.x_axis.x)?;
+        write!(f, "{:.*}, ", precision, m.y_axis.x)?;
+        write!(f, "{:.*}, ", precision, m.z_axis.x)?;
+        writeln!(f, "{:.*},", precision, m.w_axis.x)?;
+
+        write!(f, "{level}{:.*}, ", precision, m.x_axis.y)?;
+        write!(f, "{:.*}, ", precision, m.y_axis.y)?;
+        write!(f, "{:.*}, ", precision, m.z_axis.y)?;
+        writeln!(f, "{:.*},", precision, m.w_axis.y)?;
+
+        write!(f, "{level}{:.*}, ", precision, m.x_axis.z)?;
+        write!(f, "{:.*}, ", precision, m.y_axis.z)?;
+        write!(f, "{:.*}, ", precision, m.z_axis.z)?;
+        writeln!(f, "{:.*},", precision, m.w_axis.z)?;
+
+        write!(f, "{level}{:.*}, ", precision, m.x_axis.w)?;
+        write!(f, "{:.*}, ", precision, m.y_axis.w)?;
+        write!(f, "{:.*}, ", precision, m.z_axis.w)?;
+        writeln!(f, "{:.*};;", precision, m.w_axis.w)?;
         level = level.back();
         writeln!(f, "{}}}", level)?;
         writeln!(f)?;
@@ -263,11 +558,12 @@ impl<'a> Display for XFileMat4Wrapper<'a> {
     }
 }
 
-struct XFileAiMeshWrapper<'a>(&'a AiScene, &'a AiMesh, Level);
+struct XFileAiMeshWrapper<'a>(&'a AiScene, &'a AiMesh, &'a WriteOptions, Level);
 
 impl<'a> Display for XFileAiMeshWrapper<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        let XFileAiMeshWrapper(scene, mesh, level) = self;
+        let XFileAiMeshWrapper(scene, mesh, options, level) = self;
+        let precision = options.precision;
         let mut level = *level;
         writeln!(
             f,
@@ -284,13 +580,13 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
                 writeln!(
                     f,
                     "{level}{:.*};{:.*};{:.*};,",
-                    PRECISION, vertex.x, PRECISION, vertex.y, PRECISION, vertex.z
+                    precision, vertex.x, precision, vertex.y, precision, vertex.z
                 )?;
             }
             writeln!(
                 f,
                 "{level}{:.*};{:.*};{:.*};;",
-                PRECISION, last_vertex.x, PRECISION, last_vertex.y, PRECISION, last_vertex.z
+                precision, last_vertex.x, precision, last_vertex.y, precision, last_vertex.z
             )?;
         }
 
@@ -321,7 +617,8 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
         if mesh.has_texture_coords(0) {
             let mat = &scene.materials[mesh.material_index as usize];
             let tex_file = mat
-                .get_string_property("", 0, AiStringPropertyType::TextureDiffuse)
+                .get_texture(AiTextureType::Diffuse, 0)
+                .map(|slot| slot.path)
                 .unwrap_or_default();
             writeln!(f, "{}MeshMaterialList {{", level)?;
             level = level.next();
@@ -339,7 +636,11 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
             writeln!(f, "{level}0.000000; 0.000000; 0.000000;;")?;
             writeln!(f, "{level}0.000000; 0.000000; 0.000000;;")?;
             write!(f, "{level}TextureFilename {{ \"")?;
-            write!(f, "{}", XFileStringPathWrapper(&tex_file))?;
+            write!(
+                f,
+                "{}",
+                XFileStringPathWrapper(&tex_file, options.path_encoding)
+            )?;
 
             writeln!(f, "\"; }}")?;
 
@@ -350,7 +651,7 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
             writeln!(f, "{level}}}")?;
         }
 
-        if mesh.has_normals() {
+        if options.normals && mesh.has_normals() {
             writeln!(f)?;
             writeln!(f, "{level}MeshNormals {{")?;
             writeln!(f, "{level}{};", vertices_len)?;
@@ -360,14 +661,14 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
                     writeln!(
                         f,
                         "{level}{:.*};{:.*};{:.*};,",
-                        PRECISION, -normal.x, PRECISION, -normal.y, PRECISION, -normal.z
+                        precision, -normal.x, precision, -normal.y, precision, -normal.z
                     )?;
                 }
                 // because we have a LHS and also changed wth winding, we need to invert the normals again
                 writeln!(
                     f,
                     "{level}{:.*};{:.*};{:.*};;",
-                    PRECISION, -last_normal.x, PRECISION, -last_normal.y, PRECISION, -last_normal.z
+                    precision, -last_normal.x, precision, -last_normal.y, precision, -last_normal.z
                 )?;
             }
 
@@ -396,7 +697,7 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
         }
 
         // write texture UVs if available
-        if mesh.has_texture_coords(0) {
+        if options.texture_coords && mesh.has_texture_coords(0) {
             writeln!(f)?;
             writeln!(f, "{level}MeshTextureCoords {{")?;
             writeln!(f, "{level}{};", vertices_len)?;
@@ -405,18 +706,18 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
                     writeln!(
                         f,
                         "{level}{:.*};{:.*};,",
-                        PRECISION,
+                        precision,
                         uv.x,
-                        PRECISION,
+                        precision,
                         1.0 - uv.y
                     )?;
                 }
                 writeln!(
                     f,
                     "{level}{:.*};{:.*};;",
-                    PRECISION,
+                    precision,
                     last_uv.x,
-                    PRECISION,
+                    precision,
                     1.0 - last_uv.y
                 )?;
             }
@@ -424,7 +725,7 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
         }
 
         // write color channel if available
-        if mesh.has_vertex_colors(0) {
+        if options.vertex_colors && mesh.has_vertex_colors(0) {
             writeln!(f)?;
             writeln!(f, "{level}MeshVertexColors {{")?;
             writeln!(f, "{level}{};", vertices_len)?;
@@ -456,32 +757,128 @@ impl<'a> Display for XFileAiMeshWrapper<'a> {
     }
 }
 
-struct XFileStringWrapper<'a>(&'a str);
+/// A light in the layout of `D3DLIGHT9`. Lights of other types than point,
+/// spot and directional are skipped.
+struct XFileLightWrapper<'a>(&'a AiLight, &'a WriteOptions, Level);
 
-impl<'a> Display for XFileStringWrapper<'a> {
+impl Display for XFileLightWrapper<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        write!(
+        let XFileLightWrapper(light, options, level) = *self;
+        let light_type = match light.light_type {
+            LightType::Point => 1,
+            LightType::Spot => 2,
+            LightType::Directional => 3,
+            _ => {
+                log::warn!(
+                    "X exporter: light {} of type {:?} is not written",
+                    light.name,
+                    light.light_type
+                );
+                return Ok(());
+            }
+        };
+        let p = options.precision;
+        let inner = level.next();
+        writeln!(f, "{level}Light {} {{", XFileStringWrapper(&light.name))?;
+        writeln!(f, "{inner}{light_type};")?;
+        for color in [
+            light.color_diffuse,
+            light.color_specular,
+            light.color_ambient,
+        ] {
+            writeln!(
+                f,
+                "{inner}{:.p$};{:.p$};{:.p$};1.0;;",
+                color.x, color.y, color.z
+            )?;
+        }
+        for v in [light.position, light.direction] {
+            writeln!(f, "{inner}{:.p$};{:.p$};{:.p$};;", v.x, v.y, v.z)?;
+        }
+        // the largest range of Direct3D, the attenuation limits the light
+        writeln!(f, "{inner}{:.p$};", f32::MAX.sqrt())?;
+        writeln!(f, "{inner}1.0;")?;
+        writeln!(
             f,
-            "{}",
-            self.0.replace(
-                |c: char| !(c.is_ascii_alphabetic() || c.is_ascii_digit()),
-                "_"
-            )
+            "{inner}{:.p$};{:.p$};{:.p$};",
+            light.attenuation_constant, light.attenuation_linear, light.attenuation_quadratic
         )?;
-        Ok(())
+        // D3D stores the full cone angles
+        let (theta, phi) = match light.light_type {
+            LightType::Spot => (light.angle_inner_cone * 2.0, light.angle_outer_cone * 2.0),
+            _ => (0.0, 0.0),
+        };
+        writeln!(f, "{inner}{theta:.p$};{phi:.p$};")?;
+        writeln!(f, "{level}}}")?;
+        writeln!(f)
+    }
+}
+
+struct XFileCameraWrapper<'a>(&'a AiCamera, &'a WriteOptions, Level);
+
+impl Display for XFileCameraWrapper<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let XFileCameraWrapper(camera, options, level) = *self;
+        let p = options.precision;
+        let inner = level.next();
+        writeln!(f, "{level}Camera {} {{", XFileStringWrapper(&camera.name))?;
+        for v in [camera.position, camera.up, camera.look_at] {
+            writeln!(f, "{inner}{:.p$};{:.p$};{:.p$};;", v.x, v.y, v.z)?;
+        }
+        writeln!(
+            f,
+            "{inner}{:.p$};{:.p$};{:.p$};{:.p$};",
+            camera.horizontal_fov, camera.clip_plane_near, camera.clip_plane_far, camera.aspect
+        )?;
+        writeln!(f, "{level}}}")?;
+        writeln!(f)
+    }
+}
+
+/// A name as identifier, see [`NameRules::X_FILE`]
+struct XFileStringWrapper<'a>(&'a str);
+
+impl<'a> Display for XFileStringWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&NameRules::X_FILE.sanitize(self.0))
     }
 }
 
-struct XFileStringPathWrapper<'a>(&'a str);
+/// A texture path inside of a string. Backslashes become slashes, quotes,
+/// which can't be escaped in X files, become apostrophes and line breaks
+/// become spaces. Characters the encoding can't represent become `?`. The
+/// changes are logged.
+struct XFileStringPathWrapper<'a>(&'a str, PathEncoding);
 
-impl<'a> Display for XFileStringPathWrapper<'a> {
+impl Display for XFileStringPathWrapper<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        let XFileStringPathWrapper(s) = self;
-        let mut dst = " ".repeat(s.len());
-        let len = encoding_rs::mem::convert_utf8_to_latin1_lossy(s.as_bytes(), unsafe {
-            dst.as_mut_vec()
-        });
-        f.write_str(&dst[..len].replace("\\", "/"))?;
+        let XFileStringPathWrapper(path, encoding) = *self;
+        let (mut unrepresentable, mut escaped) = (false, false);
+        for c in path.chars() {
+            let c = match c {
+                '\\' => '/',
+                '"' => {
+                    escaped = true;
+                    '\''
+                }
+                '\r' | '\n' => {
+                    escaped = true;
+                    ' '
+                }
+                c if encoding == PathEncoding::Latin1 && u32::from(c) > 0xff => {
+                    unrepresentable = true;
+                    '?'
+                }
+                c => c,
+            };
+            f.write_char(c)?;
+        }
+        if unrepresentable {
+            log::warn!("X exporter: texture path {path} contains characters outside of Latin-1");
+        }
+        if escaped {
+            log::warn!("X exporter: texture path {path} contains quotes or line breaks");
+        }
         Ok(())
     }
 }
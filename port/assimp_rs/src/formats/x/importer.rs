@@ -1,13 +1,23 @@
 use core::ops::Range;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, hash_map::Entry},
+    mem,
+};
 #[cfg(feature = "std")]
 use std::{fs::File, io::Read};
 
 use super::{
     errors::XFileImportError,
-    parser::Parser,
+    parser::{Parser, ParserOptions, XFile},
     structs::{Animation, Material, Mesh, Node, Scene},
 };
-use crate::utils::float_precision::{Mat3, Quat, Vec3};
+#[cfg(feature = "mmap")]
+use crate::utils::mmap::Mmap;
+use crate::{
+    core::profiler::{ProfileScope, Profiler},
+    utils::float_precision::{Vec3, decompose},
+};
 #[allow(unused)]
 use crate::{
     postprocess::{
@@ -17,23 +27,42 @@ use crate::{
         },
     },
     structs::{
+        aabb::AABB,
         anim::{AiAnimation, anim::AiNodeAnim},
         bone::AiBone,
         color::Color4D,
-        face::AiFace,
+        coordinate_system::CoordinateSystem,
+        face::AiFaceBuffer,
+        importer::{ImportProperties, report::ImportReport},
         importer_desc::{ImporterDesc, ImporterFlags},
         key::{AiQuatKey, AiVectorKey},
-        material::{AI_MATKEY_NAME, AiMaterial, AiProperty, AiShadingMode, AiStringPropertyType},
-        mesh::{AiMesh, AiVertexWeight},
+        material::{
+            AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_COLOR_EMISSIVE, AI_MATKEY_COLOR_SPECULAR,
+            AI_MATKEY_NAME, AI_MATKEY_SHADING_MODEL, AI_MATKEY_SHININESS, AddProperty, AiMaterial,
+            AiShadingMode, AiTextureType, TextureSlot,
+        },
+        mesh::{
+            AI_MAX_NUMBER_OF_COLOR_SETS, AI_MAX_NUMBER_OF_TEXTURECOORDS, AiMesh, AiVertexWeight,
+            PrimitiveTypeFlags,
+        },
+        meta::{AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, MetadataExt},
         nodes::Index,
-        scene::{AiNode, AiScene},
+        scene::{AiNode, AiScene, AiSceneFlags},
     },
     traits::importer::trait_define::{
         FormatHeader, FormatValidator, InternalImporter, encoding::convert_to_utf8,
     },
 };
+#[cfg(feature = "encoding")]
+use crate::{
+    structs::{importer::AI_CONFIG_IMPORT_TEXT_ENCODING, meta::AI_METADATA_SOURCE_ENCODING},
+    traits::importer::trait_define::encoding::convert_legacy_to_utf8,
+};
+
+/// Value of the [`AI_METADATA_SOURCE_FORMAT`] metadata of imported scenes
+pub const SOURCE_FORMAT: &str = "DirectX X";
 
-static DESC: ImporterDesc = ImporterDesc {
+pub(crate) static DESC: ImporterDesc = ImporterDesc {
     name: "Direct3D XFile Importer",
     author: "",
     maintainer: "",
@@ -75,7 +104,7 @@ impl Importer {
                 if let Some(index) = ai_scene
                     .materials
                     .iter()
-                    .map(|m| m.get_string_property("", 0, AiStringPropertyType::MaterialName))
+                    .map(|m| m.name())
                     .position(|name| name == Some(&old_mat.name))
                 {
                     *scene_index = index as u32;
@@ -100,7 +129,7 @@ impl Importer {
                         + 6,
                 )
                 .map_err(|_| XFileImportError::InsufficientMemory)?;
-            new_materials.add_property_v2(AiProperty::MaterialName(old_mat.name), 0);
+            new_materials.add_property(AI_MATKEY_NAME, old_mat.name.into_owned(), 0);
 
             // Shading model: hard-coded to PHONG, there is no such information in an XFile
             // FIX (aramis): If the specular exponent is 0, use gouraud shading. This is a bugfix
@@ -110,39 +139,36 @@ impl Importer {
             } else {
                 AiShadingMode::Phong
             };
-            new_materials.add_property_v2(AiProperty::ShadingModel(shade_mode), 0);
+            new_materials.add_property(AI_MATKEY_SHADING_MODEL, shade_mode, 0);
 
             // material colours
             // Unclear: there's no ambient colour, but emissive. What to put for ambient?
             // Probably nothing at all, let the user select a suitable default.
-            new_materials.add_property_v2(AiProperty::ColorEmissive(old_mat.emissive), 0);
-            new_materials.add_property_v2(AiProperty::ColorDiffuse(old_mat.diffuse.into()), 0);
-            new_materials.add_property_v2(AiProperty::ColorSpecular(old_mat.specular), 0);
-            new_materials.add_property_v2(AiProperty::Shiness(old_mat.specular_exponent), 0);
+            new_materials.add_property(AI_MATKEY_COLOR_EMISSIVE, old_mat.emissive, 0);
+            new_materials.add_property(AI_MATKEY_COLOR_DIFFUSE, old_mat.diffuse, 0);
+            new_materials.add_property(AI_MATKEY_COLOR_SPECULAR, old_mat.specular, 0);
+            new_materials.add_property(AI_MATKEY_SHININESS, old_mat.specular_exponent, 0);
 
             // texture, if there is one
             if old_mat.textures.len() == 1 {
                 let old_tex = old_mat.textures.remove(0);
                 if !old_tex.name.is_empty() {
                     // if there is only one texture assume it contains the diffuse color
-                    let tex = old_tex.name;
-                    if old_tex.is_normal_map {
-                        new_materials.add_property_v2(AiProperty::TextureNormals(tex), 0);
+                    let tex = old_tex.name.into_owned();
+                    let ty = if old_tex.is_normal_map {
+                        AiTextureType::Normals
                     } else {
-                        new_materials.add_property_v2(AiProperty::TextureDiffuse(tex), 0);
-                    }
+                        AiTextureType::Diffuse
+                    };
+                    new_materials.set_texture(ty, 0, TextureSlot::new(tex));
                 }
             } else {
                 // Otherwise ... try to search for typical strings in the
                 // texture's file name like 'bump' or 'diffuse'
-                let mut index_of_height_property = 0;
-                let mut index_of_normal_map_property = 0;
-                let mut index_of_specular_property = 0;
-                let mut index_of_ambient_property = 0;
-                let mut index_of_emissive_property = 0;
-                let mut index_of_diffuse_property = 0;
+                // next free index of every texture type
+                let mut indices = [0; AiTextureType::Transmission as usize + 1];
                 for old_tex in old_mat.textures.into_iter() {
-                    let mut sz = old_tex.name.as_str();
+                    let mut sz = old_tex.name.as_ref();
                     if sz.is_empty() {
                         continue;
                     }
@@ -161,47 +187,24 @@ impl Importer {
                     let sz = sz.to_ascii_lowercase();
 
                     // Place texture filename property under the corresponding name
-                    let tex = old_tex.name;
-
-                    // bump map
-                    if sz.contains("bump") || sz.contains("height") {
-                        new_materials.add_property_v2(
-                            AiProperty::TextureHeight(tex),
-                            index_of_height_property,
-                        );
-                        index_of_height_property += 1;
+                    let ty = if sz.contains("bump") || sz.contains("height") {
+                        // bump map
+                        AiTextureType::Height
                     } else if old_tex.is_normal_map || sz.contains("normal") || sz.contains("nm") {
-                        new_materials.add_property_v2(
-                            AiProperty::TextureNormals(tex),
-                            index_of_normal_map_property,
-                        );
-                        index_of_normal_map_property += 1;
+                        AiTextureType::Normals
                     } else if sz.contains("spec") || sz.contains("glanz") {
-                        new_materials.add_property_v2(
-                            AiProperty::TextureSpecular(tex),
-                            index_of_specular_property,
-                        );
-                        index_of_specular_property += 1;
+                        AiTextureType::Specular
                     } else if sz.contains("ambi") || sz.contains("env") {
-                        new_materials.add_property_v2(
-                            AiProperty::TextureAmbient(tex),
-                            index_of_ambient_property,
-                        );
-                        index_of_ambient_property += 1;
+                        AiTextureType::Ambient
                     } else if sz.contains("emissive") || sz.contains("self") {
-                        new_materials.add_property_v2(
-                            AiProperty::TextureEmissive(tex),
-                            index_of_emissive_property,
-                        );
-                        index_of_emissive_property += 1;
+                        AiTextureType::Emissive
                     } else {
                         // Assume it is a diffuse texture
-                        new_materials.add_property_v2(
-                            AiProperty::TextureDiffuse(tex),
-                            index_of_diffuse_property,
-                        );
-                        index_of_diffuse_property += 1;
-                    }
+                        AiTextureType::Diffuse
+                    };
+                    let index = &mut indices[ty as usize];
+                    new_materials.set_texture(ty, *index, TextureSlot::new(old_tex.name));
+                    *index += 1;
                 }
             }
             ai_scene.materials.push(new_materials);
@@ -212,6 +215,7 @@ impl Importer {
 
     fn create_node(
         scene: &mut AiScene,
+        root: Option<Index<Node>>,
         nodes: Vec<Node>,
     ) -> Result<Option<Index<AiNode>>, XFileImportError> {
         let len = nodes.len();
@@ -221,19 +225,18 @@ impl Importer {
         let mut new_nodes = Vec::with_capacity(len);
         for node in nodes {
             let mut new_node = AiNode {
-                name: node.name,
+                name: node.name.into_owned(),
                 transformation: node.transformation_matrix,
-                parent: Index::new(node.parent.value() as u32),
-                // SAFETY: Only the generic is different, the size and the value are the same
-                children: unsafe { core::mem::transmute(node.children) },
+                parent: node.parent.map(Index::cast),
+                children: node.children.into_iter().map(Index::cast).collect(),
                 meshes: Range::default(),
-                metadata: Box::default(),
+                metadata: Box::new(node.metadata),
             };
             new_node.meshes = Self::create_mesh(scene, node.meshes)?;
             new_nodes.push(new_node);
         }
         scene.nodes = new_nodes;
-        Ok(Some(Index::default()))
+        Ok(root.map(Index::cast))
     }
 
     fn create_mesh(scene: &mut AiScene, meshes: Vec<Mesh>) -> Result<Range<u32>, XFileImportError> {
@@ -248,14 +251,26 @@ impl Importer {
             pos_faces: s_pos_faces,
             normals: s_normals,
             norm_faces: s_norm_faces,
+            tangents: s_tangents,
+            bitangents: s_bitangents,
             tex_coords: s_tex_coords,
+            num_uv_components: s_num_uv_components,
+            tex_coords_names: s_tex_coords_names,
             colors: s_colors,
             face_materials: s_face_materials,
             materials: s_materials,
             bones: s_bones,
+            duplication_indices: s_duplication_indices,
             ..
         } in meshes
         {
+            // only sets containing data are kept, packed to the front
+            let uv_sets = (0..AI_MAX_NUMBER_OF_TEXTURECOORDS)
+                .filter(|&set| !s_tex_coords[set].is_empty())
+                .collect::<Vec<_>>();
+            let color_sets = (0..AI_MAX_NUMBER_OF_COLOR_SETS)
+                .filter(|&set| !s_colors[set].is_empty())
+                .collect::<Vec<_>>();
             let num_materials = s_materials.len().max(1) as u32;
             let material_indices = Self::convert_material(scene, s_materials)?;
             for material_index in 0..num_materials {
@@ -268,14 +283,14 @@ impl Importer {
                     {
                         if *face_material == material_index {
                             new_faces.push(index as u32);
-                            num_vertices += face.indices.len() as u32;
+                            num_vertices += face.len() as u32;
                         }
                     }
                 } else {
                     // if there is no per-face material, place everything into one mesh
                     for (index, face) in s_pos_faces.iter().enumerate() {
                         new_faces.push(index as u32);
-                        num_vertices += face.indices.len() as u32;
+                        num_vertices += face.len() as u32;
                     }
                 }
 
@@ -288,7 +303,10 @@ impl Importer {
                 // find the material in the scene's material list. Either own material
                 // or referenced material, it should already have a valid index
                 if !s_face_materials.is_empty() {
-                    new_mesh.material_index = material_indices[material_index as usize];
+                    new_mesh.material_index = material_indices
+                        .get(material_index as usize)
+                        .copied()
+                        .unwrap_or_default();
                 } else {
                     new_mesh.material_index = 0;
                 }
@@ -296,91 +314,145 @@ impl Importer {
                 // Create properly sized data arrays in the mesh. We store unique vertices per face,
                 // as specified
                 new_mesh.vertices = vec![Vec3::default(); num_vertices as usize];
-                new_mesh.faces = vec![AiFace::default(); new_faces.len() as usize];
+                let mut faces = AiFaceBuffer::with_capacity(new_faces.len());
+                let mut face = Vec::new();
 
-                new_mesh.name = s_name.clone();
+                new_mesh.name = s_name.to_string();
 
                 // normals?
                 if !s_normals.is_empty() {
                     new_mesh.normals = vec![Vec3::default(); num_vertices as usize];
                 }
+                // tangents and bitangents are stored per position, missing
+                // bitangents can be derived from the normals
+                if !s_tangents.is_empty() && (!s_bitangents.is_empty() || !s_normals.is_empty()) {
+                    new_mesh.tangents = vec![Vec3::default(); num_vertices as usize];
+                    new_mesh.bitangents = vec![Vec3::default(); num_vertices as usize];
+                }
                 // texture coords
-                for (old_tex_coords, new_tex_coords) in
-                    s_tex_coords.iter().zip(new_mesh.texture_coords.iter_mut())
-                {
-                    if !old_tex_coords.is_empty() {
-                        *new_tex_coords = vec![Vec3::default(); num_vertices as usize];
+                for (new_set, &set) in uv_sets.iter().enumerate() {
+                    new_mesh.texture_coords[new_set] = vec![Vec3::default(); num_vertices as usize];
+                    new_mesh.num_of_uv_components[new_set] = s_num_uv_components[set];
+                    if !s_tex_coords_names[set].is_empty() {
+                        new_mesh.set_texture_coords_name(new_set, &s_tex_coords_names[set]);
                     }
                 }
                 // vertex colors
-                for (old_colors, new_colors) in s_colors.iter().zip(new_mesh.colors.iter_mut()) {
-                    if !old_colors.is_empty() {
-                        *new_colors = vec![Color4D::default(); num_vertices as usize];
-                    }
+                for new_set in 0..color_sets.len() {
+                    new_mesh.colors[new_set] = vec![Color4D::default(); num_vertices as usize];
                 }
 
                 // now collect the vertex data of all data streams present in the imported mesh
                 let mut new_index: usize = 0;
 
+                // Files with duplication indices were written from an indexed
+                // D3DX vertex buffer, which only splits vertices where their
+                // normals or texture coordinates differ. Corners with the same
+                // position and normal share a vertex again, so smoothing and
+                // skinning see the topology of the source mesh.
+                let share_vertices = !s_duplication_indices.is_empty();
+                let mut shared = HashMap::new();
+
                 let mut org_points = vec![0u32; num_vertices as usize];
-                for (c, &f) in new_faces.iter().enumerate() {
+                for &f in new_faces.iter() {
                     let pos_face = &s_pos_faces[f as usize]; // position source face
 
                     // create face. either triangle or triangle fan depending on the index count
-                    let dst_face = &mut new_mesh.faces[c]; // destination face
-                    dst_face.indices =
-                        vec![0u32; pos_face.indices.len() as usize].into_boxed_slice();
+                    new_mesh.primitive_type |= PrimitiveTypeFlags::for_index_count(pos_face.len());
+                    face.clear();
 
                     // collect vertex data for indices of this face
-                    for (d, (dst_idx, new_idx)) in dst_face
-                        .indices
-                        .iter_mut()
-                        .zip(pos_face.indices.iter())
-                        .enumerate()
-                    {
-                        *dst_idx = new_index as u32;
-                        let new_idx = *new_idx;
+                    for (d, &new_idx) in pos_face.iter().enumerate() {
+                        face.push(new_index as u32);
+                        let dst_idx = face.last_mut().unwrap();
                         if new_idx >= s_positions.len() as u32 {
                             continue;
                         }
+                        let normal_idx = match s_norm_faces.get(f as usize) {
+                            Some(norm_face) => norm_face.get(d).copied(),
+                            // normals from vertex declarations are per position
+                            None => Some(new_idx),
+                        };
+                        if share_vertices {
+                            match shared.entry((new_idx, normal_idx)) {
+                                Entry::Occupied(entry) => {
+                                    *dst_idx = *entry.get();
+                                    continue;
+                                }
+                                Entry::Vacant(entry) => {
+                                    entry.insert(new_index as u32);
+                                }
+                            }
+                        }
 
                         org_points[new_index] = new_idx;
                         // Position
                         new_mesh.vertices[new_index] = s_positions[new_idx as usize];
                         // Normal, if present
-                        if !s_normals.is_empty() {
-                            if s_norm_faces[f as usize].indices.len() > d {
-                                let idx = s_norm_faces[f as usize].indices[d] as usize;
-                                if idx < s_normals.len() {
-                                    new_mesh.normals[new_index] = s_normals[idx];
-                                }
-                            }
+                        if !s_normals.is_empty()
+                            && let Some(normal) =
+                                normal_idx.and_then(|idx| s_normals.get(idx as usize))
+                        {
+                            new_mesh.normals[new_index] = *normal;
+                        }
+
+                        // Tangent and bitangent, if present
+                        if !new_mesh.tangents.is_empty() {
+                            let tangent = s_tangents
+                                .get(new_idx as usize)
+                                .copied()
+                                .unwrap_or_default();
+                            new_mesh.tangents[new_index] = tangent;
+                            new_mesh.bitangents[new_index] =
+                                match s_bitangents.get(new_idx as usize) {
+                                    Some(bitangent) => *bitangent,
+                                    None => new_mesh.normals[new_index].cross(tangent),
+                                };
                         }
 
                         // texture coord sets
-                        for (old_tex_coords, tex_coord) in
-                            s_tex_coords.iter().zip(new_mesh.texture_coords.iter_mut())
-                        {
-                            if !tex_coord.is_empty() {
-                                let tex = old_tex_coords[new_idx as usize];
-                                tex_coord[new_index] = Vec3::new(tex.x, 1.0 - tex.y, 0.0);
-                            }
+                        for (new_set, &set) in uv_sets.iter().enumerate() {
+                            let tex = s_tex_coords[set]
+                                .get(new_idx as usize)
+                                .copied()
+                                .unwrap_or_default();
+                            new_mesh.texture_coords[new_set][new_index] =
+                                Vec3::new(tex.x, 1.0 - tex.y, tex.z);
                         }
                         // vertex color sets
-                        for (old_colors, new_colors) in
-                            s_colors.iter().zip(new_mesh.colors.iter_mut())
-                        {
-                            if !new_colors.is_empty() {
-                                new_colors[new_index] = old_colors[new_idx as usize];
-                            }
+                        for (new_set, &set) in color_sets.iter().enumerate() {
+                            new_mesh.colors[new_set][new_index] = s_colors[set]
+                                .get(new_idx as usize)
+                                .copied()
+                                .unwrap_or_default();
                         }
 
                         new_index += 1;
                     }
+                    faces.push(&face);
+                }
+                new_mesh.faces = faces.to_faces();
+
+                // there should be as much new vertices as we calculated before,
+                // unless some are shared
+                debug_assert!(share_vertices || new_index == num_vertices as usize);
+                if new_index < num_vertices as usize {
+                    org_points.truncate(new_index);
+                    for stream in [
+                        &mut new_mesh.vertices,
+                        &mut new_mesh.normals,
+                        &mut new_mesh.tangents,
+                        &mut new_mesh.bitangents,
+                    ]
+                    .into_iter()
+                    .chain(new_mesh.texture_coords.iter_mut())
+                    {
+                        stream.truncate(new_index);
+                    }
+                    for colors in new_mesh.colors.iter_mut() {
+                        colors.truncate(new_index);
+                    }
                 }
-
-                // there should be as much new vertices as we calculated before
-                debug_assert!(new_index == num_vertices as usize);
 
                 for bone in s_bones.iter() {
                     let mut old_weights = vec![0.0; s_positions.len() as usize];
@@ -393,7 +465,9 @@ impl Importer {
                         // meshes, textures, animations etc. and asserts against these values may pass;
                         // when touching importer code, it is crucial that developers also run manual, visual
                         // checks to ensure there's no obvious breakage _before_ commiting to main branch
-                        old_weights[weight.vertex as usize] = weight.weight;
+                        if let Some(old_weight) = old_weights.get_mut(weight.vertex as usize) {
+                            *old_weight = weight.weight;
+                        }
                     }
                     // collect all vertex weights that influence a vertex in the new mesh
                     let mut new_weights = Vec::with_capacity(num_vertices as usize);
@@ -414,13 +488,15 @@ impl Importer {
                     }
 
                     // create
-                    let mut new_bone = AiBone::default();
-                    new_bone.name = bone.name.clone();
-                    new_bone.offset_matrix = bone.offset_matrix;
-                    new_bone.weights = new_weights;
-                    new_mesh.bones.push(new_bone);
+                    new_mesh.bones.push(AiBone {
+                        name: bone.name.to_string(),
+                        offset_matrix: bone.offset_matrix,
+                        weights: new_weights,
+                        ..Default::default()
+                    });
                 }
 
+                new_mesh.aabb = AABB::from_points(&new_mesh.vertices);
                 scene.meshes.push(new_mesh);
             }
         }
@@ -437,50 +513,33 @@ impl Importer {
             if anim.anims.is_empty() {
                 continue;
             }
-            let mut new_anim = AiAnimation::default();
-            new_anim.ticks_per_second = ticks_per_second as f64;
+            let mut new_anim = AiAnimation {
+                name: anim.name.into_owned(),
+                ticks_per_second: ticks_per_second as f64,
+                ..Default::default()
+            };
             let mut new_channels = Vec::new();
             for bone in anim.anims {
-                let mut new_bone = AiNodeAnim::default();
-                new_bone.node_name = bone.name.into();
+                let mut new_bone = AiNodeAnim {
+                    node_name: bone.name.into(),
+                    ..Default::default()
+                };
                 if let Some(last) = bone.trafo_keys.last() {
                     let len = bone.trafo_keys.len();
                     new_bone.position_keys.reserve(len);
                     new_bone.rotation_keys.reserve(len);
                     new_bone.scaling_keys.reserve(len);
                     for key in bone.trafo_keys.iter() {
-                        let time = key.time;
-                        let trafo = key.matrix;
-
-                        // extract position
-                        new_bone.position_keys.push(AiVectorKey {
-                            time,
-                            value: Vec3::new(trafo.x_axis.w, trafo.y_axis.w, trafo.z_axis.w),
-                            interpolation: Default::default(),
-                        });
-
-                        // extract scaling
-                        let scale = Vec3::new(
-                            Vec3::new(trafo.x_axis.x, trafo.y_axis.x, trafo.z_axis.x).length(),
-                            Vec3::new(trafo.x_axis.y, trafo.y_axis.y, trafo.z_axis.y).length(),
-                            Vec3::new(trafo.x_axis.z, trafo.y_axis.z, trafo.z_axis.z).length(),
-                        );
-                        new_bone.scaling_keys.push(AiVectorKey {
-                            time,
-                            value: scale,
-                            interpolation: Default::default(),
-                        });
-
-                        // extract rotation
-                        let mut rotmat = Mat3::from_mat4(trafo);
-                        rotmat.x_axis /= scale.x;
-                        rotmat.y_axis /= scale.y;
-                        rotmat.z_axis /= scale.z;
-                        new_bone.rotation_keys.push(AiQuatKey {
-                            time,
-                            value: Quat::from_mat3(&rotmat),
-                            interpolation: Default::default(),
-                        });
+                        let (position, rotation, scale) = decompose(key.matrix);
+                        new_bone
+                            .position_keys
+                            .push(AiVectorKey::new(key.time, position));
+                        new_bone
+                            .rotation_keys
+                            .push(AiQuatKey::new(key.time, rotation));
+                        new_bone
+                            .scaling_keys
+                            .push(AiVectorKey::new(key.time, scale));
                     }
                     // longest lasting key sequence determines duration
                     new_anim.duration = new_anim.duration.max(last.time);
@@ -520,26 +579,34 @@ impl Importer {
         Ok(())
     }
 
-    fn to_ai_scene(scene: Scene, ai_scene: &mut AiScene) -> Result<(), XFileImportError> {
+    /// Converts a parsed scene, the second half of an import after
+    /// [`Parser::parse_with_options`]
+    pub fn to_ai_scene(scene: Scene, ai_scene: &mut AiScene) -> Result<(), XFileImportError> {
         let Scene {
+            root_node,
             nodes,
             global_meshes,
             global_materials,
             animations,
             anim_ticks_per_second,
+            lights,
+            cameras,
+            metadata,
             ..
         } = scene;
+        ai_scene.metadata.extend(metadata);
+        ai_scene.lights.extend(lights);
+        ai_scene.cameras.extend(cameras);
         Self::convert_material(ai_scene, global_materials)?;
 
-        let root_node = Self::create_node(ai_scene, nodes)?;
+        let root_node = Self::create_node(ai_scene, root_node, nodes)?;
         ai_scene.root = root_node;
 
         if !global_meshes.is_empty() {
-            if ai_scene.root.is_none() {
-                ai_scene.root = Some(Index::default());
-                ai_scene.nodes.push(AiNode::default());
-            }
-            ai_scene.nodes[0].meshes = Self::create_mesh(ai_scene, global_meshes)?;
+            let root = *ai_scene
+                .root
+                .get_or_insert_with(|| Index::push(&mut ai_scene.nodes, AiNode::default()));
+            ai_scene.nodes[root.value()].meshes = Self::create_mesh(ai_scene, global_meshes)?;
         }
 
         if root_node.is_none() {
@@ -554,12 +621,11 @@ impl Importer {
 
         if ai_scene.materials.is_empty() {
             let mut new_material = AiMaterial::default();
-            new_material.add_property_v2(AiProperty::ShadingModel(AiShadingMode::Gouraud), 0);
-            new_material.add_property_v2(AiProperty::ColorEmissive(Vec3::ZERO), 0);
-            new_material.add_property_v2(AiProperty::ColorSpecular(Vec3::ZERO), 0);
-            new_material
-                .add_property_v2(AiProperty::ColorDiffuse(Vec3::new(0.5, 0.5, 0.5).into()), 0);
-            new_material.add_property_v2(AiProperty::Shiness(1.0), 0);
+            new_material.add_property(AI_MATKEY_SHADING_MODEL, AiShadingMode::Gouraud, 0);
+            new_material.add_property(AI_MATKEY_COLOR_EMISSIVE, Vec3::ZERO, 0);
+            new_material.add_property(AI_MATKEY_COLOR_SPECULAR, Vec3::ZERO, 0);
+            new_material.add_property(AI_MATKEY_COLOR_DIFFUSE, Vec3::splat(0.5), 0);
+            new_material.add_property(AI_MATKEY_SHININESS, 1.0, 0);
             ai_scene.materials.push(new_material);
         }
 
@@ -574,6 +640,19 @@ impl FormatHeader<4> for Importer {
 impl InternalImporter<XFileImportError> for Importer {
     #[cfg(feature = "std")]
     fn import_from_file(file_name: &str, ai_scene: &mut AiScene) -> Result<(), XFileImportError> {
+        Self::import_from_file_with_properties(file_name, ai_scene, &ImportProperties::default())
+    }
+
+    fn import_from_buf(buf: &[u8], ai_scene: &mut AiScene) -> Result<(), XFileImportError> {
+        Self::import_from_buf_with_properties(buf, ai_scene, &ImportProperties::default())
+    }
+
+    #[cfg(feature = "std")]
+    fn import_from_file_with_properties(
+        file_name: &str,
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+    ) -> Result<(), XFileImportError> {
         let mut file = File::open(file_name)?;
         let file_size = file.metadata()?.len();
         if file_size < 16 {
@@ -582,22 +661,136 @@ impl InternalImporter<XFileImportError> for Importer {
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
 
-        let text = convert_to_utf8(buf).map_err(|e| XFileImportError::from(e))?;
-        let buf = text.as_bytes();
-        if Self::can_read_from_buf(buf) {
-            Self::import_from_buf(buf, ai_scene)
+        let buf = Self::decode_text(&buf)?;
+        if Self::can_read_from_buf(&buf) {
+            Self::import_from_buf_with_properties(&buf, ai_scene, properties)
         } else {
             Err(XFileImportError::InvalidFormat)
         }
     }
 
-    fn import_from_buf(buf: &[u8], ai_scene: &mut AiScene) -> Result<(), XFileImportError> {
-        Self::to_ai_scene(Parser::parse(buf)?.scene, ai_scene)?;
-        Ok(())
+    #[cfg(feature = "mmap")]
+    unsafe fn import_from_mmap(
+        file_name: &str,
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+    ) -> Result<(), XFileImportError> {
+        let file = File::open(file_name)?;
+        // SAFETY: forwarded to the caller
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < 16 {
+            return Err(XFileImportError::FileTooSmall);
+        }
+        let buf = Self::decode_text(&map)?;
+        if Self::can_read_from_buf(&buf) {
+            Self::import_from_buf_with_properties(&buf, ai_scene, properties)
+        } else {
+            Err(XFileImportError::InvalidFormat)
+        }
+    }
+
+    fn import_from_buf_with_properties(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+    ) -> Result<(), XFileImportError> {
+        Self::import_from_buf_with_profiler(buf, ai_scene, properties, &mut Profiler::disabled())
+    }
+
+    fn import_from_buf_with_profiler(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<(), XFileImportError> {
+        Self::import(buf, ai_scene, properties, profiler).map(|_| ())
+    }
+
+    fn import_from_buf_with_report(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+    ) -> Result<ImportReport, XFileImportError> {
+        let mut profiler = Profiler::new();
+        let mut report = Self::import(buf, ai_scene, properties, &mut profiler)?;
+        report.finish(ai_scene, &profiler);
+        Ok(report)
     }
 }
 
 impl Importer {
+    fn import(
+        buf: &[u8],
+        ai_scene: &mut AiScene,
+        properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<ImportReport, XFileImportError> {
+        // files read from a buffer may still have a byte order mark
+        let buf = &*Self::decode_text(buf)?;
+        #[cfg(feature = "encoding")]
+        let (decoded, source_encoding) = Self::decode_legacy_text(buf, properties)?;
+        #[cfg(feature = "encoding")]
+        let buf = &*decoded;
+        let XFile { header, mut scene } =
+            Parser::parse_with_profiler(buf, ParserOptions::from_properties(properties), profiler)?;
+        let is_partial = scene.partial_error.is_some();
+        let report = mem::take(&mut scene.report);
+        profiler.measure(ProfileScope::Import, "conversion", || {
+            Self::to_ai_scene(scene, ai_scene)
+        })?;
+        let metadata = &mut ai_scene.metadata;
+        metadata.set(AI_METADATA_SOURCE_FORMAT, SOURCE_FORMAT);
+        // X files have no unit, so the default of assimp is recorded
+        CoordinateSystem::Y_UP_LEFT_HANDED.write_metadata(metadata);
+        metadata.set(
+            AI_METADATA_SOURCE_FORMAT_VERSION,
+            format!("{}.{}", header.major_version, header.minor_version),
+        );
+        #[cfg(feature = "encoding")]
+        if let Some(name) = source_encoding {
+            metadata.set(AI_METADATA_SOURCE_ENCODING, name);
+        }
+        if is_partial {
+            ai_scene.flags |= AiSceneFlags::INCOMPLETE | AiSceneFlags::VALIDATION_WARNING;
+        }
+        Ok(report)
+    }
+
+    /// Strips the UTF-8 byte order mark and converts UTF-16 and UTF-32 text
+    /// to UTF-8. Everything else, including binary files, is passed through
+    /// unchanged.
+    fn decode_text(buf: &[u8]) -> Result<Cow<'_, [u8]>, XFileImportError> {
+        Ok(match buf {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => Cow::Borrowed(rest),
+            [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] | [0x00, 0x00, 0xFE, 0xFF, ..] => {
+                Cow::Owned(convert_to_utf8(buf.to_vec())?.into_bytes())
+            }
+            _ => Cow::Borrowed(buf),
+        })
+    }
+
+    /// Converts text files which are not valid UTF-8 from a legacy codepage,
+    /// see [`AI_CONFIG_IMPORT_TEXT_ENCODING`]. Returns the name of the
+    /// codepage if the text was converted. Binary and compressed files are
+    /// passed through unchanged.
+    #[cfg(feature = "encoding")]
+    fn decode_legacy_text<'a>(
+        buf: &'a [u8],
+        properties: &ImportProperties,
+    ) -> Result<(Cow<'a, [u8]>, Option<&'static str>), XFileImportError> {
+        if buf.get(8..12) != Some(b"txt ") {
+            return Ok((Cow::Borrowed(buf), None));
+        }
+        let label = properties.get_string(AI_CONFIG_IMPORT_TEXT_ENCODING);
+        Ok(match convert_legacy_to_utf8(buf, label)? {
+            Some((text, name)) => {
+                log::info!("Converted text from {name}");
+                (Cow::Owned(text.into_bytes()), Some(name))
+            }
+            None => (Cow::Borrowed(buf), None),
+        })
+    }
+
     #[allow(unused)]
     pub(crate) fn get_tokens(buf: &[u8]) -> Result<Vec<&[u8]>, XFileImportError> {
         // let parser = Parser::new(buf)?;
@@ -605,3 +798,56 @@ impl Importer {
         Ok(vec![])
     }
 }
+
+/// Entry point for fuzzers, imports arbitrary bytes in lenient, strict and
+/// partial mode, see [`fuzzing::fuzz_import_with`](crate::fuzzing::fuzz_import_with)
+#[cfg(feature = "fuzzing")]
+pub fn fuzz_import(data: &[u8]) {
+    use crate::{
+        fuzzing::fuzz_import_with,
+        structs::importer::{AI_CONFIG_IMPORT_ALLOW_PARTIAL, AI_CONFIG_IMPORT_X_STRICT_PARSING},
+    };
+
+    let mut strict = ImportProperties::default();
+    strict.set_bool(AI_CONFIG_IMPORT_X_STRICT_PARSING, true);
+    let mut partial = ImportProperties::default();
+    partial.set_bool(AI_CONFIG_IMPORT_ALLOW_PARTIAL, true);
+    fuzz_import_with::<Importer, _>(data, &[ImportProperties::default(), strict, partial]);
+}
+
+/// Like [`fuzz_import`], but the data is the body of a text file
+#[cfg(feature = "fuzzing")]
+pub fn fuzz_import_text(data: &[u8]) {
+    fuzz_import(&[b"xof 0303txt 0032", data].concat());
+}
+
+/// Like [`fuzz_import`], but the data is the body of a binary file. The
+/// first byte selects between 32 and 64 bit floats.
+#[cfg(feature = "fuzzing")]
+pub fn fuzz_import_binary(data: &[u8]) {
+    let Some((flavour, data)) = data.split_first() else {
+        return;
+    };
+    let header: &[u8] = if flavour & 1 == 0 {
+        b"xof 0303bin 0032"
+    } else {
+        b"xof 0303bin 0064"
+    };
+    fuzz_import(&[header, data].concat());
+}
+
+/// Like [`fuzz_import`], but the data follows the header of a compressed
+/// file, starting with the MSZIP blocks. The first byte selects between
+/// compressed text and binary files.
+#[cfg(feature = "fuzzing")]
+pub fn fuzz_import_compressed(data: &[u8]) {
+    let Some((flavour, data)) = data.split_first() else {
+        return;
+    };
+    let header: &[u8] = if flavour & 1 == 0 {
+        b"xof 0303tzip0032"
+    } else {
+        b"xof 0303bzip0032"
+    };
+    fuzz_import(&[header, data].concat());
+}
@@ -6,14 +6,66 @@ pub mod structs;
 
 #[allow(unused)]
 mod test {
-    use std::{fs, io::Write};
+    use std::{borrow::Cow, collections::HashMap, fs, io::Write};
 
-    use super::importer::Importer;
+    use super::{
+        errors::{XFileExportError, XFileImportError, XFileParseError},
+        importer::Importer,
+        parser::{
+            Parser,
+            template::{Dimension, MemberType, Restriction},
+            token::{Token, lex_text},
+        },
+    };
+    #[cfg(test)]
+    use crate::utils::golden::{self, DumpScene};
     use crate::{
-        formats::x::exporter::{self, Exporter},
-        structs::scene::AiScene,
-        traits::importer::trait_define::InternalImporter,
-        utils::{float_precision::Mat4, get_model_path},
+        AiReal,
+        core::ProfileScope,
+        formats::x::exporter::Exporter,
+        postprocess::{
+            PostProcess,
+            convert_to_left_hand_process::{
+                ConvertToLeftHandProcess, flip_winding_order_process::FlipWindingOrderProcess,
+            },
+            validate::ValidateDSProcess,
+        },
+        structs::{
+            camera::AiCamera,
+            color::{Color3D, Color4D},
+            diff::Tolerance,
+            exporter::{
+                AI_CONFIG_EXPORT_X_COLLAPSE_ROOT, AI_CONFIG_EXPORT_X_NORMALS,
+                AI_CONFIG_EXPORT_X_PATH_ENCODING, AI_CONFIG_EXPORT_X_PRECISION,
+                AI_CONFIG_EXPORT_X_VERTEX_COLORS, ExportProperties,
+            },
+            face::AiFace,
+            importer::{
+                AI_CONFIG_IMPORT_ALLOW_PARTIAL, AI_CONFIG_IMPORT_MAX_ALLOC_BYTES,
+                AI_CONFIG_IMPORT_X_STRICT_PARSING, ImportProperties,
+            },
+            light::AiLight,
+            material::{AiMaterial, AiTextureType, TextureSlot},
+            mesh::{AiMesh, AiVertexWeight, PrimitiveTypeFlags},
+            meta::{
+                AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, Metadata,
+                MetadataEntry, MetadataExt,
+            },
+            nodes::Index,
+            scene::{AiScene, AiSceneFlags},
+            scene_builder::SceneBuilder,
+        },
+        traits::{
+            exporter::trait_define::{EXPORT_BUFFER_SIZE, Exporter as _},
+            importer::{
+                error::ImportError,
+                trait_define::{Importer as _, InternalImporter},
+            },
+        },
+        utils::{
+            float_precision::{Mat4, Quat, Vec3},
+            get_model_path,
+        },
     };
     // #[test]
     // fn test_import_from_file() {
@@ -65,4 +117,1383 @@ mod test {
         writer.flush().unwrap();
         println!("flush time: {:?}", t.elapsed());
     }
+
+    fn import(source: &[u8]) -> AiScene {
+        let mut scene = AiScene::default();
+        Importer::import_from_buf(source, &mut scene).unwrap();
+        scene
+    }
+
+    #[test]
+    fn test_import_multiple_uv_sets() {
+        let scene = import(
+            br#"xof 0303txt 0032
+Frame Root {
+Mesh triangle {
+  3; 0;0;0;, 1;0;0;, 0;1;0;;
+  1; 3;0,1,2;;
+  MeshTextureCoords tc0 { 3; 0.0;0.0;, 1.0;0.0;, 0.0;1.0;; }
+  MeshTextureCoords lightmap { 3; 0.5;0.5;, 0.5;0.5;, 0.5;0.5;; }
+  MeshTextureCoords { 3; 0.25;0.0;, 0.25;0.0;, 0.25;0.0;; }
+  DeclData {
+    1; 2;0;5;3;;
+    9; 1065353216,0,1073741824, 1065353216,0,1073741824, 1065353216,0,1073741824;
+  }
+}
+}
+"#,
+        );
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.num_of_uv_channels(), 4);
+        assert_eq!(&mesh.num_of_uv_components[..5], &[2, 2, 2, 3, 0]);
+        assert_eq!(mesh.get_texture_coords_name(0), Some("tc0"));
+        assert_eq!(mesh.get_texture_coords_name(1), Some("lightmap"));
+        assert!(!mesh.has_texture_coords_name(2));
+        // v is flipped on import
+        assert_eq!(mesh.texture_coords[0][1], Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.texture_coords[1][0], Vec3::new(0.5, 0.5, 0.0));
+        assert_eq!(mesh.texture_coords[2][2], Vec3::new(0.25, 1.0, 0.0));
+        assert_eq!(mesh.texture_coords[3][0], Vec3::new(1.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn test_import_uv_and_color_sets_are_packed() {
+        // the declaration only fills the third texture coordinate set and
+        // the second color set
+        let scene = import(
+            br#"xof 0303txt 0032
+Frame Root {
+Mesh triangle {
+  3; 0;0;0;, 1;0;0;, 0;1;0;;
+  1; 3;0,1,2;;
+  DeclData {
+    2; 1;0;5;2;, 4;0;10;1;;
+    9; 0,0,4278190335, 0,0,4278190335, 0,0,4278190335;
+  }
+  MeshTextureCoords tc0 { 3; 0.0;0.0;, 1.0;0.0;, 0.0;1.0;; }
+}
+}
+"#,
+        );
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.num_of_uv_channels(), 2);
+        assert!(mesh.has_texture_coords(0) && mesh.has_texture_coords(1));
+        assert_eq!(&mesh.num_of_uv_components[..2], &[2, 2]);
+        assert_eq!(mesh.get_texture_coords_name(0), Some("tc0"));
+        assert_eq!(mesh.get_texture_coords_name(1), Some(""));
+        assert_eq!(mesh.num_of_color_channels(), 1);
+        assert_eq!(mesh.colors[0][0], Color4D::new(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_import_vertex_colors_and_tangents() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = import(&source);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.num_of_uv_channels(), 1);
+        assert_eq!(mesh.num_of_uv_components[0], 2);
+        assert_eq!(mesh.get_texture_coords_name(0), Some("tc0"));
+        assert_eq!(mesh.num_of_color_channels(), 1);
+        assert!(mesh.has_tangents_and_bitangents());
+        assert_eq!(mesh.tangents.len(), mesh.vertices.len());
+    }
+
+    #[test]
+    fn test_import_metadata() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = import(&source);
+        assert_eq!(
+            scene.metadata.get("User"),
+            Some(&MetadataEntry::String("JWatte".into()))
+        );
+        assert_eq!(
+            scene.metadata.get("CoreTime"),
+            Some(&MetadataEntry::String("0".into()))
+        );
+        assert_eq!(scene.metadata.get_str("User"), Some("JWatte"));
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT),
+            Some("DirectX X")
+        );
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_SOURCE_FORMAT_VERSION),
+            Some("3.3")
+        );
+
+        let mut metadata = Metadata::default();
+        metadata.set("Flag", true);
+        metadata.set("Count", 3u32);
+        metadata.set_path("Asset.Author", "someone");
+        metadata.set_path("Asset.Origin", Vec3::ONE);
+        assert_eq!(metadata.get_bool("Flag"), Some(true));
+        assert_eq!(metadata.get_int32("Count"), None);
+        assert_eq!(metadata.get_int64("Count"), Some(3));
+        assert_eq!(metadata.get_str("Asset.Author"), Some("someone"));
+        assert_eq!(metadata.get_vec3("Asset.Origin"), Some(Vec3::ONE));
+        assert_eq!(metadata.get_metadata("Asset").map(|m| m.len()), Some(2));
+        assert_eq!(metadata.get_str("Asset.Missing"), None);
+        // keys with dots are found before nested ones
+        metadata.set("Asset.Author", 1.5);
+        assert_eq!(metadata.get_float("Asset.Author"), Some(1.5));
+        // a value on the way is replaced by nested metadata
+        metadata.set_path("Flag.Inner", 1);
+        assert_eq!(metadata.get_int32("Flag.Inner"), Some(1));
+    }
+
+    #[test]
+    fn test_import_truncated_file() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        // cut the file in the middle of the normals
+        let normals = source
+            .windows(19)
+            .position(|w| w == b"MeshNormals normals")
+            .unwrap();
+        let truncated = &source[..normals + 200];
+
+        let mut scene = AiScene::default();
+        assert!(Importer::import_from_buf(truncated, &mut scene).is_err());
+
+        let mut properties = ImportProperties::default();
+        properties.set_bool(AI_CONFIG_IMPORT_ALLOW_PARTIAL, true);
+        let mut scene = AiScene::default();
+        Importer::import_from_buf_with_properties(truncated, &mut scene, &properties).unwrap();
+        assert!(scene.flags.contains(AiSceneFlags::INCOMPLETE));
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.faces.len(), 12);
+        assert!(!mesh.has_normals());
+        assert!(!mesh.has_texture_coords(0));
+    }
+
+    #[test]
+    fn test_import_strict_parsing() {
+        // a single material index for two faces and a NaN written by Blender
+        let source = br#"xof 0303txt 0032
+Frame Root {
+Mesh quad {
+  4; 0;0;0;, 1;0;0;, 1;1;0;, 1.#QNAN0;1;0;;
+  2; 3;0,1,2;, 3;0,2,3;;
+  MeshMaterialList { 1; 1; 0;; Material { 1;1;1;1;; 0; 0;0;0;; 0;0;0;; } }
+}
+}
+"#;
+        let scene = import(source);
+        assert_eq!(scene.meshes[0].faces.len(), 2);
+
+        let mut properties = ImportProperties::default();
+        properties.set_bool(AI_CONFIG_IMPORT_X_STRICT_PARSING, true);
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf_with_properties(source, &mut scene, &properties)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("1.#QNAN0"), "{error}");
+    }
+
+    #[test]
+    fn test_import_hierarchy() {
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let scene = import(&source);
+        let root = scene.root.unwrap();
+        assert_eq!(root.value(), 0);
+        assert_eq!(scene.nodes[0].parent, None);
+        assert_eq!(scene.nodes[0].children, [Index::new(1)]);
+        assert_eq!(scene.nodes[1].name, "Cube");
+        assert_eq!(scene.nodes[1].parent, Some(root));
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+
+        // the anonymous frame holding the mesh is merged into its parent,
+        // further top level frames are grouped below a dummy root
+        let source = br#"xof 0303txt 0032
+Frame A {
+  Frame B {
+    Frame {
+      Mesh { 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; }
+      Frame C {}
+    }
+  }
+}
+Frame D {}
+"#;
+        let scene = import(source);
+        let names = scene
+            .nodes
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["$dummy_root", "A", "B", "C", "D"]);
+        let parents = scene
+            .nodes
+            .iter()
+            .map(|n| n.parent.map(|p| p.value()))
+            .collect::<Vec<_>>();
+        assert_eq!(parents, [None, Some(0), Some(1), Some(2), Some(0)]);
+        assert_eq!(scene.nodes[2].meshes, 0..1);
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+    }
+
+    #[test]
+    fn test_parse_error_snippet() {
+        let source = b"xof 0303txt 0032\nFrame Root {\nMesh m {\n\t3; 0;0;0;, 1;x;0;, 0;1;0;;\n";
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf(source, &mut scene)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Line 4, column 15:"), "{error}");
+        assert!(
+            error.ends_with("4 | \t3; 0;0;0;, 1;x;0;, 0;1;0;;\n  | \t             ^"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_tokenizer() {
+        let lex = |s: &'static str| lex_text(s.as_bytes()).unwrap();
+        assert_eq!(lex("\"a;b}\";"), (Token::String(b"a;b}"), 6));
+        assert_eq!(lex("-1.#IND00;"), (Token::Float(b"-1.#IND00"), 9));
+        assert_eq!(lex("1.#QNAN0,"), (Token::Float(b"1.#QNAN0"), 8));
+        assert_eq!(lex("-12;"), (Token::Integer(b"-12"), 3));
+        assert_eq!(lex("3ds_root {"), (Token::Identifier(b"3ds_root"), 8));
+        assert_eq!(
+            lex("Bip01_L_Thigh{"),
+            (Token::Identifier(b"Bip01_L_Thigh"), 13)
+        );
+        assert_eq!(lex("Cube// comment"), (Token::Identifier(b"Cube"), 4));
+        assert_eq!(
+            lex("<3D82AB44-62DA-11cf-AB39-0020AF71E433>"),
+            (Token::Guid(b"<3D82AB44-62DA-11cf-AB39-0020AF71E433>"), 38)
+        );
+        assert_eq!(lex("}"), (Token::Punctuation(b'}'), 1));
+        assert_eq!(lex(""), (Token::Eof, 0));
+        assert!(lex_text(b"\"open").is_err());
+
+        // quirks of Blender, kwXport and Maxon XPort
+        let source = br#"xof 0303txt 0032
+// Blender exporter
+KeyValuePair { "Path"; "C:\\{tmp};"; }
+XPortInfo { "Cinema 4D }"; }
+Frame Null-1.2 {
+  Mesh m {
+    3; 0;0;0;, 1;0;-1.#IND00;, 0;1;0;;
+    1; 3;0,1,2;;
+    MeshMaterialList { 1; 1; 0;;
+      Material Mat.1 { 1;1;1;1;; 0; 0;0;0;; 0;0;0;;
+        TextureFilename { "tex{1};.png"; }
+      }
+    }
+    Comment { "}"; }
+  }
+}
+"#;
+        let scene = import(source);
+        assert_eq!(scene.metadata.get_str("Path"), Some("C:\\\\{tmp};"));
+        assert_eq!(scene.metadata.get_str("XPortInfo"), Some("Cinema 4D }"));
+        assert_eq!(scene.nodes[0].name, "Null-1.2");
+        assert_eq!(scene.meshes[0].faces.len(), 1);
+        assert_eq!(scene.meshes[0].vertices[1].z, 0.0);
+        let material = &scene.materials[scene.meshes[0].material_index as usize];
+        assert_eq!(
+            material
+                .get_texture(AiTextureType::Diffuse, 0)
+                .map(|t| t.path),
+            Some("tex{1};.png".to_owned())
+        );
+
+        let source = b"xof 0303txt 0032\nFrame Root {\n  Comment { \"open; }\n}\n";
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf(source, &mut scene)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Line 3, column 13: XFileTextParseError: Unterminated string"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let source = br#"xof 0303txt 0032
+template Weights {
+ <5d6d9e0c-2a3b-4c1e-9f4a-0b1c2d3e4f50>
+ DWORD nWeights;
+ array FLOAT weights[nWeights];
+ Vector offset;
+ STRING label;
+ [...]
+}
+template Tag { <5d6d9e0c-2a3b-4c1e-9f4a-0b1c2d3e4f51> STRING text; [Weights <5d6d9e0c-2a3b-4c1e-9f4a-0b1c2d3e4f50>] }
+Frame Root {
+  Weights w1 { 2; 0.5, 0.25; 1.0; 2.0; 3.0;; "a;b}"; Tag { "nested"; } { Root } }
+  Tag { "tagged"; }
+}
+"#;
+        let parsed = Parser::parse(source).unwrap().scene;
+        let weights = parsed.templates.get("Weights").unwrap();
+        assert_eq!(weights.restriction, Restriction::Open);
+        assert_eq!(weights.members.len(), 4);
+        assert_eq!(
+            weights.members[1].dimensions,
+            [Dimension::Member("nWeights".into())]
+        );
+        assert_eq!(weights.members[2].ty, MemberType::Template("Vector".into()));
+        assert_eq!(
+            parsed.templates.get("Tag").unwrap().restriction,
+            Restriction::Restricted(vec!["Weights".into()])
+        );
+        assert!(parsed.templates.get("Matrix4x4").is_some());
+
+        let scene = import(source);
+        let metadata = &scene.nodes[0].metadata;
+        assert_eq!(metadata.get_uint32("w1.nWeights"), Some(2));
+        assert_eq!(metadata.get_float("w1.weights.1"), Some(0.25));
+        assert_eq!(metadata.get_float("w1.offset.z"), Some(3.0));
+        assert_eq!(metadata.get_str("w1.label"), Some("a;b}"));
+        // string only templates keep their plain value
+        assert_eq!(metadata.get_str("Tag"), Some("tagged"));
+
+        // binary files need the declaration to read the members
+        let mut source = b"xof 0303bin 0064".to_vec();
+        let mut write = |token: u16, data: &[u8]| {
+            source.extend(token.to_le_bytes());
+            source.extend(data);
+        };
+        let name = |name: &str| [&(name.len() as u32).to_le_bytes(), name.as_bytes()].concat();
+        // template Pair { <guid> DWORD n; array DOUBLE v[n]; [...] }
+        write(0x1f, &[]);
+        write(1, &name("Pair"));
+        write(0x0a, &[]);
+        write(5, &[0; 16]);
+        write(0x29, &[]);
+        write(1, &name("n"));
+        write(0x14, &[]);
+        write(0x34, &[]);
+        write(0x2b, &[]);
+        write(1, &name("v"));
+        write(0x0e, &[]);
+        write(1, &name("n"));
+        write(0x0f, &[]);
+        write(0x14, &[]);
+        write(0x0e, &[]);
+        write(0x12, &[]);
+        write(0x12, &[]);
+        write(0x12, &[]);
+        write(0x0f, &[]);
+        write(0x0b, &[]);
+        // Frame Root { Pair p { 2; 0.5, 4.0; } }
+        write(1, &name("Frame"));
+        write(1, &name("Root"));
+        write(0x0a, &[]);
+        write(1, &name("Pair"));
+        write(1, &name("p"));
+        write(0x0a, &[]);
+        write(6, &[1, 0, 0, 0, 2, 0, 0, 0]);
+        write(
+            7,
+            &[
+                [2, 0, 0, 0].as_slice(),
+                &0.5f64.to_le_bytes(),
+                &4.0f64.to_le_bytes(),
+            ]
+            .concat(),
+        );
+        write(0x0b, &[]);
+        write(0x0b, &[]);
+
+        let parsed = Parser::parse(&source).unwrap().scene;
+        let pair = parsed.templates.get("Pair").unwrap();
+        assert_eq!(pair.members[1].ty, MemberType::Double);
+        assert_eq!(pair.restriction, Restriction::Open);
+        let metadata = &parsed.nodes[0].metadata;
+        assert_eq!(metadata.get_uint32("p.n"), Some(2));
+        assert_eq!(metadata.get_float("p.v.1"), Some(4.0));
+    }
+
+    #[test]
+    fn test_vertex_duplication_indices() {
+        let source = fs::read(get_model_path("X", "test.x")).unwrap();
+        let parsed = Parser::parse(&source).unwrap().scene;
+        let mesh = &parsed
+            .nodes
+            .iter()
+            .find(|n| !n.meshes.is_empty())
+            .unwrap()
+            .meshes[0];
+        assert_eq!(mesh.duplication_indices.len(), mesh.positions.len());
+        let mut originals = mesh.duplication_indices.clone();
+        originals.sort();
+        originals.dedup();
+        assert_eq!(originals, [0, 1, 2, 3, 6, 7, 10, 11]);
+
+        // the corners of the 12 triangles share the 24 vertices of the file
+        let scene = import(&source);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.faces.len(), 12);
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.normals.len(), 24);
+        assert!(
+            mesh.faces
+                .iter()
+                .flat_map(|f| f.indices.iter())
+                .all(|&i| i < 24)
+        );
+
+        let source = br#"xof 0303txt 0032
+Frame Root {
+Mesh quad {
+  4; 0;0;0;, 1;0;0;, 1;1;0;, 0;1;0;;
+  2; 3;0,1,2;, 3;0,2,3;;
+  VertexDuplicationIndices { 3; 3; 0, 1, 2; }
+}
+}
+"#;
+        // a count not matching the vertices is ignored
+        let scene = import(source);
+        assert_eq!(scene.meshes[0].vertices.len(), 6);
+
+        let mut properties = ImportProperties::default();
+        properties.set_bool(AI_CONFIG_IMPORT_X_STRICT_PARSING, true);
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf_with_properties(source, &mut scene, &properties)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("duplication index count"), "{error}");
+    }
+
+    #[test]
+    fn test_parse_borrows_names() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = Parser::parse(&source).unwrap().scene;
+        let names = scene.nodes.iter().map(|node| &node.name).chain(
+            scene
+                .nodes
+                .iter()
+                .flat_map(|node| &node.meshes)
+                .map(|mesh| &mesh.name),
+        );
+        for name in names {
+            assert!(matches!(name, Cow::Borrowed(_)), "{name}");
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_import_from_mmap() {
+        let mut properties = ImportProperties::default();
+        properties.set_bool(AI_CONFIG_IMPORT_X_STRICT_PARSING, true);
+        let path = get_model_path("X", "kwxport_test_cubewithvcolors.x");
+        let path = path.to_str().unwrap();
+        let mut mapped = AiScene::default();
+        // SAFETY: the test models are not modified
+        unsafe { Importer::import_from_mmap(path, &mut mapped, &properties) }.unwrap();
+        let mut read = AiScene::default();
+        Importer::import_from_file_with_properties(path, &mut read, &properties).unwrap();
+        assert_eq!(mapped.meshes.len(), read.meshes.len());
+        assert_eq!(mapped.meshes[0].vertices, read.meshes[0].vertices);
+
+        // the properties are forwarded, strict parsing rejects NaN literals
+        let path = std::env::temp_dir().join("assimp_rs_import_from_mmap.x");
+        fs::write(
+            &path,
+            "xof 0303txt 0032\nFrame Root { Mesh { 3; 0;0;0;, 1;0;0;, 0;1;1.#QNAN0;; 1; 3;0,1,2;; } }\n",
+        )
+        .unwrap();
+        let path_str = path.to_str().unwrap();
+        let mut scene = AiScene::default();
+        // SAFETY: the file is not modified
+        let lenient = unsafe {
+            Importer::import_from_mmap(path_str, &mut scene, &ImportProperties::default())
+        };
+        let strict = unsafe { Importer::import_from_mmap(path_str, &mut scene, &properties) };
+        fs::remove_file(&path).unwrap();
+        lenient.unwrap();
+        assert!(strict.is_err());
+    }
+
+    #[test]
+    fn test_read_from_compressed_file() {
+        let scene =
+            Importer::read_from_file(get_model_path("X", "test_cube_text.x.gz").to_str().unwrap())
+                .unwrap();
+        let expected =
+            Importer::read_from_file(get_model_path("X", "test_cube_text.x").to_str().unwrap())
+                .unwrap();
+        assert_eq!(scene.meshes.len(), expected.meshes.len());
+        assert_eq!(scene.meshes[0].vertices, expected.meshes[0].vertices);
+
+        let error = Importer::read_from_file(get_model_path("3MF", "box.3mf").to_str().unwrap())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            XFileImportError::ImportError(ImportError::AmbiguousArchive(3))
+        ));
+    }
+
+    #[test]
+    fn test_export_compressed() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = import(&source);
+        let properties = Default::default();
+        let mut compressed = Vec::new();
+        Exporter::new(&scene, &properties)
+            .write_compressed(&mut compressed)
+            .unwrap();
+        assert!(compressed.starts_with(b"xof 0303tzip"));
+
+        let reimported = import(&compressed);
+        assert_eq!(reimported.meshes.len(), scene.meshes.len());
+        assert_eq!(reimported.meshes[0].vertices, scene.meshes[0].vertices);
+        assert_eq!(
+            reimported.meshes[0].faces.len(),
+            scene.meshes[0].faces.len()
+        );
+    }
+
+    #[test]
+    fn test_export_properties() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = import(&source);
+        let export = |properties: &ExportProperties| {
+            let mut text = String::new();
+            Exporter::new(&scene, properties)
+                .write_to_stream(&mut text)
+                .unwrap();
+            text
+        };
+        let full = export(&ExportProperties::default());
+        // the templates in the header have the same names
+        let count = |text: &str, name: &str| text.matches(&format!("{name} {{")).count() - 1;
+        assert!(full.contains("Frame DXCC_ROOT {"));
+        assert!(count(&full, "MeshNormals") > 0);
+        assert!(count(&full, "MeshTextureCoords") > 0);
+        assert!(count(&full, "MeshVertexColors") > 0);
+
+        let mut properties = ExportProperties::default();
+        properties.set_int(AI_CONFIG_EXPORT_X_PRECISION, 3);
+        properties.set_bool(AI_CONFIG_EXPORT_X_NORMALS, false);
+        properties.set_bool(AI_CONFIG_EXPORT_X_VERTEX_COLORS, false);
+        properties.set_bool(AI_CONFIG_EXPORT_X_COLLAPSE_ROOT, true);
+        let small = export(&properties);
+        assert!(small.len() < full.len());
+        assert!(!small.contains("DXCC_ROOT"));
+        assert_eq!(count(&small, "MeshNormals"), 0);
+        assert_eq!(
+            count(&small, "MeshTextureCoords"),
+            count(&full, "MeshTextureCoords")
+        );
+        assert_eq!(count(&small, "MeshVertexColors"), 0);
+        assert!(small.contains("1.000, 0.000, 0.000, 0.000,"));
+
+        let reimported = import(small.as_bytes());
+        // without the frame of DXCC_ROOT
+        assert_eq!(
+            reimported.nodes.len(),
+            import(full.as_bytes()).nodes.len() - 1
+        );
+        assert_eq!(
+            reimported.meshes[0].vertices.len(),
+            scene.meshes[0].vertices.len()
+        );
+        assert!(reimported.meshes[0].colors[0].is_empty());
+    }
+
+    #[test]
+    fn test_export_to_writer() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = import(&source);
+        let properties = ExportProperties::default();
+        let text = Exporter::export_to_string(&scene, &properties).unwrap();
+        let mut data = Vec::new();
+        Exporter::export_to_writer(&scene, &properties, &mut data).unwrap();
+        assert_eq!(data, text.as_bytes());
+
+        // the error of the writer is passed on
+        struct Full(usize);
+        impl std::io::Write for Full {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(std::io::ErrorKind::StorageFull.into());
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let error = Exporter::export_to_writer(&scene, &properties, &mut Full(4096)).unwrap_err();
+        assert!(
+            matches!(&error, XFileExportError::IoError(e) if e.kind() == std::io::ErrorKind::StorageFull),
+            "{error:?}"
+        );
+
+        let path = std::env::temp_dir().join("assimp_rs_export_to_writer.x");
+        Exporter::export_to_file(&scene, &properties, &path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), text.as_bytes());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_export_streaming() {
+        // a grid large enough for many buffers and compressed blocks
+        let size = 200;
+        let vertices = (0..size * size)
+            .map(|i| Vec3::new((i % size) as AiReal, (i / size) as AiReal, 0.0))
+            .collect();
+        let faces = (0..size - 1)
+            .flat_map(|y| (0..size - 1).map(move |x| y * size + x))
+            .map(|i| AiFace {
+                indices: Box::new([i, i + 1, i + size + 1, i + size]),
+            })
+            .collect();
+        let mut builder = SceneBuilder::new();
+        let root = builder.add_node("grid", Mat4::IDENTITY, None);
+        builder.add_material(AiMaterial::default());
+        let mesh = builder.add_mesh(AiMesh {
+            name: "grid".to_owned(),
+            primitive_type: PrimitiveTypeFlags::POLYGON,
+            vertices,
+            faces,
+            ..Default::default()
+        });
+        builder.attach_mesh(root, mesh);
+        let scene = builder.build().unwrap();
+        let properties = ExportProperties::default();
+
+        #[derive(Default)]
+        struct Writes {
+            data: Vec<u8>,
+            count: usize,
+            largest: usize,
+        }
+        impl std::io::Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.count += 1;
+                self.largest = self.largest.max(buf.len());
+                self.data.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut writes = Writes::default();
+        Exporter::export_to_writer(&scene, &properties, &mut writes).unwrap();
+        assert!(writes.data.len() > 10 * EXPORT_BUFFER_SIZE);
+        assert!(writes.largest <= EXPORT_BUFFER_SIZE);
+        assert!(writes.count <= writes.data.len() / EXPORT_BUFFER_SIZE * 2 + 1);
+        let reimported = import(&writes.data);
+        assert_eq!(
+            reimported.meshes[0].faces.len(),
+            scene.meshes[0].faces.len()
+        );
+
+        // the blocks are the same as the ones of the whole text
+        #[cfg(feature = "compression")]
+        {
+            let mut compressed = Vec::new();
+            Exporter::new(&scene, &properties)
+                .write_compressed(&mut compressed)
+                .unwrap();
+            assert_eq!(
+                u32::from_le_bytes(compressed[16..20].try_into().unwrap()) as usize,
+                writes.data.len()
+            );
+            let reimported = import(&compressed);
+            assert_eq!(
+                reimported.meshes[0].faces.len(),
+                scene.meshes[0].faces.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_import_adversarial_input() {
+        let inputs: [&[u8]; 4] = [
+            // vertex count far beyond the size of the file
+            b"xof 0303txt 0032\nMesh {\n4294967295;\n0.0;0.0;0.0;;\n}",
+            // overflowing face index and count
+            b"xof 0303txt 0032\nMesh {\n1;\n0.0;0.0;0.0;;\n1;\n99999999999;0;;\n}",
+            // bone weights referencing vertices which do not exist
+            b"xof 0303txt 0032\nMesh {\n3;\n0;0;0;,\n1;0;0;,\n0;1;0;;\n1;\n3;0,1,2;;\n\
+              SkinWeights {\n\"bone\";\n2;\n7,4000000000;\n1.0,1.0;\n\
+              1;0;0;0;0;1;0;0;0;0;1;0;0;0;0;1;;\n}\n}",
+            // binary file ending in the middle of an array
+            b"xof 0303bin 0032\x01\x00\x04\x00\x00\x00Mesh\x06\x00\xff\xff\xff\xff\x00",
+        ];
+        for input in inputs {
+            let mut scene = AiScene::default();
+            let _ = Importer::import_from_buf(input, &mut scene);
+        }
+    }
+
+    #[test]
+    fn test_import_allocation_limit() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let mut properties = ImportProperties::default();
+        properties.set_int(AI_CONFIG_IMPORT_MAX_ALLOC_BYTES, 64);
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf_with_properties(&source, &mut scene, &properties)
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            XFileImportError::XFileParseError {
+                error: XFileParseError::InsufficientMemory,
+                ..
+            }
+        ));
+
+        // the same file fits into a realistic budget
+        properties.set_int(AI_CONFIG_IMPORT_MAX_ALLOC_BYTES, 1 << 20);
+        let mut scene = AiScene::default();
+        Importer::import_from_buf_with_properties(&source, &mut scene, &properties).unwrap();
+        assert_eq!(scene.meshes.len(), 3);
+
+        // transparently decompressed files are limited as well
+        let path = get_model_path("X", "test_cube_text.x.gz");
+        properties.set_int(AI_CONFIG_IMPORT_MAX_ALLOC_BYTES, 64);
+        assert!(Importer::read_file_with_report(path.to_str().unwrap(), &properties).is_err());
+
+        // empty MSZIP blocks, each claiming a full block of output
+        let mut compressed = b"xof 0303tzip0032\0\0\0\0\0\0".to_vec();
+        for _ in 0..64 {
+            compressed.extend_from_slice(b"\0\0CK");
+        }
+        let mut scene = AiScene::default();
+        let error = Importer::import_from_buf_with_properties(&compressed, &mut scene, &properties)
+            .unwrap_err();
+        assert!(matches!(error, XFileImportError::InsufficientMemory));
+    }
+
+    #[test]
+    fn test_import_binary_doubles() {
+        // far from the origin, where single precision can not hold the
+        // fraction
+        let coordinates = [100_000_000.25, 0.1, -2.5, 1.0, 2.0, 3.0, 0.0, 0.0, 1.0e-12];
+        let mut source = b"xof 0303bin 0064".to_vec();
+        let mut write = |token: u16, data: &[u8]| {
+            source.extend(token.to_le_bytes());
+            source.extend(data);
+        };
+        let name = |name: &str| [&(name.len() as u32).to_le_bytes(), name.as_bytes()].concat();
+        let list = |values: &[u32]| {
+            let mut data = (values.len() as u32).to_le_bytes().to_vec();
+            values.iter().for_each(|v| data.extend(v.to_le_bytes()));
+            data
+        };
+        let mut floats = (coordinates.len() as u32).to_le_bytes().to_vec();
+        coordinates
+            .iter()
+            .for_each(|c: &f64| floats.extend(c.to_le_bytes()));
+
+        // Frame Root { Mesh { 3; <vertices>; 1; 3; 0, 1, 2;; } }
+        write(1, &name("Frame"));
+        write(1, &name("Root"));
+        write(0x0a, &[]);
+        write(1, &name("Mesh"));
+        write(0x0a, &[]);
+        write(6, &list(&[3]));
+        write(7, &floats);
+        write(6, &list(&[1, 3, 0, 1, 2]));
+        write(0x0b, &[]);
+        write(0x0b, &[]);
+
+        let scene = import(&source);
+        let vertices = &scene.meshes[0].vertices;
+        assert_eq!(vertices.len(), 3);
+        for (vertex, expected) in vertices.iter().zip(coordinates.chunks(3)) {
+            assert_eq!(vertex.to_array(), [0, 1, 2].map(|i| expected[i] as AiReal));
+        }
+        #[cfg(feature = "double_precision")]
+        assert_eq!(vertices[0].x, 100_000_000.25);
+    }
+
+    /// Converts an imported scene to the conventions of the C++ importer,
+    /// which transposes the matrices of the file and inverts its rotation
+    /// keys, converts to right handed coordinates and gives every face
+    /// corner its own vertex. It does not read the tangents of `DeclData`.
+    fn to_assimp_conventions(scene: &mut AiScene) {
+        fn gather<T: Copy>(values: &mut Vec<T>, corners: &[u32]) {
+            if !values.is_empty() {
+                *values = corners.iter().map(|&i| values[i as usize]).collect();
+            }
+        }
+
+        for node in &mut scene.nodes {
+            node.transformation = node.transformation.transpose();
+        }
+        for mesh in &mut scene.meshes {
+            let corners: Vec<u32> = mesh.faces.iter().flat_map(|f| f.indices.to_vec()).collect();
+            gather(&mut mesh.vertices, &corners);
+            gather(&mut mesh.normals, &corners);
+            for set in mesh.texture_coords.iter_mut() {
+                gather(set, &corners);
+            }
+            for set in mesh.colors.iter_mut() {
+                gather(set, &corners);
+            }
+            let mut next = 0;
+            for face in &mut mesh.faces {
+                for index in face.indices.iter_mut() {
+                    *index = next;
+                    next += 1;
+                }
+            }
+            for bone in &mut mesh.bones {
+                bone.offset_matrix = bone.offset_matrix.transpose();
+                let mut weights = HashMap::new();
+                for weight in &bone.weights {
+                    weights.insert(weight.vertex_id, weight.weight);
+                }
+                bone.weights = (0..)
+                    .zip(&corners)
+                    .filter_map(|(vertex_id, corner)| {
+                        let weight = *weights.get(corner)?;
+                        (weight > 0.0).then_some(AiVertexWeight { vertex_id, weight })
+                    })
+                    .collect();
+            }
+            mesh.tangents.clear();
+            mesh.bitangents.clear();
+        }
+        for animation in &mut scene.animations {
+            for channel in &mut animation.channels {
+                for key in &mut channel.rotation_keys {
+                    key.value = key.value.conjugate();
+                }
+            }
+        }
+        ConvertToLeftHandProcess::execute(scene);
+        FlipWindingOrderProcess::execute(scene);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_golden_models() {
+        // the dumps of the C++ library by `assimp dump <model> -b -z`
+        for name in [
+            "anim_test.x",
+            "BCN_Epileptic.X",
+            "fromtruespace_bin32.x",
+            "kwxport_test_cubewithvcolors.x",
+            "test.x",
+            "test_cube_binary.x",
+            "test_cube_compressed.x",
+            "test_cube_text.x",
+            "Testwuson.X",
+        ] {
+            let dump = golden::golden_path("X", name)
+                .unwrap_or_else(|| panic!("{name}: missing dump in X/ref/golden"));
+            let dump = DumpScene::from_file(&dump).unwrap();
+            let mut scene = import(&fs::read(get_model_path("X", name)).unwrap());
+            to_assimp_conventions(&mut scene);
+            let mismatches = golden::compare(&scene, &dump, &Tolerance::default());
+            assert!(mismatches.is_empty(), "{name}: {mismatches:?}");
+        }
+    }
+
+    #[test]
+    fn test_export_path_encoding() {
+        let mut builder = SceneBuilder::new();
+        let root = builder.add_node("root", Mat4::IDENTITY, None);
+        let mut mesh = AiMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2]),
+            }],
+            ..Default::default()
+        };
+        mesh.texture_coords[0] = vec![Vec3::ZERO; 3];
+        let mesh = builder.add_mesh(mesh);
+        builder.attach_mesh(root, mesh);
+        let mut material = AiMaterial::default();
+        material.set_texture(
+            AiTextureType::Diffuse,
+            0,
+            TextureSlot::new("maps\\grün €\"a\".png"),
+        );
+        builder.add_material(material);
+        let scene = builder.build().unwrap();
+
+        let mut properties = ExportProperties::default();
+        let text = Exporter::export_to_string(&scene, &properties).unwrap();
+        assert!(text.contains("TextureFilename { \"maps/grün ?'a'.png\"; }"));
+        let mut data = Vec::new();
+        Exporter::export_to_writer(&scene, &properties, &mut data).unwrap();
+        let latin1 = b"\"maps/gr\xfcn ?'a'.png\"";
+        assert!(data.windows(latin1.len()).any(|w| w == latin1));
+
+        properties.set_int(AI_CONFIG_EXPORT_X_PATH_ENCODING, 1);
+        let mut data = Vec::new();
+        Exporter::export_to_writer(&scene, &properties, &mut data).unwrap();
+        let material = &import(&data).materials[0];
+        assert_eq!(
+            material
+                .get_texture(AiTextureType::Diffuse, 0)
+                .map(|t| t.path),
+            Some("maps/grün €'a'.png".to_owned())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn test_import_legacy_encoding() {
+        use crate::structs::{
+            importer::AI_CONFIG_IMPORT_TEXT_ENCODING, meta::AI_METADATA_SOURCE_ENCODING,
+        };
+
+        let source = |name: &[u8]| {
+            [
+                b"xof 0303txt 0032\nFrame ".as_slice(),
+                name,
+                b" {\nMesh m {\n3; 0;0;0;, 1;0;0;, 0;1;0;;\n1; 3;0,1,2;;\nMeshMaterialList { 1; 1; 0;; Material ",
+                name,
+                b" { 1;1;1;1;; 0; 0;0;0;; 0;0;0;; } }\n}\n}\n",
+            ]
+            .concat()
+        };
+        let check = |scene: &AiScene, name: &str, encoding: Option<&str>| {
+            assert_eq!(scene.nodes[scene.root.unwrap().value()].name, name);
+            assert_eq!(scene.materials[0].name(), Some(name));
+            assert_eq!(
+                scene.metadata.get_str(AI_METADATA_SOURCE_ENCODING),
+                encoding
+            );
+        };
+
+        // the second byte of "表" is a backslash
+        check(
+            &import(&source(b"\x95\x5c\x96\xca")),
+            "表面",
+            Some("Shift_JIS"),
+        );
+        check(
+            &import(&source(b"Gr\xfcn_\xc4pfel")),
+            "Grün_Äpfel",
+            Some("windows-1252"),
+        );
+        check(&import(&source("Grün".as_bytes())), "Grün", None);
+
+        let mut properties = ImportProperties::default();
+        properties.set_string(AI_CONFIG_IMPORT_TEXT_ENCODING, "iso-8859-2");
+        let mut scene = AiScene::default();
+        Importer::import_from_buf_with_properties(
+            &source(b"\xb3\xf3d\xbc"),
+            &mut scene,
+            &properties,
+        )
+        .unwrap();
+        check(&scene, "łódź", Some("ISO-8859-2"));
+        properties.set_string(AI_CONFIG_IMPORT_TEXT_ENCODING, "klingon");
+        assert!(
+            Importer::import_from_buf_with_properties(&source(b"\xff"), &mut scene, &properties)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_import_error_policy() {
+        use crate::structs::importer::{
+            AI_CONFIG_IMPORT_ERROR_POLICY,
+            report::{ImportReport, IssueAction, IssueKind},
+        };
+
+        let quirks = br#"xof 0303txt 0032
+Frame Root {
+Mesh m {
+  4; 0;0;0;, 1;0;0;, 1;1;0;, 0;1;0;;
+  2; 3;0,1,7;, 3;0,2,3;;
+  MeshMaterialList { 1; 1; 0;; Material { 1;1;1;1;; 0; 0;0;0;; 0;0;0;; } }
+}
+}
+}
+"#;
+        let broken = br#"xof 0303txt 0032
+Frame Root {
+Mesh m {
+  3; 0;0;0;, 1;0;0;, 1;1;0;;
+  1; 3;0,1,2;;
+  MeshMaterialList { 1; 3; 0, 0, 0;; Material { 1;1;1;1;; 0; 0;0;0;; 0;0;0;; } }
+  MeshVertexColors { 2; 0;1;0;0;1;;, 5;0;1;0;1;;; }
+}
+}
+"#;
+        let import_with = |source: &[u8], policy: i32| {
+            let mut properties = ImportProperties::default();
+            properties.set_int(AI_CONFIG_IMPORT_ERROR_POLICY, policy);
+            let mut scene = AiScene::default();
+            Importer::import_from_buf_with_report(source, &mut scene, &properties)
+                .map(|report| (scene, report))
+        };
+        let kinds = |report: &ImportReport| {
+            report
+                .issues
+                .iter()
+                .map(|issue| (issue.kind, issue.action))
+                .collect::<Vec<_>>()
+        };
+
+        let (scene, report) = import_with(quirks, 0).unwrap();
+        assert_eq!(
+            kinds(&report),
+            [
+                (IssueKind::VertexIndexOutOfRange, IssueAction::Warned),
+                (IssueKind::MaterialIndexCount, IssueAction::Warned),
+                (IssueKind::UnbalancedBrace, IssueAction::Warned),
+            ]
+        );
+        assert_eq!(
+            report.issues[0].position.as_deref(),
+            Some("Line 5, column 13")
+        );
+        assert_eq!(scene.meshes.len(), 1);
+        assert!(import_with(quirks, 1).is_err());
+        assert!(import_with(broken, 0).is_err());
+
+        let (scene, report) = import_with(broken, 2).unwrap();
+        assert_eq!(
+            kinds(&report),
+            [
+                (IssueKind::MaterialIndexCount, IssueAction::Repaired),
+                (IssueKind::VertexDataCount, IssueAction::Repaired),
+                (IssueKind::VertexDataIndex, IssueAction::Repaired),
+            ]
+        );
+        assert_eq!(
+            scene.meshes[0].colors[0],
+            [
+                Color4D::new(1.0, 0.0, 0.0, 1.0),
+                Color4D::ZERO,
+                Color4D::ZERO
+            ]
+        );
+
+        // clean files have an empty report
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        assert!(import_with(&source, 2).unwrap().1.is_clean());
+    }
+
+    #[test]
+    fn test_import_report() {
+        use crate::structs::importer::report::IssueKind;
+
+        let path = get_model_path("X", "test_cube_text.x.gz");
+        let (scene, report) =
+            Importer::read_file_with_report(path.to_str().unwrap(), &ImportProperties::default())
+                .unwrap();
+        assert_eq!(scene.meshes.len(), 1);
+        assert!(report.is_clean());
+        assert_eq!(report.format.as_deref(), Some("DirectX X"));
+        assert_eq!(report.format_version.as_deref(), Some("3.3"));
+        assert!(
+            report
+                .timings
+                .iter()
+                .any(|entry| entry.scope == ProfileScope::Import && entry.name == "parse")
+        );
+
+        let source = [
+            &[0xEF, 0xBB, 0xBF][..],
+            br#"xof 0303txt 0032
+Frame Root {
+Mesh m {
+  3; 0;0;0;, 1;0;0;, 0;1;0;;
+  1; 3;0,1,2;;
+  MeshFaceWraps { 1; 0; 0;; }
+}
+}
+"#,
+        ]
+        .concat();
+        let (_, report) =
+            Importer::read_with_report(&source, &ImportProperties::default()).unwrap();
+        assert!(report.is_clean());
+        let skipped = report.skipped().collect::<Vec<_>>();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].kind, IssueKind::UnknownObject);
+        assert_eq!(skipped[0].message, "Unknown data object MeshFaceWraps");
+        assert_eq!(report.warnings().count() + report.repairs().count(), 0);
+    }
+
+    #[test]
+    fn test_import_animation_set_names() {
+        let animation = |name: &[u8]| {
+            [
+                b"AnimationSet ".as_slice(),
+                name,
+                b" {\nAnimation { { Root } AnimationKey { 2; 2; 0;3;0.0,0.0,0.0;;, 10;3;1.0,0.0,0.0;;; } }\n}\n",
+            ]
+            .concat()
+        };
+        let source = [
+            b"xof 0303txt 0032\nFrame Root {\nMesh { 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; }\n}\n"
+                .as_slice(),
+            &animation(b"walk"),
+            &animation(b""),
+            &animation(b"caf\xe9"),
+        ]
+        .concat();
+        let scene = import(&source);
+        let names: Vec<_> = scene.animations.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names[..2], ["walk", "AnimationSet_1"]);
+        // replaced or decoded as a legacy encoding, but never dropped
+        assert!(names[2].starts_with("caf") && names[2].len() > 3);
+        assert_eq!(scene.animations[0].duration, 10.0);
+
+        let scene = import(&fs::read(get_model_path("X", "anim_test.x")).unwrap());
+        assert_eq!(scene.animations[0].name, "cylinder_test");
+    }
+
+    #[test]
+    fn test_import_matrix_keys() {
+        use crate::compose;
+
+        let translation = Vec3::new(1.0, -2.0, 3.0);
+        let rotation = Quat::from_euler(glam::EulerRot::XYZ, 0.3, -0.7, 1.1);
+        let scale = Vec3::new(2.0, 0.5, 3.0);
+        // matrix keys with a non-uniform scale, the file stores the columns
+        let columns = compose(translation, rotation, scale)
+            .transpose()
+            .to_cols_array();
+        let columns: Vec<_> = columns.iter().map(|c| format!("{c:.7}")).collect();
+        let source = format!(
+            "xof 0303txt 0032\nFrame Root {{\nMesh {{ 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; }}\n}}\n\
+             AnimationSet {{ Animation {{ {{ Root }} AnimationKey {{ 4; 1; 0;16;{};;; }} }} }}\n",
+            columns.join(",")
+        );
+        let scene = import(source.as_bytes());
+        let channel = &scene.animations[0].channels[0];
+        assert!(
+            channel.position_keys[0]
+                .value
+                .abs_diff_eq(translation, 1e-4)
+        );
+        assert!(channel.scaling_keys[0].value.abs_diff_eq(scale, 1e-4));
+        let r = channel.rotation_keys[0].value;
+        assert!(r.abs_diff_eq(rotation, 1e-4) || r.abs_diff_eq(-rotation, 1e-4));
+    }
+
+    #[test]
+    fn test_binary_token_records() {
+        let mut source = b"xof 0303bin 0032".to_vec();
+        let mut write = |token: u16, data: &[u8]| {
+            source.extend(token.to_le_bytes());
+            source.extend(data);
+        };
+        let name = |name: &str| [&(name.len() as u32).to_le_bytes(), name.as_bytes()].concat();
+        let ints = |values: &[u32]| {
+            let mut data = (values.len() as u32).to_le_bytes().to_vec();
+            values.iter().for_each(|v| data.extend(v.to_le_bytes()));
+            data
+        };
+        let floats = |values: &[f32]| {
+            let mut data = (values.len() as u32).to_le_bytes().to_vec();
+            values.iter().for_each(|v| data.extend(v.to_le_bytes()));
+            data
+        };
+        let material = [1.0, 0.5, 0.25, 1.0, 8.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let guid = [
+            0x43, 0xab, 0x82, 0x3d, 0xda, 0x62, 0xcf, 0x11, 0xab, 0x39, 0x00, 0x20, 0xaf, 0x71,
+            0xe4, 0x33,
+        ];
+
+        // template Tag { <guid> STRING text; }
+        write(0x1f, &[]);
+        write(1, &name("Tag"));
+        write(0x0a, &[]);
+        write(5, &guid);
+        write(0x31, &[]);
+        write(1, &name("text"));
+        write(0x14, &[]);
+        write(0x0b, &[]);
+        // Frame Root { Mesh { <vertices> <faces> MeshMaterialList { ... } } }
+        write(1, &name("Frame"));
+        write(1, &name("Root"));
+        write(0x0a, &[]);
+        write(1, &name("Mesh"));
+        write(0x0a, &[]);
+        write(6, &ints(&[3]));
+        write(7, &floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]));
+        write(6, &ints(&[1, 3, 0, 1, 2]));
+        write(1, &name("MeshMaterialList"));
+        write(0x0a, &[]);
+        write(6, &ints(&[2, 1, 1]));
+        for (i, terminator) in [true, false].into_iter().enumerate() {
+            write(1, &name("Material"));
+            write(0x0a, &[]);
+            write(7, &floats(&material));
+            write(1, &name("TextureFilename"));
+            write(0x0a, &[]);
+            write(2, &name(&format!("tex{i}.png")));
+            if terminator {
+                write(0x14, &[]);
+            }
+            write(0x0b, &[]);
+            write(0x0b, &[]);
+        }
+        write(0x0b, &[]);
+        write(0x0b, &[]);
+        write(0x0b, &[]);
+
+        let parsed = Parser::parse(&source).unwrap().scene;
+        let tag = parsed.templates.get("Tag").unwrap();
+        assert_eq!(
+            tag.guid.as_deref(),
+            Some("<3D82AB43-62DA-11CF-AB39-0020AF71E433>")
+        );
+        let scene = import(&source);
+        assert_eq!(scene.meshes[0].vertices[1], Vec3::X);
+        let textures: Vec<_> = scene
+            .materials
+            .iter()
+            .flat_map(|m| m.properties.iter().filter_map(|p| p.texture_path()))
+            .collect();
+        assert_eq!(textures, ["tex0.png", "tex1.png"]);
+
+        // unknown tokens and records cut short are errors, not the end of
+        // the file
+        let mut unknown = source.clone();
+        unknown.truncate(16);
+        unknown.extend(0x99u16.to_le_bytes());
+        let error = Parser::parse(&unknown).unwrap_err();
+        assert!(error.to_string().contains("Unknown token 0x99"), "{error}");
+        let mut truncated = source[..16].to_vec();
+        truncated.extend(2u16.to_le_bytes());
+        truncated.extend(100u32.to_le_bytes());
+        truncated.extend(b"short");
+        assert!(Parser::parse(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_binary_bulk_lists() {
+        let mut source = b"xof 0303bin 0032".to_vec();
+        let mut write = |token: u16, data: &[u8]| {
+            source.extend(token.to_le_bytes());
+            source.extend(data);
+        };
+        let name = |name: &str| [&(name.len() as u32).to_le_bytes(), name.as_bytes()].concat();
+        let ints = |values: &[u32]| {
+            let mut data = (values.len() as u32).to_le_bytes().to_vec();
+            values.iter().for_each(|v| data.extend(v.to_le_bytes()));
+            data
+        };
+        let floats = |values: &[f32]| {
+            let mut data = (values.len() as u32).to_le_bytes().to_vec();
+            values.iter().for_each(|v| data.extend(v.to_le_bytes()));
+            data
+        };
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0];
+
+        // Mesh { 4; <vertices split over two lists>; 2; 3;0,1,2;, 3;1,3,2;;
+        //        MeshTextureCoords { 4; <uvs>; } }
+        write(1, &name("Frame"));
+        write(1, &name("Root"));
+        write(0x0a, &[]);
+        write(1, &name("Mesh"));
+        write(0x0a, &[]);
+        write(6, &ints(&[4]));
+        write(7, &floats(&positions[..5]));
+        write(7, &floats(&positions[5..]));
+        write(6, &ints(&[2, 3, 0, 1]));
+        // a single integer outside of a list
+        write(3, &2u32.to_le_bytes());
+        write(6, &ints(&[3, 1, 3, 2]));
+        write(1, &name("MeshTextureCoords"));
+        write(0x0a, &[]);
+        write(6, &ints(&[4]));
+        write(7, &floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0]));
+        write(0x0b, &[]);
+        write(0x0b, &[]);
+        write(0x0b, &[]);
+
+        let parsed = Parser::parse(&source).unwrap().scene;
+        let mesh = &parsed.nodes[0].meshes[0];
+        let expected: Vec<_> = positions
+            .chunks(3)
+            .map(|v| Vec3::new(v[0] as AiReal, v[1] as AiReal, v[2] as AiReal))
+            .collect();
+        assert_eq!(mesh.positions, expected);
+        let faces: Vec<_> = mesh.pos_faces.iter().map(<[u32]>::to_vec).collect();
+        assert_eq!(faces, [vec![0, 1, 2], vec![1, 3, 2]]);
+        assert_eq!(mesh.tex_coords[0][3], Vec3::new(1.0, 1.0, 0.0));
+
+        // cut in the middle of the vertex list
+        let error = Parser::parse(&source[..60]).unwrap_err();
+        assert!(error.to_string().contains("end of file"), "{error}");
+    }
+
+    #[test]
+    fn test_export_lights_cameras() {
+        use crate::structs::{exporter::AI_CONFIG_EXPORT_X_LIGHTS_CAMERAS, light::LightType};
+
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let mut scene = import(&source);
+        let node = scene.nodes.last().unwrap().name.clone();
+        scene.lights = vec![
+            AiLight {
+                name: node.clone(),
+                light_type: LightType::Spot,
+                position: Vec3::new(1.0, 2.0, 3.0),
+                direction: Vec3::NEG_Y,
+                attenuation_constant: 1.0,
+                attenuation_linear: 0.5,
+                color_diffuse: Color3D::new(1.0, 0.5, 0.25),
+                angle_inner_cone: 0.25,
+                angle_outer_cone: 0.5,
+                ..Default::default()
+            },
+            AiLight {
+                name: "sun".into(),
+                light_type: LightType::Directional,
+                direction: Vec3::new(0.0, -1.0, 1.0),
+                ..Default::default()
+            },
+            AiLight {
+                name: "area".into(),
+                light_type: LightType::Area,
+                ..Default::default()
+            },
+        ];
+        scene.cameras = vec![AiCamera {
+            name: "camera".into(),
+            position: Vec3::new(0.0, 1.0, -5.0),
+            look_at: Vec3::Z,
+            up: Vec3::Y,
+            horizontal_fov: 0.75,
+            clip_plane_near: 0.5,
+            clip_plane_far: 500.0,
+            aspect: 1.5,
+            ..Default::default()
+        }];
+
+        let text = Exporter::export_to_string(&scene, &ExportProperties::default()).unwrap();
+        assert!(!text.contains("Light"));
+        let mut properties = ExportProperties::default();
+        properties.set_bool(AI_CONFIG_EXPORT_X_LIGHTS_CAMERAS, true);
+        let text = Exporter::export_to_string(&scene, &properties).unwrap();
+        assert!(text.contains("template Light {") && text.contains("template Camera {"));
+
+        let imported = import(text.as_bytes());
+        // area lights have no D3D equivalent
+        assert_eq!(imported.lights.len(), 2);
+        let (spot, expected) = (&imported.lights[0], &scene.lights[0]);
+        assert_eq!(spot.name, node);
+        assert_eq!(spot.light_type, LightType::Spot);
+        assert_eq!(spot.position, expected.position);
+        assert_eq!(spot.direction, expected.direction);
+        assert_eq!(spot.color_diffuse, expected.color_diffuse);
+        assert_eq!(spot.attenuation_linear, 0.5);
+        assert_eq!((spot.angle_inner_cone, spot.angle_outer_cone), (0.25, 0.5));
+        assert_eq!(imported.lights[1].name, "sun");
+        assert_eq!(imported.lights[1].light_type, LightType::Directional);
+        assert_eq!(imported.cameras.len(), 1);
+        let (camera, expected) = (&imported.cameras[0], &scene.cameras[0]);
+        assert_eq!(camera.name, expected.name);
+        assert_eq!(
+            (camera.position, camera.look_at, camera.up),
+            (expected.position, expected.look_at, expected.up)
+        );
+        assert_eq!(camera.horizontal_fov, 0.75);
+        assert_eq!(camera.clip_plane_far, 500.0);
+        assert_eq!(camera.aspect, 1.5);
+    }
 }
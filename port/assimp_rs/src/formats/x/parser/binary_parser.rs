@@ -5,7 +5,7 @@ use crate::{
     AiReal,
     formats::x::{
         errors::{XFileBinaryParseError, XFileParseError},
-        parser::{XFileHeader, XFileParser},
+        parser::{XFileHeader, XFileParser, token::Token},
     },
 };
 
@@ -31,27 +31,79 @@ impl<'source> BinaryParser<'source> {
     }
 
     fn read_binary_word(&mut self) -> Result<u16, XFileParseError> {
-        let word = self
-            .forward(2)
-            .map_err(|_| XFileParseError::from(XFileBinaryParseError::ReadBinaryWordError))?;
-        Ok(u16::from_le_bytes([word[0], word[1]]))
+        let word = self.take::<2>().ok_or(XFileParseError::from(
+            XFileBinaryParseError::ReadBinaryWordError,
+        ))?;
+        Ok(u16::from_le_bytes(word))
     }
 
-    unsafe fn read_binary_word_unchecked(&mut self) -> u16 {
-        let word = unsafe { self.forward_unchecked(2) };
-        u16::from_le_bytes([word[0], word[1]])
+    fn read_binary_dword(&mut self) -> Result<u32, XFileParseError> {
+        let dword = self.take::<4>().ok_or(XFileParseError::from(
+            XFileBinaryParseError::ReadBinaryDwordError,
+        ))?;
+        Ok(u32::from_le_bytes(dword))
     }
 
-    fn read_binary_dword(&mut self) -> Result<u32, XFileParseError> {
-        let dword = self
-            .forward(4)
-            .map_err(|_| XFileParseError::from(XFileBinaryParseError::ReadBinaryDwordError))?;
-        Ok(u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]]))
+    /// Reads the next `N` bytes, `None` if less are left
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (data, rest) = self.source.split_first_chunk::<N>()?;
+        self.source = rest;
+        Some(*data)
+    }
+
+    /// Reads the data of a token record, `N` bytes
+    fn take_record<const N: usize>(
+        &mut self,
+        record: &'static str,
+    ) -> Result<&'source [u8; N], XFileParseError> {
+        let (data, rest) = self
+            .source
+            .split_first_chunk::<N>()
+            .ok_or(XFileParseError::unexpected_end_of_file(record))?;
+        self.source = rest;
+        Ok(data)
     }
 
-    unsafe fn read_binary_dword_unchecked(&mut self) -> u32 {
-        let dword = unsafe { self.forward_unchecked(4) };
-        u32::from_le_bytes([dword[0], dword[1], dword[2], dword[3]])
+    fn record_dword(&mut self, record: &'static str) -> Result<u32, XFileParseError> {
+        self.take_record::<4>(record)
+            .map(|v| u32::from_le_bytes(*v))
+    }
+
+    /// Reads a token record made of a count and as many elements of
+    /// `element_size` bytes
+    fn read_record(
+        &mut self,
+        record: &'static str,
+        element_size: usize,
+    ) -> Result<&'source [u8], XFileParseError> {
+        let count = self.record_dword(record)? as usize;
+        let size = count
+            .checked_mul(element_size)
+            .ok_or(XFileParseError::unexpected_end_of_file(record))?;
+        let (data, rest) = self
+            .source
+            .split_at_checked(size)
+            .ok_or(XFileParseError::unexpected_end_of_file(record))?;
+        self.source = rest;
+        Ok(data)
+    }
+
+    /// Starts a new number list if the last one is used up. A list is
+    /// either a single number or an array token (`array_token`) followed by
+    /// the number of elements.
+    fn next_list_element(&mut self, array_token: u16) {
+        if self.binary_num_count == 0
+            && let Some(token) = self.take::<2>()
+        {
+            self.binary_num_count = match self.peek::<4>() {
+                Some(_) if u16::from_le_bytes(token) == array_token => {
+                    self.read_binary_dword().unwrap_or_default()
+                }
+                _ => 1,
+            };
+        }
+        // an empty array or the end of file
+        self.binary_num_count = self.binary_num_count.saturating_sub(1);
     }
 }
 
@@ -60,12 +112,6 @@ impl<'source> XFileParser<'source> for BinaryParser<'source> {
         format!("Offset {:X}", self.offset())
     }
 
-    unsafe fn forward_unchecked(&mut self, n: usize) -> &'source [u8] {
-        let (data, rest) = unsafe { self.source.split_at_unchecked(n) };
-        self.source = rest;
-        data
-    }
-
     fn peek<const N: usize>(&self) -> Option<&'source [u8; N]> {
         self.source.get(..N).map(|slice| slice.try_into().unwrap())
     }
@@ -85,197 +131,138 @@ impl<'source> XFileParser<'source> for BinaryParser<'source> {
     }
 
     fn read_int(&mut self) -> Result<u32, XFileParseError> {
-        if self.binary_num_count == 0 && self.rest() >= 2 {
-            // SAFETY: we know that the next 2 bytes are a word
-            let tmp = unsafe { self.read_binary_word_unchecked() };
-            if tmp == 0x06 && self.rest() >= 4 {
-                // array of floats following
-                // SAFETY: we know that the next 4 bytes are a dword
-                self.binary_num_count = unsafe { self.read_binary_dword_unchecked() };
-            } else {
-                // single float following
-                self.binary_num_count = 1;
-            }
-        }
-        self.binary_num_count -= 1;
-        if self.rest() >= 4 {
-            // SAFETY: we know that the next 4 bytes are a dword
-            return Ok(unsafe { self.read_binary_dword_unchecked() });
-        } else {
-            self.source = &[];
-            return Ok(0);
-        }
+        // array of integers
+        self.next_list_element(0x06);
+        self.take::<4>()
+            .map(u32::from_le_bytes)
+            .ok_or(XFileParseError::unexpected_end_of_file("read_int"))
     }
 
     fn read_float(&mut self) -> Result<AiReal, XFileParseError> {
-        if self.binary_num_count == 0 && self.rest() >= 2 {
-            // SAFETY: we know that the next 2 bytes are a word
-            let tmp = unsafe { self.read_binary_word_unchecked() };
-            if tmp == 0x07 && self.rest() >= 4 {
-                // array of floats following
-                // SAFETY: we know that the next 4 bytes are a dword
-                self.binary_num_count = unsafe { self.read_binary_dword_unchecked() };
-            } else {
-                // single float following
-                self.binary_num_count = 1;
-            }
-        }
-        self.binary_num_count -= 1;
-        if self.binary_float_size == 8 {
-            if self.rest() >= 8 {
-                // SAFETY: we know that the next 8 bytes are a double
-                return Ok(f64::from_le_bytes(
-                    unsafe { self.forward_unchecked(8) }.try_into().unwrap(),
-                ) as f32);
-            } else {
-                self.source = &[];
-                return Ok(0.0);
-            }
+        // array of floats
+        self.next_list_element(0x07);
+        let value = if self.binary_float_size == 8 {
+            self.take::<8>().map(|v| f64::from_le_bytes(v) as AiReal)
         } else {
-            if self.rest() >= 4 {
-                return Ok(f32::from_le_bytes(
-                    unsafe { self.forward_unchecked(4) }.try_into().unwrap(),
-                ));
+            self.take::<4>()
+                .map(|v| AiReal::from(f32::from_le_bytes(v)))
+        };
+        value.ok_or(XFileParseError::unexpected_end_of_file("read_float"))
+    }
+
+    fn read_int_array(&mut self, values: &mut Vec<u32>, n: usize) -> Result<(), XFileParseError> {
+        values.reserve(n.min(self.source.len() / size_of::<u32>()));
+        let end = values.len() + n;
+        while values.len() < end {
+            if self.binary_num_count == 0 {
+                // starts the next list
+                values.push(self.read_int()?);
+                continue;
+            }
+            let count = (end - values.len()).min(self.binary_num_count as usize);
+            let data = self
+                .forward(count * size_of::<u32>())
+                .map_err(|_| XFileParseError::unexpected_end_of_file("read_int_array"))?;
+            values.extend(
+                data.chunks_exact(size_of::<u32>())
+                    .map(|v| u32::from_le_bytes(v.try_into().unwrap())),
+            );
+            self.binary_num_count -= count as u32;
+        }
+        Ok(())
+    }
+
+    fn read_float_array(&mut self, n: usize) -> Result<Vec<AiReal>, XFileParseError> {
+        let size = self.binary_float_size as usize;
+        let mut values = Vec::with_capacity(n.min(self.source.len() / size));
+        while values.len() < n {
+            if self.binary_num_count == 0 {
+                // starts the next list
+                values.push(self.read_float()?);
+                continue;
+            }
+            let count = (n - values.len()).min(self.binary_num_count as usize);
+            let data = self
+                .forward(count * size)
+                .map_err(|_| XFileParseError::unexpected_end_of_file("read_float_array"))?;
+            if size == 8 {
+                values.extend(
+                    data.chunks_exact(8)
+                        .map(|v| f64::from_le_bytes(v.try_into().unwrap()) as AiReal),
+                );
             } else {
-                self.source = &[];
-                return Ok(0.0);
+                values.extend(
+                    data.chunks_exact(4)
+                        .map(|v| AiReal::from(f32::from_le_bytes(v.try_into().unwrap()))),
+                );
             }
+            self.binary_num_count -= count as u32;
         }
+        Ok(values)
     }
 
-    fn next_token(&mut self) -> Result<&'source [u8], XFileParseError> {
-        let Ok(token) = self.read_binary_word() else {
-            return Ok(&[]);
-        };
+    fn next_token(&mut self) -> Result<Token<'source>, XFileParseError> {
+        if self.source.is_empty() {
+            return Ok(Token::Eof);
+        }
+        let offset = self.offset();
+        let token = self.read_binary_word()?;
         // References:
         // https://learn.microsoft.com/en-us/windows/win32/direct3d9/tokens
         // https://learn.microsoft.com/en-us/windows/win32/direct3d9/token-records
-        match token {
-            1 => {
-                let Ok(len) = self.read_binary_dword() else {
-                    return Ok(&[]);
-                };
-                let Ok(s) = self.forward(len as usize) else {
-                    return Ok(&[]);
-                };
-                return Ok(s);
-            }
+        let token = match token {
+            1 => Token::Identifier(self.read_record("name", 1)?),
             2 => {
-                let Ok(len) = self.read_binary_dword() else {
-                    return Ok(&[]);
-                };
-                let Ok(s) = self.forward(len as usize + 2) else {
-                    return Ok(&[]);
-                };
-                return Ok(&s[..s.len() - 2]);
-            }
-            3 => {
-                let _ = self.forward(4);
-                return Ok(b"<integer>");
-            }
+                let s = self.read_record("string", 1)?;
+                // the string is terminated by a separator token, which
+                // some exporters leave out
+                if let Some(&[0x13 | 0x14, 0]) = self.peek::<2>() {
+                    self.source = &self.source[2..];
+                }
+                Token::String(s)
+            }
+            3 => Token::BinaryInteger(self.record_dword("integer")?),
             5 => {
-                let _ = self.forward(16);
-                return Ok(b"<guid>");
-            }
-            6 => {
-                let Ok(count) = self.read_binary_dword() else {
-                    return Ok(&[]);
-                };
-                let size = count as usize * size_of::<u32>();
-                let _ = self.forward(size);
-                return Ok(b"<int_list>");
+                let guid = self.take_record::<16>("guid")?;
+                Token::BinaryGuid(guid)
             }
+            6 => Token::IntegerList(self.read_record("integer list", size_of::<u32>())?),
             7 => {
-                let Ok(count) = self.read_binary_dword() else {
-                    return Ok(&[]);
-                };
-                let size = count as usize * self.binary_float_size as usize;
-                let _ = self.forward(size);
-                return Ok(b"<flt_list>");
-            }
-            0x0a => {
-                return Ok(b"{");
-            }
-            0x0b => {
-                return Ok(b"}");
-            }
-            0x0c => {
-                return Ok(b"(");
-            }
-            0x0d => {
-                return Ok(b")");
-            }
-            0x0e => {
-                return Ok(b"[");
-            }
-            0x0f => {
-                return Ok(b"]");
-            }
-            0x10 => {
-                return Ok(b"<");
-            }
-            0x11 => {
-                return Ok(b">");
-            }
-            0x12 => {
-                return Ok(b".");
-            }
-            0x13 => {
-                return Ok(b",");
-            }
-            0x14 => {
-                return Ok(b";");
-            }
-            0x1f => {
-                return Ok(b"template");
-            }
-            0x28 => {
-                return Ok(b"WORD");
-            }
-            0x29 => {
-                return Ok(b"DWORD");
-            }
-            0x2a => {
-                return Ok(b"FLOAT");
-            }
-            0x2b => {
-                return Ok(b"DOUBLE");
-            }
-            0x2c => {
-                return Ok(b"CHAR");
-            }
-            0x2d => {
-                return Ok(b"UCHAR");
-            }
-            0x2e => {
-                return Ok(b"SWORD");
-            }
-            0x2f => {
-                return Ok(b"SDWORD");
-            }
-            0x30 => {
-                return Ok(b"void");
-            }
-            0x31 => {
-                return Ok(b"string");
-            }
-            0x32 => {
-                return Ok(b"unicode");
-            }
-            0x33 => {
-                return Ok(b"cstring");
-            }
-            0x34 => {
-                return Ok(b"array");
-            }
-            _ => {
-                return Ok(&[]);
-            }
-        }
+                let size = self.binary_float_size as usize;
+                Token::FloatList(self.read_record("float list", size)?)
+            }
+            0x0a => Token::Punctuation(b'{'),
+            0x0b => Token::Punctuation(b'}'),
+            0x0c => Token::Punctuation(b'('),
+            0x0d => Token::Punctuation(b')'),
+            0x0e => Token::Punctuation(b'['),
+            0x0f => Token::Punctuation(b']'),
+            0x10 => Token::Punctuation(b'<'),
+            0x11 => Token::Punctuation(b'>'),
+            0x12 => Token::Punctuation(b'.'),
+            0x13 => Token::Punctuation(b','),
+            0x14 => Token::Punctuation(b';'),
+            0x1f => Token::Identifier(b"template"),
+            0x28 => Token::Identifier(b"WORD"),
+            0x29 => Token::Identifier(b"DWORD"),
+            0x2a => Token::Identifier(b"FLOAT"),
+            0x2b => Token::Identifier(b"DOUBLE"),
+            0x2c => Token::Identifier(b"CHAR"),
+            0x2d => Token::Identifier(b"UCHAR"),
+            0x2e => Token::Identifier(b"SWORD"),
+            0x2f => Token::Identifier(b"SDWORD"),
+            0x30 => Token::Identifier(b"void"),
+            0x31 => Token::Identifier(b"string"),
+            0x32 => Token::Identifier(b"unicode"),
+            0x33 => Token::Identifier(b"cstring"),
+            0x34 => Token::Identifier(b"array"),
+            token => return Err(XFileBinaryParseError::UnknownToken { token, offset }.into()),
+        };
+        Ok(token)
     }
 
     fn next_token_as_str(&mut self) -> Result<Cow<'source, str>, XFileParseError> {
         let token = self.next_token()?;
-        Ok(String::from_utf8_lossy(token))
+        Ok(String::from_utf8_lossy(token.as_bytes()))
     }
 }
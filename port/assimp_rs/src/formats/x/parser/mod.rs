@@ -5,40 +5,80 @@ use std::{borrow::Cow, mem};
 use zlib_rs::{InflateFlush, MAX_WBITS};
 
 mod binary_parser;
+pub mod template;
 mod text_parser;
+pub mod token;
+pub mod vertex_decl;
 
 use binary_parser::BinaryParser;
+use template::{Dimension, MemberType, Template, TemplateRegistry};
 use text_parser::TextParser;
+use token::Token;
+use vertex_decl::{DeclType, VertexElement};
 
 use crate::{
     AiReal,
+    core::profiler::{ProfileScope, Profiler},
     formats::x::{
         errors::{XFileImportError, XFileParseError},
         structs::{
-            AnimBone, Animation, Bone, BoneWeight, Face, Material, MatrixKey, Mesh, Node, Scene,
-            TexEntry,
+            AI_METADATA_X_ANIM_TICKS_PER_SECOND, AI_METADATA_X_COMMENTS, AnimBone, Animation, Bone,
+            BoneWeight, FaceList, Material, MatrixKey, Mesh, Node, Scene, TexEntry,
         },
     },
     structs::{
-        anim::AiAnimInterpolation,
-        color::{Color3D, Color4D},
+        camera::AiCamera,
+        color::Color4D,
+        importer::{
+            AI_CONFIG_IMPORT_ALLOW_PARTIAL, ImportProperties,
+            report::{ErrorPolicy, IssueAction, IssueKind},
+        },
         key::{AiQuatKey, AiVectorKey},
+        light::{AiLight, LightType},
+        meta::{Metadata, MetadataEntry},
         nodes::Index,
     },
     utils::{
+        alloc_budget::AllocBudget,
         compression::{Compression, Format},
-        float_precision::{Mat4, Quat, Vec2, Vec3, Vec4},
+        float_precision::{Mat4, Quat, Vec2, Vec3, Vec4, to_f32},
         read::parse_4digits_decimal,
     },
 };
 
-const AI_MAX_NUMBER_OF_TEXTURECOORDS: usize = 0x8;
-
 const MSZIP_BLOCK: usize = 32786;
 const MSZIP_MAGIC: u16 = u16::from_le_bytes([b'C', b'K']);
 
 pub struct Parser;
 
+/// Options controlling how forgiving the parser is
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParserOptions {
+    /// Keep the data parsed so far if the file ends unexpectedly, see
+    /// [`AI_CONFIG_IMPORT_ALLOW_PARTIAL`]
+    pub allow_partial: bool,
+
+    /// How deviations from the specification are handled. The lenient
+    /// policies tolerate the quirks of known exporters, e.g. `1.#IND00`
+    /// floats, a single material index for all faces or out of range face
+    /// indices.
+    pub policy: ErrorPolicy,
+
+    /// Memory limit in bytes for the parsed data, see
+    /// [`ImportProperties::max_alloc_bytes`]
+    pub max_alloc_bytes: Option<usize>,
+}
+
+impl ParserOptions {
+    pub fn from_properties(properties: &ImportProperties) -> Self {
+        Self {
+            allow_partial: properties.get_bool(AI_CONFIG_IMPORT_ALLOW_PARTIAL),
+            policy: ErrorPolicy::from_properties(properties),
+            max_alloc_bytes: properties.max_alloc_bytes(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct XFileHeader {
     pub major_version: u8,
@@ -53,14 +93,33 @@ impl XFileHeader {
 }
 
 #[derive(Debug, Clone)]
-pub struct XFile {
+pub struct XFile<'source> {
     pub header: XFileHeader,
-    pub scene: Scene,
+    pub scene: Scene<'source>,
 }
 
 impl Parser {
-    pub fn parse<'source>(source: &'source [u8]) -> Result<XFile, XFileImportError> {
-        let (header, source) = Self::parse_header(source)?;
+    pub fn parse<'source>(source: &'source [u8]) -> Result<XFile<'source>, XFileImportError> {
+        Self::parse_with_options(source, ParserOptions::default())
+    }
+
+    pub fn parse_with_options<'source>(
+        source: &'source [u8],
+        options: ParserOptions,
+    ) -> Result<XFile<'source>, XFileImportError> {
+        Self::parse_with_profiler(source, options, &mut Profiler::disabled())
+    }
+
+    /// Parses like [`Self::parse_with_options`] and records the durations of
+    /// the `header`, `decompression` and `parse` phases in `profiler`.
+    pub fn parse_with_profiler<'source>(
+        source: &'source [u8],
+        options: ParserOptions,
+        profiler: &mut Profiler,
+    ) -> Result<XFile<'source>, XFileImportError> {
+        let (header, source) = profiler.measure(ProfileScope::Import, "header", || {
+            Self::parse_header(source)
+        })?;
 
         let XFileHeader {
             is_compressed,
@@ -68,14 +127,30 @@ impl Parser {
             binary_float_size,
             ..
         } = header;
+        let budget = AllocBudget::new(options.max_alloc_bytes);
 
         Ok(XFile {
             header,
             scene: {
                 let mut scene = if is_compressed {
-                    Self::parse_compressed_file(source, is_binary_format, binary_float_size)?
+                    Self::parse_compressed_file(
+                        source,
+                        is_binary_format,
+                        binary_float_size,
+                        options,
+                        budget,
+                        profiler,
+                    )?
                 } else {
-                    Self::parse_by_format(source, is_binary_format, binary_float_size)?
+                    profiler.measure(ProfileScope::Import, "parse", || {
+                        Self::parse_by_format(
+                            source,
+                            is_binary_format,
+                            binary_float_size,
+                            options,
+                            budget,
+                        )
+                    })?
                 };
                 Self::filter_hierarchy(&mut scene);
                 scene
@@ -84,30 +159,75 @@ impl Parser {
     }
 
     /// Filters the imported hierarchy for some degenerated cases that some exporters produce.
+    ///
+    /// Afterwards the nodes are stored in depth first order, starting with
+    /// the root node, and nodes not reachable from the root are dropped.
     fn filter_hierarchy(scene: &mut Scene) {
-        if let Some(root) = scene.root_node {
-            let mut filter = vec![];
-            let mut stack = vec![root];
-            while let Some(node) = stack.pop() {
-                let mut node =
-                    mem::replace(scene.nodes.get_mut(node.value()).unwrap(), Node::default());
-                // if the node has just a single unnamed child containing a mesh, remove
-                // the anonymous node between. The 3DSMax kwXport plugin seems to produce this
-                // mess in some cases
-                if node.meshes.is_empty() && node.children.len() == 1 {
-                    let child = *node.children.first().unwrap();
-                    let child = scene.nodes.get_mut(child.value()).unwrap();
-                    if child.name.is_empty() && !child.meshes.is_empty() {
-                        // transfer its meshes to us
-                        node.meshes.extend(child.meshes.drain(..));
-                        node.transformation_matrix *= child.transformation_matrix;
-                    }
+        let Some(root) = scene.root_node else {
+            return;
+        };
+        for i in 0..scene.nodes.len() {
+            // if the node has just a single unnamed child containing a mesh, remove
+            // the anonymous node between. The 3DSMax kwXport plugin seems to produce this
+            // mess in some cases
+            let node = &scene.nodes[i];
+            let &[child] = node.children.as_slice() else {
+                continue;
+            };
+            if !node.meshes.is_empty() {
+                continue;
+            }
+            let Some(child) = child.get_mut(&mut scene.nodes) else {
+                continue;
+            };
+            if !child.name.is_empty() || child.meshes.is_empty() {
+                continue;
+            }
+            // transfer its meshes to us, its children keep their transformation
+            // relative to the merged node
+            let meshes = mem::take(&mut child.meshes);
+            let metadata = mem::take(&mut child.metadata);
+            let grandchildren = mem::take(&mut child.children);
+            let transformation = child.transformation_matrix;
+            for &grandchild in &grandchildren {
+                if let Some(grandchild) = grandchild.get_mut(&mut scene.nodes) {
+                    grandchild.parent = Some(Index::new(i as u32));
                 }
-                stack.extend(node.children.drain(..));
-                filter.push(node);
             }
-            scene.nodes = filter;
+            let node = &mut scene.nodes[i];
+            node.meshes = meshes;
+            node.metadata.extend(metadata);
+            node.transformation_matrix *= transformation;
+            node.children = grandchildren;
+        }
+
+        // store the remaining nodes in depth first order
+        let mut new_index = vec![None; scene.nodes.len()];
+        let mut order = Vec::with_capacity(scene.nodes.len());
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            let Some(slot) = new_index.get_mut(node.value()) else {
+                continue;
+            };
+            if slot.is_some() {
+                continue;
+            }
+            *slot = Some(Index::new(order.len() as u32));
+            order.push(node);
+            stack.extend(scene.nodes[node.value()].children.iter().rev());
         }
+        let remap = |index: Index<Node>| new_index.get(index.value()).copied().flatten();
+        let mut nodes = mem::take(&mut scene.nodes);
+        scene.nodes = order
+            .into_iter()
+            .map(|index| {
+                let mut node = mem::take(&mut nodes[index.value()]);
+                node.parent = node.parent.and_then(remap);
+                node.children = node.children.into_iter().filter_map(remap).collect();
+                node
+            })
+            .collect();
+        scene.root_node = Some(Index::new(0));
     }
 
     fn parse_header<'source>(
@@ -119,6 +239,7 @@ impl Parser {
                 return Err(XFileImportError::XFileParseError {
                     position: 0.to_string(),
                     error: XFileParseError::NotEnoughDataToReadHeader(source.len()),
+                    snippet: None,
                 });
             }
         };
@@ -126,11 +247,18 @@ impl Parser {
             return Err(XFileImportError::XFileParseError {
                 position: 0.to_string(),
                 error: XFileParseError::UnsupportedFileFormat(header[..4].try_into().unwrap()),
+                snippet: None,
             });
         }
 
-        let major_version = (header[4] - b'0') * 10 + (header[5] - b'0');
-        let minor_version = (header[6] - b'0') * 10 + (header[7] - b'0');
+        // the version is informational only, garbage is passed through
+        let digit = |b: u8| b.wrapping_sub(b'0');
+        let major_version = digit(header[4])
+            .wrapping_mul(10)
+            .wrapping_add(digit(header[5]));
+        let minor_version = digit(header[6])
+            .wrapping_mul(10)
+            .wrapping_add(digit(header[7]));
 
         let file_format_signature: &[u8; 4] = &header[8..12].try_into().unwrap();
         let is_compressed;
@@ -151,6 +279,7 @@ impl Parser {
             return Err(XFileImportError::XFileParseError {
                 position: 8.to_string(),
                 error: XFileParseError::UnsupportedFileFormat(*file_format_signature),
+                snippet: None,
             });
         };
 
@@ -161,6 +290,7 @@ impl Parser {
             return Err(XFileImportError::XFileParseError {
                 position: 12.to_string(),
                 error: XFileParseError::UnsupportedFloatSize(binary_format_size),
+                snippet: None,
             });
         }
         let binary_float_size = (binary_format_size / u8::BITS) as u8;
@@ -180,42 +310,80 @@ impl Parser {
         source: &'source [u8],
         is_binary_format: bool,
         binary_float_size: u8,
-    ) -> Result<Scene, XFileImportError> {
+        options: ParserOptions,
+        budget: AllocBudget,
+    ) -> Result<Scene<'source>, XFileImportError> {
         if is_binary_format {
-            let mut parser = ParserImpl::new(
-                BinaryParser::new(source, binary_float_size),
-                is_binary_format,
-            );
-            if let Err(e) = parser.parse_file() {
-                Err(XFileImportError::XFileParseError {
-                    position: parser.get_position(),
-                    error: e,
-                })
-            } else {
-                Ok(parser.scene)
-            }
+            Self::run(
+                ParserImpl::new(
+                    BinaryParser::new(source, binary_float_size),
+                    is_binary_format,
+                    options.policy,
+                    budget,
+                ),
+                options,
+            )
         } else {
-            let mut parser = ParserImpl::new(TextParser::new(source), is_binary_format);
-            if let Err(e) = parser.parse_file() {
-                Err(XFileImportError::XFileParseError {
-                    position: parser.get_position(),
-                    error: e,
-                })
-            } else {
-                Ok(parser.scene)
+            Self::run(
+                ParserImpl::new(
+                    TextParser::new(source).strict(options.policy == ErrorPolicy::FailFast),
+                    is_binary_format,
+                    options.policy,
+                    budget,
+                ),
+                options,
+            )
+        }
+    }
+
+    fn run<'source, P: XFileParser<'source>>(
+        mut parser: ParserImpl<'source, P>,
+        options: ParserOptions,
+    ) -> Result<Scene<'source>, XFileImportError> {
+        let Err(error) = parser.parse_file() else {
+            return Ok(parser.scene);
+        };
+        let position = parser.get_position();
+        if options.allow_partial && (error.is_end_of_file() || parser.rest() == 0) {
+            // keep what we have, but drop data which is only half read
+            let mut scene = parser.scene;
+            for mesh in scene
+                .nodes
+                .iter_mut()
+                .flat_map(|node| node.meshes.iter_mut())
+                .chain(scene.global_meshes.iter_mut())
+            {
+                mesh.discard_incomplete_data();
             }
+            scene.report.push(
+                IssueKind::Truncated,
+                IssueAction::Warned,
+                Some(position.clone()),
+                error.to_string(),
+            );
+            scene.partial_error = Some(format!("{position}: {error}"));
+            return Ok(scene);
         }
+        Err(XFileImportError::XFileParseError {
+            position,
+            error,
+            snippet: parser.get_snippet(),
+        })
     }
 
     fn parse_compressed_file<'source>(
         mut source: &'source [u8],
         is_binary_format: bool,
         binary_float_size: u8,
-    ) -> Result<Scene, XFileImportError> {
+        options: ParserOptions,
+        mut budget: AllocBudget,
+        profiler: &mut Profiler,
+    ) -> Result<Scene<'source>, XFileImportError> {
         let start = source.as_ptr() as usize;
         let error_handler = |error: XFileParseError| XFileImportError::XFileParseError {
             position: format!("Offset {}", source.as_ptr() as usize - start),
             error,
+            snippet: None,
         };
         #[cfg(feature = "compression")]
         {
@@ -238,6 +406,7 @@ impl Parser {
              *  http://www.kdedevelopers.org/node/3181 has been very helpful.
              * ///////////////////////////////////////////////////////////////////////
              */
+            let decompression = profiler.start();
             // skip unknown data (checksum, flags?)
             if let Some((_, rest)) = source.split_at_checked(6) {
                 source = rest;
@@ -266,9 +435,12 @@ impl Parser {
                     )));
                 }
 
-                // and advance to the next offset
-                if let Some(s) = cloned_source.get(ofs..) {
+                // and advance to the next offset, the size of the next block
+                // is counted in `ofs`, so the last block is two bytes shorter
+                if let Some(s) = cloned_source.get(4 + ofs..) {
                     cloned_source = s;
+                } else if cloned_source.len() + 2 >= 4 + ofs {
+                    cloned_source = &[];
                 } else {
                     return Err(error_handler(XFileParseError::TooSmallZipFile {
                         left: cloned_source.len(),
@@ -277,18 +449,24 @@ impl Parser {
                 }
                 est_out += MSZIP_BLOCK; // one decompressed block is 32786 in size
             }
-            let mut decompressed_source: Vec<u8> = vec![0u8; est_out + 1];
-            let mut compression = Compression::new();
-            compression
-                .open(
-                    if is_binary_format {
-                        Format::Binary
-                    } else {
-                        Format::Text
-                    },
-                    InflateFlush::SyncFlush,
-                    -MAX_WBITS,
-                )
+            // a stream of empty blocks claims a lot of memory for little input
+            if !budget.charge::<u8>(est_out + 1) {
+                return Err(XFileImportError::InsufficientMemory);
+            }
+            let mut decompressed_source: Vec<u8> = Vec::new();
+            decompressed_source
+                .try_reserve_exact(est_out + 1)
+                .map_err(|_| XFileImportError::InsufficientMemory)?;
+            decompressed_source.resize(est_out + 1, 0);
+            let mut compression = Compression::builder()
+                .format(if is_binary_format {
+                    Format::Binary
+                } else {
+                    Format::Text
+                })
+                .flush_mode(InflateFlush::SyncFlush)
+                .window_bits(-MAX_WBITS)
+                .open()
                 .map_err(|e| error_handler(XFileParseError::DecompressionError(e)))?;
             let mut out = decompressed_source.as_mut_slice();
             while let &[a, b, _c, _d, ref rest @ ..] = source {
@@ -298,29 +476,42 @@ impl Parser {
                     return Err(XFileImportError::FileTooSmall);
                 }
 
+                // blocks never grow beyond MSZIP_BLOCK, anything else is cut off
+                let len = out.len().min(MSZIP_BLOCK);
                 let size = compression
-                    .decompress_block(source, &mut out[..MSZIP_BLOCK])
+                    .decompress_block(source, &mut out[..len])
                     .map_err(|e| error_handler(XFileParseError::DecompressionError(e)))?;
-                // SAFETY: size is guaranteed to be less than MSZIP_BLOCK
-                out = unsafe { out.get_unchecked_mut(size..) };
+                out = &mut mem::take(&mut out)[size..];
                 if let Some(s) = source.get(ofs..) {
                     source = s;
                 } else {
                     break;
                 }
             }
-            compression
-                .close()
-                .map_err(|e| error_handler(XFileParseError::DecompressionError(e)))?;
+            // drop the unused part of the estimated size
+            let unused = out.len();
+            decompressed_source.truncate(decompressed_source.len() - unused);
             drop(compression);
-
-            Self::parse_by_format(&decompressed_source, is_binary_format, binary_float_size)
+            profiler.finish(ProfileScope::Import, "decompression", decompression);
+
+            // the names can not borrow from the temporary buffer
+            profiler.measure(ProfileScope::Import, "parse", || {
+                Self::parse_by_format(
+                    &decompressed_source,
+                    is_binary_format,
+                    binary_float_size,
+                    options,
+                    budget,
+                )
+                .map(Scene::into_owned)
+            })
         }
         #[cfg(not(feature = "compression"))]
         {
             Err(XFileImportError::XFileParseError {
                 position: format!("Offset {}", start),
                 error: XFileParseError::CompressionFeatureNotEnabled,
+                snippet: None,
             })
         }
     }
@@ -329,6 +520,12 @@ impl Parser {
 pub(super) trait XFileParser<'source> {
     fn get_position(&self) -> String;
 
+    /// The source line at the current position with a caret under it, for
+    /// error messages.
+    fn get_snippet(&self) -> Option<String> {
+        None
+    }
+
     fn rest(&self) -> usize {
         0
     }
@@ -337,10 +534,6 @@ pub(super) trait XFileParser<'source> {
         Ok(&[])
     }
 
-    unsafe fn forward_unchecked(&mut self, _n: usize) -> &'source [u8] {
-        &[]
-    }
-
     fn peek<const N: usize>(&self) -> Option<&'source [u8; N]> {
         None
     }
@@ -351,8 +544,7 @@ pub(super) trait XFileParser<'source> {
 
     fn next_byte_if_eq(&mut self, test_byte: u8) {
         if self.peek_one() == Some(test_byte) {
-            // SAFETY: we know that the next byte is the test byte
-            unsafe { self.forward_unchecked(1) };
+            let _ = self.forward(1);
         }
     }
 
@@ -364,6 +556,31 @@ pub(super) trait XFileParser<'source> {
 
     fn read_float(&mut self) -> Result<AiReal, XFileParseError>;
 
+    /// Appends `n` integers to `values`, the binary parser copies them from
+    /// its integer lists in one go
+    fn read_int_array(&mut self, values: &mut Vec<u32>, n: usize) -> Result<(), XFileParseError> {
+        values.reserve(n.min(self.rest()));
+        for _ in 0..n {
+            values.push(self.read_int()?);
+        }
+        Ok(())
+    }
+
+    /// Reads `n` floats, the binary parser copies them from its float lists
+    /// in one go
+    fn read_float_array(&mut self, n: usize) -> Result<Vec<AiReal>, XFileParseError> {
+        let mut values = Vec::with_capacity(n.min(self.rest()));
+        for _ in 0..n {
+            values.push(self.read_float()?);
+        }
+        Ok(values)
+    }
+
+    /// Reads a float stored as `f32` in the scene in any precision
+    fn read_f32(&mut self) -> Result<f32, XFileParseError> {
+        self.read_float().map(to_f32)
+    }
+
     fn read_vec2(&mut self) -> Result<Vec2, XFileParseError> {
         let x = self.read_float()?;
         let y = self.read_float()?;
@@ -379,12 +596,13 @@ pub(super) trait XFileParser<'source> {
         Ok(Vec3::new(x, y, z))
     }
 
-    fn read_rgb(&mut self) -> Result<Color3D, XFileParseError> {
-        let r = self.read_float()?;
-        let g = self.read_float()?;
-        let b = self.read_float()?;
+    fn read_vec4(&mut self) -> Result<Vec4, XFileParseError> {
+        let x = self.read_float()?;
+        let y = self.read_float()?;
+        let z = self.read_float()?;
+        let w = self.read_float()?;
         self.test_for_separator();
-        Ok(Color3D::new(r as f32, g as f32, b as f32))
+        Ok(Vec4::new(x, y, z, w))
     }
 
     fn read_rgba(&mut self) -> Result<Color4D, XFileParseError> {
@@ -393,13 +611,24 @@ pub(super) trait XFileParser<'source> {
         let b = self.read_float()?;
         let a = self.read_float()?;
         self.test_for_separator();
-        Ok(Color4D::new(r as f32, g as f32, b as f32, a as f32))
+        Ok(Color4D::new(to_f32(r), to_f32(g), to_f32(b), to_f32(a)))
     }
 
-    fn next_token(&mut self) -> Result<&'source [u8], XFileParseError>;
+    fn next_token(&mut self) -> Result<Token<'source>, XFileParseError>;
 
     fn next_token_as_str(&mut self) -> Result<Cow<'source, str>, XFileParseError>;
 
+    /// Returns the comments skipped so far.
+    fn take_comments(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Line breaks read so far, counted from 0 like assimp, which names
+    /// unnamed materials after it. Binary files have no lines.
+    fn line_number(&self) -> u32 {
+        0
+    }
+
     fn check_for_separator(&mut self) -> Result<(), XFileParseError> {
         Ok(())
     }
@@ -410,10 +639,8 @@ pub(super) trait XFileParser<'source> {
 
     fn check_for_closing_brace(&mut self) -> Result<(), XFileParseError> {
         let next = self.next_token()?;
-        if next != b"}" {
-            return Err(XFileParseError::ClosingBraceExpected(
-                String::from_utf8_lossy(next).into_owned(),
-            ));
+        if next != Token::Punctuation(b'}') {
+            return Err(XFileParseError::ClosingBraceExpected(next.to_string()));
         }
         Ok(())
     }
@@ -424,8 +651,9 @@ pub(super) trait XFileParser<'source> {
 struct ParserImpl<'source, P: XFileParser<'source>> {
     inner_parser: P,
     is_binary_format: bool,
-    line_number: u32,
-    scene: Scene,
+    policy: ErrorPolicy,
+    budget: AllocBudget,
+    scene: Scene<'source>,
     _marker: PhantomData<&'source [u8]>,
 }
 
@@ -434,6 +662,14 @@ impl<'source, P: XFileParser<'source>> XFileParser<'source> for ParserImpl<'sour
         self.inner_parser.get_position()
     }
 
+    fn get_snippet(&self) -> Option<String> {
+        self.inner_parser.get_snippet()
+    }
+
+    fn rest(&self) -> usize {
+        self.inner_parser.rest()
+    }
+
     fn peek<const N: usize>(&self) -> Option<&'source [u8; N]> {
         self.inner_parser.peek::<N>()
     }
@@ -446,7 +682,15 @@ impl<'source, P: XFileParser<'source>> XFileParser<'source> for ParserImpl<'sour
         self.inner_parser.read_float()
     }
 
-    fn next_token(&mut self) -> Result<&'source [u8], XFileParseError> {
+    fn read_int_array(&mut self, values: &mut Vec<u32>, n: usize) -> Result<(), XFileParseError> {
+        self.inner_parser.read_int_array(values, n)
+    }
+
+    fn read_float_array(&mut self, n: usize) -> Result<Vec<AiReal>, XFileParseError> {
+        self.inner_parser.read_float_array(n)
+    }
+
+    fn next_token(&mut self) -> Result<Token<'source>, XFileParseError> {
         self.inner_parser.next_token()
     }
 
@@ -454,6 +698,14 @@ impl<'source, P: XFileParser<'source>> XFileParser<'source> for ParserImpl<'sour
         self.inner_parser.next_token_as_str()
     }
 
+    fn take_comments(&mut self) -> Vec<String> {
+        self.inner_parser.take_comments()
+    }
+
+    fn line_number(&self) -> u32 {
+        self.inner_parser.line_number()
+    }
+
     fn check_for_separator(&mut self) -> Result<(), XFileParseError> {
         self.inner_parser.check_for_separator()
     }
@@ -466,90 +718,214 @@ impl<'source, P: XFileParser<'source>> XFileParser<'source> for ParserImpl<'sour
         self.inner_parser.test_for_separator()
     }
 }
+/// Appends a default element and returns it
 impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
+    /// Capacity to reserve for `count` elements read from the file. Every
+    /// element takes at least one byte, so the remaining input limits the
+    /// memory a corrupt count can allocate up front.
+    fn capacity_for(&self, count: u32) -> usize {
+        (count as usize).min(self.rest())
+    }
+
+    /// Reserves room for `count` more elements read from the file, after
+    /// charging them to the allocation budget.
+    fn reserve<T>(&mut self, vec: &mut Vec<T>, count: u32) -> Result<(), XFileParseError> {
+        if !self.budget.charge::<T>(count as usize) {
+            return Err(XFileParseError::InsufficientMemory);
+        }
+        vec.try_reserve(self.capacity_for(count))
+            .map_err(|_| XFileParseError::InsufficientMemory)
+    }
+
+    /// Reads `count` vectors into `vec`. Text files separate the vectors,
+    /// binary files store them in float lists which are read in one go.
+    fn read_vec3_array(&mut self, vec: &mut Vec<Vec3>, count: u32) -> Result<(), XFileParseError> {
+        self.reserve(vec, count)?;
+        if self.is_binary_format {
+            let values = self.read_float_array(count as usize * 3)?;
+            vec.extend(values.chunks_exact(3).map(Vec3::from_slice));
+        } else {
+            for _ in 0..count {
+                vec.push(self.read_vec3()?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::read_vec3_array`] for texture coordinates
+    fn read_vec2_array(&mut self, vec: &mut Vec<Vec3>, count: u32) -> Result<(), XFileParseError> {
+        self.reserve(vec, count)?;
+        if self.is_binary_format {
+            let values = self.read_float_array(count as usize * 2)?;
+            vec.extend(values.chunks_exact(2).map(|v| Vec3::new(v[0], v[1], 0.0)));
+        } else {
+            for _ in 0..count {
+                vec.push(self.read_vec2()?.extend(0.0));
+            }
+        }
+        Ok(())
+    }
+
     /// Source should be bytes of valid UTF-8 text.
     #[inline]
-    pub fn new(inner_parser: P, is_binary_format: bool) -> Self {
+    pub fn new(
+        inner_parser: P,
+        is_binary_format: bool,
+        policy: ErrorPolicy,
+        budget: AllocBudget,
+    ) -> Self {
         Self {
             inner_parser,
             is_binary_format,
-            line_number: 0,
+            policy,
+            budget,
             scene: Scene::default(),
             _marker: PhantomData,
         }
     }
 
+    /// Handles a quirk of some exporters: fails with `error` under
+    /// [`ErrorPolicy::FailFast`], else reports it and goes on.
+    fn tolerate(
+        &mut self,
+        kind: IssueKind,
+        error: XFileParseError,
+        handling: &str,
+    ) -> Result<(), XFileParseError> {
+        if self.policy == ErrorPolicy::FailFast {
+            return Err(error);
+        }
+        let position = self.get_position();
+        self.scene.report.push(
+            kind,
+            IssueAction::Warned,
+            Some(position),
+            format!("{error}, {handling}"),
+        );
+        Ok(())
+    }
+
+    /// Handles inconsistent data: fails with `error` unless the policy is
+    /// [`ErrorPolicy::BestEffort`], which reports the repair and goes on.
+    fn repair(
+        &mut self,
+        kind: IssueKind,
+        error: XFileParseError,
+        repair: &str,
+    ) -> Result<(), XFileParseError> {
+        if self.policy != ErrorPolicy::BestEffort {
+            return Err(error);
+        }
+        let position = self.get_position();
+        self.scene.report.push(
+            kind,
+            IssueAction::Repaired,
+            Some(position),
+            format!("{error}, {repair}"),
+        );
+        Ok(())
+    }
+
     fn parse_file(&mut self) -> Result<(), XFileParseError> {
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                break;
-            }
             // parse specific object
-            if token == b"template" {
-                self.parse_data_object_template()?;
-            } else if token == b"Frame" {
-                self.parse_data_object_frame(None)?;
-            } else if token == b"Mesh" {
-                // some meshes have no frames at all
-                let mut mesh = Mesh::default();
-                self.parse_data_object_mesh(&mut mesh)?;
-                self.scene.global_meshes.push(mesh);
-            } else if token == b"AnimTicksPerSecond" {
-                self.parse_data_object_anim_ticks_per_second()?;
-            } else if token == b"AnimationSet" {
-                self.parse_data_object_animation_set()?;
-            } else if token == b"Material" {
-                // Material outside of a mesh or node
-                let material = self.parse_data_object_material()?;
-                self.scene.global_materials.push(material);
-            } else if token == b"}" {
-                // whatever?
-            } else {
-                self.parse_unknown_data_object()?;
+            match self.next_token()? {
+                Token::Eof => break,
+                Token::Identifier(b"template") => self.parse_data_object_template()?,
+                Token::Identifier(b"Frame") => self.parse_data_object_frame(None)?,
+                Token::Identifier(b"Mesh") => {
+                    // some meshes have no frames at all
+                    let mut mesh = Mesh::default();
+                    // keep the mesh even if it is incomplete, for partial imports
+                    let result = self.parse_data_object_mesh(&mut mesh);
+                    self.scene.global_meshes.push(mesh);
+                    result?;
+                }
+                Token::Identifier(b"AnimTicksPerSecond") => {
+                    self.parse_data_object_anim_ticks_per_second()?
+                }
+                Token::Identifier(b"AnimationSet") => self.parse_data_object_animation_set()?,
+                Token::Identifier(b"Material") => {
+                    // Material outside of a mesh or node
+                    let material = self.parse_data_object_material()?;
+                    self.scene.global_materials.push(material);
+                }
+                Token::Identifier(b"Light") => {
+                    let light = self.parse_data_object_light(None)?;
+                    self.scene.lights.push(light);
+                }
+                Token::Identifier(b"Camera") => {
+                    let camera = self.parse_data_object_camera(None)?;
+                    self.scene.cameras.push(camera);
+                }
+                Token::Punctuation(b'}') => {
+                    // some exporters write one closing brace too many
+                    self.tolerate(
+                        IssueKind::UnbalancedBrace,
+                        XFileParseError::unexpected_token("data object", b"}"),
+                        "ignored",
+                    )?;
+                }
+                token => self.parse_data_object_user(token, None)?,
             }
         }
+        let comments = self.take_comments();
+        if !comments.is_empty() {
+            self.scene.metadata.insert(
+                AI_METADATA_X_COMMENTS.to_owned(),
+                MetadataEntry::String(comments.join("\n").into()),
+            );
+        }
         Ok(())
     }
 
     fn parse_data_object_frame(
         &mut self,
-        parent: Option<Index<Node>>,
+        parent: Option<Index<Node<'source>>>,
     ) -> Result<(), XFileParseError> {
-        let name = if let Ok(s) = self.read_head_of_data_object() {
-            if let Ok(s) = str::from_utf8(s) { s } else { "" }
-        } else {
-            ""
+        let name = match self.read_head_of_data_object() {
+            Ok(s) => str::from_utf8(s).unwrap_or_default(),
+            Err(e) => {
+                self.tolerate(IssueKind::InvalidName, e, "frame left unnamed")?;
+                ""
+            }
         };
-        let parent = parent.unwrap_or(Index::new(0));
         let mut node = Node::new(parent);
-        node.name = name.to_owned();
+        node.name = Cow::Borrowed(name);
 
         let node_index = self.scene.push_node(parent, node);
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_frame",
-                ));
-            }
-            if token == b"}" {
-                break; // frame finished
-            } else if token == b"Frame" {
-                self.parse_data_object_frame(Some(node_index))?; // child frame
-            } else if token == b"FrameTransformMatrix" {
-                let matrix = self.parse_data_object_transformation_matrix()?;
-                // SAFETY: node_index is guaranteed to be valid
-                let node = unsafe { node_index.get_mut_unchecked(&mut self.scene.nodes) };
-                node.transformation_matrix = matrix;
-            } else if token == b"Mesh" {
-                let mut mesh = Mesh::new(name.to_owned());
-                self.parse_data_object_mesh(&mut mesh)?;
-                // SAFETY: node_index is guaranteed to be valid
-                let node = unsafe { node_index.get_mut_unchecked(&mut self.scene.nodes) };
-                node.meshes.push(mesh);
-            } else {
-                self.parse_unknown_data_object()?;
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_frame",
+                    ));
+                }
+                Token::Punctuation(b'}') => break, // frame finished
+                Token::Identifier(b"Frame") => {
+                    self.parse_data_object_frame(Some(node_index))?; // child frame
+                }
+                Token::Identifier(b"FrameTransformMatrix") => {
+                    let matrix = self.parse_data_object_transformation_matrix()?;
+                    self.scene.nodes[node_index.value()].transformation_matrix = matrix;
+                }
+                Token::Identifier(b"Mesh") => {
+                    let mut mesh = Mesh::new(Cow::Borrowed(name));
+                    // keep the mesh even if it is incomplete, for partial imports
+                    let result = self.parse_data_object_mesh(&mut mesh);
+                    self.scene.nodes[node_index.value()].meshes.push(mesh);
+                    result?;
+                }
+                Token::Identifier(b"Light") => {
+                    // the light belongs to the frame it is declared in
+                    let light = self.parse_data_object_light(Some(name))?;
+                    self.scene.lights.push(light);
+                }
+                Token::Identifier(b"Camera") => {
+                    let camera = self.parse_data_object_camera(Some(name))?;
+                    self.scene.cameras.push(camera);
+                }
+                token => self.parse_data_object_user(token, Some(node_index))?,
             }
         }
         Ok(())
@@ -557,33 +933,126 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
     fn read_head_of_data_object(&mut self) -> Result<&'source [u8], XFileParseError> {
         let name_or_brace = self.next_token()?;
-        if name_or_brace != b"{" {
+        if name_or_brace != Token::Punctuation(b'{') {
             let next = self.next_token()?;
-            if next != b"{" {
-                return Err(XFileParseError::unexpected_token("{", name_or_brace));
+            if next != Token::Punctuation(b'{') {
+                return Err(XFileParseError::unexpected_token(
+                    "{",
+                    name_or_brace.as_bytes(),
+                ));
             } else {
-                return Ok(name_or_brace);
+                return Ok(name_or_brace.as_bytes());
             }
         }
         Ok(&[])
     }
 
     fn parse_data_object_template(&mut self) -> Result<(), XFileParseError> {
-        let _name = self.read_head_of_data_object()?;
-        let _guid = self.next_token()?;
-
+        let name = self.read_head_of_data_object()?;
+        let mut tokens = Vec::new();
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_template",
-                ));
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_template",
+                    ));
+                }
+                Token::Punctuation(b'}') => break,
+                token => tokens.push(token),
             }
+        }
+        match Template::from_tokens(name, &tokens) {
+            Ok(template) => self.scene.templates.insert(template),
+            Err(e) => self.tolerate(IssueKind::InvalidTemplate, e, "template ignored")?,
+        }
+        Ok(())
+    }
 
-            if token == b"}" {
-                return Ok(());
-            }
+    /// Reads the members of an instance of `template` in the declared
+    /// order, keyed by their names. Arrays become nested metadata keyed by
+    /// the element indices.
+    fn read_template_instance(
+        &mut self,
+        templates: &TemplateRegistry,
+        template: &Template,
+    ) -> Result<Metadata, XFileParseError> {
+        let mut values = Metadata::default();
+        for member in &template.members {
+            let value = if member.dimensions.is_empty() {
+                self.read_template_member(templates, &member.ty)?
+            } else {
+                let mut count: u32 = 1;
+                for dimension in &member.dimensions {
+                    let size = match dimension {
+                        Dimension::Fixed(size) => *size,
+                        Dimension::Member(name) => match values.get(name) {
+                            Some(MetadataEntry::UInt32(size)) => *size,
+                            Some(MetadataEntry::Int32(size)) => (*size).max(0) as u32,
+                            _ => 0,
+                        },
+                    };
+                    count = count.saturating_mul(size);
+                }
+                let mut elements = Vec::new();
+                self.reserve(&mut elements, count)?;
+                for _ in 0..count {
+                    elements.push(self.read_template_member(templates, &member.ty)?);
+                }
+                MetadataEntry::Metadata(Box::new(
+                    elements
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, element)| (i.to_string(), element))
+                        .collect(),
+                ))
+            };
+            values.insert(member.key(), value);
         }
+        Ok(values)
+    }
+
+    fn read_template_member(
+        &mut self,
+        templates: &TemplateRegistry,
+        ty: &MemberType,
+    ) -> Result<MetadataEntry, XFileParseError> {
+        Ok(match ty {
+            MemberType::Word | MemberType::Dword | MemberType::Uchar => {
+                MetadataEntry::UInt32(self.read_int()?)
+            }
+            MemberType::Char | MemberType::Sword | MemberType::Sdword => {
+                MetadataEntry::Int32(self.read_int()? as i32)
+            }
+            MemberType::Float | MemberType::Double => MetadataEntry::Float(self.read_float()?),
+            MemberType::String | MemberType::Unicode | MemberType::Cstring => {
+                match self.next_token()? {
+                    Token::String(value) => {
+                        self.test_for_separator();
+                        MetadataEntry::String(String::from_utf8_lossy(value).into())
+                    }
+                    Token::Eof => {
+                        return Err(XFileParseError::unexpected_end_of_file(
+                            "read_template_member",
+                        ));
+                    }
+                    token => {
+                        return Err(XFileParseError::unexpected_token(
+                            "string",
+                            token.as_bytes(),
+                        ));
+                    }
+                }
+            }
+            MemberType::Template(name) => {
+                // only readable templates are read, so the nested ones exist
+                let Some(template) = templates.get(name) else {
+                    return Err(XFileParseError::UnknownDataObject);
+                };
+                let values = self.read_template_instance(templates, template)?;
+                self.test_for_separator();
+                MetadataEntry::Metadata(Box::new(values))
+            }
+        })
     }
 
     fn parse_data_object_transformation_matrix(&mut self) -> Result<Mat4, XFileParseError> {
@@ -615,57 +1084,128 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
         Ok(mat)
     }
 
-    fn parse_data_object_mesh(&mut self, m: &mut Mesh) -> Result<(), XFileParseError> {
+    fn parse_data_object_mesh(&mut self, m: &mut Mesh<'source>) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
         let num_of_vertices = self.read_int()?;
-        m.positions = Vec::with_capacity(num_of_vertices as usize);
-        for _ in 0..num_of_vertices {
-            let v = self.read_vec3()?;
-            m.positions.push(v);
-        }
+        m.positions = Vec::new();
+        self.read_vec3_array(&mut m.positions, num_of_vertices)?;
         let num_of_faces = self.read_int()?;
-        m.pos_faces = vec![Face::default(); num_of_faces as usize];
-        for face in m.pos_faces.iter_mut() {
+        m.pos_faces = FaceList::new();
+        self.reserve(&mut m.pos_faces.ends, num_of_faces)?;
+        for _ in 0..num_of_faces {
             let num_indices = self.read_int()?;
-            for _ in 0..num_indices {
-                let idx = self.read_int()?;
-                if idx <= num_of_vertices {
-                    face.indices.push(idx);
+            let faces = &mut m.pos_faces;
+            self.reserve(&mut faces.indices, num_indices)?;
+            let start = faces.indices.len();
+            self.read_int_array(&mut faces.indices, num_indices as usize)?;
+            // drop indices out of range in place
+            let mut kept = start;
+            for i in start..faces.indices.len() {
+                let idx = faces.indices[i];
+                if idx < num_of_vertices {
+                    faces.indices[kept] = idx;
+                    kept += 1;
+                } else {
+                    self.tolerate(
+                        IssueKind::VertexIndexOutOfRange,
+                        XFileParseError::VertexIndexOutOfRange {
+                            index: idx,
+                            count: num_of_vertices,
+                        },
+                        "index dropped",
+                    )?;
                 }
             }
+            faces.indices.truncate(kept);
+            faces.finish_face();
             self.test_for_separator();
         }
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_mesh",
-                ));
-            }
-            if token == b"}" {
-                return Ok(());
-            }
-            if token == b"MeshNormals" {
-                self.parse_data_object_mesh_normals(m)?;
-            } else if token == b"MeshTextureCoords" {
-                self.parse_data_object_mesh_texture_coords(m)?;
-            } else if token == b"MeshVertexColors" {
-                self.parse_data_object_mesh_vertex_colors(m)?;
-            } else if token == b"MeshMaterialList" {
-                self.parse_data_object_mesh_material_list(m)?;
-            } else if token == b"VertexDuplicationIndices" {
-                self.parse_unknown_data_object()?;
-            } else if token == b"XSkinMeshHeader" {
-                self.parse_data_object_skin_mesh_header()?;
-            } else if token == b"SkinWeights" {
-                self.parse_data_object_skin_weights(m)?;
-            } else {
-                self.parse_unknown_data_object()?;
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_mesh",
+                    ));
+                }
+                Token::Punctuation(b'}') => return Ok(()),
+                Token::Identifier(b"MeshNormals") => self.parse_data_object_mesh_normals(m)?,
+                Token::Identifier(b"MeshTextureCoords") => {
+                    self.parse_data_object_mesh_texture_coords(m)?
+                }
+                Token::Identifier(b"MeshVertexColors") => {
+                    self.parse_data_object_mesh_vertex_colors(m)?
+                }
+                Token::Identifier(b"MeshMaterialList") => {
+                    self.parse_data_object_mesh_material_list(m)?
+                }
+                Token::Identifier(b"VertexDuplicationIndices") => {
+                    self.parse_data_object_vertex_duplication_indices(m)?
+                }
+                Token::Identifier(b"XSkinMeshHeader") => {
+                    self.parse_data_object_skin_mesh_header()?
+                }
+                Token::Identifier(b"SkinWeights") => self.parse_data_object_skin_weights(m)?,
+                Token::Identifier(b"DeclData") => self.parse_data_object_decl_data(m)?,
+                Token::Identifier(b"FVFData") => self.parse_data_object_fvf_data(m)?,
+                token => self.parse_unknown_data_object(token)?,
             }
         }
     }
 
-    fn parse_data_object_mesh_normals(&mut self, m: &mut Mesh) -> Result<(), XFileParseError> {
+    fn parse_data_object_decl_data(
+        &mut self,
+        m: &mut Mesh<'source>,
+    ) -> Result<(), XFileParseError> {
+        self.read_head_of_data_object()?;
+
+        // read vertex elements
+        let num_elements = self.read_int()?;
+        let mut elements = Vec::new();
+        self.reserve(&mut elements, num_elements)?;
+        for _ in 0..num_elements {
+            let ty = DeclType::try_from(self.read_int()?)?;
+            let method = self.read_int()?;
+            let usage = self.read_int()?;
+            let usage_index = self.read_int()?;
+            self.test_for_separator();
+            elements.push(VertexElement {
+                ty,
+                method,
+                usage,
+                usage_index,
+            });
+        }
+        self.test_for_separator();
+
+        let data = self.read_dword_array()?;
+        self.check_for_closing_brace()?;
+        m.merge_vertex_streams(&elements, &data)
+    }
+
+    fn parse_data_object_fvf_data(&mut self, m: &mut Mesh<'source>) -> Result<(), XFileParseError> {
+        self.read_head_of_data_object()?;
+        let flags = self.read_int()?;
+        let data = self.read_dword_array()?;
+        self.check_for_closing_brace()?;
+        m.merge_vertex_streams(&VertexElement::from_fvf(flags), &data)
+    }
+
+    /// Reads a DWORD count followed by as many DWORDs.
+    fn read_dword_array(&mut self) -> Result<Vec<u32>, XFileParseError> {
+        let num_dwords = self.read_int()?;
+        let mut data = Vec::new();
+        self.reserve(&mut data, num_dwords)?;
+        for _ in 0..num_dwords {
+            data.push(self.read_int()?);
+        }
+        self.test_for_separator();
+        Ok(data)
+    }
+
+    fn parse_data_object_mesh_normals(
+        &mut self,
+        m: &mut Mesh<'source>,
+    ) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
 
         // read count
@@ -674,12 +1214,8 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
             return Ok(());
         }
 
-        m.normals.resize(num_of_normals as usize, Vec3::ZERO);
-
-        // read normal vectors
-        for normal in m.normals.iter_mut() {
-            *normal = self.read_vec3()?;
-        }
+        m.normals = Vec::new();
+        self.read_vec3_array(&mut m.normals, num_of_normals)?;
 
         // read normal indices
         let num_of_indices = self.read_int()?;
@@ -688,20 +1224,13 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
         }
 
         if num_of_indices > 0 {
-            m.norm_faces
-                .resize(num_of_indices as usize, Face::default());
-            for face in m.norm_faces.iter_mut() {
+            m.norm_faces = FaceList::new();
+            self.reserve(&mut m.norm_faces.ends, num_of_indices)?;
+            for _ in 0..num_of_indices {
                 let num_indices = self.read_int()?;
-                *face = Face::default();
-                face.indices
-                    .try_reserve(num_indices as usize)
-                    .map_err(|_| XFileParseError::InsufficientMemory)?;
-                for _ in 0..num_indices {
-                    let idx = self.read_int()?;
-                    // if idx <= num_indices {
-                    face.indices.push(idx);
-                    // }
-                }
+                self.reserve(&mut m.norm_faces.indices, num_indices)?;
+                self.read_int_array(&mut m.norm_faces.indices, num_indices as usize)?;
+                m.norm_faces.finish_face();
                 self.test_for_separator();
             }
         }
@@ -711,49 +1240,59 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
     fn parse_data_object_mesh_texture_coords(
         &mut self,
-        m: &mut Mesh,
+        m: &mut Mesh<'source>,
     ) -> Result<(), XFileParseError> {
-        self.read_head_of_data_object()?;
-        if m.num_textures + 1 > AI_MAX_NUMBER_OF_TEXTURECOORDS as u32 {
+        let name = self.read_head_of_data_object()?;
+        // take the first free set, vertex declarations may have filled some
+        let Some(set) = m.tex_coords.iter().position(|coords| coords.is_empty()) else {
             return Err(XFileParseError::TooManySetsOfTextureCoordinates);
-        }
+        };
+        m.num_textures = m.num_textures.max(set as u32 + 1);
+        m.num_uv_components[set] = 2;
+        m.tex_coords_names[set] = String::from_utf8_lossy(name);
 
-        let tex_coords = &mut m.tex_coords[m.num_textures as usize];
-        m.num_textures += 1;
         let num_coords = self.read_int()?;
         if num_coords != m.positions.len() as u32 {
             return Err(XFileParseError::TextureCoordCountDoesNotMatchVertexCount);
         }
 
-        tex_coords.resize(num_coords as usize, Vec2::ZERO);
-        for coord in tex_coords.iter_mut() {
-            *coord = self.read_vec2()?;
-        }
+        let mut tex_coords = Vec::new();
+        self.read_vec2_array(&mut tex_coords, num_coords)?;
+        m.tex_coords[set] = tex_coords;
         self.check_for_closing_brace()?;
         Ok(())
     }
 
     fn parse_data_object_mesh_vertex_colors(
         &mut self,
-        m: &mut Mesh,
+        m: &mut Mesh<'source>,
     ) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
         let Some(colors) = m.colors.get_mut(m.num_color_sets as usize) else {
             return Err(XFileParseError::TooManyColorSets);
         };
         m.num_color_sets += 1;
-        let num_colors = self.read_int()? as usize;
-        if num_colors != m.positions.len() {
-            return Err(XFileParseError::VertexColorCountDoesNotMatchVertexCount);
+        let num_colors = self.read_int()?;
+        if num_colors as usize != m.positions.len() {
+            self.repair(
+                IssueKind::VertexDataCount,
+                XFileParseError::VertexColorCountDoesNotMatchVertexCount,
+                "colors of missing vertices set to black",
+            )?;
         }
 
-        *colors = vec![Color4D::default(); num_colors];
+        self.reserve(colors, num_colors)?;
+        colors.resize(m.positions.len(), Color4D::default());
         for _ in 0..num_colors {
             let index = self.read_int()? as usize;
-
+            let color = self.read_rgba()?;
             match colors.get_mut(index) {
-                Some(color) => *color = self.read_rgba()?,
-                None => return Err(XFileParseError::VertexColorIndexOutOfBounds),
+                Some(c) => *c = color,
+                None => self.repair(
+                    IssueKind::VertexDataIndex,
+                    XFileParseError::VertexColorIndexOutOfBounds,
+                    "color ignored",
+                )?,
             }
             // HACK: (thom) Maxon Cinema XPort plugin puts a third separator here, kwxPort puts a comma.
             // Ignore gracefully.
@@ -766,7 +1305,7 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
     fn parse_data_object_mesh_material_list(
         &mut self,
-        m: &mut Mesh,
+        m: &mut Mesh<'source>,
     ) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
         // read material count
@@ -776,8 +1315,18 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
         // some models have a material index count of 1... to be able to read them we
         // replicate this single material index on every face
-        if num_mat_indices != m.pos_faces.len() && num_mat_indices != 1 {
-            return Err(XFileParseError::PerFaceMaterialIndexCountDoesNotMatchFaceCount);
+        match num_mat_indices {
+            count if count == m.pos_faces.len() => {}
+            1 => self.tolerate(
+                IssueKind::MaterialIndexCount,
+                XFileParseError::PerFaceMaterialIndexCountDoesNotMatchFaceCount,
+                "index used for all faces",
+            )?,
+            _ => self.repair(
+                IssueKind::MaterialIndexCount,
+                XFileParseError::PerFaceMaterialIndexCountDoesNotMatchFaceCount,
+                "indices cut or padded with the first one",
+            )?,
         }
 
         // read per-face material indices
@@ -792,6 +1341,7 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
         }
 
         // if there was only a single material index, replicate it on all faces
+        m.face_materials.truncate(m.pos_faces.len());
         if m.face_materials.len() < m.pos_faces.len() {
             m.face_materials.extend(
                 core::iter::repeat(m.face_materials.get(0).copied().unwrap_or_default())
@@ -801,70 +1351,198 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
         // read following data objects
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_mesh_material_list",
-                ));
-            }
-            if token == b"}" {
-                break; // material list finished
-            } else if token == b"{" {
-                // template materials
-                let mat_name = self.next_token()?;
-                let mut material = Material::default();
-                material.is_reference = true;
-
-                material.name = String::from_utf8_lossy(mat_name).into_owned();
-                m.materials.push(material);
-
-                self.check_for_closing_brace()?; // skip }
-            } else if token == b"Material" {
-                m.materials.push(self.parse_data_object_material()?);
-            } else if token == b";" {
-                // ignore
-            } else {
-                self.parse_unknown_data_object()?;
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_mesh_material_list",
+                    ));
+                }
+                Token::Punctuation(b'}') => break, // material list finished
+                Token::Punctuation(b'{') => {
+                    // template materials
+                    let mat_name = self.next_token()?;
+                    m.materials.push(Material {
+                        is_reference: true,
+                        name: String::from_utf8_lossy(mat_name.as_bytes()),
+                        ..Default::default()
+                    });
+
+                    self.check_for_closing_brace()?; // skip }
+                }
+                Token::Identifier(b"Material") => {
+                    m.materials.push(self.parse_data_object_material()?)
+                }
+                Token::Punctuation(b';') => {} // ignore
+                token => self.parse_unknown_data_object(token)?,
             }
         }
         Ok(())
     }
 
-    fn parse_data_object_material(&mut self) -> Result<Material, XFileParseError> {
+    /// Parses a light, laid out like `D3DLIGHT9`:
+    ///
+    /// ```text
+    /// template Light {
+    ///     DWORD type;          // 1 point, 2 spot, 3 directional
+    ///     ColorRGBA diffuse;
+    ///     ColorRGBA specular;
+    ///     ColorRGBA ambient;
+    ///     Vector position;
+    ///     Vector direction;
+    ///     FLOAT range;
+    ///     FLOAT falloff;
+    ///     FLOAT attenuation0;
+    ///     FLOAT attenuation1;
+    ///     FLOAT attenuation2;
+    ///     FLOAT theta;         // full inner cone angle in radians
+    ///     FLOAT phi;           // full outer cone angle in radians
+    /// }
+    /// ```
+    ///
+    /// Lights declared inside a frame are named after the frame.
+    fn parse_data_object_light(
+        &mut self,
+        frame_name: Option<&str>,
+    ) -> Result<AiLight, XFileParseError> {
+        let light_name = self.read_head_of_data_object()?;
+        let light_type = match self.read_int()? {
+            1 => LightType::Point,
+            2 => LightType::Spot,
+            3 => LightType::Directional,
+            _ => LightType::Undefined,
+        };
+        let diffuse = self.read_rgba()?;
+        let specular = self.read_rgba()?;
+        let ambient = self.read_rgba()?;
+        let position = self.read_vec3()?;
+        let direction = self.read_vec3()?;
+        let _range = self.read_float()?;
+        let _falloff = self.read_float()?;
+        let attenuation_constant = self.read_f32()?;
+        let attenuation_linear = self.read_f32()?;
+        let attenuation_quadratic = self.read_f32()?;
+        let theta = self.read_f32()?;
+        let phi = self.read_f32()?;
+        self.skip_data_object_rest("parse_data_object_light")?;
+
+        let name = match frame_name {
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ if !light_name.is_empty() => String::from_utf8_lossy(light_name).into_owned(),
+            _ => format!("Light_{}", self.scene.lights.len()),
+        };
+        let (angle_inner_cone, angle_outer_cone) = match light_type {
+            LightType::Spot => (theta * 0.5, phi * 0.5),
+            _ => (core::f32::consts::TAU, core::f32::consts::TAU),
+        };
+        Ok(AiLight {
+            name,
+            light_type,
+            position,
+            direction,
+            up: Vec3::Y,
+            attenuation_constant,
+            attenuation_linear,
+            attenuation_quadratic,
+            color_diffuse: diffuse.truncate(),
+            color_specular: specular.truncate(),
+            color_ambient: ambient.truncate(),
+            angle_inner_cone,
+            angle_outer_cone,
+            ..Default::default()
+        })
+    }
+
+    /// Parses a camera:
+    ///
+    /// ```text
+    /// template Camera {
+    ///     Vector position;
+    ///     Vector up;
+    ///     Vector lookAt;
+    ///     FLOAT horizontalFov; // half angle in radians
+    ///     FLOAT nearPlane;
+    ///     FLOAT farPlane;
+    ///     FLOAT aspect;
+    /// }
+    /// ```
+    ///
+    /// Cameras declared inside a frame are named after the frame.
+    fn parse_data_object_camera(
+        &mut self,
+        frame_name: Option<&str>,
+    ) -> Result<AiCamera, XFileParseError> {
+        let camera_name = self.read_head_of_data_object()?;
+        let position = self.read_vec3()?;
+        let up = self.read_vec3()?;
+        let look_at = self.read_vec3()?;
+        let horizontal_fov = self.read_f32()?;
+        let clip_plane_near = self.read_f32()?;
+        let clip_plane_far = self.read_f32()?;
+        let aspect = self.read_f32()?;
+        self.skip_data_object_rest("parse_data_object_camera")?;
+
+        let name = match frame_name {
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ if !camera_name.is_empty() => String::from_utf8_lossy(camera_name).into_owned(),
+            _ => format!("Camera_{}", self.scene.cameras.len()),
+        };
+        Ok(AiCamera {
+            name: name.into(),
+            position,
+            up,
+            look_at,
+            horizontal_fov,
+            clip_plane_near,
+            clip_plane_far,
+            aspect,
+            orthographic_width: 0.0,
+        })
+    }
+
+    /// Skips any nested data objects up to the closing brace of the current one.
+    fn skip_data_object_rest(&mut self, context: &'static str) -> Result<(), XFileParseError> {
+        loop {
+            match self.next_token()? {
+                Token::Eof => return Err(XFileParseError::unexpected_end_of_file(context)),
+                Token::Punctuation(b'}') => return Ok(()),
+                token => self.parse_unknown_data_object(token)?,
+            }
+        }
+    }
+
+    fn parse_data_object_material(&mut self) -> Result<Material<'source>, XFileParseError> {
         let mat_name = self.read_head_of_data_object()?;
         let name = if mat_name.is_empty() {
-            format!("material{}", self.line_number)
+            Cow::Owned(format!("material{}", self.line_number()))
         } else {
-            String::from_utf8_lossy(mat_name).into_owned()
+            String::from_utf8_lossy(mat_name)
         };
         let is_reference = false;
-        let diffuse = self.read_rgba()?;
+        let diffuse = self.read_vec4()?;
         let specular_exponent = self.read_float()?;
-        let specular = self.read_rgb()?;
-        let emissive = self.read_rgb()?;
+        let specular = self.read_vec3()?;
+        let emissive = self.read_vec3()?;
         let mut textures = Vec::new();
         // read other data objects
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_material",
-                ));
-            }
-            if token == b"}" {
-                break; // material finished
-            }
-
-            if token == b"TextureFilename" || token == b"TextureFileName" {
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_material",
+                    ));
+                }
+                Token::Punctuation(b'}') => break, // material finished
                 // some exporters write "TextureFileName" instead.
-                let tex_name = self.parse_data_object_material_texture_filename()?;
-                textures.push(TexEntry::new(tex_name, false));
-            } else if token == b"NormalmapFilename" || token == b"NormalmapFileName" {
+                Token::Identifier(b"TextureFilename" | b"TextureFileName") => {
+                    let tex_name = self.parse_data_object_material_texture_filename()?;
+                    textures.push(TexEntry::new(tex_name, false));
+                }
                 // one exporter writes out the normal map in a separate filename tag
-                let tex_name = self.parse_data_object_material_texture_filename()?;
-                textures.push(TexEntry::new(tex_name, true));
-            } else {
-                self.parse_unknown_data_object()?;
+                Token::Identifier(b"NormalmapFilename" | b"NormalmapFileName") => {
+                    let tex_name = self.parse_data_object_material_texture_filename()?;
+                    textures.push(TexEntry::new(tex_name, true));
+                }
+                token => self.parse_unknown_data_object(token)?,
             }
         }
         Ok(Material {
@@ -879,13 +1557,58 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
         })
     }
 
-    fn parse_data_object_material_texture_filename(&mut self) -> Result<String, XFileParseError> {
+    fn parse_data_object_material_texture_filename(
+        &mut self,
+    ) -> Result<Cow<'source, str>, XFileParseError> {
         self.read_head_of_data_object()?;
-        let name = self.next_token_as_str()?.replace("\\\\", "\\");
+        let mut name = self.next_token_as_str()?;
+        if name.contains("\\\\") {
+            name = Cow::Owned(name.replace("\\\\", "\\"));
+        }
         self.check_for_closing_brace()?;
         Ok(name)
     }
 
+    fn parse_data_object_vertex_duplication_indices(
+        &mut self,
+        m: &mut Mesh<'source>,
+    ) -> Result<(), XFileParseError> {
+        self.read_head_of_data_object()?;
+        let num_indices = self.read_int()?;
+        let _num_original_vertices = self.read_int()?;
+        if num_indices as usize != m.positions.len() {
+            self.tolerate(
+                IssueKind::VertexDataCount,
+                XFileParseError::DuplicationIndexCountMismatch,
+                "indices ignored",
+            )?;
+        }
+        let mut indices = Vec::new();
+        self.reserve(&mut indices, num_indices)?;
+        for i in 0..num_indices {
+            let index = self.read_int()?;
+            // copies refer to the first vertex split from the same original,
+            // which comes before them and refers to itself
+            if index == i || indices.get(index as usize) == Some(&index) {
+                indices.push(index);
+            } else {
+                self.tolerate(
+                    IssueKind::VertexDataIndex,
+                    XFileParseError::VertexIndexOutOfRange { index, count: i },
+                    "vertex treated as original",
+                )?;
+                indices.push(i);
+            }
+        }
+        self.test_for_separator();
+        self.check_for_closing_brace()?;
+        // the indices are useless if they do not match the positions
+        if indices.len() == m.positions.len() {
+            m.duplication_indices = indices;
+        }
+        Ok(())
+    }
+
     fn parse_data_object_skin_mesh_header(&mut self) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
         let _max_skin_weights_per_vertex = self.read_int()?;
@@ -895,15 +1618,18 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
         Ok(())
     }
 
-    fn parse_data_object_skin_weights(&mut self, m: &mut Mesh) -> Result<(), XFileParseError> {
+    fn parse_data_object_skin_weights(
+        &mut self,
+        m: &mut Mesh<'source>,
+    ) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
 
         let transform_node_name = self.next_token_as_str()?;
-        let mut bone = Bone::new(transform_node_name.into_owned());
+        let mut bone = Bone::new(transform_node_name);
 
         // read vertex weights
         let num_weights = self.read_int()?;
-        bone.weights.reserve(num_weights as usize);
+        self.reserve(&mut bone.weights, num_weights)?;
 
         for _ in 0..num_weights {
             let mut weight = BoneWeight::default();
@@ -913,7 +1639,7 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
         // read vertex weights
         for weight in bone.weights.iter_mut() {
-            weight.weight = self.read_float()?;
+            weight.weight = self.read_f32()?;
         }
 
         // read matrix offset
@@ -943,53 +1669,55 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
     fn parse_data_object_animation_set(&mut self) -> Result<(), XFileParseError> {
         let anim_name = self.read_head_of_data_object()?;
-        let anim_name = String::from_utf8(anim_name.to_owned()).unwrap_or_default();
+        let anim_name = if anim_name.is_empty() {
+            Cow::Owned(format!("AnimationSet_{}", self.scene.animations.len()))
+        } else {
+            String::from_utf8_lossy(anim_name)
+        };
         let mut anim = Animation::new(anim_name);
 
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_animation_set",
-                ));
-            }
-            if token == b"}" {
-                break; // animation set finished
-            } else if token == b"Animation" {
-                self.parse_data_object_animation(&mut anim)?;
-            } else {
-                self.parse_unknown_data_object()?;
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_animation_set",
+                    ));
+                }
+                Token::Punctuation(b'}') => break, // animation set finished
+                Token::Identifier(b"Animation") => self.parse_data_object_animation(&mut anim)?,
+                token => self.parse_unknown_data_object(token)?,
             }
         }
         self.scene.animations.push(anim);
         Ok(())
     }
 
-    fn parse_data_object_animation(&mut self, anim: &mut Animation) -> Result<(), XFileParseError> {
+    fn parse_data_object_animation(
+        &mut self,
+        anim: &mut Animation<'source>,
+    ) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
         let mut banim = AnimBone::new();
 
         loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_animation",
-                ));
-            }
-            if token == b"}" {
-                break; // animation finished
-            }
-            if token == b"AnimationKey" {
-                self.parse_data_object_animation_key(&mut banim)?;
-            } else if token == b"AnimationOptions" {
-                self.parse_unknown_data_object()?; // not interested
-            } else if token == b"{" {
-                // read frame name
-                let name = self.next_token()?;
-                banim.name = String::from_utf8_lossy(name).into_owned();
-                self.check_for_closing_brace()?;
-            } else {
-                self.parse_unknown_data_object()?;
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_animation",
+                    ));
+                }
+                Token::Punctuation(b'}') => break, // animation finished
+                Token::Identifier(b"AnimationKey") => {
+                    self.parse_data_object_animation_key(&mut banim)?
+                }
+                Token::Punctuation(b'{') => {
+                    // read frame name
+                    let name = self.next_token()?;
+                    banim.name = String::from_utf8_lossy(name.as_bytes());
+                    self.check_for_closing_brace()?;
+                }
+                // AnimationOptions, not interested
+                token => self.parse_unknown_data_object(token)?,
             }
         }
         anim.anims.push(banim);
@@ -999,13 +1727,17 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
     fn parse_data_object_anim_ticks_per_second(&mut self) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
         self.scene.anim_ticks_per_second = self.read_int()?;
+        self.scene.metadata.insert(
+            AI_METADATA_X_ANIM_TICKS_PER_SECOND.to_owned(),
+            MetadataEntry::UInt32(self.scene.anim_ticks_per_second),
+        );
         self.check_for_closing_brace()?;
         Ok(())
     }
 
     fn parse_data_object_animation_key(
         &mut self,
-        banim: &mut AnimBone,
+        banim: &mut AnimBone<'source>,
     ) -> Result<(), XFileParseError> {
         self.read_head_of_data_object()?;
 
@@ -1014,32 +1746,11 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
 
         // read number of keys
         let num_keys = self.read_int()?;
-
         match key_type {
-            0 => {
-                banim
-                    .rot_keys
-                    .try_reserve(num_keys as usize)
-                    .map_err(|_| XFileParseError::InsufficientMemory)?;
-            }
-            1 => {
-                banim
-                    .scale_keys
-                    .try_reserve(num_keys as usize)
-                    .map_err(|_| XFileParseError::InsufficientMemory)?;
-            }
-            2 => {
-                banim
-                    .pos_keys
-                    .try_reserve(num_keys as usize)
-                    .map_err(|_| XFileParseError::InsufficientMemory)?;
-            }
-            3 | 4 => {
-                banim
-                    .trafo_keys
-                    .try_reserve(num_keys as usize)
-                    .map_err(|_| XFileParseError::InsufficientMemory)?;
-            }
+            0 => self.reserve(&mut banim.rot_keys, num_keys)?,
+            1 => self.reserve(&mut banim.scale_keys, num_keys)?,
+            2 => self.reserve(&mut banim.pos_keys, num_keys)?,
+            3 | 4 => self.reserve(&mut banim.trafo_keys, num_keys)?,
             _ => {}
         }
 
@@ -1063,11 +1774,7 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
                     let x = self.read_float()?;
                     let y = self.read_float()?;
                     let z = self.read_float()?;
-                    let key = AiQuatKey {
-                        time: time as f64,
-                        value: Quat::from_xyzw(x, y, z, w),
-                        interpolation: AiAnimInterpolation::default(),
-                    };
+                    let key = AiQuatKey::new(time as f64, Quat::from_xyzw(x, y, z, w));
 
                     self.check_for_semicolon()?;
 
@@ -1085,11 +1792,7 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
                         });
                     }
 
-                    let key = AiVectorKey {
-                        time: time as f64,
-                        value: self.read_vec3()?,
-                        interpolation: AiAnimInterpolation::default(),
-                    };
+                    let key = AiVectorKey::new(time as f64, self.read_vec3()?);
 
                     if key_type == 2 {
                         banim.pos_keys.push(key);
@@ -1152,35 +1855,164 @@ impl<'source, P: XFileParser<'source>> ParserImpl<'source, P> {
         Ok(())
     }
 
-    fn parse_unknown_data_object(&mut self) -> Result<(), XFileParseError> {
-        // find opening delimiter
-        loop {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_data_object_animation_key",
-                ));
+    /// Parses a data object of a template unknown to us. Strings stored
+    /// directly in the object are kept as metadata of the enclosing frame, or
+    /// of the scene for top level objects; everything else is skipped.
+    fn parse_data_object_user(
+        &mut self,
+        template: Token<'source>,
+        node: Option<Index<Node<'source>>>,
+    ) -> Result<(), XFileParseError> {
+        if template == Token::Punctuation(b'{') {
+            // reference to another data object, e.g. `{ FrameName }`
+            loop {
+                match self.next_token()? {
+                    Token::Eof => {
+                        return Err(XFileParseError::unexpected_end_of_file(
+                            "parse_data_object_user",
+                        ));
+                    }
+                    Token::Punctuation(b'}') => return Ok(()),
+                    _ => {}
+                }
             }
-            if token == b"{" {
-                break;
+        }
+
+        // find opening delimiter, instance name and guid are optional
+        let mut name: &[u8] = &[];
+        loop {
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_user",
+                    ));
+                }
+                Token::Punctuation(b'{') => break,
+                token => {
+                    if name.is_empty()
+                        && let Some(token) = token.name()
+                    {
+                        name = token;
+                    }
+                }
             }
         }
 
+        // members of declared templates are read by their layout, nested
+        // data objects and references are skipped
+        let mut members = Metadata::default();
+        if let Ok(template) = str::from_utf8(template.as_bytes())
+            && self.scene.templates.is_readable(template)
+        {
+            let templates = mem::take(&mut self.scene.templates);
+            let result = match templates.get(template) {
+                Some(template) => self.read_template_instance(&templates, template),
+                None => Ok(Metadata::default()),
+            };
+            self.scene.templates = templates;
+            members = result?;
+        }
+        let mut values = Vec::new();
+        if members
+            .values()
+            .all(|value| matches!(value, MetadataEntry::String(_)))
+        {
+            values.extend(members.drain(..).filter_map(|(_, value)| match value {
+                MetadataEntry::String(value) => Some(value.into_string()),
+                _ => None,
+            }));
+        }
         let mut brace_left_match_cnt = 1;
-
-        // parse until closing delimiter
         while brace_left_match_cnt > 0 {
-            let token = self.next_token()?;
-            if token.is_empty() {
-                return Err(XFileParseError::unexpected_end_of_file(
-                    "parse_unknown_data_object",
-                ));
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_user",
+                    ));
+                }
+                Token::String(value) if brace_left_match_cnt == 1 => {
+                    values.push(String::from_utf8_lossy(value).into_owned());
+                }
+                Token::Punctuation(b'{') => brace_left_match_cnt += 1,
+                Token::Punctuation(b'}') => brace_left_match_cnt -= 1,
+                _ => {}
+            }
+        }
+
+        let template = template.as_bytes();
+        let mut key = String::from_utf8_lossy(if name.is_empty() { template } else { name });
+        let value = match values.len() {
+            _ if !members.is_empty() => MetadataEntry::Metadata(Box::new(members)),
+            0 => return Ok(()),
+            // kwXport writes `KeyValuePair { "key"; "value"; }`
+            2 if template == b"KeyValuePair" => {
+                let value = values.pop().unwrap_or_default();
+                key = values.pop().unwrap_or_default().into();
+                MetadataEntry::String(value.into())
             }
+            1 => MetadataEntry::String(values.swap_remove(0).into()),
+            _ => MetadataEntry::Metadata(Box::new(
+                values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, v)| (i.to_string(), MetadataEntry::String(v.into())))
+                    .collect(),
+            )),
+        };
+        let metadata = match node.and_then(|node| Index::get_mut(node, &mut self.scene.nodes)) {
+            Some(node) => &mut node.metadata,
+            None => &mut self.scene.metadata,
+        };
+        // several instances of the same template may appear
+        let mut unique_key = key.to_string();
+        let mut i = 1;
+        while metadata.contains_key(&unique_key) {
+            unique_key = format!("{key}_{i}");
+            i += 1;
+        }
+        metadata.insert(unique_key, value);
+        Ok(())
+    }
 
-            if token == b"{" {
-                brace_left_match_cnt += 1;
-            } else if token == b"}" {
-                brace_left_match_cnt -= 1;
+    /// Skips a data object of type `template` which the importer does not
+    /// read
+    fn parse_unknown_data_object(
+        &mut self,
+        template: Token<'source>,
+    ) -> Result<(), XFileParseError> {
+        let position = self.get_position();
+        self.scene.report.push(
+            IssueKind::UnknownObject,
+            IssueAction::Skipped,
+            Some(position),
+            format!("Unknown data object {template}"),
+        );
+        // find opening delimiter
+        loop {
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_data_object_animation_key",
+                    ));
+                }
+                Token::Punctuation(b'{') => break,
+                _ => {}
+            }
+        }
+
+        let mut brace_left_match_cnt = 1;
+
+        // parse until closing delimiter, strings may contain braces
+        while brace_left_match_cnt > 0 {
+            match self.next_token()? {
+                Token::Eof => {
+                    return Err(XFileParseError::unexpected_end_of_file(
+                        "parse_unknown_data_object",
+                    ));
+                }
+                Token::Punctuation(b'{') => brace_left_match_cnt += 1,
+                Token::Punctuation(b'}') => brace_left_match_cnt -= 1,
+                _ => {}
             }
         }
         Ok(())
@@ -0,0 +1,383 @@
+//! Template declarations of X files, which describe the members of data
+//! objects, e.g.
+//!
+//! ```text
+//! template MeshFace {
+//!  <3d82ab5f-62da-11cf-ab39-0020af71e433>
+//!  DWORD nFaceVertexIndices;
+//!  array DWORD faceVertexIndices[nFaceVertexIndices];
+//! }
+//! ```
+
+use indexmap::IndexMap;
+
+use crate::formats::x::{
+    errors::XFileParseError,
+    parser::token::{Token, format_guid, lex_text},
+};
+
+/// Nesting of template members deeper than this is not followed
+const MAX_TEMPLATE_DEPTH: usize = 8;
+
+/// Declarations of templates commonly used by the members of custom
+/// templates, files using them often do not declare them
+const STANDARD_TEMPLATES: &str = "
+template Vector { <3d82ab5e-62da-11cf-ab39-0020af71e433> FLOAT x; FLOAT y; FLOAT z; }
+template Coords2d { <f6f23f44-7686-11cf-8f52-0040333594a3> FLOAT u; FLOAT v; }
+template Matrix4x4 { <f6f23f45-7686-11cf-8f52-0040333594a3> array FLOAT matrix[16]; }
+template ColorRGBA { <35ff44e0-6c7c-11cf-8f52-0040333594a3> FLOAT red; FLOAT green; FLOAT blue; FLOAT alpha; }
+template ColorRGB { <d3e16e81-7835-11cf-8f52-0040333594a3> FLOAT red; FLOAT green; FLOAT blue; }
+template IndexedColor { <1630b820-7842-11cf-8f52-0040333594a3> DWORD index; ColorRGBA indexColor; }
+template Boolean { <4885ae61-78e8-11cf-8f52-0040333594a3> WORD truefalse; }
+template Boolean2d { <4885ae63-78e8-11cf-8f52-0040333594a3> Boolean u; Boolean v; }
+template FloatKeys { <10dd46a9-775b-11cf-8f52-0040333594a3> DWORD nValues; array FLOAT values[nValues]; }
+template TimedFloatKeys { <f406b180-7b3b-11cf-8f52-0040333594a3> DWORD time; FloatKeys tfkeys; }
+";
+
+/// Type of a template member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemberType {
+    Word,
+    Dword,
+    Float,
+    Double,
+    Char,
+    Uchar,
+    Sword,
+    Sdword,
+    String,
+    Unicode,
+    Cstring,
+    /// Another template, by name
+    Template(String),
+}
+
+impl MemberType {
+    fn from_name(name: &[u8]) -> Self {
+        const PRIMITIVES: [(&[u8], MemberType); 12] = [
+            (b"WORD", MemberType::Word),
+            (b"DWORD", MemberType::Dword),
+            (b"FLOAT", MemberType::Float),
+            (b"DOUBLE", MemberType::Double),
+            (b"CHAR", MemberType::Char),
+            (b"UCHAR", MemberType::Uchar),
+            (b"BYTE", MemberType::Uchar),
+            (b"SWORD", MemberType::Sword),
+            (b"SDWORD", MemberType::Sdword),
+            (b"STRING", MemberType::String),
+            (b"UNICODE", MemberType::Unicode),
+            (b"CSTRING", MemberType::Cstring),
+        ];
+        PRIMITIVES
+            .into_iter()
+            .find(|(keyword, _)| keyword.eq_ignore_ascii_case(name))
+            .map_or_else(
+                || Self::Template(String::from_utf8_lossy(name).into_owned()),
+                |(_, ty)| ty,
+            )
+    }
+
+    /// Whether values of the type are integers
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::Word | Self::Dword | Self::Char | Self::Uchar | Self::Sword | Self::Sdword
+        )
+    }
+}
+
+/// Size of one dimension of an array member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dimension {
+    Fixed(u32),
+    /// The value of an earlier integer member
+    Member(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateMember {
+    pub ty: MemberType,
+    /// References to templates may leave out the name
+    pub name: Option<String>,
+    /// Empty for members which are not arrays
+    pub dimensions: Vec<Dimension>,
+}
+
+impl TemplateMember {
+    /// The name of the member, or of its type for unnamed members
+    pub fn key(&self) -> String {
+        match (&self.name, &self.ty) {
+            (Some(name), _) => name.clone(),
+            (None, MemberType::Template(name)) => name.clone(),
+            (None, ty) => format!("{ty:?}"),
+        }
+    }
+}
+
+/// Which data objects may be nested in instances of a template
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Restriction {
+    /// No nested data objects
+    #[default]
+    Closed,
+    /// Any data objects, written as `[...]`
+    Open,
+    /// Only instances of the listed templates
+    Restricted(Vec<String>),
+}
+
+/// A template declaration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    /// The GUID with its angle brackets, unknown in binary files
+    pub guid: Option<String>,
+    pub members: Vec<TemplateMember>,
+    pub restriction: Restriction,
+}
+
+/// Part of a template declaration. Text tokens like `faces[nFaces]` are
+/// split into several pieces, the binary format stores them separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece<'a> {
+    Name(&'a [u8]),
+    Number(u32),
+    Guid(&'a [u8]),
+    BinaryGuid(&'a [u8; 16]),
+    Open,
+    Close,
+    Dots,
+    Separator,
+}
+
+fn pieces<'a>(tokens: &[Token<'a>]) -> Vec<Piece<'a>> {
+    let mut pieces = Vec::new();
+    for &token in tokens {
+        let text = match token {
+            Token::Guid(guid) => {
+                pieces.push(Piece::Guid(guid));
+                continue;
+            }
+            Token::BinaryGuid(guid) => {
+                pieces.push(Piece::BinaryGuid(guid));
+                continue;
+            }
+            Token::BinaryInteger(value) => {
+                pieces.push(Piece::Number(value));
+                continue;
+            }
+            Token::Punctuation(b'[') => {
+                pieces.push(Piece::Open);
+                continue;
+            }
+            Token::Punctuation(b']') => {
+                pieces.push(Piece::Close);
+                continue;
+            }
+            Token::Punctuation(b'.') => {
+                pieces.push(Piece::Dots);
+                continue;
+            }
+            Token::Punctuation(_) => {
+                pieces.push(Piece::Separator);
+                continue;
+            }
+            token => token.as_bytes(),
+        };
+        for part in text.split_inclusive(|&b| matches!(b, b'[' | b']')) {
+            let (word, bracket) = match part.split_last() {
+                Some((b'[', word)) => (word, Some(Piece::Open)),
+                Some((b']', word)) => (word, Some(Piece::Close)),
+                _ => (part, None),
+            };
+            if word.iter().all(|&b| b == b'.') && !word.is_empty() {
+                pieces.push(Piece::Dots);
+            } else if let Some(number) = str::from_utf8(word).ok().and_then(|s| s.parse().ok()) {
+                pieces.push(Piece::Number(number));
+            } else if !word.is_empty() {
+                pieces.push(Piece::Name(word));
+            }
+            pieces.extend(bracket);
+        }
+    }
+    pieces
+}
+
+impl Template {
+    /// Parses the body of a template declaration, the tokens between the
+    /// braces.
+    pub fn from_tokens(name: &[u8], tokens: &[Token<'_>]) -> Result<Self, XFileParseError> {
+        let name = String::from_utf8_lossy(name).into_owned();
+        let invalid = |reason| XFileParseError::InvalidTemplate {
+            name: name.clone(),
+            reason,
+        };
+        let owned = |word: &[u8]| String::from_utf8_lossy(word).into_owned();
+
+        let mut guid = None;
+        let mut members = Vec::new();
+        let mut restriction = Restriction::Closed;
+        let pieces = pieces(tokens);
+        let mut pieces = pieces.into_iter().peekable();
+        while let Some(piece) = pieces.next() {
+            match piece {
+                Piece::Guid(text) if guid.is_none() && members.is_empty() => {
+                    guid = Some(owned(text));
+                }
+                Piece::BinaryGuid(bytes) if guid.is_none() && members.is_empty() => {
+                    guid = Some(format_guid(bytes));
+                }
+                Piece::Separator => {}
+                Piece::Open => {
+                    // `[...]` or a list of template names with optional GUIDs
+                    let mut names = Vec::new();
+                    loop {
+                        match pieces.next() {
+                            Some(Piece::Close) => break,
+                            Some(Piece::Dots) => restriction = Restriction::Open,
+                            Some(Piece::Name(name)) => names.push(owned(name)),
+                            Some(Piece::Guid(_) | Piece::BinaryGuid(_) | Piece::Separator) => {}
+                            _ => return Err(invalid("invalid restriction")),
+                        }
+                    }
+                    if restriction != Restriction::Open {
+                        restriction = Restriction::Restricted(names);
+                    }
+                }
+                Piece::Name(word) => {
+                    let is_array = word == b"array";
+                    let ty = if is_array {
+                        match pieces.next() {
+                            Some(Piece::Name(ty)) => MemberType::from_name(ty),
+                            _ => return Err(invalid("array without type")),
+                        }
+                    } else {
+                        MemberType::from_name(word)
+                    };
+                    let name = match pieces.peek() {
+                        Some(&Piece::Name(name)) => {
+                            pieces.next();
+                            Some(owned(name))
+                        }
+                        _ => None,
+                    };
+                    let mut dimensions = Vec::new();
+                    while pieces.next_if_eq(&Piece::Open).is_some() {
+                        dimensions.push(match pieces.next() {
+                            Some(Piece::Number(size)) => Dimension::Fixed(size),
+                            Some(Piece::Name(member)) => Dimension::Member(owned(member)),
+                            _ => return Err(invalid("invalid array size")),
+                        });
+                        if pieces.next() != Some(Piece::Close) {
+                            return Err(invalid("missing ']' after array size"));
+                        }
+                    }
+                    if is_array && (dimensions.is_empty() || name.is_none()) {
+                        return Err(invalid("array without name or size"));
+                    }
+                    members.push(TemplateMember {
+                        ty,
+                        name,
+                        dimensions,
+                    });
+                }
+                _ => return Err(invalid("unexpected token")),
+            }
+        }
+        Ok(Self {
+            name,
+            guid,
+            members,
+            restriction,
+        })
+    }
+}
+
+/// The templates declared in a file, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateRegistry {
+    templates: IndexMap<String, Template>,
+}
+
+impl TemplateRegistry {
+    /// A registry knowing a few standard templates like `Vector` and
+    /// `Matrix4x4`
+    pub fn with_standard_templates() -> Self {
+        let mut registry = Self::default();
+        let mut source = STANDARD_TEMPLATES.as_bytes();
+        let mut tokens = Vec::new();
+        loop {
+            source = source.trim_ascii_start();
+            let Ok((token, len)) = lex_text(source) else {
+                break;
+            };
+            source = &source[len..];
+            match token {
+                Token::Eof => break,
+                Token::Punctuation(b'}') => {
+                    // `template Name {` and the body
+                    if let [_, Token::Identifier(name), _, body @ ..] = tokens.as_slice()
+                        && let Ok(template) = Template::from_tokens(name, body)
+                    {
+                        registry.insert(template);
+                    }
+                    tokens.clear();
+                }
+                token => tokens.push(token),
+            }
+        }
+        registry
+    }
+
+    /// Adds a template, replacing an earlier one with the same name
+    pub fn insert(&mut self, template: Template) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Template> {
+        self.templates.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Whether instances of the template can be read member by member: all
+    /// members have primitive types or readable templates, and array sizes
+    /// refer to earlier integer members.
+    pub fn is_readable(&self, name: &str) -> bool {
+        self.is_readable_at(name, 0)
+    }
+
+    fn is_readable_at(&self, name: &str, depth: usize) -> bool {
+        let Some(template) = self.get(name) else {
+            return false;
+        };
+        if depth >= MAX_TEMPLATE_DEPTH || template.members.is_empty() {
+            return false;
+        }
+        template.members.iter().enumerate().all(|(i, member)| {
+            let type_readable = match &member.ty {
+                MemberType::Template(ty) => self.is_readable_at(ty, depth + 1),
+                _ => true,
+            };
+            type_readable
+                && member.dimensions.iter().all(|dimension| match dimension {
+                    Dimension::Fixed(_) => true,
+                    Dimension::Member(size) => template.members[..i].iter().any(|m| {
+                        m.name.as_deref() == Some(size)
+                            && m.ty.is_integer()
+                            && m.dimensions.is_empty()
+                    }),
+                })
+        })
+    }
+}
@@ -2,27 +2,116 @@ use std::borrow::Cow;
 
 use crate::{
     AiReal,
-    formats::x::{errors::XFileParseError, parser::XFileParser},
+    formats::x::{
+        errors::XFileParseError,
+        parser::{
+            XFileParser,
+            token::{Token, lex_text},
+        },
+    },
     utils::fast_atof::fast_atoreal_move,
 };
 
+/// Characters shown on each side of the error position in snippets
+const SNIPPET_CONTEXT: usize = 60;
+
 pub struct TextParser<'source> {
     source: &'source [u8],
+    full_source: &'source [u8],
     pub line_number: u32,
+    /// Offset and line of the token read last, errors are reported there
+    token_start: usize,
+    token_line: u32,
+    comments: Vec<String>,
+    strict: bool,
 }
 
 impl<'source> TextParser<'source> {
     pub fn new(source: &'source [u8]) -> Self {
         Self {
             source,
+            full_source: source,
             line_number: 1,
+            token_start: 0,
+            token_line: 1,
+            comments: Vec::new(),
+            strict: false,
         }
     }
+
+    /// Rejects the special float literals some exporters write for NaNs
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.full_source.len() - self.source.len()
+    }
+
+    /// Skips `n` bytes, or everything if less are left
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        self.source = self.source.get(n..).unwrap_or_default();
+    }
+
+    /// Start of the line containing the last token
+    fn line_start(&self) -> usize {
+        self.full_source[..self.token_start]
+            .iter()
+            .rposition(|&b| b == b'\n' || b == b'\r')
+            .map_or(0, |i| i + 1)
+    }
+
+    /// One-based column of the last token, counted in characters
+    pub fn column(&self) -> usize {
+        String::from_utf8_lossy(&self.full_source[self.line_start()..self.token_start])
+            .chars()
+            .count()
+            + 1
+    }
 }
 
 impl<'source> XFileParser<'source> for TextParser<'source> {
     fn get_position(&self) -> String {
-        format!("Line {}", self.line_number)
+        format!("Line {}, column {}", self.token_line, self.column())
+    }
+
+    fn get_snippet(&self) -> Option<String> {
+        let start = self.line_start();
+        let end = self.full_source[self.token_start..]
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+            .map_or(self.full_source.len(), |i| self.token_start + i);
+        let before = String::from_utf8_lossy(&self.full_source[start..self.token_start]);
+        let after = String::from_utf8_lossy(&self.full_source[self.token_start..end]);
+
+        // only show the surroundings of the error on long lines
+        let skip = before.chars().count().saturating_sub(SNIPPET_CONTEXT);
+        let mut before = before.chars().skip(skip).collect::<String>();
+        if skip > 0 {
+            before.insert_str(0, "...");
+        }
+        let mut after_chars = after.chars();
+        let mut after = after_chars
+            .by_ref()
+            .take(SNIPPET_CONTEXT + 1)
+            .collect::<String>();
+        if after_chars.next().is_some() {
+            after.push_str("...");
+        }
+        // keep tabs so the caret lines up with the token
+        let caret = before
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        let line = self.token_line.to_string();
+        let pad = " ".repeat(line.len());
+        Some(format!(
+            "\n{pad} |\n{line} | {before}{after}\n{pad} | {caret}^"
+        ))
     }
 
     #[inline(always)]
@@ -39,12 +128,6 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
         Ok(data)
     }
 
-    unsafe fn forward_unchecked(&mut self, n: usize) -> &'source [u8] {
-        let (data, rest) = unsafe { self.source.split_at_unchecked(n) };
-        self.source = rest;
-        data
-    }
-
     fn peek<const N: usize>(&self) -> Option<&'source [u8; N]> {
         let (data, _) = self.source.split_at_checked(N)?;
         Some(data.try_into().unwrap())
@@ -77,11 +160,22 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
                 }
             }
             if self.rest() == 0 {
-                return;
+                break;
             }
             if let &[a, b, ref rest @ ..] = self.source {
                 if a == b'/' && b == b'/' || a == b'#' {
-                    self.source = rest;
+                    self.source = if a == b'#' { &self.source[1..] } else { rest };
+                    // keep the comment, exporters often put their name there
+                    let len = self
+                        .source
+                        .iter()
+                        .position(|&b| b == b'\n' || b == b'\r')
+                        .unwrap_or(self.rest());
+                    let comment = String::from_utf8_lossy(&self.source[..len]);
+                    let comment = comment.trim();
+                    if !comment.is_empty() {
+                        self.comments.push(comment.to_owned());
+                    }
                     self.skip_until_next_line();
                 } else {
                     break;
@@ -90,6 +184,8 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
                 break;
             }
         }
+        self.token_start = self.offset();
+        self.token_line = self.line_number;
     }
 
     fn read_int(&mut self) -> Result<u32, XFileParseError> {
@@ -98,8 +194,7 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
             return Err(XFileParseError::NotEnoughDataToRead(1));
         };
         let is_neg: bool = if b == b'-' {
-            // SAFETY: we know that the next byte is a '-'
-            unsafe { self.forward_unchecked(1) };
+            self.advance(1);
             true
         } else {
             if !b.is_ascii_digit() {
@@ -107,10 +202,11 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
             }
             false
         };
-        let mut value = 0;
+        let mut value: u32 = 0;
         while let &[b, ref rest @ ..] = self.source {
             if b.is_ascii_digit() {
-                value = value * 10 + (b - b'0') as u32;
+                // overflows wrap around like in assimp
+                value = value.wrapping_mul(10).wrapping_add((b - b'0') as u32);
                 self.source = rest;
             } else {
                 break;
@@ -118,7 +214,7 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
         }
         self.check_for_separator()?;
         return Ok(if is_neg {
-            (-(value as i32)) as u32
+            (value as i32).wrapping_neg() as u32
         } else {
             value
         });
@@ -127,98 +223,70 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
     fn read_float(&mut self) -> Result<AiReal, XFileParseError> {
         self.skip_whitespace();
 
+        let (rest, mut f) =
+            fast_atoreal_move(self.source, false).map_err(XFileParseError::FastAtofError)?;
         // check for various special strings to allow reading files from faulty exporters
         // I mean you, Blender!
-        let special_string = self.peek::<9>();
-
-        if special_string == Some(b"-1.#IND00") {
-            // SAFETY: we know that the next 8 bytes are a special string
-            unsafe { self.forward_unchecked(8) };
-            self.check_for_separator()?;
-            return Ok(0.0);
-        } else if matches!(self.peek::<8>(), Some(b"1.#IND00") | Some(b"1.#QNAN0")) {
-            // SAFETY: we know that the next 8 bytes are a special string
-            unsafe { self.forward_unchecked(8) };
-            self.check_for_separator()?;
-            return Ok(0.0);
+        let literal = &self.source[..self.source.len() - rest.len()];
+        if literal.contains(&b'#') {
+            if self.strict {
+                return Err(XFileParseError::NonStandardNumberLiteral(
+                    String::from_utf8_lossy(literal).into_owned(),
+                ));
+            }
+            f = 0.0;
         }
-        let (rest, f) =
-            fast_atoreal_move(self.source, true).map_err(|e| XFileParseError::FastAtofError(e))?;
-
         self.source = rest;
         self.check_for_separator()?;
         Ok(f)
     }
 
-    fn next_token(&mut self) -> Result<&'source [u8], XFileParseError> {
+    fn next_token(&mut self) -> Result<Token<'source>, XFileParseError> {
         self.skip_whitespace();
-        if self.rest() == 0 {
-            return Ok(&[]);
-        }
-        let mut index = 0;
-        let mut next = self.source;
-        while let &[b, ref rest @ ..] = next {
-            if b.is_ascii_whitespace() {
-                break;
-            }
-            if matches!(b, b';' | b'}' | b'{' | b',') {
-                if index == 0 {
-                    next = rest;
-                    index += 1;
-                }
-                break;
-            }
-            next = rest;
-            index += 1;
+        let (token, len) = lex_text(self.source)?;
+        if let Token::String(s) = token {
+            self.line_number += s.iter().filter(|&&b| b == b'\n').count() as u32;
         }
-        let token = &self.source[..index];
-        self.source = next;
-        return Ok(token);
+        self.advance(len);
+        Ok(token)
     }
 
     fn next_token_as_str(&mut self) -> Result<Cow<'source, str>, XFileParseError> {
-        self.skip_whitespace();
-        let Some(b) = self.peek_one() else {
-            return Err(XFileParseError::unexpected_end_of_file("next_token_as_str"));
-        };
-        if b != b'"' {
-            return Err(XFileParseError::unexpected_token("\"", &[b]));
-        }
-        // SAFETY: we know that the next byte is '"'
-        unsafe { self.forward_unchecked(1) };
-        let mut cnt = 0;
-        for b in self.source {
-            if *b == b'"' {
-                break;
+        match self.next_token()? {
+            Token::String(s) => {
+                self.check_for_semicolon()?;
+                Ok(String::from_utf8_lossy(s))
             }
-            cnt += 1;
-        }
-        // SAFETY: cnt is within the bounds of the source.
-        let token = unsafe { self.forward_unchecked(cnt) };
-        let deliminator = self
-            .forward(2)
-            .map_err(|_| XFileParseError::unexpected_end_of_file("next_token_as_str"))?;
-        if deliminator != b"\";" {
-            return Err(XFileParseError::unexpected_token("\";", deliminator));
+            Token::Eof => Err(XFileParseError::unexpected_end_of_file("next_token_as_str")),
+            token => Err(XFileParseError::unexpected_token(
+                "string",
+                token.as_bytes(),
+            )),
         }
-        Ok(String::from_utf8_lossy(token))
+    }
+
+    fn take_comments(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.comments)
+    }
+
+    fn line_number(&self) -> u32 {
+        // lines are counted from 1 for error messages
+        self.line_number - 1
     }
 
     fn check_for_semicolon(&mut self) -> Result<(), XFileParseError> {
         let next = self.next_token()?;
-        if next != b";" {
-            return Err(XFileParseError::SemicolonExpected(
-                String::from_utf8_lossy(next).into_owned(),
-            ));
+        if next != Token::Punctuation(b';') {
+            return Err(XFileParseError::SemicolonExpected(next.to_string()));
         }
         Ok(())
     }
 
     fn check_for_separator(&mut self) -> Result<(), XFileParseError> {
         let next = self.next_token()?;
-        if !matches!(next, b"," | b";") {
+        if !matches!(next, Token::Punctuation(b',' | b';')) {
             return Err(XFileParseError::SeparatorCharacterExpected(
-                String::from_utf8_lossy(next).into_owned(),
+                next.to_string(),
             ));
         }
         Ok(())
@@ -228,8 +296,7 @@ impl<'source> XFileParser<'source> for TextParser<'source> {
         self.skip_whitespace();
         if let Some(b) = self.peek_one() {
             if matches!(b, b',' | b';') {
-                // SAFETY: we know that the next byte is a separator
-                unsafe { self.forward_unchecked(1) };
+                self.advance(1);
             }
         }
     }
@@ -0,0 +1,172 @@
+use core::fmt;
+
+use crate::formats::x::errors::XFileTextParseError;
+
+/// Punctuation characters ending text tokens, the binary format has a few
+/// more
+const PUNCTUATION: &[u8] = b"{}()[]<>.,;";
+
+/// A token of an X file, in text or binary encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'source> {
+    /// The end of the input
+    Eof,
+    /// Names of templates and data objects, and keywords like `template`
+    Identifier(&'source [u8]),
+    /// A quoted string, without the quotes
+    String(&'source [u8]),
+    Integer(&'source [u8]),
+    /// An integer of the binary format
+    BinaryInteger(u32),
+    /// Floats, including the `1.#IND00` style values of some exporters
+    Float(&'source [u8]),
+    /// A template GUID like `<3D82AB43-62DA-11CF-AB39-0020AF71E433>`
+    Guid(&'source [u8]),
+    /// A template GUID of the binary format, see [`format_guid`]
+    BinaryGuid(&'source [u8; 16]),
+    Punctuation(u8),
+    /// A binary array of integers, the little endian values
+    IntegerList(&'source [u8]),
+    /// A binary array of floats, the little endian values of the float
+    /// size of the file
+    FloatList(&'source [u8]),
+}
+
+impl<'source> Token<'source> {
+    /// The text of the token, empty at the end of the input
+    pub fn as_bytes(&self) -> &'source [u8] {
+        match *self {
+            Self::Eof => &[],
+            Self::Identifier(s)
+            | Self::String(s)
+            | Self::Integer(s)
+            | Self::Float(s)
+            | Self::Guid(s) => s,
+            Self::Punctuation(c) => PUNCTUATION
+                .iter()
+                .position(|&p| p == c)
+                .map_or(&[], |i| &PUNCTUATION[i..=i]),
+            Self::BinaryInteger(_) => b"<integer>",
+            Self::BinaryGuid(_) => b"<guid>",
+            Self::IntegerList(_) => b"<int_list>",
+            Self::FloatList(_) => b"<flt_list>",
+        }
+    }
+
+    /// Identifiers, numbers and strings, which all may name a data object
+    pub fn name(&self) -> Option<&'source [u8]> {
+        match *self {
+            Self::Identifier(s) | Self::String(s) | Self::Integer(s) | Self::Float(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eof => f.write_str("end of file"),
+            Self::String(s) => write!(f, "\"{}\"", String::from_utf8_lossy(s)),
+            Self::BinaryGuid(guid) => f.write_str(&format_guid(guid)),
+            _ => f.write_str(&String::from_utf8_lossy(self.as_bytes())),
+        }
+    }
+}
+
+/// The text form of a binary GUID, like
+/// `<3D82AB43-62DA-11CF-AB39-0020AF71E433>`. The first three fields are
+/// stored little endian.
+pub fn format_guid(guid: &[u8; 16]) -> String {
+    let data1 = u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]);
+    let data2 = u16::from_le_bytes([guid[4], guid[5]]);
+    let data3 = u16::from_le_bytes([guid[6], guid[7]]);
+    let mut text = format!("<{data1:08X}-{data2:04X}-{data3:04X}-");
+    for (i, b) in guid[8..].iter().enumerate() {
+        if i == 2 {
+            text.push('-');
+        }
+        text.push_str(&format!("{b:02X}"));
+    }
+    text.push('>');
+    text
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    /// A leading `-` or `+`
+    Sign,
+    Integer,
+    Float,
+    Identifier,
+    Guid,
+    String,
+}
+
+/// Whether `b` ends identifiers and numbers
+#[inline]
+fn is_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || matches!(b, b'{' | b'}' | b',' | b';' | b'"')
+}
+
+/// Reads the first token of `source`, which must not start with whitespace
+/// or comments. Returns the token and the number of bytes it takes.
+///
+/// Strings end at the next quote only, so they may contain braces,
+/// semicolons and line breaks.
+pub fn lex_text(source: &[u8]) -> Result<(Token<'_>, usize), XFileTextParseError> {
+    let mut state = State::Start;
+    for (i, &b) in source.iter().enumerate() {
+        state = match state {
+            State::Start => match b {
+                b'{' | b'}' | b',' | b';' => return Ok((Token::Punctuation(b), 1)),
+                b'"' => State::String,
+                b'<' => State::Guid,
+                b'-' | b'+' => State::Sign,
+                b'0'..=b'9' => State::Integer,
+                b'.' => State::Float,
+                _ => State::Identifier,
+            },
+            State::String => {
+                if b == b'"' {
+                    return Ok((Token::String(&source[1..i]), i + 1));
+                }
+                State::String
+            }
+            State::Guid if b == b'>' => return Ok((Token::Guid(&source[..=i]), i + 1)),
+            _ if is_delimiter(b) || source[i..].starts_with(b"//") => {
+                return Ok((finish(state, &source[..i]), i));
+            }
+            State::Sign => match b {
+                b'0'..=b'9' => State::Integer,
+                b'.' => State::Float,
+                _ => State::Identifier,
+            },
+            State::Integer => match b {
+                b'0'..=b'9' => State::Integer,
+                // `1.#IND00` and `1.#QNAN0` written by Blender
+                b'.' | b'e' | b'E' | b'#' => State::Float,
+                _ => State::Identifier,
+            },
+            State::Float => match b {
+                b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-' | b'#' => State::Float,
+                b if b.is_ascii_uppercase() => State::Float,
+                _ => State::Identifier,
+            },
+            State::Identifier | State::Guid => state,
+        };
+    }
+    match state {
+        State::String => Err(XFileTextParseError::UnterminatedString),
+        State::Start => Ok((Token::Eof, 0)),
+        _ => Ok((finish(state, source), source.len())),
+    }
+}
+
+fn finish(state: State, text: &[u8]) -> Token<'_> {
+    match state {
+        State::Integer => Token::Integer(text),
+        State::Float => Token::Float(text),
+        _ => Token::Identifier(text),
+    }
+}
@@ -0,0 +1,430 @@
+//! Vertex element declarations of the `DeclData` and `FVFData` templates.
+//!
+//! Both templates store additional per-vertex streams (tangents, binormals,
+//! extra texture coordinate or color sets) as a flat list of DWORDs, the
+//! elements of one vertex are packed one after another.
+
+use crate::{
+    formats::x::{errors::XFileParseError, structs::Mesh},
+    structs::color::Color4D,
+    structs::mesh::{AI_MAX_NUMBER_OF_COLOR_SETS, AI_MAX_NUMBER_OF_TEXTURECOORDS},
+    utils::float_precision::{Vec3, real3},
+};
+
+/// `D3DDECLTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclType {
+    Float1 = 0,
+    Float2 = 1,
+    Float3 = 2,
+    Float4 = 3,
+    D3DColor = 4,
+    UByte4 = 5,
+    Short2 = 6,
+    Short4 = 7,
+    UByte4N = 8,
+    Short2N = 9,
+    Short4N = 10,
+    UShort2N = 11,
+    UShort4N = 12,
+    UDec3 = 13,
+    Dec3N = 14,
+    Float16_2 = 15,
+    Float16_4 = 16,
+    Unused = 17,
+}
+
+impl TryFrom<u32> for DeclType {
+    type Error = XFileParseError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Float1,
+            1 => Self::Float2,
+            2 => Self::Float3,
+            3 => Self::Float4,
+            4 => Self::D3DColor,
+            5 => Self::UByte4,
+            6 => Self::Short2,
+            7 => Self::Short4,
+            8 => Self::UByte4N,
+            9 => Self::Short2N,
+            10 => Self::Short4N,
+            11 => Self::UShort2N,
+            12 => Self::UShort4N,
+            13 => Self::UDec3,
+            14 => Self::Dec3N,
+            15 => Self::Float16_2,
+            16 => Self::Float16_4,
+            17 => Self::Unused,
+            _ => return Err(XFileParseError::UnknownVertexElementType(value)),
+        })
+    }
+}
+
+impl DeclType {
+    /// Size of one element in DWORDs
+    pub fn size(self) -> usize {
+        match self {
+            Self::Float1
+            | Self::D3DColor
+            | Self::UByte4
+            | Self::Short2
+            | Self::UByte4N
+            | Self::Short2N
+            | Self::UShort2N
+            | Self::UDec3
+            | Self::Dec3N
+            | Self::Float16_2 => 1,
+            Self::Float2 | Self::Short4 | Self::Short4N | Self::UShort4N | Self::Float16_4 => 2,
+            Self::Float3 => 3,
+            Self::Float4 => 4,
+            Self::Unused => 0,
+        }
+    }
+
+    /// Number of components of one element
+    pub fn components(self) -> u32 {
+        match self {
+            Self::Float1 => 1,
+            Self::Float2 | Self::Short2 | Self::Short2N | Self::UShort2N | Self::Float16_2 => 2,
+            Self::Float3 | Self::UDec3 | Self::Dec3N => 3,
+            Self::Float4
+            | Self::D3DColor
+            | Self::UByte4
+            | Self::Short4
+            | Self::UByte4N
+            | Self::Short4N
+            | Self::UShort4N
+            | Self::Float16_4 => 4,
+            Self::Unused => 0,
+        }
+    }
+
+    /// Decodes one element, missing components are 0 except w which is 1.
+    /// All types fit into single precision floats.
+    pub fn decode(self, data: &[u32]) -> Color4D {
+        fn lo(v: u32) -> u16 {
+            v as u16
+        }
+        fn hi(v: u32) -> u16 {
+            (v >> 16) as u16
+        }
+        fn snorm16(v: u16) -> f32 {
+            (v as i16 as f32 / i16::MAX as f32).max(-1.0)
+        }
+        fn unorm16(v: u16) -> f32 {
+            v as f32 / u16::MAX as f32
+        }
+        fn snorm10(v: u32) -> f32 {
+            // sign extend the 10 bit value
+            let v = ((v << 22) as i32) >> 22;
+            (v as f32 / 511.0).max(-1.0)
+        }
+        let bytes = |v: u32| v.to_le_bytes().map(|b| b as f32);
+
+        match self {
+            Self::Float1 => Color4D::new(f32::from_bits(data[0]), 0.0, 0.0, 1.0),
+            Self::Float2 => {
+                Color4D::new(f32::from_bits(data[0]), f32::from_bits(data[1]), 0.0, 1.0)
+            }
+            Self::Float3 => Color4D::new(
+                f32::from_bits(data[0]),
+                f32::from_bits(data[1]),
+                f32::from_bits(data[2]),
+                1.0,
+            ),
+            Self::Float4 => Color4D::new(
+                f32::from_bits(data[0]),
+                f32::from_bits(data[1]),
+                f32::from_bits(data[2]),
+                f32::from_bits(data[3]),
+            ),
+            Self::D3DColor => {
+                // stored as BGRA
+                let [b, g, r, a] = bytes(data[0]);
+                Color4D::new(r, g, b, a) / 255.0
+            }
+            Self::UByte4 => Color4D::from_array(bytes(data[0])),
+            Self::UByte4N => Color4D::from_array(bytes(data[0])) / 255.0,
+            Self::Short2 => Color4D::new(
+                lo(data[0]) as i16 as f32,
+                hi(data[0]) as i16 as f32,
+                0.0,
+                1.0,
+            ),
+            Self::Short4 => Color4D::new(
+                lo(data[0]) as i16 as f32,
+                hi(data[0]) as i16 as f32,
+                lo(data[1]) as i16 as f32,
+                hi(data[1]) as i16 as f32,
+            ),
+            Self::Short2N => Color4D::new(snorm16(lo(data[0])), snorm16(hi(data[0])), 0.0, 1.0),
+            Self::Short4N => Color4D::new(
+                snorm16(lo(data[0])),
+                snorm16(hi(data[0])),
+                snorm16(lo(data[1])),
+                snorm16(hi(data[1])),
+            ),
+            Self::UShort2N => Color4D::new(unorm16(lo(data[0])), unorm16(hi(data[0])), 0.0, 1.0),
+            Self::UShort4N => Color4D::new(
+                unorm16(lo(data[0])),
+                unorm16(hi(data[0])),
+                unorm16(lo(data[1])),
+                unorm16(hi(data[1])),
+            ),
+            Self::UDec3 => Color4D::new(
+                (data[0] & 0x3ff) as f32,
+                ((data[0] >> 10) & 0x3ff) as f32,
+                ((data[0] >> 20) & 0x3ff) as f32,
+                1.0,
+            ),
+            Self::Dec3N => Color4D::new(
+                snorm10(data[0]),
+                snorm10(data[0] >> 10),
+                snorm10(data[0] >> 20),
+                1.0,
+            ),
+            Self::Float16_2 => {
+                Color4D::new(half_to_f32(lo(data[0])), half_to_f32(hi(data[0])), 0.0, 1.0)
+            }
+            Self::Float16_4 => Color4D::new(
+                half_to_f32(lo(data[0])),
+                half_to_f32(hi(data[0])),
+                half_to_f32(lo(data[1])),
+                half_to_f32(hi(data[1])),
+            ),
+            Self::Unused => Color4D::W,
+        }
+    }
+}
+
+/// Converts an IEEE 754 half precision float.
+fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        // subnormal, the value is mantissa * 2^-24
+        0 => {
+            let value = mantissa as f32 / (1 << 24) as f32;
+            return if sign != 0 { -value } else { value };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// `D3DDECLUSAGE`
+pub mod usage {
+    pub const POSITION: u32 = 0;
+    pub const BLENDWEIGHT: u32 = 1;
+    pub const BLENDINDICES: u32 = 2;
+    pub const NORMAL: u32 = 3;
+    pub const PSIZE: u32 = 4;
+    pub const TEXCOORD: u32 = 5;
+    pub const TANGENT: u32 = 6;
+    pub const BINORMAL: u32 = 7;
+    pub const TESSFACTOR: u32 = 8;
+    pub const POSITIONT: u32 = 9;
+    pub const COLOR: u32 = 10;
+    pub const FOG: u32 = 11;
+    pub const DEPTH: u32 = 12;
+    pub const SAMPLE: u32 = 13;
+}
+
+/// `D3DFVF` flags used by the `FVFData` template
+pub mod fvf {
+    pub const POSITION_MASK: u32 = 0x400e;
+    pub const XYZ: u32 = 0x002;
+    pub const XYZRHW: u32 = 0x004;
+    pub const XYZB1: u32 = 0x006;
+    pub const XYZB2: u32 = 0x008;
+    pub const XYZB3: u32 = 0x00a;
+    pub const XYZB4: u32 = 0x00c;
+    pub const XYZB5: u32 = 0x00e;
+    pub const XYZW: u32 = 0x4002;
+    pub const NORMAL: u32 = 0x010;
+    pub const PSIZE: u32 = 0x020;
+    pub const DIFFUSE: u32 = 0x040;
+    pub const SPECULAR: u32 = 0x080;
+    pub const TEXCOUNT_MASK: u32 = 0xf00;
+    pub const TEXCOUNT_SHIFT: u32 = 8;
+    pub const LASTBETA_UBYTE4: u32 = 0x1000;
+    pub const LASTBETA_D3DCOLOR: u32 = 0x8000;
+}
+
+/// One entry of a vertex declaration, the `VertexElement` template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexElement {
+    pub ty: DeclType,
+    pub method: u32,
+    pub usage: u32,
+    pub usage_index: u32,
+}
+
+impl VertexElement {
+    pub const fn new(ty: DeclType, usage: u32, usage_index: u32) -> Self {
+        Self {
+            ty,
+            method: 0,
+            usage,
+            usage_index,
+        }
+    }
+
+    /// Builds the vertex declaration equivalent to a flexible vertex format.
+    pub fn from_fvf(flags: u32) -> Vec<Self> {
+        let mut elements = Vec::new();
+        let blend_weights = match flags & fvf::POSITION_MASK {
+            fvf::XYZ => {
+                elements.push(Self::new(DeclType::Float3, usage::POSITION, 0));
+                0
+            }
+            fvf::XYZRHW => {
+                elements.push(Self::new(DeclType::Float4, usage::POSITIONT, 0));
+                0
+            }
+            fvf::XYZW => {
+                elements.push(Self::new(DeclType::Float4, usage::POSITION, 0));
+                0
+            }
+            b @ (fvf::XYZB1 | fvf::XYZB2 | fvf::XYZB3 | fvf::XYZB4 | fvf::XYZB5) => {
+                elements.push(Self::new(DeclType::Float3, usage::POSITION, 0));
+                (b - fvf::XYZ) / 2
+            }
+            _ => 0,
+        };
+        if blend_weights > 0 {
+            // the last beta may hold the blend indices instead of a weight
+            let indexed = flags & (fvf::LASTBETA_UBYTE4 | fvf::LASTBETA_D3DCOLOR) != 0;
+            let weights = blend_weights - indexed as u32;
+            let ty = [
+                DeclType::Unused,
+                DeclType::Float1,
+                DeclType::Float2,
+                DeclType::Float3,
+                DeclType::Float4,
+            ];
+            if weights > 0 {
+                elements.push(Self::new(
+                    ty[weights.min(4) as usize],
+                    usage::BLENDWEIGHT,
+                    0,
+                ));
+            }
+            if indexed {
+                let ty = if flags & fvf::LASTBETA_UBYTE4 != 0 {
+                    DeclType::UByte4
+                } else {
+                    DeclType::D3DColor
+                };
+                elements.push(Self::new(ty, usage::BLENDINDICES, 0));
+            }
+        }
+        if flags & fvf::NORMAL != 0 {
+            elements.push(Self::new(DeclType::Float3, usage::NORMAL, 0));
+        }
+        if flags & fvf::PSIZE != 0 {
+            elements.push(Self::new(DeclType::Float1, usage::PSIZE, 0));
+        }
+        if flags & fvf::DIFFUSE != 0 {
+            elements.push(Self::new(DeclType::D3DColor, usage::COLOR, 0));
+        }
+        if flags & fvf::SPECULAR != 0 {
+            elements.push(Self::new(DeclType::D3DColor, usage::COLOR, 1));
+        }
+        let num_tex_coords = (flags & fvf::TEXCOUNT_MASK) >> fvf::TEXCOUNT_SHIFT;
+        for i in 0..num_tex_coords {
+            // D3DFVF_TEXCOORDSIZEn, two bits per set starting at bit 16
+            let ty = match (flags >> (16 + i * 2)) & 0x3 {
+                0 => DeclType::Float2,
+                1 => DeclType::Float3,
+                2 => DeclType::Float4,
+                _ => DeclType::Float1,
+            };
+            elements.push(Self::new(ty, usage::TEXCOORD, i));
+        }
+        elements
+    }
+}
+
+/// The first three components of a decoded element in the float precision
+/// of the scene
+fn to_real(v: Color4D) -> Vec3 {
+    real3(v.truncate())
+}
+
+impl Mesh<'_> {
+    /// Merges the per-vertex streams of a vertex declaration into the mesh.
+    ///
+    /// Only streams which the mesh does not have yet are taken over, positions
+    /// and data without an `aiMesh` counterpart are ignored.
+    pub fn merge_vertex_streams(
+        &mut self,
+        elements: &[VertexElement],
+        data: &[u32],
+    ) -> Result<(), XFileParseError> {
+        let stride = elements.iter().map(|e| e.ty.size()).sum::<usize>();
+        let num_vertices = self.positions.len();
+        if stride == 0 || num_vertices == 0 {
+            return Ok(());
+        }
+        if data.len() != stride * num_vertices {
+            return Err(XFileParseError::VertexDeclarationDataCountMismatch {
+                expected: stride * num_vertices,
+                found: data.len(),
+            });
+        }
+
+        let mut offset = 0;
+        for element in elements {
+            let (start, size) = (offset, element.ty.size());
+            offset += size;
+            let values = data
+                .chunks_exact(stride)
+                .map(|vertex| element.ty.decode(&vertex[start..start + size]));
+            let index = element.usage_index as usize;
+            match element.usage {
+                usage::NORMAL if self.normals.is_empty() && index == 0 => {
+                    self.normals = values.map(to_real).collect();
+                    // normals are indexed like the positions
+                    self.norm_faces = self.pos_faces.clone();
+                }
+                usage::TANGENT if self.tangents.is_empty() && index == 0 => {
+                    self.tangents = values.map(to_real).collect();
+                }
+                usage::BINORMAL if self.bitangents.is_empty() && index == 0 => {
+                    self.bitangents = values.map(to_real).collect();
+                }
+                usage::TEXCOORD
+                    if index < AI_MAX_NUMBER_OF_TEXTURECOORDS
+                        && self.tex_coords[index].is_empty() =>
+                {
+                    // at most 3 components are supported per set
+                    let components = element.ty.components().min(3);
+                    self.tex_coords[index] = values
+                        .map(to_real)
+                        .map(|v| match components {
+                            1 => Vec3::new(v.x, 0.0, 0.0),
+                            2 => Vec3::new(v.x, v.y, 0.0),
+                            _ => v,
+                        })
+                        .collect();
+                    self.num_uv_components[index] = components;
+                    self.num_textures = self.num_textures.max(index as u32 + 1);
+                }
+                usage::COLOR
+                    if index < AI_MAX_NUMBER_OF_COLOR_SETS && self.colors[index].is_empty() =>
+                {
+                    self.colors[index] = values.collect();
+                    self.num_color_sets = self.num_color_sets.max(index as u32 + 1);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,62 +1,166 @@
-use std::array;
+use std::{array, borrow::Cow};
 
+use super::parser::template::TemplateRegistry;
 use crate::{
+    AiReal,
     structs::{
-        color::{Color3D, Color4D},
+        camera::AiCamera,
+        color::Color4D,
+        importer::report::ImportReport,
         key::{AiQuatKey, AiVectorKey},
+        light::AiLight,
         mesh::{AI_MAX_NUMBER_OF_COLOR_SETS, AI_MAX_NUMBER_OF_TEXTURECOORDS},
+        meta::Metadata,
         nodes::Index,
     },
-    utils::float_precision::{Mat4, Vec2, Vec3},
+    utils::float_precision::{Mat4, Vec3, Vec4},
 };
 
-#[derive(Debug, Clone, Default)]
-pub struct Face {
-    pub indices: Vec<u32>,
+/// Scene metadata key of the comments found in a text file, one per line
+pub const AI_METADATA_X_COMMENTS: &str = "X_Comments";
+
+/// Scene metadata key of the `AnimTicksPerSecond` data object
+pub const AI_METADATA_X_ANIM_TICKS_PER_SECOND: &str = "X_AnimTicksPerSecond";
+
+/// The faces of a mesh. The indices of all faces share one buffer, so
+/// meshes with hundreds of thousands of small faces do not allocate once
+/// per face while parsing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaceList {
+    pub(super) indices: Vec<u32>,
+    /// End of each face in `indices`
+    pub(super) ends: Vec<u32>,
+}
+
+impl FaceList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of faces
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// The indices of face `face`
+    pub fn get(&self, face: usize) -> Option<&[u32]> {
+        let end = *self.ends.get(face)? as usize;
+        let start = match face {
+            0 => 0,
+            _ => self.ends[face - 1] as usize,
+        };
+        Some(&self.indices[start..end])
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u32]> + '_ {
+        (0..self.len()).map(|face| &self[face])
+    }
+
+    /// Adds a face with `indices`
+    pub fn push(&mut self, indices: &[u32]) {
+        self.indices.extend_from_slice(indices);
+        self.finish_face();
+    }
+
+    /// Adds a face with the indices pushed to `indices` since the last face
+    pub(super) fn finish_face(&mut self) {
+        self.ends.push(self.indices.len() as u32);
+    }
+
+    /// Keeps the first `len` faces, and drops the indices of a face which
+    /// was not finished
+    pub fn truncate(&mut self, len: usize) {
+        self.ends.truncate(len);
+        self.indices
+            .truncate(self.ends.last().map_or(0, |&end| end as usize));
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+}
+
+impl core::ops::Index<usize> for FaceList {
+    type Output = [u32];
+
+    fn index(&self, face: usize) -> &[u32] {
+        self.get(face).expect("face index out of range")
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct TexEntry {
-    pub name: String,
+pub struct TexEntry<'source> {
+    pub name: Cow<'source, str>,
     pub is_normal_map: bool,
 }
 
-impl TexEntry {
-    pub fn new(name: String, is_normal_map: bool) -> Self {
+impl<'source> TexEntry<'source> {
+    pub fn new(name: Cow<'source, str>, is_normal_map: bool) -> Self {
         Self {
             name,
             is_normal_map,
         }
     }
+
+    pub fn into_owned(self) -> TexEntry<'static> {
+        TexEntry {
+            name: Cow::Owned(self.name.into_owned()),
+            is_normal_map: self.is_normal_map,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct Material {
-    pub name: String,
+pub struct Material<'source> {
+    pub name: Cow<'source, str>,
     pub is_reference: bool, // if true, name holds a name by which the actual material can be found in the material list
-    pub diffuse: Color4D,
-    pub specular_exponent: f32,
-    pub specular: Color3D,
-    pub emissive: Color3D,
-    pub textures: Vec<TexEntry>,
+    // material colors are stored in the precision of the scene
+    pub diffuse: Vec4,
+    pub specular_exponent: AiReal,
+    pub specular: Vec3,
+    pub emissive: Vec3,
+    pub textures: Vec<TexEntry<'source>>,
     pub scene_index: u32,
 }
 
-impl Default for Material {
+impl Default for Material<'_> {
     fn default() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             is_reference: false,
-            diffuse: Color4D::default(),
+            diffuse: Vec4::ZERO,
             specular_exponent: 0.0,
-            specular: Color3D::default(),
-            emissive: Color3D::default(),
+            specular: Vec3::ZERO,
+            emissive: Vec3::ZERO,
             textures: Vec::new(),
             scene_index: u32::MAX,
         }
     }
 }
 
+impl Material<'_> {
+    pub fn into_owned(self) -> Material<'static> {
+        Material {
+            name: Cow::Owned(self.name.into_owned()),
+            textures: self
+                .textures
+                .into_iter()
+                .map(TexEntry::into_owned)
+                .collect(),
+            is_reference: self.is_reference,
+            diffuse: self.diffuse,
+            specular_exponent: self.specular_exponent,
+            specular: self.specular,
+            emissive: self.emissive,
+            scene_index: self.scene_index,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct BoneWeight {
     pub vertex: u32,
@@ -64,66 +168,172 @@ pub struct BoneWeight {
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct Bone {
-    pub name: String,
+pub struct Bone<'source> {
+    pub name: Cow<'source, str>,
     pub weights: Vec<BoneWeight>,
     pub offset_matrix: Mat4,
 }
 
-impl Bone {
-    pub fn new(name: String) -> Self {
+impl<'source> Bone<'source> {
+    pub fn new(name: Cow<'source, str>) -> Self {
         Self {
             name,
             weights: Vec::new(),
             offset_matrix: Mat4::ZERO,
         }
     }
+
+    pub fn into_owned(self) -> Bone<'static> {
+        Bone {
+            name: Cow::Owned(self.name.into_owned()),
+            weights: self.weights,
+            offset_matrix: self.offset_matrix,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct Mesh {
-    pub name: String,
+pub struct Mesh<'source> {
+    pub name: Cow<'source, str>,
     pub positions: Vec<Vec3>,
-    pub pos_faces: Vec<Face>,
+    pub pos_faces: FaceList,
     pub normals: Vec<Vec3>,
-    pub norm_faces: Vec<Face>,
+    pub norm_faces: FaceList,
+    pub tangents: Vec<Vec3>,
+    pub bitangents: Vec<Vec3>,
     pub num_textures: u32,
-    pub tex_coords: [Vec<Vec2>; AI_MAX_NUMBER_OF_TEXTURECOORDS],
+    pub tex_coords: [Vec<Vec3>; AI_MAX_NUMBER_OF_TEXTURECOORDS],
+    pub num_uv_components: [u32; AI_MAX_NUMBER_OF_TEXTURECOORDS],
+    pub tex_coords_names: [Cow<'source, str>; AI_MAX_NUMBER_OF_TEXTURECOORDS],
     pub num_color_sets: u32,
     pub colors: [Vec<Color4D>; AI_MAX_NUMBER_OF_COLOR_SETS],
 
     pub face_materials: Vec<u32>,
-    pub materials: Vec<Material>,
+    pub materials: Vec<Material<'source>>,
 
-    pub bones: Vec<Bone>,
+    pub bones: Vec<Bone<'source>>,
+
+    /// The original vertex of each position, from `VertexDuplicationIndices`.
+    /// D3DX splits vertices with several normals or texture coordinates and
+    /// writes this to map the copies back to the first vertex split from the
+    /// same original.
+    pub duplication_indices: Vec<u32>,
 }
 
-impl Default for Mesh {
+impl Default for Mesh<'_> {
     fn default() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             positions: Vec::new(),
-            pos_faces: Vec::new(),
+            pos_faces: FaceList::new(),
             normals: Vec::new(),
-            norm_faces: Vec::new(),
+            norm_faces: FaceList::new(),
+            tangents: Vec::new(),
+            bitangents: Vec::new(),
             num_textures: 0,
             tex_coords: array::from_fn(|_| Vec::new()),
+            num_uv_components: [0; AI_MAX_NUMBER_OF_TEXTURECOORDS],
+            tex_coords_names: array::from_fn(|_| Cow::Borrowed("")),
             num_color_sets: 0,
             colors: array::from_fn(|_| Vec::new()),
             face_materials: Vec::new(),
             materials: Vec::new(),
             bones: Vec::new(),
+            duplication_indices: Vec::new(),
         }
     }
 }
 
-impl Mesh {
-    pub fn new(name: String) -> Self {
+impl<'source> Mesh<'source> {
+    pub fn new(name: Cow<'source, str>) -> Self {
         Self {
             name,
             ..Default::default()
         }
     }
+
+    pub fn into_owned(self) -> Mesh<'static> {
+        Mesh {
+            name: Cow::Owned(self.name.into_owned()),
+            tex_coords_names: self
+                .tex_coords_names
+                .map(|name| Cow::Owned(name.into_owned())),
+            materials: self
+                .materials
+                .into_iter()
+                .map(Material::into_owned)
+                .collect(),
+            bones: self.bones.into_iter().map(Bone::into_owned).collect(),
+            positions: self.positions,
+            pos_faces: self.pos_faces,
+            normals: self.normals,
+            norm_faces: self.norm_faces,
+            tangents: self.tangents,
+            bitangents: self.bitangents,
+            num_textures: self.num_textures,
+            tex_coords: self.tex_coords,
+            num_uv_components: self.num_uv_components,
+            num_color_sets: self.num_color_sets,
+            colors: self.colors,
+            face_materials: self.face_materials,
+            duplication_indices: self.duplication_indices,
+        }
+    }
+
+    /// Drops everything which was not read completely, used when keeping a
+    /// mesh of a truncated file.
+    pub fn discard_incomplete_data(&mut self) {
+        let num_vertices = self.positions.len();
+        let num_faces = self
+            .pos_faces
+            .iter()
+            .take_while(|face| {
+                !face.is_empty() && face.iter().all(|&i| (i as usize) < num_vertices)
+            })
+            .count();
+        self.pos_faces.truncate(num_faces);
+        self.face_materials.truncate(num_faces);
+
+        if self.norm_faces.len() < num_faces
+            || self
+                .norm_faces
+                .iter()
+                .take(num_faces)
+                .any(|face| face.iter().any(|&i| i as usize >= self.normals.len()))
+        {
+            self.normals.clear();
+            self.norm_faces.clear();
+        } else {
+            self.norm_faces.truncate(num_faces);
+        }
+        for vertices in [&mut self.tangents, &mut self.bitangents] {
+            if vertices.len() != num_vertices {
+                vertices.clear();
+            }
+        }
+        for (tex_coords, components) in self
+            .tex_coords
+            .iter_mut()
+            .zip(self.num_uv_components.iter_mut())
+        {
+            if tex_coords.len() != num_vertices {
+                tex_coords.clear();
+                *components = 0;
+            }
+        }
+        for colors in self.colors.iter_mut() {
+            if colors.len() != num_vertices {
+                colors.clear();
+            }
+        }
+        if self.duplication_indices.len() != num_vertices {
+            self.duplication_indices.clear();
+        }
+        for bone in self.bones.iter_mut() {
+            bone.weights
+                .retain(|weight| (weight.vertex as usize) < num_vertices);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -134,91 +344,143 @@ pub struct MatrixKey {
 
 /** Helper structure representing a single animated bone in a XFile */
 #[derive(Debug, Clone, Default)]
-pub struct AnimBone {
-    pub name: String,
+pub struct AnimBone<'source> {
+    pub name: Cow<'source, str>,
     pub pos_keys: Vec<AiVectorKey>, // either three separate key sequences for position, rotation, scaling
     pub rot_keys: Vec<AiQuatKey>,
     pub scale_keys: Vec<AiVectorKey>,
     pub trafo_keys: Vec<MatrixKey>, // or a combined key sequence of transformation matrices.
 }
 
-impl AnimBone {
+impl AnimBone<'_> {
     pub fn new() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             pos_keys: Vec::new(),
             rot_keys: Vec::new(),
             scale_keys: Vec::new(),
             trafo_keys: Vec::new(),
         }
     }
+
+    pub fn into_owned(self) -> AnimBone<'static> {
+        AnimBone {
+            name: Cow::Owned(self.name.into_owned()),
+            pos_keys: self.pos_keys,
+            rot_keys: self.rot_keys,
+            scale_keys: self.scale_keys,
+            trafo_keys: self.trafo_keys,
+        }
+    }
 }
 
 /** Helper structure to represent an animation set in a XFile */
 #[derive(Debug, Clone, Default)]
-pub struct Animation {
-    pub name: String,
-    pub anims: Vec<AnimBone>,
+pub struct Animation<'source> {
+    pub name: Cow<'source, str>,
+    pub anims: Vec<AnimBone<'source>>,
 }
 
-impl Animation {
-    pub fn new(name: String) -> Self {
+impl<'source> Animation<'source> {
+    pub fn new(name: Cow<'source, str>) -> Self {
         Self {
             name,
             anims: Vec::new(),
         }
     }
+
+    pub fn into_owned(self) -> Animation<'static> {
+        Animation {
+            name: Cow::Owned(self.name.into_owned()),
+            anims: self.anims.into_iter().map(AnimBone::into_owned).collect(),
+        }
+    }
 }
 
+/// Name of the node holding several top level frames
+pub const DUMMY_ROOT_NAME: &str = "$dummy_root";
+
 /** Helper structure to represent a XFile frame */
 #[derive(Debug, Clone)]
-pub struct Node {
-    pub name: String,
+pub struct Node<'source> {
+    pub name: Cow<'source, str>,
     pub transformation_matrix: Mat4,
-    pub parent: Index<Node>,
-    pub children: Vec<Index<Node>>,
-    pub meshes: Vec<Mesh>,
+    /// `None` for the root node
+    pub parent: Option<Index<Node<'source>>>,
+    pub children: Vec<Index<Node<'source>>>,
+    pub meshes: Vec<Mesh<'source>>,
+    pub metadata: Metadata,
 }
 
-impl Default for Node {
+impl Default for Node<'_> {
     fn default() -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             transformation_matrix: Mat4::IDENTITY,
-            parent: Index::new(0),
+            parent: None,
             children: Vec::new(),
             meshes: Vec::new(),
+            metadata: Metadata::default(),
         }
     }
 }
 
-impl Node {
-    pub fn new(parent: Index<Node>) -> Self {
+impl<'source> Node<'source> {
+    pub fn new(parent: Option<Index<Node<'source>>>) -> Self {
         Self {
-            name: String::new(),
+            name: Cow::Borrowed(""),
             transformation_matrix: Mat4::IDENTITY,
             parent,
             children: Vec::new(),
             meshes: Vec::new(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    pub fn into_owned(self) -> Node<'static> {
+        Node {
+            name: Cow::Owned(self.name.into_owned()),
+            transformation_matrix: self.transformation_matrix,
+            parent: self.parent.map(Index::cast),
+            children: self.children.into_iter().map(Index::cast).collect(),
+            meshes: self.meshes.into_iter().map(Mesh::into_owned).collect(),
+            metadata: self.metadata,
         }
     }
 }
 
-/** Helper structure analogue to aiScene */
+/// Helper structure analogue to aiScene.
+///
+/// Names borrow from the source buffer where possible, see
+/// [`Scene::into_owned`] to detach the scene from it.
 #[derive(Debug, Clone)]
-pub struct Scene {
-    pub root_node: Option<Index<Node>>,
+pub struct Scene<'source> {
+    pub root_node: Option<Index<Node<'source>>>,
 
-    pub nodes: Vec<Node>,
+    pub nodes: Vec<Node<'source>>,
 
-    pub global_meshes: Vec<Mesh>, // global meshes found outside of any frames
-    pub global_materials: Vec<Material>, // global materials found outside of any meshes.
+    pub global_meshes: Vec<Mesh<'source>>, // global meshes found outside of any frames
+    pub global_materials: Vec<Material<'source>>, // global materials found outside of any meshes.
 
-    pub animations: Vec<Animation>,
+    pub animations: Vec<Animation<'source>>,
     pub anim_ticks_per_second: u32,
+
+    pub lights: Vec<AiLight>,
+    pub cameras: Vec<AiCamera>,
+
+    pub metadata: Metadata,
+
+    /// The templates declared in the file, and a few standard ones
+    pub templates: TemplateRegistry,
+
+    /// The error which ended parsing early, if a partial scene was kept
+    pub partial_error: Option<String>,
+
+    /// The problems tolerated or repaired while parsing
+    pub report: ImportReport,
 }
 
-impl Default for Scene {
+impl Default for Scene<'_> {
     fn default() -> Self {
         Self {
             root_node: None,
@@ -227,20 +489,93 @@ impl Default for Scene {
             global_materials: Vec::new(),
             animations: Vec::new(),
             anim_ticks_per_second: 0,
+            lights: Vec::new(),
+            cameras: Vec::new(),
+            metadata: Metadata::default(),
+            templates: TemplateRegistry::with_standard_templates(),
+            partial_error: None,
+            report: ImportReport::default(),
         }
     }
 }
 
-impl Scene {
-    pub fn push_node(&mut self, parent: Index<Node>, node: Node) -> Index<Node> {
+impl<'source> Scene<'source> {
+    /// Adds a frame below `parent`. The first top level frame becomes the
+    /// root node, further ones are grouped below a dummy root node like in
+    /// assimp.
+    pub fn push_node(
+        &mut self,
+        parent: Option<Index<Node<'source>>>,
+        mut node: Node<'source>,
+    ) -> Index<Node<'source>> {
+        let parent = match (parent, self.root_node) {
+            (Some(parent), _) => Some(parent),
+            (None, None) => None,
+            (None, Some(root)) => Some(self.dummy_root(root)),
+        };
+        node.parent = parent;
         let index = Index::push(&mut self.nodes, node);
-        if index.value() == 0 {
-            self.root_node = Some(index);
-            return index;
-        }
-        if let Some(parent) = Index::get_mut(parent, &mut self.nodes) {
-            parent.children.push(index);
+        match parent {
+            None => self.root_node = Some(index),
+            Some(parent) => {
+                if let Some(parent) = Index::get_mut(parent, &mut self.nodes) {
+                    parent.children.push(index);
+                }
+            }
         }
         index
     }
+
+    fn dummy_root(&mut self, root: Index<Node<'source>>) -> Index<Node<'source>> {
+        if root
+            .get(&self.nodes)
+            .is_some_and(|node| node.name == DUMMY_ROOT_NAME)
+        {
+            return root;
+        }
+        let dummy = Index::push(
+            &mut self.nodes,
+            Node {
+                name: Cow::Borrowed(DUMMY_ROOT_NAME),
+                children: vec![root],
+                ..Default::default()
+            },
+        );
+        if let Some(root) = root.get_mut(&mut self.nodes) {
+            root.parent = Some(dummy);
+        }
+        self.root_node = Some(dummy);
+        dummy
+    }
+
+    /// Copies all borrowed names, e.g. when the source was a temporary
+    /// decompression buffer.
+    pub fn into_owned(self) -> Scene<'static> {
+        Scene {
+            root_node: self.root_node.map(Index::cast),
+            nodes: self.nodes.into_iter().map(Node::into_owned).collect(),
+            global_meshes: self
+                .global_meshes
+                .into_iter()
+                .map(Mesh::into_owned)
+                .collect(),
+            global_materials: self
+                .global_materials
+                .into_iter()
+                .map(Material::into_owned)
+                .collect(),
+            animations: self
+                .animations
+                .into_iter()
+                .map(Animation::into_owned)
+                .collect(),
+            anim_ticks_per_second: self.anim_ticks_per_second,
+            lights: self.lights,
+            cameras: self.cameras,
+            metadata: self.metadata,
+            templates: self.templates,
+            partial_error: self.partial_error,
+            report: self.report,
+        }
+    }
 }
@@ -0,0 +1,30 @@
+//! Entry points for fuzzers, see the fuzz directory. The importers get
+//! arbitrary bytes and the scenes are post-processed like the assimp fuzzer
+//! does. Errors are expected, panics are bugs.
+
+use crate::{
+    postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+    structs::{importer::ImportProperties, scene::AiScene},
+    traits::importer::trait_define::Importer,
+};
+
+/// Imports `data` with `I` once for every set of `properties` and
+/// post-processes the scenes which were imported
+pub fn fuzz_import_with<I: Importer<E>, E>(data: &[u8], properties: &[ImportProperties]) {
+    let pipeline = PostProcessPipeline::new(
+        AiPostProcessSteps::Preset_TargetRealtime_Quality
+            | AiPostProcessSteps::ValidateDataStructure,
+    );
+    for properties in properties {
+        let mut scene = AiScene::default();
+        if I::import_from_buf_with_properties(data, &mut scene, properties).is_ok() {
+            pipeline.run(&mut scene);
+        }
+    }
+}
+
+/// Imports `data` with `I` and the default properties, see
+/// [`fuzz_import_with`]
+pub fn fuzz_import<I: Importer<E>, E>(data: &[u8]) {
+    fuzz_import_with::<I, E>(data, &[ImportProperties::default()]);
+}
@@ -1,9 +1,32 @@
+#[cfg(feature = "bevy")]
+pub mod bevy;
 pub mod core;
 pub mod errors;
 pub mod formats;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
 pub mod postprocess;
 pub mod structs;
 pub mod traits;
 pub(crate) mod utils;
 
-pub use utils::AiReal;
+/// The math types of the scene structures, glam types of the precision of
+/// the build.
+///
+/// With the `mint` feature they convert from and to `mint` types with
+/// `From`, which in turn convert to nalgebra and other math crates, so
+/// applications don't have to depend on the same glam version.
+/// [`AABB`](crate::structs::aabb::AABB) converts to its corners.
+pub mod math {
+    pub use glam;
+    #[cfg(feature = "mint")]
+    pub use mint;
+
+    pub use crate::utils::float_precision::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
+}
+
+pub use utils::{
+    AiReal,
+    float_precision::{compose, decompose},
+    get_model_path,
+};
@@ -0,0 +1,121 @@
+use super::{AiPostProcessSteps, PostProcess};
+use crate::structs::{
+    mesh::{AiMesh, Skeleton, SkeletonBone},
+    nodes::Index,
+    scene::{AiNode, AiScene},
+};
+
+/// Postprocessing step to build [`Skeleton`]s from the bones of the meshes.
+///
+/// Every mesh with bones gets one skeleton. The bones are resolved to their
+/// nodes by name, a bone's parent is the nearest ancestor node which is also a
+/// bone of the same mesh. The armature is the node above the top-most bone.
+/// Existing skeletons of the scene are replaced.
+pub struct ArmaturePopulateProcess;
+
+impl ArmaturePopulateProcess {
+    /// Builds the skeletons of all meshes without modifying the scene.
+    pub fn build_skeletons(scene: &AiScene) -> Vec<Skeleton> {
+        let Some(root) = scene.root else {
+            return Vec::new();
+        };
+        scene
+            .meshes
+            .iter()
+            .enumerate()
+            .filter(|(_, mesh)| mesh.has_bones())
+            .map(|(i, mesh)| Self::build_skeleton(scene, root, mesh, Index::new(i as u32)))
+            .collect()
+    }
+
+    fn build_skeleton(
+        scene: &AiScene,
+        root: Index<AiNode>,
+        mesh: &AiMesh,
+        mesh_id: Index<AiMesh>,
+    ) -> Skeleton {
+        let nodes = mesh
+            .bones
+            .iter()
+            .map(|bone| scene.find_node_by_name(&bone.name, root))
+            .collect::<Vec<_>>();
+
+        let parent_of = |node: Index<AiNode>| -> Option<Index<AiNode>> {
+            if node == root {
+                None
+            } else {
+                scene.get_node_by_index(node).and_then(|n| n.parent)
+            }
+        };
+
+        let mut bones = Vec::with_capacity(mesh.bones.len());
+        let mut armature = None;
+        for (bone, node) in mesh.bones.iter().zip(nodes.iter()) {
+            let mut parent = -1;
+            let mut bone_armature = None;
+            if let Some(node) = *node {
+                // Walk up until another bone of this mesh is found
+                let mut current = parent_of(node);
+                let mut depth = 0;
+                while let Some(ancestor) = current {
+                    if let Some(p) = nodes
+                        .iter()
+                        .position(|n| n.is_some_and(|n| n.value() == ancestor.value()))
+                    {
+                        parent = p as i32;
+                        break;
+                    }
+                    bone_armature.get_or_insert(ancestor);
+                    depth += 1;
+                    // Guard against cycles in malformed hierarchies
+                    if depth > scene.nodes.len() {
+                        break;
+                    }
+                    current = parent_of(ancestor);
+                }
+                if parent == -1 {
+                    armature = armature.or(bone_armature);
+                }
+            }
+            bones.push(SkeletonBone {
+                parent,
+                armature: None,
+                node: *node,
+                mesh_id,
+                weights: bone.weights.clone().into_boxed_slice(),
+                offset_matrix: bone.offset_matrix,
+                local_matrix: node
+                    .and_then(|node| scene.get_node_by_index(node))
+                    .map(|node| node.transformation)
+                    .unwrap_or_default(),
+            });
+        }
+
+        let armature = armature.unwrap_or(root);
+        for bone in bones.iter_mut() {
+            bone.armature = Some(armature);
+        }
+        let name = match scene.get_node_by_index(armature) {
+            Some(node) if !node.name.is_empty() => node.name.as_str(),
+            _ => mesh.name.as_str(),
+        };
+        Skeleton {
+            name: name.into(),
+            bones: bones.into_boxed_slice(),
+        }
+    }
+}
+
+impl PostProcess for ArmaturePopulateProcess {
+    fn execute(scene: &mut AiScene) {
+        scene.skeletons = Self::build_skeletons(scene);
+        log::debug!(
+            "ArmaturePopulateProcess finished, {} skeletons built",
+            scene.skeletons.len()
+        );
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::PopulateArmatureData)
+    }
+}
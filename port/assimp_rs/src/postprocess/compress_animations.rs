@@ -0,0 +1,252 @@
+use crate::{
+    AiReal,
+    structs::{
+        anim::{AiAnimInterpolation, AiAnimation, interpolate::SampleKey},
+        key::{AiQuatKey, AiVectorKey},
+        scene::AiScene,
+    },
+    utils::float_precision::{Quat, Vec3},
+};
+
+/// Configuration of [`CompressAnimationsProcess`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimCompressionConfig {
+    /// Largest distance between a removed position or scaling key and the
+    /// value interpolated from the remaining keys
+    pub linear_tolerance: AiReal,
+
+    /// Largest angle in radians between a removed rotation key and the
+    /// rotation interpolated from the remaining keys
+    pub angular_tolerance: AiReal,
+
+    /// Frames per second the key times are rounded to before the keys are
+    /// removed, `None` keeps the times
+    pub frame_rate: Option<f64>,
+}
+
+impl Default for AnimCompressionConfig {
+    fn default() -> Self {
+        Self {
+            linear_tolerance: 1e-4,
+            angular_tolerance: 1e-3,
+            frame_rate: None,
+        }
+    }
+}
+
+/// Postprocessing step to remove redundant keys of node animation channels,
+/// e.g. of exporters which write a key for every frame.
+///
+/// A key is removed if interpolating between its remaining neighbors
+/// reproduces it within the tolerances of the [`AnimCompressionConfig`].
+/// Keys are only removed between keys with the same interpolation, cubic
+/// splines are kept. Channels which stay constant are reduced to one key.
+pub struct CompressAnimationsProcess;
+
+/// Key frames of node channels which can be removed
+trait CompressKey: SampleKey + Copy {
+    fn interpolation(&self) -> AiAnimInterpolation;
+
+    fn time_mut(&mut self) -> &mut f64;
+
+    /// The difference between two values, to compare with the tolerance
+    fn error(a: Self::Value, b: Self::Value) -> AiReal;
+}
+
+impl CompressKey for AiVectorKey {
+    fn interpolation(&self) -> AiAnimInterpolation {
+        self.interpolation
+    }
+
+    fn time_mut(&mut self) -> &mut f64 {
+        &mut self.time
+    }
+
+    fn error(a: Vec3, b: Vec3) -> AiReal {
+        a.distance(b)
+    }
+}
+
+impl CompressKey for AiQuatKey {
+    fn interpolation(&self) -> AiAnimInterpolation {
+        self.interpolation
+    }
+
+    fn time_mut(&mut self) -> &mut f64 {
+        &mut self.time
+    }
+
+    fn error(a: Quat, b: Quat) -> AiReal {
+        a.angle_between(b)
+    }
+}
+
+impl CompressAnimationsProcess {
+    pub fn execute_with_config(scene: &mut AiScene, config: &AnimCompressionConfig) {
+        log::debug!("CompressAnimationsProcess begin");
+        let mut removed = 0;
+        for animation in scene.animations.iter_mut() {
+            removed += Self::process_animation(animation, config);
+        }
+        log::info!("CompressAnimationsProcess finished. Removed {removed} keys");
+    }
+
+    /// Compresses the node channels of an animation, returns the number of
+    /// removed keys.
+    pub fn process_animation(animation: &mut AiAnimation, config: &AnimCompressionConfig) -> usize {
+        let frame = config
+            .frame_rate
+            .filter(|rate| *rate > 0.0 && rate.is_finite())
+            .map(|rate| animation.ticks_per_second_or_default() / rate);
+        let mut removed = 0;
+        for channel in animation.channels.iter_mut() {
+            removed += compress(&mut channel.position_keys, config.linear_tolerance, frame);
+            removed += compress(&mut channel.rotation_keys, config.angular_tolerance, frame);
+            removed += compress(&mut channel.scaling_keys, config.linear_tolerance, frame);
+        }
+        removed
+    }
+}
+
+/// Rounds the key times to multiples of `frame`, if any, and removes the
+/// redundant keys. Returns the number of removed keys.
+fn compress<K: CompressKey>(keys: &mut Vec<K>, tolerance: AiReal, frame: Option<f64>) -> usize {
+    let count = keys.len();
+    if let Some(frame) = frame {
+        // of several keys rounded to the same frame the closest one is kept
+        let mut quantized: Vec<(K, f64)> = Vec::with_capacity(count);
+        for mut key in keys.drain(..) {
+            let time = (key.time() / frame).round() * frame;
+            let distance = (key.time() - time).abs();
+            *key.time_mut() = time;
+            match quantized.last_mut() {
+                Some((last, last_distance)) if last.time() == time => {
+                    if distance < *last_distance {
+                        (*last, *last_distance) = (key, distance);
+                    }
+                }
+                _ => quantized.push((key, distance)),
+            }
+        }
+        keys.extend(quantized.into_iter().map(|(key, _)| key));
+    }
+    if let [first, .., last] = keys.as_slice() {
+        let (first, last) = (*first, *last);
+        let mut kept = vec![first];
+        let mut anchor = 0;
+        for end in 2..keys.len() {
+            if !is_redundant(&keys[anchor..=end], tolerance) {
+                kept.push(keys[end - 1]);
+                anchor = end - 1;
+            }
+        }
+        // a constant channel keeps only its first key
+        let constant = kept.len() == 1
+            && first.interpolation() != AiAnimInterpolation::CubicSpline
+            && K::error(first.value(), last.value()) <= tolerance;
+        if !constant {
+            kept.push(last);
+        }
+        *keys = kept;
+    }
+    count - keys.len()
+}
+
+/// Whether interpolating between the first and the last of `keys`
+/// reproduces the keys in between
+fn is_redundant<K: CompressKey>(keys: &[K], tolerance: AiReal) -> bool {
+    let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+        return true;
+    };
+    let interpolation = first.interpolation();
+    if interpolation == AiAnimInterpolation::CubicSpline {
+        return false;
+    }
+    let span = last.time() - first.time();
+    keys[1..keys.len() - 1].iter().all(|key| {
+        let factor = if span > 0.0 {
+            ((key.time() - first.time()) / span) as AiReal
+        } else {
+            0.0
+        };
+        key.interpolation() == interpolation
+            && K::error(first.sample(last, factor, span), key.value()) <= tolerance
+    })
+}
+
+#[allow(unused)]
+mod test {
+    use super::{AnimCompressionConfig, CompressAnimationsProcess};
+    use crate::{
+        AiReal,
+        structs::{
+            anim::{AiAnimInterpolation, AiAnimation, anim::AiNodeAnim},
+            key::{AiQuatKey, AiVectorKey},
+        },
+        utils::float_precision::{Quat, Vec3},
+    };
+
+    #[test]
+    fn test_compress_animations() {
+        // a key on every tick: a linear move with a stop, a constant
+        // rotation with some noise and a step to another scaling
+        let linear = (0..=20)
+            .map(|i| {
+                let x = i.min(10) as AiReal;
+                AiVectorKey::new(i as f64, Vec3::new(x, 0.0, 0.0))
+            })
+            .collect::<Vec<_>>();
+        let rotation = (0..=20)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 1e-4 } else { 0.0 };
+                AiQuatKey::new(i as f64, Quat::from_rotation_y(noise))
+            })
+            .collect::<Vec<_>>();
+        let scaling = (0..=20)
+            .map(|i| {
+                let key = AiVectorKey::new(i as f64, Vec3::splat(if i < 5 { 1.0 } else { 2.0 }));
+                key.with_interpolation(AiAnimInterpolation::Step)
+            })
+            .collect::<Vec<_>>();
+        let mut animation = AiAnimation {
+            duration: 20.0,
+            ticks_per_second: 10.0,
+            channels: vec![AiNodeAnim {
+                node_name: "hip".into(),
+                position_keys: linear,
+                rotation_keys: rotation,
+                scaling_keys: scaling,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let config = AnimCompressionConfig::default();
+        let mut compressed = animation.clone();
+        assert_eq!(
+            CompressAnimationsProcess::process_animation(&mut compressed, &config),
+            18 + 20 + 18
+        );
+        let channel = &compressed.channels[0];
+        let times = |keys: &[AiVectorKey]| keys.iter().map(|k| k.time).collect::<Vec<_>>();
+        assert_eq!(times(&channel.position_keys), [0.0, 10.0, 20.0]);
+        assert_eq!(channel.position_keys[1].value, Vec3::new(10.0, 0.0, 0.0));
+        assert_eq!(channel.rotation_keys.len(), 1);
+        assert_eq!(times(&channel.scaling_keys), [0.0, 5.0, 20.0]);
+
+        // 2 frames per second are 5 ticks per frame, the key closest to a
+        // frame is kept
+        let config = AnimCompressionConfig {
+            linear_tolerance: 0.0,
+            frame_rate: Some(2.0),
+            ..config
+        };
+        for key in animation.channels[0].position_keys.iter_mut() {
+            key.time += 0.4;
+        }
+        CompressAnimationsProcess::process_animation(&mut animation, &config);
+        let keys = &animation.channels[0].position_keys;
+        assert_eq!(times(keys), [0.0, 10.0, 20.0]);
+        assert_eq!(keys[1].value, Vec3::new(10.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    AiReal,
+    structs::{color::ColorSpace, material::AiProperty, scene::AiScene},
+};
+
+/// Prefix of the keys of all material colors
+const COLOR_KEY_PREFIX: &str = "$clr.";
+
+/// Postprocessing step to convert the vertex colors, material colors and
+/// light colors of a scene between sRGB and linear color space.
+///
+/// The source color space is read from the scene metadata, see
+/// [`ColorSpace::from_metadata`], scenes without one are assumed to be
+/// sRGB. Afterwards the metadata describes the target color space. Alpha
+/// values are never converted.
+pub struct ConvertColorSpaceProcess;
+
+impl ConvertColorSpaceProcess {
+    pub fn execute_with_target(scene: &mut AiScene, target: ColorSpace) {
+        log::debug!("ConvertColorSpaceProcess begin");
+        let source = ColorSpace::from_metadata(&scene.metadata).unwrap_or_default();
+        if source != target {
+            Self::convert(scene, source, target);
+            log::info!(
+                "ConvertColorSpaceProcess finished. Converted from {} to {}",
+                source.name(),
+                target.name()
+            );
+        } else {
+            log::debug!("ConvertColorSpaceProcess finished. Nothing to be done");
+        }
+        target.write_metadata(&mut scene.metadata);
+    }
+
+    fn convert(scene: &mut AiScene, source: ColorSpace, target: ColorSpace) {
+        let f32_channel = |c: &mut f32| *c = source.convert_channel(target, f64::from(*c)) as f32;
+        let real_channel =
+            |c: &mut AiReal| *c = source.convert_channel(target, f64::from(*c)) as AiReal;
+
+        for mesh in scene.meshes.iter_mut() {
+            let sets = mesh.colors.iter_mut().flat_map(|set| set.iter_mut());
+            let anim_sets = mesh
+                .anim_meshes
+                .iter_mut()
+                .flat_map(|anim| anim.colors.iter_mut())
+                .flat_map(|set| set.iter_mut());
+            for color in sets.chain(anim_sets) {
+                f32_channel(&mut color.x);
+                f32_channel(&mut color.y);
+                f32_channel(&mut color.z);
+            }
+        }
+
+        for material in scene.materials.iter_mut() {
+            for property in material.properties.iter_mut() {
+                if !property.key.starts_with(COLOR_KEY_PREFIX) {
+                    continue;
+                }
+                match &mut property.property {
+                    AiProperty::Vec3(color) => {
+                        real_channel(&mut color.x);
+                        real_channel(&mut color.y);
+                        real_channel(&mut color.z);
+                    }
+                    AiProperty::Vec4(color) => {
+                        real_channel(&mut color.x);
+                        real_channel(&mut color.y);
+                        real_channel(&mut color.z);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for light in scene.lights.iter_mut() {
+            for color in [
+                &mut light.color_diffuse,
+                &mut light.color_specular,
+                &mut light.color_ambient,
+            ] {
+                f32_channel(&mut color.x);
+                f32_channel(&mut color.y);
+                f32_channel(&mut color.z);
+            }
+        }
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::ConvertColorSpaceProcess;
+    use crate::{
+        formats::x::importer::Importer,
+        postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+        structs::{
+            color::{ColorSpace, linear_to_srgb, srgb_to_linear},
+            diff::Tolerance,
+            material::{AI_MATKEY_COLOR_DIFFUSE, GetProperty},
+            meta::{AI_METADATA_COLOR_SPACE, MetadataExt},
+            scene::AiScene,
+        },
+        traits::importer::trait_define::Importer as _,
+        utils::{float_precision::Vec4, get_model_path},
+    };
+
+    #[test]
+    fn test_convert_color_space() {
+        for value in [0.0, 0.02, 0.2, 0.5, 1.0, 1.5, -0.3] {
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-12);
+        }
+        assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-6);
+
+        let mut scene = *Importer::read_from_buf(
+            &fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap(),
+        )
+        .unwrap();
+        let original = scene.clone();
+        let diffuse = |scene: &AiScene| -> Vec4 {
+            *scene.materials[0]
+                .get_property(AI_MATKEY_COLOR_DIFFUSE, 0)
+                .unwrap()
+        };
+        assert_eq!(ColorSpace::from_metadata(&scene.metadata), None);
+
+        PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .convert_color_space(ColorSpace::Linear)
+            .run(&mut scene);
+        assert_eq!(
+            scene.metadata.get_str(AI_METADATA_COLOR_SPACE),
+            Some("Linear")
+        );
+        let (before, after) = (diffuse(&original), diffuse(&scene));
+        assert!((f64::from(after.x) - srgb_to_linear(f64::from(before.x))).abs() < 1e-6);
+        assert_eq!(after.w, before.w);
+        let mesh = scene
+            .meshes
+            .iter()
+            .position(|m| m.has_vertex_colors(0))
+            .unwrap();
+        let (before, after) = (
+            original.meshes[mesh].colors[0][0],
+            scene.meshes[mesh].colors[0][0],
+        );
+        assert!((f64::from(after.y) - srgb_to_linear(f64::from(before.y))).abs() < 1e-6);
+        assert_eq!(after.w, before.w);
+
+        // converting twice to the same space changes nothing
+        let linear = diffuse(&scene);
+        ConvertColorSpaceProcess::execute_with_target(&mut scene, ColorSpace::Linear);
+        assert_eq!(diffuse(&scene), linear);
+        ConvertColorSpaceProcess::execute_with_target(&mut scene, ColorSpace::Srgb);
+        assert_eq!(
+            ColorSpace::from_metadata(&scene.metadata),
+            Some(ColorSpace::Srgb)
+        );
+        assert_eq!(original.diff(&scene, Tolerance::default()), []);
+    }
+}
@@ -0,0 +1,213 @@
+use super::convert_to_left_hand_process::flip_winding_order_process::FlipWindingOrderProcess;
+use crate::{
+    structs::{
+        coordinate_system::CoordinateSystem,
+        material::{AI_MATKEY_TEXMAP_AXIS, AiProperty},
+        meta::{AI_METADATA_ORIGINAL_UNIT_SCALE_FACTOR, MetadataExt},
+        scene::AiScene,
+    },
+    utils::float_precision::{Mat3, Mat4, Quat, Vec3},
+};
+
+/// Postprocessing step to convert a scene to another axis convention and
+/// unit, e.g. from a Z-up file in meters to the Y-up right-handed
+/// convention of assimp.
+///
+/// The source convention is read from the scene metadata, see
+/// [`CoordinateSystem::from_metadata`], scenes without one are assumed to
+/// follow the convention of assimp. Afterwards the metadata describes the
+/// target convention. Unlike `MakeLeftHanded`, all data is converted,
+/// including node transformations, bones, animations, cameras and lights,
+/// and the winding order of the faces is kept if the handedness changes.
+pub struct ConvertCoordinateSystemProcess;
+
+impl ConvertCoordinateSystemProcess {
+    pub fn execute_with_target(scene: &mut AiScene, target: &CoordinateSystem) {
+        log::debug!("ConvertCoordinateSystemProcess begin");
+        if !target.is_valid() {
+            log::error!("Invalid target coordinate system {target:?}");
+            return;
+        }
+        let source = CoordinateSystem::from_metadata(&scene.metadata).unwrap_or_default();
+        if source == *target {
+            log::debug!("ConvertCoordinateSystemProcess finished. Nothing to be done");
+            return;
+        }
+        let conversion = source.conversion_to(target);
+        Self::convert(
+            scene,
+            conversion,
+            source.handedness() != target.handedness(),
+        );
+
+        if scene
+            .metadata
+            .get_float(AI_METADATA_ORIGINAL_UNIT_SCALE_FACTOR)
+            .is_none()
+        {
+            scene.metadata.set(
+                AI_METADATA_ORIGINAL_UNIT_SCALE_FACTOR,
+                source.unit_scale_factor,
+            );
+        }
+        target.write_metadata(&mut scene.metadata);
+        log::info!("ConvertCoordinateSystemProcess finished. Converted from {source:?}");
+    }
+
+    /// Applies `conversion`, a scaled rotation or reflection, to all
+    /// positions and directions of the scene. Transformations `T` become
+    /// `C * T * C⁻¹`, so they map converted coordinates to converted
+    /// coordinates.
+    fn convert(scene: &mut AiScene, conversion: Mat4, flip_winding: bool) {
+        let inverse = conversion.inverse();
+        let rotation =
+            Mat3::from_mat4(conversion).mul_scalar(1.0 / conversion.x_axis.truncate().length());
+        let point = |p: &mut Vec3| *p = conversion.transform_point3(*p);
+        let direction = |d: &mut Vec3| *d = rotation * *d;
+        let transform = |m: &mut Mat4| *m = conversion * *m * inverse;
+
+        for node in scene.nodes.iter_mut() {
+            transform(&mut node.transformation);
+        }
+
+        for mesh in scene.meshes.iter_mut() {
+            mesh.vertices.iter_mut().for_each(point);
+            mesh.normals.iter_mut().for_each(direction);
+            mesh.tangents.iter_mut().for_each(direction);
+            mesh.bitangents.iter_mut().for_each(direction);
+            mesh.aabb = mesh.aabb.transformed(conversion);
+            for anim_mesh in mesh.anim_meshes.iter_mut() {
+                anim_mesh.vertices.iter_mut().for_each(point);
+                anim_mesh.normals.iter_mut().for_each(direction);
+                anim_mesh.tangents.iter_mut().for_each(direction);
+                anim_mesh.bitangents.iter_mut().for_each(direction);
+            }
+            for bone in mesh.bones.iter_mut() {
+                transform(&mut bone.offset_matrix);
+            }
+            if flip_winding {
+                FlipWindingOrderProcess::process_mesh(mesh);
+            }
+        }
+
+        for skeleton in scene.skeletons.iter_mut() {
+            for bone in skeleton.bones.iter_mut() {
+                transform(&mut bone.offset_matrix);
+                transform(&mut bone.local_matrix);
+            }
+        }
+
+        for material in scene.materials.iter_mut() {
+            for property in material.properties.iter_mut() {
+                if property.key == AI_MATKEY_TEXMAP_AXIS
+                    && let AiProperty::Vec3(axis) = &mut property.property
+                {
+                    direction(axis);
+                }
+            }
+        }
+
+        // a signed permutation maps scaling along the old axes to scaling
+        // along the new ones
+        let abs_rotation = Mat3::from_cols(
+            rotation.x_axis.abs(),
+            rotation.y_axis.abs(),
+            rotation.z_axis.abs(),
+        );
+        for channel in scene
+            .animations
+            .iter_mut()
+            .flat_map(|a| a.channels.iter_mut())
+        {
+            for key in channel.position_keys.iter_mut() {
+                point(&mut key.value);
+            }
+            for key in channel.rotation_keys.iter_mut() {
+                let matrix = rotation * Mat3::from_quat(key.value) * rotation.transpose();
+                key.value = Quat::from_mat3(&matrix).normalize();
+            }
+            for key in channel.scaling_keys.iter_mut() {
+                key.value = abs_rotation * key.value;
+            }
+        }
+
+        for camera in scene.cameras.iter_mut() {
+            point(&mut camera.position);
+            direction(&mut camera.up);
+            direction(&mut camera.look_at);
+        }
+        for light in scene.lights.iter_mut() {
+            point(&mut light.position);
+            direction(&mut light.direction);
+            direction(&mut light.up);
+        }
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use crate::{
+        formats::x::importer::Importer,
+        postprocess::{
+            AiPostProcessSteps, pipeline::PostProcessPipeline, validate::ValidateDSProcess,
+        },
+        structs::{
+            coordinate_system::{CoordinateSystem, Handedness},
+            meta::{AI_METADATA_ORIGINAL_UNIT_SCALE_FACTOR, AI_METADATA_UP_AXIS, MetadataExt},
+        },
+        traits::importer::trait_define::Importer as _,
+        utils::{float_precision::Vec3, get_model_path},
+    };
+
+    #[test]
+    fn test_convert_coordinate_system() {
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let original = *Importer::read_from_buf(&source).unwrap();
+        assert_eq!(
+            CoordinateSystem::from_metadata(&original.metadata),
+            Some(CoordinateSystem::Y_UP_LEFT_HANDED)
+        );
+
+        let target =
+            CoordinateSystem::Z_UP_RIGHT_HANDED.with_unit_scale_factor(CoordinateSystem::METERS);
+        assert_eq!(target.handedness(), Handedness::Right);
+        let mut scene = *Importer::read_from_buf(&source).unwrap();
+        PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .convert_coordinate_system(target)
+            .run(&mut scene);
+        let report = ValidateDSProcess::validate(&scene);
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert_eq!(
+            CoordinateSystem::from_metadata(&scene.metadata),
+            Some(target)
+        );
+        assert_eq!(scene.metadata.get_int32(AI_METADATA_UP_AXIS), Some(2));
+        assert_eq!(
+            scene
+                .metadata
+                .get_float(AI_METADATA_ORIGINAL_UNIT_SCALE_FACTOR),
+            Some(1.0)
+        );
+
+        // the front axes are -z and -y, so y and z are swapped
+        for (mesh, converted) in original.meshes.iter().zip(&scene.meshes) {
+            for (v, c) in mesh.vertices.iter().zip(&converted.vertices) {
+                assert!(c.abs_diff_eq(0.01 * Vec3::new(v.x, v.z, v.y), 1e-6));
+            }
+            for (f, c) in mesh.faces.iter().zip(&converted.faces) {
+                assert!(f.indices.iter().eq(c.indices.iter().rev()));
+            }
+        }
+
+        // MakeLeftHanded mirrors the recorded system as well
+        let mut scene = *Importer::read_from_buf(&source).unwrap();
+        PostProcessPipeline::new(AiPostProcessSteps::MakeLeftHanded).run(&mut scene);
+        assert_eq!(
+            CoordinateSystem::from_metadata(&scene.metadata),
+            Some(CoordinateSystem::Y_UP_RIGHT_HANDED)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::structs::{
+    material::{AiNormalMapConvention, AiTextureType},
+    meta::{AI_METADATA_SOURCE_FORMAT, MetadataExt},
+    scene::AiScene,
+};
+
+/// Postprocessing step to convert the normal maps of a scene to a green
+/// channel convention.
+///
+/// The convention of every normal map is read from its
+/// [`AI_MATKEY_NORMAL_MAP_CONVENTION`](crate::structs::material::AI_MATKEY_NORMAL_MAP_CONVENTION)
+/// property, normal maps without one get the convention of the source
+/// format, see [`AiNormalMapConvention::from_source_format`], or OpenGL.
+/// The green channel of uncompressed embedded textures is flipped and the
+/// property is set to the target. Compressed and external textures can't
+/// be converted, their property records their current convention so the
+/// application can flip them when they are decoded.
+pub struct ConvertNormalMapsProcess;
+
+impl ConvertNormalMapsProcess {
+    pub fn execute_with_target(scene: &mut AiScene, target: AiNormalMapConvention) {
+        log::debug!("ConvertNormalMapsProcess begin");
+        let default = scene
+            .metadata
+            .get_str(AI_METADATA_SOURCE_FORMAT)
+            .and_then(AiNormalMapConvention::from_source_format)
+            .unwrap_or_default();
+
+        // the convention every embedded texture was converted from
+        let mut flipped = HashMap::<usize, AiNormalMapConvention>::new();
+        let (mut converted, mut skipped) = (0, 0);
+        for material in scene.materials.iter_mut() {
+            for index in 0..material.texture_count(AiTextureType::Normals) as u32 {
+                let source = material.normal_map_convention(index).unwrap_or(default);
+                let Some(slot) = material.get_texture(AiTextureType::Normals, index) else {
+                    continue;
+                };
+                let embedded = slot
+                    .path
+                    .strip_prefix('*')
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|&n| scene.textures.get(n).is_some_and(|t| t.height != 0));
+                let convention = match embedded {
+                    _ if source == target => target,
+                    Some(n) => {
+                        match flipped.get(&n) {
+                            Some(&other) if other != source => log::warn!(
+                                "ConvertNormalMapsProcess: texture {} is used with different conventions",
+                                slot.path
+                            ),
+                            Some(_) => {}
+                            None => {
+                                for texel in scene.textures[n].data.iter_mut().flatten() {
+                                    texel.g = 255 - texel.g;
+                                }
+                                flipped.insert(n, source);
+                                converted += 1;
+                            }
+                        }
+                        target
+                    }
+                    None => {
+                        log::warn!(
+                            "ConvertNormalMapsProcess: normal map {} is compressed or external and can't be converted",
+                            slot.path
+                        );
+                        skipped += 1;
+                        source
+                    }
+                };
+                material.set_normal_map_convention(index, convention);
+            }
+        }
+        log::info!(
+            "ConvertNormalMapsProcess finished. {converted} textures flipped, {skipped} normal maps left as they are"
+        );
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::ConvertNormalMapsProcess;
+    use crate::{
+        postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+        structs::{
+            material::{AiMaterial, AiNormalMapConvention, AiTextureType, TextureSlot},
+            meta::{AI_METADATA_SOURCE_FORMAT, MetadataExt},
+            scene::AiScene,
+            texture::{AiTexel, AiTexture},
+        },
+    };
+
+    #[test]
+    fn test_convert_normal_maps() {
+        let mut scene = AiScene::default();
+        scene.metadata.set(AI_METADATA_SOURCE_FORMAT, "DirectX X");
+        scene.textures.push(AiTexture {
+            width: 2,
+            height: 1,
+            data: vec![
+                vec![
+                    AiTexel::new(255, 0, 128, 255),
+                    AiTexel::new(0, 200, 128, 255),
+                ]
+                .into(),
+            ]
+            .into(),
+            ..Default::default()
+        });
+        let mut material = AiMaterial::default();
+        material.set_texture(AiTextureType::Normals, 0, TextureSlot::new("*0"));
+        material.set_texture(AiTextureType::Normals, 1, TextureSlot::new("normal.png"));
+        // both materials share the embedded texture, it is flipped once
+        scene.materials = vec![material.clone(), material];
+
+        ConvertNormalMapsProcess::execute_with_target(&mut scene, AiNormalMapConvention::OpenGl);
+        let green = |scene: &AiScene| {
+            scene.textures[0].data[0]
+                .iter()
+                .map(|t| t.g)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(green(&scene), [255, 55]);
+        for material in &scene.materials {
+            assert_eq!(
+                material.normal_map_convention(0),
+                Some(AiNormalMapConvention::OpenGl)
+            );
+            // external textures keep their convention
+            assert_eq!(
+                material.normal_map_convention(1),
+                Some(AiNormalMapConvention::DirectX)
+            );
+        }
+
+        // the recorded convention wins over the source format
+        ConvertNormalMapsProcess::execute_with_target(&mut scene, AiNormalMapConvention::OpenGl);
+        assert_eq!(green(&scene), [255, 55]);
+        PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .convert_normal_maps(AiNormalMapConvention::DirectX)
+            .run(&mut scene);
+        assert_eq!(green(&scene), [0, 200]);
+        assert_eq!(
+            scene.materials[0].normal_map_convention(0),
+            Some(AiNormalMapConvention::DirectX)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    core::simd,
     postprocess::{AiPostProcessSteps, PostProcess},
     structs::{
         material::{AI_MATKEY_UVTRANSFORM, AiMaterial, AiProperty},
@@ -14,9 +15,7 @@ pub struct FlipUVsProcess;
 impl FlipUVsProcess {
     fn flip_uvs(texture_coords: &mut Box<[Vec<Vec3>; AI_MAX_NUMBER_OF_TEXTURECOORDS]>) {
         for texture_coord in texture_coords.iter_mut() {
-            for uv in texture_coord.iter_mut() {
-                uv.y = 1.0 - uv.y;
-            }
+            simd::flip_v(texture_coord);
         }
     }
 
@@ -3,6 +3,15 @@ use crate::{
     structs::{mesh::AiMesh, scene::AiScene},
 };
 
+/// Postprocessing step to reverse the order of the vertices of all faces,
+/// switching from counter-clockwise to clockwise winding.
+///
+/// Only the faces change. The vertex streams keep their order, so bone
+/// weights and anim meshes, which refer to vertices by index or position in
+/// the stream, stay valid. Unlike C++ assimp the arrays of anim meshes are
+/// not reversed, as that would assign each morph target vertex to another
+/// base vertex. Faces with different materials are always in separate
+/// meshes, so there is no per-face data to update.
 pub struct FlipWindingOrderProcess;
 
 impl FlipWindingOrderProcess {
@@ -11,27 +20,6 @@ impl FlipWindingOrderProcess {
         for face in mesh.faces.iter_mut() {
             face.indices.reverse();
         }
-        // invert the order of all components in this mesh anim meshes
-        for anim_mesh in mesh.anim_meshes.iter_mut() {
-            if !anim_mesh.vertices.is_empty() {
-                anim_mesh.vertices.reverse();
-            }
-            if !anim_mesh.normals.is_empty() {
-                anim_mesh.normals.reverse();
-            }
-            for texture_coord in anim_mesh.texture_coords.iter_mut() {
-                texture_coord.reverse();
-            }
-            if !anim_mesh.tangents.is_empty() {
-                anim_mesh.tangents.reverse();
-            }
-            if !anim_mesh.bitangents.is_empty() {
-                anim_mesh.bitangents.reverse();
-            }
-            for color in anim_mesh.colors.iter_mut() {
-                color.reverse();
-            }
-        }
     }
 }
 
@@ -46,3 +34,70 @@ impl PostProcess for FlipWindingOrderProcess {
         flag.contains(AiPostProcessSteps::FlipWindingOrder)
     }
 }
+
+#[allow(unused)]
+mod test {
+    use super::FlipWindingOrderProcess;
+    use crate::{
+        structs::{
+            bone::AiBone,
+            face::AiFace,
+            mesh::{AiMesh, AiVertexWeight, AnimMesh},
+        },
+        utils::float_precision::Vec3,
+    };
+
+    #[test]
+    fn test_flip_winding_order() {
+        let vertices = vec![Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::ONE];
+        let mut mesh = AiMesh {
+            vertices: vertices.clone(),
+            faces: vec![
+                AiFace {
+                    indices: Box::new([0, 1, 2]),
+                },
+                AiFace {
+                    indices: Box::new([1, 3, 2]),
+                },
+            ],
+            bones: vec![AiBone {
+                name: "bone".into(),
+                weights: vec![AiVertexWeight {
+                    vertex_id: 3,
+                    weight: 1.0,
+                }],
+                ..Default::default()
+            }],
+            anim_meshes: vec![AnimMesh {
+                vertices: vertices.iter().map(|v| *v + Vec3::Z).collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let normal = |vertices: &[Vec3], face: &AiFace| {
+            let [a, b, c] = [0, 1, 2].map(|i| vertices[face.indices[i] as usize]);
+            (b - a).cross(c - a)
+        };
+        let original = mesh.clone();
+
+        FlipWindingOrderProcess::process_mesh(&mut mesh);
+        assert_eq!(mesh.vertices, original.vertices);
+        assert_eq!(
+            mesh.anim_meshes[0].vertices,
+            original.anim_meshes[0].vertices
+        );
+        assert_eq!(mesh.bones[0].weights[0].vertex_id, 3);
+        for (face, original_face) in mesh.faces.iter().zip(&original.faces) {
+            assert_eq!(
+                normal(&mesh.vertices, face),
+                -normal(&vertices, original_face)
+            );
+            // the morph target is flipped the same way as the base mesh
+            let target = &mesh.anim_meshes[0].vertices;
+            assert_eq!(normal(target, face), -normal(target, original_face));
+        }
+
+        FlipWindingOrderProcess::process_mesh(&mut mesh);
+        assert_eq!(mesh.faces, original.faces);
+    }
+}
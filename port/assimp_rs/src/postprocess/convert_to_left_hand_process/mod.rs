@@ -1,14 +1,16 @@
 use super::{AiPostProcessSteps, PostProcess};
 use crate::{
+    core::simd,
     structs::{
         anim::anim::AiNodeAnim,
         camera::AiCamera,
+        coordinate_system::{Axis, CoordinateSystem},
+        light::AiLight,
         material::{AI_MATKEY_TEXMAP_AXIS, AiMaterial, AiProperty},
         mesh::AiMesh,
         nodes::Index,
         scene::{AiNode, AiScene},
     },
-    utils::float_precision::Mat4,
 };
 
 pub mod flip_uvs_process;
@@ -17,71 +19,52 @@ pub mod flip_winding_order_process;
 pub struct ConvertToLeftHandProcess;
 
 impl ConvertToLeftHandProcess {
-    fn process_node(root: Option<Index<AiNode>>, nodes: &mut [AiNode], root_transformataion: Mat4) {
-        if let Some(root) = root {
-            let root = [root];
-            let nodes_ptr = nodes.as_mut_ptr();
-            let mut stack = vec![(&root[..], root_transformataion)];
-            while let Some((inner_nodes_index, current_parent_transformataion)) = stack.pop() {
-                for node in inner_nodes_index.iter() {
-                    let index = node.value();
-                    let node = unsafe { nodes_ptr.add(index) };
-                    {
-                        // Trick borrow checker as we won't modify the children vector.
-                        // SAFETY: indexes should be unique and valid
-                        let node = unsafe { node.as_mut().unwrap_unchecked() };
-                        // let node = node.get_mut(nodes).unwrap();
-                        // mirror all base vectors at the local Z axis
-                        node.transformation.z_axis = -node.transformation.z_axis;
-
-                        // now invert the Z axis again to keep the matrix determinant positive.
-                        // The local meshes will be inverted accordingly so that the result should look just fine again.
-                        node.transformation.x_axis.z = -node.transformation.x_axis.z;
-                        node.transformation.y_axis.z = -node.transformation.y_axis.z;
-                        node.transformation.z_axis.z = -node.transformation.z_axis.z;
-                        node.transformation.w_axis.z = -node.transformation.w_axis.z; // useless, but anyways...
-                    }
-                    let node = unsafe { node.as_ref().unwrap() };
-                    // let node = node.get(nodes).unwrap();
-                    stack.push((
-                        node.children.as_slice(),
-                        node.transformation * current_parent_transformataion,
-                    ));
-                }
+    /// Mirrors the transformations of all nodes below `root`. Every local
+    /// transformation `M` becomes `S * M * S`, with `S` mirroring the z axis,
+    /// so the products along the hierarchy become `S * G * S` as well and no
+    /// parent transformation needs to be passed down.
+    fn process_node(root: Option<Index<AiNode>>, nodes: &mut [AiNode]) {
+        let mut visited = vec![false; nodes.len()];
+        let mut stack = Vec::from_iter(root);
+        while let Some(index) = stack.pop() {
+            // skip invalid indices and nodes referenced twice
+            let Some(node) = nodes.get_mut(index.value()) else {
+                continue;
+            };
+            if core::mem::replace(&mut visited[index.value()], true) {
+                continue;
             }
+            // mirror all base vectors at the local Z axis
+            node.transformation.z_axis = -node.transformation.z_axis;
+
+            // now invert the Z axis again to keep the matrix determinant positive.
+            // The local meshes will be inverted accordingly so that the result should look just fine again.
+            node.transformation.x_axis.z = -node.transformation.x_axis.z;
+            node.transformation.y_axis.z = -node.transformation.y_axis.z;
+            node.transformation.z_axis.z = -node.transformation.z_axis.z;
+            node.transformation.w_axis.z = -node.transformation.w_axis.z; // useless, but anyways...
+
+            stack.extend(node.children.iter().rev());
         }
     }
 
     fn process_mesh(mesh: &mut AiMesh) {
         // mirror positions, normals and stuff along the Z axis
-        for v in mesh.vertices.iter_mut() {
-            v.z = -v.z;
-        }
-        for v in mesh.normals.iter_mut() {
-            v.z = -v.z;
-        }
-        for v in mesh.tangents.iter_mut() {
-            v.z = -v.z;
-        }
+        simd::flip_z(&mut mesh.vertices);
+        simd::flip_z(&mut mesh.normals);
+        simd::flip_z(&mut mesh.tangents);
         // mirror bitangents as well as they're derived from the texture coords
-        for v in mesh.bitangents.iter_mut() {
-            v.z = -v.z;
-        }
+        simd::flip_z(&mut mesh.bitangents);
+        // keep the bounding box in sync, mirroring swaps its z bounds
+        let aabb = &mut mesh.aabb;
+        (aabb.min.z, aabb.max.z) = (-aabb.max.z, -aabb.min.z);
 
         // mirror anim meshes positions, normals and stuff along the Z axis
         for anim_mesh in mesh.anim_meshes.iter_mut() {
-            for v in anim_mesh.vertices.iter_mut() {
-                v.z = -v.z;
-            }
-            for v in anim_mesh.normals.iter_mut() {
-                v.z = -v.z;
-            }
-            for v in anim_mesh.tangents.iter_mut() {
-                v.z = -v.z;
-            }
-            for v in anim_mesh.bitangents.iter_mut() {
-                v.z = -v.z;
-            }
+            simd::flip_z(&mut anim_mesh.vertices);
+            simd::flip_z(&mut anim_mesh.normals);
+            simd::flip_z(&mut anim_mesh.tangents);
+            simd::flip_z(&mut anim_mesh.bitangents);
         }
 
         // mirror offset matrices of all bones
@@ -120,14 +103,28 @@ impl ConvertToLeftHandProcess {
         }
     }
 
+    /// Mirrors the camera in the space of its node, which is mirrored by
+    /// [`Self::process_node`]. C++ assimp reflects the look-at vector at the
+    /// position instead, which only matches the mirrored node for cameras
+    /// looking along the z axis.
     fn process_camera(camera: &mut AiCamera) {
-        camera.look_at = (camera.position * 2.0) - camera.look_at;
+        camera.position.z = -camera.position.z;
+        camera.look_at.z = -camera.look_at.z;
+        camera.up.z = -camera.up.z;
+    }
+
+    /// Mirrors the light in the space of its node like cameras, C++ assimp
+    /// leaves lights untouched.
+    fn process_light(light: &mut AiLight) {
+        light.position.z = -light.position.z;
+        light.direction.z = -light.direction.z;
+        light.up.z = -light.up.z;
     }
 }
 
 impl PostProcess for ConvertToLeftHandProcess {
     fn execute(scene: &mut AiScene) {
-        Self::process_node(scene.root, &mut scene.nodes, Mat4::IDENTITY);
+        Self::process_node(scene.root, &mut scene.nodes);
         for mesh in scene.meshes.iter_mut() {
             Self::process_mesh(mesh);
         }
@@ -142,8 +139,233 @@ impl PostProcess for ConvertToLeftHandProcess {
         for camera in scene.cameras.iter_mut() {
             Self::process_camera(camera);
         }
+        for light in scene.lights.iter_mut() {
+            Self::process_light(light);
+        }
+        if let Some(system) = CoordinateSystem::from_metadata(&scene.metadata) {
+            system.mirrored(Axis::Z).write_metadata(&mut scene.metadata);
+        }
     }
     fn is_active(flag: AiPostProcessSteps) -> bool {
         flag.contains(AiPostProcessSteps::MakeLeftHanded)
     }
 }
+
+#[allow(unused)]
+mod test {
+    #[cfg(test)]
+    use crate::utils::golden::{self, DumpScene};
+    use crate::{
+        postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+        structs::{
+            anim::skinning::SkinningPalette,
+            camera::AiCamera,
+            color::Color3D,
+            diff::Tolerance,
+            face::AiFace,
+            light::{AiLight, LightType},
+            material::AiMaterial,
+            mesh::AiMesh,
+            nodes::Index,
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Quat, Vec3},
+    };
+
+    #[test]
+    fn test_make_left_handed() {
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node(
+            "root",
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+            None,
+        );
+        let arm = builder.add_node("arm", Mat4::from_rotation_y(0.5), Some(root));
+        let hand = builder.add_node(
+            "hand",
+            Mat4::from_rotation_translation(Quat::from_rotation_x(0.25), Vec3::new(0.0, 0.0, -4.0)),
+            Some(arm),
+        );
+        builder.add_node("leg", Mat4::from_scale(Vec3::splat(2.0)), Some(root));
+        let mesh = builder.add_mesh(AiMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::new(0.0, 1.0, 1.0)],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2]),
+            }],
+            ..Default::default()
+        });
+        builder.attach_mesh(hand, mesh);
+        builder.add_camera(AiCamera {
+            position: Vec3::new(0.0, 0.0, 1.0),
+            look_at: Vec3::new(0.0, 0.0, -1.0),
+            ..Default::default()
+        });
+        let original = builder.build().unwrap();
+
+        let mut scene = original.clone();
+        PostProcessPipeline::new(AiPostProcessSteps::MakeLeftHanded).run(&mut scene);
+
+        // the matrices of C++ assimp for the same hierarchy
+        let root = &scene.nodes[0];
+        assert!(
+            root.transformation
+                .abs_diff_eq(Mat4::from_translation(Vec3::new(1.0, 2.0, -3.0)), 1e-6)
+        );
+        let arm = &scene.nodes[1];
+        assert!(
+            arm.transformation
+                .abs_diff_eq(Mat4::from_rotation_y(-0.5), 1e-6)
+        );
+        let hand = &scene.nodes[2];
+        let expected =
+            Mat4::from_rotation_translation(Quat::from_rotation_x(-0.25), Vec3::new(0.0, 0.0, 4.0));
+        assert!(hand.transformation.abs_diff_eq(expected, 1e-6));
+        assert_eq!(
+            scene.nodes[3].transformation,
+            Mat4::from_scale(Vec3::splat(2.0))
+        );
+
+        // the mirrored vertices end up at the mirrored world positions
+        let mirror = Mat4::from_scale(Vec3::new(1.0, 1.0, -1.0));
+        let before = SkinningPalette::new(&original);
+        let after = SkinningPalette::new(&scene);
+        for node in 0..scene.nodes.len() as u32 {
+            let node = Index::new(node);
+            let expected = mirror * before.global_transform(node).unwrap() * mirror;
+            assert!(
+                after
+                    .global_transform(node)
+                    .unwrap()
+                    .abs_diff_eq(expected, 1e-5)
+            );
+        }
+        let global = after.global_transform(Index::new(2)).unwrap();
+        let original_global = before.global_transform(Index::new(2)).unwrap();
+        for (v, o) in scene.meshes[0]
+            .vertices
+            .iter()
+            .zip(&original.meshes[0].vertices)
+        {
+            let expected = mirror.transform_point3(original_global.transform_point3(*o));
+            assert!(global.transform_point3(*v).abs_diff_eq(expected, 1e-5));
+        }
+        assert_eq!(scene.cameras[0].position, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(scene.cameras[0].look_at, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_left_handed_cameras_and_lights() {
+        let mut builder = SceneBuilder::new();
+        let root = builder.add_node(
+            "root",
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+            None,
+        );
+        builder.add_node("camera", Mat4::from_rotation_y(0.5), Some(root));
+        builder.add_node("light", Mat4::IDENTITY, Some(root));
+        builder.add_camera(AiCamera {
+            name: "camera".into(),
+            position: Vec3::new(1.0, 2.0, 3.0),
+            look_at: Vec3::new(1.0, 0.0, -1.0),
+            up: Vec3::new(0.0, 1.0, 0.5),
+            horizontal_fov: 0.8,
+            clip_plane_near: 0.1,
+            clip_plane_far: 1000.0,
+            aspect: 1.5,
+            ..Default::default()
+        });
+        builder.add_light(AiLight {
+            name: "light".into(),
+            light_type: LightType::Spot,
+            position: Vec3::new(0.0, 1.0, 2.0),
+            direction: Vec3::new(0.5, -1.0, -1.0),
+            up: Vec3::new(0.0, 0.0, 1.0),
+            attenuation_constant: 1.0,
+            color_diffuse: Color3D::ONE,
+            angle_inner_cone: 0.5,
+            angle_outer_cone: 0.75,
+            ..Default::default()
+        });
+        let original = builder.build().unwrap();
+        let mut scene = original.clone();
+        PostProcessPipeline::new(AiPostProcessSteps::MakeLeftHanded).run(&mut scene);
+
+        // reference output in the format of `assimp export -f assjson`
+        let assjson = br#"{
+            "rootnode": {
+                "name": "root",
+                "transformation": [1, 0, 0, 1, 0, 1, 0, 2, 0, 0, 1, -3, 0, 0, 0, 1],
+                "children": [
+                    {
+                        "name": "camera",
+                        "transformation": [
+                            0.8775826, 0, -0.4794255, 0,
+                            0, 1, 0, 0,
+                            0.4794255, 0, 0.8775826, 0,
+                            0, 0, 0, 1
+                        ]
+                    },
+                    {
+                        "name": "light",
+                        "transformation": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]
+                    }
+                ]
+            },
+            "flags": 0,
+            "lights": [{
+                "name": "light",
+                "type": 3,
+                "attenuationconstant": 1,
+                "attenuationlinear": 0,
+                "attenuationquadratic": 0,
+                "diffusecolor": [1, 1, 1],
+                "specularcolor": [0, 0, 0],
+                "ambientcolor": [0, 0, 0],
+                "position": [0, 1, -2],
+                "direction": [0.5, -1, 1],
+                "up": [0, 0, -1],
+                "angleinnercone": 0.5,
+                "angleoutercone": 0.75
+            }],
+            "cameras": [{
+                "name": "camera",
+                "position": [1, 2, -3],
+                "lookat": [1, 0, 1],
+                "up": [0, 1, -0.5],
+                "horizontalfov": 0.8,
+                "clipplanenear": 0.1,
+                "clipplanefar": 1000,
+                "aspect": 1.5
+            }]
+        }"#;
+        let dump = DumpScene::from_bytes(assjson).unwrap();
+        assert_eq!(golden::compare(&scene, &dump, &Tolerance::default()), []);
+
+        // the world space vectors are mirrored at the z axis
+        let mirror = Mat4::from_scale(Vec3::new(1.0, 1.0, -1.0));
+        let before = SkinningPalette::new(&original);
+        let after = SkinningPalette::new(&scene);
+        let global =
+            |palette: &SkinningPalette, node| palette.global_transform(Index::new(node)).unwrap();
+        let (camera, expected) = (&scene.cameras[0], &original.cameras[0]);
+        for (v, e) in [(camera.look_at, expected.look_at), (camera.up, expected.up)] {
+            let world = global(&after, 1).transform_vector3(v);
+            let expected = mirror.transform_vector3(global(&before, 1).transform_vector3(e));
+            assert!(world.abs_diff_eq(expected, 1e-5));
+        }
+        let world = global(&after, 1).transform_point3(camera.position);
+        let expected =
+            mirror.transform_point3(global(&before, 1).transform_point3(expected.position));
+        assert!(world.abs_diff_eq(expected, 1e-5));
+        let (light, expected) = (&scene.lights[0], &original.lights[0]);
+        let world = global(&after, 2).transform_vector3(light.direction);
+        let expected =
+            mirror.transform_vector3(global(&before, 2).transform_vector3(expected.direction));
+        assert!(world.abs_diff_eq(expected, 1e-5));
+
+        // converting twice restores the scene
+        PostProcessPipeline::new(AiPostProcessSteps::MakeLeftHanded).run(&mut scene);
+        assert_eq!(original.diff(&scene, Tolerance::default()), []);
+    }
+}
@@ -0,0 +1,39 @@
+use super::{AiPostProcessSteps, PostProcess};
+use crate::structs::{mesh::AiMesh, scene::AiScene};
+
+/// Postprocessing step to drop the normals of all meshes.
+///
+/// Combined with [`AiPostProcessSteps::GenNormals`] or
+/// [`AiPostProcessSteps::GenSmoothNormals`] this forces the normals to be
+/// recomputed.
+pub struct DropFaceNormalsProcess;
+
+impl DropFaceNormalsProcess {
+    /// Removes the normals of a mesh, returns whether there were any.
+    pub fn drop_mesh_face_normals(mesh: &mut AiMesh) -> bool {
+        if mesh.normals.is_empty() {
+            return false;
+        }
+        mesh.normals = Vec::new();
+        true
+    }
+}
+
+impl PostProcess for DropFaceNormalsProcess {
+    fn execute(scene: &mut AiScene) {
+        log::debug!("DropFaceNormalsProcess begin");
+        let mut has = false;
+        for mesh in scene.meshes.iter_mut() {
+            has |= Self::drop_mesh_face_normals(mesh);
+        }
+        if has {
+            log::info!("DropFaceNormalsProcess finished. Face normals have been removed");
+        } else {
+            log::debug!("DropFaceNormalsProcess finished. No normals were present");
+        }
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::DropNormals)
+    }
+}
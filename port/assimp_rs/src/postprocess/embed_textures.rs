@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    postprocess::{AiPostProcessSteps, PostProcess},
+    structs::{material::AiMaterial, scene::AiScene, texture::AiTexture},
+    traits::io_system::IoSystem,
+};
+
+/// Configuration of the texture path resolution.
+///
+/// The default configuration only normalizes the paths, it neither touches
+/// the file system nor rebases the paths.
+#[derive(Default, Clone, Copy)]
+pub struct TexturePathConfig<'a> {
+    /// Folder containing the imported model file.
+    pub root_path: Option<&'a Path>,
+
+    /// Rewrite paths pointing below `root_path` so they are relative to it.
+    pub make_relative: bool,
+
+    /// When set, every texture is looked up through this io system. If it
+    /// cannot be found at its path, a file with the same name in `root_path`
+    /// is used instead.
+    pub io_system: Option<&'a dyn IoSystem>,
+
+    /// Read the textures which could be found through the io system into
+    /// [`AiScene::textures`] and reference them as `*0`, `*1`, ...
+    pub embed: bool,
+}
+
+/// Postprocessing step to normalize and resolve the texture paths of all
+/// materials.
+///
+/// Many exporters (e.g. the 3DSMax X exporters) write absolute Windows paths
+/// like `C:\textures\wood.png`, which are useless on other machines. This step
+/// converts backslashes to forward slashes, strips drive letters and,
+/// depending on the [`TexturePathConfig`], rebases the paths onto the model
+/// folder. Embedded texture references (`*0`, `*1`, ...) are left untouched.
+/// With [`TexturePathConfig::embed`] the files are embedded into the scene
+/// afterwards, like assimp does.
+pub struct EmbedTexturesProcess;
+
+impl EmbedTexturesProcess {
+    /// Converts all separators to `/`, collapses duplicated separators and
+    /// strips a leading drive letter, so the result is a relative path.
+    pub fn normalize_path(path: &str) -> String {
+        if path.starts_with('*') {
+            return path.to_owned();
+        }
+        let mut normalized = String::with_capacity(path.len());
+        for c in path.trim().chars() {
+            let c = if c == '\\' { '/' } else { c };
+            if c == '/' && normalized.ends_with('/') {
+                continue;
+            }
+            normalized.push(c);
+        }
+        let rest = Self::strip_drive_letter(&normalized);
+        rest.trim_start_matches('/').to_owned()
+    }
+
+    fn strip_drive_letter(path: &str) -> &str {
+        match path.as_bytes() {
+            [drive, b':', ..] if drive.is_ascii_alphabetic() => &path[2..],
+            _ => path,
+        }
+    }
+
+    /// Makes `path` relative to `root` if it points below it.
+    fn make_relative<'p>(path: &'p str, root: &str) -> Option<&'p str> {
+        let root = root.trim_end_matches('/');
+        if root.is_empty() || path.len() <= root.len() {
+            return None;
+        }
+        let (prefix, rest) = path.split_at_checked(root.len())?;
+        if prefix.eq_ignore_ascii_case(root) && rest.starts_with('/') {
+            Some(rest.trim_start_matches('/'))
+        } else {
+            None
+        }
+    }
+
+    /// Resolves a single texture path.
+    ///
+    /// Returns the new path and whether the texture could be found. Without
+    /// an io system, every texture counts as found.
+    pub fn resolve_path(path: &str, config: &TexturePathConfig) -> (String, bool) {
+        if path.starts_with('*') {
+            return (path.to_owned(), true);
+        }
+        let unified = path.replace('\\', "/");
+        let root = config
+            .root_path
+            .map(|p| p.to_string_lossy().replace('\\', "/"));
+
+        let mut normalized = match (&root, config.make_relative) {
+            (Some(root), true) => match Self::make_relative(&unified, root) {
+                Some(relative) => Self::normalize_path(relative),
+                None => Self::normalize_path(&unified),
+            },
+            _ => Self::normalize_path(&unified),
+        };
+
+        let Some(io_system) = config.io_system else {
+            return (normalized, true);
+        };
+
+        let in_root = |p: &str| match config.root_path {
+            Some(root) => root.join(p),
+            None => PathBuf::from(p),
+        };
+
+        // Test the original path first, it may be valid on this machine
+        if io_system.exists(Path::new(path)) {
+            if !config.make_relative {
+                normalized = path.to_owned();
+            }
+            return (normalized, true);
+        }
+        // Test the normalized path in the root folder
+        if io_system.exists(&in_root(&normalized)) {
+            return (normalized, true);
+        }
+        // Test the file name in the root folder
+        let file_name = normalized.rsplit('/').next().unwrap_or_default();
+        if !file_name.is_empty() && io_system.exists(&in_root(file_name)) {
+            let resolved = if config.make_relative || config.root_path.is_none() {
+                file_name.to_owned()
+            } else {
+                in_root(file_name).to_string_lossy().into_owned()
+            };
+            return (resolved, true);
+        }
+        (normalized, false)
+    }
+
+    /// Resolves all texture paths of a material, returns the paths which
+    /// could not be found.
+    pub fn process_material(material: &mut AiMaterial, config: &TexturePathConfig) -> Vec<String> {
+        let mut missing = Vec::new();
+        for p in material.properties.iter_mut() {
+            let Some(path) = p.texture_path_mut() else {
+                continue;
+            };
+            let (resolved, found) = Self::resolve_path(path, config);
+            if !found {
+                missing.push(resolved.clone());
+            }
+            *path = resolved;
+        }
+        missing
+    }
+
+    /// Runs the step with the given configuration, returns the texture paths
+    /// which could not be found or read.
+    pub fn execute_with_config(scene: &mut AiScene, config: &TexturePathConfig) -> Vec<String> {
+        let mut missing = Vec::new();
+        for material in scene.materials.iter_mut() {
+            missing.extend(Self::process_material(material, config));
+        }
+        if let Some(io_system) = config.io_system
+            && config.embed
+        {
+            Self::embed_textures(scene, config, io_system, &mut missing);
+        }
+        missing
+    }
+
+    /// Replaces the resolved texture paths by references to embedded
+    /// textures, a file used by several materials is embedded once.
+    fn embed_textures(
+        scene: &mut AiScene,
+        config: &TexturePathConfig,
+        io_system: &dyn IoSystem,
+        missing: &mut Vec<String>,
+    ) {
+        let mut embedded = HashMap::<String, usize>::new();
+        for material in scene.materials.iter_mut() {
+            for p in material.properties.iter_mut() {
+                let Some(path) = p.texture_path_mut() else {
+                    continue;
+                };
+                if path.starts_with('*') || missing.contains(path) {
+                    continue;
+                }
+                let index = match embedded.get(path.as_str()) {
+                    Some(&index) => index,
+                    None => {
+                        // resolved paths are relative to the root, unless the
+                        // original path was valid as it is
+                        let file = match config.root_path {
+                            Some(root) if io_system.exists(&root.join(&*path)) => root.join(&*path),
+                            _ => PathBuf::from(&*path),
+                        };
+                        let Ok(bytes) = io_system.read(&file) else {
+                            missing.push(path.clone());
+                            continue;
+                        };
+                        let mut texture =
+                            AiTexture::from_compressed(&bytes, &Self::format_hint(path));
+                        texture.filename = path.as_str().into();
+                        scene.textures.push(texture);
+                        embedded.insert(path.clone(), scene.textures.len() - 1);
+                        scene.textures.len() - 1
+                    }
+                };
+                *path = format!("*{index}");
+            }
+        }
+    }
+
+    /// The lowercase file extension, the shortest one for JPEG files
+    fn format_hint(path: &str) -> String {
+        let file_name = path.rsplit('/').next().unwrap_or_default();
+        match file_name.rsplit_once('.') {
+            Some((_, extension)) => match extension.to_ascii_lowercase().as_str() {
+                "jpeg" => "jpg".to_owned(),
+                extension => extension.to_owned(),
+            },
+            None => String::new(),
+        }
+    }
+}
+
+impl PostProcess for EmbedTexturesProcess {
+    fn execute(scene: &mut AiScene) {
+        Self::execute_with_config(scene, &TexturePathConfig::default());
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::EmbedTextures)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use std::fs;
+
+    use super::{EmbedTexturesProcess, TexturePathConfig};
+    use crate::{
+        get_model_path,
+        structs::{
+            material::{AiMaterial, AiTextureType, TextureSlot},
+            scene::AiScene,
+            texture::AiTexture,
+        },
+        traits::io_system::DefaultIoSystem,
+    };
+
+    fn material(textures: &[(AiTextureType, &str)]) -> AiMaterial {
+        let mut material = AiMaterial::default();
+        for &(ty, path) in textures {
+            material.set_texture(ty, 0, TextureSlot::new(path));
+        }
+        material
+    }
+
+    #[test]
+    fn test_embed_textures() {
+        let root = get_model_path("X", "");
+        let mut scene = AiScene {
+            materials: vec![
+                material(&[
+                    (AiTextureType::Diffuse, ".\\test.png"),
+                    (AiTextureType::Normals, "C:\\assets\\bottom.tga"),
+                    (AiTextureType::Specular, "*0"),
+                ]),
+                material(&[
+                    (AiTextureType::Diffuse, "bottom.tga"),
+                    (AiTextureType::Normals, "missing.png"),
+                ]),
+            ],
+            textures: vec![AiTexture::from_compressed(b"already embedded", "png")],
+            ..Default::default()
+        };
+        let config = TexturePathConfig {
+            root_path: Some(&root),
+            make_relative: true,
+            io_system: Some(&DefaultIoSystem),
+            embed: true,
+        };
+        let missing = EmbedTexturesProcess::execute_with_config(&mut scene, &config);
+        assert_eq!(missing, ["missing.png"]);
+
+        let path = |material: usize, ty| {
+            scene.materials[material]
+                .get_texture(ty, 0)
+                .map(|slot| slot.path)
+                .unwrap()
+        };
+        assert_eq!(path(0, AiTextureType::Diffuse), "*1");
+        assert_eq!(path(0, AiTextureType::Normals), "*2");
+        assert_eq!(path(0, AiTextureType::Specular), "*0");
+        // the second reference to the same file shares the texture
+        assert_eq!(path(1, AiTextureType::Diffuse), "*2");
+        assert_eq!(path(1, AiTextureType::Normals), "missing.png");
+
+        assert_eq!(scene.textures.len(), 3);
+        for (texture, (file, hint)) in scene.textures[1..]
+            .iter()
+            .zip([("test.png", "png"), ("bottom.tga", "tga")])
+        {
+            assert!(texture.is_compressed());
+            assert_eq!(texture.format_hint(), hint);
+            assert!(texture.filename.ends_with(file));
+            let bytes = fs::read(get_model_path("X", file)).unwrap();
+            assert_eq!(texture.compressed_data().unwrap(), bytes);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Range,
+};
+
+use super::{AiPostProcessSteps, PostProcess};
+use crate::structs::{
+    mesh::AiMesh,
+    nodes::Index,
+    scene::{AiScene, AiSceneFlags},
+};
+
+/// Postprocessing step to find meshes which are copies of each other and
+/// let all nodes reference a single one of them.
+///
+/// Meshes are compared by their geometry and material, names are ignored.
+/// As the meshes of a node are a contiguous range, a node can only share
+/// the meshes of another node if all of them are identical. Meshes which
+/// are no longer referenced by any node are removed and the scene gets the
+/// [`AiSceneFlags::ALLOW_SHARED`] flag. Meshes with bones or morph targets
+/// are never shared, they depend on the nodes of one instance.
+pub struct FindInstancesProcess;
+
+impl FindInstancesProcess {
+    /// Hash of everything compared by [`Self::is_instance`]
+    pub fn geometry_hash(mesh: &AiMesh) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        mesh.material_index.hash(&mut hasher);
+        mesh.primitive_type.bits().hash(&mut hasher);
+        let streams = [
+            &mesh.vertices,
+            &mesh.normals,
+            &mesh.tangents,
+            &mesh.bitangents,
+        ];
+        for stream in streams.into_iter().chain(mesh.texture_coords.iter()) {
+            stream.len().hash(&mut hasher);
+            for v in stream {
+                v.to_array().map(|c| c.to_bits()).hash(&mut hasher);
+            }
+        }
+        for colors in mesh.colors.iter() {
+            colors.len().hash(&mut hasher);
+            for c in colors {
+                c.to_array().map(|c| c.to_bits()).hash(&mut hasher);
+            }
+        }
+        mesh.num_of_uv_components.hash(&mut hasher);
+        mesh.faces.len().hash(&mut hasher);
+        for face in &mesh.faces {
+            face.indices.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Whether `a` and `b` can be replaced by each other
+    pub fn is_instance(a: &AiMesh, b: &AiMesh) -> bool {
+        a.bones.is_empty()
+            && b.bones.is_empty()
+            && a.anim_meshes.is_empty()
+            && b.anim_meshes.is_empty()
+            && a.material_index == b.material_index
+            && a.primitive_type == b.primitive_type
+            && a.vertices == b.vertices
+            && a.normals == b.normals
+            && a.tangents == b.tangents
+            && a.bitangents == b.bitangents
+            && a.colors == b.colors
+            && a.texture_coords == b.texture_coords
+            && a.num_of_uv_components == b.num_of_uv_components
+            && a.faces == b.faces
+    }
+}
+
+impl PostProcess for FindInstancesProcess {
+    fn execute(scene: &mut AiScene) {
+        log::debug!("FindInstancesProcess begin");
+
+        // the first mesh with the same geometry as each mesh
+        let mut original: Vec<u32> = Vec::with_capacity(scene.meshes.len());
+        let mut buckets = HashMap::<u64, Vec<u32>>::new();
+        for (i, mesh) in scene.meshes.iter().enumerate() {
+            let i = i as u32;
+            if !mesh.bones.is_empty() || !mesh.anim_meshes.is_empty() {
+                original.push(i);
+                continue;
+            }
+            let bucket = buckets.entry(Self::geometry_hash(mesh)).or_default();
+            match bucket
+                .iter()
+                .find(|&&j| Self::is_instance(&scene.meshes[j as usize], mesh))
+            {
+                Some(&j) => original.push(j),
+                None => {
+                    bucket.push(i);
+                    original.push(i);
+                }
+            }
+        }
+
+        // the first range with the same meshes
+        let mut ranges = HashMap::<Vec<u32>, Range<u32>>::new();
+        let mut referenced = vec![false; scene.meshes.len()];
+        let mut used = vec![false; scene.meshes.len()];
+        let mut shared = 0;
+        for node in scene.nodes.iter_mut() {
+            if node.meshes.is_empty() || node.meshes.end as usize > scene.meshes.len() {
+                continue;
+            }
+            node.meshes
+                .clone()
+                .for_each(|m| referenced[m as usize] = true);
+            let key = node.meshes.clone().map(|m| original[m as usize]).collect();
+            let range = ranges.entry(key).or_insert_with(|| node.meshes.clone());
+            if *range != node.meshes {
+                node.meshes = range.clone();
+                shared += 1;
+            }
+            node.meshes.clone().for_each(|m| used[m as usize] = true);
+        }
+        if shared == 0 {
+            log::debug!("FindInstancesProcess finished. No instanced meshes found");
+            return;
+        }
+
+        // drop the meshes replaced by an instance
+        let mut new_index = Vec::with_capacity(scene.meshes.len());
+        let mut count = 0;
+        let meshes = core::mem::take(&mut scene.meshes);
+        for (i, mesh) in meshes.into_iter().enumerate() {
+            new_index.push(count);
+            if used[i] || !referenced[i] {
+                scene.meshes.push(mesh);
+                count += 1;
+            }
+        }
+        let removed = new_index.len() - scene.meshes.len();
+        for node in scene.nodes.iter_mut() {
+            if let Some(&start) = new_index.get(node.meshes.start as usize) {
+                node.meshes = start..start + node.meshes.len() as u32;
+            }
+        }
+        for bone in scene.skeletons.iter_mut().flat_map(|s| s.bones.iter_mut()) {
+            // meshes with bones are never replaced
+            if let Some(&new) = new_index.get(bone.mesh_id.value()) {
+                bone.mesh_id = Index::new(new);
+            }
+        }
+        scene.flags |= AiSceneFlags::ALLOW_SHARED;
+        log::info!(
+            "FindInstancesProcess finished. {shared} nodes share meshes, {removed} meshes removed"
+        );
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::FindInstances)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::FindInstancesProcess;
+    use crate::{
+        AiReal,
+        postprocess::{PostProcess, validate::ValidateDSProcess},
+        structs::{
+            diff::Tolerance,
+            face::AiFace,
+            material::AiMaterial,
+            mesh::{AiMesh, PrimitiveTypeFlags},
+            scene::AiSceneFlags,
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Vec3},
+    };
+
+    #[test]
+    fn test_find_instances() {
+        let quad = |offset: AiReal| AiMesh {
+            primitive_type: PrimitiveTypeFlags::POLYGON,
+            vertices: vec![
+                Vec3::new(offset, 0.0, 0.0),
+                Vec3::new(offset + 1.0, 0.0, 0.0),
+                Vec3::new(offset + 1.0, 1.0, 0.0),
+                Vec3::new(offset, 1.0, 0.0),
+            ],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2, 3]),
+            }],
+            ..Default::default()
+        };
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", Mat4::IDENTITY, None);
+        for (i, offset) in [0.0, 0.0, 5.0, 0.0].into_iter().enumerate() {
+            let node = builder.add_node(format!("instance{i}"), Mat4::IDENTITY, Some(root));
+            let mesh = builder.add_mesh(AiMesh {
+                name: format!("quad{i}"),
+                ..quad(offset)
+            });
+            builder.attach_mesh(node, mesh);
+        }
+        let mut scene = builder.build().unwrap();
+        FindInstancesProcess::execute(&mut scene);
+
+        assert_eq!(scene.meshes.len(), 2);
+        assert!(scene.flags.contains(AiSceneFlags::ALLOW_SHARED));
+        let ranges = scene.nodes[1..]
+            .iter()
+            .map(|n| n.meshes.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [0..1, 0..1, 1..2, 0..1]);
+        assert_eq!(scene.meshes[1].vertices[0].x, 5.0);
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+
+        // nothing changes without duplicates
+        let before = scene.clone();
+        FindInstancesProcess::execute(&mut scene);
+        assert_eq!(before.diff(&scene, Tolerance::EXACT), []);
+    }
+}
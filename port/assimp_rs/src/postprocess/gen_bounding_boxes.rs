@@ -0,0 +1,23 @@
+use super::{AiPostProcessSteps, PostProcess};
+use crate::structs::{aabb::AABB, scene::AiScene};
+
+/// Postprocessing step to compute the axis-aligned bounding box of every
+/// mesh from its vertices, stored in `AiMesh::aabb`.
+///
+/// The step runs after all steps modifying vertices, so the boxes match
+/// the final vertex positions.
+pub struct GenBoundingBoxesProcess;
+
+impl PostProcess for GenBoundingBoxesProcess {
+    fn execute(scene: &mut AiScene) {
+        log::debug!("GenBoundingBoxesProcess begin");
+        for mesh in scene.meshes.iter_mut() {
+            mesh.aabb = AABB::from_points(&mesh.vertices);
+        }
+        log::debug!("GenBoundingBoxesProcess end");
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::GenBoundingBoxes)
+    }
+}
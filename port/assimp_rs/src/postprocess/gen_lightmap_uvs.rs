@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use crate::{
+    AiReal,
+    structs::{mesh::AiMesh, mesh::AiVertexWeight, scene::AiScene},
+    utils::float_precision::Vec3,
+};
+
+/// Configuration of [`GenLightmapUVsProcess`]
+#[derive(Debug, Clone, Copy)]
+pub struct LightmapUvConfig {
+    /// Expected size of the lightmap in texels, used for the padding
+    pub resolution: u32,
+
+    /// Free texels between two charts
+    pub padding: u32,
+}
+
+impl Default for LightmapUvConfig {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            padding: 2,
+        }
+    }
+}
+
+/// Postprocessing step to generate non-overlapping texture coordinates for
+/// lightmaps in the second UV channel.
+///
+/// Faces are grouped into charts of connected faces facing the same side
+/// of a box and projected onto that side, the charts are packed into the
+/// unit square. Vertices shared by several charts are duplicated. Meshes
+/// without texture coordinates get the lightmap coordinates in the first
+/// channel as well, meshes which already have a second channel are left
+/// untouched.
+pub struct GenLightmapUVsProcess;
+
+/// Rectangle of a chart in the projection plane
+#[derive(Debug, Clone, Copy)]
+struct Chart {
+    min: [AiReal; 2],
+    size: [AiReal; 2],
+    offset: [AiReal; 2],
+}
+
+/// The axes spanning the projection plane of a box side
+const PLANES: [[usize; 2]; 3] = [[1, 2], [0, 2], [0, 1]];
+
+impl GenLightmapUVsProcess {
+    pub fn execute_with_config(scene: &mut AiScene, config: &LightmapUvConfig) {
+        log::debug!("GenLightmapUVsProcess begin");
+        let mut count = 0;
+        for mesh in scene.meshes.iter_mut() {
+            count += usize::from(Self::process_mesh(mesh, config));
+        }
+        log::info!("GenLightmapUVsProcess finished. {count} meshes got lightmap coordinates");
+    }
+
+    /// Generates the lightmap coordinates of a mesh, returns whether the
+    /// mesh was changed.
+    pub fn process_mesh(mesh: &mut AiMesh, config: &LightmapUvConfig) -> bool {
+        if mesh.has_texture_coords(1)
+            || mesh.faces.is_empty()
+            || mesh
+                .faces
+                .iter()
+                .flat_map(|f| f.indices.iter())
+                .any(|&i| i as usize >= mesh.vertices.len())
+        {
+            return false;
+        }
+
+        // the box side of every face, by the largest component of its normal
+        let sides = mesh
+            .faces
+            .iter()
+            .map(|face| {
+                // Newell's method, robust for polygons
+                let normal = face
+                    .indices
+                    .iter()
+                    .enumerate()
+                    .fold(Vec3::ZERO, |n, (k, &i)| {
+                        let a = mesh.vertices[i as usize];
+                        let b = mesh.vertices[face.indices[(k + 1) % face.indices.len()] as usize];
+                        n + (a - b).cross(a + b)
+                    });
+                let abs = normal.abs();
+                let axis = if abs.x >= abs.y && abs.x >= abs.z {
+                    0
+                } else if abs.y >= abs.z {
+                    1
+                } else {
+                    2
+                };
+                axis * 2 + usize::from(normal[axis] < 0.0)
+            })
+            .collect::<Vec<_>>();
+
+        // connected faces on the same side form a chart
+        let mut positions = HashMap::new();
+        let position_of = mesh
+            .vertices
+            .iter()
+            .map(|v| {
+                let len = positions.len();
+                *positions
+                    .entry(v.to_array().map(|c| c.to_bits()))
+                    .or_insert(len)
+            })
+            .collect::<Vec<_>>();
+        let mut parent = (0..mesh.faces.len()).collect::<Vec<_>>();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        let mut edges = HashMap::<(usize, usize), usize>::new();
+        for (f, face) in mesh.faces.iter().enumerate() {
+            for (k, &i) in face.indices.iter().enumerate() {
+                let j = face.indices[(k + 1) % face.indices.len()];
+                let (a, b) = (position_of[i as usize], position_of[j as usize]);
+                match edges.get(&(a.min(b), a.max(b))) {
+                    Some(&other) if sides[other] == sides[f] => {
+                        let (x, y) = (find(&mut parent, other), find(&mut parent, f));
+                        parent[x] = y;
+                    }
+                    Some(_) => {}
+                    None => {
+                        edges.insert((a.min(b), a.max(b)), f);
+                    }
+                }
+            }
+        }
+        let mut chart_of = vec![usize::MAX; mesh.faces.len()];
+        let mut charts = Vec::new();
+        for f in 0..mesh.faces.len() {
+            let root = find(&mut parent, f);
+            if chart_of[root] == usize::MAX {
+                chart_of[root] = charts.len();
+                charts.push(Chart {
+                    min: [AiReal::MAX; 2],
+                    size: [0.0; 2],
+                    offset: [0.0; 2],
+                });
+            }
+            chart_of[f] = chart_of[root];
+        }
+
+        // duplicate the vertices used by several charts
+        let project = |side: usize, v: Vec3| PLANES[side / 2].map(|axis| v[axis]);
+        let mut copies = HashMap::<(u32, usize), u32>::new();
+        let mut source = Vec::with_capacity(mesh.vertices.len());
+        let mut vertex_chart = Vec::with_capacity(mesh.vertices.len());
+        for (f, face) in mesh.faces.iter_mut().enumerate() {
+            let chart = chart_of[f];
+            for index in face.indices.iter_mut() {
+                *index = *copies.entry((*index, chart)).or_insert_with(|| {
+                    source.push(*index);
+                    vertex_chart.push((chart, sides[f]));
+                    source.len() as u32 - 1
+                });
+            }
+        }
+        let mut max = vec![[AiReal::MIN; 2]; charts.len()];
+        for (&old, &(chart, side)) in source.iter().zip(&vertex_chart) {
+            let p = project(side, mesh.vertices[old as usize]);
+            for k in 0..2 {
+                charts[chart].min[k] = charts[chart].min[k].min(p[k]);
+                max[chart][k] = max[chart][k].max(p[k]);
+            }
+        }
+        for (chart, max) in charts.iter_mut().zip(max) {
+            chart.size = [0, 1].map(|k| max[k] - chart.min[k]);
+        }
+
+        let extent = Self::pack(&mut charts, 0.0);
+        let padding = extent * config.padding as AiReal / config.resolution.max(1) as AiReal;
+        let extent = Self::pack(&mut charts, padding).max(AiReal::EPSILON);
+
+        let uvs = source
+            .iter()
+            .zip(&vertex_chart)
+            .map(|(&old, &(chart, side))| {
+                let p = project(side, mesh.vertices[old as usize]);
+                let chart = &charts[chart];
+                let [u, v] = [0, 1].map(|k| (p[k] - chart.min[k] + chart.offset[k]) / extent);
+                Vec3::new(u, v, 0.0)
+            })
+            .collect::<Vec<_>>();
+        Self::duplicate_vertices(mesh, &source);
+        if !mesh.has_texture_coords(0) {
+            mesh.texture_coords[0] = uvs.clone();
+            mesh.num_of_uv_components[0] = 2;
+        }
+        mesh.texture_coords[1] = uvs;
+        mesh.num_of_uv_components[1] = 2;
+        true
+    }
+
+    /// Places the charts in rows, sorted by their height. Returns the size
+    /// of the square containing all of them.
+    fn pack(charts: &mut [Chart], padding: AiReal) -> AiReal {
+        let area = charts
+            .iter()
+            .map(|c| (c.size[0] + padding) * (c.size[1] + padding))
+            .sum::<AiReal>();
+        let width = area.sqrt();
+        let mut order = (0..charts.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| charts[b].size[1].total_cmp(&charts[a].size[1]));
+        let (mut x, mut y, mut row_height, mut extent): (AiReal, AiReal, AiReal, AiReal) =
+            (0.0, 0.0, 0.0, 0.0);
+        for i in order {
+            let chart = &mut charts[i];
+            if x > 0.0 && x + chart.size[0] > width {
+                x = 0.0;
+                y += row_height + padding;
+                row_height = 0.0;
+            }
+            chart.offset = [x, y];
+            x += chart.size[0] + padding;
+            row_height = row_height.max(chart.size[1]);
+            extent = extent.max(x - padding).max(y + chart.size[1]);
+        }
+        extent
+    }
+
+    /// Rebuilds all per-vertex data, new vertex `i` is a copy of
+    /// `source[i]`
+    fn duplicate_vertices(mesh: &mut AiMesh, source: &[u32]) {
+        let count = mesh.vertices.len();
+        fn gather<T: Clone>(stream: &[T], source: &[u32], count: usize) -> Vec<T> {
+            if stream.len() != count {
+                return stream.to_vec();
+            }
+            source.iter().map(|&i| stream[i as usize].clone()).collect()
+        }
+
+        mesh.normals = gather(&mesh.normals, source, count);
+        mesh.tangents = gather(&mesh.tangents, source, count);
+        mesh.bitangents = gather(&mesh.bitangents, source, count);
+        for colors in mesh.colors.iter_mut() {
+            *colors = gather(colors, source, count);
+        }
+        for coords in mesh.texture_coords.iter_mut() {
+            *coords = gather(coords, source, count);
+        }
+        for anim in &mut mesh.anim_meshes {
+            anim.vertices = gather(&anim.vertices, source, count).into();
+            anim.normals = gather(&anim.normals, source, count).into();
+            anim.tangents = gather(&anim.tangents, source, count).into();
+            anim.bitangents = gather(&anim.bitangents, source, count).into();
+            for colors in anim.colors.iter_mut() {
+                *colors = gather(colors, source, count).into();
+            }
+            for coords in anim.texture_coords.iter_mut() {
+                *coords = gather(coords, source, count);
+            }
+        }
+        let mut copies = vec![Vec::new(); count];
+        for (new, &old) in source.iter().enumerate() {
+            copies[old as usize].push(new as u32);
+        }
+        for bone in &mut mesh.bones {
+            bone.weights = bone
+                .weights
+                .iter()
+                .flat_map(|w| {
+                    let copies = copies.get(w.vertex_id as usize).map_or(&[][..], |c| c);
+                    copies.iter().map(|&vertex_id| AiVertexWeight {
+                        vertex_id,
+                        weight: w.weight,
+                    })
+                })
+                .collect();
+        }
+        mesh.vertices = gather(&mesh.vertices, source, count);
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::{GenLightmapUVsProcess, LightmapUvConfig};
+    use crate::{
+        AiReal,
+        formats::x::importer::Importer,
+        postprocess::{
+            AiPostProcessSteps, pipeline::PostProcessPipeline, validate::ValidateDSProcess,
+        },
+        structs::{
+            aabb::AABB,
+            face::AiFace,
+            mesh::{AiMesh, PrimitiveTypeFlags},
+        },
+        traits::importer::trait_define::Importer as _,
+        utils::{float_precision::Vec3, get_model_path},
+    };
+
+    #[test]
+    fn test_gen_lightmap_uvs() {
+        // cube with shared corners
+        let vertices = (0..8)
+            .map(|i| {
+                Vec3::new(
+                    (i & 1) as AiReal,
+                    ((i >> 1) & 1) as AiReal,
+                    (i >> 2) as AiReal,
+                )
+            })
+            .collect::<Vec<_>>();
+        let quads: [[u32; 4]; 6] = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let mut cube = AiMesh {
+            primitive_type: PrimitiveTypeFlags::POLYGON,
+            vertices,
+            faces: quads
+                .iter()
+                .map(|q| AiFace {
+                    indices: Box::new(*q),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let config = LightmapUvConfig::default();
+        assert!(GenLightmapUVsProcess::process_mesh(&mut cube, &config));
+        assert_eq!(cube.vertices.len(), 24);
+        assert_eq!(cube.texture_coords[1].len(), 24);
+        assert_eq!(cube.texture_coords[0], cube.texture_coords[1]);
+        assert_eq!(cube.num_of_uv_components[1], 2);
+        let bounds = cube
+            .faces
+            .iter()
+            .map(|f| {
+                AABB::from_points(
+                    &f.indices
+                        .iter()
+                        .map(|&i| cube.texture_coords[1][i as usize])
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (i, a) in bounds.iter().enumerate() {
+            assert!(a.min.min_element() >= 0.0 && a.max.max_element() <= 1.0);
+            assert!(a.max.x - a.min.x > 0.1 && a.max.y - a.min.y > 0.1);
+            for b in &bounds[i + 1..] {
+                let overlap = a.min.x < b.max.x
+                    && b.min.x < a.max.x
+                    && a.min.y < b.max.y
+                    && b.min.y < a.max.y;
+                assert!(!overlap, "{a:?} {b:?}");
+            }
+        }
+        assert!(!GenLightmapUVsProcess::process_mesh(&mut cube, &config));
+
+        let mut scene =
+            *Importer::read_from_buf(&fs::read(get_model_path("X", "test_cube_text.x")).unwrap())
+                .unwrap();
+        PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .gen_lightmap_uvs(config)
+            .run(&mut scene);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.texture_coords[1].len(), mesh.vertices.len());
+        assert_eq!(mesh.texture_coords[0].len(), mesh.vertices.len());
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use super::{AiPostProcessSteps, PostProcess};
+use crate::{
+    AiReal,
+    core::spatial::PointBvh,
+    structs::{mesh::AiMesh, mesh::AiVertexWeight, scene::AiScene},
+    utils::float_precision::Vec3,
+};
+
+/// Default of `AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE`, in degrees
+pub const AI_DEFAULT_MAX_SMOOTHING_ANGLE: AiReal = 175.0;
+
+/// Configuration of the normal generation steps
+#[derive(Debug, Clone, Copy)]
+pub struct GenNormalsConfig {
+    /// Discard existing normals and always regenerate them
+    pub force: bool,
+
+    /// The winding order of the faces is reversed, either by
+    /// `FlipWindingOrder` or `MakeLeftHanded` (but not both)
+    pub flipped_winding_order: bool,
+
+    /// Maximum angle between two face normals to be smoothed, in degrees
+    pub max_smoothing_angle: AiReal,
+}
+
+impl Default for GenNormalsConfig {
+    fn default() -> Self {
+        Self {
+            force: false,
+            flipped_winding_order: false,
+            max_smoothing_angle: AI_DEFAULT_MAX_SMOOTHING_ANGLE,
+        }
+    }
+}
+
+impl GenNormalsConfig {
+    /// Derives the configuration from the post-processing flags.
+    pub fn from_flags(flags: AiPostProcessSteps) -> Self {
+        Self {
+            force: flags.contains(AiPostProcessSteps::ForceGenNormals),
+            flipped_winding_order: flags.contains(AiPostProcessSteps::FlipWindingOrder)
+                != flags.contains(AiPostProcessSteps::MakeLeftHanded),
+            ..Default::default()
+        }
+    }
+}
+
+/// Computes the normal of a face from its first, second and last vertex.
+/// Returns `None` for points and lines.
+fn face_normal(mesh: &AiMesh, indices: &[u32], flipped: bool) -> Option<Vec3> {
+    let [first, second, .., last] = indices else {
+        return None;
+    };
+    let v1 = mesh.vertices[*first as usize];
+    let (mut v2, mut v3) = (
+        mesh.vertices[*second as usize],
+        mesh.vertices[*last as usize],
+    );
+    if flipped {
+        core::mem::swap(&mut v2, &mut v3);
+    }
+    Some((v2 - v1).cross(v3 - v1).normalize_or_zero())
+}
+
+/// Returns whether the mesh can get normals at all, i.e. it contains
+/// polygons and all indices are valid.
+fn has_polygons(mesh: &AiMesh) -> bool {
+    let len = mesh.vertices.len();
+    let mut polygons = false;
+    for face in &mesh.faces {
+        if face.indices.iter().any(|&i| i as usize >= len) {
+            log::error!("Mesh {}: face index out of range", mesh.name);
+            return false;
+        }
+        polygons |= face.indices.len() >= 3;
+    }
+    if !polygons {
+        log::info!("Normal vectors are undefined for line and point meshes");
+    }
+    polygons
+}
+
+/// Postprocessing step to generate flat normals for all meshes.
+///
+/// Every face gets its own normal, vertices shared by several faces are
+/// duplicated including all their other components.
+pub struct GenFaceNormalsProcess;
+
+impl GenFaceNormalsProcess {
+    pub fn execute_with_config(scene: &mut AiScene, config: &GenNormalsConfig) {
+        log::debug!("GenFaceNormalsProcess begin");
+        let mut has = false;
+        for mesh in scene.meshes.iter_mut() {
+            has |= Self::gen_mesh_face_normals(mesh, config);
+        }
+        if has {
+            log::info!("GenFaceNormalsProcess finished. Face normals have been calculated");
+        } else {
+            log::debug!("GenFaceNormalsProcess finished. Normals are already there");
+        }
+    }
+
+    /// Generates the normals of a single mesh, returns whether normals
+    /// were generated.
+    pub fn gen_mesh_face_normals(mesh: &mut AiMesh, config: &GenNormalsConfig) -> bool {
+        if mesh.has_normals() && !config.force {
+            return false;
+        }
+        if !has_polygons(mesh) {
+            return false;
+        }
+        mesh.normals = vec![Vec3::NAN; mesh.vertices.len()];
+        let mut referenced = vec![false; mesh.vertices.len()];
+        let mut duplicates: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        let mut faces = core::mem::take(&mut mesh.faces);
+        for face in faces.iter_mut() {
+            let normal =
+                face_normal(mesh, &face.indices, config.flipped_winding_order).unwrap_or(Vec3::NAN);
+            for index in face.indices.iter_mut() {
+                let i = *index as usize;
+                if !referenced[i] {
+                    referenced[i] = true;
+                    mesh.normals[i] = normal;
+                } else {
+                    let new_index = Self::duplicate_vertex(mesh, i);
+                    mesh.normals[new_index as usize] = normal;
+                    duplicates.entry(*index).or_default().push(new_index);
+                    *index = new_index;
+                }
+            }
+        }
+        mesh.faces = faces;
+
+        // Duplicated vertices are influenced by the same bones
+        for bone in mesh.bones.iter_mut() {
+            let extra = bone
+                .weights
+                .iter()
+                .filter_map(|w| duplicates.get(&w.vertex_id).map(|d| (d, w.weight)))
+                .flat_map(|(d, weight)| {
+                    d.iter()
+                        .map(move |&vertex_id| AiVertexWeight { vertex_id, weight })
+                })
+                .collect::<Vec<_>>();
+            bone.weights.extend(extra);
+        }
+        true
+    }
+
+    fn duplicate_vertex(mesh: &mut AiMesh, i: usize) -> u32 {
+        fn dup<T: Copy>(v: &mut Vec<T>, i: usize) {
+            if let Some(&value) = v.get(i) {
+                v.push(value);
+            }
+        }
+        dup(&mut mesh.vertices, i);
+        dup(&mut mesh.normals, i);
+        dup(&mut mesh.tangents, i);
+        dup(&mut mesh.bitangents, i);
+        for colors in mesh.colors.iter_mut() {
+            dup(colors, i);
+        }
+        for uvs in mesh.texture_coords.iter_mut() {
+            dup(uvs, i);
+        }
+        (mesh.vertices.len() - 1) as u32
+    }
+}
+
+impl PostProcess for GenFaceNormalsProcess {
+    fn execute(scene: &mut AiScene) {
+        Self::execute_with_config(scene, &GenNormalsConfig::default());
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::GenNormals)
+    }
+}
+
+/// Postprocessing step to generate smooth normals for all meshes.
+///
+/// The face normals of all faces sharing a vertex position are averaged, as
+/// long as the angle between them does not exceed the maximum smoothing
+/// angle.
+pub struct GenVertexNormalsProcess;
+
+impl GenVertexNormalsProcess {
+    pub fn execute_with_config(scene: &mut AiScene, config: &GenNormalsConfig) {
+        log::debug!("GenVertexNormalsProcess begin");
+        let mut has = false;
+        for mesh in scene.meshes.iter_mut() {
+            has |= Self::gen_mesh_vertex_normals(mesh, config);
+        }
+        if has {
+            log::info!("GenVertexNormalsProcess finished. Vertex normals have been calculated");
+        } else {
+            log::debug!("GenVertexNormalsProcess finished. Normals are already there");
+        }
+    }
+
+    /// Generates the normals of a single mesh, returns whether normals
+    /// were generated.
+    pub fn gen_mesh_vertex_normals(mesh: &mut AiMesh, config: &GenNormalsConfig) -> bool {
+        if mesh.has_normals() && !config.force {
+            return false;
+        }
+        if !has_polygons(mesh) {
+            return false;
+        }
+
+        // Compute per-face normals but store them per-vertex
+        let mut face_normals = vec![Vec3::NAN; mesh.vertices.len()];
+        for face in &mesh.faces {
+            let normal =
+                face_normal(mesh, &face.indices, config.flipped_winding_order).unwrap_or(Vec3::NAN);
+            for &index in face.indices.iter() {
+                face_normals[index as usize] = normal;
+            }
+        }
+
+        let epsilon = Self::position_epsilon(&mesh.vertices);
+        let finder = PointBvh::new(&mesh.vertices);
+        let max_angle = config
+            .max_smoothing_angle
+            .clamp(0.0, AI_DEFAULT_MAX_SMOOTHING_ANGLE)
+            .to_radians();
+        let mut found = Vec::new();
+        let mut normals = vec![Vec3::ZERO; mesh.vertices.len()];
+
+        if max_angle >= AI_DEFAULT_MAX_SMOOTHING_ANGLE.to_radians() {
+            // There is no angle limit, so all vertices close to each other
+            // receive the same normal
+            let mut had = vec![false; mesh.vertices.len()];
+            for i in 0..mesh.vertices.len() {
+                if had[i] {
+                    continue;
+                }
+                finder.find_within(mesh.vertices[i], epsilon, &mut found);
+                let normal = found
+                    .iter()
+                    .map(|&v| face_normals[v as usize])
+                    .filter(|n| !n.x.is_nan())
+                    .sum::<Vec3>()
+                    .normalize_or_zero();
+                for &v in found.iter() {
+                    normals[v as usize] = normal;
+                    had[v as usize] = true;
+                }
+            }
+        } else {
+            let limit = max_angle.cos();
+            for (i, normal) in normals.iter_mut().enumerate() {
+                finder.find_within(mesh.vertices[i], epsilon, &mut found);
+                let own = face_normals[i];
+                // Skip the angle check on our own normal to avoid false
+                // negatives, n * n is not guaranteed to be 1.0
+                *normal = found
+                    .iter()
+                    .filter(|&&v| v as usize == i || face_normals[v as usize].dot(own) >= limit)
+                    .map(|&v| face_normals[v as usize])
+                    .filter(|n| !n.x.is_nan())
+                    .sum::<Vec3>()
+                    .normalize_or_zero();
+            }
+        }
+        mesh.normals = normals;
+        true
+    }
+
+    /// Epsilon for two positions to be considered equal, relative to the
+    /// extent of the mesh.
+    fn position_epsilon(positions: &[Vec3]) -> AiReal {
+        let (min, max) = positions.iter().fold(
+            (Vec3::splat(AiReal::MAX), Vec3::splat(AiReal::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        (max - min).length() * 1e-4
+    }
+}
+
+impl PostProcess for GenVertexNormalsProcess {
+    fn execute(scene: &mut AiScene) {
+        Self::execute_with_config(scene, &GenNormalsConfig::default());
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::GenSmoothNormals)
+    }
+}
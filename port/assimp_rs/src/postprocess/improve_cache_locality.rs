@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+
+use super::{AiPostProcessSteps, PostProcess};
+use crate::structs::{face::AiFace, mesh::AiMesh, scene::AiScene};
+
+/// Default size of the simulated post-transform vertex cache
+pub const PP_ICL_PTCACHE_SIZE: u32 = 12;
+
+/// Postprocessing step to reorder triangles for better vertex cache
+/// locality.
+///
+/// Implements the Tipsify algorithm from "Fast Triangle Reordering for Vertex
+/// Locality and Reduced Overdraw" (Sander et al.). Only meshes made of
+/// triangles are processed, the vertices themselves are not touched. The
+/// average cache miss ratio (ACMR) before and after the optimization is
+/// reported through the `log` facade.
+pub struct ImproveCacheLocalityProcess;
+
+/// Vertex to triangle adjacency, stored as one offset table into a flat list
+struct VertexTriangleAdjacency {
+    offsets: Vec<u32>,
+    triangles: Vec<u32>,
+}
+
+impl VertexTriangleAdjacency {
+    fn new(faces: &[AiFace], num_vertices: usize) -> Self {
+        let mut offsets = vec![0u32; num_vertices + 1];
+        for face in faces {
+            for &index in face.indices.iter() {
+                offsets[index as usize + 1] += 1;
+            }
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        let mut cursor = offsets.clone();
+        let mut triangles = vec![0u32; offsets[num_vertices] as usize];
+        for (face_index, face) in faces.iter().enumerate() {
+            for &index in face.indices.iter() {
+                let slot = &mut cursor[index as usize];
+                triangles[*slot as usize] = face_index as u32;
+                *slot += 1;
+            }
+        }
+        Self { offsets, triangles }
+    }
+
+    fn adjacent(&self, vertex: usize) -> &[u32] {
+        &self.triangles[self.offsets[vertex] as usize..self.offsets[vertex + 1] as usize]
+    }
+
+    fn count(&self, vertex: usize) -> u32 {
+        self.offsets[vertex + 1] - self.offsets[vertex]
+    }
+}
+
+impl ImproveCacheLocalityProcess {
+    /// Runs the step with the given cache size.
+    pub fn execute_with_cache_size(scene: &mut AiScene, cache_size: u32) {
+        if scene.meshes.is_empty() {
+            log::debug!("ImproveCacheLocalityProcess skipped; there are no meshes");
+            return;
+        }
+        log::debug!("ImproveCacheLocalityProcess begin");
+
+        let mut out = 0.0;
+        let mut num_faces = 0;
+        let mut num_meshes = 0;
+        for (i, mesh) in scene.meshes.iter_mut().enumerate() {
+            if let Some((input, output)) = Self::process_mesh(mesh, cache_size) {
+                log::trace!(
+                    "Mesh {i} | ACMR in: {input} out: {output} | improvement {}%",
+                    (input - output) / input * 100.0
+                );
+                num_faces += mesh.faces.len();
+                out += output * mesh.faces.len() as f32;
+                num_meshes += 1;
+            }
+        }
+        if num_faces > 0 {
+            log::info!(
+                "Cache relevant are {num_meshes} meshes ({num_faces} faces). Average output ACMR is {}",
+                out / num_faces as f32
+            );
+        }
+        log::debug!("ImproveCacheLocalityProcess finished.");
+    }
+
+    /// Computes the average cache miss ratio of a triangle mesh for a FIFO
+    /// cache of the given size.
+    pub fn calculate_acmr(faces: &[AiFace], cache_size: u32) -> f32 {
+        if faces.is_empty() || cache_size == 0 {
+            return 0.0;
+        }
+        let mut cache = VecDeque::with_capacity(cache_size as usize);
+        let mut misses = 0u32;
+        for index in faces.iter().flat_map(|face| face.indices.iter()) {
+            if !cache.contains(index) {
+                misses += 1;
+                if cache.len() == cache_size as usize {
+                    cache.pop_front();
+                }
+                cache.push_back(*index);
+            }
+        }
+        misses as f32 / faces.len() as f32
+    }
+
+    /// Reorders the faces of a single mesh, returns the ACMR before and after
+    /// or `None` if the mesh was not processed.
+    pub fn process_mesh(mesh: &mut AiMesh, cache_size: u32) -> Option<(f32, f32)> {
+        if !mesh.has_face() || !mesh.has_positions() {
+            return None;
+        }
+        if mesh.faces.iter().any(|face| face.indices.len() != 3) {
+            log::error!("This algorithm works on triangle meshes only");
+            return None;
+        }
+        let num_vertices = mesh.vertices.len();
+        if num_vertices <= cache_size as usize {
+            return None;
+        }
+        if mesh
+            .faces
+            .iter()
+            .flat_map(|face| face.indices.iter())
+            .any(|&index| index as usize >= num_vertices)
+        {
+            log::error!("Mesh {}: face index out of range", mesh.name);
+            return None;
+        }
+
+        let input_acmr = Self::calculate_acmr(&mesh.faces, cache_size);
+        if input_acmr == 3.0 {
+            // The JoinIdenticalVertices process has not been executed on this
+            // mesh, otherwise this value would be at least minimally smaller
+            log::warn!("Mesh {}: Not suitable for vcache optimization", mesh.name);
+            return None;
+        }
+
+        let adjacency = VertexTriangleAdjacency::new(&mesh.faces, num_vertices);
+        let mut live_triangles = (0..num_vertices)
+            .map(|v| adjacency.count(v))
+            .collect::<Vec<_>>();
+        let mut caching_stamps = vec![0u32; num_vertices];
+        let mut emitted = vec![false; mesh.faces.len()];
+        let mut order = Vec::with_capacity(mesh.faces.len());
+        let mut dead_end_stack = Vec::new();
+        let mut candidates = Vec::new();
+
+        let mut fanning_vertex = Some(0usize);
+        let mut cursor = 0usize;
+        let mut time_stamp = cache_size + 1;
+        while let Some(vertex) = fanning_vertex {
+            candidates.clear();
+            // emit all triangles in the neighborhood which are still alive
+            for &triangle in adjacency.adjacent(vertex) {
+                let triangle = triangle as usize;
+                if emitted[triangle] {
+                    continue;
+                }
+                for &index in mesh.faces[triangle].indices.iter() {
+                    let index = index as usize;
+                    dead_end_stack.push(index);
+                    candidates.push(index);
+                    live_triangles[index] -= 1;
+                    // if the vertex is not yet in cache, set its cache count
+                    if time_stamp - caching_stamps[index] > cache_size {
+                        caching_stamps[index] = time_stamp;
+                        time_stamp += 1;
+                    }
+                }
+                emitted[triangle] = true;
+                order.push(triangle);
+            }
+
+            // get next fanning vertex, preferring vertices which stay in the
+            // cache even after fanning
+            fanning_vertex = None;
+            let mut max_priority = -1i64;
+            for &candidate in candidates.iter() {
+                let live = live_triangles[candidate];
+                if live == 0 {
+                    continue;
+                }
+                let age = time_stamp - caching_stamps[candidate];
+                let priority = if age + 2 * live <= cache_size {
+                    age as i64
+                } else {
+                    0
+                };
+                if priority > max_priority {
+                    max_priority = priority;
+                    fanning_vertex = Some(candidate);
+                }
+            }
+
+            if fanning_vertex.is_none() {
+                // dead end, take a recently used vertex which probably is
+                // still in the cache
+                while let Some(index) = dead_end_stack.pop() {
+                    if live_triangles[index] > 0 {
+                        fanning_vertex = Some(index);
+                        break;
+                    }
+                }
+            }
+            if fanning_vertex.is_none() {
+                // otherwise simply get the next vertex in input order
+                while cursor + 1 < num_vertices {
+                    cursor += 1;
+                    if live_triangles[cursor] > 0 {
+                        fanning_vertex = Some(cursor);
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut faces = core::mem::take(&mut mesh.faces)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        mesh.faces = order
+            .into_iter()
+            .filter_map(|triangle| faces[triangle].take())
+            .collect();
+
+        let output_acmr = Self::calculate_acmr(&mesh.faces, cache_size);
+        Some((input_acmr, output_acmr))
+    }
+}
+
+impl PostProcess for ImproveCacheLocalityProcess {
+    fn execute(scene: &mut AiScene) {
+        Self::execute_with_cache_size(scene, PP_ICL_PTCACHE_SIZE);
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::ImproveCacheLocality)
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::{ImproveCacheLocalityProcess, PP_ICL_PTCACHE_SIZE};
+    use crate::{
+        formats::x::importer::Importer,
+        get_model_path,
+        postprocess::weld_vertices::{WeldConfig, WeldVerticesProcess},
+        structs::{mesh::AiMesh, scene::AiScene},
+        traits::importer::trait_define::InternalImporter,
+    };
+
+    fn sorted_faces(mesh: &AiMesh) -> Vec<&[u32]> {
+        let mut faces = mesh.faces.iter().map(|f| &*f.indices).collect::<Vec<_>>();
+        faces.sort_unstable();
+        faces
+    }
+
+    #[test]
+    fn test_improve_cache_locality() {
+        let mut processed = 0;
+        for name in ["BCN_Epileptic.X", "Testwuson.X"] {
+            let source = fs::read(get_model_path("X", name)).unwrap();
+            let mut scene = AiScene::default();
+            Importer::import_from_buf(&source, &mut scene).unwrap();
+            for mesh in &mut scene.meshes {
+                // the importer unshares the vertices of the faces, which
+                // leaves nothing to optimize
+                WeldVerticesProcess::process_mesh(mesh, &WeldConfig::default());
+                let input = mesh.clone();
+                let (input_acmr, output_acmr) =
+                    ImproveCacheLocalityProcess::process_mesh(mesh, PP_ICL_PTCACHE_SIZE).unwrap();
+                processed += 1;
+
+                // the triangles are reordered, but neither changed nor lost
+                assert_eq!(mesh.vertices, input.vertices);
+                assert_eq!(sorted_faces(mesh), sorted_faces(&input));
+                assert_ne!(mesh.faces, input.faces);
+                assert_eq!(
+                    input_acmr,
+                    ImproveCacheLocalityProcess::calculate_acmr(&input.faces, PP_ICL_PTCACHE_SIZE)
+                );
+                assert_eq!(
+                    output_acmr,
+                    ImproveCacheLocalityProcess::calculate_acmr(&mesh.faces, PP_ICL_PTCACHE_SIZE)
+                );
+                assert!(
+                    output_acmr <= input_acmr,
+                    "{name} {}: {input_acmr} -> {output_acmr}",
+                    mesh.name
+                );
+            }
+        }
+        assert_eq!(processed, 4);
+    }
+}
@@ -1,6 +1,26 @@
 use crate::structs::scene::AiScene;
 
+pub mod armature_populate;
+pub mod compress_animations;
+pub mod convert_color_space;
+pub mod convert_coordinate_system;
+pub mod convert_normal_maps;
 pub mod convert_to_left_hand_process;
+pub mod drop_normals;
+pub mod embed_textures;
+pub mod find_instances;
+pub mod gen_bounding_boxes;
+#[cfg(feature = "lightmap_uv")]
+pub mod gen_lightmap_uvs;
+pub mod gen_normals;
+pub mod improve_cache_locality;
+pub mod pipeline;
+pub mod remove_component;
+#[cfg(feature = "lod")]
+pub mod simplify;
+pub mod unique_names;
+pub mod validate;
+pub mod weld_vertices;
 
 pub trait PostProcess {
     fn execute(scene: &mut AiScene);
@@ -18,6 +38,7 @@ bitflags::bitflags! {
     ///  @see AiImportFile
     ///  @see AiImportFileEx
     ///
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
     pub struct AiPostProcessSteps: u32 {
 
         /// <hr>Calculates the tangents and bitangents for the imported meshes.
@@ -0,0 +1,383 @@
+use super::{
+    AiPostProcessSteps, PostProcess,
+    armature_populate::ArmaturePopulateProcess,
+    compress_animations::{AnimCompressionConfig, CompressAnimationsProcess},
+    convert_color_space::ConvertColorSpaceProcess,
+    convert_coordinate_system::ConvertCoordinateSystemProcess,
+    convert_normal_maps::ConvertNormalMapsProcess,
+    convert_to_left_hand_process::{
+        ConvertToLeftHandProcess, flip_uvs_process::FlipUVsProcess,
+        flip_winding_order_process::FlipWindingOrderProcess,
+    },
+    drop_normals::DropFaceNormalsProcess,
+    embed_textures::EmbedTexturesProcess,
+    find_instances::FindInstancesProcess,
+    gen_bounding_boxes::GenBoundingBoxesProcess,
+    gen_normals::{GenFaceNormalsProcess, GenNormalsConfig, GenVertexNormalsProcess},
+    improve_cache_locality::{ImproveCacheLocalityProcess, PP_ICL_PTCACHE_SIZE},
+    remove_component::{AiComponent, RemoveComponentProcess},
+    unique_names::UniqueNamesProcess,
+    validate::ValidateDSProcess,
+    weld_vertices::{WeldConfig, WeldVerticesProcess},
+};
+use crate::{
+    core::profiler::{ProfileScope, Profiler},
+    structs::{
+        color::ColorSpace,
+        coordinate_system::CoordinateSystem,
+        importer::{
+            AI_CONFIG_PP_FORCE_GEN_NORMALS, AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE,
+            AI_CONFIG_PP_ICL_PTCACHE_SIZE, AI_CONFIG_PP_JIV_EPSILON,
+            AI_CONFIG_PP_JIV_POSITION_ONLY, AI_CONFIG_PP_RVC_FLAGS, ImportProperties,
+        },
+        material::AiNormalMapConvention,
+        scene::AiScene,
+    },
+};
+
+/// Runs the requested post-processing steps on a scene.
+///
+/// The steps are executed in the same order as in assimp, steps which are
+/// not implemented yet are skipped. Configurable steps read their settings
+/// from the [`ImportProperties`].
+#[derive(Debug, Default, Clone)]
+pub struct PostProcessPipeline {
+    flags: AiPostProcessSteps,
+    properties: ImportProperties,
+    coordinate_system: Option<CoordinateSystem>,
+    color_space: Option<ColorSpace>,
+    normal_map_convention: Option<AiNormalMapConvention>,
+    unique_names: bool,
+    compress_animations: Option<AnimCompressionConfig>,
+    #[cfg(feature = "lod")]
+    simplify_ratio: Option<f32>,
+    #[cfg(feature = "lightmap_uv")]
+    lightmap_uvs: Option<super::gen_lightmap_uvs::LightmapUvConfig>,
+}
+
+impl PostProcessPipeline {
+    pub fn new(flags: AiPostProcessSteps) -> Self {
+        Self {
+            flags,
+            properties: ImportProperties::default(),
+            coordinate_system: None,
+            color_space: None,
+            normal_map_convention: None,
+            unique_names: false,
+            compress_animations: None,
+            #[cfg(feature = "lod")]
+            simplify_ratio: None,
+            #[cfg(feature = "lightmap_uv")]
+            lightmap_uvs: None,
+        }
+    }
+
+    pub fn with_properties(mut self, properties: ImportProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn flags(&self) -> AiPostProcessSteps {
+        self.flags
+    }
+
+    pub fn properties(&self) -> &ImportProperties {
+        &self.properties
+    }
+
+    pub fn properties_mut(&mut self) -> &mut ImportProperties {
+        &mut self.properties
+    }
+
+    /// Discard imported normals and always regenerate them, the same as
+    /// setting [`AI_CONFIG_PP_FORCE_GEN_NORMALS`].
+    pub fn force_gen_normals(mut self, force: bool) -> Self {
+        self.properties
+            .set_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS, force);
+        self
+    }
+
+    /// Converts the scene to `target` before the other steps run, see
+    /// [`ConvertCoordinateSystemProcess`].
+    pub fn convert_coordinate_system(mut self, target: CoordinateSystem) -> Self {
+        self.coordinate_system = Some(target);
+        self
+    }
+
+    /// Simplifies all meshes to about `ratio` of their triangles, see
+    /// [`SimplifyMeshesProcess`](super::simplify::SimplifyMeshesProcess).
+    #[cfg(feature = "lod")]
+    pub fn simplify(mut self, ratio: f32) -> Self {
+        self.simplify_ratio = Some(ratio);
+        self
+    }
+
+    /// Generates texture coordinates for lightmaps in the second UV channel,
+    /// see [`GenLightmapUVsProcess`](super::gen_lightmap_uvs::GenLightmapUVsProcess).
+    #[cfg(feature = "lightmap_uv")]
+    pub fn gen_lightmap_uvs(mut self, config: super::gen_lightmap_uvs::LightmapUvConfig) -> Self {
+        self.lightmap_uvs = Some(config);
+        self
+    }
+
+    /// Converts the colors of the scene to `target`, see
+    /// [`ConvertColorSpaceProcess`].
+    pub fn convert_color_space(mut self, target: ColorSpace) -> Self {
+        self.color_space = Some(target);
+        self
+    }
+
+    /// Converts the normal maps of the scene to `target`, see
+    /// [`ConvertNormalMapsProcess`].
+    pub fn convert_normal_maps(mut self, target: AiNormalMapConvention) -> Self {
+        self.normal_map_convention = Some(target);
+        self
+    }
+
+    /// Gives all nodes, meshes, bones and animations unique names before the
+    /// other steps run, see [`UniqueNamesProcess`].
+    pub fn unique_names(mut self, enable: bool) -> Self {
+        self.unique_names = enable;
+        self
+    }
+
+    /// Removes redundant keys of node animations, see
+    /// [`CompressAnimationsProcess`].
+    pub fn compress_animations(mut self, config: AnimCompressionConfig) -> Self {
+        self.compress_animations = Some(config);
+        self
+    }
+
+    /// Returns the flags which are effectively executed, taking the
+    /// properties into account.
+    pub fn effective_flags(&self) -> AiPostProcessSteps {
+        let mut flags = self.flags;
+        if self.properties.get_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS) {
+            flags |= AiPostProcessSteps::ForceGenNormals;
+        }
+        if flags.contains(AiPostProcessSteps::ForceGenNormals)
+            && !flags
+                .intersects(AiPostProcessSteps::GenNormals | AiPostProcessSteps::GenSmoothNormals)
+        {
+            flags |= AiPostProcessSteps::GenSmoothNormals;
+        }
+        // Flat and smooth normals are mutually exclusive, flat ones win
+        if flags.contains(AiPostProcessSteps::GenNormals) {
+            flags.remove(AiPostProcessSteps::GenSmoothNormals);
+        }
+        flags
+    }
+
+    /// Executes all active steps on the scene.
+    pub fn run(&self, scene: &mut AiScene) {
+        self.run_with_profiler(scene, &mut Profiler::disabled());
+    }
+
+    /// Executes all active steps like [`Self::run`] and records the duration
+    /// of each step in `profiler`, named like its flag.
+    pub fn run_with_profiler(&self, scene: &mut AiScene, profiler: &mut Profiler) {
+        let flags = self.effective_flags();
+        let properties = &self.properties;
+        let mut step = |name, f: &mut dyn FnMut(&mut AiScene)| {
+            profiler.measure(ProfileScope::PostProcess, name, || f(scene));
+        };
+
+        if ValidateDSProcess::is_active(flags) {
+            step("ValidateDataStructure", &mut ValidateDSProcess::execute);
+        }
+        if self.unique_names {
+            step("UniqueNames", &mut UniqueNamesProcess::execute);
+        }
+        if let Some(target) = &self.coordinate_system {
+            step("ConvertCoordinateSystem", &mut |scene| {
+                ConvertCoordinateSystemProcess::execute_with_target(scene, target)
+            });
+        }
+        if let Some(target) = self.color_space {
+            step("ConvertColorSpace", &mut |scene| {
+                ConvertColorSpaceProcess::execute_with_target(scene, target)
+            });
+        }
+        if ConvertToLeftHandProcess::is_active(flags) {
+            step("MakeLeftHanded", &mut ConvertToLeftHandProcess::execute);
+        }
+        if FlipUVsProcess::is_active(flags) {
+            step("FlipUVs", &mut FlipUVsProcess::execute);
+        }
+        if FlipWindingOrderProcess::is_active(flags) {
+            step("FlipWindingOrder", &mut FlipWindingOrderProcess::execute);
+        }
+        if RemoveComponentProcess::is_active(flags) {
+            let components =
+                AiComponent::from_bits_retain(properties.get_int(AI_CONFIG_PP_RVC_FLAGS) as u32);
+            step("RemoveComponent", &mut |scene| {
+                RemoveComponentProcess::execute_with_config(scene, components)
+            });
+        }
+        if EmbedTexturesProcess::is_active(flags) {
+            step("EmbedTextures", &mut EmbedTexturesProcess::execute);
+        }
+        if let Some(target) = self.normal_map_convention {
+            step("ConvertNormalMaps", &mut |scene| {
+                ConvertNormalMapsProcess::execute_with_target(scene, target)
+            });
+        }
+        if FindInstancesProcess::is_active(flags) {
+            step("FindInstances", &mut FindInstancesProcess::execute);
+        }
+        if let Some(config) = &self.compress_animations {
+            step("CompressAnimations", &mut |scene| {
+                CompressAnimationsProcess::execute_with_config(scene, config)
+            });
+        }
+        if ArmaturePopulateProcess::is_active(flags) {
+            step(
+                "PopulateArmatureData",
+                &mut ArmaturePopulateProcess::execute,
+            );
+        }
+        if DropFaceNormalsProcess::is_active(flags) {
+            step("DropNormals", &mut DropFaceNormalsProcess::execute);
+        }
+
+        let mut normals_config = GenNormalsConfig::from_flags(flags);
+        normals_config.max_smoothing_angle = properties.get_float_or(
+            AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE,
+            normals_config.max_smoothing_angle,
+        );
+        if GenFaceNormalsProcess::is_active(flags) {
+            step("GenNormals", &mut |scene| {
+                GenFaceNormalsProcess::execute_with_config(scene, &normals_config)
+            });
+        }
+        if GenVertexNormalsProcess::is_active(flags) {
+            step("GenSmoothNormals", &mut |scene| {
+                GenVertexNormalsProcess::execute_with_config(scene, &normals_config)
+            });
+        }
+
+        if WeldVerticesProcess::is_active(flags) {
+            let config = WeldConfig {
+                epsilon: properties.get_float_or(AI_CONFIG_PP_JIV_EPSILON, 0.0),
+                position_only: properties.get_bool(AI_CONFIG_PP_JIV_POSITION_ONLY),
+            };
+            step("JoinIdenticalVertices", &mut |scene| {
+                WeldVerticesProcess::execute_with_config(scene, &config)
+            });
+        }
+
+        #[cfg(feature = "lod")]
+        if let Some(ratio) = self.simplify_ratio {
+            step("SimplifyMeshes", &mut |scene| {
+                super::simplify::SimplifyMeshesProcess::execute_with_ratio(scene, ratio)
+            });
+        }
+
+        #[cfg(feature = "lightmap_uv")]
+        if let Some(config) = &self.lightmap_uvs {
+            step("GenLightmapUVs", &mut |scene| {
+                super::gen_lightmap_uvs::GenLightmapUVsProcess::execute_with_config(scene, config)
+            });
+        }
+
+        if ImproveCacheLocalityProcess::is_active(flags) {
+            let cache_size =
+                properties.get_int_or(AI_CONFIG_PP_ICL_PTCACHE_SIZE, PP_ICL_PTCACHE_SIZE as i32);
+            step("ImproveCacheLocality", &mut |scene| {
+                ImproveCacheLocalityProcess::execute_with_cache_size(
+                    scene,
+                    cache_size.max(0) as u32,
+                )
+            });
+        }
+        if GenBoundingBoxesProcess::is_active(flags) {
+            step("GenBoundingBoxes", &mut GenBoundingBoxesProcess::execute);
+        }
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::PostProcessPipeline;
+    use crate::{
+        formats::x::importer::Importer,
+        get_model_path,
+        postprocess::AiPostProcessSteps,
+        structs::{
+            importer::{
+                AI_CONFIG_PP_FORCE_GEN_NORMALS, AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE,
+                ImportProperties,
+            },
+            scene::AiScene,
+        },
+        traits::importer::trait_define::InternalImporter,
+        utils::float_precision::Vec3,
+    };
+
+    /// Replaces the imported normals, not parallel to any side of the cube
+    const DIAGONAL: Vec3 = Vec3::new(0.6, 0.0, 0.8);
+
+    /// The exporter of the cube mixed up the winding order of some sides, so
+    /// only the direction of the normals is compared
+    fn is_parallel(a: Vec3, b: Vec3) -> bool {
+        a.dot(b).abs() > 0.9999
+    }
+
+    /// Keeps smooth normals from blending across the edges of the cube
+    fn sides_apart() -> ImportProperties {
+        let mut properties = ImportProperties::default();
+        properties.set_float(AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE, 80.0);
+        properties
+    }
+
+    /// The cube with its normals replaced, and the imported normals
+    fn import_cube_with_wrong_normals() -> (AiScene, Vec<Vec3>) {
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let mut scene = AiScene::default();
+        Importer::import_from_buf(&source, &mut scene).unwrap();
+        let mesh = &mut scene.meshes[0];
+        let imported = core::mem::replace(&mut mesh.normals, vec![DIAGONAL; mesh.vertices.len()]);
+        (scene, imported)
+    }
+
+    #[test]
+    fn test_force_gen_normals() {
+        for steps in [
+            AiPostProcessSteps::GenNormals,
+            AiPostProcessSteps::GenSmoothNormals,
+        ] {
+            // existing normals are kept by default
+            let (mut scene, _) = import_cube_with_wrong_normals();
+            PostProcessPipeline::new(steps).run(&mut scene);
+            assert!(scene.meshes[0].normals.iter().all(|&n| n == DIAGONAL));
+
+            let (mut scene, imported) = import_cube_with_wrong_normals();
+            let mut properties = sides_apart();
+            properties.set_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS, true);
+            PostProcessPipeline::new(steps)
+                .with_properties(properties)
+                .run(&mut scene);
+            let mesh = &scene.meshes[0];
+            assert_eq!(mesh.normals.len(), mesh.vertices.len());
+            for (&generated, &imported) in mesh.normals.iter().zip(&imported) {
+                assert!(is_parallel(generated, imported), "{generated} {imported}");
+            }
+        }
+
+        // forcing without a normals step generates smooth normals
+        let (mut scene, imported) = import_cube_with_wrong_normals();
+        let pipeline = PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .with_properties(sides_apart())
+            .force_gen_normals(true);
+        assert!(
+            pipeline
+                .effective_flags()
+                .contains(AiPostProcessSteps::GenSmoothNormals)
+        );
+        pipeline.run(&mut scene);
+        assert!(is_parallel(scene.meshes[0].normals[0], imported[0]));
+    }
+}
@@ -0,0 +1,326 @@
+use super::{AiPostProcessSteps, PostProcess};
+use crate::{
+    structs::{
+        material::{
+            AI_MATKEY_COLOR_AMBIENT, AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_NAME, AddProperty,
+            AiMaterial,
+        },
+        mesh::{AI_MAX_NUMBER_OF_COLOR_SETS, AI_MAX_NUMBER_OF_TEXTURECOORDS, AiMesh},
+        scene::AiScene,
+    },
+    utils::float_precision::Vec3,
+};
+
+bitflags::bitflags! {
+    /// Components of the scene which can be removed by the
+    /// [`RemoveComponentProcess`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct AiComponent: u32 {
+        /// Normal vectors
+        const Normals = 0x2;
+
+        /// Tangents and bitangents go always together ...
+        const TangentsAndBitangents = 0x4;
+
+        /// ALL color sets, use [`AiComponent::colors_n`] to specify the
+        /// N'th set
+        const Colors = 0x8;
+
+        /// ALL texture UV sets, use [`AiComponent::texcoords_n`] to specify
+        /// the N'th set
+        const TexCoords = 0x10;
+
+        /// Removes all bone weights from all meshes. The scenegraph nodes
+        /// corresponding to the bones are NOT removed.
+        const BoneWeights = 0x20;
+
+        /// Removes all node animations. The corresponding scenegraph nodes
+        /// are NOT removed.
+        const Animations = 0x40;
+
+        /// Removes all embedded textures
+        const Textures = 0x80;
+
+        /// Removes all light sources. The corresponding scenegraph nodes are
+        /// NOT removed.
+        const Lights = 0x100;
+
+        /// Removes all cameras. The corresponding scenegraph nodes are NOT
+        /// removed.
+        const Cameras = 0x200;
+
+        /// Removes all meshes
+        const Meshes = 0x400;
+
+        /// Removes all materials. One default material will be generated, so
+        /// every mesh refers to a valid material.
+        const Materials = 0x800;
+
+        const _ = !0;
+    }
+}
+
+impl AiComponent {
+    /// The N'th color set, empty for sets above 11.
+    ///
+    /// Like in assimp, the bits of the sets 5 to 7 are those of
+    /// [`Self::texcoords_n`] 0 to 2, so removing one of these color sets
+    /// also removes the texture coordinate set with the same bit.
+    pub const fn colors_n(n: u32) -> Self {
+        match 1u32.checked_shl(n + 20) {
+            Some(bits) => Self::from_bits_retain(bits),
+            None => Self::empty(),
+        }
+    }
+
+    /// The N'th texture coordinate set. The bits end at set 6, so the last
+    /// set can only be removed with [`Self::TexCoords`] and this is empty
+    /// for it.
+    pub const fn texcoords_n(n: u32) -> Self {
+        match 1u32.checked_shl(n + 25) {
+            Some(bits) => Self::from_bits_retain(bits),
+            None => Self::empty(),
+        }
+    }
+}
+
+/// Postprocessing step to remove specific data components from a scene.
+///
+/// The main purpose is to cut the memory of data the application does not
+/// need, and to get rid of components before a step like
+/// `JoinIdenticalVertices` so more vertices can be joined.
+pub struct RemoveComponentProcess;
+
+impl RemoveComponentProcess {
+    /// Runs the step removing the given components.
+    pub fn execute_with_config(scene: &mut AiScene, flags: AiComponent) {
+        log::debug!("RemoveVCProcess begin");
+        if flags.is_empty() {
+            log::warn!("RemoveVCProcess: no components to remove are specified.");
+        }
+        let mut has = false;
+
+        if flags.contains(AiComponent::Animations) {
+            has |= !scene.animations.is_empty();
+            scene.animations.clear();
+        }
+        if flags.contains(AiComponent::Textures) {
+            has |= !scene.textures.is_empty();
+            scene.textures.clear();
+        }
+        if flags.contains(AiComponent::Materials) && !scene.materials.is_empty() {
+            has = true;
+            scene.materials.clear();
+            scene.materials.push(Self::default_material());
+        }
+        if flags.contains(AiComponent::Lights) {
+            has |= !scene.lights.is_empty();
+            scene.lights.clear();
+        }
+        if flags.contains(AiComponent::Cameras) {
+            has |= !scene.cameras.is_empty();
+            scene.cameras.clear();
+        }
+        if flags.contains(AiComponent::Meshes) {
+            has |= !scene.meshes.is_empty();
+            scene.meshes.clear();
+            for node in scene.nodes.iter_mut() {
+                node.meshes = 0..0;
+            }
+        } else {
+            for mesh in scene.meshes.iter_mut() {
+                has |= Self::process_mesh(mesh, flags);
+            }
+        }
+
+        if has {
+            log::info!("RemoveVCProcess finished. Data structure cleanup has been done.");
+        } else {
+            log::debug!("RemoveVCProcess finished. Nothing to be done ...");
+        }
+    }
+
+    fn default_material() -> AiMaterial {
+        let mut material = AiMaterial::default();
+        // gray
+        material.add_property(AI_MATKEY_COLOR_DIFFUSE, Vec3::splat(0.6), 0);
+        // add a small ambient color value
+        material.add_property(AI_MATKEY_COLOR_AMBIENT, Vec3::splat(0.05), 0);
+        material.add_property(AI_MATKEY_NAME, "Dummy_MaterialsRemoved".to_owned(), 0);
+        material
+    }
+
+    /// Removes the components of a single mesh, returns whether anything was
+    /// removed.
+    pub fn process_mesh(mesh: &mut AiMesh, flags: AiComponent) -> bool {
+        let mut ret = false;
+
+        // if all materials have been deleted let the material
+        // index of the mesh point to the created default material
+        if flags.contains(AiComponent::Materials) {
+            mesh.material_index = 0;
+        }
+
+        if flags.contains(AiComponent::Normals) && !mesh.normals.is_empty() {
+            mesh.normals = Vec::new();
+            ret = true;
+        }
+
+        if flags.contains(AiComponent::TangentsAndBitangents) && !mesh.tangents.is_empty() {
+            mesh.tangents = Vec::new();
+            mesh.bitangents = Vec::new();
+            ret = true;
+        }
+
+        // Removed sets are collapsed, so the remaining ones stay continuous
+        let all = flags.contains(AiComponent::TexCoords);
+        let mut i = 0;
+        for real in 0..AI_MAX_NUMBER_OF_TEXTURECOORDS {
+            if mesh.texture_coords[i].is_empty() {
+                break;
+            }
+            let set = AiComponent::texcoords_n(real as u32);
+            if all || (!set.is_empty() && flags.contains(set)) {
+                ret = true;
+                mesh.texture_coords[i..].rotate_left(1);
+                mesh.texture_coords[AI_MAX_NUMBER_OF_TEXTURECOORDS - 1] = Vec::new();
+                mesh.num_of_uv_components[i..].rotate_left(1);
+                mesh.num_of_uv_components[AI_MAX_NUMBER_OF_TEXTURECOORDS - 1] = 0;
+                if let Some(names) = &mut mesh.texture_coords_names {
+                    names[i..].rotate_left(1);
+                    names[AI_MAX_NUMBER_OF_TEXTURECOORDS - 1] = String::new();
+                }
+                continue;
+            }
+            i += 1;
+        }
+
+        let all = flags.contains(AiComponent::Colors);
+        let mut i = 0;
+        for real in 0..AI_MAX_NUMBER_OF_COLOR_SETS {
+            if mesh.colors[i].is_empty() {
+                break;
+            }
+            let set = AiComponent::colors_n(real as u32);
+            if all || (!set.is_empty() && flags.contains(set)) {
+                ret = true;
+                mesh.colors[i..].rotate_left(1);
+                mesh.colors[AI_MAX_NUMBER_OF_COLOR_SETS - 1] = Vec::new();
+                continue;
+            }
+            i += 1;
+        }
+
+        if flags.contains(AiComponent::BoneWeights) && !mesh.bones.is_empty() {
+            mesh.bones = Vec::new();
+            ret = true;
+        }
+        ret
+    }
+}
+
+impl PostProcess for RemoveComponentProcess {
+    fn execute(scene: &mut AiScene) {
+        Self::execute_with_config(scene, AiComponent::empty());
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::RemoveComponent)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{AiComponent, RemoveComponentProcess};
+    use crate::{
+        structs::{
+            color::Color4D,
+            mesh::{AI_MAX_NUMBER_OF_COLOR_SETS, AI_MAX_NUMBER_OF_TEXTURECOORDS, AiMesh},
+        },
+        utils::float_precision::Vec3,
+    };
+
+    /// A mesh with one vertex in every set, the value of a set is its index
+    fn mesh_with_all_sets() -> AiMesh {
+        let mut mesh = AiMesh {
+            vertices: vec![Vec3::ZERO],
+            ..Default::default()
+        };
+        for i in 0..AI_MAX_NUMBER_OF_TEXTURECOORDS {
+            mesh.texture_coords[i] = vec![Vec3::splat(i as _)];
+            mesh.num_of_uv_components[i] = 2;
+        }
+        for i in 0..AI_MAX_NUMBER_OF_COLOR_SETS {
+            mesh.colors[i] = vec![Color4D::splat(i as f32)];
+        }
+        mesh
+    }
+
+    fn uv_sets(mesh: &AiMesh) -> Vec<usize> {
+        mesh.texture_coords
+            .iter()
+            .take_while(|set| !set.is_empty())
+            .map(|set| set[0].x as usize)
+            .collect()
+    }
+
+    fn color_sets(mesh: &AiMesh) -> Vec<usize> {
+        mesh.colors
+            .iter()
+            .take_while(|set| !set.is_empty())
+            .map(|set| set[0].x as usize)
+            .collect()
+    }
+
+    #[test]
+    fn test_component_bits() {
+        assert_eq!(AiComponent::texcoords_n(6).bits(), 1 << 31);
+        // the last sets have no bit of their own
+        assert!(AiComponent::texcoords_n(7).is_empty());
+        assert!(AiComponent::colors_n(12).is_empty());
+        // the overlap of assimp
+        assert_eq!(AiComponent::colors_n(5), AiComponent::texcoords_n(0));
+    }
+
+    #[test]
+    fn test_remove_single_uv_set() {
+        let mut mesh = mesh_with_all_sets();
+        assert!(RemoveComponentProcess::process_mesh(
+            &mut mesh,
+            AiComponent::texcoords_n(1)
+        ));
+        // the remaining sets are moved down, the 8th set is kept
+        assert_eq!(uv_sets(&mesh), [0, 2, 3, 4, 5, 6, 7]);
+        // the bit is also the one of the 7th color set
+        assert_eq!(color_sets(&mesh), [0, 1, 2, 3, 4, 5, 7]);
+        assert_eq!(mesh.num_of_uv_components[6], 2);
+        assert_eq!(mesh.num_of_uv_components[7], 0);
+
+        let mut mesh = mesh_with_all_sets();
+        RemoveComponentProcess::process_mesh(&mut mesh, AiComponent::texcoords_n(6));
+        assert_eq!(uv_sets(&mesh), [0, 1, 2, 3, 4, 5, 7]);
+
+        // the 8th set can't be removed on its own
+        let mut mesh = mesh_with_all_sets();
+        assert!(!RemoveComponentProcess::process_mesh(
+            &mut mesh,
+            AiComponent::texcoords_n(7) | AiComponent::Normals
+        ));
+        assert_eq!(uv_sets(&mesh).len(), AI_MAX_NUMBER_OF_TEXTURECOORDS);
+    }
+
+    #[test]
+    fn test_remove_single_color_set() {
+        let mut mesh = mesh_with_all_sets();
+        RemoveComponentProcess::process_mesh(&mut mesh, AiComponent::colors_n(0));
+        assert_eq!(color_sets(&mesh), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(uv_sets(&mesh).len(), AI_MAX_NUMBER_OF_TEXTURECOORDS);
+
+        // the 8th color set shares its bit with the third texture coordinate
+        // set, so both are removed
+        let mut mesh = mesh_with_all_sets();
+        RemoveComponentProcess::process_mesh(&mut mesh, AiComponent::colors_n(7));
+        assert_eq!(color_sets(&mesh), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(uv_sets(&mesh), [0, 1, 3, 4, 5, 6, 7]);
+    }
+}
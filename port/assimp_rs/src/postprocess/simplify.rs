@@ -0,0 +1,571 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    ops::Range,
+};
+
+use glam::DVec3;
+
+use crate::structs::{
+    face::AiFace,
+    mesh::{AiMesh, PrimitiveTypeFlags},
+    nodes::Index,
+    scene::{AiNode, AiScene},
+};
+
+/// Weight of the planes keeping open borders in place, relative to the
+/// planes of the faces
+const BORDER_WEIGHT: f64 = 100.0;
+
+/// Postprocessing step to reduce the number of triangles of all meshes with
+/// the quadric error metric of Garland and Heckbert.
+///
+/// Edges are collapsed into one of their end points, the attributes of the
+/// remaining vertices are kept. Vertices at the same position, e.g. split
+/// at UV seams, are collapsed together. Polygons are triangulated first,
+/// points and lines are kept. Open borders are preserved as far as
+/// possible and collapses flipping faces are rejected, so the result may
+/// have more triangles than requested.
+pub struct SimplifyMeshesProcess;
+
+/// Symmetric 4x4 matrix of the squared distances to a set of planes
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: DVec3, d: f64, weight: f64) -> Self {
+        let [a, b, c] = normal.to_array();
+        Self(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * weight),
+        )
+    }
+
+    fn add(&mut self, other: &Self) {
+        for (q, o) in self.0.iter_mut().zip(other.0) {
+            *q += o;
+        }
+    }
+
+    fn error(&self, p: DVec3) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = p.to_array();
+        let error = q[0] * x * x
+            + 2.0 * (q[1] * x * y + q[2] * x * z + q[3] * x)
+            + q[4] * y * y
+            + 2.0 * (q[5] * y * z + q[6] * y)
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9];
+        if error.is_finite() {
+            error.max(0.0)
+        } else {
+            f64::MAX
+        }
+    }
+}
+
+/// A possible collapse of `remove` into `keep`, ordered by the error. The
+/// versions detect candidates outdated by other collapses.
+type Candidate = Reverse<(u64, u32, u32, u32, u32)>;
+
+struct Simplifier {
+    positions: Vec<DVec3>,
+    quadrics: Vec<Quadric>,
+    /// Triangles in position indices
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    /// Triangles around each position
+    adjacent: Vec<Vec<u32>>,
+    version: Vec<u32>,
+    collapsed_into: Vec<Option<u32>>,
+    heap: BinaryHeap<Candidate>,
+}
+
+impl Simplifier {
+    fn new(positions: Vec<DVec3>, triangles: Vec<[u32; 3]>) -> Self {
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut adjacent = vec![Vec::new(); positions.len()];
+        let mut edges = HashMap::<(u32, u32), (u32, u32)>::new();
+        for (t, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|i| positions[i as usize]);
+            let cross = (b - a).cross(c - a);
+            let area = cross.length();
+            let normal = cross.normalize_or_zero();
+            let quadric = Quadric::plane(normal, -normal.dot(a), area);
+            for (k, &i) in triangle.iter().enumerate() {
+                quadrics[i as usize].add(&quadric);
+                adjacent[i as usize].push(t as u32);
+                let j = triangle[(k + 1) % 3];
+                edges.entry((i.min(j), i.max(j))).or_insert((0, t as u32)).0 += 1;
+            }
+        }
+        // planes perpendicular to the faces along open borders
+        for (&(i, j), &(count, t)) in &edges {
+            if count == 1 {
+                let [a, b, c] = triangles[t as usize].map(|i| positions[i as usize]);
+                let edge = positions[j as usize] - positions[i as usize];
+                let normal = edge.cross((b - a).cross(c - a)).normalize_or_zero();
+                let quadric = Quadric::plane(
+                    normal,
+                    -normal.dot(positions[i as usize]),
+                    BORDER_WEIGHT * edge.length_squared(),
+                );
+                quadrics[i as usize].add(&quadric);
+                quadrics[j as usize].add(&quadric);
+            }
+        }
+
+        let count = positions.len();
+        let mut simplifier = Self {
+            positions,
+            quadrics,
+            alive: vec![true; triangles.len()],
+            triangles,
+            adjacent,
+            version: vec![0; count],
+            collapsed_into: vec![None; count],
+            heap: BinaryHeap::new(),
+        };
+        for (i, j) in edges.into_keys() {
+            simplifier.push(i, j);
+        }
+        simplifier
+    }
+
+    fn push(&mut self, a: u32, b: u32) {
+        let mut quadric = self.quadrics[a as usize];
+        quadric.add(&self.quadrics[b as usize]);
+        let error_a = quadric.error(self.positions[a as usize]);
+        let error_b = quadric.error(self.positions[b as usize]);
+        let (error, keep, remove) = if error_a <= error_b {
+            (error_a, a, b)
+        } else {
+            (error_b, b, a)
+        };
+        self.heap.push(Reverse((
+            error.to_bits(),
+            keep,
+            remove,
+            self.version[keep as usize],
+            self.version[remove as usize],
+        )));
+    }
+
+    /// Whether moving `remove` to `keep` flips or collapses a face which
+    /// does not contain both
+    fn flips(&self, keep: u32, remove: u32) -> bool {
+        let target = self.positions[keep as usize];
+        self.adjacent[remove as usize].iter().any(|&t| {
+            let triangle = self.triangles[t as usize];
+            if !self.alive[t as usize] || triangle.contains(&keep) {
+                return false;
+            }
+            let [a, b, c] = triangle.map(|i| self.positions[i as usize]);
+            let [d, e, f] = triangle.map(|i| {
+                if i == remove {
+                    target
+                } else {
+                    self.positions[i as usize]
+                }
+            });
+            let before = (b - a).cross(c - a);
+            let after = (e - d).cross(f - d);
+            before.dot(after) <= 0.0
+                || after.length_squared() <= f64::EPSILON * before.length_squared()
+        })
+    }
+
+    /// Collapses edges until at most `target` triangles are left or no edge
+    /// can be collapsed
+    fn run(&mut self, target: usize) {
+        let mut count = self.triangles.len();
+        while count > target
+            && let Some(Reverse((_, keep, remove, keep_version, remove_version))) = self.heap.pop()
+        {
+            let (k, r) = (keep as usize, remove as usize);
+            if self.collapsed_into[k].is_some()
+                || self.collapsed_into[r].is_some()
+                || self.version[k] != keep_version
+                || self.version[r] != remove_version
+                || self.flips(keep, remove)
+            {
+                continue;
+            }
+
+            let triangles = core::mem::take(&mut self.adjacent[r]);
+            for t in triangles {
+                if !self.alive[t as usize] {
+                    continue;
+                }
+                let triangle = &mut self.triangles[t as usize];
+                if triangle.contains(&keep) {
+                    self.alive[t as usize] = false;
+                    count -= 1;
+                } else {
+                    triangle
+                        .iter_mut()
+                        .filter(|i| **i == remove)
+                        .for_each(|i| *i = keep);
+                    self.adjacent[k].push(t);
+                }
+            }
+            let quadric = self.quadrics[r];
+            self.quadrics[k].add(&quadric);
+            self.collapsed_into[r] = Some(keep);
+            self.version[k] += 1;
+
+            self.adjacent[k].retain(|&t| self.alive[t as usize]);
+            let mut neighbors = self.adjacent[k]
+                .iter()
+                .flat_map(|&t| self.triangles[t as usize])
+                .filter(|&i| i != keep)
+                .collect::<Vec<_>>();
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            for neighbor in neighbors {
+                self.push(keep, neighbor);
+            }
+        }
+    }
+
+    /// The position `index` was collapsed into
+    fn resolve(&self, mut index: u32) -> u32 {
+        while let Some(next) = self.collapsed_into[index as usize] {
+            index = next;
+        }
+        index
+    }
+}
+
+/// Keeps the vertices marked in `used` in all per-vertex streams and
+/// remaps the faces and bone weights
+fn retain_vertices(mesh: &mut AiMesh, used: &[bool]) {
+    let mut new_index = Vec::with_capacity(used.len());
+    let mut count = 0;
+    for &used in used {
+        new_index.push(count);
+        count += u32::from(used);
+    }
+    fn retain<T: Clone>(stream: &[T], used: &[bool]) -> Vec<T> {
+        if stream.len() != used.len() {
+            return stream.to_vec();
+        }
+        stream
+            .iter()
+            .zip(used)
+            .filter(|(_, u)| **u)
+            .map(|(v, _)| v.clone())
+            .collect()
+    }
+
+    mesh.vertices = retain(&mesh.vertices, used);
+    mesh.normals = retain(&mesh.normals, used);
+    mesh.tangents = retain(&mesh.tangents, used);
+    mesh.bitangents = retain(&mesh.bitangents, used);
+    for colors in mesh.colors.iter_mut() {
+        *colors = retain(colors, used);
+    }
+    for coords in mesh.texture_coords.iter_mut() {
+        *coords = retain(coords, used);
+    }
+    for anim in &mut mesh.anim_meshes {
+        anim.vertices = retain(&anim.vertices, used).into();
+        anim.normals = retain(&anim.normals, used).into();
+        anim.tangents = retain(&anim.tangents, used).into();
+        anim.bitangents = retain(&anim.bitangents, used).into();
+        for colors in anim.colors.iter_mut() {
+            *colors = retain(colors, used).into();
+        }
+        for coords in anim.texture_coords.iter_mut() {
+            *coords = retain(coords, used);
+        }
+    }
+    for face in &mut mesh.faces {
+        face.indices
+            .iter_mut()
+            .for_each(|i| *i = new_index[*i as usize]);
+    }
+    for bone in &mut mesh.bones {
+        bone.weights
+            .retain(|w| used.get(w.vertex_id as usize) == Some(&true));
+        bone.weights
+            .iter_mut()
+            .for_each(|w| w.vertex_id = new_index[w.vertex_id as usize]);
+    }
+}
+
+impl SimplifyMeshesProcess {
+    /// Simplifies all meshes to about `ratio` of their triangles.
+    pub fn execute_with_ratio(scene: &mut AiScene, ratio: f32) {
+        log::debug!("SimplifyMeshesProcess begin");
+        let (mut before, mut after) = (0, 0);
+        for mesh in scene.meshes.iter_mut() {
+            before += mesh.faces.len();
+            *mesh = Self::simplify_mesh(mesh, ratio);
+            after += mesh.faces.len();
+        }
+        log::info!("SimplifyMeshesProcess finished. {before} faces reduced to {after}");
+    }
+
+    /// Returns a copy of `mesh` with about `ratio` of its triangles, a
+    /// ratio of 1 or more returns an unchanged copy.
+    pub fn simplify_mesh(mesh: &AiMesh, ratio: f32) -> AiMesh {
+        let mut triangles = Vec::new();
+        let mut others = Vec::new();
+        for face in &mesh.faces {
+            match &*face.indices {
+                [first, rest @ ..] if rest.len() >= 2 => {
+                    triangles.extend(rest.windows(2).map(|w| [*first, w[0], w[1]]));
+                }
+                _ => others.push(face.clone()),
+            }
+        }
+        let target = (triangles.len() as f64 * f64::from(ratio.max(0.0))).ceil() as usize;
+        if target >= triangles.len()
+            || triangles
+                .iter()
+                .flatten()
+                .any(|&i| i as usize >= mesh.vertices.len())
+        {
+            return mesh.clone();
+        }
+
+        // vertices at the same position share a position index
+        let mut position_of = Vec::with_capacity(mesh.vertices.len());
+        let mut first_vertex = Vec::new();
+        let mut lookup = HashMap::new();
+        for (i, v) in mesh.vertices.iter().enumerate() {
+            let key = v.to_array().map(|c| c.to_bits());
+            position_of.push(*lookup.entry(key).or_insert_with(|| {
+                first_vertex.push(i);
+                first_vertex.len() as u32 - 1
+            }));
+        }
+        let positions = first_vertex
+            .iter()
+            .map(|&i| {
+                let v = mesh.vertices[i];
+                DVec3::new(f64::from(v.x), f64::from(v.y), f64::from(v.z))
+            })
+            .collect();
+        let mut kept = Vec::new();
+        let mut simplifier = Simplifier::new(
+            positions,
+            triangles
+                .iter()
+                .filter_map(|triangle| {
+                    let mapped = triangle.map(|i| position_of[i as usize]);
+                    let degenerate =
+                        mapped[0] == mapped[1] || mapped[1] == mapped[2] || mapped[0] == mapped[2];
+                    (!degenerate).then(|| {
+                        kept.push(*triangle);
+                        mapped
+                    })
+                })
+                .collect(),
+        );
+        simplifier.run(target);
+
+        let mut out = mesh.clone();
+        let moved = |i: usize| first_vertex[simplifier.resolve(position_of[i]) as usize];
+        for i in 0..out.vertices.len() {
+            let source = moved(i);
+            if source != i {
+                out.vertices[i] = mesh.vertices[source];
+                for anim in &mut out.anim_meshes {
+                    if let (Some(&v), true) = (anim.vertices.get(source), i < anim.vertices.len()) {
+                        anim.vertices[i] = v;
+                    }
+                }
+            }
+        }
+        out.faces = others;
+        out.faces.extend(
+            kept.iter()
+                .zip(&simplifier.alive)
+                .filter(|(_, alive)| **alive)
+                .map(|(triangle, _)| AiFace {
+                    indices: Box::new(*triangle),
+                }),
+        );
+        let mut used = vec![false; out.vertices.len()];
+        for &i in out.faces.iter().flat_map(|f| f.indices.iter()) {
+            used[i as usize] = true;
+        }
+        retain_vertices(&mut out, &used);
+        out.primitive_type &= !PrimitiveTypeFlags::POLYGON;
+        out.update_primitive_types();
+        out
+    }
+}
+
+impl AiScene {
+    /// Adds a simplified copy of the meshes of every node for each ratio.
+    /// The copies of level `n` (starting at 1) are named `<mesh>_LOD<n>`
+    /// and are referenced by a new child node `<node>_LOD<n>` with an
+    /// identity transformation, which applications show instead of the
+    /// meshes of the node itself. Returns the new nodes.
+    pub fn generate_lods(&mut self, ratios: &[f32]) -> Vec<Index<AiNode>> {
+        let nodes = self.nodes.len();
+        let mut added = Vec::new();
+        for (level, &ratio) in ratios.iter().enumerate() {
+            let level = level + 1;
+            // nodes sharing meshes share their levels
+            let mut ranges = Vec::<(Range<u32>, Range<u32>)>::new();
+            for node in 0..nodes {
+                let meshes = self.nodes[node].meshes.clone();
+                if meshes.is_empty() || meshes.end as usize > self.meshes.len() {
+                    continue;
+                }
+                let lod = match ranges.iter().find(|(from, _)| *from == meshes) {
+                    Some((_, to)) => to.clone(),
+                    None => {
+                        let start = self.meshes.len() as u32;
+                        for mesh in meshes.clone() {
+                            let mesh = &self.meshes[mesh as usize];
+                            let mut lod = SimplifyMeshesProcess::simplify_mesh(mesh, ratio);
+                            lod.name = format!("{}_LOD{level}", mesh.name);
+                            self.meshes.push(lod);
+                        }
+                        let to = start..self.meshes.len() as u32;
+                        ranges.push((meshes, to.clone()));
+                        to
+                    }
+                };
+                let child = AiNode {
+                    name: format!("{}_LOD{level}", self.nodes[node].name),
+                    meshes: lod,
+                    ..Default::default()
+                };
+                if let Ok(index) = self.add_node(Some(Index::new(node as u32)), child) {
+                    added.push(index);
+                }
+            }
+        }
+        added
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::SimplifyMeshesProcess;
+    use crate::{
+        AiReal,
+        formats::x::importer::Importer,
+        postprocess::{
+            AiPostProcessSteps, pipeline::PostProcessPipeline, validate::ValidateDSProcess,
+        },
+        structs::{
+            face::AiFace,
+            material::AiMaterial,
+            mesh::{AiMesh, PrimitiveTypeFlags},
+            scene_builder::SceneBuilder,
+        },
+        traits::importer::trait_define::Importer as _,
+        utils::{
+            float_precision::{Mat4, Vec3},
+            get_model_path,
+        },
+    };
+
+    #[test]
+    fn test_simplify_meshes() {
+        // UV sphere with poles split into separate vertices
+        let (rings, segments) = (32, 64);
+        let mut vertices = Vec::new();
+        for ring in 0..=rings {
+            let theta = ring as AiReal / rings as AiReal * core::f64::consts::PI as AiReal;
+            for segment in 0..=segments {
+                let phi = segment as AiReal / segments as AiReal * core::f64::consts::TAU as AiReal;
+                vertices.push(Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ));
+            }
+        }
+        let mut faces = Vec::new();
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let a = ring * (segments + 1) + segment;
+                let b = a + segments + 1;
+                faces.push(AiFace {
+                    indices: Box::new([a, b, a + 1]),
+                });
+                faces.push(AiFace {
+                    indices: Box::new([a + 1, b, b + 1]),
+                });
+            }
+        }
+        let triangles = faces.len();
+        let sphere = AiMesh {
+            name: "sphere".to_owned(),
+            primitive_type: PrimitiveTypeFlags::TRIANGLE,
+            normals: vertices.clone(),
+            vertices,
+            faces,
+            ..Default::default()
+        };
+
+        let simplified = SimplifyMeshesProcess::simplify_mesh(&sphere, 0.25);
+        assert!(
+            simplified.faces.len() <= triangles / 4 + 1,
+            "{}",
+            simplified.faces.len()
+        );
+        assert!(simplified.faces.len() > triangles / 8);
+        assert_eq!(simplified.normals.len(), simplified.vertices.len());
+        assert!(simplified.vertices.len() < sphere.vertices.len());
+        for v in &simplified.vertices {
+            assert!((v.length() - 1.0).abs() < 1e-4);
+        }
+        assert_eq!(
+            SimplifyMeshesProcess::simplify_mesh(&sphere, 1.0).faces,
+            sphere.faces
+        );
+
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", Mat4::IDENTITY, None);
+        let ball = builder.add_node("ball", Mat4::from_translation(Vec3::X), Some(root));
+        let mesh = builder.add_mesh(sphere);
+        builder.attach_mesh(ball, mesh);
+        let mut scene = builder.build().unwrap();
+        let lods = scene.generate_lods(&[0.5, 0.1]);
+        let names = lods
+            .iter()
+            .map(|&n| scene.nodes[n.value()].name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["ball_LOD1", "ball_LOD2"]);
+        assert_eq!(scene.meshes[1].name, "sphere_LOD1");
+        assert_eq!(scene.meshes[2].name, "sphere_LOD2");
+        assert!(scene.meshes[2].faces.len() < scene.meshes[1].faces.len());
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+
+        let mut scene =
+            *Importer::read_from_buf(&fs::read(get_model_path("X", "anim_test.x")).unwrap())
+                .unwrap();
+        let before = scene.meshes.iter().map(|m| m.faces.len()).sum::<usize>();
+        PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .simplify(0.5)
+            .run(&mut scene);
+        let after = scene.meshes.iter().map(|m| m.faces.len()).sum::<usize>();
+        assert!(after < before);
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use crate::structs::scene::AiScene;
+
+/// Postprocessing step to give all nodes, meshes and animations a unique,
+/// non-empty name, and all bones a name unique within their mesh.
+///
+/// X files often contain several frames with the same name or none at all,
+/// which breaks consumers looking nodes up by name, like skinning or glTF
+/// export. The first object with a name keeps it, later duplicates get a
+/// `_1`, `_2`, ... suffix and unnamed objects are called like their kind
+/// and index, e.g. `Node_3`. Suffixes never collide with names present in
+/// the scene. Bones and animation channels still refer to the first node
+/// of a duplicated name, as they did before.
+pub struct UniqueNamesProcess;
+
+/// Hands out unique names for one kind of object
+struct NameSet<'a> {
+    /// All names present before renaming, suffixes must avoid them
+    present: HashSet<String>,
+    taken: HashSet<String>,
+    kind: &'a str,
+}
+
+impl<'a> NameSet<'a> {
+    fn new<'n>(kind: &'a str, names: impl Iterator<Item = &'n str>) -> Self {
+        Self {
+            present: names.map(str::to_owned).collect(),
+            taken: HashSet::new(),
+            kind,
+        }
+    }
+
+    /// The unique name for the object at `index` named `name`, `None` if
+    /// it can keep its name
+    fn assign(&mut self, name: &str, index: usize) -> Option<String> {
+        if !name.is_empty() && self.taken.insert(name.to_owned()) {
+            return None;
+        }
+        let base = if name.is_empty() {
+            format!("{}_{index}", self.kind)
+        } else {
+            name.to_owned()
+        };
+        let mut candidate = base.clone();
+        let mut suffix = 0;
+        while self.taken.contains(&candidate) || self.present.contains(&candidate) {
+            suffix += 1;
+            candidate = format!("{base}_{suffix}");
+        }
+        self.taken.insert(candidate.clone());
+        Some(candidate)
+    }
+}
+
+impl UniqueNamesProcess {
+    /// Renames the objects of the scene, returns the number of renamed
+    /// objects.
+    pub fn execute_with_count(scene: &mut AiScene) -> usize {
+        let mut renamed = 0;
+
+        let mut nodes = NameSet::new("Node", scene.nodes.iter().map(|n| n.name.as_str()));
+        for (i, node) in scene.nodes.iter_mut().enumerate() {
+            if let Some(name) = nodes.assign(&node.name, i) {
+                node.name = name;
+                renamed += 1;
+            }
+        }
+
+        let mut meshes = NameSet::new("Mesh", scene.meshes.iter().map(|m| m.name.as_str()));
+        for (i, mesh) in scene.meshes.iter_mut().enumerate() {
+            if let Some(name) = meshes.assign(&mesh.name, i) {
+                mesh.name = name;
+                renamed += 1;
+            }
+            let mut bones = NameSet::new("Bone", mesh.bones.iter().map(|b| b.name.as_str()));
+            for (i, bone) in mesh.bones.iter_mut().enumerate() {
+                if let Some(name) = bones.assign(&bone.name, i) {
+                    bone.name = name;
+                    renamed += 1;
+                }
+            }
+        }
+
+        let mut animations = NameSet::new(
+            "Animation",
+            scene.animations.iter().map(|a| a.name.as_str()),
+        );
+        for (i, animation) in scene.animations.iter_mut().enumerate() {
+            if let Some(name) = animations.assign(&animation.name, i) {
+                animation.name = name;
+                renamed += 1;
+            }
+        }
+        renamed
+    }
+
+    pub fn execute(scene: &mut AiScene) {
+        log::debug!("UniqueNamesProcess begin");
+        let renamed = Self::execute_with_count(scene);
+        if renamed > 0 {
+            log::info!("UniqueNamesProcess finished. {renamed} objects were renamed");
+        } else {
+            log::debug!("UniqueNamesProcess finished. All names were unique");
+        }
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use super::UniqueNamesProcess;
+    use crate::{
+        formats::x::importer::Importer,
+        postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+        structs::scene::AiScene,
+        traits::importer::trait_define::Importer as _,
+    };
+
+    #[test]
+    fn test_unique_names() {
+        let mut scene = *Importer::read_from_buf(
+            br#"xof 0303txt 0032
+Frame Root {
+Frame Part { Mesh part { 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; } }
+Frame Part { Mesh part { 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; } }
+Frame Part_1 { Mesh { 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; } }
+}
+"#,
+        )
+        .unwrap();
+        let names = |scene: &AiScene| -> Vec<String> {
+            scene.nodes.iter().map(|n| n.name.clone()).collect()
+        };
+        let before = names(&scene);
+        PostProcessPipeline::new(AiPostProcessSteps::empty())
+            .unique_names(true)
+            .run(&mut scene);
+        let after = names(&scene);
+        let unique: std::collections::HashSet<_> = after.iter().collect();
+        assert_eq!(unique.len(), after.len());
+        assert!(after.iter().all(|name| !name.is_empty()));
+        // the first duplicate keeps its name, the suffix skips Part_1
+        assert_eq!(after.iter().filter(|n| *n == "Part").count(), 1);
+        assert!(after.contains(&"Part_1".to_owned()));
+        assert!(after.contains(&"Part_2".to_owned()));
+        assert_eq!(before.len(), after.len());
+
+        let meshes: std::collections::HashSet<_> = scene.meshes.iter().map(|m| &m.name).collect();
+        assert_eq!(meshes.len(), scene.meshes.len());
+        assert!(scene.meshes.iter().all(|m| !m.name.is_empty()));
+        // nothing left to rename
+        assert_eq!(UniqueNamesProcess::execute_with_count(&mut scene), 0);
+    }
+}
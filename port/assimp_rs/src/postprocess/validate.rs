@@ -0,0 +1,298 @@
+use super::{AiPostProcessSteps, PostProcess};
+use crate::structs::{
+    anim::AiAnimation,
+    mesh::{AiMesh, PrimitiveTypeFlags},
+    scene::{AiScene, AiSceneFlags},
+};
+
+/// Problems found by [`ValidateDSProcess`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Violations which make the scene unusable, e.g. out of range indices
+    pub errors: Vec<String>,
+    /// Suspicious data which is still safe to use
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.push(message);
+    }
+}
+
+fn is_ascending(mut times: impl Iterator<Item = f64>) -> bool {
+    times
+        .try_fold(f64::MIN, |last, time| (time >= last).then_some(time))
+        .is_some()
+}
+
+/// Postprocessing step to check the scene data structure for consistency,
+/// like the `ValidateDataStructure` step of assimp.
+///
+/// The step does not modify any data besides the scene flags:
+/// [`AiSceneFlags::VALIDATED`] is set if no errors were found and
+/// [`AiSceneFlags::VALIDATION_WARNING`] if there were warnings.
+pub struct ValidateDSProcess;
+
+impl ValidateDSProcess {
+    /// Checks the scene and returns all problems found.
+    pub fn validate(scene: &AiScene) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        Self::validate_nodes(scene, &mut report);
+        if !scene.meshes.is_empty() && scene.materials.is_empty() {
+            report.error("The scene has meshes but no materials".to_owned());
+        }
+        let mut referenced = vec![false; scene.meshes.len()];
+        for node in &scene.nodes {
+            for i in node.meshes.clone() {
+                if let Some(r) = referenced.get_mut(i as usize) {
+                    *r = true;
+                }
+            }
+        }
+        for (i, mesh) in scene.meshes.iter().enumerate() {
+            Self::validate_mesh(scene, i, mesh, &mut report);
+            if !referenced[i] {
+                report.warn(format!(
+                    "Mesh {i} ({}) is not referenced by any node",
+                    mesh.name
+                ));
+            }
+        }
+        for animation in &scene.animations {
+            Self::validate_animation(scene, animation, &mut report);
+        }
+        report
+    }
+
+    fn validate_nodes(scene: &AiScene, report: &mut ValidationReport) {
+        let Some(root) = scene.root else {
+            if !scene.nodes.is_empty() || !scene.meshes.is_empty() {
+                report.error("The scene has no root node".to_owned());
+            }
+            return;
+        };
+        if root.value() >= scene.nodes.len() {
+            report.error(format!("Root node index {} is out of range", root.value()));
+            return;
+        }
+        if let Some(parent) = scene.nodes[root.value()].parent {
+            report.error(format!("The root node has the parent {}", parent.value()));
+        }
+        let mut visited = vec![false; scene.nodes.len()];
+        let mut stack = vec![root];
+        while let Some(index) = stack.pop() {
+            let i = index.value();
+            if visited[i] {
+                report.error(format!("Node {i} is referenced more than once"));
+                continue;
+            }
+            visited[i] = true;
+            let node = &scene.nodes[i];
+            if node.meshes.end as usize > scene.meshes.len() || node.meshes.start > node.meshes.end
+            {
+                report.error(format!(
+                    "Node {i} ({}) references meshes {:?}, but there are only {}",
+                    node.name,
+                    node.meshes,
+                    scene.meshes.len()
+                ));
+            }
+            for &child in &node.children {
+                let c = child.value();
+                if c >= scene.nodes.len() {
+                    report.error(format!("Node {i} ({}) has invalid child {c}", node.name));
+                    continue;
+                }
+                if scene.nodes[c].parent != Some(index) {
+                    report.error(format!(
+                        "Node {c} ({}) is a child of node {i} but has another parent",
+                        scene.nodes[c].name
+                    ));
+                }
+                stack.push(child);
+            }
+        }
+        let unreachable = visited.iter().filter(|v| !**v).count();
+        if unreachable > 0 {
+            report.warn(format!(
+                "{unreachable} nodes are not reachable from the root node"
+            ));
+        }
+    }
+
+    fn validate_mesh(scene: &AiScene, i: usize, mesh: &AiMesh, report: &mut ValidationReport) {
+        let name = &mesh.name;
+        let len = mesh.vertices.len();
+        if len == 0 {
+            report.error(format!("Mesh {i} ({name}) has no vertices"));
+        }
+        if mesh.faces.is_empty() {
+            report.error(format!("Mesh {i} ({name}) has no faces"));
+        }
+        if !scene.materials.is_empty() && mesh.material_index as usize >= scene.materials.len() {
+            report.error(format!(
+                "Mesh {i} ({name}) references material {}, but there are only {}",
+                mesh.material_index,
+                scene.materials.len()
+            ));
+        }
+
+        let mut face_types = PrimitiveTypeFlags::empty();
+        for (f, face) in mesh.faces.iter().enumerate() {
+            if face.indices.is_empty() {
+                report.error(format!("Face {f} of mesh {i} ({name}) has no indices"));
+            }
+            face_types |= PrimitiveTypeFlags::for_index_count(face.indices.len());
+            if let Some(index) = face.indices.iter().find(|&&index| index as usize >= len) {
+                report.error(format!(
+                    "Face {f} of mesh {i} ({name}) references vertex {index}, but there are only {len}"
+                ));
+            }
+        }
+
+        if mesh.primitive_type.is_empty() {
+            report.warn(format!("Mesh {i} ({name}) has no primitive types"));
+        } else if !mesh.primitive_type.contains(face_types) {
+            report.error(format!(
+                "Mesh {i} ({name}) has faces of types {face_types:?}, but its primitive types are {:?}",
+                mesh.primitive_type
+            ));
+        }
+
+        let mut check_len = |component: &str, component_len: usize| {
+            if component_len != 0 && component_len != len {
+                report.error(format!(
+                    "Mesh {i} ({name}) has {component_len} {component} for {len} vertices"
+                ));
+            }
+        };
+        check_len("normals", mesh.normals.len());
+        check_len("tangents", mesh.tangents.len());
+        check_len("bitangents", mesh.bitangents.len());
+        for colors in mesh.colors.iter() {
+            check_len("colors", colors.len());
+        }
+        for uvs in mesh.texture_coords.iter() {
+            check_len("texture coordinates", uvs.len());
+        }
+        if mesh.tangents.is_empty() != mesh.bitangents.is_empty() {
+            report.error(format!(
+                "Mesh {i} ({name}) has tangents or bitangents, but not both"
+            ));
+        }
+
+        for bone in &mesh.bones {
+            for weight in &bone.weights {
+                if weight.vertex_id as usize >= len {
+                    report.error(format!(
+                        "Bone {} of mesh {i} ({name}) references vertex {}, but there are only {len}",
+                        bone.name, weight.vertex_id
+                    ));
+                }
+                if !(0.0..=1.0).contains(&weight.weight) {
+                    report.warn(format!(
+                        "Bone {} of mesh {i} ({name}) has weight {} outside of [0, 1]",
+                        bone.name, weight.weight
+                    ));
+                }
+            }
+        }
+    }
+
+    fn validate_animation(scene: &AiScene, animation: &AiAnimation, report: &mut ValidationReport) {
+        let name = &animation.name;
+        for channel in &animation.channels {
+            let node = &channel.node_name;
+            if !scene.nodes.iter().any(|n| n.name == **node) {
+                report.error(format!(
+                    "Animation {name} animates node {node}, which does not exist"
+                ));
+            }
+            if !is_ascending(channel.position_keys.iter().map(|k| k.time))
+                || !is_ascending(channel.rotation_keys.iter().map(|k| k.time))
+                || !is_ascending(channel.scaling_keys.iter().map(|k| k.time))
+            {
+                report.warn(format!(
+                    "Animation {name} has keys of node {node} which are not in ascending order"
+                ));
+            }
+        }
+    }
+}
+
+impl PostProcess for ValidateDSProcess {
+    fn execute(scene: &mut AiScene) {
+        log::debug!("ValidateDataStructureProcess begin");
+        let report = Self::validate(scene);
+        for warning in &report.warnings {
+            log::warn!("Validation warning: {warning}");
+        }
+        for error in &report.errors {
+            log::error!("Validation failed: {error}");
+        }
+        if report.is_valid() {
+            scene.flags |= AiSceneFlags::VALIDATED;
+        }
+        if !report.warnings.is_empty() {
+            scene.flags |= AiSceneFlags::VALIDATION_WARNING;
+        }
+        log::debug!("ValidateDataStructureProcess end");
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::ValidateDataStructure)
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::ValidateDSProcess;
+    use crate::{
+        formats::x::importer::Importer,
+        postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+        structs::{mesh::PrimitiveTypeFlags, scene::AiSceneFlags},
+        traits::importer::trait_define::Importer as _,
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_validate_data_structure() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let mut scene = *Importer::read_from_buf(&source).unwrap();
+        let report = ValidateDSProcess::validate(&scene);
+        assert!(report.is_valid(), "{:?}", report.errors);
+
+        PostProcessPipeline::new(AiPostProcessSteps::ValidateDataStructure).run(&mut scene);
+        assert!(scene.flags.contains(AiSceneFlags::VALIDATED));
+
+        assert!(
+            scene
+                .meshes
+                .iter()
+                .all(|m| m.primitive_type == PrimitiveTypeFlags::TRIANGLE)
+        );
+
+        let len = scene.meshes[0].vertices.len() as u32;
+        scene.meshes[0].faces[0].indices[0] = len;
+        scene.meshes[0].normals.pop();
+        scene.meshes[1].faces[0].indices = Box::new([0, 1, 2, 0]);
+        let report = ValidateDSProcess::validate(&scene);
+        assert_eq!(report.errors.len(), 3, "{:?}", report.errors);
+
+        scene.meshes[1].update_primitive_types();
+        let polygons = PrimitiveTypeFlags::TRIANGLE | PrimitiveTypeFlags::POLYGON;
+        assert_eq!(scene.meshes[1].primitive_type, polygons);
+    }
+}
@@ -0,0 +1,398 @@
+use core::ops::Add;
+
+use super::{AiPostProcessSteps, PostProcess};
+use crate::{
+    AiReal,
+    core::spatial::PointBvh,
+    structs::{
+        color::Color4D,
+        mesh::{AiMesh, AiVertexWeight},
+        scene::{AiScene, AiSceneFlags},
+    },
+    utils::float_precision::Vec3,
+};
+
+/// Configuration of [`WeldVerticesProcess`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeldConfig {
+    /// Maximum distance of two positions, and of any other attribute, for
+    /// the vertices to be joined. Zero joins identical vertices only.
+    pub epsilon: AiReal,
+
+    /// Join all vertices at the same position regardless of their other
+    /// attributes, which are averaged
+    pub position_only: bool,
+}
+
+/// Postprocessing step to join vertices which are equal, or close to each
+/// other, so they are shared by the faces.
+///
+/// By default only vertices whose attributes all match within the epsilon
+/// are joined and the first of them is kept, the same as
+/// `JoinIdenticalVertices`. In the position only mode all vertices within
+/// the epsilon of each other are joined and their attributes, including
+/// bone weights, are averaged. This closes cracks between faces at the
+/// cost of blurring UV seams and hard edges. Faces whose vertices collapse
+/// are removed.
+pub struct WeldVerticesProcess;
+
+/// How the attributes of joined vertices are combined
+struct Joiner<'a> {
+    new_index: &'a [u32],
+    sizes: &'a [u32],
+    average: bool,
+}
+
+impl Joiner<'_> {
+    /// Sums `stream` per joined vertex and scales the sums with `finish`,
+    /// or keeps the first value if not averaging. Streams of the wrong
+    /// length are kept.
+    fn join<T: Copy + Default + Add<Output = T>>(
+        &self,
+        stream: &[T],
+        finish: impl Fn(T, u32) -> T,
+    ) -> Vec<T> {
+        if stream.len() != self.new_index.len() {
+            return stream.to_vec();
+        }
+        let mut out = vec![T::default(); self.sizes.len()];
+        let mut seen = vec![false; self.sizes.len()];
+        for (value, &index) in stream.iter().zip(self.new_index) {
+            let index = index as usize;
+            out[index] = match (self.average, seen[index]) {
+                (true, _) => out[index] + *value,
+                (false, false) => *value,
+                (false, true) => continue,
+            };
+            seen[index] = true;
+        }
+        if self.average {
+            for (value, &size) in out.iter_mut().zip(self.sizes) {
+                *value = finish(*value, size);
+            }
+        }
+        out
+    }
+}
+
+fn mean(sum: Vec3, size: u32) -> Vec3 {
+    sum / size as AiReal
+}
+
+fn mean_direction(sum: Vec3, size: u32) -> Vec3 {
+    if size > 1 {
+        sum.normalize_or_zero()
+    } else {
+        sum
+    }
+}
+
+fn mean_color(sum: Color4D, size: u32) -> Color4D {
+    sum / size as f32
+}
+
+impl WeldVerticesProcess {
+    pub fn execute_with_config(scene: &mut AiScene, config: &WeldConfig) {
+        log::debug!("WeldVerticesProcess begin");
+        let (mut before, mut after) = (0, 0);
+        for mesh in scene.meshes.iter_mut() {
+            before += mesh.vertices.len();
+            Self::process_mesh(mesh, config);
+            after += mesh.vertices.len();
+        }
+        scene.flags |= AiSceneFlags::NON_VERBOSE_FORMAT;
+        log::info!("WeldVerticesProcess finished. {before} vertices joined to {after}");
+    }
+
+    /// Whether vertices `a` and `b` have the same attributes within
+    /// `epsilon`
+    // the casts are no-ops unless double_precision is enabled
+    #[allow(clippy::unnecessary_cast)]
+    fn same_attributes(
+        mesh: &AiMesh,
+        weights: &[Vec<(usize, f32)>],
+        a: usize,
+        b: usize,
+        epsilon: AiReal,
+    ) -> bool {
+        let close = |stream: &[Vec3]| {
+            stream.len() <= a.max(b) || (stream[a] - stream[b]).abs().max_element() <= epsilon
+        };
+        let close_color = |stream: &[Color4D]| {
+            stream.len() <= a.max(b)
+                || (stream[a] - stream[b]).abs().max_element() <= epsilon as f32
+        };
+        close(&mesh.normals)
+            && close(&mesh.tangents)
+            && close(&mesh.bitangents)
+            && mesh.texture_coords.iter().all(|s| close(s))
+            && mesh.colors.iter().all(|s| close_color(s))
+            && mesh.anim_meshes.iter().all(|anim| {
+                close(&anim.vertices)
+                    && close(&anim.normals)
+                    && anim.colors.iter().all(|s| close_color(s))
+                    && anim.texture_coords.iter().all(|s| close(s))
+            })
+            && weights[a].len() == weights[b].len()
+            && weights[a]
+                .iter()
+                .zip(&weights[b])
+                .all(|(x, y)| x.0 == y.0 && (x.1 - y.1).abs() <= epsilon as f32)
+    }
+
+    /// Joins the vertices of a mesh, returns whether any were joined.
+    pub fn process_mesh(mesh: &mut AiMesh, config: &WeldConfig) -> bool {
+        let count = mesh.vertices.len();
+        if count == 0 {
+            return false;
+        }
+        let epsilon = config.epsilon.max(0.0);
+        let mut weights = vec![Vec::new(); count];
+        if !config.position_only {
+            for (b, bone) in mesh.bones.iter().enumerate() {
+                for w in &bone.weights {
+                    if let Some(list) = weights.get_mut(w.vertex_id as usize) {
+                        list.push((b, w.weight));
+                    }
+                }
+            }
+        }
+
+        let finder = PointBvh::new(&mesh.vertices);
+        let mut found = Vec::new();
+        let mut new_index = vec![u32::MAX; count];
+        let mut sizes = Vec::new();
+        let mut first = Vec::new();
+        for i in 0..count {
+            if new_index[i] != u32::MAX {
+                continue;
+            }
+            let index = sizes.len() as u32;
+            first.push(i as u32);
+            new_index[i] = index;
+            let mut size = 1;
+            finder.find_within(mesh.vertices[i], epsilon, &mut found);
+            for &j in &found {
+                let j = j as usize;
+                if new_index[j] == u32::MAX
+                    && (config.position_only
+                        || Self::same_attributes(mesh, &weights, i, j, epsilon))
+                {
+                    new_index[j] = index;
+                    size += 1;
+                }
+            }
+            sizes.push(size);
+        }
+        if sizes.len() == count {
+            return false;
+        }
+
+        let joiner = Joiner {
+            new_index: &new_index,
+            sizes: &sizes,
+            average: config.position_only,
+        };
+        mesh.vertices = joiner.join(&mesh.vertices, mean);
+        mesh.normals = joiner.join(&mesh.normals, mean_direction);
+        mesh.tangents = joiner.join(&mesh.tangents, mean_direction);
+        mesh.bitangents = joiner.join(&mesh.bitangents, mean_direction);
+        for colors in mesh.colors.iter_mut() {
+            *colors = joiner.join(colors, mean_color);
+        }
+        for coords in mesh.texture_coords.iter_mut() {
+            *coords = joiner.join(coords, mean);
+        }
+        for anim in &mut mesh.anim_meshes {
+            anim.vertices = joiner.join(&anim.vertices, mean).into();
+            anim.normals = joiner.join(&anim.normals, mean_direction).into();
+            anim.tangents = joiner.join(&anim.tangents, mean_direction).into();
+            anim.bitangents = joiner.join(&anim.bitangents, mean_direction).into();
+            for colors in anim.colors.iter_mut() {
+                *colors = joiner.join(colors, mean_color).into();
+            }
+            for coords in anim.texture_coords.iter_mut() {
+                *coords = joiner.join(coords, mean);
+            }
+        }
+        if config.position_only {
+            for bone in &mut mesh.bones {
+                let mut sums = vec![None::<f32>; sizes.len()];
+                for w in &bone.weights {
+                    if let Some(&index) = new_index.get(w.vertex_id as usize) {
+                        *sums[index as usize].get_or_insert(0.0) += w.weight;
+                    }
+                }
+                bone.weights = sums
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, sum)| {
+                        sum.map(|sum| AiVertexWeight {
+                            vertex_id: index as u32,
+                            weight: sum / sizes[index] as f32,
+                        })
+                    })
+                    .collect();
+            }
+        } else {
+            // the weights of the joined vertices are equal
+            for bone in &mut mesh.bones {
+                bone.weights
+                    .retain_mut(|w| match new_index.get(w.vertex_id as usize) {
+                        Some(&index) if first[index as usize] == w.vertex_id => {
+                            w.vertex_id = index;
+                            true
+                        }
+                        _ => false,
+                    });
+            }
+        }
+
+        let faces = mesh.faces.len();
+        mesh.faces.retain_mut(|face| {
+            let mut indices = face
+                .indices
+                .iter()
+                .map(|&i| new_index.get(i as usize).copied().unwrap_or(i))
+                .collect::<Vec<_>>();
+            indices.dedup();
+            if indices.len() > 1 && indices.first() == indices.last() {
+                indices.pop();
+            }
+            let keep = indices.len() == face.indices.len();
+            face.indices = indices.into_boxed_slice();
+            keep
+        });
+        if mesh.faces.len() != faces {
+            log::debug!(
+                "WeldVerticesProcess removed {} collapsed faces of {}",
+                faces - mesh.faces.len(),
+                mesh.name
+            );
+            mesh.update_primitive_types();
+        }
+        true
+    }
+}
+
+impl PostProcess for WeldVerticesProcess {
+    fn execute(scene: &mut AiScene) {
+        Self::execute_with_config(scene, &WeldConfig::default());
+    }
+
+    fn is_active(flag: AiPostProcessSteps) -> bool {
+        flag.contains(AiPostProcessSteps::JoinIdenticalVertices)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{WeldConfig, WeldVerticesProcess};
+    use crate::{
+        postprocess::{
+            AiPostProcessSteps, pipeline::PostProcessPipeline, validate::ValidateDSProcess,
+        },
+        structs::{
+            bone::AiBone,
+            face::AiFace,
+            importer::{
+                AI_CONFIG_PP_JIV_EPSILON, AI_CONFIG_PP_JIV_POSITION_ONLY, ImportProperties,
+            },
+            material::AiMaterial,
+            mesh::{AiMesh, AiVertexWeight, PrimitiveTypeFlags},
+            scene::AiSceneFlags,
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Vec3},
+    };
+
+    #[test]
+    fn test_weld_vertices() {
+        // two quads, the right one is split off by a hairline crack and has
+        // other texture coordinates along it, vertex 4 is a copy of vertex 1
+        let crack = 1e-5;
+        let vertices = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0 + crack, 1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+        ];
+        let mut uvs = vertices.clone();
+        uvs[5].y = 0.5;
+        let quads = [[0, 1, 2, 3], [4, 6, 7, 5]];
+        let mesh = AiMesh {
+            primitive_type: PrimitiveTypeFlags::POLYGON,
+            texture_coords: Box::new([uvs, vec![], vec![], vec![], vec![], vec![], vec![], vec![]]),
+            vertices,
+            faces: quads
+                .iter()
+                .map(|q| AiFace {
+                    indices: Box::new(*q),
+                })
+                .collect(),
+            bones: vec![AiBone {
+                name: "bone".to_owned(),
+                weights: vec![AiVertexWeight {
+                    vertex_id: 5,
+                    weight: 1.0,
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let mut exact = mesh.clone();
+        assert!(WeldVerticesProcess::process_mesh(
+            &mut exact,
+            &WeldConfig::default()
+        ));
+        assert_eq!(exact.vertices.len(), 7);
+        assert_eq!(&*exact.faces[1].indices, &[1, 5, 6, 4]);
+        assert_eq!(exact.bones[0].weights[0].vertex_id, 4);
+
+        // the texture coordinates differ
+        let config = WeldConfig {
+            epsilon: 1e-4,
+            position_only: false,
+        };
+        let mut close = mesh.clone();
+        WeldVerticesProcess::process_mesh(&mut close, &config);
+        assert_eq!(close.vertices.len(), 7);
+
+        let config = WeldConfig {
+            position_only: true,
+            ..config
+        };
+        let mut welded = mesh.clone();
+        WeldVerticesProcess::process_mesh(&mut welded, &config);
+        assert_eq!(welded.vertices.len(), 6);
+        assert_eq!(&*welded.faces[1].indices, &[1, 4, 5, 2]);
+        assert!((welded.vertices[2].x - (1.0 + crack / 2.0)).abs() < 1e-6);
+        assert!((welded.texture_coords[0][2].y - 0.75).abs() < 1e-6);
+        assert_eq!(welded.bones[0].weights[0].vertex_id, 2);
+        assert!((welded.bones[0].weights[0].weight - 0.5).abs() < 1e-6);
+
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", Mat4::IDENTITY, None);
+        let index = builder.add_mesh(AiMesh {
+            bones: Vec::new(),
+            ..mesh
+        });
+        builder.attach_mesh(root, index);
+        let mut scene = builder.build().unwrap();
+        let mut properties = ImportProperties::default();
+        properties.set_float(AI_CONFIG_PP_JIV_EPSILON, 1e-4);
+        properties.set_bool(AI_CONFIG_PP_JIV_POSITION_ONLY, true);
+        PostProcessPipeline::new(AiPostProcessSteps::JoinIdenticalVertices)
+            .with_properties(properties)
+            .run(&mut scene);
+        assert_eq!(scene.meshes[0].vertices.len(), 6);
+        assert!(scene.flags.contains(AiSceneFlags::NON_VERBOSE_FORMAT));
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+    }
+}
@@ -1,13 +1,170 @@
-use crate::utils::float_precision::Vec3;
+#[cfg(feature = "mint")]
+use crate::utils::AiReal;
+use crate::{
+    core::simd,
+    utils::float_precision::{Mat4, Vec3},
+};
 
-#[derive(Debug, Clone, Default)]
+/// Axis-aligned bounding box.
+///
+/// A box without any points is represented by [`AABB::EMPTY`], with `min`
+/// above `max`, so it is neutral for [`union`](Self::union).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct AABB {
     pub min: Vec3,
     pub max: Vec3,
 }
 
 impl AABB {
+    /// The box containing nothing
+    pub const EMPTY: Self = Self {
+        min: Vec3::INFINITY,
+        max: Vec3::NEG_INFINITY,
+    };
+
     pub fn new(min: Vec3, max: Vec3) -> Self {
         Self { min, max }
     }
+
+    /// The smallest box containing all points, [`AABB::EMPTY`] for none
+    pub fn from_points(points: &[Vec3]) -> Self {
+        simd::bounds(points)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    /// The smallest box containing this box and `point`
+    pub fn extended(&self, point: Vec3) -> Self {
+        Self::new(self.min.min(point), self.max.max(point))
+    }
+
+    /// The smallest box containing both boxes
+    pub fn union(&self, other: &AABB) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Size of the box along each axis, zero for empty boxes
+    pub fn extent(&self) -> Vec3 {
+        (self.max - self.min).max(Vec3::ZERO)
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// The box of all eight corners after transforming them, which contains
+    /// the transformed content of this box.
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        (0..8).fold(Self::EMPTY, |aabb, corner| {
+            let point = Vec3::select(
+                glam::BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                self.max,
+                self.min,
+            );
+            aabb.extended(transform.transform_point3(point))
+        })
+    }
+}
+
+/// The `min` and `max` corners
+#[cfg(feature = "mint")]
+impl From<AABB> for [mint::Point3<AiReal>; 2] {
+    fn from(aabb: AABB) -> Self {
+        [aabb.min.into(), aabb.max.into()]
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<[mint::Point3<AiReal>; 2]> for AABB {
+    fn from([min, max]: [mint::Point3<AiReal>; 2]) -> Self {
+        Self::new(min.into(), max.into())
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::AABB;
+    use crate::{
+        AiReal,
+        math::{Mat4, Quat, Vec3},
+    };
+
+    #[cfg(feature = "mint")]
+    #[test]
+    fn test_mint_conversions() {
+        let translation = Vec3::new(1.0, 2.0, 3.0);
+        let matrix = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_y(0.5),
+            translation,
+        );
+        let m: mint::ColumnMatrix4<AiReal> = matrix.into();
+        // columns stay columns, the translation is the last one
+        assert_eq!(m.w, mint::Vector4::from([1.0, 2.0, 3.0, 1.0]));
+        assert_eq!(Mat4::from(m), matrix);
+
+        let v: mint::Vector3<AiReal> = translation.into();
+        assert_eq!([v.x, v.y, v.z], [1.0, 2.0, 3.0]);
+        assert_eq!(Vec3::from(v), translation);
+
+        let aabb = AABB::new(-translation, translation);
+        let [min, max]: [mint::Point3<AiReal>; 2] = aabb.into();
+        assert_eq!([min.x, max.z], [-1.0, 3.0]);
+        assert_eq!(AABB::from([min, max]), aabb);
+    }
+
+    #[cfg(feature = "x_file")]
+    #[test]
+    fn test_mesh_bounding_boxes() {
+        use std::fs;
+
+        use crate::{
+            formats::x::importer::Importer,
+            get_model_path,
+            postprocess::{AiPostProcessSteps, pipeline::PostProcessPipeline},
+            traits::importer::trait_define::Importer as _,
+        };
+
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let mut scene = *Importer::read_from_buf(&source).unwrap();
+        for mesh in &scene.meshes {
+            assert_eq!(mesh.aabb, AABB::from_points(&mesh.vertices));
+            assert!(mesh.vertices.iter().all(|&v| mesh.aabb.contains(v)));
+        }
+        let aabb = scene.meshes[0].aabb;
+        assert!(!aabb.is_empty());
+        assert!(AABB::from_points(&[]).is_empty());
+        assert_eq!(AABB::EMPTY.union(&aabb), aabb);
+
+        let moved = aabb.transformed(Mat4::from_translation(Vec3::X));
+        assert_eq!(moved.center(), aabb.center() + Vec3::X);
+        assert_eq!(moved.extent(), aabb.extent());
+        let rotated = aabb.transformed(Mat4::from_rotation_z(
+            core::f64::consts::FRAC_PI_2 as AiReal,
+        ));
+        let extent = aabb.extent();
+        assert!(
+            rotated
+                .extent()
+                .abs_diff_eq(Vec3::new(extent.y, extent.x, extent.z), 1e-5)
+        );
+
+        // the box follows the vertices when converting to left handed
+        PostProcessPipeline::new(AiPostProcessSteps::MakeLeftHanded).run(&mut scene);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.aabb, AABB::from_points(&mesh.vertices));
+
+        scene.meshes[0].vertices.push(Vec3::splat(100.0));
+        PostProcessPipeline::new(AiPostProcessSteps::GenBoundingBoxes).run(&mut scene);
+        assert_eq!(scene.meshes[0].aabb.max, Vec3::splat(100.0));
+    }
 }
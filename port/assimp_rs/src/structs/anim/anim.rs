@@ -18,7 +18,7 @@ pub struct AiMeshKey {
 /** Defines how an animation channel behaves outside the defined time
  *  range. This corresponds to aiNodeAnim::mPreState and
  *  aiNodeAnim::mPostState.*/
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum AiAnimBehaviour {
     /** The value from the default node transformation is taken*/
     #[default]
@@ -0,0 +1,437 @@
+//! Editing of animation clips: trimming, resampling, merging and splitting.
+//!
+//! All times are in ticks of the animation. Keys are sampled without the
+//! wrap around of [`AnimEvaluator`](super::evaluator::AnimEvaluator), times
+//! before the first or after the last key use the value of that key.
+
+use super::{
+    AiAnimation,
+    anim::{AiMeshAnim, AiMeshKey, AiMeshMorphAnim, AiNodeAnim},
+    evaluator::DEFAULT_TICKS_PER_SECOND,
+    interpolate::SampleKey,
+};
+use crate::{
+    AiReal,
+    structs::key::{AiMeshMorphKey, AiQuatKey, AiVectorKey},
+    utils::float_precision::{Quat, Vec3},
+};
+
+/// A named time range of an animation, in ticks
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipMarker {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl ClipMarker {
+    pub fn new(name: impl Into<String>, start: f64, end: f64) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            end,
+        }
+    }
+}
+
+trait Keyframe: Clone {
+    fn time(&self) -> f64;
+
+    fn time_mut(&mut self) -> &mut f64;
+
+    /// The key at `time`, which is between `self` and `next`. Keys without
+    /// interpolation keep the value of `self`.
+    fn between(&self, _next: &Self, time: f64) -> Self {
+        let mut key = self.clone();
+        *key.time_mut() = time;
+        key
+    }
+}
+
+impl Keyframe for AiVectorKey {
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn time_mut(&mut self) -> &mut f64 {
+        &mut self.time
+    }
+
+    /// Keys cutting a cubic spline keep the tangents of `self`, so the cut
+    /// segments only approximate the original curve.
+    fn between(&self, next: &Self, time: f64) -> Self {
+        let span = next.time - self.time;
+        let factor = ((time - self.time) / span) as AiReal;
+        Self {
+            time,
+            value: self.sample(next, factor, span),
+            ..*self
+        }
+    }
+}
+
+impl Keyframe for AiQuatKey {
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn time_mut(&mut self) -> &mut f64 {
+        &mut self.time
+    }
+
+    fn between(&self, next: &Self, time: f64) -> Self {
+        let span = next.time - self.time;
+        let factor = ((time - self.time) / span) as AiReal;
+        Self {
+            time,
+            value: self.sample(next, factor, span),
+            ..*self
+        }
+    }
+}
+
+impl Keyframe for AiMeshKey {
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn time_mut(&mut self) -> &mut f64 {
+        &mut self.time
+    }
+}
+
+impl Keyframe for AiMeshMorphKey {
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn time_mut(&mut self) -> &mut f64 {
+        &mut self.time
+    }
+}
+
+/// The key at `time`, keys must be sorted by time. If several keys have
+/// this time, the first one is used for `end` and the last one otherwise,
+/// so the ends of clips joined by [`AiAnimation::merge`] stay apart.
+fn key_at<K: Keyframe>(keys: &[K], time: f64, end: bool) -> Option<K> {
+    let next = keys.partition_point(|key| key.time() < time);
+    let mut key = match keys.get(next) {
+        Some(key) if end && key.time() == time => key.clone(),
+        _ => {
+            let next = keys.partition_point(|key| key.time() <= time);
+            match (next.checked_sub(1), keys.get(next)) {
+                (Some(previous), Some(next)) => keys[previous].between(next, time),
+                (Some(previous), None) => keys[previous].clone(),
+                (None, _) => keys.first()?.clone(),
+            }
+        }
+    };
+    *key.time_mut() = time;
+    Some(key)
+}
+
+/// The keys between `start` and `end`, starting at time 0. Keys are
+/// inserted at both ends, so the values at the ends do not change.
+fn trim_keys<K: Keyframe>(keys: &[K], start: f64, end: f64) -> Vec<K> {
+    let Some(first) = key_at(keys, start, false) else {
+        return Vec::new();
+    };
+    let mut result = vec![first];
+    result.extend(
+        keys.iter()
+            .filter(|key| key.time() > start && key.time() < end)
+            .cloned(),
+    );
+    if end > start {
+        result.extend(key_at(keys, end, true));
+    }
+    for key in result.iter_mut() {
+        *key.time_mut() -= start;
+    }
+    result
+}
+
+/// Appends `keys` with their times scaled by `scale` and moved by `offset`
+fn append_keys<K: Keyframe>(target: &mut Vec<K>, keys: &[K], scale: f64, offset: f64) {
+    target.extend(keys.iter().map(|key| {
+        let mut key = key.clone();
+        *key.time_mut() = key.time() * scale + offset;
+        key
+    }));
+}
+
+impl AiAnimation {
+    /// The ticks per second, or [`DEFAULT_TICKS_PER_SECOND`] if they are not
+    /// specified
+    pub fn ticks_per_second_or_default(&self) -> f64 {
+        if self.ticks_per_second != 0.0 {
+            self.ticks_per_second
+        } else {
+            DEFAULT_TICKS_PER_SECOND
+        }
+    }
+
+    /// The part of the animation between `start` and `end`, moved to start
+    /// at time 0. Channels get keys at both ends of the range.
+    pub fn trimmed(&self, start: f64, end: f64) -> Self {
+        let end = end.max(start);
+        Self {
+            name: self.name.clone(),
+            duration: end - start,
+            ticks_per_second: self.ticks_per_second,
+            channels: self
+                .channels
+                .iter()
+                .map(|channel| AiNodeAnim {
+                    node_name: channel.node_name.clone(),
+                    position_keys: trim_keys(&channel.position_keys, start, end),
+                    rotation_keys: trim_keys(&channel.rotation_keys, start, end),
+                    scaling_keys: trim_keys(&channel.scaling_keys, start, end),
+                    pre_state: channel.pre_state,
+                    post_state: channel.post_state,
+                })
+                .collect(),
+            mesh_channels: self
+                .mesh_channels
+                .iter()
+                .map(|channel| AiMeshAnim {
+                    name: channel.name.clone(),
+                    key_frames: trim_keys(&channel.key_frames, start, end),
+                })
+                .collect(),
+            morph_mesh_channels: self
+                .morph_mesh_channels
+                .iter()
+                .map(|channel| AiMeshMorphAnim {
+                    name: channel.name.clone(),
+                    key_frames: trim_keys(&channel.key_frames, start, end),
+                })
+                .collect(),
+        }
+    }
+
+    /// Replaces the keys of all node channels by linear keys sampled
+    /// `frames_per_second` times per second, from time 0 to the duration.
+    /// Mesh channels switch between discrete states and are kept.
+    pub fn resample(&mut self, frames_per_second: f64) {
+        if frames_per_second <= 0.0 || !frames_per_second.is_finite() {
+            return;
+        }
+        let step = self.ticks_per_second_or_default() / frames_per_second;
+        let count = (self.duration / step).ceil().max(0.0) as usize;
+        let times = (0..=count)
+            .map(|i| (i as f64 * step).min(self.duration))
+            .collect::<Vec<_>>();
+        for channel in self.channels.iter_mut() {
+            let sample = |keys: &[AiVectorKey]| {
+                times
+                    .iter()
+                    .filter_map(|&time| key_at(keys, time, false))
+                    .map(|key| AiVectorKey::new(key.time, key.value))
+                    .collect()
+            };
+            channel.position_keys = sample(&channel.position_keys);
+            channel.scaling_keys = sample(&channel.scaling_keys);
+            channel.rotation_keys = times
+                .iter()
+                .filter_map(|&time| key_at(&channel.rotation_keys, time, false))
+                .map(|key| AiQuatKey::new(key.time, key.value))
+                .collect();
+        }
+    }
+
+    /// Plays `clips` one after another in a single animation and returns
+    /// it with the time range of every clip. Channels of the same node or
+    /// mesh are joined, times are converted to the tick rate of the first
+    /// clip.
+    pub fn merge(name: impl Into<String>, clips: &[AiAnimation]) -> (Self, Vec<ClipMarker>) {
+        let ticks_per_second = clips.first().map_or(0.0, |clip| clip.ticks_per_second);
+        let mut merged = Self {
+            name: name.into(),
+            ticks_per_second,
+            ..Default::default()
+        };
+        let target_rate = merged.ticks_per_second_or_default();
+        let mut markers = Vec::with_capacity(clips.len());
+        for clip in clips {
+            let scale = target_rate / clip.ticks_per_second_or_default();
+            let offset = merged.duration;
+            for channel in &clip.channels {
+                let index = match merged
+                    .channels
+                    .iter()
+                    .position(|c| c.node_name == channel.node_name)
+                {
+                    Some(index) => index,
+                    None => {
+                        merged.channels.push(AiNodeAnim {
+                            node_name: channel.node_name.clone(),
+                            pre_state: channel.pre_state,
+                            post_state: channel.post_state,
+                            ..Default::default()
+                        });
+                        merged.channels.len() - 1
+                    }
+                };
+                let target = &mut merged.channels[index];
+                append_keys(
+                    &mut target.position_keys,
+                    &channel.position_keys,
+                    scale,
+                    offset,
+                );
+                append_keys(
+                    &mut target.rotation_keys,
+                    &channel.rotation_keys,
+                    scale,
+                    offset,
+                );
+                append_keys(
+                    &mut target.scaling_keys,
+                    &channel.scaling_keys,
+                    scale,
+                    offset,
+                );
+            }
+            for channel in &clip.mesh_channels {
+                let index = match merged
+                    .mesh_channels
+                    .iter()
+                    .position(|c| c.name == channel.name)
+                {
+                    Some(index) => index,
+                    None => {
+                        merged.mesh_channels.push(AiMeshAnim {
+                            name: channel.name.clone(),
+                            ..Default::default()
+                        });
+                        merged.mesh_channels.len() - 1
+                    }
+                };
+                let target = &mut merged.mesh_channels[index].key_frames;
+                append_keys(target, &channel.key_frames, scale, offset);
+            }
+            for channel in &clip.morph_mesh_channels {
+                let index = match merged
+                    .morph_mesh_channels
+                    .iter()
+                    .position(|c| c.name == channel.name)
+                {
+                    Some(index) => index,
+                    None => {
+                        merged.morph_mesh_channels.push(AiMeshMorphAnim {
+                            name: channel.name.clone(),
+                            ..Default::default()
+                        });
+                        merged.morph_mesh_channels.len() - 1
+                    }
+                };
+                let target = &mut merged.morph_mesh_channels[index].key_frames;
+                append_keys(target, &channel.key_frames, scale, offset);
+            }
+            merged.duration += clip.duration * scale;
+            markers.push(ClipMarker::new(clip.name.clone(), offset, merged.duration));
+        }
+        (merged, markers)
+    }
+
+    /// Splits the animation into one clip per marker, see [`Self::trimmed`]
+    pub fn split(&self, markers: &[ClipMarker]) -> Vec<Self> {
+        markers
+            .iter()
+            .map(|marker| {
+                let mut clip = self.trimmed(marker.start, marker.end);
+                clip.name.clone_from(&marker.name);
+                clip
+            })
+            .collect()
+    }
+}
+
+impl AiNodeAnim {
+    /// The position, rotation and scaling at `time`, interpolated between
+    /// the keys like [`AiAnimation::resample`] does. Missing parts are
+    /// `None` if the channel has no keys for them.
+    pub fn sample(&self, time: f64) -> (Option<Vec3>, Option<Quat>, Option<Vec3>) {
+        (
+            key_at(&self.position_keys, time, false).map(|key| key.value),
+            key_at(&self.rotation_keys, time, false).map(|key| key.value),
+            key_at(&self.scaling_keys, time, false).map(|key| key.value),
+        )
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::ClipMarker;
+    use crate::{
+        structs::{
+            anim::{AiAnimation, anim::AiNodeAnim},
+            key::{AiQuatKey, AiVectorKey},
+        },
+        utils::float_precision::{Quat, Vec3},
+    };
+
+    #[test]
+    fn test_animation_clips() {
+        let walk = AiAnimation {
+            name: "walk".into(),
+            duration: 10.0,
+            ticks_per_second: 10.0,
+            channels: vec![AiNodeAnim {
+                node_name: "hip".into(),
+                position_keys: vec![
+                    AiVectorKey::new(0.0, Vec3::ZERO),
+                    AiVectorKey::new(10.0, Vec3::new(10.0, 0.0, 0.0)),
+                ],
+                rotation_keys: vec![AiQuatKey::new(0.0, Quat::IDENTITY)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let trimmed = walk.trimmed(2.5, 5.0);
+        assert_eq!(trimmed.duration, 2.5);
+        let keys = &trimmed.channels[0].position_keys;
+        assert_eq!(keys.iter().map(|k| k.time).collect::<Vec<_>>(), [0.0, 2.5]);
+        assert_eq!(keys[0].value, Vec3::new(2.5, 0.0, 0.0));
+        assert_eq!(keys[1].value, Vec3::new(5.0, 0.0, 0.0));
+        assert_eq!(trimmed.channels[0].rotation_keys.len(), 2);
+
+        // 4 frames per second are 2.5 ticks per frame
+        let mut resampled = walk.clone();
+        resampled.resample(4.0);
+        let keys = &resampled.channels[0].position_keys;
+        assert_eq!(
+            keys.iter().map(|k| k.time).collect::<Vec<_>>(),
+            [0.0, 2.5, 5.0, 7.5, 10.0]
+        );
+        assert_eq!(keys[3].value, Vec3::new(7.5, 0.0, 0.0));
+
+        // the second clip runs at half the rate
+        let run = AiAnimation {
+            name: "run".into(),
+            ticks_per_second: 5.0,
+            ..walk.clone()
+        };
+        let (merged, markers) = AiAnimation::merge("all", &[walk.clone(), run]);
+        assert_eq!(merged.duration, 30.0);
+        assert_eq!(merged.channels.len(), 1);
+        assert_eq!(
+            markers,
+            [
+                ClipMarker::new("walk", 0.0, 10.0),
+                ClipMarker::new("run", 10.0, 30.0)
+            ]
+        );
+        let clips = merged.split(&markers);
+        assert_eq!(clips[0].name, "walk");
+        assert_eq!(
+            clips[0].channels[0].position_keys,
+            walk.channels[0].position_keys
+        );
+        assert_eq!(clips[1].duration, 20.0);
+        let key = clips[1].channels[0].position_keys.last().unwrap();
+        assert_eq!((key.time, key.value), (20.0, Vec3::new(10.0, 0.0, 0.0)));
+    }
+}
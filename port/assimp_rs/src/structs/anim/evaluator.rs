@@ -0,0 +1,234 @@
+use super::{AiAnimation, interpolate::SampleKey};
+use crate::{
+    AiReal,
+    utils::float_precision::{Mat4, Quat, Vec3},
+};
+
+/// Ticks per second assumed if the animation does not specify them
+pub const DEFAULT_TICKS_PER_SECOND: f64 = 25.0;
+
+/// Calculates the local transformations of all channels of an animation for a
+/// given point in time.
+///
+/// The last used key frame of every channel is remembered, so evaluating with
+/// steadily increasing times only has to look at a few keys per channel.
+#[derive(Debug, Clone)]
+pub struct AnimEvaluator<'a> {
+    animation: &'a AiAnimation,
+    last_time: f64,
+    last_positions: Vec<(usize, usize, usize)>,
+    transforms: Vec<Mat4>,
+}
+
+impl<'a> AnimEvaluator<'a> {
+    pub fn new(animation: &'a AiAnimation) -> Self {
+        let len = animation.channels.len();
+        Self {
+            animation,
+            last_time: 0.0,
+            last_positions: vec![(0, 0, 0); len],
+            transforms: vec![Mat4::IDENTITY; len],
+        }
+    }
+
+    pub fn animation(&self) -> &'a AiAnimation {
+        self.animation
+    }
+
+    /// Evaluates the animation tracks for a given time in seconds. Times
+    /// beyond the duration of the animation wrap around.
+    pub fn evaluate(&mut self, time: f64) {
+        let animation = self.animation;
+        let ticks_per_second = animation.ticks_per_second_or_default();
+        let duration = animation.duration;
+        // every following time calculation happens in ticks
+        let time = if duration > 0.0 {
+            (time * ticks_per_second) % duration
+        } else {
+            0.0
+        };
+
+        for ((channel, last), transform) in animation
+            .channels
+            .iter()
+            .zip(self.last_positions.iter_mut())
+            .zip(self.transforms.iter_mut())
+        {
+            let mut position = Vec3::ZERO;
+            if let Some((frame, value)) = Self::sample(
+                &channel.position_keys,
+                time,
+                self.last_time,
+                last.0,
+                duration,
+            ) {
+                position = value;
+                last.0 = frame;
+            }
+
+            let mut rotation = Quat::IDENTITY;
+            if let Some((frame, value)) = Self::sample(
+                &channel.rotation_keys,
+                time,
+                self.last_time,
+                last.1,
+                duration,
+            ) {
+                rotation = value;
+                last.1 = frame;
+            }
+
+            let mut scaling = Vec3::ONE;
+            if let Some((frame, value)) = Self::sample(
+                &channel.scaling_keys,
+                time,
+                self.last_time,
+                last.2,
+                duration,
+            ) {
+                scaling = value;
+                last.2 = frame;
+            }
+
+            // Matrices are stored row by row like the node transformations
+            *transform =
+                Mat4::from_scale_rotation_translation(scaling, rotation, position).transpose();
+        }
+        self.last_time = time;
+    }
+
+    /// The local transformations of all channels, in the order of
+    /// [`AiAnimation::channels`]. Valid after [`Self::evaluate`] was called.
+    pub fn transforms(&self) -> &[Mat4] {
+        &self.transforms
+    }
+
+    /// Finds the key frame for `time`, starting at the last used frame if
+    /// the time did not go backwards.
+    fn find_frame(
+        times: impl Iterator<Item = f64>,
+        time: f64,
+        last_time: f64,
+        last: usize,
+    ) -> usize {
+        let start = if time >= last_time { last } else { 0 };
+        let mut frame = start;
+        for next in times.skip(start + 1) {
+            if time < next {
+                break;
+            }
+            frame += 1;
+        }
+        frame
+    }
+
+    /// Returns the interpolation factor between two keys and the time
+    /// between them, the next key wraps around to the first one at the end
+    /// of the animation.
+    fn factor(time: f64, key_time: f64, next_time: f64, duration: f64) -> Option<(AiReal, f64)> {
+        let mut diff_time = next_time - key_time;
+        if diff_time < 0.0 {
+            diff_time += duration;
+        }
+        (diff_time > 0.0).then(|| (((time - key_time) / diff_time) as AiReal, diff_time))
+    }
+
+    fn sample<K: SampleKey>(
+        keys: &[K],
+        time: f64,
+        last_time: f64,
+        last: usize,
+        duration: f64,
+    ) -> Option<(usize, K::Value)> {
+        if keys.is_empty() {
+            return None;
+        }
+        let frame = Self::find_frame(keys.iter().map(|k| k.time()), time, last_time, last);
+        let key = &keys[frame];
+        let next = &keys[(frame + 1) % keys.len()];
+        let value = match Self::factor(time, key.time(), next.time(), duration) {
+            Some((factor, span)) => key.sample(next, factor, span),
+            None => key.value(),
+        };
+        Some((frame, value))
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::AnimEvaluator;
+    use crate::{
+        structs::{
+            anim::{AiAnimInterpolation, AiAnimation, anim::AiNodeAnim, interpolate::Interpolate},
+            key::{AiQuatKey, AiVectorKey},
+        },
+        utils::float_precision::{Quat, Vec3},
+    };
+
+    #[test]
+    fn test_key_interpolation() {
+        let animation = AiAnimation {
+            duration: 40.0,
+            ticks_per_second: 1.0,
+            channels: vec![AiNodeAnim {
+                node_name: "node".into(),
+                position_keys: vec![
+                    AiVectorKey::new(0.0, Vec3::ZERO).with_interpolation(AiAnimInterpolation::Step),
+                    AiVectorKey::new(10.0, Vec3::new(10.0, 0.0, 0.0)),
+                    AiVectorKey::cubic_spline(20.0, Vec3::new(20.0, 0.0, 0.0), Vec3::ZERO, Vec3::Y),
+                    AiVectorKey::cubic_spline(
+                        30.0,
+                        Vec3::new(30.0, 0.0, 0.0),
+                        -Vec3::Y,
+                        Vec3::ZERO,
+                    ),
+                ],
+                rotation_keys: vec![
+                    AiQuatKey::new(0.0, Quat::IDENTITY)
+                        .with_interpolation(AiAnimInterpolation::Step),
+                    AiQuatKey::new(10.0, Quat::IDENTITY)
+                        .with_interpolation(AiAnimInterpolation::SphericalLinear),
+                    AiQuatKey::new(20.0, Quat::from_rotation_z(1.0)),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut evaluator = AnimEvaluator::new(&animation);
+        let mut sample = |time| {
+            evaluator.evaluate(time);
+            // the transformations are stored row by row
+            let (_, rotation, position) = evaluator.transforms()[0]
+                .transpose()
+                .to_scale_rotation_translation();
+            (position, rotation)
+        };
+
+        let (position, rotation) = sample(5.0);
+        assert_eq!(position, Vec3::ZERO);
+        assert!(rotation.abs_diff_eq(Quat::IDENTITY, 1e-6));
+        let (position, rotation) = sample(15.0);
+        assert!(position.abs_diff_eq(Vec3::new(15.0, 0.0, 0.0), 1e-5));
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_z(0.5), 1e-5));
+        let (position, _) = sample(20.0);
+        assert!(position.abs_diff_eq(Vec3::new(20.0, 0.0, 0.0), 1e-5));
+        // both tangents point away from the x axis, scaled by the 10 ticks
+        let (position, _) = sample(25.0);
+        assert!(position.abs_diff_eq(Vec3::new(25.0, 2.5, 0.0), 1e-4));
+
+        let mut key = AiVectorKey::default();
+        let a =
+            AiVectorKey::new(0.0, Vec3::X).with_interpolation(AiAnimInterpolation::SphericalLinear);
+        key.interpolate(a, AiVectorKey::new(1.0, Vec3::Y), 0.5);
+        assert!(
+            key.value
+                .abs_diff_eq(Vec3::new(0.5, 0.5, 0.0).normalize(), 1e-5)
+        );
+        key.interpolate(
+            a.with_interpolation(AiAnimInterpolation::Linear),
+            AiVectorKey::new(1.0, Vec3::Y),
+            0.5,
+        );
+        assert!(key.value.abs_diff_eq(Vec3::new(0.5, 0.5, 0.0), 1e-6));
+    }
+}
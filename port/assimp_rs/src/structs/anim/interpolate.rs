@@ -1,8 +1,10 @@
-use super::anim::AiMeshKey;
+use core::ops::{Add, Mul};
+
+use super::{AiAnimInterpolation, anim::AiMeshKey};
 use crate::{
     AiReal,
     structs::key::{AiQuatKey, AiVectorKey},
-    utils::float_precision::{Quat, Vec3},
+    utils::float_precision::{Quat, Vec3, Vec4},
 };
 pub trait Interpolate {
     fn interpolate(&mut self, a: Self, b: Self, d: AiReal);
@@ -29,17 +31,19 @@ impl Interpolate for Vec3 {
     }
 }
 
+/// Interpolates according to the interpolation setting of `a`
 impl Interpolate for AiVectorKey {
     #[inline]
     fn interpolate(&mut self, a: Self, b: Self, d: AiReal) {
-        self.value.interpolate(a.value, b.value, d);
+        self.value = a.sample(&b, d, b.time - a.time);
     }
 }
 
+/// Interpolates according to the interpolation setting of `a`
 impl Interpolate for AiQuatKey {
     #[inline]
     fn interpolate(&mut self, a: Self, b: Self, d: AiReal) {
-        self.value.interpolate(a.value, b.value, d);
+        self.value = a.sample(&b, d, b.time - a.time);
     }
 }
 
@@ -49,3 +53,97 @@ impl Interpolate for AiMeshKey {
         self.value.interpolate(a.value, b.value, d);
     }
 }
+
+/// Key frames which are interpolated according to their
+/// [`AiAnimInterpolation`]
+pub trait SampleKey {
+    type Value;
+
+    fn time(&self) -> f64;
+
+    fn value(&self) -> Self::Value;
+
+    /// The value between `self` and `next` at `factor` in `[0, 1]`, `span`
+    /// is the time between both keys in ticks. The interpolation setting of
+    /// `self` is used for the whole segment.
+    fn sample(&self, next: &Self, factor: AiReal, span: f64) -> Self::Value;
+}
+
+/// Cubic Hermite spline between `p0` and `p1`, with the tangents already
+/// scaled to the length of the segment
+fn hermite<T>(p0: T, m0: T, p1: T, m1: T, t: AiReal) -> T
+where
+    T: Add<Output = T> + Mul<AiReal, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    p0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+        + m0 * (t3 - 2.0 * t2 + t)
+        + p1 * (-2.0 * t3 + 3.0 * t2)
+        + m1 * (t3 - t2)
+}
+
+impl SampleKey for AiVectorKey {
+    type Value = Vec3;
+
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn value(&self) -> Vec3 {
+        self.value
+    }
+
+    fn sample(&self, next: &Self, factor: AiReal, span: f64) -> Vec3 {
+        match self.interpolation {
+            AiAnimInterpolation::Step => self.value,
+            AiAnimInterpolation::Linear => self.value.lerp(next.value, factor),
+            AiAnimInterpolation::SphericalLinear => self.value.slerp(next.value, factor),
+            AiAnimInterpolation::CubicSpline => {
+                let span = span as AiReal;
+                hermite(
+                    self.value,
+                    self.out_tangent * span,
+                    next.value,
+                    next.in_tangent * span,
+                    factor,
+                )
+            }
+        }
+    }
+}
+
+impl SampleKey for AiQuatKey {
+    type Value = Quat;
+
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn value(&self) -> Quat {
+        self.value
+    }
+
+    /// Rotations are interpolated on the sphere for both linear settings,
+    /// like assimp does. Cubic splines are evaluated per component and
+    /// normalized afterwards, as defined by glTF.
+    fn sample(&self, next: &Self, factor: AiReal, span: f64) -> Quat {
+        match self.interpolation {
+            AiAnimInterpolation::Step => self.value,
+            AiAnimInterpolation::Linear | AiAnimInterpolation::SphericalLinear => {
+                self.value.slerp(next.value, factor)
+            }
+            AiAnimInterpolation::CubicSpline => {
+                let span = span as AiReal;
+                let value = hermite(
+                    Vec4::from(self.value),
+                    self.out_tangent * span,
+                    Vec4::from(next.value),
+                    next.in_tangent * span,
+                    factor,
+                );
+                Quat::from_vec4(value).normalize()
+            }
+        }
+    }
+}
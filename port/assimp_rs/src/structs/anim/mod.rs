@@ -1,7 +1,11 @@
 use anim::{AiMeshAnim, AiMeshMorphAnim, AiNodeAnim};
 
 pub mod anim;
+pub mod clip;
+pub mod evaluator;
 pub mod interpolate;
+pub mod morph;
+pub mod skinning;
 
 #[derive(Debug, Clone, Default)]
 pub struct AiAnimation {
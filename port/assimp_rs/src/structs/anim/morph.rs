@@ -0,0 +1,228 @@
+use super::{AiAnimation, anim::AiMeshMorphAnim};
+use crate::{
+    AiReal,
+    structs::mesh::{AiMesh, AnimMesh, MorphingMethod},
+    utils::float_precision::Vec3,
+};
+
+/// Calculates the weights of the morph targets of all morph mesh channels
+/// of an animation for a given point in time.
+///
+/// Weights are interpolated linearly between two keys, a target missing in
+/// one of them has the weight 0 there.
+#[derive(Debug, Clone)]
+pub struct MorphEvaluator<'a> {
+    animation: &'a AiAnimation,
+    weights: Vec<Vec<(u32, AiReal)>>,
+}
+
+impl<'a> MorphEvaluator<'a> {
+    pub fn new(animation: &'a AiAnimation) -> Self {
+        Self {
+            animation,
+            weights: vec![Vec::new(); animation.morph_mesh_channels.len()],
+        }
+    }
+
+    pub fn animation(&self) -> &'a AiAnimation {
+        self.animation
+    }
+
+    /// Evaluates the morph channels for a given time in seconds. Times
+    /// beyond the duration of the animation wrap around.
+    pub fn evaluate(&mut self, time: f64) {
+        let animation = self.animation;
+        let duration = animation.duration;
+        let time = if duration > 0.0 {
+            (time * animation.ticks_per_second_or_default()) % duration
+        } else {
+            0.0
+        };
+        for (channel, weights) in animation
+            .morph_mesh_channels
+            .iter()
+            .zip(self.weights.iter_mut())
+        {
+            Self::sample(channel, time, weights);
+        }
+    }
+
+    /// The anim mesh indices and weights of a channel, in the order of
+    /// [`AiAnimation::morph_mesh_channels`]. Valid after [`Self::evaluate`]
+    /// was called.
+    pub fn weights(&self, channel: usize) -> &[(u32, AiReal)] {
+        self.weights.get(channel).map_or(&[], Vec::as_slice)
+    }
+
+    fn sample(channel: &AiMeshMorphAnim, time: f64, weights: &mut Vec<(u32, AiReal)>) {
+        weights.clear();
+        let keys = &channel.key_frames;
+        let next = keys.partition_point(|key| key.time <= time);
+        let (key, next, factor) = match (next.checked_sub(1), keys.get(next)) {
+            (Some(previous), Some(next)) => {
+                let key = &keys[previous];
+                let span = next.time - key.time;
+                (key, Some(next), ((time - key.time) / span) as AiReal)
+            }
+            (Some(previous), None) => (&keys[previous], None, 0.0),
+            (None, Some(first)) => (first, None, 0.0),
+            (None, None) => return,
+        };
+        for (&value, &weight) in key.values.iter().zip(key.weights.iter()) {
+            weights.push((value, weight as AiReal * (1.0 - factor)));
+        }
+        let Some(next) = next else {
+            return;
+        };
+        for (&value, &weight) in next.values.iter().zip(next.weights.iter()) {
+            let weight = weight as AiReal * factor;
+            match weights.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, w)) => *w += weight,
+                None => weights.push((value, weight)),
+            }
+        }
+    }
+}
+
+/// Vertex data of a mesh with its morph targets applied
+#[derive(Debug, Clone, Default)]
+pub struct MorphedMesh {
+    pub vertices: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub tangents: Vec<Vec3>,
+    pub bitangents: Vec<Vec3>,
+}
+
+impl MorphedMesh {
+    /// Blends the anim meshes of `mesh` with the given weights, indices of
+    /// missing anim meshes are ignored. Use [`Self::update_static`] for the
+    /// weights stored in the anim meshes.
+    ///
+    /// With [`MorphingMethod::MorphRelative`] the anim meshes contain
+    /// offsets which are added to the mesh, otherwise they replace the
+    /// vertex data and the mesh gets the remaining weight. For
+    /// [`MorphingMethod::MorphNormalized`] the weights are scaled down if
+    /// they sum up to more than 1.
+    pub fn update(&mut self, mesh: &AiMesh, weights: &[(u32, AiReal)]) {
+        let relative = matches!(mesh.method, MorphingMethod::MorphRelative);
+        let mut scale = 1.0;
+        if matches!(mesh.method, MorphingMethod::MorphNormalized) {
+            let sum = weights.iter().map(|(_, w)| w).sum::<AiReal>();
+            if sum > 1.0 {
+                scale = sum.recip();
+            }
+        }
+        let targets = weights
+            .iter()
+            .filter_map(|&(index, weight)| {
+                let target = mesh.anim_meshes.get(index as usize)?;
+                Some((target, weight * scale))
+            })
+            .collect::<Vec<_>>();
+
+        let blend = |output: &mut Vec<Vec3>, base: &[Vec3], target: fn(&AnimMesh) -> &[Vec3]| {
+            output.clear();
+            output.extend_from_slice(base);
+            for &(anim_mesh, weight) in &targets {
+                let target = target(anim_mesh);
+                if target.len() != base.len() {
+                    continue;
+                }
+                for ((output, base), target) in output.iter_mut().zip(base).zip(target) {
+                    *output += if relative {
+                        *target * weight
+                    } else {
+                        (*target - *base) * weight
+                    };
+                }
+            }
+        };
+        blend(&mut self.vertices, &mesh.vertices, |m| &m.vertices);
+        blend(&mut self.normals, &mesh.normals, |m| &m.normals);
+        blend(&mut self.tangents, &mesh.tangents, |m| &m.tangents);
+        blend(&mut self.bitangents, &mesh.bitangents, |m| &m.bitangents);
+        for direction in self
+            .normals
+            .iter_mut()
+            .chain(self.tangents.iter_mut())
+            .chain(self.bitangents.iter_mut())
+        {
+            *direction = direction.normalize_or_zero();
+        }
+    }
+
+    /// Blends the anim meshes of `mesh` with their own weights
+    pub fn update_static(&mut self, mesh: &AiMesh) {
+        let weights = (0..)
+            .zip(mesh.anim_meshes.iter())
+            .map(|(index, anim_mesh)| (index, anim_mesh.weight as AiReal))
+            .collect::<Vec<_>>();
+        self.update(mesh, &weights);
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{MorphEvaluator, MorphedMesh};
+    use crate::{
+        structs::{
+            anim::{AiAnimation, anim::AiMeshMorphAnim},
+            key::AiMeshMorphKey,
+            mesh::{AiMesh, AnimMesh, MorphingMethod},
+        },
+        utils::float_precision::Vec3,
+    };
+
+    #[test]
+    fn test_morph_targets() {
+        let target = |vertex: Vec3, weight| AnimMesh {
+            vertices: Box::new([vertex, Vec3::ONE]),
+            weight,
+            ..Default::default()
+        };
+        let mut mesh = AiMesh {
+            vertices: vec![Vec3::ZERO, Vec3::ONE],
+            anim_meshes: vec![target(Vec3::X, 1.0), target(Vec3::Y, 0.5)],
+            ..Default::default()
+        };
+        let key = |time, values: &[u32], weights: &[f64]| AiMeshMorphKey {
+            time,
+            values: values.into(),
+            weights: weights.into(),
+        };
+        let animation = AiAnimation {
+            duration: 20.0,
+            ticks_per_second: 1.0,
+            morph_mesh_channels: vec![AiMeshMorphAnim {
+                name: "mesh".into(),
+                key_frames: vec![key(0.0, &[0], &[1.0]), key(10.0, &[1], &[1.0])],
+            }],
+            ..Default::default()
+        };
+        let mut evaluator = MorphEvaluator::new(&animation);
+        evaluator.evaluate(5.0);
+        assert_eq!(evaluator.weights(0), [(0, 0.5), (1, 0.5)]);
+        evaluator.evaluate(15.0);
+        assert_eq!(evaluator.weights(0), [(1, 1.0)]);
+
+        let mut morphed = MorphedMesh::default();
+        morphed.update(&mesh, &[(0, 0.5), (1, 0.5)]);
+        assert_eq!(morphed.vertices, [Vec3::new(0.5, 0.5, 0.0), Vec3::ONE]);
+        morphed.update_static(&mesh);
+        assert_eq!(morphed.vertices[0], Vec3::new(1.0, 0.5, 0.0));
+        assert!(morphed.normals.is_empty());
+
+        mesh.method = MorphingMethod::MorphNormalized;
+        morphed.update_static(&mesh);
+        let third = 1.0 / 3.0;
+        assert!(morphed.vertices[0].abs_diff_eq(Vec3::new(2.0 * third, third, 0.0), 1e-6));
+
+        // relative targets are offsets
+        mesh.method = MorphingMethod::MorphRelative;
+        morphed.update(&mesh, &[(0, 0.5), (7, 1.0)]);
+        assert_eq!(
+            morphed.vertices,
+            [Vec3::new(0.5, 0.0, 0.0), Vec3::splat(1.5)]
+        );
+    }
+}
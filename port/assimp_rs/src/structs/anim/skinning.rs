@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use super::evaluator::AnimEvaluator;
+use crate::{
+    structs::{
+        mesh::AiMesh,
+        nodes::Index,
+        scene::{AiNode, AiScene},
+    },
+    utils::float_precision::Mat4,
+};
+
+/// Computes the bone matrices used for GPU skinning.
+///
+/// The palette caches the mapping from node names to node indices, so bones
+/// and animation channels only have to be resolved by name once. After
+/// [`Self::update`] the global transformation of every node is known and
+/// [`Self::bone_matrices`] returns `global * offset` for every bone of a mesh,
+/// in the same order as [`AiMesh::bones`].
+///
+/// All matrices use the same layout as [`AiNode::transformation`].
+#[derive(Debug, Clone, Default)]
+pub struct SkinningPalette {
+    node_by_name: HashMap<String, Index<AiNode>>,
+    locals: Vec<Mat4>,
+    globals: Vec<Mat4>,
+}
+
+impl SkinningPalette {
+    /// Builds the name to node mapping of `scene` and computes the global
+    /// transformations of the bind pose.
+    pub fn new(scene: &AiScene) -> Self {
+        let mut node_by_name = HashMap::with_capacity(scene.nodes.len());
+        for (i, node) in scene.nodes.iter().enumerate() {
+            // The first node wins if names are not unique
+            node_by_name
+                .entry(node.name.clone())
+                .or_insert(Index::new(i as u32));
+        }
+        let mut palette = Self {
+            node_by_name,
+            locals: Vec::with_capacity(scene.nodes.len()),
+            globals: vec![Mat4::IDENTITY; scene.nodes.len()],
+        };
+        palette.reset(scene);
+        palette
+    }
+
+    /// Returns the cached node index for the given name.
+    pub fn node_index(&self, name: &str) -> Option<Index<AiNode>> {
+        self.node_by_name.get(name).copied()
+    }
+
+    /// Returns the global transformation of a node, as computed by the last
+    /// update.
+    pub fn global_transform(&self, node: Index<AiNode>) -> Option<Mat4> {
+        self.globals.get(node.value()).copied()
+    }
+
+    /// Restores the bind pose, i.e. the node transformations of the scene.
+    pub fn reset(&mut self, scene: &AiScene) {
+        self.locals.clear();
+        self.locals
+            .extend(scene.nodes.iter().map(|node| node.transformation));
+        self.calculate_globals(scene);
+    }
+
+    /// Evaluates the animation at `time` (in seconds) and updates the global
+    /// transformations of all nodes. Nodes without an animation channel keep
+    /// their own transformation.
+    pub fn update(&mut self, scene: &AiScene, evaluator: &mut AnimEvaluator, time: f64) {
+        evaluator.evaluate(time);
+        self.locals.clear();
+        self.locals
+            .extend(scene.nodes.iter().map(|node| node.transformation));
+        for (channel, transform) in evaluator
+            .animation()
+            .channels
+            .iter()
+            .zip(evaluator.transforms())
+        {
+            if let Some(local) = self
+                .node_index(&channel.node_name)
+                .and_then(|index| self.locals.get_mut(index.value()))
+            {
+                *local = *transform;
+            }
+        }
+        self.calculate_globals(scene);
+    }
+
+    /// Returns the skinning matrices of all bones of `mesh`. Bones which do
+    /// not refer to a node of the scene get their offset matrix only.
+    pub fn bone_matrices(&self, mesh: &AiMesh) -> Vec<Mat4> {
+        let mut matrices = Vec::with_capacity(mesh.bones.len());
+        self.bone_matrices_into(mesh, &mut matrices);
+        matrices
+    }
+
+    /// Like [`Self::bone_matrices`], but reuses the given buffer.
+    pub fn bone_matrices_into(&self, mesh: &AiMesh, matrices: &mut Vec<Mat4>) {
+        matrices.clear();
+        matrices.extend(mesh.bones.iter().map(|bone| {
+            let global = self
+                .node_index(&bone.name)
+                .and_then(|index| self.global_transform(index))
+                .unwrap_or(Mat4::IDENTITY);
+            // Row layout, so this is `global * offset`
+            bone.offset_matrix * global
+        }));
+    }
+
+    fn calculate_globals(&mut self, scene: &AiScene) {
+        self.globals.resize(scene.nodes.len(), Mat4::IDENTITY);
+        let Some(root) = scene.root else {
+            return;
+        };
+        // Guard against malformed hierarchies referencing a node twice
+        let mut visited = vec![false; scene.nodes.len()];
+        let mut stack = vec![(root, Mat4::IDENTITY)];
+        while let Some((index, parent)) = stack.pop() {
+            let Some(node) = scene.get_node_by_index(index) else {
+                continue;
+            };
+            if core::mem::replace(&mut visited[index.value()], true) {
+                continue;
+            }
+            let global = self.locals[index.value()] * parent;
+            self.globals[index.value()] = global;
+            stack.extend(node.children.iter().map(|child| (*child, global)));
+        }
+    }
+}
+
+impl AiScene {
+    /// Reconstructs the bind pose of all skeletons from the offset matrices
+    /// of the bones, e.g. for animations which do not start in the bind pose
+    /// or for retargeting. Returns the local transformation of every node,
+    /// indexed like [`AiScene::nodes`].
+    ///
+    /// The global transformation of a bone node in the bind pose is the
+    /// inverse of its offset matrix in the space of the first node using the
+    /// mesh. Nodes which are no bones, bones of meshes which are not used by
+    /// any node and bones with a singular offset matrix keep their
+    /// transformation. If several meshes share a bone, the first one wins.
+    pub fn bind_pose(&self) -> Vec<Mat4> {
+        let palette = SkinningPalette::new(self);
+        let mut bind_globals = vec![None; self.nodes.len()];
+        for (mesh_index, mesh) in self.meshes.iter().enumerate() {
+            let Some(mesh_global) = self
+                .nodes
+                .iter()
+                .position(|node| node.meshes.contains(&(mesh_index as u32)))
+                .and_then(|node| palette.global_transform(Index::new(node as u32)))
+            else {
+                continue;
+            };
+            for bone in &mesh.bones {
+                let Some(global) = palette
+                    .node_index(&bone.name)
+                    .and_then(|node| bind_globals.get_mut(node.value()))
+                else {
+                    continue;
+                };
+                if global.is_none() && bone.offset_matrix.determinant() != 0.0 {
+                    // Row layout, so this is `mesh_global * offset⁻¹`
+                    *global = Some(bone.offset_matrix.inverse() * mesh_global);
+                }
+            }
+        }
+
+        let mut locals = self
+            .nodes
+            .iter()
+            .map(|node| node.transformation)
+            .collect::<Vec<_>>();
+        let Some(root) = self.root else {
+            return locals;
+        };
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![(root, Mat4::IDENTITY)];
+        while let Some((index, parent)) = stack.pop() {
+            let Some(node) = self.get_node_by_index(index) else {
+                continue;
+            };
+            if core::mem::replace(&mut visited[index.value()], true) {
+                continue;
+            }
+            let global = match bind_globals[index.value()] {
+                Some(global) => {
+                    locals[index.value()] = global * parent.inverse();
+                    global
+                }
+                None => locals[index.value()] * parent,
+            };
+            stack.extend(node.children.iter().map(|child| (*child, global)));
+        }
+        locals
+    }
+
+    /// Replaces the transformations of all nodes by the [`Self::bind_pose`]
+    pub fn apply_bind_pose(&mut self) {
+        let locals = self.bind_pose();
+        for (node, local) in self.nodes.iter_mut().zip(locals) {
+            node.transformation = local;
+        }
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::SkinningPalette;
+    use crate::{
+        structs::{
+            bone::AiBone, face::AiFace, material::AiMaterial, mesh::AiMesh, nodes::Index,
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Quat, Vec3},
+    };
+
+    #[test]
+    fn test_bind_pose() {
+        // matrices in the layout of the nodes
+        let translation = |x, y, z| Mat4::from_translation(Vec3::new(x, y, z)).transpose();
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", translation(1.0, 0.0, 0.0), None);
+        let hip = builder.add_node(
+            "hip",
+            Mat4::from_rotation_translation(Quat::from_rotation_z(0.5), Vec3::Y).transpose(),
+            Some(root),
+        );
+        let knee = builder.add_node("knee", translation(0.0, -1.0, 0.0), Some(hip));
+        builder.add_node("foot", translation(0.0, -1.0, 0.5), Some(knee));
+        let body = builder.add_node("body", translation(0.0, 0.0, 2.0), Some(root));
+        let bone = |name: &str| AiBone {
+            name: name.into(),
+            ..Default::default()
+        };
+        let mesh = builder.add_mesh(AiMesh {
+            vertices: vec![Vec3::ZERO; 3],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2]),
+            }],
+            bones: vec![bone("hip"), bone("knee")],
+            ..Default::default()
+        });
+        builder.attach_mesh(body, mesh);
+        let mut original = builder.build().unwrap();
+        // offsets map from the space of the mesh to the space of the bones
+        let palette = SkinningPalette::new(&original);
+        let global = |node| palette.global_transform(Index::new(node)).unwrap();
+        for (bone, node) in original.meshes[0].bones.iter_mut().zip([1, 2]) {
+            bone.offset_matrix = global(4) * global(node).inverse();
+        }
+
+        let mut scene = original.clone();
+        scene.nodes[1].transformation = Mat4::from_rotation_x(1.0).transpose();
+        scene.nodes[2].transformation = translation(0.0, -2.0, 0.0);
+        scene.nodes[3].transformation = translation(0.0, -3.0, 0.0);
+        scene.apply_bind_pose();
+        let bones = scene.nodes.iter().zip(&original.nodes);
+        for (node, expected) in bones.filter(|(node, _)| node.name != "foot") {
+            assert!(
+                node.transformation
+                    .abs_diff_eq(expected.transformation, 1e-5),
+                "{}",
+                node.name
+            );
+        }
+        // the foot is no bone and keeps its transformation
+        assert_eq!(scene.nodes[3].transformation, translation(0.0, -3.0, 0.0));
+    }
+}
@@ -4,8 +4,8 @@ use crate::utils::float_precision::Mat4;
 #[derive(Debug, Clone, Default)]
 pub struct AiBone {
     pub name: String,
-    pub armature: Index<Node>,
-    pub node: Index<Node>,
+    pub armature: Option<Index<Node>>,
+    pub node: Option<Index<Node>>,
     pub weights: Vec<AiVertexWeight>,
     pub offset_matrix: Mat4,
 }
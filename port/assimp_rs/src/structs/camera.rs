@@ -1,4 +1,7 @@
-use crate::utils::float_precision::Vec3;
+use crate::{
+    AiReal,
+    utils::float_precision::{Mat4, Vec3},
+};
 
 // ---------------------------------------------------------------------------
 /** Helper structure to describe a virtual camera.
@@ -124,3 +127,112 @@ pub struct AiCamera {
      */
     pub orthographic_width: f32,
 }
+
+// the casts are no-ops unless double_precision is enabled
+#[allow(clippy::unnecessary_cast)]
+impl AiCamera {
+    /// Whether the camera has an orthographic projection
+    pub fn is_orthographic(&self) -> bool {
+        self.orthographic_width > 0.0
+    }
+
+    /// Right-handed view matrix looking along `look_at`, which transforms
+    /// from the space of the camera node to view space. Multiply it with
+    /// the inverse global transformation of the node to get the view
+    /// matrix of the scene. Zero `look_at` and `up` vectors are replaced by
+    /// their defaults.
+    pub fn view_matrix(&self) -> Mat4 {
+        let direction = self.look_at.normalize_or(Vec3::Z);
+        let mut up = self.up.normalize_or(Vec3::Y);
+        if direction.cross(up).length_squared() < 1e-12 {
+            up = direction.any_orthonormal_vector();
+        }
+        Mat4::look_to_rh(self.position, direction, up)
+    }
+
+    /// Right-handed projection matrix with a depth range of `[0, 1]`,
+    /// orthographic if [`Self::orthographic_width`] is set and perspective
+    /// otherwise. The aspect ratio of the camera is used unless it is
+    /// overridden, for example by the aspect ratio of the viewport, an
+    /// undefined aspect ratio is treated as 1.
+    pub fn projection_matrix(&self, aspect_override: Option<f32>) -> Mat4 {
+        let aspect = aspect_override
+            .filter(|&aspect| aspect > 0.0)
+            .unwrap_or(self.aspect);
+        let aspect = if aspect > 0.0 { aspect } else { 1.0 } as AiReal;
+        let (near, far) = (
+            self.clip_plane_near as AiReal,
+            self.clip_plane_far as AiReal,
+        );
+        if self.is_orthographic() {
+            let width = self.orthographic_width as AiReal;
+            let height = width / aspect;
+            Mat4::orthographic_rh(-width, width, -height, height, near, far)
+        } else {
+            // the field of view is the horizontal half angle
+            let fov_y = 2.0 * ((self.horizontal_fov as AiReal).tan() / aspect).atan();
+            Mat4::perspective_rh(fov_y, aspect, near, far)
+        }
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::AiCamera;
+    use crate::utils::float_precision::Vec3;
+
+    #[test]
+    fn test_camera_matrices() {
+        let mut camera = AiCamera {
+            position: Vec3::new(1.0, 2.0, 3.0),
+            look_at: Vec3::new(2.0, 0.0, 0.0),
+            up: Vec3::Y,
+            horizontal_fov: core::f32::consts::FRAC_PI_4,
+            clip_plane_near: 1.0,
+            clip_plane_far: 10.0,
+            aspect: 2.0,
+            ..Default::default()
+        };
+        let view = camera.view_matrix();
+        assert!(
+            view.transform_point3(camera.position)
+                .abs_diff_eq(Vec3::ZERO, 1e-6)
+        );
+        assert!(
+            view.transform_point3(camera.position + camera.look_at)
+                .abs_diff_eq(Vec3::new(0.0, 0.0, -2.0), 1e-6)
+        );
+        assert!(view.transform_vector3(Vec3::Y).abs_diff_eq(Vec3::Y, 1e-6));
+
+        // the corners of the near and far planes
+        let projection = camera.projection_matrix(None);
+        assert!(
+            projection
+                .project_point3(Vec3::new(1.0, 0.5, -1.0))
+                .abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-6)
+        );
+        assert!(
+            projection
+                .project_point3(Vec3::new(-10.0, -5.0, -10.0))
+                .abs_diff_eq(Vec3::new(-1.0, -1.0, 1.0), 1e-5)
+        );
+        let projection = camera.projection_matrix(Some(1.0));
+        assert!(
+            projection
+                .project_point3(Vec3::new(1.0, 1.0, -1.0))
+                .abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-6)
+        );
+
+        camera.orthographic_width = 4.0;
+        assert!(camera.is_orthographic());
+        let projection = camera.projection_matrix(None);
+        assert!(
+            projection
+                .project_point3(Vec3::new(4.0, -2.0, -10.0))
+                .abs_diff_eq(Vec3::new(1.0, -1.0, 1.0), 1e-6)
+        );
+
+        // degenerate vectors fall back to the defaults
+        assert!(!AiCamera::default().view_matrix().is_nan());
+    }
+}
@@ -1,5 +1,75 @@
 use glam::{Vec3, Vec4};
 
+use super::meta::{AI_METADATA_COLOR_SPACE, Metadata, MetadataExt};
+
 pub type Color3D = Vec3;
 
 pub type Color4D = Vec4;
+
+/// Color space of the colors of a scene, stored in the scene metadata as
+/// [`AI_METADATA_COLOR_SPACE`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Gamma encoded colors as shown by most editors, assumed for scenes
+    /// without the metadata
+    #[default]
+    Srgb,
+    /// Colors proportional to the light intensity, as used for shading
+    Linear,
+}
+
+impl ColorSpace {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Srgb => "sRGB",
+            Self::Linear => "Linear",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Srgb, Self::Linear]
+            .into_iter()
+            .find(|space| space.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        Self::from_name(metadata.get_str(AI_METADATA_COLOR_SPACE)?)
+    }
+
+    pub fn write_metadata(self, metadata: &mut Metadata) {
+        metadata.set(AI_METADATA_COLOR_SPACE, self.name());
+    }
+
+    /// Converts a color channel in this color space to `target`
+    pub fn convert_channel(self, target: Self, value: f64) -> f64 {
+        match (self, target) {
+            (Self::Srgb, Self::Linear) => srgb_to_linear(value),
+            (Self::Linear, Self::Srgb) => linear_to_srgb(value),
+            _ => value,
+        }
+    }
+}
+
+/// Decodes an sRGB color channel, values outside of `[0, 1]` are mirrored
+/// around zero and extrapolated
+pub fn srgb_to_linear(value: f64) -> f64 {
+    let c = value.abs();
+    let linear = if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    };
+    linear.copysign(value)
+}
+
+/// Encodes a linear color channel as sRGB, the inverse of
+/// [`srgb_to_linear`]
+pub fn linear_to_srgb(value: f64) -> f64 {
+    let c = value.abs();
+    let srgb = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    srgb.copysign(value)
+}
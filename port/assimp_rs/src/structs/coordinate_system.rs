@@ -0,0 +1,222 @@
+//! Axis and unit conventions of scenes, stored in the scene metadata under
+//! the same keys as in assimp.
+
+use super::meta::{
+    AI_METADATA_COORD_AXIS, AI_METADATA_COORD_AXIS_SIGN, AI_METADATA_FRONT_AXIS,
+    AI_METADATA_FRONT_AXIS_SIGN, AI_METADATA_UNIT_SCALE_FACTOR, AI_METADATA_UP_AXIS,
+    AI_METADATA_UP_AXIS_SIGN, Metadata, MetadataExt,
+};
+use crate::{
+    AiReal,
+    utils::float_precision::{Mat3, Mat4, Vec3},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+impl Axis {
+    pub fn from_index(index: i64) -> Option<Self> {
+        match index {
+            0 => Some(Self::X),
+            1 => Some(Self::Y),
+            2 => Some(Self::Z),
+            _ => None,
+        }
+    }
+
+    pub fn unit(self) -> Vec3 {
+        match self {
+            Self::X => Vec3::X,
+            Self::Y => Vec3::Y,
+            Self::Z => Vec3::Z,
+        }
+    }
+}
+
+/// An axis and its direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignedAxis {
+    pub axis: Axis,
+    pub negative: bool,
+}
+
+impl SignedAxis {
+    pub const X: Self = Self::new(Axis::X, false);
+    pub const Y: Self = Self::new(Axis::Y, false);
+    pub const Z: Self = Self::new(Axis::Z, false);
+    pub const NEG_X: Self = Self::new(Axis::X, true);
+    pub const NEG_Y: Self = Self::new(Axis::Y, true);
+    pub const NEG_Z: Self = Self::new(Axis::Z, true);
+
+    pub const fn new(axis: Axis, negative: bool) -> Self {
+        Self { axis, negative }
+    }
+
+    /// 1 or -1, as stored in the metadata
+    pub fn sign(self) -> i32 {
+        if self.negative { -1 } else { 1 }
+    }
+
+    pub fn vector(self) -> Vec3 {
+        self.axis.unit() * self.sign() as AiReal
+    }
+
+    pub fn negated(self) -> Self {
+        Self::new(self.axis, !self.negative)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handedness {
+    Right,
+    Left,
+}
+
+/// Directions of the axes and size of the units of a scene.
+///
+/// The directions are seen from a viewer in front of the scene: `right`
+/// points to the right, `up` upwards and `front` towards the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateSystem {
+    pub right: SignedAxis,
+    pub up: SignedAxis,
+    pub front: SignedAxis,
+    /// Size of a unit in centimeters
+    pub unit_scale_factor: AiReal,
+}
+
+impl Default for CoordinateSystem {
+    /// The convention of assimp, see [`Self::Y_UP_RIGHT_HANDED`]
+    fn default() -> Self {
+        Self::Y_UP_RIGHT_HANDED
+    }
+}
+
+impl CoordinateSystem {
+    /// The convention of assimp and OpenGL, in centimeters
+    pub const Y_UP_RIGHT_HANDED: Self = Self {
+        right: SignedAxis::X,
+        up: SignedAxis::Y,
+        front: SignedAxis::Z,
+        unit_scale_factor: 1.0,
+    };
+
+    /// The convention of Direct3D, in centimeters
+    pub const Y_UP_LEFT_HANDED: Self = Self {
+        right: SignedAxis::X,
+        up: SignedAxis::Y,
+        front: SignedAxis::NEG_Z,
+        unit_scale_factor: 1.0,
+    };
+
+    /// The convention of Blender and most CAD applications, in centimeters
+    pub const Z_UP_RIGHT_HANDED: Self = Self {
+        right: SignedAxis::X,
+        up: SignedAxis::Z,
+        front: SignedAxis::NEG_Y,
+        unit_scale_factor: 1.0,
+    };
+
+    /// Unit scale factor of systems measured in meters
+    pub const METERS: AiReal = 100.0;
+
+    pub fn with_unit_scale_factor(mut self, unit_scale_factor: AiReal) -> Self {
+        self.unit_scale_factor = unit_scale_factor;
+        self
+    }
+
+    /// The system after mirroring the scene along `axis`, like
+    /// `MakeLeftHanded` does with the z axis
+    pub fn mirrored(mut self, axis: Axis) -> Self {
+        for signed in [&mut self.right, &mut self.up, &mut self.front] {
+            if signed.axis == axis {
+                *signed = signed.negated();
+            }
+        }
+        self
+    }
+
+    /// Whether the axes are different and the unit is a positive size
+    pub fn is_valid(&self) -> bool {
+        let (right, up, front) = (self.right.axis, self.up.axis, self.front.axis);
+        right != up
+            && right != front
+            && up != front
+            && self.unit_scale_factor.is_finite()
+            && self.unit_scale_factor > 0.0
+    }
+
+    pub fn handedness(&self) -> Handedness {
+        if self.basis().determinant() > 0.0 {
+            Handedness::Right
+        } else {
+            Handedness::Left
+        }
+    }
+
+    /// The matrix with the `right`, `up` and `front` vectors as columns
+    pub fn basis(&self) -> Mat3 {
+        Mat3::from_cols(self.right.vector(), self.up.vector(), self.front.vector())
+    }
+
+    /// The transformation of coordinates in this system to coordinates in
+    /// `target`. It only swaps, negates and scales axes.
+    pub fn conversion_to(&self, target: &Self) -> Mat4 {
+        let rotation = target.basis() * self.basis().transpose();
+        Mat4::from_mat3(rotation * (self.unit_scale_factor / target.unit_scale_factor))
+    }
+
+    /// The system recorded in the metadata of a scene. The up and front axes
+    /// are required, the third axis defaults to the one making the system
+    /// right-handed and the unit to centimeters.
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        let axis = |key, sign_key| {
+            let axis = Axis::from_index(metadata.get_int64(key)?)?;
+            let negative = metadata.get_int64(sign_key).is_some_and(|sign| sign < 0);
+            Some(SignedAxis::new(axis, negative))
+        };
+        let up = axis(AI_METADATA_UP_AXIS, AI_METADATA_UP_AXIS_SIGN)?;
+        let front = axis(AI_METADATA_FRONT_AXIS, AI_METADATA_FRONT_AXIS_SIGN)?;
+        let right = axis(AI_METADATA_COORD_AXIS, AI_METADATA_COORD_AXIS_SIGN).or_else(|| {
+            let right = up.vector().cross(front.vector());
+            [Axis::X, Axis::Y, Axis::Z]
+                .into_iter()
+                .find(|&axis| axis != up.axis && axis != front.axis)
+                .map(|axis| SignedAxis::new(axis, right.dot(axis.unit()) < 0.0))
+        })?;
+        let system = Self {
+            right,
+            up,
+            front,
+            unit_scale_factor: metadata
+                .get_float(AI_METADATA_UNIT_SCALE_FACTOR)
+                .unwrap_or(1.0),
+        };
+        system.is_valid().then_some(system)
+    }
+
+    /// Records the system in the metadata of a scene.
+    pub fn write_metadata(&self, metadata: &mut Metadata) {
+        for (key, sign_key, axis) in [
+            (AI_METADATA_UP_AXIS, AI_METADATA_UP_AXIS_SIGN, self.up),
+            (
+                AI_METADATA_FRONT_AXIS,
+                AI_METADATA_FRONT_AXIS_SIGN,
+                self.front,
+            ),
+            (
+                AI_METADATA_COORD_AXIS,
+                AI_METADATA_COORD_AXIS_SIGN,
+                self.right,
+            ),
+        ] {
+            metadata.set(key, axis.axis as i32);
+            metadata.set(sign_key, axis.sign());
+        }
+        metadata.set(AI_METADATA_UNIT_SCALE_FACTOR, self.unit_scale_factor);
+    }
+}
@@ -0,0 +1,672 @@
+use std::fmt::{Debug, Display, Formatter};
+
+use super::{
+    anim::{AiAnimation, anim::AiNodeAnim},
+    key::{AiQuatKey, AiVectorKey},
+    material::{AiProperty, MaterialDiff},
+    mesh::AiMesh,
+    nodes::Index,
+    scene::{AiNode, AiScene},
+};
+use crate::{AiReal, utils::float_precision::Mat4};
+
+/// Allowed difference of floats, the absolute one matters for values close
+/// to zero and the relative one for large values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            absolute: 1e-4,
+            relative: 1e-4,
+        }
+    }
+}
+
+impl Tolerance {
+    /// Only identical values are equal
+    pub const EXACT: Self = Self {
+        absolute: 0.0,
+        relative: 0.0,
+    };
+
+    pub fn eq(&self, a: f64, b: f64) -> bool {
+        a == b
+            || (a.is_nan() && b.is_nan())
+            || (a - b).abs() <= self.absolute + self.relative * a.abs().max(b.abs())
+    }
+
+    pub(crate) fn eq_all(&self, a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| self.eq(a, b))
+    }
+}
+
+/// A difference between two scenes found by [`AiScene::diff`].
+///
+/// Paths name the element like `meshes[0].normals[3]`, nodes are named by
+/// the names of their ancestors like `nodes/root/arm`. Arrays of floats are
+/// reported at their first element outside of the tolerance only.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneDiff<'a> {
+    /// The element exists only in the first scene
+    Removed(String),
+    /// The element exists only in the second scene
+    Added(String),
+    /// The number of elements differs, like the meshes of the scenes or the
+    /// vertices of a mesh
+    Count {
+        path: String,
+        old: usize,
+        new: usize,
+    },
+    /// Names, flags and indices, which are compared exactly
+    Value {
+        path: String,
+        old: String,
+        new: String,
+    },
+    /// Floats outside of the tolerance
+    Numbers {
+        path: String,
+        old: Vec<f64>,
+        new: Vec<f64>,
+    },
+    /// A property of the material with the index
+    Material {
+        material: usize,
+        diff: MaterialDiff<'a>,
+    },
+    /// The first key of an animation channel outside of the tolerance,
+    /// with the largest difference of its value components
+    Key {
+        path: String,
+        key: usize,
+        time_delta: f64,
+        value_delta: f64,
+    },
+}
+
+impl Display for SceneDiff<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneDiff::Removed(path) => write!(f, "- {path}"),
+            SceneDiff::Added(path) => write!(f, "+ {path}"),
+            SceneDiff::Count { path, old, new } => write!(f, "{path}: {old} -> {new} elements"),
+            SceneDiff::Value { path, old, new } => write!(f, "{path}: {old} -> {new}"),
+            SceneDiff::Numbers { path, old, new } => write!(f, "{path}: {old:?} -> {new:?}"),
+            SceneDiff::Material { material, diff } => write!(f, "materials[{material}]:\n{diff}"),
+            SceneDiff::Key {
+                path,
+                key,
+                time_delta,
+                value_delta,
+            } => write!(
+                f,
+                "{path}[{key}]: time differs by {time_delta}, value by {value_delta}"
+            ),
+        }
+    }
+}
+
+/// The numbers of a property, `None` for strings and buffers
+pub(crate) fn property_numbers(property: &AiProperty) -> Option<Vec<f64>> {
+    let floats = |values: &[AiReal]| values.iter().map(|&v| v.into()).collect();
+    Some(match property {
+        AiProperty::Floats(values) => floats(values),
+        AiProperty::Float(value) => floats(&[*value]),
+        AiProperty::Vec3(value) => floats(&value.to_array()),
+        AiProperty::Vec4(value) => floats(&value.to_array()),
+        AiProperty::UvTransform(transform) => floats(&[
+            transform.translation.x,
+            transform.translation.y,
+            transform.scaling.x,
+            transform.scaling.y,
+            transform.rotation,
+        ]),
+        AiProperty::ShadingModel(mode) => vec![mode.bits().into()],
+        AiProperty::Integers(values) => values.iter().map(|&v| v.into()).collect(),
+        AiProperty::Integer(value) => vec![(*value).into()],
+        AiProperty::String(_) | AiProperty::Buffer(_) | AiProperty::WildCard(()) => return None,
+    })
+}
+
+fn f64s<T: Into<f64>, const N: usize>(values: [T; N]) -> [f64; N] {
+    values.map(Into::into)
+}
+
+fn matrix(matrix: &Mat4) -> [f64; 16] {
+    f64s(matrix.to_cols_array())
+}
+
+struct Differ<'a> {
+    old: &'a AiScene,
+    new: &'a AiScene,
+    tolerance: Tolerance,
+    diff: Vec<SceneDiff<'a>>,
+}
+
+impl<'a> Differ<'a> {
+    /// Compares lengths, `true` if they are equal
+    fn count(&mut self, path: impl Display, old: usize, new: usize) -> bool {
+        if old != new {
+            self.diff.push(SceneDiff::Count {
+                path: path.to_string(),
+                old,
+                new,
+            });
+        }
+        old == new
+    }
+
+    fn value<T: PartialEq + Debug>(&mut self, path: impl Display, old: T, new: T) {
+        if old != new {
+            self.diff.push(SceneDiff::Value {
+                path: path.to_string(),
+                old: format!("{old:?}"),
+                new: format!("{new:?}"),
+            });
+        }
+    }
+
+    fn numbers(&mut self, path: impl Display, old: &[f64], new: &[f64]) {
+        if !self.tolerance.eq_all(old, new) {
+            self.diff.push(SceneDiff::Numbers {
+                path: path.to_string(),
+                old: old.to_vec(),
+                new: new.to_vec(),
+            });
+        }
+    }
+
+    /// Compares arrays of vectors element by element
+    fn vectors<const N: usize>(&mut self, path: impl Display, old: &[[f64; N]], new: &[[f64; N]]) {
+        if !self.count(format_args!("{path}.len"), old.len(), new.len()) {
+            return;
+        }
+        if let Some(i) = old
+            .iter()
+            .zip(new)
+            .position(|(a, b)| !self.tolerance.eq_all(a, b))
+        {
+            self.numbers(format_args!("{path}[{i}]"), &old[i], &new[i]);
+        }
+    }
+
+    fn node(&mut self, old: Index<AiNode>, new: Index<AiNode>, path: &str) {
+        let (a, b) = (&self.old.nodes[old.value()], &self.new.nodes[new.value()]);
+        self.numbers(
+            format_args!("{path}.transformation"),
+            &matrix(&a.transformation),
+            &matrix(&b.transformation),
+        );
+        self.value(format_args!("{path}.meshes"), &a.meshes, &b.meshes);
+
+        // children are matched by their names
+        let mut unmatched = b.children.clone();
+        for &child in &a.children {
+            let name = &self.old.nodes[child.value()].name;
+            let child_path = format!("{path}/{name}");
+            match unmatched
+                .iter()
+                .position(|&other| self.new.nodes[other.value()].name == *name)
+            {
+                Some(i) => {
+                    let other = unmatched.remove(i);
+                    self.node(child, other, &child_path);
+                }
+                None => self.diff.push(SceneDiff::Removed(child_path)),
+            }
+        }
+        for other in unmatched {
+            let name = &self.new.nodes[other.value()].name;
+            self.diff.push(SceneDiff::Added(format!("{path}/{name}")));
+        }
+    }
+
+    fn mesh(&mut self, old: &AiMesh, new: &AiMesh, path: &str) {
+        self.value(format_args!("{path}.name"), &old.name, &new.name);
+        self.value(
+            format_args!("{path}.primitive_type"),
+            old.primitive_type,
+            new.primitive_type,
+        );
+        self.value(
+            format_args!("{path}.material_index"),
+            old.material_index,
+            new.material_index,
+        );
+
+        let vectors = |values: &[crate::utils::float_precision::Vec3]| -> Vec<[f64; 3]> {
+            values.iter().map(|v| f64s(v.to_array())).collect()
+        };
+        let streams = [
+            ("vertices", &old.vertices, &new.vertices),
+            ("normals", &old.normals, &new.normals),
+            ("tangents", &old.tangents, &new.tangents),
+            ("bitangents", &old.bitangents, &new.bitangents),
+        ];
+        for (name, a, b) in streams {
+            self.vectors(format_args!("{path}.{name}"), &vectors(a), &vectors(b));
+        }
+        for (i, (a, b)) in old.colors.iter().zip(new.colors.iter()).enumerate() {
+            let colors = |set: &[crate::structs::color::Color4D]| -> Vec<[f64; 4]> {
+                set.iter().map(|c| f64s(c.to_array())).collect()
+            };
+            self.vectors(format_args!("{path}.colors[{i}]"), &colors(a), &colors(b));
+        }
+        for (i, (a, b)) in old
+            .texture_coords
+            .iter()
+            .zip(new.texture_coords.iter())
+            .enumerate()
+        {
+            self.value(
+                format_args!("{path}.num_of_uv_components[{i}]"),
+                old.num_of_uv_components[i],
+                new.num_of_uv_components[i],
+            );
+            self.vectors(
+                format_args!("{path}.texture_coords[{i}]"),
+                &vectors(a),
+                &vectors(b),
+            );
+        }
+
+        if self.count(
+            format_args!("{path}.faces.len"),
+            old.faces.len(),
+            new.faces.len(),
+        ) && let Some(i) = old
+            .faces
+            .iter()
+            .zip(&new.faces)
+            .position(|(a, b)| a.indices != b.indices)
+        {
+            self.value(
+                format_args!("{path}.faces[{i}]"),
+                &old.faces[i].indices,
+                &new.faces[i].indices,
+            );
+        }
+
+        // bones are matched by their names
+        for bone in &old.bones {
+            let bone_path = format!("{path}.bones[{:?}]", bone.name);
+            let Some(other) = new.bones.iter().find(|other| other.name == bone.name) else {
+                self.diff.push(SceneDiff::Removed(bone_path));
+                continue;
+            };
+            self.numbers(
+                format_args!("{bone_path}.offset_matrix"),
+                &matrix(&bone.offset_matrix),
+                &matrix(&other.offset_matrix),
+            );
+            let weights = |bone: &crate::structs::bone::AiBone| -> Vec<[f64; 2]> {
+                bone.weights
+                    .iter()
+                    .map(|w| [w.vertex_id.into(), w.weight.into()])
+                    .collect()
+            };
+            self.vectors(
+                format_args!("{bone_path}.weights"),
+                &weights(bone),
+                &weights(other),
+            );
+        }
+        for bone in &new.bones {
+            if !old.bones.iter().any(|other| other.name == bone.name) {
+                self.diff
+                    .push(SceneDiff::Added(format!("{path}.bones[{:?}]", bone.name)));
+            }
+        }
+    }
+
+    fn material(&mut self, index: usize) {
+        let tolerance = self.tolerance;
+        let changes = self.old.materials[index]
+            .diff(&self.new.materials[index])
+            .into_iter()
+            .filter(|diff| match diff {
+                MaterialDiff::Changed(a, b) => {
+                    match (property_numbers(&a.property), property_numbers(&b.property)) {
+                        (Some(a), Some(b)) => !tolerance.eq_all(&a, &b),
+                        _ => true,
+                    }
+                }
+                _ => true,
+            })
+            .map(|diff| SceneDiff::Material {
+                material: index,
+                diff,
+            });
+        self.diff.extend(changes);
+    }
+
+    /// Reports the first key outside of the tolerance
+    fn keys<const N: usize>(
+        &mut self,
+        path: impl Display,
+        old: &[(f64, [f64; N])],
+        new: &[(f64, [f64; N])],
+    ) {
+        if !self.count(format_args!("{path}.len"), old.len(), new.len()) {
+            return;
+        }
+        for (key, (&(t0, v0), &(t1, v1))) in old.iter().zip(new).enumerate() {
+            if !self.tolerance.eq(t0, t1) || !self.tolerance.eq_all(&v0, &v1) {
+                let value_delta = v0
+                    .iter()
+                    .zip(&v1)
+                    .map(|(a, b)| (a - b).abs())
+                    .fold(0.0, f64::max);
+                self.diff.push(SceneDiff::Key {
+                    path: path.to_string(),
+                    key,
+                    time_delta: t1 - t0,
+                    value_delta,
+                });
+                return;
+            }
+        }
+    }
+
+    fn channel(&mut self, old: &AiNodeAnim, new: &AiNodeAnim, path: &str) {
+        self.value(
+            format_args!("{path}.pre_state"),
+            &old.pre_state,
+            &new.pre_state,
+        );
+        self.value(
+            format_args!("{path}.post_state"),
+            &old.post_state,
+            &new.post_state,
+        );
+        let vector_keys = |keys: &[AiVectorKey]| -> Vec<_> {
+            keys.iter()
+                .map(|k| (k.time, f64s(k.value.to_array())))
+                .collect()
+        };
+        self.keys(
+            format_args!("{path}.position_keys"),
+            &vector_keys(&old.position_keys),
+            &vector_keys(&new.position_keys),
+        );
+        let quat_keys = |keys: &[AiQuatKey]| -> Vec<_> {
+            keys.iter()
+                .map(|k| (k.time, f64s(k.value.to_array())))
+                .collect()
+        };
+        self.keys(
+            format_args!("{path}.rotation_keys"),
+            &quat_keys(&old.rotation_keys),
+            &quat_keys(&new.rotation_keys),
+        );
+        self.keys(
+            format_args!("{path}.scaling_keys"),
+            &vector_keys(&old.scaling_keys),
+            &vector_keys(&new.scaling_keys),
+        );
+    }
+
+    fn animation(&mut self, old: &AiAnimation, new: &AiAnimation, path: &str) {
+        self.value(format_args!("{path}.name"), &old.name, &new.name);
+        self.numbers(
+            format_args!("{path}.duration"),
+            &[old.duration, old.ticks_per_second],
+            &[new.duration, new.ticks_per_second],
+        );
+        // channels are matched by the names of their nodes
+        for channel in &old.channels {
+            let channel_path = format!("{path}.channels[{:?}]", channel.node_name);
+            match new
+                .channels
+                .iter()
+                .find(|other| other.node_name == channel.node_name)
+            {
+                Some(other) => self.channel(channel, other, &channel_path),
+                None => self.diff.push(SceneDiff::Removed(channel_path)),
+            }
+        }
+        for channel in &new.channels {
+            if !old
+                .channels
+                .iter()
+                .any(|other| other.node_name == channel.node_name)
+            {
+                self.diff.push(SceneDiff::Added(format!(
+                    "{path}.channels[{:?}]",
+                    channel.node_name
+                )));
+            }
+        }
+        self.count(
+            format_args!("{path}.mesh_channels.len"),
+            old.mesh_channels.len(),
+            new.mesh_channels.len(),
+        );
+        self.count(
+            format_args!("{path}.morph_mesh_channels.len"),
+            old.morph_mesh_channels.len(),
+            new.morph_mesh_channels.len(),
+        );
+    }
+
+    fn lights_and_cameras(&mut self) {
+        let (old, new) = (self.old, self.new);
+        if self.count("lights.len", old.lights.len(), new.lights.len()) {
+            for (i, (a, b)) in old.lights.iter().zip(&new.lights).enumerate() {
+                self.value(format_args!("lights[{i}].name"), &a.name, &b.name);
+                self.value(
+                    format_args!("lights[{i}].light_type"),
+                    &a.light_type,
+                    &b.light_type,
+                );
+                let vectors = [
+                    ("position", a.position, b.position),
+                    ("direction", a.direction, b.direction),
+                    ("up", a.up, b.up),
+                ];
+                for (name, a, b) in vectors {
+                    self.numbers(
+                        format_args!("lights[{i}].{name}"),
+                        &f64s(a.to_array()),
+                        &f64s(b.to_array()),
+                    );
+                }
+                let colors = [
+                    ("color_diffuse", a.color_diffuse, b.color_diffuse),
+                    ("color_specular", a.color_specular, b.color_specular),
+                    ("color_ambient", a.color_ambient, b.color_ambient),
+                ];
+                for (name, a, b) in colors {
+                    self.numbers(
+                        format_args!("lights[{i}].{name}"),
+                        &f64s(a.to_array()),
+                        &f64s(b.to_array()),
+                    );
+                }
+                let parameters = |light: &crate::structs::light::AiLight| {
+                    f64s([
+                        light.attenuation_constant,
+                        light.attenuation_linear,
+                        light.attenuation_quadratic,
+                        light.angle_inner_cone,
+                        light.angle_outer_cone,
+                    ])
+                };
+                self.numbers(
+                    format_args!("lights[{i}].attenuation_and_cone"),
+                    &parameters(a),
+                    &parameters(b),
+                );
+            }
+        }
+        if self.count("cameras.len", old.cameras.len(), new.cameras.len()) {
+            for (i, (a, b)) in old.cameras.iter().zip(&new.cameras).enumerate() {
+                self.value(format_args!("cameras[{i}].name"), &a.name, &b.name);
+                let vectors = [
+                    ("position", a.position, b.position),
+                    ("up", a.up, b.up),
+                    ("look_at", a.look_at, b.look_at),
+                ];
+                for (name, a, b) in vectors {
+                    self.numbers(
+                        format_args!("cameras[{i}].{name}"),
+                        &f64s(a.to_array()),
+                        &f64s(b.to_array()),
+                    );
+                }
+                let parameters = |camera: &crate::structs::camera::AiCamera| {
+                    f64s([
+                        camera.horizontal_fov,
+                        camera.clip_plane_near,
+                        camera.clip_plane_far,
+                        camera.aspect,
+                        camera.orthographic_width,
+                    ])
+                };
+                self.numbers(
+                    format_args!("cameras[{i}].projection"),
+                    &parameters(a),
+                    &parameters(b),
+                );
+            }
+        }
+    }
+}
+
+impl AiScene {
+    /// Compares the scene with `other`, e.g. a scene exported and imported
+    /// again, floats are compared with the `tolerance`. An empty result means
+    /// the scenes are equal.
+    ///
+    /// Nodes, bones and animation channels are matched by their names,
+    /// meshes, materials and all other elements by their indices. Metadata
+    /// and the data of embedded textures are not compared.
+    pub fn diff<'a>(&'a self, other: &'a AiScene, tolerance: Tolerance) -> Vec<SceneDiff<'a>> {
+        let mut differ = Differ {
+            old: self,
+            new: other,
+            tolerance,
+            diff: Vec::new(),
+        };
+        differ.value("flags", self.flags, other.flags);
+        match (self.root, other.root) {
+            (Some(a), Some(b)) => {
+                let (name, other_name) =
+                    (&self.nodes[a.value()].name, &other.nodes[b.value()].name);
+                let path = format!("nodes/{name}");
+                if name == other_name {
+                    differ.node(a, b, &path);
+                } else {
+                    differ.diff.push(SceneDiff::Removed(path));
+                    differ
+                        .diff
+                        .push(SceneDiff::Added(format!("nodes/{other_name}")));
+                }
+            }
+            (Some(a), None) => differ.diff.push(SceneDiff::Removed(format!(
+                "nodes/{}",
+                self.nodes[a.value()].name
+            ))),
+            (None, Some(b)) => differ.diff.push(SceneDiff::Added(format!(
+                "nodes/{}",
+                other.nodes[b.value()].name
+            ))),
+            (None, None) => {}
+        }
+
+        if differ.count("meshes.len", self.meshes.len(), other.meshes.len()) {
+            for (i, (a, b)) in self.meshes.iter().zip(&other.meshes).enumerate() {
+                differ.mesh(a, b, &format!("meshes[{i}]"));
+            }
+        }
+        if differ.count("materials.len", self.materials.len(), other.materials.len()) {
+            for i in 0..self.materials.len() {
+                differ.material(i);
+            }
+        }
+        if differ.count(
+            "animations.len",
+            self.animations.len(),
+            other.animations.len(),
+        ) {
+            for (i, (a, b)) in self.animations.iter().zip(&other.animations).enumerate() {
+                differ.animation(a, b, &format!("animations[{i}]"));
+            }
+        }
+        differ.count("textures.len", self.textures.len(), other.textures.len());
+        differ.lights_and_cameras();
+        differ.diff
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::{SceneDiff, Tolerance};
+    use crate::{
+        formats::x::importer::Importer,
+        structs::{
+            hierarchy::RemovePolicy,
+            material::{AI_MATKEY_SHININESS, AddProperty},
+        },
+        traits::importer::trait_define::Importer as _,
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_scene_diff() {
+        let scene =
+            *Importer::read_from_buf(&fs::read(get_model_path("X", "anim_test.x")).unwrap())
+                .unwrap();
+        assert_eq!(scene.diff(&scene.clone(), Tolerance::EXACT), []);
+
+        let mut other = scene.clone();
+        // within the tolerance
+        other.meshes[0].vertices[0].x += 1e-6;
+        other.meshes[0].vertices[5].x += 1.0;
+        other.meshes[0].faces.pop();
+        other.materials[0].add_property(AI_MATKEY_SHININESS, 1234.0, 0);
+        other.animations[0].channels[0].position_keys[1].time += 0.5;
+        let root = other.root.unwrap();
+        let child = other.nodes[root.value()].children[0];
+        let child_name = other.nodes[child.value()].name.clone();
+        other
+            .remove_node(child, RemovePolicy::RemoveSubtree)
+            .unwrap();
+
+        let diff = scene.diff(&other, Tolerance::default());
+        let root_name = &scene.nodes[root.value()].name;
+        assert!(diff.contains(&SceneDiff::Removed(format!(
+            "nodes/{root_name}/{child_name}"
+        ))));
+        assert!(diff.iter().any(|d| matches!(
+            d,
+            SceneDiff::Numbers { path, old, new } if path == "meshes[0].vertices[5]" && new[0] - old[0] > 0.99
+        )));
+        assert!(diff.iter().any(|d| matches!(
+            d,
+            SceneDiff::Count { path, old, new } if path == "meshes[0].faces.len" && old - new == 1
+        )));
+        assert!(diff.iter().any(|d| matches!(
+            d,
+            SceneDiff::Material { material: 0, diff } if diff.to_string().ends_with("+ $mat.shininess = 1234")
+        )));
+        assert!(diff.iter().any(|d| matches!(
+            d,
+            SceneDiff::Key {
+                key: 1,
+                time_delta: 0.5,
+                ..
+            }
+        )));
+        assert_eq!(diff.len(), 5, "{diff:#?}");
+    }
+}
@@ -5,6 +5,47 @@ use std::{
 
 use crate::utils::float_precision::Mat4;
 
+/// Write X files with 64 bit floats, `xof 0303txt 0064`.
+pub const AI_CONFIG_EXPORT_XFILE_64BIT: &str = "EXPORT_XFILE_64BIT";
+
+/// Number of digits after the decimal point of the floats in X files.
+/// Defaults to the precision of `AiReal`, 9 or 17 digits.
+pub const AI_CONFIG_EXPORT_X_PRECISION: &str = "EXPORT_X_PRECISION";
+
+/// Write the normals of meshes to X files. Defaults to true.
+pub const AI_CONFIG_EXPORT_X_NORMALS: &str = "EXPORT_X_NORMALS";
+
+/// Write the first texture coordinate set of meshes to X files. Defaults to
+/// true.
+pub const AI_CONFIG_EXPORT_X_TEXCOORDS: &str = "EXPORT_X_TEXCOORDS";
+
+/// Write the first vertex color set of meshes to X files. Defaults to true.
+pub const AI_CONFIG_EXPORT_X_VERTEX_COLORS: &str = "EXPORT_X_VERTEX_COLORS";
+
+/// Write the root node as the top frame of X files instead of wrapping it in
+/// the identity `DXCC_ROOT` frame. Defaults to false.
+pub const AI_CONFIG_EXPORT_X_COLLAPSE_ROOT: &str = "EXPORT_X_COLLAPSE_ROOT";
+
+/// Encoding of the texture paths in X files, `0` for ISO-8859-1 (Latin-1)
+/// and `1` for UTF-8. Defaults to Latin-1 like the DirectX SDK, characters
+/// it can't represent are replaced by `?`.
+pub const AI_CONFIG_EXPORT_X_PATH_ENCODING: &str = "EXPORT_X_PATH_ENCODING";
+
+/// Write the lights and cameras of the scene to X files as `Light` and
+/// `Camera` data objects, which are no standard templates but are read by
+/// several DCC tools and the X importer. They are written inside the frame
+/// of the node with the same name, or at the top level if there is none.
+/// Defaults to false.
+pub const AI_CONFIG_EXPORT_X_LIGHTS_CAMERAS: &str = "EXPORT_X_LIGHTS_CAMERAS";
+
+/// Write X files as compressed text, `xof 0303tzip0032`, with the
+/// `compression` feature. Defaults to false.
+pub const AI_CONFIG_EXPORT_X_COMPRESS: &str = "EXPORT_X_COMPRESS";
+
+/// Compress the chunks of Model 3D files with zlib if that makes them
+/// smaller. Defaults to true.
+pub const AI_CONFIG_EXPORT_M3D_COMPRESS: &str = "EXPORT_M3D_COMPRESS";
+
 type KeyType = u64;
 
 // typedefs for our four configuration maps.
@@ -15,6 +56,12 @@ type StringPropertyMap = BTreeMap<KeyType, String>;
 type MatrixPropertyMap = BTreeMap<KeyType, Mat4>;
 // typedef std::map<KeyType, std::function<void *(void *)>> CallbackPropertyMap;
 
+fn hash_key(key: &str) -> KeyType {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[allow(unused)]
 #[derive(Debug, Default)]
 pub struct ExportProperties {
@@ -26,15 +73,27 @@ pub struct ExportProperties {
 }
 
 impl ExportProperties {
+    pub fn set_bool(&mut self, key: &str, value: bool) {
+        self.set_int(key, value as i32);
+    }
+
+    pub fn set_int(&mut self, key: &str, value: i32) {
+        self.int_properties.insert(hash_key(key), value);
+    }
+
     pub fn get_bool(&self, key: &str) -> bool {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        self.int_properties.get(&hasher.finish()).unwrap_or(&0) != &0
+        self.get_int(key) != 0
+    }
+
+    pub fn get_bool_or(&self, key: &str, default: bool) -> bool {
+        self.get_int_or(key, default as i32) != 0
     }
 
     pub fn get_int(&self, key: &str) -> i32 {
-        let mut hasher = DefaultHasher::new();
-        key.hash(&mut hasher);
-        *self.int_properties.get(&hasher.finish()).unwrap_or(&0)
+        self.get_int_or(key, 0)
+    }
+
+    pub fn get_int_or(&self, key: &str, default: i32) -> i32 {
+        *self.int_properties.get(&hash_key(key)).unwrap_or(&default)
     }
 }
@@ -0,0 +1,15 @@
+/// Describes a file format which can be exported to, the counterpart of
+/// [`ImporterDesc`](super::importer_desc::ImporterDesc) for exporters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExporterDesc {
+    /// Short string ID to uniquely identify the export format,
+    /// e.g. **"x"** or **"collada"**
+    pub id: &'static str,
+
+    /// Short description of the file format to present to users
+    pub description: &'static str,
+
+    /// Recommended file extension for the exported file in lower case
+    /// without a leading dot
+    pub file_extension: &'static str,
+}
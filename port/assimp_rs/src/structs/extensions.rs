@@ -0,0 +1,842 @@
+//! Extensions of glTF objects. The extensions known here map onto material
+//! properties and lights, all others are kept as JSON text, so exporters
+//! can write them back unchanged:
+//!
+//! | Extension                           | Maps onto                                         |
+//! |-------------------------------------|---------------------------------------------------|
+//! | `KHR_texture_transform`             | [`AI_MATKEY_UVTRANSFORM`] and [`AI_MATKEY_UVWSRC`] |
+//! | `KHR_materials_emissive_strength`   | [`AI_MATKEY_EMISSIVE_INTENSITY`]                  |
+//! | `KHR_lights_punctual`               | [`AiLight`], see [`PunctualLight`]                |
+//!
+//! Known extensions with values which can't be mapped exactly, like
+//! malformed ones or texture transforms without an [`AiUVTransform`]
+//! counterpart, are kept as JSON text as well.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use indexmap::IndexMap;
+
+use super::{
+    color::Color3D,
+    light::{AiLight, PunctualLight, PunctualLightType},
+    material::{
+        AI_MATKEY_EMISSIVE_INTENSITY, AI_MATKEY_UVTRANSFORM, AI_MATKEY_UVWSRC, AiMaterial,
+        AiProperty, AiTextureType, AiUVTransform, TextureTransform,
+    },
+    meta::{Metadata, MetadataEntry},
+    scene::AiNode,
+};
+use crate::{AiReal, utils::float_precision::Vec2};
+
+pub const KHR_TEXTURE_TRANSFORM: &str = "KHR_texture_transform";
+pub const KHR_MATERIALS_EMISSIVE_STRENGTH: &str = "KHR_materials_emissive_strength";
+pub const KHR_LIGHTS_PUNCTUAL: &str = "KHR_lights_punctual";
+
+/// Prefix of the material keys of extensions kept as JSON text, followed by
+/// the name of the extension. Extensions of textures have the texture type
+/// and index of the texture.
+pub const AI_MATKEY_GLTF_EXTENSION: &str = "$gltf.ext.";
+
+/// Metadata key of the extensions of nodes and scenes kept as JSON text,
+/// nested metadata with a string per extension
+pub const AI_METADATA_GLTF_EXTENSIONS: &str = "glTF_extensions";
+
+/// A JSON value, the content of an extension
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(JsonObject),
+}
+
+pub type JsonObject = IndexMap<String, JsonValue>;
+
+/// The `extensions` object of a glTF object, by extension name
+pub type Extensions = JsonObject;
+
+impl JsonValue {
+    /// The member of an object
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            Self::Object(object) => object.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Parses JSON text, `None` if it is malformed
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parser = JsonParser {
+            text: text.as_bytes(),
+            pos: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        (parser.pos == parser.text.len()).then_some(value)
+    }
+
+    /// A number member, `default` if it is missing and `None` if it is no
+    /// number
+    fn number_or(&self, key: &str, default: f64) -> Option<f64> {
+        self.get(key).map_or(Some(default), JsonValue::as_f64)
+    }
+
+    /// An array member of `N` numbers, see [`Self::number_or`]
+    fn numbers_or<const N: usize>(&self, key: &str, default: [f64; N]) -> Option<[f64; N]> {
+        let Some(value) = self.get(key) else {
+            return Some(default);
+        };
+        let values = value.as_array()?;
+        let mut result = [0.0; N];
+        if values.len() != N {
+            return None;
+        }
+        for (result, value) in result.iter_mut().zip(values) {
+            *result = value.as_f64()?;
+        }
+        Some(result)
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl<const N: usize> From<[f64; N]> for JsonValue {
+    fn from(values: [f64; N]) -> Self {
+        Self::Array(values.into_iter().map(Self::Number).collect())
+    }
+}
+
+impl From<JsonObject> for JsonValue {
+    fn from(object: JsonObject) -> Self {
+        Self::Object(object)
+    }
+}
+
+fn write_json_string(f: &mut Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Display for JsonValue {
+    /// Compact JSON text
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(value) => write!(f, "{value}"),
+            // JSON has no infinity and NaN
+            Self::Number(value) if !value.is_finite() => f.write_str("null"),
+            Self::Number(value) => write!(f, "{value}"),
+            Self::String(value) => write_json_string(f, value),
+            Self::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(object) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in object.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Nesting depth at which [`JsonValue::parse`] gives up, so malicious
+/// files can't overflow the stack
+const MAX_JSON_DEPTH: usize = 128;
+
+struct JsonParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|c| matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips whitespace and consumes `c` if it is next
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.text.get(self.pos) == Some(&c);
+        self.pos += found as usize;
+        found
+    }
+
+    fn keyword(&mut self, keyword: &str, value: JsonValue) -> Option<JsonValue> {
+        let found = self.text[self.pos..].starts_with(keyword.as_bytes());
+        self.pos += keyword.len() * found as usize;
+        found.then_some(value)
+    }
+
+    fn value(&mut self, depth: usize) -> Option<JsonValue> {
+        if depth > MAX_JSON_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match *self.text.get(self.pos)? {
+            b'n' => self.keyword("null", JsonValue::Null),
+            b't' => self.keyword("true", JsonValue::Bool(true)),
+            b'f' => self.keyword("false", JsonValue::Bool(false)),
+            b'"' => self.string().map(JsonValue::String),
+            b'[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        values.push(self.value(depth + 1)?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        self.eat(b',').then_some(())?;
+                    }
+                }
+                Some(JsonValue::Array(values))
+            }
+            b'{' => {
+                self.pos += 1;
+                let mut object = JsonObject::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.eat(b':').then_some(())?;
+                        object.insert(key, self.value(depth + 1)?);
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        self.eat(b',').then_some(())?;
+                    }
+                }
+                Some(JsonValue::Object(object))
+            }
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Option<JsonValue> {
+        let start = self.pos;
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|c| matches!(c, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+        {
+            self.pos += 1;
+        }
+        let text = core::str::from_utf8(&self.text[start..self.pos]).ok()?;
+        text.parse().ok().map(JsonValue::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        (self.text.get(self.pos) == Some(&b'"')).then_some(())?;
+        self.pos += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let c = *self.text.get(self.pos)?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self.text.get(self.pos)?;
+                    self.pos += 1;
+                    let c = match escaped {
+                        b'"' | b'\\' | b'/' => escaped as char,
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                c => bytes.push(c),
+            }
+        }
+        String::from_utf8(bytes).ok()
+    }
+
+    /// The four hexadecimal digits of `\uXXXX`
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.text.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        u32::from_str_radix(core::str::from_utf8(digits).ok()?, 16).ok()
+    }
+
+    /// The character of `\uXXXX`, including surrogate pairs
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high);
+        }
+        if self.text.get(self.pos..self.pos + 2) != Some(b"\\u") {
+            return None;
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        (0xDC00..0xE000).contains(&low).then_some(())?;
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    }
+}
+
+/// An extension mapped onto the properties of a material or of one of its
+/// textures
+struct MaterialExtension {
+    name: &'static str,
+    /// Sets the properties, `None` if the value can't be mapped
+    import: fn(&JsonValue, &mut AiMaterial, AiTextureType, u32) -> Option<()>,
+    /// The value of the properties, `None` if they are not set
+    export: fn(&AiMaterial, AiTextureType, u32) -> Option<JsonValue>,
+}
+
+static MATERIAL_EXTENSIONS: &[MaterialExtension] = &[MaterialExtension {
+    name: KHR_MATERIALS_EMISSIVE_STRENGTH,
+    import: import_emissive_strength,
+    export: export_emissive_strength,
+}];
+
+static TEXTURE_EXTENSIONS: &[MaterialExtension] = &[MaterialExtension {
+    name: KHR_TEXTURE_TRANSFORM,
+    import: import_texture_transform,
+    export: export_texture_transform,
+}];
+
+fn import_emissive_strength(
+    value: &JsonValue,
+    material: &mut AiMaterial,
+    _: AiTextureType,
+    _: u32,
+) -> Option<()> {
+    let strength = value.number_or("emissiveStrength", 1.0)?;
+    let strength = AiProperty::Float(strength as AiReal);
+    material.set_property(
+        AI_MATKEY_EMISSIVE_INTENSITY,
+        AiTextureType::None,
+        0,
+        strength,
+    );
+    Some(())
+}
+
+fn export_emissive_strength(material: &AiMaterial, _: AiTextureType, _: u32) -> Option<JsonValue> {
+    let strength = material.get_float(AI_MATKEY_EMISSIVE_INTENSITY)?;
+    let object = JsonObject::from([("emissiveStrength".to_owned(), (strength as f64).into())]);
+    (strength != 1.0).then_some(object.into())
+}
+
+fn import_texture_transform(
+    value: &JsonValue,
+    material: &mut AiMaterial,
+    ty: AiTextureType,
+    index: u32,
+) -> Option<()> {
+    let [x, y] = value.numbers_or("offset", [0.0, 0.0])?;
+    let [sx, sy] = value.numbers_or("scale", [1.0, 1.0])?;
+    let tex_coord = match value.get("texCoord") {
+        Some(tex_coord) => {
+            let tex_coord = tex_coord.as_f64()?;
+            (tex_coord >= 0.0 && tex_coord.fract() == 0.0).then_some(Some(tex_coord as u32))?
+        }
+        None => None,
+    };
+    let transform = TextureTransform {
+        offset: Vec2::new(x as AiReal, y as AiReal),
+        rotation: value.number_or("rotation", 0.0)? as AiReal,
+        scale: Vec2::new(sx as AiReal, sy as AiReal),
+        tex_coord,
+    };
+    let uv_transform = AiUVTransform::from_texture_transform(&transform)?;
+    material.set_property(
+        AI_MATKEY_UVTRANSFORM,
+        ty,
+        index,
+        AiProperty::UvTransform(uv_transform),
+    );
+    if let Some(tex_coord) = tex_coord {
+        let uv_index = AiProperty::Integer(tex_coord as i32);
+        material.set_property(AI_MATKEY_UVWSRC, ty, index, uv_index);
+    }
+    Some(())
+}
+
+/// The UV channel of the texture is written by the exporter as `texCoord`
+/// of the texture, the extension has none.
+fn export_texture_transform(
+    material: &AiMaterial,
+    ty: AiTextureType,
+    index: u32,
+) -> Option<JsonValue> {
+    let transform = material
+        .property(AI_MATKEY_UVTRANSFORM, ty, index)?
+        .is_uv_transform_property()?
+        .to_texture_transform()?;
+    let mut object = JsonObject::new();
+    if transform.offset != Vec2::ZERO {
+        let offset = [transform.offset.x as f64, transform.offset.y as f64];
+        object.insert("offset".to_owned(), offset.into());
+    }
+    if transform.rotation != 0.0 {
+        object.insert("rotation".to_owned(), (transform.rotation as f64).into());
+    }
+    if transform.scale != Vec2::ONE {
+        let scale = [transform.scale.x as f64, transform.scale.y as f64];
+        object.insert("scale".to_owned(), scale.into());
+    }
+    Some(object.into())
+}
+
+fn import_extensions(
+    known: &[MaterialExtension],
+    extensions: &Extensions,
+    material: &mut AiMaterial,
+    ty: AiTextureType,
+    index: u32,
+) {
+    for (name, value) in extensions {
+        let imported = known
+            .iter()
+            .find(|extension| extension.name == name)
+            .and_then(|extension| (extension.import)(value, material, ty, index));
+        if imported.is_none() {
+            let key = format!("{AI_MATKEY_GLTF_EXTENSION}{name}");
+            material.set_property(key, ty, index, AiProperty::String(value.to_string()));
+        }
+    }
+}
+
+fn export_extensions(
+    known: &[MaterialExtension],
+    material: &AiMaterial,
+    ty: AiTextureType,
+    index: u32,
+) -> Extensions {
+    let mut extensions = Extensions::new();
+    for extension in known {
+        if let Some(value) = (extension.export)(material, ty, index) {
+            extensions.insert(extension.name.to_owned(), value);
+        }
+    }
+    for property in material
+        .properties
+        .iter()
+        .filter(|p| p.semantic == ty && p.index == index)
+    {
+        let Some(name) = property.key.strip_prefix(AI_MATKEY_GLTF_EXTENSION) else {
+            continue;
+        };
+        let value = property
+            .property
+            .is_string_property()
+            .and_then(|text| JsonValue::parse(text));
+        if let Some(value) = value {
+            // the properties of known extensions may have been edited
+            extensions.entry(name.to_owned()).or_insert(value);
+        }
+    }
+    extensions
+}
+
+/// Maps the extensions of a glTF material onto `material`
+pub fn import_material_extensions(extensions: &Extensions, material: &mut AiMaterial) {
+    import_extensions(
+        MATERIAL_EXTENSIONS,
+        extensions,
+        material,
+        AiTextureType::None,
+        0,
+    );
+}
+
+/// Maps the extensions of the texture info of a glTF material onto the
+/// texture `ty` with the given index of `material`
+pub fn import_texture_extensions(
+    extensions: &Extensions,
+    material: &mut AiMaterial,
+    ty: AiTextureType,
+    index: u32,
+) {
+    import_extensions(TEXTURE_EXTENSIONS, extensions, material, ty, index);
+}
+
+/// The extensions of the glTF material of `material`, the inverse of
+/// [`import_material_extensions`]
+pub fn export_material_extensions(material: &AiMaterial) -> Extensions {
+    export_extensions(MATERIAL_EXTENSIONS, material, AiTextureType::None, 0)
+}
+
+/// The extensions of the texture info of the texture `ty` with the given
+/// index, the inverse of [`import_texture_extensions`]
+pub fn export_texture_extensions(
+    material: &AiMaterial,
+    ty: AiTextureType,
+    index: u32,
+) -> Extensions {
+    export_extensions(TEXTURE_EXTENSIONS, material, ty, index)
+}
+
+impl PunctualLight {
+    /// Reads a light of the `lights` array of `KHR_lights_punctual`, `None`
+    /// if it is malformed
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        let light_type = match value.get("type")?.as_str()? {
+            "directional" => PunctualLightType::Directional,
+            "point" => PunctualLightType::Point,
+            "spot" => {
+                let spot = value.get("spot")?;
+                PunctualLightType::Spot {
+                    inner_cone_angle: spot.number_or("innerConeAngle", 0.0)? as f32,
+                    outer_cone_angle: spot
+                        .number_or("outerConeAngle", core::f64::consts::FRAC_PI_4)?
+                        as f32,
+                }
+            }
+            _ => return None,
+        };
+        let [r, g, b] = value.numbers_or("color", [1.0; 3])?;
+        let range = match value.get("range") {
+            Some(range) => Some(range.as_f64()? as f32),
+            None => None,
+        };
+        Some(Self {
+            light_type,
+            color: Color3D::new(r as f32, g as f32, b as f32),
+            intensity: value.number_or("intensity", 1.0)? as f32,
+            range,
+        })
+    }
+
+    /// The inverse of [`Self::from_json`], members with their default value
+    /// are left out
+    pub fn to_json(&self) -> JsonValue {
+        let mut object = JsonObject::new();
+        let light_type = match self.light_type {
+            PunctualLightType::Directional => "directional",
+            PunctualLightType::Point => "point",
+            PunctualLightType::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                let spot = JsonObject::from([
+                    (
+                        "innerConeAngle".to_owned(),
+                        (inner_cone_angle as f64).into(),
+                    ),
+                    (
+                        "outerConeAngle".to_owned(),
+                        (outer_cone_angle as f64).into(),
+                    ),
+                ]);
+                object.insert("spot".to_owned(), spot.into());
+                "spot"
+            }
+        };
+        object.insert("type".to_owned(), light_type.into());
+        if self.color != Color3D::ONE {
+            let [r, g, b] = self.color.to_array().map(f64::from);
+            object.insert("color".to_owned(), [r, g, b].into());
+        }
+        if self.intensity != 1.0 {
+            object.insert("intensity".to_owned(), (self.intensity as f64).into());
+        }
+        if let Some(range) = self.range {
+            object.insert("range".to_owned(), (range as f64).into());
+        }
+        object.into()
+    }
+}
+
+/// Keeps `value` as JSON text in the [`AI_METADATA_GLTF_EXTENSIONS`] of
+/// `metadata`
+fn keep_extension(metadata: &mut Metadata, name: &str, value: &JsonValue) {
+    let entry = metadata
+        .entry(AI_METADATA_GLTF_EXTENSIONS.to_owned())
+        .or_insert_with(|| MetadataEntry::Metadata(Box::default()));
+    if let MetadataEntry::Metadata(kept) = entry {
+        kept.insert(name.to_owned(), value.to_string().into());
+    }
+}
+
+/// Maps the extensions of the glTF root object onto the metadata of the
+/// scene, which is usually `scene.metadata`, and returns the lights of
+/// `KHR_lights_punctual`, which nodes refer to by their index, see
+/// [`import_node_extensions`]
+pub fn import_scene_extensions(
+    extensions: &Extensions,
+    metadata: &mut Metadata,
+) -> Vec<PunctualLight> {
+    let mut lights = Vec::new();
+    for (name, value) in extensions {
+        if name == KHR_LIGHTS_PUNCTUAL {
+            let parsed = value
+                .get("lights")
+                .and_then(JsonValue::as_array)
+                .and_then(|values| values.iter().map(PunctualLight::from_json).collect());
+            if let Some(parsed) = parsed {
+                lights = parsed;
+                continue;
+            }
+        }
+        keep_extension(metadata, name, value);
+    }
+    lights
+}
+
+/// Maps the extensions of a glTF node onto `node`. A light of `lights`,
+/// the result of [`import_scene_extensions`], referenced by the node is
+/// appended to `scene_lights` with the name of the node, like assimp
+/// attaches lights to nodes.
+pub fn import_node_extensions(
+    extensions: &Extensions,
+    lights: &[PunctualLight],
+    node: &mut AiNode,
+    scene_lights: &mut Vec<AiLight>,
+) {
+    for (name, value) in extensions {
+        if name == KHR_LIGHTS_PUNCTUAL {
+            let light = value
+                .get("light")
+                .and_then(JsonValue::as_f64)
+                .filter(|index| index.fract() == 0.0 && *index >= 0.0)
+                .and_then(|index| lights.get(index as usize));
+            if let Some(light) = light {
+                scene_lights.push(AiLight::from_punctual(node.name.clone(), light));
+                continue;
+            }
+        }
+        keep_extension(&mut node.metadata, name, value);
+    }
+}
+
+/// The extensions kept in `metadata` by [`import_scene_extensions`] and
+/// [`import_node_extensions`], for the exporter to write back
+pub fn kept_extensions(metadata: &Metadata) -> Extensions {
+    let Some(MetadataEntry::Metadata(kept)) = metadata.get(AI_METADATA_GLTF_EXTENSIONS) else {
+        return Extensions::new();
+    };
+    kept.iter()
+        .filter_map(|(name, text)| Some((name.clone(), JsonValue::parse(text.as_str()?)?)))
+        .collect()
+}
+
+#[allow(unused)]
+mod test {
+    use super::*;
+    use crate::structs::light::LightType;
+
+    fn extensions(text: &str) -> Extensions {
+        match JsonValue::parse(text).unwrap() {
+            JsonValue::Object(object) => object,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let text = r#"{"a":[1,-2.5,1e3,true,null],"b":{"c":"x\"\n\u00e9\ud83d\ude00"}}"#;
+        let value = JsonValue::parse(text).unwrap();
+        assert_eq!(value.get("a").unwrap().as_array().unwrap().len(), 5);
+        assert_eq!(
+            value.get("b").unwrap().get("c").unwrap().as_str(),
+            Some("x\"\n\u{e9}\u{1f600}")
+        );
+        assert_eq!(JsonValue::parse(&value.to_string()), Some(value));
+        assert_eq!(
+            JsonValue::parse(" [ ] ").unwrap(),
+            JsonValue::Array(Vec::new())
+        );
+        for malformed in ["", "[1,]", "{\"a\" 1}", "tru", "[1] 2", "\"\\x\""] {
+            assert!(JsonValue::parse(malformed).is_none(), "{malformed}");
+        }
+        assert!(JsonValue::parse(&"[".repeat(1000)).is_none());
+    }
+
+    #[test]
+    fn test_material_extensions() {
+        let mut material = AiMaterial::default();
+        import_material_extensions(
+            &extensions(
+                r#"{
+                    "KHR_materials_emissive_strength": {"emissiveStrength": 5},
+                    "EXT_unknown": {"value": [1, 2]}
+                }"#,
+            ),
+            &mut material,
+        );
+        assert_eq!(material.get_float(AI_MATKEY_EMISSIVE_INTENSITY), Some(5.0));
+        assert_eq!(
+            material.get_string("$gltf.ext.EXT_unknown"),
+            Some(r#"{"value":[1,2]}"#)
+        );
+        let exported = export_material_extensions(&material);
+        assert_eq!(
+            exported.keys().collect::<Vec<_>>(),
+            [KHR_MATERIALS_EMISSIVE_STRENGTH, "EXT_unknown"]
+        );
+        assert_eq!(
+            exported[KHR_MATERIALS_EMISSIVE_STRENGTH].get("emissiveStrength"),
+            Some(&JsonValue::Number(5.0))
+        );
+
+        // malformed values are kept instead of being dropped
+        let mut material = AiMaterial::default();
+        let malformed =
+            extensions(r#"{"KHR_materials_emissive_strength": {"emissiveStrength": "5"}}"#);
+        import_material_extensions(&malformed, &mut material);
+        assert_eq!(material.get_float(AI_MATKEY_EMISSIVE_INTENSITY), None);
+        assert_eq!(export_material_extensions(&material), malformed);
+    }
+
+    #[test]
+    fn test_texture_extensions() {
+        let ty = AiTextureType::BaseColor;
+        let mut material = AiMaterial::default();
+        import_texture_extensions(
+            &extensions(
+                r#"{"KHR_texture_transform": {"offset": [0.5, 0], "scale": [2, 2], "texCoord": 1}}"#,
+            ),
+            &mut material,
+            ty,
+            0,
+        );
+        let transform = material
+            .property(AI_MATKEY_UVTRANSFORM, ty, 0)
+            .and_then(AiProperty::is_uv_transform_property)
+            .unwrap();
+        assert_eq!(transform.scaling, Vec2::splat(2.0));
+        assert_eq!(
+            material.property(AI_MATKEY_UVWSRC, ty, 0),
+            Some(&AiProperty::Integer(1))
+        );
+        let exported = export_texture_extensions(&material, ty, 0);
+        let exported = &exported[KHR_TEXTURE_TRANSFORM];
+        assert_eq!(exported.numbers_or("offset", [0.0; 2]), Some([0.5, 0.0]));
+        assert_eq!(exported.numbers_or("scale", [1.0; 2]), Some([2.0, 2.0]));
+        assert!(export_texture_extensions(&material, ty, 1).is_empty());
+
+        // a rotated non-uniform scaling has no exact AiUVTransform
+        let mut material = AiMaterial::default();
+        let sheared = extensions(r#"{"KHR_texture_transform":{"rotation":0.5,"scale":[1,2]}}"#);
+        import_texture_extensions(&sheared, &mut material, ty, 0);
+        assert!(material.property(AI_MATKEY_UVTRANSFORM, ty, 0).is_none());
+        assert_eq!(export_texture_extensions(&material, ty, 0), sheared);
+    }
+
+    #[test]
+    fn test_light_extensions() {
+        let mut metadata = Metadata::default();
+        let lights = import_scene_extensions(
+            &extensions(
+                r#"{
+                    "KHR_lights_punctual": {"lights": [
+                        {"type": "point", "color": [1, 0.5, 0], "intensity": 20},
+                        {"type": "spot", "spot": {"outerConeAngle": 0.5}, "range": 10}
+                    ]},
+                    "EXT_scene": {}
+                }"#,
+            ),
+            &mut metadata,
+        );
+        assert_eq!(lights.len(), 2);
+        assert_eq!(lights[0].intensity, 20.0);
+        assert_eq!(
+            lights[1].light_type,
+            PunctualLightType::Spot {
+                inner_cone_angle: 0.0,
+                outer_cone_angle: 0.5
+            }
+        );
+        assert_eq!(lights[1].range, Some(10.0));
+        assert_eq!(
+            PunctualLight::from_json(&lights[1].to_json()),
+            Some(lights[1])
+        );
+        assert_eq!(
+            kept_extensions(&metadata).keys().collect::<Vec<_>>(),
+            ["EXT_scene"]
+        );
+
+        // one light per node referencing it, named after the node
+        let mut scene_lights = Vec::new();
+        for name in ["a", "b"] {
+            let mut node = AiNode {
+                name: name.to_owned(),
+                ..Default::default()
+            };
+            let node_extensions = extensions(r#"{"KHR_lights_punctual": {"light": 0}}"#);
+            import_node_extensions(&node_extensions, &lights, &mut node, &mut scene_lights);
+            assert!(node.metadata.is_empty());
+        }
+        assert_eq!(scene_lights.len(), 2);
+        assert_eq!(scene_lights[1].name, "b");
+        assert_eq!(scene_lights[1].light_type, LightType::Point);
+
+        // dangling references are kept
+        let mut node = AiNode::default();
+        let dangling = extensions(r#"{"KHR_lights_punctual": {"light": 2}}"#);
+        import_node_extensions(&dangling, &lights, &mut node, &mut scene_lights);
+        assert_eq!(scene_lights.len(), 2);
+        assert_eq!(kept_extensions(&node.metadata), dangling);
+    }
+}
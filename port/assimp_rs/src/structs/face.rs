@@ -2,3 +2,149 @@
 pub struct AiFace {
     pub indices: Box<[u32]>,
 }
+
+/// The faces of a mesh in a single index buffer, a compact alternative to
+/// one [`AiFace`] per face, which costs an allocation per face. See
+/// [`AiMesh::face_buffer`](super::mesh::AiMesh::face_buffer).
+///
+/// While all faces are triangles no offsets are stored, the faces are the
+/// consecutive triples of the indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AiFaceBuffer {
+    indices: Vec<u32>,
+    /// Start of each face in `indices` followed by the end of the last
+    /// face, empty while all faces are triangles
+    offsets: Vec<u32>,
+}
+
+impl AiFaceBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A buffer with room for `faces` triangles
+    pub fn with_capacity(faces: usize) -> Self {
+        Self {
+            indices: Vec::with_capacity(faces * 3),
+            offsets: Vec::new(),
+        }
+    }
+
+    pub fn from_faces(faces: &[AiFace]) -> Self {
+        let mut buffer = Self::with_capacity(faces.len());
+        for face in faces {
+            buffer.push(&face.indices);
+        }
+        buffer
+    }
+
+    /// Converts the buffer into one [`AiFace`] per face
+    pub fn to_faces(&self) -> Vec<AiFace> {
+        self.iter()
+            .map(|indices| AiFace {
+                indices: indices.into(),
+            })
+            .collect()
+    }
+
+    /// Number of faces
+    pub fn len(&self) -> usize {
+        match self.offsets.len() {
+            0 => self.indices.len() / 3,
+            n => n - 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether all faces are triangles, their indices are then
+    /// [`indices`](Self::indices) in groups of three
+    pub fn is_triangles(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// The indices of all faces
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Start of each face in [`indices`](Self::indices) followed by the
+    /// end of the last face, `None` if all faces are triangles
+    pub fn offsets(&self) -> Option<&[u32]> {
+        (!self.offsets.is_empty()).then_some(self.offsets.as_slice())
+    }
+
+    /// The indices of face `face`
+    pub fn get(&self, face: usize) -> Option<&[u32]> {
+        if self.offsets.is_empty() {
+            return self.indices.get(face * 3..face * 3 + 3);
+        }
+        let start = *self.offsets.get(face)? as usize;
+        let end = *self.offsets.get(face + 1)? as usize;
+        Some(&self.indices[start..end])
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u32]> + '_ {
+        (0..self.len()).map(|face| self.get(face).unwrap())
+    }
+
+    /// Adds a face with `indices`
+    pub fn push(&mut self, indices: &[u32]) {
+        if self.offsets.is_empty() {
+            if indices.len() == 3 {
+                self.indices.extend_from_slice(indices);
+                return;
+            }
+            // the first face which is no triangle needs the offsets of all
+            // triangles before it
+            self.offsets
+                .extend((0..=self.indices.len() as u32).step_by(3));
+        }
+        self.indices.extend_from_slice(indices);
+        self.offsets.push(self.indices.len() as u32);
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{AiFace, AiFaceBuffer};
+    use crate::structs::mesh::{AiMesh, PrimitiveTypeFlags};
+
+    #[test]
+    fn test_face_buffer() {
+        let mut buffer = AiFaceBuffer::new();
+        buffer.push(&[0, 1, 2]);
+        buffer.push(&[2, 1, 3]);
+        assert!(buffer.is_triangles());
+        assert_eq!(buffer.offsets(), None);
+        assert_eq!(buffer.get(1), Some(&[2, 1, 3][..]));
+        assert_eq!(buffer.get(2), None);
+
+        buffer.push(&[3, 4, 5, 6]);
+        buffer.push(&[6, 7]);
+        assert!(!buffer.is_triangles());
+        assert_eq!(buffer.offsets(), Some(&[0, 3, 6, 10, 12][..]));
+        assert_eq!(buffer.len(), 4);
+        let faces: Vec<_> = buffer.iter().map(<[u32]>::to_vec).collect();
+        assert_eq!(
+            faces,
+            [vec![0, 1, 2], vec![2, 1, 3], vec![3, 4, 5, 6], vec![6, 7]]
+        );
+
+        let mut mesh = AiMesh::default();
+        mesh.set_face_buffer(&buffer);
+        assert_eq!(
+            mesh.faces[2],
+            AiFace {
+                indices: [3, 4, 5, 6].into()
+            }
+        );
+        assert_eq!(
+            mesh.primitive_type,
+            PrimitiveTypeFlags::LINE | PrimitiveTypeFlags::TRIANGLE | PrimitiveTypeFlags::POLYGON
+        );
+        assert_eq!(mesh.face_buffer(), buffer);
+    }
+}
@@ -0,0 +1,131 @@
+//! Byte views of vertex and index buffers for upload to GPUs, e.g. with
+//! `Queue::write_buffer` of wgpu, and the vertex formats to describe them.
+//!
+//! Single attribute layouts of single precision builds are viewed in place
+//! with `bytemuck`, other layouts are interleaved into a new buffer.
+
+use std::borrow::Cow;
+
+use super::{
+    mesh::AiMesh,
+    vertex_layout::{IndexBuffer, VertexAttribute, VertexLayout, VertexLayoutError},
+};
+
+/// Format of an attribute in a vertex buffer, named like `wgpu::VertexFormat`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    Float32x2,
+    Float32x3,
+    Float32x4,
+    Unorm8x4,
+}
+
+impl VertexAttribute {
+    pub const fn format(self) -> VertexFormat {
+        match self {
+            Self::Position
+            | Self::Normal
+            | Self::Tangent
+            | Self::Bitangent
+            | Self::TexCoord3(_) => VertexFormat::Float32x3,
+            Self::TexCoord(_) => VertexFormat::Float32x2,
+            Self::Color(_) => VertexFormat::Float32x4,
+            Self::ColorUnorm8(_) => VertexFormat::Unorm8x4,
+        }
+    }
+}
+
+impl VertexLayout {
+    /// Byte offset and format of each attribute, with the shader location
+    /// being the position in the iterator
+    pub fn formats(&self) -> impl Iterator<Item = (usize, VertexFormat)> + '_ {
+        self.attributes().iter().scan(0, |offset, attribute| {
+            let start = *offset;
+            *offset += attribute.size();
+            Some((start, attribute.format()))
+        })
+    }
+}
+
+impl AiMesh {
+    /// The vertices with the attributes of `layout` as bytes, see
+    /// [`interleave`](Self::interleave). Layouts of a single attribute
+    /// stored as `f32` are borrowed from the mesh.
+    pub fn as_vertex_bytes(
+        &self,
+        layout: &VertexLayout,
+    ) -> Result<Cow<'_, [u8]>, VertexLayoutError> {
+        #[cfg(not(feature = "double_precision"))]
+        if let &[attribute] = layout.attributes()
+            && attribute.format() != VertexFormat::Float32x2
+            && attribute.format() != VertexFormat::Unorm8x4
+            && let Some(stream) = self.stream(attribute)
+            && stream.len() == self.vertices.len()
+        {
+            use super::vertex_layout::VertexStream;
+            return Ok(Cow::Borrowed(match stream {
+                VertexStream::Vec3(v) => bytemuck::cast_slice(v),
+                VertexStream::Color(c) => bytemuck::cast_slice(c),
+            }));
+        }
+        self.interleave(layout).map(Cow::Owned)
+    }
+}
+
+impl IndexBuffer {
+    /// The indices as bytes. wgpu needs buffer sizes which are multiples of
+    /// 4 bytes, so `u16` buffers with an odd number of indices must be
+    /// padded when uploading.
+    pub fn as_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(match self {
+            Self::U16(v) => bytemuck::cast_slice(v),
+            Self::U32(v) => bytemuck::cast_slice(v),
+        })
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::VertexFormat;
+    use crate::{
+        structs::{
+            face::AiFace,
+            mesh::AiMesh,
+            vertex_layout::{VertexAttribute as A, VertexLayout},
+        },
+        utils::float_precision::Vec3,
+    };
+
+    #[test]
+    fn test_gpu_bytes() {
+        let mut mesh = AiMesh::default();
+        mesh.vertices = vec![Vec3::X, Vec3::Y, Vec3::Z];
+        mesh.texture_coords[0] = vec![Vec3::ONE; 3];
+        mesh.faces = vec![AiFace {
+            indices: [0, 1, 2].into(),
+        }];
+
+        let positions = VertexLayout::new(&[A::Position]);
+        let bytes = mesh.as_vertex_bytes(&positions).unwrap();
+        assert_eq!(bytes.as_ref(), mesh.interleave(&positions).unwrap());
+        let in_place = cfg!(not(feature = "double_precision"));
+        assert_eq!(matches!(bytes, Cow::Borrowed(_)), in_place);
+
+        let layout = positions.with(A::TexCoord(0));
+        assert!(matches!(
+            mesh.as_vertex_bytes(&layout).unwrap(),
+            Cow::Owned(_)
+        ));
+        let formats: Vec<_> = layout.formats().collect();
+        assert_eq!(
+            formats,
+            [(0, VertexFormat::Float32x3), (12, VertexFormat::Float32x2)]
+        );
+
+        let indices = mesh.index_buffer();
+        assert_eq!(indices.as_bytes().len(), 6);
+        assert_eq!(indices.as_bytes()[2..4], 1u16.to_ne_bytes());
+    }
+}
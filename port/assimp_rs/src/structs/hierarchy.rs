@@ -0,0 +1,861 @@
+//! Editing of the node hierarchy of an [`AiScene`], keeping the `parent`
+//! and `children` links of all nodes consistent.
+
+use core::ops::Range;
+
+use crate::{
+    core::simd,
+    utils::float_precision::{Mat3, Mat4, Vec3},
+};
+
+use thiserror::Error;
+
+use super::{
+    anim::AiAnimation,
+    node::Node,
+    nodes::Index,
+    scene::{AiNode, AiScene, AiSceneFlags},
+};
+
+/// Errors of the node editing methods of [`AiScene`]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NodeError {
+    #[error("Node {0} does not exist")]
+    InvalidNode(usize),
+
+    #[error("The root node can not be moved or removed")]
+    RootNode,
+
+    #[error("The scene already has a root node")]
+    RootExists,
+
+    #[error("Node {node} can not become a child of its descendant {parent}")]
+    Cycle { node: usize, parent: usize },
+
+    #[error("New nodes can not have children, add them one by one")]
+    HasChildren,
+}
+
+/// What happens to the children of a node removed by
+/// [`AiScene::remove_node`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RemovePolicy {
+    /// The children take the place of the node in its parent. Their
+    /// transformations are combined with the one of the node, so they stay
+    /// at the same place.
+    #[default]
+    ReattachChildren,
+    /// All descendants are removed as well
+    RemoveSubtree,
+}
+
+impl AiScene {
+    fn check_node(&self, node: Index<AiNode>) -> Result<(), NodeError> {
+        if node.value() < self.nodes.len() {
+            Ok(())
+        } else {
+            Err(NodeError::InvalidNode(node.value()))
+        }
+    }
+
+    /// Whether `node` is `ancestor` or one of its descendants
+    pub fn is_descendant(&self, node: Index<AiNode>, ancestor: Index<AiNode>) -> bool {
+        let mut current = Some(node);
+        // the length limits the walk in malformed hierarchies with cycles
+        for _ in 0..=self.nodes.len() {
+            match current {
+                Some(index) if index == ancestor => return true,
+                Some(index) => current = self.get_node_by_index(index).and_then(|n| n.parent),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Adds `node` as the last child of `parent`, or as the root node if
+    /// `parent` is `None` and the scene has no root yet. The parent of
+    /// `node` is overwritten.
+    pub fn add_node(
+        &mut self,
+        parent: Option<Index<AiNode>>,
+        mut node: AiNode,
+    ) -> Result<Index<AiNode>, NodeError> {
+        if !node.children.is_empty() {
+            return Err(NodeError::HasChildren);
+        }
+        match parent {
+            Some(parent) => self.check_node(parent)?,
+            None if self.root.is_some() => return Err(NodeError::RootExists),
+            None => {}
+        }
+        node.parent = parent;
+        let index = Index::push(&mut self.nodes, node);
+        match parent {
+            Some(parent) => self.nodes[parent.value()].children.push(index),
+            None => self.root = Some(index),
+        }
+        Ok(index)
+    }
+
+    /// Adds `children` to `parent`, see [`Self::add_node`]. Nothing is added
+    /// if one of the nodes can not be added.
+    pub fn add_children(
+        &mut self,
+        parent: Index<AiNode>,
+        children: Vec<AiNode>,
+    ) -> Result<Vec<Index<AiNode>>, NodeError> {
+        self.check_node(parent)?;
+        if children.iter().any(|child| !child.children.is_empty()) {
+            return Err(NodeError::HasChildren);
+        }
+        children
+            .into_iter()
+            .map(|child| self.add_node(Some(parent), child))
+            .collect()
+    }
+
+    /// Moves `node` to the end of the children of `new_parent`. The local
+    /// transformation is kept, so the node moves with its new parent.
+    pub fn reparent(
+        &mut self,
+        node: Index<AiNode>,
+        new_parent: Index<AiNode>,
+    ) -> Result<(), NodeError> {
+        self.check_node(node)?;
+        self.check_node(new_parent)?;
+        let Some(old_parent) = self.nodes[node.value()].parent else {
+            return Err(NodeError::RootNode);
+        };
+        if self.is_descendant(new_parent, node) {
+            return Err(NodeError::Cycle {
+                node: node.value(),
+                parent: new_parent.value(),
+            });
+        }
+        if old_parent == new_parent {
+            return Ok(());
+        }
+        if let Some(old_parent) = self.get_node_by_index_mut(old_parent) {
+            old_parent.children.retain(|&child| child != node);
+        }
+        self.nodes[new_parent.value()].children.push(node);
+        self.nodes[node.value()].parent = Some(new_parent);
+        Ok(())
+    }
+
+    /// Removes `node` from the scene and returns it without parent and
+    /// children. The indices of all nodes after it change, references to
+    /// removed nodes in skeletons and bones of meshes are cleared.
+    pub fn remove_node(
+        &mut self,
+        node: Index<AiNode>,
+        policy: RemovePolicy,
+    ) -> Result<AiNode, NodeError> {
+        self.check_node(node)?;
+        let Some(parent) = self.nodes[node.value()].parent else {
+            return Err(NodeError::RootNode);
+        };
+
+        let mut removed = vec![false; self.nodes.len()];
+        removed[node.value()] = true;
+        let children = core::mem::take(&mut self.nodes[node.value()].children);
+        match policy {
+            RemovePolicy::ReattachChildren => {
+                let transformation = self.nodes[node.value()].transformation;
+                for &child in &children {
+                    if let Some(child) = self.get_node_by_index_mut(child) {
+                        child.parent = Some(parent);
+                        // row layout, so this is `transformation * child`
+                        child.transformation *= transformation;
+                    }
+                }
+                if let Some(parent) = self.get_node_by_index_mut(parent)
+                    && let Some(position) = parent.children.iter().position(|&c| c == node)
+                {
+                    parent.children.splice(position..=position, children);
+                }
+            }
+            RemovePolicy::RemoveSubtree => {
+                let mut stack = children;
+                while let Some(index) = stack.pop() {
+                    if let Some(flag) = removed.get_mut(index.value())
+                        && !*flag
+                    {
+                        *flag = true;
+                        stack.extend(&self.nodes[index.value()].children);
+                    }
+                }
+                if let Some(parent) = self.get_node_by_index_mut(parent) {
+                    parent.children.retain(|&child| child != node);
+                }
+            }
+        }
+
+        let mut new_index = Vec::with_capacity(removed.len());
+        let mut count = 0;
+        for &removed in &removed {
+            new_index.push((!removed).then(|| Index::new(count)));
+            count += u32::from(!removed);
+        }
+        let remap = |index: Index<AiNode>| new_index.get(index.value()).copied().flatten();
+
+        let mut result = core::mem::take(&mut self.nodes[node.value()]);
+        result.parent = None;
+        let nodes = core::mem::take(&mut self.nodes);
+        for (i, mut current) in nodes.into_iter().enumerate() {
+            if !removed[i] {
+                current.parent = current.parent.and_then(remap);
+                current.children.retain_mut(|child| match remap(*child) {
+                    Some(new) => {
+                        *child = new;
+                        true
+                    }
+                    None => false,
+                });
+                self.nodes.push(current);
+            }
+        }
+        self.root = self.root.and_then(remap);
+        for bone in self.skeletons.iter_mut().flat_map(|s| s.bones.iter_mut()) {
+            bone.node = bone.node.and_then(remap);
+            bone.armature = bone.armature.and_then(remap);
+        }
+        // bones of meshes index the nodes with `Index<Node>`
+        let remap_bone = |index: Index<Node>| {
+            remap(Index::new(index.value() as u32)).map(|index| Index::new(index.value() as u32))
+        };
+        for bone in self.meshes.iter_mut().flat_map(|m| m.bones.iter_mut()) {
+            bone.node = bone.node.and_then(remap_bone);
+            bone.armature = bone.armature.and_then(remap_bone);
+        }
+        Ok(result)
+    }
+
+    /// Moves all nodes, meshes, materials, textures, animations, lights,
+    /// cameras and skeletons of `other` into this scene. The root of `other`
+    /// becomes the last child of `under`, the indices stored in the moved
+    /// data are rebased. Returns the new index of the root of `other`.
+    pub fn merge(
+        &mut self,
+        other: AiScene,
+        under: Index<AiNode>,
+    ) -> Result<Option<Index<AiNode>>, NodeError> {
+        self.check_node(under)?;
+        let node_offset = self.nodes.len() as u32;
+        let mesh_offset = self.meshes.len() as u32;
+        let material_offset = self.materials.len() as u32;
+        let texture_offset = self.textures.len();
+        let node = |index: Index<AiNode>| Index::new(index.value() as u32 + node_offset);
+
+        let root = other.root.map(node);
+        self.nodes
+            .extend(other.nodes.into_iter().map(|mut current| {
+                current.parent = current.parent.map(node);
+                current
+                    .children
+                    .iter_mut()
+                    .for_each(|child| *child = node(*child));
+                current.meshes =
+                    current.meshes.start + mesh_offset..current.meshes.end + mesh_offset;
+                current
+            }));
+        if let Some(root) = root {
+            self.nodes[root.value()].parent = Some(under);
+            self.nodes[under.value()].children.push(root);
+        }
+
+        self.meshes.extend(other.meshes.into_iter().map(|mut mesh| {
+            mesh.material_index += material_offset;
+            for bone in &mut mesh.bones {
+                bone.node = bone
+                    .node
+                    .map(|n| Index::new(n.value() as u32 + node_offset));
+                bone.armature = bone
+                    .armature
+                    .map(|n| Index::new(n.value() as u32 + node_offset));
+            }
+            mesh
+        }));
+        self.materials
+            .extend(other.materials.into_iter().map(|mut material| {
+                // embedded textures are referenced as `*<index>`
+                for property in &mut material.properties {
+                    if let Some(path) = property.texture_path_mut()
+                        && let Some(index) = path.strip_prefix('*')
+                        && let Ok(index) = index.parse::<usize>()
+                    {
+                        *path = format!("*{}", index + texture_offset);
+                    }
+                }
+                material
+            }));
+        self.textures.extend(other.textures);
+        self.animations.extend(other.animations);
+        self.lights.extend(other.lights);
+        self.cameras.extend(other.cameras);
+        self.skeletons
+            .extend(other.skeletons.into_iter().map(|mut skeleton| {
+                for bone in skeleton.bones.iter_mut() {
+                    bone.node = bone.node.map(node);
+                    bone.armature = bone.armature.map(node);
+                    bone.mesh_id = Index::new(bone.mesh_id.value() as u32 + mesh_offset);
+                }
+                skeleton
+            }));
+        self.flags |= other.flags & AiSceneFlags::INCOMPLETE;
+        Ok(root)
+    }
+
+    /// Copies the subtree of `node` into a standalone scene with `node` as
+    /// its root. Only the meshes, materials, embedded textures, animation
+    /// channels, lights, cameras and skeletons referenced by the subtree are
+    /// copied, their indices are rebased. The root keeps its local
+    /// transformation.
+    pub fn extract_subtree(&self, node: Index<AiNode>) -> Result<AiScene, NodeError> {
+        self.check_node(node)?;
+        let mut scene = AiScene::new();
+        scene.flags = self.flags;
+        scene.name = self.nodes[node.value()].name.as_str().into();
+
+        // nodes in depth first order, parents before their children
+        let mut new_index = vec![None; self.nodes.len()];
+        let mut order = Vec::new();
+        let mut stack = vec![node];
+        while let Some(index) = stack.pop() {
+            if let Some(slot) = new_index.get_mut(index.value())
+                && slot.is_none()
+            {
+                *slot = Some(Index::<AiNode>::new(order.len() as u32));
+                order.push(index);
+                stack.extend(self.nodes[index.value()].children.iter().rev());
+            }
+        }
+        let remap = |index: Index<AiNode>| new_index.get(index.value()).copied().flatten();
+        let bone_remap = |index: Index<Node>| {
+            remap(Index::new(index.value() as u32)).map(|new| Index::new(new.value() as u32))
+        };
+
+        // shared ranges stay shared
+        let mut ranges = Vec::<(Range<u32>, Range<u32>)>::new();
+        let mut mesh_index = vec![None; self.meshes.len()];
+        for &index in &order {
+            let old = &self.nodes[index.value()];
+            let meshes = match ranges.iter().find(|(from, _)| *from == old.meshes) {
+                Some((_, to)) => to.clone(),
+                None => {
+                    let start = scene.meshes.len() as u32;
+                    for mesh in old.meshes.clone() {
+                        if let Some(slot) = mesh_index.get_mut(mesh as usize) {
+                            slot.get_or_insert(scene.meshes.len() as u32);
+                        }
+                        scene.meshes.extend(self.meshes.get(mesh as usize).cloned());
+                    }
+                    let to = start..scene.meshes.len() as u32;
+                    ranges.push((old.meshes.clone(), to.clone()));
+                    to
+                }
+            };
+            scene.nodes.push(AiNode {
+                name: old.name.clone(),
+                transformation: old.transformation,
+                parent: old.parent.and_then(remap).filter(|_| index != node),
+                children: old.children.iter().filter_map(|&c| remap(c)).collect(),
+                meshes,
+                metadata: old.metadata.clone(),
+            });
+        }
+        scene.root = Some(Index::new(0));
+
+        let mut material_index = vec![None; self.materials.len()];
+        let mut texture_index = vec![None; self.textures.len()];
+        for mesh in &mut scene.meshes {
+            let old = mesh.material_index as usize;
+            if let Some(slot) = material_index.get_mut(old) {
+                mesh.material_index = *slot.get_or_insert_with(|| {
+                    scene.materials.push(self.materials[old].clone());
+                    scene.materials.len() as u32 - 1
+                });
+            }
+            for bone in &mut mesh.bones {
+                bone.node = bone.node.and_then(bone_remap);
+                bone.armature = bone.armature.and_then(bone_remap);
+            }
+        }
+        for material in &mut scene.materials {
+            for property in &mut material.properties {
+                if let Some(path) = property.texture_path_mut()
+                    && let Some(old) = path.strip_prefix('*')
+                    && let Ok(old) = old.parse::<usize>()
+                    && let Some(slot) = texture_index.get_mut(old)
+                {
+                    let new = *slot.get_or_insert_with(|| {
+                        scene.textures.push(self.textures[old].clone());
+                        scene.textures.len() - 1
+                    });
+                    *path = format!("*{new}");
+                }
+            }
+        }
+
+        let has_node = |name: &str| scene.nodes.iter().any(|n| n.name == name);
+        let has_mesh = |name: &str| scene.meshes.iter().any(|m| m.name == name);
+        for animation in &self.animations {
+            let mut copy = AiAnimation {
+                name: animation.name.clone(),
+                duration: animation.duration,
+                ticks_per_second: animation.ticks_per_second,
+                ..Default::default()
+            };
+            copy.channels = animation
+                .channels
+                .iter()
+                .filter(|c| has_node(&c.node_name))
+                .cloned()
+                .collect();
+            copy.mesh_channels = animation
+                .mesh_channels
+                .iter()
+                .filter(|c| has_mesh(&c.name))
+                .cloned()
+                .collect();
+            copy.morph_mesh_channels = animation
+                .morph_mesh_channels
+                .iter()
+                .filter(|c| has_mesh(&c.name))
+                .cloned()
+                .collect();
+            if !(copy.channels.is_empty()
+                && copy.mesh_channels.is_empty()
+                && copy.morph_mesh_channels.is_empty())
+            {
+                scene.animations.push(copy);
+            }
+        }
+        let lights = self.lights.iter().filter(|l| has_node(&l.name)).cloned();
+        let cameras = self.cameras.iter().filter(|c| has_node(&c.name)).cloned();
+        scene.lights = lights.collect();
+        scene.cameras = cameras.collect();
+
+        for skeleton in &self.skeletons {
+            let kept = skeleton.bones.iter().all(|bone| {
+                mesh_index
+                    .get(bone.mesh_id.value())
+                    .is_some_and(Option::is_some)
+            });
+            if kept {
+                let mut skeleton = skeleton.clone();
+                for bone in skeleton.bones.iter_mut() {
+                    bone.node = bone.node.and_then(remap);
+                    bone.armature = bone.armature.and_then(remap);
+                    bone.mesh_id = Index::new(mesh_index[bone.mesh_id.value()].unwrap_or_default());
+                }
+                scene.skeletons.push(skeleton);
+            }
+        }
+        Ok(scene)
+    }
+
+    /// The transformation of every node relative to the root, indexed like
+    /// [`AiScene::nodes`], stored row by row like the node transformations.
+    /// Each is the product of the transformations of the node and its
+    /// parents, like in [`SkinningPalette`](super::anim::skinning::SkinningPalette).
+    /// Nodes not reachable from the root keep the identity.
+    pub fn compute_global_transforms(&self) -> Vec<Mat4> {
+        let mut globals = vec![Mat4::IDENTITY; self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack: Vec<_> = self.root.into_iter().map(|r| (r, Mat4::IDENTITY)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let Some(node) = self.get_node_by_index(index) else {
+                continue;
+            };
+            if core::mem::replace(&mut visited[index.value()], true) {
+                continue;
+            }
+            // row layout, so this is `parent * local`
+            let global = node.transformation * parent;
+            globals[index.value()] = global;
+            stack.extend(node.children.iter().map(|&child| (child, global)));
+        }
+        globals
+    }
+
+    /// Applies `matrix`, stored row by row like the node transformations, to
+    /// everything below `node` in its local space, while the node keeps its
+    /// own transformation: the children and their animation channels are
+    /// transformed, as are the vertices and bone offsets of the meshes of
+    /// the node. The meshes must not be used by other nodes.
+    ///
+    /// Animation channels are split into separate keys, so a `matrix` with
+    /// a non-uniform scale can only be applied approximately to rotated
+    /// channels.
+    pub fn bake_transform(&mut self, node: Index<AiNode>, matrix: Mat4) -> Result<(), NodeError> {
+        self.check_node(node)?;
+        // the rows of `matrix` are the columns of the glam matrix
+        let transform = matrix.transpose();
+        let inverse = matrix.inverse();
+        // the inverse transpose of `transform`
+        let normal_matrix = Mat3::from_mat4(inverse);
+        let direction = |d: &mut [Vec3]| {
+            d.iter_mut().for_each(|d| *d = normal_matrix * *d);
+            simd::normalize(d);
+        };
+        let tangent = |d: &mut [Vec3]| {
+            d.iter_mut()
+                .for_each(|d| *d = transform.transform_vector3(*d));
+            simd::normalize(d);
+        };
+
+        let meshes = self.nodes[node.value()].meshes.clone();
+        for mesh in meshes {
+            let Some(mesh) = self.meshes.get_mut(mesh as usize) else {
+                continue;
+            };
+            for v in mesh.vertices.iter_mut() {
+                *v = transform.transform_point3(*v);
+            }
+            direction(&mut mesh.normals);
+            tangent(&mut mesh.tangents);
+            tangent(&mut mesh.bitangents);
+            mesh.aabb = mesh.aabb.transformed(transform);
+            for anim_mesh in mesh.anim_meshes.iter_mut() {
+                for v in anim_mesh.vertices.iter_mut() {
+                    *v = transform.transform_point3(*v);
+                }
+                direction(&mut anim_mesh.normals);
+                tangent(&mut anim_mesh.tangents);
+                tangent(&mut anim_mesh.bitangents);
+            }
+            // offsets map from mesh space, which is now transformed, row
+            // layout, so this is `offset * inverse`
+            for bone in mesh.bones.iter_mut() {
+                bone.offset_matrix = inverse * bone.offset_matrix;
+            }
+        }
+
+        let children = self.nodes[node.value()].children.clone();
+        let (_, rotation, scale) = transform.to_scale_rotation_translation();
+        let uniform = (scale.x - scale.y).abs() < 1e-5 && (scale.x - scale.z).abs() < 1e-5;
+        for &child in &children {
+            let Some(child) = self.nodes.get_mut(child.value()) else {
+                continue;
+            };
+            // row layout, so this is `matrix * child`
+            child.transformation *= matrix;
+            let name = &child.name;
+            for channel in self
+                .animations
+                .iter_mut()
+                .flat_map(|a| a.channels.iter_mut())
+                .filter(|c| *c.node_name == *name)
+            {
+                if !uniform && !channel.rotation_keys.is_empty() {
+                    log::warn!("Non-uniform scale baked approximately into channel {name}");
+                }
+                for key in channel.position_keys.iter_mut() {
+                    key.value = transform.transform_point3(key.value);
+                }
+                for key in channel.rotation_keys.iter_mut() {
+                    key.value = (rotation * key.value).normalize();
+                }
+                for key in channel.scaling_keys.iter_mut() {
+                    key.value *= scale;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{NodeError, RemovePolicy};
+    use crate::{
+        compose, decompose,
+        postprocess::validate::ValidateDSProcess,
+        structs::{
+            anim::{AiAnimation, anim::AiNodeAnim, skinning::SkinningPalette},
+            bone::AiBone,
+            face::AiFace,
+            key::AiVectorKey,
+            material::AiMaterial,
+            mesh::AiMesh,
+            nodes::Index,
+            scene::{AiNode, AiScene},
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Quat, Vec3},
+    };
+
+    #[test]
+    fn test_node_editing() {
+        // the node transformations are stored row by row, like imported ones
+        let rotation = Quat::from_rotation_z(core::f32::consts::FRAC_PI_2 as _);
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", Mat4::IDENTITY, None);
+        let a = builder.add_node("a", compose(Vec3::X, rotation, Vec3::ONE), Some(root));
+        let b = builder.add_node("b", compose(Vec3::Y, Quat::IDENTITY, Vec3::ONE), Some(a));
+        let c = builder.add_node("c", Mat4::IDENTITY, Some(root));
+        let bone = |name: &str, node: Index<AiNode>| AiBone {
+            name: name.to_owned(),
+            armature: Some(Index::new(root.value() as u32)),
+            node: Some(Index::new(node.value() as u32)),
+            ..Default::default()
+        };
+        let mesh = builder.add_mesh(AiMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2]),
+            }],
+            bones: vec![bone("a", a), bone("b", b), bone("c", c)],
+            ..Default::default()
+        });
+        builder.attach_mesh(root, mesh);
+        let mut scene = builder.build().unwrap();
+        let names = |scene: &AiScene, node: Index<AiNode>| {
+            scene.nodes[node.value()]
+                .children
+                .iter()
+                .map(|&child| scene.nodes[child.value()].name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            scene.add_node(None, AiNode::default()),
+            Err(NodeError::RootExists)
+        );
+        let d = scene
+            .add_node(
+                Some(b),
+                AiNode {
+                    name: "d".to_owned(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(scene.nodes[d.value()].parent, Some(b));
+        assert_eq!(
+            scene.reparent(a, d),
+            Err(NodeError::Cycle {
+                node: a.value(),
+                parent: d.value()
+            })
+        );
+        assert_eq!(scene.reparent(root, c), Err(NodeError::RootNode));
+        scene.reparent(d, c).unwrap();
+        assert_eq!(names(&scene, b), Vec::<String>::new());
+        assert_eq!(names(&scene, c), ["d"]);
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+
+        // b moves up to the root and keeps its place in the world
+        let global = scene.compute_global_transforms()[b.value()];
+        let removed = scene
+            .remove_node(a, RemovePolicy::ReattachChildren)
+            .unwrap();
+        assert_eq!(removed.name, "a");
+        let b = scene.find_node_by_name("b", root).unwrap();
+        assert_eq!(names(&scene, root), ["b", "c"]);
+        assert!(scene.compute_global_transforms()[b.value()].abs_diff_eq(global, 1e-6));
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+        // the bones follow the new indices of their nodes
+        let c = scene.find_node_by_name("c", root).unwrap();
+        let nodes = |scene: &AiScene| {
+            scene.meshes[0]
+                .bones
+                .iter()
+                .map(|bone| {
+                    (
+                        bone.node.map(|n| n.value()),
+                        bone.armature.map(|n| n.value()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            nodes(&scene),
+            [
+                (None, Some(0)),
+                (Some(b.value()), Some(0)),
+                (Some(c.value()), Some(0))
+            ]
+        );
+
+        scene.remove_node(c, RemovePolicy::RemoveSubtree).unwrap();
+        assert_eq!(scene.nodes.len(), 2);
+        assert_eq!(names(&scene, root), ["b"]);
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+        assert_eq!(
+            nodes(&scene),
+            [(None, Some(0)), (Some(b.value()), Some(0)), (None, Some(0))]
+        );
+        assert_eq!(
+            scene
+                .remove_node(Index::new(5), RemovePolicy::default())
+                .unwrap_err(),
+            NodeError::InvalidNode(5)
+        );
+    }
+
+    #[test]
+    fn test_global_transforms_and_bake() {
+        // the node transformations are stored row by row, like imported ones
+        let rotation = Quat::from_rotation_z(core::f32::consts::FRAC_PI_2 as _);
+        let translation = |t: Vec3| compose(t, Quat::IDENTITY, Vec3::ONE);
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", compose(Vec3::ZERO, rotation, Vec3::ONE), None);
+        let arm = builder.add_node("arm", translation(Vec3::X), Some(root));
+        let hand = builder.add_node("hand", translation(Vec3::Y * 5.0), Some(arm));
+        let mesh = builder.add_mesh(AiMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2]),
+            }],
+            bones: vec![AiBone {
+                name: "hand".to_owned(),
+                offset_matrix: translation(Vec3::Z),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        builder.attach_mesh(root, mesh);
+        let mut scene = builder.build().unwrap();
+        scene.animations.push(AiAnimation {
+            channels: vec![AiNodeAnim {
+                node_name: "arm".into(),
+                position_keys: vec![AiVectorKey::new(0.0, Vec3::X)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        let globals = scene.compute_global_transforms();
+        // (1, 5, 0) in the space of the root, rotated about z
+        let (position, _, _) = decompose(globals[hand.value()]);
+        assert!(position.abs_diff_eq(Vec3::new(-5.0, 1.0, 0.0), 1e-5));
+        let palette = SkinningPalette::new(&scene);
+        for (index, global) in globals.iter().enumerate() {
+            let expected = palette.global_transform(Index::new(index as u32));
+            assert!(global.abs_diff_eq(expected.unwrap(), 1e-5));
+        }
+
+        let scale = compose(Vec3::ZERO, Quat::IDENTITY, Vec3::splat(2.0));
+        scene.bake_transform(root, scale).unwrap();
+        let baked = scene.compute_global_transforms();
+        assert_eq!(baked[root.value()], globals[root.value()]);
+        // the children are scaled in the space of the root
+        let (position, _, _) = decompose(baked[hand.value()]);
+        assert!(position.abs_diff_eq(Vec3::new(-10.0, 2.0, 0.0), 1e-5));
+        assert_eq!(scene.meshes[0].vertices[1], Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(
+            scene.animations[0].channels[0].position_keys[0].value,
+            Vec3::new(2.0, 0.0, 0.0)
+        );
+        // the bone maps the baked vertices like the original ones
+        let offset = scene.meshes[0].bones[0].offset_matrix.transpose();
+        assert!(
+            offset
+                .transform_point3(scene.meshes[0].vertices[1])
+                .abs_diff_eq(Vec3::new(1.0, 0.0, 1.0), 1e-6)
+        );
+    }
+
+    #[cfg(feature = "x_file")]
+    #[test]
+    fn test_merge_scenes() {
+        use std::fs;
+
+        use crate::{
+            formats::x::importer::Importer, get_model_path,
+            traits::importer::trait_define::Importer as _,
+        };
+
+        let mut scene =
+            *Importer::read_from_buf(&fs::read(get_model_path("X", "test_cube_text.x")).unwrap())
+                .unwrap();
+        let other = *Importer::read_from_buf(
+            &fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap(),
+        )
+        .unwrap();
+        let (nodes, meshes, materials) =
+            (scene.nodes.len(), scene.meshes.len(), scene.materials.len());
+        let other_root = other.root.unwrap();
+        let other_meshes = other.meshes.clone();
+        let other_node_count = other.nodes.len();
+
+        assert_eq!(
+            scene.merge(other.clone(), Index::new(nodes as u32)),
+            Err(NodeError::InvalidNode(nodes))
+        );
+        let root = scene.root.unwrap();
+        let merged = scene.merge(other, root).unwrap().unwrap();
+        assert_eq!(merged.value(), nodes + other_root.value());
+        assert_eq!(scene.nodes[merged.value()].parent, Some(root));
+        assert_eq!(scene.nodes[root.value()].children.last(), Some(&merged));
+        assert_eq!(scene.nodes.len(), nodes + other_node_count);
+        assert_eq!(scene.meshes.len(), meshes + other_meshes.len());
+        for node in &scene.nodes[nodes..] {
+            assert!(node.meshes.is_empty() || node.meshes.start as usize >= meshes);
+        }
+        for (mesh, original) in scene.meshes[meshes..].iter().zip(&other_meshes) {
+            assert_eq!(
+                mesh.material_index as usize,
+                original.material_index as usize + materials
+            );
+            assert_eq!(mesh.faces.len(), original.faces.len());
+        }
+        assert!(ValidateDSProcess::validate(&scene).is_valid());
+    }
+
+    #[cfg(feature = "x_file")]
+    #[test]
+    fn test_extract_subtree() {
+        use std::fs;
+
+        use crate::{
+            formats::x::importer::Importer, get_model_path, structs::diff::Tolerance,
+            traits::importer::trait_define::Importer as _,
+        };
+
+        let scene =
+            *Importer::read_from_buf(&fs::read(get_model_path("X", "anim_test.x")).unwrap())
+                .unwrap();
+        let root = scene.root.unwrap();
+        assert_eq!(
+            scene
+                .extract_subtree(Index::new(scene.nodes.len() as u32))
+                .err(),
+            Some(NodeError::InvalidNode(scene.nodes.len()))
+        );
+
+        let whole = scene.extract_subtree(root).unwrap();
+        assert_eq!(scene.diff(&whole, Tolerance::EXACT), []);
+
+        let (index, node) = scene
+            .nodes
+            .iter()
+            .enumerate()
+            .find(|(i, n)| *i != root.value() && !n.meshes.is_empty())
+            .unwrap();
+        let part = scene.extract_subtree(Index::new(index as u32)).unwrap();
+        assert_eq!(part.nodes[0].name, node.name);
+        assert_eq!(part.nodes[0].parent, None);
+        assert!(part.nodes.len() < scene.nodes.len());
+        assert_eq!(part.meshes.len() as u32, {
+            let mut count = 0;
+            let mut stack = vec![Index::<AiNode>::new(index as u32)];
+            while let Some(n) = stack.pop() {
+                count += scene.nodes[n.value()].meshes.len() as u32;
+                stack.extend(&scene.nodes[n.value()].children);
+            }
+            count
+        });
+        assert!(part.materials.len() <= scene.materials.len());
+        for animation in &part.animations {
+            for channel in &animation.channels {
+                assert!(part.nodes.iter().any(|n| n.name == *channel.node_name));
+            }
+        }
+        assert!(ValidateDSProcess::validate(&part).is_valid());
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use crate::{AiReal, utils::float_precision::Mat4};
+
+pub mod report;
+
+/// Maximum angle between two face normals at a vertex for them to be
+/// smoothed by `GenSmoothNormals`, in degrees. Defaults to 175.
+pub const AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE: &str = "PP_GSN_MAX_SMOOTHING_ANGLE";
+
+/// Discard imported normals and always regenerate them. Flat normals are
+/// generated if `GenNormals` is requested, smooth normals otherwise.
+pub const AI_CONFIG_PP_FORCE_GEN_NORMALS: &str = "PP_FORCE_GEN_NORMALS";
+
+/// Components to be removed by the `RemoveComponent` step, a combination of
+/// `AiComponent` flags.
+pub const AI_CONFIG_PP_RVC_FLAGS: &str = "PP_RVC_FLAGS";
+
+/// Size of the post-transform vertex cache to optimize for in
+/// `ImproveCacheLocality`.
+pub const AI_CONFIG_PP_ICL_PTCACHE_SIZE: &str = "PP_ICL_PTCACHE_SIZE";
+
+/// Maximum distance of two vertices to be joined by
+/// `JoinIdenticalVertices`, applied to the positions and all other
+/// attributes. Defaults to 0, only identical vertices are joined.
+pub const AI_CONFIG_PP_JIV_EPSILON: &str = "PP_JIV_EPSILON";
+
+/// Let `JoinIdenticalVertices` join all vertices within the epsilon by
+/// their position only and average their other attributes, to close
+/// cracks in scanned meshes.
+pub const AI_CONFIG_PP_JIV_POSITION_ONLY: &str = "PP_JIV_POSITION_ONLY";
+
+/// Keep the data read so far if a file turns out to be truncated instead of
+/// failing the whole import. The scene is marked as incomplete then.
+pub const AI_CONFIG_IMPORT_ALLOW_PARTIAL: &str = "ALLOW_PARTIAL";
+
+/// Parse X files strictly by the specification instead of tolerating the
+/// quirks of known exporters. Useful to validate files.
+pub const AI_CONFIG_IMPORT_X_STRICT_PARSING: &str = "IMPORT_X_STRICT_PARSING";
+
+/// Upper bound in bytes for the memory a parser may allocate for the data
+/// of one file, including the decompressed copy of compressed files. Files
+/// exceeding it fail with `InsufficientMemory` instead of trusting the
+/// element counts they claim. Zero or negative values mean no limit, except
+/// for files decompressed transparently, which are limited to 1 GiB then.
+pub const AI_CONFIG_IMPORT_MAX_ALLOC_BYTES: &str = "IMPORT_MAX_ALLOC_BYTES";
+
+/// How importers deal with deviations from the specification and
+/// inconsistent data: 0 tolerates the quirks of known exporters (the
+/// default), 1 fails on every deviation and 2 repairs inconsistent data as
+/// well. See [`report::ErrorPolicy`] and [`report::ImportReport`].
+pub const AI_CONFIG_IMPORT_ERROR_POLICY: &str = "IMPORT_ERROR_POLICY";
+
+/// Codepage of text files which are not valid UTF-8, as a WHATWG label like
+/// `shift_jis` or `windows-1252`. Without it Shift-JIS or Windows-1252 is
+/// detected. Requires the `encoding` feature.
+pub const AI_CONFIG_IMPORT_TEXT_ENCODING: &str = "IMPORT_TEXT_ENCODING";
+
+type KeyType = u64;
+
+type IntPropertyMap = BTreeMap<KeyType, i32>;
+type FloatPropertyMap = BTreeMap<KeyType, AiReal>;
+type StringPropertyMap = BTreeMap<KeyType, String>;
+type MatrixPropertyMap = BTreeMap<KeyType, Mat4>;
+
+fn hash_key(key: &str) -> KeyType {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Configuration of the import and the post-processing steps
+#[derive(Debug, Default, Clone)]
+pub struct ImportProperties {
+    int_properties: IntPropertyMap,
+    float_properties: FloatPropertyMap,
+    string_properties: StringPropertyMap,
+    matrix_properties: MatrixPropertyMap,
+}
+
+impl ImportProperties {
+    pub fn set_bool(&mut self, key: &str, value: bool) {
+        self.set_int(key, value as i32);
+    }
+
+    pub fn set_int(&mut self, key: &str, value: i32) {
+        self.int_properties.insert(hash_key(key), value);
+    }
+
+    pub fn set_float(&mut self, key: &str, value: AiReal) {
+        self.float_properties.insert(hash_key(key), value);
+    }
+
+    pub fn set_string(&mut self, key: &str, value: impl Into<String>) {
+        self.string_properties.insert(hash_key(key), value.into());
+    }
+
+    pub fn set_matrix(&mut self, key: &str, value: Mat4) {
+        self.matrix_properties.insert(hash_key(key), value);
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        self.get_int(key) != 0
+    }
+
+    pub fn get_int(&self, key: &str) -> i32 {
+        self.get_int_or(key, 0)
+    }
+
+    pub fn get_int_or(&self, key: &str, default: i32) -> i32 {
+        *self.int_properties.get(&hash_key(key)).unwrap_or(&default)
+    }
+
+    pub fn get_float_or(&self, key: &str, default: AiReal) -> AiReal {
+        *self
+            .float_properties
+            .get(&hash_key(key))
+            .unwrap_or(&default)
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.string_properties
+            .get(&hash_key(key))
+            .map(String::as_str)
+    }
+
+    /// The limit of [`AI_CONFIG_IMPORT_MAX_ALLOC_BYTES`], `None` if there
+    /// is none
+    pub fn max_alloc_bytes(&self) -> Option<usize> {
+        usize::try_from(self.get_int(AI_CONFIG_IMPORT_MAX_ALLOC_BYTES))
+            .ok()
+            .filter(|&bytes| bytes > 0)
+    }
+
+    pub fn get_matrix(&self, key: &str) -> Option<&Mat4> {
+        self.matrix_properties.get(&hash_key(key))
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{
+        AI_CONFIG_PP_FORCE_GEN_NORMALS, AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE,
+        AI_CONFIG_PP_ICL_PTCACHE_SIZE, ImportProperties,
+    };
+    use crate::utils::float_precision::Mat4;
+
+    #[test]
+    fn test_import_properties() {
+        let mut properties = ImportProperties::default();
+        assert!(!properties.get_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS));
+        assert_eq!(properties.get_int_or(AI_CONFIG_PP_ICL_PTCACHE_SIZE, 12), 12);
+        assert_eq!(properties.get_string("name"), None);
+        assert_eq!(properties.get_matrix("matrix"), None);
+
+        properties.set_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS, true);
+        properties.set_int(AI_CONFIG_PP_ICL_PTCACHE_SIZE, 24);
+        properties.set_float(AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE, 80.0);
+        properties.set_string("name", "value");
+        properties.set_matrix("matrix", Mat4::from_scale([2.0; 3].into()));
+        assert!(properties.get_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS));
+        assert_eq!(properties.get_int(AI_CONFIG_PP_FORCE_GEN_NORMALS), 1);
+        assert_eq!(properties.get_int(AI_CONFIG_PP_ICL_PTCACHE_SIZE), 24);
+        assert_eq!(
+            properties.get_float_or(AI_CONFIG_PP_GSN_MAX_SMOOTHING_ANGLE, 175.0),
+            80.0
+        );
+        assert_eq!(properties.get_string("name"), Some("value"));
+        assert_eq!(properties.get_matrix("matrix").unwrap().x_axis.x, 2.0);
+
+        // every type has its own map, keys compare by their full string
+        assert_eq!(properties.get_int("name"), 0);
+        assert_eq!(
+            properties.get_float_or(AI_CONFIG_PP_ICL_PTCACHE_SIZE, 1.0),
+            1.0
+        );
+        assert_eq!(properties.get_string("Name"), None);
+        assert_eq!(properties.get_int("PP_ICL_PTCACHE_SIZE "), 0);
+
+        // setting a key again replaces the value
+        properties.set_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS, false);
+        properties.set_string("name", String::from("other"));
+        assert!(!properties.get_bool(AI_CONFIG_PP_FORCE_GEN_NORMALS));
+        assert_eq!(properties.get_string("name"), Some("other"));
+    }
+}
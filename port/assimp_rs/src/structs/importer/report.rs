@@ -0,0 +1,187 @@
+//! Problems found while importing a file and how the importer dealt with
+//! them.
+
+use core::fmt;
+
+use super::{AI_CONFIG_IMPORT_ERROR_POLICY, AI_CONFIG_IMPORT_X_STRICT_PARSING, ImportProperties};
+use crate::{
+    core::profiler::{ProfileEntry, Profiler},
+    structs::{
+        meta::{AI_METADATA_SOURCE_FORMAT, AI_METADATA_SOURCE_FORMAT_VERSION, MetadataExt},
+        scene::AiScene,
+    },
+};
+
+/// What an importer does about files which deviate from the specification
+/// or contain inconsistent data, see [`AI_CONFIG_IMPORT_ERROR_POLICY`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Every deviation from the specification is an error, meant for
+    /// validating files
+    FailFast,
+    /// The quirks of known exporters are tolerated and reported as
+    /// warnings, inconsistent data is an error
+    #[default]
+    CollectWarnings,
+    /// Inconsistent data is repaired as well if possible, e.g. per-vertex
+    /// data of the wrong length is padded or cut
+    BestEffort,
+}
+
+impl ErrorPolicy {
+    /// The policy set by [`AI_CONFIG_IMPORT_ERROR_POLICY`], or
+    /// [`ErrorPolicy::FailFast`] for [`AI_CONFIG_IMPORT_X_STRICT_PARSING`]
+    pub fn from_properties(properties: &ImportProperties) -> Self {
+        if properties.get_bool(AI_CONFIG_IMPORT_X_STRICT_PARSING) {
+            return Self::FailFast;
+        }
+        match properties.get_int(AI_CONFIG_IMPORT_ERROR_POLICY) {
+            1 => Self::FailFast,
+            2 => Self::BestEffort,
+            _ => Self::CollectWarnings,
+        }
+    }
+}
+
+/// The kind of problem an [`ImportIssue`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// A face refers to a vertex which does not exist
+    VertexIndexOutOfRange,
+    /// The number of per-face material indices differs from the face count
+    MaterialIndexCount,
+    /// Per-vertex data has a different length than the vertices
+    VertexDataCount,
+    /// Per-vertex data refers to a vertex which does not exist
+    VertexDataIndex,
+    /// A data object has no valid name
+    InvalidName,
+    /// A template declaration could not be read and was ignored
+    InvalidTemplate,
+    /// A closing brace without an open data object
+    UnbalancedBrace,
+    /// A data object of a type the importer does not read
+    UnknownObject,
+    /// The file ended early and the data read so far was kept
+    Truncated,
+    /// An external file referenced by the model was not found
+    MissingFile,
+}
+
+/// What the importer did about an [`ImportIssue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssueAction {
+    /// The offending data was skipped or read as it is
+    Warned,
+    /// The offending data was changed to be consistent
+    Repaired,
+    /// The data was valid but not read
+    Skipped,
+}
+
+/// A problem found in the file which did not stop the import
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportIssue {
+    pub kind: IssueKind,
+    pub action: IssueAction,
+    /// Position in the file, like `Line 3, column 7`, if known
+    pub position: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            IssueAction::Warned => "warning",
+            IssueAction::Repaired => "repaired",
+            IssueAction::Skipped => "skipped",
+        };
+        match &self.position {
+            Some(position) => write!(f, "{action}: {} ({position})", self.message),
+            None => write!(f, "{action}: {}", self.message),
+        }
+    }
+}
+
+/// Everything an importer had to tolerate, repair or skip to read a file,
+/// and what it found out about the file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// The issues in the order they were found
+    pub issues: Vec<ImportIssue>,
+    /// The [`AI_METADATA_SOURCE_FORMAT`] of the scene
+    pub format: Option<String>,
+    /// The [`AI_METADATA_SOURCE_FORMAT_VERSION`] of the scene
+    pub format_version: Option<String>,
+    /// The durations of the import phases
+    pub timings: Vec<ProfileEntry>,
+}
+
+impl ImportReport {
+    /// Whether nothing had to be tolerated or repaired, skipped data
+    /// objects do not count
+    pub fn is_clean(&self) -> bool {
+        self.issues
+            .iter()
+            .all(|issue| issue.action == IssueAction::Skipped)
+    }
+
+    /// Deviations which were tolerated
+    pub fn warnings(&self) -> impl Iterator<Item = &ImportIssue> {
+        self.issues_with(IssueAction::Warned)
+    }
+
+    /// Inconsistent data which was repaired
+    pub fn repairs(&self) -> impl Iterator<Item = &ImportIssue> {
+        self.issues_with(IssueAction::Repaired)
+    }
+
+    /// Data objects which were not read
+    pub fn skipped(&self) -> impl Iterator<Item = &ImportIssue> {
+        self.issues_with(IssueAction::Skipped)
+    }
+
+    fn issues_with(&self, action: IssueAction) -> impl Iterator<Item = &ImportIssue> {
+        self.issues
+            .iter()
+            .filter(move |issue| issue.action == action)
+    }
+
+    /// The issues of one kind
+    pub fn issues_of(&self, kind: IssueKind) -> impl Iterator<Item = &ImportIssue> {
+        self.issues.iter().filter(move |issue| issue.kind == kind)
+    }
+
+    pub fn push(
+        &mut self,
+        kind: IssueKind,
+        action: IssueAction,
+        position: Option<String>,
+        message: impl Into<String>,
+    ) {
+        let issue = ImportIssue {
+            kind,
+            action,
+            position,
+            message: message.into(),
+        };
+        match action {
+            IssueAction::Skipped => log::debug!("{issue}"),
+            _ => log::warn!("{issue}"),
+        }
+        self.issues.push(issue);
+    }
+
+    /// Records the format of the imported `scene` and the durations of
+    /// `profiler`
+    pub fn finish(&mut self, scene: &AiScene, profiler: &Profiler) {
+        let metadata = &scene.metadata;
+        self.format = metadata
+            .get_str(AI_METADATA_SOURCE_FORMAT)
+            .map(str::to_owned);
+        self.format_version = metadata
+            .get_str(AI_METADATA_SOURCE_FORMAT_VERSION)
+            .map(str::to_owned);
+        self.timings = profiler.entries().to_vec();
+    }
+}
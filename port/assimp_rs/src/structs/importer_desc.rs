@@ -97,3 +97,10 @@ pub struct ImporterDesc {
     /// file extensions such as XML would be tediously slow.
     pub file_extensions: &'static str,
 }
+
+impl ImporterDesc {
+    /// The entries of [`file_extensions`](Self::file_extensions)
+    pub fn extensions(&self) -> impl Iterator<Item = &'static str> {
+        self.file_extensions.split_ascii_whitespace()
+    }
+}
@@ -2,7 +2,7 @@ use std::cmp::Ordering;
 
 use crate::{
     structs::anim::AiAnimInterpolation,
-    utils::float_precision::{Quat, Vec3},
+    utils::float_precision::{Quat, Vec3, Vec4},
 };
 
 /** A time-value pair specifying a certain 3D vector for the given time. */
@@ -14,8 +14,17 @@ pub struct AiVectorKey {
     /** The value of this key */
     pub value: Vec3,
 
-    /** The interpolation setting of this key */
+    /** The interpolation setting of this key, used between this key and
+     *  the next one */
     pub interpolation: AiAnimInterpolation,
+
+    /** Incoming tangent of a cubic spline, per tick. Only used if the
+     *  previous key is a cubic spline key */
+    pub in_tangent: Vec3,
+
+    /** Outgoing tangent of a cubic spline, per tick. Only used if this key
+     *  is a cubic spline key */
+    pub out_tangent: Vec3,
 }
 
 impl AiVectorKey {
@@ -24,6 +33,25 @@ impl AiVectorKey {
             time,
             value,
             interpolation: AiAnimInterpolation::default(),
+            in_tangent: Vec3::ZERO,
+            out_tangent: Vec3::ZERO,
+        }
+    }
+
+    /// A key using `interpolation` towards the next key
+    pub fn with_interpolation(mut self, interpolation: AiAnimInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// A cubic spline key with the given tangents
+    pub fn cubic_spline(time: f64, value: Vec3, in_tangent: Vec3, out_tangent: Vec3) -> Self {
+        Self {
+            time,
+            value,
+            interpolation: AiAnimInterpolation::CubicSpline,
+            in_tangent,
+            out_tangent,
         }
     }
 }
@@ -61,8 +89,17 @@ pub struct AiQuatKey {
     /** The value of this key */
     pub value: Quat,
 
-    /** The interpolation setting of this key */
+    /** The interpolation setting of this key, used between this key and
+     *  the next one */
     pub interpolation: AiAnimInterpolation,
+
+    /** Incoming tangent of a cubic spline, per tick. Only used if the
+     *  previous key is a cubic spline key */
+    pub in_tangent: Vec4,
+
+    /** Outgoing tangent of a cubic spline, per tick. Only used if this key
+     *  is a cubic spline key */
+    pub out_tangent: Vec4,
 }
 
 impl AiQuatKey {
@@ -71,6 +108,25 @@ impl AiQuatKey {
             time,
             value,
             interpolation: AiAnimInterpolation::default(),
+            in_tangent: Vec4::ZERO,
+            out_tangent: Vec4::ZERO,
+        }
+    }
+
+    /// A key using `interpolation` towards the next key
+    pub fn with_interpolation(mut self, interpolation: AiAnimInterpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// A cubic spline key with the given tangents
+    pub fn cubic_spline(time: f64, value: Quat, in_tangent: Vec4, out_tangent: Vec4) -> Self {
+        Self {
+            time,
+            value,
+            interpolation: AiAnimInterpolation::CubicSpline,
+            in_tangent,
+            out_tangent,
         }
     }
 }
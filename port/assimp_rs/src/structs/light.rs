@@ -4,7 +4,7 @@ use crate::utils::float_precision::{Vec2, Vec3};
 // ---------------------------------------------------------------------------
 /** Enumerates all supported types of light sources.
  */
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub enum LightType {
     #[default]
     Undefined = 0x0,
@@ -162,3 +162,195 @@ pub struct AiLight {
     /** Size of area light source. */
     pub size: Vec2,
 }
+
+/// Type of a [`PunctualLight`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PunctualLightType {
+    Directional,
+    Point,
+    /// Cone angles in radians, measured from the direction of the light to
+    /// the edge of the cone as in glTF
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// A light as defined by the `KHR_lights_punctual` glTF extension. Point
+/// and spot lights follow the inverse square law, their intensity is given
+/// in candela, the intensity of directional lights in lux.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PunctualLight {
+    pub light_type: PunctualLightType,
+    /// Linear color, the components are usually at most 1
+    pub color: Color3D,
+    pub intensity: f32,
+    /// Distance at which the light reaches zero, `None` for infinity
+    pub range: Option<f32>,
+}
+
+impl AiLight {
+    /// Whether the light is positional and attenuated by the distance
+    fn is_attenuated(&self) -> bool {
+        matches!(
+            self.light_type,
+            LightType::Point | LightType::Spot | LightType::Area
+        )
+    }
+
+    /// The factor the light is scaled with at `distance` from its position,
+    /// always 1 for lights without a position and for lights without
+    /// attenuation factors
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        let d = distance.abs();
+        let divisor = self.attenuation_constant
+            + self.attenuation_linear * d
+            + self.attenuation_quadratic * d * d;
+        if !self.is_attenuated() || divisor <= 0.0 {
+            1.0
+        } else {
+            1.0 / divisor
+        }
+    }
+
+    /// The distance at which the [`Self::attenuation`] falls to `cutoff`,
+    /// `None` if it never does, like for directional lights
+    pub fn effective_range(&self, cutoff: f32) -> Option<f32> {
+        if !self.is_attenuated() || cutoff <= 0.0 {
+            return None;
+        }
+        // solve constant + linear * d + quadratic * d^2 = 1 / cutoff
+        let (a, b, c) = (
+            self.attenuation_quadratic,
+            self.attenuation_linear,
+            self.attenuation_constant - 1.0 / cutoff,
+        );
+        if c >= 0.0 {
+            // already below the cutoff at the position of the light
+            return Some(0.0);
+        }
+        if a > 0.0 {
+            Some((-b + (b * b - 4.0 * a * c).sqrt()) / (2.0 * a))
+        } else if b > 0.0 {
+            Some(-c / b)
+        } else {
+            None
+        }
+    }
+
+    /// Converts the light to a glTF punctual light, `None` for ambient,
+    /// area and undefined lights.
+    ///
+    /// The diffuse color is split into a color with a largest component
+    /// of 1 and the intensity. The cone angles of assimp span the whole
+    /// cone, glTF's are half angles. The attenuation is not converted as
+    /// glTF always uses the inverse square law.
+    pub fn to_punctual(&self) -> Option<PunctualLight> {
+        let light_type = match self.light_type {
+            LightType::Directional => PunctualLightType::Directional,
+            LightType::Point => PunctualLightType::Point,
+            LightType::Spot => PunctualLightType::Spot {
+                inner_cone_angle: self.angle_inner_cone * 0.5,
+                outer_cone_angle: self.angle_outer_cone * 0.5,
+            },
+            LightType::Undefined | LightType::Ambient | LightType::Area => return None,
+        };
+        let intensity = self.color_diffuse.max_element().max(0.0);
+        let color = if intensity > 0.0 {
+            self.color_diffuse / intensity
+        } else {
+            Color3D::ONE
+        };
+        Some(PunctualLight {
+            light_type,
+            color,
+            intensity,
+            range: None,
+        })
+    }
+
+    /// Creates a light from a glTF punctual light, the inverse of
+    /// [`Self::to_punctual`]. Positional lights get quadratic attenuation,
+    /// the range can't be represented and is dropped. Like in glTF the light
+    /// is at the origin of its node and points along -Z.
+    pub fn from_punctual(name: impl Into<String>, light: &PunctualLight) -> Self {
+        let mut result = Self {
+            name: name.into(),
+            color_diffuse: light.color * light.intensity,
+            color_specular: light.color * light.intensity,
+            direction: -Vec3::Z,
+            up: Vec3::Y,
+            attenuation_quadratic: 1.0,
+            angle_inner_cone: core::f32::consts::TAU,
+            angle_outer_cone: core::f32::consts::TAU,
+            ..Default::default()
+        };
+        match light.light_type {
+            PunctualLightType::Directional => {
+                result.light_type = LightType::Directional;
+                result.attenuation_quadratic = 0.0;
+            }
+            PunctualLightType::Point => result.light_type = LightType::Point,
+            PunctualLightType::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                result.light_type = LightType::Spot;
+                result.angle_inner_cone = inner_cone_angle * 2.0;
+                result.angle_outer_cone = outer_cone_angle * 2.0;
+            }
+        }
+        result
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{AiLight, LightType, PunctualLight, PunctualLightType};
+    use crate::structs::color::Color3D;
+
+    #[test]
+    fn test_light_attenuation() {
+        let mut light = AiLight {
+            light_type: LightType::Point,
+            attenuation_constant: 1.0,
+            attenuation_quadratic: 0.25,
+            ..Default::default()
+        };
+        assert_eq!(light.attenuation(0.0), 1.0);
+        assert_eq!(light.attenuation(2.0), 0.5);
+        assert_eq!(light.effective_range(0.5), Some(2.0));
+        assert_eq!(light.effective_range(2.0), Some(0.0));
+        light.attenuation_quadratic = 0.0;
+        light.attenuation_linear = 0.5;
+        assert_eq!(light.effective_range(0.5), Some(2.0));
+        light.attenuation_linear = 0.0;
+        assert_eq!(light.effective_range(0.5), None);
+        light.light_type = LightType::Directional;
+        light.attenuation_quadratic = 1.0;
+        assert_eq!(light.attenuation(10.0), 1.0);
+        assert_eq!(light.effective_range(0.5), None);
+
+        // glTF round trip of a spot light with 200 cd
+        let punctual = PunctualLight {
+            light_type: PunctualLightType::Spot {
+                inner_cone_angle: 0.25,
+                outer_cone_angle: 0.5,
+            },
+            color: Color3D::new(1.0, 0.5, 0.25),
+            intensity: 200.0,
+            range: None,
+        };
+        let light = AiLight::from_punctual("spot", &punctual);
+        assert_eq!(light.light_type, LightType::Spot);
+        assert_eq!(light.color_diffuse, Color3D::new(200.0, 100.0, 50.0));
+        assert_eq!(light.angle_outer_cone, 1.0);
+        assert_eq!(light.attenuation(10.0), 0.01);
+        assert_eq!(light.to_punctual(), Some(punctual));
+        let ambient = AiLight {
+            light_type: LightType::Ambient,
+            ..Default::default()
+        };
+        assert_eq!(ambient.to_punctual(), None);
+    }
+}
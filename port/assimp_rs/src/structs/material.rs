@@ -1,8 +1,12 @@
-use std::borrow::Cow;
+use core::cmp::Ordering;
+use std::{
+    borrow::Cow,
+    fmt::{Display, Formatter},
+};
 
 use crate::{
     AiReal,
-    utils::float_precision::{Vec2, Vec3, Vec4},
+    utils::float_precision::{Mat3, Vec2, Vec3, Vec4},
 };
 
 pub const AI_MATKEY_NAME: &str = "?mat.name";
@@ -32,6 +36,16 @@ pub const AI_MATKEY_SHADER_TESSELATION: &str = "?sh.ts";
 pub const AI_MATKEY_SHADER_PRIMITIVE: &str = "?sh.ps";
 pub const AI_MATKEY_SHADER_COMPUTE: &str = "?sh.cs";
 
+// ---------------------------------------------------------------------------
+// PBR material properties, see `PbrMaterial`
+pub const AI_MATKEY_BASE_COLOR: &str = "$clr.base";
+pub const AI_MATKEY_METALLIC_FACTOR: &str = "$mat.metallicFactor";
+pub const AI_MATKEY_ROUGHNESS_FACTOR: &str = "$mat.roughnessFactor";
+pub const AI_MATKEY_EMISSIVE_INTENSITY: &str = "$mat.emissiveIntensity";
+pub const AI_MATKEY_CLEARCOAT_FACTOR: &str = "$mat.clearcoat.factor";
+pub const AI_MATKEY_CLEARCOAT_ROUGHNESS_FACTOR: &str = "$mat.clearcoat.roughnessFactor";
+pub const AI_MATKEY_TRANSMISSION_FACTOR: &str = "$mat.transmission.factor";
+
 // ---------------------------------------------------------------------------
 // Pure key names for all texture-related properties
 pub const AI_MATKEY_TEXTURE: &str = "$tex.file";
@@ -44,25 +58,159 @@ pub const AI_MATKEY_MAPPINGMODE_V: &str = "$tex.mapmodev";
 pub const AI_MATKEY_TEXMAP_AXIS: &str = "$tex.mapaxis";
 pub const AI_MATKEY_UVTRANSFORM: &str = "$tex.uvtrafo";
 pub const AI_MATKEY_TEXFLAGS: &str = "$tex.flags";
+/// Scale of the normals read from a normal map
+pub const AI_MATKEY_TEXTURE_SCALE: &str = "$tex.scale";
+/// Strength of an ambient occlusion map
+pub const AI_MATKEY_TEXTURE_STRENGTH: &str = "$tex.strength";
+/// Green channel convention of a normal map, see [`AiNormalMapConvention`]
+pub const AI_MATKEY_NORMAL_MAP_CONVENTION: &str = "$tex.normalconvention";
+
+/// Defines the purpose of a texture, the semantic of the texture
+/// properties of a material.
+///
+/// Textures are identified by their type and an index, there may be
+/// multiple textures of the same type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AiTextureType {
+    /// Not a texture property, used for all other material properties
+    #[default]
+    None = 0,
+    /// Combined with the result of the diffuse lighting equation
+    Diffuse = 1,
+    /// Combined with the result of the specular lighting equation
+    Specular = 2,
+    /// Combined with the result of the ambient lighting equation
+    Ambient = 3,
+    /// Added to the result of the lighting calculation
+    Emissive = 4,
+    /// Height map, higher values are higher elevations
+    Height = 5,
+    /// Tangent space normal map
+    Normals = 6,
+    /// Glossiness of the material, the exponent of the specular equation
+    Shininess = 7,
+    /// Per pixel opacity, usually white is opaque
+    Opacity = 8,
+    /// Displacement texture, its exact purpose depends on the format
+    Displacement = 9,
+    /// Lightmap or ambient occlusion texture
+    Lightmap = 10,
+    /// Reflection texture, e.g. a cube map
+    Reflection = 11,
+    /// PBR base color
+    BaseColor = 12,
+    /// PBR normal map
+    NormalCamera = 13,
+    /// PBR emissive color
+    EmissionColor = 14,
+    /// PBR metalness
+    Metalness = 15,
+    /// PBR roughness
+    DiffuseRoughness = 16,
+    /// PBR ambient occlusion
+    AmbientOcclusion = 17,
+    /// A texture the importer could not classify
+    Unknown = 18,
+    /// PBR sheen layer
+    Sheen = 19,
+    /// PBR clearcoat layer
+    Clearcoat = 20,
+    /// PBR transmission
+    Transmission = 21,
+}
+
+/// Defines how UV coordinates outside the `[0, 1]` range are handled,
+/// stored in [`AI_MATKEY_MAPPINGMODE_U`] and [`AI_MATKEY_MAPPINGMODE_V`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AiTextureMapMode {
+    /// The texture is repeated
+    #[default]
+    Wrap = 0,
+    /// The coordinates are clamped to the valid range
+    Clamp = 1,
+    /// The texture is mirrored at every integer boundary
+    Mirror = 2,
+    /// Outside the valid range the texture is not applied
+    Decal = 3,
+}
+
+impl TryFrom<i32> for AiTextureMapMode {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Wrap),
+            1 => Ok(Self::Clamp),
+            2 => Ok(Self::Mirror),
+            3 => Ok(Self::Decal),
+            _ => Err(value),
+        }
+    }
+}
 
-#[derive(Clone, Debug)]
-pub enum AiColorDiffuseProperty {
-    Color3D(Vec3),
-    Color4D(Vec4),
+/// Direction of the green channel of a normal map, stored per
+/// [`AiTextureType::Normals`] texture in [`AI_MATKEY_NORMAL_MAP_CONVENTION`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AiNormalMapConvention {
+    /// Green points up (Y+), used by OpenGL, glTF and Blender
+    #[default]
+    OpenGl = 0,
+    /// Green points down (Y-), used by DirectX and Unreal Engine
+    DirectX = 1,
 }
 
-impl From<Vec3> for AiColorDiffuseProperty {
-    fn from(value: Vec3) -> Self {
-        Self::Color3D(value)
+impl AiNormalMapConvention {
+    /// The convention of the normal maps of a file format, by the
+    /// [`AI_METADATA_SOURCE_FORMAT`](super::meta::AI_METADATA_SOURCE_FORMAT)
+    /// of an imported scene
+    pub fn from_source_format(format: &str) -> Option<Self> {
+        match format {
+            #[cfg(feature = "x_file")]
+            crate::formats::x::importer::SOURCE_FORMAT => Some(Self::DirectX),
+            _ => None,
+        }
     }
 }
-impl From<Vec4> for AiColorDiffuseProperty {
-    fn from(value: Vec4) -> Self {
-        Self::Color4D(value)
+
+impl TryFrom<i32> for AiNormalMapConvention {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::OpenGl),
+            1 => Ok(Self::DirectX),
+            _ => Err(value),
+        }
     }
 }
 
-#[derive(Clone, Debug)]
+/// All properties of one texture of a material, see
+/// [`AiMaterial::get_texture`] and [`AiMaterial::set_texture`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureSlot {
+    /// File path, or `*n` for the n-th embedded texture ([`AI_MATKEY_TEXTURE`])
+    pub path: String,
+    /// UV channel of the mesh used by the texture ([`AI_MATKEY_UVWSRC`])
+    pub uv_index: u32,
+    /// Strength of the texture ([`AI_MATKEY_TEXBLEND`])
+    pub blend: AiReal,
+    /// Handling of coordinates outside of `[0, 1]` on the u and v axes
+    pub map_mode: [AiTextureMapMode; 2],
+}
+
+impl TextureSlot {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            uv_index: 0,
+            blend: 1.0,
+            map_mode: [AiTextureMapMode::Wrap; 2],
+        }
+    }
+}
+
+/// Value of a material property, its meaning is defined by the key
+#[derive(Clone, Debug, PartialEq)]
 pub enum AiProperty {
     /// Array of single-precision (32 Bit) floats
     ///
@@ -73,38 +221,19 @@ pub enum AiProperty {
 
     Float(AiReal),
 
+    /// RGB colors and vectors
     Vec3(Vec3),
 
+    /// RGBA colors
     Vec4(Vec4),
 
     ShadingModel(AiShadingMode),
 
-    ColorEmissive(Vec3),
-
-    ColorSpecular(Vec3),
-
-    ColorDiffuse(AiColorDiffuseProperty),
-
-    Shiness(AiReal),
-
     /// The material property is an aiString.
     ///
     ///  Arrays of strings aren't possible, aiGetMaterialString() (or the
     ///  C++-API aiMaterial::Get()) *must* be used to query a string property.
     String(String),
-    Name(String),
-    MaterialName(String),
-    TextureDiffuse(String),
-    TextureSpecular(String),
-    TextureAmbient(String),
-    TextureEmissive(String),
-    TextureNormals(String),
-    TextureHeight(String),
-    TextureShininess(String),
-    TextureOpacity(String),
-    TextureDisplacement(String),
-    TextureLightmap(String),
-    TextureReflection(String),
 
     UvTransform(AiUVTransform),
 
@@ -154,87 +283,197 @@ impl AiProperty {
         Buffer, Vec<u8>, is_buffer_property
         // String properties
         String, String, is_string_property
-        Name, String, is_name_property
-        TextureDiffuse, String, is_texture_diffuse_property
-        TextureSpecular, String, is_texture_specular_property
-        TextureAmbient, String, is_texture_ambient_property
-        TextureEmissive, String, is_texture_emissive_property
-        TextureNormals, String, is_texture_normals_property
-        TextureHeight, String, is_texture_height_property
-        TextureShininess, String, is_texture_shininess_property
-        TextureOpacity, String, is_texture_opacity_property
-        TextureDisplacement, String, is_texture_displacement_property
-        TextureLightmap, String, is_texture_lightmap_property
+        // UV transform properties
+        UvTransform, AiUVTransform, is_uv_transform_property
         // Wildcard properties
         WildCard, (), is_wildcard_property
     );
 }
 
+impl AiProperty {
+    /// The value as float, integers are converted
+    pub fn as_float(&self) -> Option<AiReal> {
+        match self {
+            AiProperty::Float(v) => Some(*v),
+            AiProperty::Floats(v) => v.first().copied(),
+            AiProperty::Integer(v) => Some(*v as AiReal),
+            AiProperty::Integers(v) => v.first().map(|&v| v as AiReal),
+            _ => None,
+        }
+    }
+
+    /// The value as integer, floats are truncated
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            AiProperty::Integer(v) => Some(*v),
+            AiProperty::Integers(v) => v.first().copied(),
+            AiProperty::Float(v) => Some(*v as i32),
+            AiProperty::Floats(v) => v.first().map(|&v| v as i32),
+            _ => None,
+        }
+    }
+
+    /// The value as RGBA color, RGB colors get an alpha of 1
+    pub fn as_color(&self) -> Option<Vec4> {
+        match self {
+            AiProperty::Vec4(v) => Some(*v),
+            AiProperty::Vec3(v) => Some(v.extend(1.0)),
+            AiProperty::Floats(v) => match v[..] {
+                [r, g, b] => Some(Vec4::new(r, g, b, 1.0)),
+                [r, g, b, a] => Some(Vec4::new(r, g, b, a)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 impl Default for AiProperty {
     fn default() -> Self {
         Self::WildCard(())
     }
 }
 
-pub enum AiStringPropertyType {
-    Name,
-    MaterialName,
-    TextureDiffuse,
-    TextureSpecular,
-    TextureAmbient,
-    TextureEmissive,
-    TextureNormals,
-    TextureHeight,
-    TextureShininess,
-    TextureOpacity,
-    TextureDisplacement,
-    TextureLightmap,
-    TextureReflection,
+/// Writes the values separated by commas
+fn write_list<T: Display>(
+    f: &mut Formatter<'_>,
+    values: impl IntoIterator<Item = T>,
+) -> std::fmt::Result {
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{value}")?;
+    }
+    Ok(())
 }
 
-#[derive(Default, Clone, Debug)]
+impl Display for AiProperty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AiProperty::Float(v) => write!(f, "{v}"),
+            AiProperty::Integer(v) => write!(f, "{v}"),
+            AiProperty::Floats(v) => {
+                f.write_str("[")?;
+                write_list(f, v)?;
+                f.write_str("]")
+            }
+            AiProperty::Integers(v) => {
+                f.write_str("[")?;
+                write_list(f, v)?;
+                f.write_str("]")
+            }
+            AiProperty::Vec3(v) => {
+                f.write_str("(")?;
+                write_list(f, v.to_array())?;
+                f.write_str(")")
+            }
+            AiProperty::Vec4(v) => {
+                f.write_str("(")?;
+                write_list(f, v.to_array())?;
+                f.write_str(")")
+            }
+            AiProperty::ShadingModel(mode) => {
+                write_list(f, mode.iter_names().map(|(name, _)| name))
+            }
+            AiProperty::String(v) => write!(f, "{v:?}"),
+            AiProperty::UvTransform(t) => write!(
+                f,
+                "translation ({}, {}), scaling ({}, {}), rotation {}",
+                t.translation.x, t.translation.y, t.scaling.x, t.scaling.y, t.rotation
+            ),
+            AiProperty::Buffer(v) => write!(f, "<{} bytes>", v.len()),
+            AiProperty::WildCard(()) => f.write_str("*"),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct AiMaterialProperty {
+    /// One of the `AI_MATKEY_*` constants or a format specific key
     pub key: Cow<'static, str>,
+    /// Texture type for the `$tex.*` keys, [`AiTextureType::None`] otherwise
+    pub semantic: AiTextureType,
+    /// Index of the texture for the `$tex.*` keys, 0 otherwise
     pub index: u32,
     pub property: AiProperty,
 }
 
+impl AiMaterialProperty {
+    fn id(&self) -> (&str, AiTextureType, u32) {
+        (&self.key, self.semantic, self.index)
+    }
+
+    /// Returns the file path if this property references a texture
+    pub fn texture_path(&self) -> Option<&str> {
+        match &self.property {
+            AiProperty::String(path) if self.key == AI_MATKEY_TEXTURE => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the file path if this property references a texture
+    pub fn texture_path_mut(&mut self) -> Option<&mut String> {
+        match &mut self.property {
+            AiProperty::String(path) if self.key == AI_MATKEY_TEXTURE => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// A material, a list of properties identified by their key and for
+/// textures additionally by their texture type and index.
 #[derive(Default, Clone, Debug)]
 pub struct AiMaterial {
     pub properties: Vec<AiMaterialProperty>,
 }
 
 impl AiMaterial {
+    /// Returns the property with the given key, texture type and index.
+    /// The index `u32::MAX` matches any index.
+    pub fn property(&self, key: &str, semantic: AiTextureType, index: u32) -> Option<&AiProperty> {
+        self.properties
+            .iter()
+            .find(|p| {
+                p.key == key && p.semantic == semantic && (index == u32::MAX || p.index == index)
+            })
+            .map(|p| &p.property)
+    }
+
     fn inner_get_property<V: ?Sized>(
         &self,
         key: &str,
         index: u32,
         type_match_fn: impl Fn(&AiProperty) -> Option<&V>,
     ) -> Option<&V> {
-        for p in self.properties.iter() {
-            if p.key == key && (index == u32::MAX || p.index == index) {
-                if let Some(v) = type_match_fn(&p.property) {
-                    return Some(v);
-                }
-            }
-        }
-        None
+        self.property(key, AiTextureType::None, index)
+            .and_then(type_match_fn)
     }
 
-    #[allow(unused)]
-    fn inner_get_property_by_index<V: ?Sized>(
-        &self,
-        variant_match_fn: impl Fn(&AiProperty) -> Option<&V>,
+    /// Adds a property, replacing one with the same key, texture type and
+    /// index.
+    pub fn set_property<K: Into<Cow<'static, str>>>(
+        &mut self,
+        key: K,
+        semantic: AiTextureType,
         index: u32,
-    ) -> Option<&V> {
-        for p in self.properties.iter() {
-            if p.index == index {
-                if let Some(v) = variant_match_fn(&p.property) {
-                    return Some(v);
-                }
-            }
+        property: AiProperty,
+    ) {
+        let key = key.into();
+        if let Some(p) = self
+            .properties
+            .iter_mut()
+            .find(|p| p.key == key && p.semantic == semantic && p.index == index)
+        {
+            p.property = property;
+            return;
         }
-        None
+        self.properties.push(AiMaterialProperty {
+            key,
+            semantic,
+            index,
+            property,
+        });
     }
 
     fn inner_add_property<K: Into<Cow<'static, str>>>(
@@ -243,89 +482,197 @@ impl AiMaterial {
         property: AiProperty,
         index: u32,
     ) {
-        let key = key.into();
+        self.set_property(key, AiTextureType::None, index, property);
+    }
 
-        self.properties.push(AiMaterialProperty {
-            key,
+    /// A float property, integers are converted
+    pub fn get_float(&self, key: &str) -> Option<AiReal> {
+        self.property(key, AiTextureType::None, 0)?.as_float()
+    }
+
+    /// An integer property, floats are truncated
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        self.property(key, AiTextureType::None, 0)?.as_int()
+    }
+
+    /// A color property as RGBA, RGB colors get an alpha of 1
+    pub fn get_color(&self, key: &str) -> Option<Vec4> {
+        self.property(key, AiTextureType::None, 0)?.as_color()
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.inner_get_property(key, 0, |p| p.is_string_property())
+            .map(String::as_str)
+    }
+
+    /// The name of the material ([`AI_MATKEY_NAME`])
+    pub fn name(&self) -> Option<&str> {
+        self.get_string(AI_MATKEY_NAME)
+    }
+
+    /// Number of textures of the given type
+    pub fn texture_count(&self, ty: AiTextureType) -> usize {
+        self.properties
+            .iter()
+            .filter(|p| p.semantic == ty && p.key == AI_MATKEY_TEXTURE)
+            .count()
+    }
+
+    /// The texture of the given type and index with all its properties,
+    /// missing properties have their default values.
+    pub fn get_texture(&self, ty: AiTextureType, index: u32) -> Option<TextureSlot> {
+        let get = |key| self.property(key, ty, index);
+        let path = get(AI_MATKEY_TEXTURE)?.is_string_property()?;
+        let mut slot = TextureSlot::new(path.as_str());
+        if let Some(uv_index) = get(AI_MATKEY_UVWSRC).and_then(AiProperty::as_int) {
+            slot.uv_index = uv_index.max(0) as u32;
+        }
+        if let Some(blend) = get(AI_MATKEY_TEXBLEND).and_then(AiProperty::as_float) {
+            slot.blend = blend;
+        }
+        for (map_mode, key) in slot
+            .map_mode
+            .iter_mut()
+            .zip([AI_MATKEY_MAPPINGMODE_U, AI_MATKEY_MAPPINGMODE_V])
+        {
+            if let Some(mode) = get(key)
+                .and_then(AiProperty::as_int)
+                .and_then(|mode| AiTextureMapMode::try_from(mode).ok())
+            {
+                *map_mode = mode;
+            }
+        }
+        Some(slot)
+    }
+
+    /// The convention of the normal map with the given index, if recorded
+    pub fn normal_map_convention(&self, index: u32) -> Option<AiNormalMapConvention> {
+        self.property(
+            AI_MATKEY_NORMAL_MAP_CONVENTION,
+            AiTextureType::Normals,
             index,
-            property,
-        });
+        )?
+        .as_int()?
+        .try_into()
+        .ok()
     }
 
-    pub fn add_property_v2(&mut self, property: AiProperty, index: u32) {
-        self.properties.push(AiMaterialProperty {
-            key: "".into(),
+    pub fn set_normal_map_convention(&mut self, index: u32, convention: AiNormalMapConvention) {
+        self.set_property(
+            AI_MATKEY_NORMAL_MAP_CONVENTION,
+            AiTextureType::Normals,
             index,
-            property,
-        });
+            AiProperty::Integer(convention as i32),
+        );
     }
 
-    pub fn add_string_property<K: Into<Cow<'static, str>>>(
-        &mut self,
-        key: K,
-        value: String,
-        index: u32,
-        string_type: AiStringPropertyType,
-    ) {
-        macro_rules! add_string_property_impl {
-            ($($string_type:ident, $variant:ident)*) => {
-                match string_type {
-                    $(
-                        AiStringPropertyType::$string_type => self.inner_add_property(key, AiProperty::$variant(value), index),
-                    )*
-                }
-            };
+    /// Sets the texture of the given type and index, properties with their
+    /// default value are not stored.
+    pub fn set_texture(&mut self, ty: AiTextureType, index: u32, slot: TextureSlot) {
+        self.set_property(AI_MATKEY_TEXTURE, ty, index, AiProperty::String(slot.path));
+        if slot.uv_index != 0 {
+            let uv_index = AiProperty::Integer(slot.uv_index as i32);
+            self.set_property(AI_MATKEY_UVWSRC, ty, index, uv_index);
         }
-        add_string_property_impl!(
-            Name, Name
-            MaterialName, MaterialName
-            TextureHeight, TextureHeight
-            TextureDiffuse, TextureDiffuse
-            TextureSpecular, TextureSpecular
-            TextureAmbient, TextureAmbient
-            TextureEmissive, TextureEmissive
-            TextureNormals, TextureNormals
-            TextureShininess, TextureShininess
-            TextureOpacity, TextureOpacity
-            TextureDisplacement, TextureDisplacement
-            TextureLightmap, TextureLightmap
-            TextureReflection, TextureReflection
-        )
-    }
-
-    pub fn get_string_property(
-        &self,
-        key: &str,
-        index: u32,
-        string_type: AiStringPropertyType,
-    ) -> Option<&str> {
-        macro_rules! get_string_property_impl {
-            ($($string_type:ident, $variant:ident)*) => {
-                match string_type {
-                    $(
-                        AiStringPropertyType::$string_type => self.inner_get_property(key, index, |v| match v {
-                            AiProperty::$variant(v) => Some(v.as_str()),
-                            _ => None,
-                        }),
-                    )*
-                }
+        if slot.blend != 1.0 {
+            self.set_property(AI_MATKEY_TEXBLEND, ty, index, AiProperty::Float(slot.blend));
+        }
+        for (mode, key) in slot
+            .map_mode
+            .into_iter()
+            .zip([AI_MATKEY_MAPPINGMODE_U, AI_MATKEY_MAPPINGMODE_V])
+        {
+            if mode != AiTextureMapMode::Wrap {
+                self.set_property(key, ty, index, AiProperty::Integer(mode as i32));
+            }
+        }
+    }
+}
+
+impl Display for AiMaterialProperty {
+    /// `key = value` for plain properties, `key[Diffuse, 0] = value` for
+    /// texture properties
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.key)?;
+        if self.semantic != AiTextureType::None || self.index != 0 {
+            write!(f, "[{:?}, {}]", self.semantic, self.index)?;
+        }
+        write!(f, " = {}", self.property)
+    }
+}
+
+/// A difference between two materials found by [`AiMaterial::diff`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialDiff<'a> {
+    /// The property exists only in the first material
+    Removed(&'a AiMaterialProperty),
+    /// The property exists only in the second material
+    Added(&'a AiMaterialProperty),
+    /// The property exists in both materials with different values
+    Changed(&'a AiMaterialProperty, &'a AiMaterialProperty),
+}
+
+impl Display for MaterialDiff<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaterialDiff::Removed(p) => write!(f, "- {p}"),
+            MaterialDiff::Added(p) => write!(f, "+ {p}"),
+            MaterialDiff::Changed(old, new) => write!(f, "- {old}\n+ {new}"),
+        }
+    }
+}
+
+impl AiMaterial {
+    /// The properties ordered by key, texture type and index
+    fn sorted_properties(&self) -> Vec<&AiMaterialProperty> {
+        let mut properties = self.properties.iter().collect::<Vec<_>>();
+        properties.sort_by(|a, b| a.id().cmp(&b.id()));
+        properties
+    }
+
+    /// Lists all properties one per line, ordered by key, texture type and
+    /// index so the output does not depend on the order the importer
+    /// added them in.
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        for property in self.sorted_properties() {
+            dump.push_str(&property.to_string());
+            dump.push('\n');
+        }
+        dump
+    }
+
+    /// Compares the properties of two materials, in the order of
+    /// [`dump`](Self::dump).
+    pub fn diff<'a>(&'a self, other: &'a AiMaterial) -> Vec<MaterialDiff<'a>> {
+        let (old, new) = (self.sorted_properties(), other.sorted_properties());
+        let (mut i, mut j) = (0, 0);
+        let mut diff = Vec::new();
+        while i < old.len() || j < new.len() {
+            let ordering = match (old.get(i), new.get(j)) {
+                (Some(a), Some(b)) => a.id().cmp(&b.id()),
+                (Some(_), None) => Ordering::Less,
+                _ => Ordering::Greater,
             };
+            match ordering {
+                Ordering::Less => {
+                    diff.push(MaterialDiff::Removed(old[i]));
+                    i += 1;
+                }
+                Ordering::Greater => {
+                    diff.push(MaterialDiff::Added(new[j]));
+                    j += 1;
+                }
+                Ordering::Equal => {
+                    if old[i].property != new[j].property {
+                        diff.push(MaterialDiff::Changed(old[i], new[j]));
+                    }
+                    i += 1;
+                    j += 1;
+                }
+            }
         }
-        get_string_property_impl!(
-            Name, Name
-            MaterialName, MaterialName
-            TextureHeight, TextureHeight
-            TextureDiffuse, TextureDiffuse
-            TextureSpecular, TextureSpecular
-            TextureAmbient, TextureAmbient
-            TextureEmissive, TextureEmissive
-            TextureNormals, TextureNormals
-            TextureShininess, TextureShininess
-            TextureOpacity, TextureOpacity
-            TextureDisplacement, TextureDisplacement
-            TextureLightmap, TextureLightmap
-            TextureReflection, TextureReflection
-        )
+        diff
     }
 }
 
@@ -351,6 +698,9 @@ add_property_impls!(
     Vec4, Vec4
     i32, Integer
     Vec<u8>, Buffer
+    String, String
+    AiShadingMode, ShadingModel
+    AiUVTransform, UvTransform
 );
 
 pub trait GetProperty<V> {
@@ -378,6 +728,9 @@ get_property_impls!(
     Vec4, Vec4
     i32, Integer
     Vec<u8>, Buffer
+    String, String
+    AiShadingMode, ShadingModel
+    AiUVTransform, UvTransform
 );
 
 bitflags::bitflags! {
@@ -394,7 +747,7 @@ bitflags::bitflags! {
     /// Again, this value is just a hint. Assimp tries to select the shader whose
     /// most common implementation matches the original rendering results of the
     /// 3D modeler which wrote a particular model as closely as possible.
-   #[derive(Clone,Copy, Debug)]
+   #[derive(Clone,Copy, Debug, PartialEq, Eq)]
    pub struct AiShadingMode: u32 {
        /// Flat shading. Shading is done on per-face base,
        /// diffuse only. Also known as 'faceted shading'.
@@ -445,7 +798,7 @@ bitflags::bitflags! {
 /// we keep separate scaling/translation/rotation values to make it
 /// easier to process and optimize UV transformations internally.
 ///
-#[derive(Default, Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct AiUVTransform {
     /// Translation on the u and v axes.
     ///
@@ -464,3 +817,329 @@ pub struct AiUVTransform {
     /// 0.f.
     pub rotation: AiReal,
 }
+
+impl Default for AiUVTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vec2::ZERO,
+            scaling: Vec2::ONE,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl AiUVTransform {
+    /// The transformation as matrix for homogeneous UV coordinates
+    /// `(u, v, 1)`: scaling and rotation around the center `(0.5, 0.5)`,
+    /// applied after the translation, like the `TransformUVCoords` step of
+    /// assimp does.
+    pub fn to_matrix(&self) -> Mat3 {
+        let center = Vec2::splat(0.5);
+        Mat3::from_translation(center)
+            * Mat3::from_scale(self.scaling)
+            * Mat3::from_angle(self.rotation)
+            * Mat3::from_translation(self.translation - center)
+    }
+
+    /// Transforms a single UV coordinate
+    pub fn transform(&self, uv: Vec2) -> Vec2 {
+        self.to_matrix().transform_point2(uv)
+    }
+
+    /// The matrix applying `self` first and then `next`. The result is not
+    /// an [`AiUVTransform`] in general, rotations after a non-uniform
+    /// scaling introduce shearing.
+    pub fn then(&self, next: &AiUVTransform) -> Mat3 {
+        next.to_matrix() * self.to_matrix()
+    }
+
+    /// Whether the transformation has no effect, up to `epsilon`
+    pub fn is_identity(&self, epsilon: AiReal) -> bool {
+        self.abs_diff_eq(&Self::default(), epsilon)
+    }
+
+    /// Compares the components, up to `epsilon`
+    pub fn abs_diff_eq(&self, other: &AiUVTransform, epsilon: AiReal) -> bool {
+        self.translation.abs_diff_eq(other.translation, epsilon)
+            && self.scaling.abs_diff_eq(other.scaling, epsilon)
+            && (self.rotation - other.rotation).abs() <= epsilon
+    }
+}
+
+/// A texture transformation as defined by the `KHR_texture_transform` glTF
+/// extension. glTF scales, then rotates and then offsets the coordinates,
+/// with the origin of the UV space in the upper left corner of the texture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureTransform {
+    pub offset: Vec2,
+    /// Rotation around the origin in radians, counter-clockwise in the
+    /// coordinates of glTF
+    pub rotation: AiReal,
+    pub scale: Vec2,
+    /// The UV channel used instead of the one of the texture
+    pub tex_coord: Option<u32>,
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+            tex_coord: None,
+        }
+    }
+}
+
+/// The scaling `s'` with `S(s') R(r) = R(r) S(s)`, which exists if the
+/// scaling is uniform or the rotation a multiple of 90 degrees
+fn commute_scaling(scaling: Vec2, rotation: AiReal) -> Option<Vec2> {
+    const EPSILON: AiReal = 1e-6;
+    let (sin, cos) = rotation.sin_cos();
+    if (scaling.x - scaling.y).abs() <= EPSILON * scaling.abs().max_element()
+        || sin.abs() <= EPSILON
+    {
+        Some(scaling)
+    } else if cos.abs() <= EPSILON {
+        Some(Vec2::new(scaling.y, scaling.x))
+    } else {
+        None
+    }
+}
+
+impl AiUVTransform {
+    /// Converts a glTF texture transformation, for UV coordinates whose v
+    /// axis is flipped to point upwards like importers do.
+    ///
+    /// glTF rotates after scaling, assimp before, so a non-uniform scaling
+    /// combined with a rotation which is no multiple of 90 degrees has no
+    /// exact counterpart and gives `None`.
+    pub fn from_texture_transform(transform: &TextureTransform) -> Option<Self> {
+        // flipping v mirrors the direction of the rotation
+        let rotation = -transform.rotation;
+        let scaling = commute_scaling(transform.scale, rotation)?;
+        let linear = Mat3::from_scale(scaling) * Mat3::from_angle(rotation);
+        // the translation moves the glTF origin, (0, 1) with flipped v, to
+        // the offset
+        let center = Vec2::splat(0.5);
+        let target = Vec2::new(transform.offset.x, 1.0 - transform.offset.y);
+        let translation = if linear.determinant() != 0.0 {
+            center - Vec2::Y + linear.inverse().transform_vector2(target - center)
+        } else {
+            Vec2::ZERO
+        };
+        Some(Self {
+            translation,
+            scaling,
+            rotation,
+        })
+    }
+
+    /// Converts the transformation to a glTF texture transformation, the
+    /// inverse of [`Self::from_texture_transform`]
+    pub fn to_texture_transform(&self) -> Option<TextureTransform> {
+        let origin = self.transform(Vec2::Y);
+        Some(TextureTransform {
+            offset: Vec2::new(origin.x, 1.0 - origin.y),
+            rotation: -self.rotation,
+            scale: commute_scaling(self.scaling, self.rotation)?,
+            tex_coord: None,
+        })
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use std::fs;
+
+    use super::{
+        AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_NAME, AI_MATKEY_SHININESS, AddProperty, AiMaterial,
+        AiTextureMapMode, AiTextureType, AiUVTransform, TextureSlot, TextureTransform,
+    };
+    use crate::{
+        AiReal,
+        traits::importer::trait_define::Importer as _,
+        utils::{
+            float_precision::{Vec2, Vec4},
+            get_model_path,
+        },
+    };
+
+    #[cfg(feature = "x_file")]
+    #[test]
+    fn test_material_accessors() {
+        use crate::formats::x::importer::Importer;
+
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = *Importer::read_from_buf(&source).unwrap();
+        let material = scene
+            .materials
+            .iter()
+            .find(|m| m.name() == Some("bottom"))
+            .unwrap();
+        assert_eq!(
+            material.get_color(AI_MATKEY_COLOR_DIFFUSE),
+            Some(Vec4::new(0.588, 0.588, 0.588, 1.0))
+        );
+        assert_eq!(material.get_float(AI_MATKEY_SHININESS), Some(31.0));
+        assert_eq!(material.texture_count(AiTextureType::Diffuse), 1);
+        let slot = material.get_texture(AiTextureType::Diffuse, 0).unwrap();
+        assert!(slot.path.ends_with("bottom.tga"), "{}", slot.path);
+        assert_eq!((slot.uv_index, slot.blend), (0, 1.0));
+        assert!(material.get_texture(AiTextureType::Normals, 0).is_none());
+
+        let mut material = AiMaterial::default();
+        let slot = TextureSlot {
+            uv_index: 1,
+            blend: 0.5,
+            map_mode: [AiTextureMapMode::Clamp, AiTextureMapMode::Mirror],
+            ..TextureSlot::new("normals.png")
+        };
+        material.set_texture(AiTextureType::Normals, 1, slot.clone());
+        material.set_texture(AiTextureType::Normals, 1, slot.clone());
+        assert_eq!(material.get_texture(AiTextureType::Normals, 1), Some(slot));
+        assert_eq!(material.texture_count(AiTextureType::Normals), 1);
+        assert!(material.get_texture(AiTextureType::Normals, 0).is_none());
+    }
+
+    #[cfg(feature = "x_file")]
+    #[test]
+    fn test_material_dump_and_diff() {
+        use crate::formats::x::importer::Importer;
+
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = *Importer::read_from_buf(&source).unwrap();
+        let material = scene
+            .materials
+            .iter()
+            .find(|m| m.name() == Some("bottom"))
+            .unwrap();
+        assert_eq!(
+            material.dump(),
+            r#"$clr.diffuse = (0.588, 0.588, 0.588, 1)
+$clr.emissive = (0, 0, 0)
+$clr.specular = (0.38, 0.38, 0.38)
+$mat.shadingm = Phong
+$mat.shininess = 31
+$tex.file[Diffuse, 0] = "C:\\code\\sourceforge\\kwxport\\trunkMerged\\test\\data\\bottom.tga"
+?mat.name = "bottom"
+"#
+        );
+        assert!(material.diff(material).is_empty());
+
+        let mut changed = material.clone();
+        changed.properties.reverse();
+        changed.properties.retain(|p| p.key != AI_MATKEY_NAME);
+        changed.add_property(AI_MATKEY_SHININESS, 8.0, 0);
+        changed.set_texture(AiTextureType::Normals, 0, TextureSlot::new("nm.png"));
+        let diff = material
+            .diff(&changed)
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diff,
+            [
+                "- $mat.shininess = 31\n+ $mat.shininess = 8",
+                "+ $tex.file[Normals, 0] = \"nm.png\"",
+                "- ?mat.name = \"bottom\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uv_transform() {
+        let uv = Vec2::new(0.25, 0.75);
+        assert!(AiUVTransform::default().is_identity(0.0));
+        assert_eq!(AiUVTransform::default().transform(uv), uv);
+
+        let shift = AiUVTransform {
+            translation: Vec2::new(0.5, 0.0),
+            ..Default::default()
+        };
+        assert_eq!(shift.transform(uv), Vec2::new(0.75, 0.75));
+
+        // rotations and scalings are centered at (0.5, 0.5)
+        let rotate = AiUVTransform {
+            rotation: core::f64::consts::PI as AiReal,
+            ..Default::default()
+        };
+        assert!(
+            rotate
+                .transform(uv)
+                .abs_diff_eq(Vec2::new(0.75, 0.25), 1e-6)
+        );
+        let scale = AiUVTransform {
+            scaling: Vec2::splat(2.0),
+            ..Default::default()
+        };
+        assert_eq!(scale.transform(uv), Vec2::new(0.0, 1.0));
+
+        let combined = shift.then(&rotate).transform_point2(uv);
+        assert!(combined.abs_diff_eq(rotate.transform(shift.transform(uv)), 1e-6));
+        assert!(!rotate.abs_diff_eq(&shift, 1e-3));
+    }
+
+    #[test]
+    fn test_texture_transform() {
+        let gltf = TextureTransform {
+            offset: Vec2::new(0.1, 0.2),
+            rotation: 0.5,
+            scale: Vec2::splat(2.0),
+            tex_coord: Some(1),
+        };
+        let transform = AiUVTransform::from_texture_transform(&gltf).unwrap();
+        // the origin of glTF is the upper left corner
+        assert!(
+            transform
+                .transform(Vec2::Y)
+                .abs_diff_eq(Vec2::new(0.1, 0.8), 1e-6)
+        );
+        let unrotated = AiUVTransform::from_texture_transform(&TextureTransform {
+            rotation: 0.0,
+            ..gltf.clone()
+        })
+        .unwrap();
+        assert!(
+            unrotated
+                .transform(Vec2::ONE)
+                .abs_diff_eq(Vec2::new(2.1, 0.8), 1e-6)
+        );
+
+        let back = transform.to_texture_transform().unwrap();
+        assert!(back.offset.abs_diff_eq(gltf.offset, 1e-6));
+        assert!((back.rotation - gltf.rotation).abs() < 1e-6);
+        assert_eq!(back.scale, gltf.scale);
+        assert_eq!(back.tex_coord, None);
+        assert!(
+            AiUVTransform::from_texture_transform(&TextureTransform::default())
+                .unwrap()
+                .is_identity(1e-6)
+        );
+
+        // a quarter turn swaps the axes of a non-uniform scaling
+        let quarter = TextureTransform {
+            rotation: core::f32::consts::FRAC_PI_2 as AiReal,
+            scale: Vec2::new(2.0, 3.0),
+            ..gltf.clone()
+        };
+        let transform = AiUVTransform::from_texture_transform(&quarter).unwrap();
+        assert_eq!(transform.scaling, Vec2::new(3.0, 2.0));
+        let back = transform.to_texture_transform().unwrap();
+        assert!(back.offset.abs_diff_eq(quarter.offset, 1e-6));
+        assert!(back.scale.abs_diff_eq(quarter.scale, 1e-6));
+
+        // other rotations of non-uniform scalings shear in assimp's order
+        let sheared = TextureTransform {
+            scale: Vec2::new(2.0, 3.0),
+            ..gltf
+        };
+        assert!(AiUVTransform::from_texture_transform(&sheared).is_none());
+        let transform = AiUVTransform {
+            scaling: Vec2::new(2.0, 3.0),
+            rotation: 0.5,
+            ..Default::default()
+        };
+        assert!(transform.to_texture_transform().is_none());
+    }
+}
@@ -1,13 +1,55 @@
-use super::{aabb::AABB, bone::AiBone, color::Color4D, face::AiFace, node::Node, nodes::Index};
+use std::collections::BTreeMap;
+
+use super::{
+    aabb::AABB,
+    bone::AiBone,
+    color::Color4D,
+    face::{AiFace, AiFaceBuffer},
+    nodes::Index,
+    scene::AiNode,
+};
 use crate::utils::float_precision::{Mat4, Vec3};
 
 pub const AI_MAX_NUMBER_OF_COLOR_SETS: usize = 0x8;
 pub const AI_MAX_NUMBER_OF_TEXTURECOORDS: usize = 0x8;
 
+bitflags::bitflags! {
+    /// Types of the faces of a mesh, see [`AiMesh::primitive_type`]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct PrimitiveTypeFlags: u32 {
+        /// Faces with a single index
+        const POINT = 0x1;
+        /// Faces with two indices
+        const LINE = 0x2;
+        /// Faces with three indices
+        const TRIANGLE = 0x4;
+        /// Faces with more than three indices
+        const POLYGON = 0x8;
+        /// The polygons are stored as triangle fans, consecutive triangles
+        /// starting with the same index belong to the same polygon
+        const NGON_ENCODING = 0x10;
+    }
+}
+
+impl PrimitiveTypeFlags {
+    /// The primitive type of a face with `count` indices, empty for 0
+    pub const fn for_index_count(count: usize) -> Self {
+        match count {
+            0 => Self::empty(),
+            1 => Self::POINT,
+            2 => Self::LINE,
+            3 => Self::TRIANGLE,
+            _ => Self::POLYGON,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct AiMesh {
     pub name: String,
-    pub primitive_type: u32,
+    /// Types of all faces of the mesh, importers set them while creating
+    /// the faces
+    pub primitive_type: PrimitiveTypeFlags,
     pub vertices: Vec<Vec3>,
     pub normals: Vec<Vec3>,
     pub tangents: Vec<Vec3>,
@@ -25,6 +67,15 @@ pub struct AiMesh {
 }
 
 impl AiMesh {
+    /// Recomputes [`primitive_type`](Self::primitive_type) from the faces,
+    /// for code which changes the faces. The `NGON_ENCODING` flag is kept.
+    pub fn update_primitive_types(&mut self) {
+        self.primitive_type = self.faces.iter().fold(
+            self.primitive_type & PrimitiveTypeFlags::NGON_ENCODING,
+            |flags, face| flags | PrimitiveTypeFlags::for_index_count(face.indices.len()),
+        );
+    }
+
     pub fn has_positions(&self) -> bool {
         !self.vertices.is_empty()
     }
@@ -98,6 +149,134 @@ impl AiMesh {
         }
         None
     }
+
+    /// The faces as a single index buffer
+    pub fn face_buffer(&self) -> AiFaceBuffer {
+        AiFaceBuffer::from_faces(&self.faces)
+    }
+
+    /// Replaces the faces with those of `buffer` and recomputes
+    /// [`primitive_type`](Self::primitive_type)
+    pub fn set_face_buffer(&mut self, buffer: &AiFaceBuffer) {
+        self.faces = buffer.to_faces();
+        self.update_primitive_types();
+    }
+
+    /// Flattens the mesh into a plain triangle list.
+    ///
+    /// Polygons are triangulated as fans, points and lines are skipped. The
+    /// positions are copied as they are, so the indices keep referring to
+    /// the vertices of the mesh.
+    pub fn to_triangle_list(&self) -> TriangleList {
+        TriangleList {
+            positions: self.vertices.clone(),
+            indices: self.triangle_indices(),
+        }
+    }
+
+    /// Merges all meshes sharing a material with [`Self::merge`], returns
+    /// one mesh per material ordered by material index, e.g. to draw the
+    /// meshes of a scene with one call per material.
+    pub fn merge_by_material(meshes: &[AiMesh]) -> Vec<AiMesh> {
+        let mut groups: BTreeMap<u32, Vec<&AiMesh>> = BTreeMap::new();
+        for mesh in meshes {
+            groups.entry(mesh.material_index).or_default().push(mesh);
+        }
+        groups
+            .into_values()
+            .map(|group| Self::merge_refs(&group))
+            .collect()
+    }
+
+    /// Joins several meshes into a single one.
+    ///
+    /// The vertex components present in the first mesh determine the
+    /// components of the result, missing ones are filled with zeros. Face
+    /// indices and bone weights are offset accordingly and bones with the
+    /// same name are combined. The material of the first mesh is used.
+    pub fn merge(meshes: &[AiMesh]) -> AiMesh {
+        Self::merge_refs(&meshes.iter().collect::<Vec<_>>())
+    }
+
+    fn merge_refs(meshes: &[&AiMesh]) -> AiMesh {
+        let Some(first) = meshes.first() else {
+            return AiMesh::default();
+        };
+        let mut out = AiMesh {
+            name: meshes
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>()
+                .join("."),
+            material_index: first.material_index,
+            texture_coords_names: first.texture_coords_names.clone(),
+            num_of_uv_components: first.num_of_uv_components.clone(),
+            method: first.method.clone(),
+            ..Default::default()
+        };
+        let num_vertices = meshes.iter().map(|m| m.vertices.len()).sum();
+        let num_faces = meshes.iter().map(|m| m.faces.len()).sum();
+        out.vertices.reserve(num_vertices);
+        out.faces.reserve(num_faces);
+
+        fn append<T: Clone + Default>(out: &mut Vec<T>, src: &[T], len: usize) {
+            if src.len() == len {
+                out.extend_from_slice(src);
+            } else {
+                out.resize(out.len() + len, T::default());
+            }
+        }
+
+        let has_normals = first.has_normals();
+        let has_tangents = first.has_tangents_and_bitangents();
+        for mesh in meshes {
+            let offset = out.vertices.len() as u32;
+            let len = mesh.vertices.len();
+            out.primitive_type |= mesh.primitive_type;
+            out.vertices.extend_from_slice(&mesh.vertices);
+            if has_normals {
+                append(&mut out.normals, &mesh.normals, len);
+            }
+            if has_tangents {
+                append(&mut out.tangents, &mesh.tangents, len);
+                append(&mut out.bitangents, &mesh.bitangents, len);
+            }
+            for (i, uvs) in out.texture_coords.iter_mut().enumerate() {
+                if first.has_texture_coords(i) {
+                    append(uvs, &mesh.texture_coords[i], len);
+                }
+            }
+            for (i, colors) in out.colors.iter_mut().enumerate() {
+                if first.has_vertex_colors(i) {
+                    append(colors, &mesh.colors[i], len);
+                }
+            }
+            out.faces.extend(mesh.faces.iter().map(|face| AiFace {
+                indices: face.indices.iter().map(|i| i + offset).collect(),
+            }));
+            for bone in &mesh.bones {
+                let weights = bone.weights.iter().map(|w| AiVertexWeight {
+                    vertex_id: w.vertex_id + offset,
+                    weight: w.weight,
+                });
+                match out.bones.iter_mut().find(|b| b.name == bone.name) {
+                    Some(merged) => merged.weights.extend(weights),
+                    None => out.bones.push(AiBone {
+                        weights: weights.collect(),
+                        ..bone.clone()
+                    }),
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Flattened triangle geometry, see [`AiMesh::to_triangle_list`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriangleList {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<u32>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -191,18 +370,18 @@ pub enum MorphingMethod {
  * Tree: s1 -> s2 -> s3
  * Offset-Matrix s3 = locale-s3 * locale-s2 * locale-s1
  */
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct SkeletonBone {
     /// The parent bone index, is -1 one if this bone represents the root bone.
     pub parent: i32,
 
     /// @brief The bone armature node - used for skeleton conversion
     /// you must enable aiProcess_PopulateArmatureData to populate this
-    pub armature: Index<Node>,
+    pub armature: Option<Index<AiNode>>,
 
     /// @brief The bone node in the scene - used for skeleton conversion
     /// you must enable aiProcess_PopulateArmatureData to populate this
-    pub node: Index<Node>,
+    pub node: Option<Index<AiNode>>,
 
     /// The mesh index, which will get influenced by the weight.
     pub mesh_id: Index<AiMesh>,
@@ -241,6 +420,7 @@ pub struct SkeletonBone {
  * root->node1->node3
  * Each node is represented as a skeleton instance.
  */
+#[derive(Debug, Clone, Default)]
 pub struct Skeleton {
     /**
      *  @brief The name of the skeleton instance.
@@ -252,3 +432,138 @@ pub struct Skeleton {
      */
     pub bones: Box<[SkeletonBone]>,
 }
+
+#[allow(unused)]
+mod test {
+    use super::{AiMesh, AiVertexWeight, PrimitiveTypeFlags, TriangleList};
+    use crate::{
+        structs::{bone::AiBone, face::AiFace},
+        utils::float_precision::Vec3,
+    };
+
+    fn mesh(name: &str, material_index: u32, vertices: usize, faces: &[&[u32]]) -> AiMesh {
+        let mut mesh = AiMesh {
+            name: name.to_owned(),
+            material_index,
+            vertices: (0..vertices)
+                .map(|i| Vec3::new(material_index as _, i as _, 0.0))
+                .collect(),
+            faces: faces
+                .iter()
+                .map(|indices| AiFace {
+                    indices: indices.to_vec().into(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        mesh.update_primitive_types();
+        mesh
+    }
+
+    fn bone(name: &str, vertices: &[u32]) -> AiBone {
+        AiBone {
+            name: name.to_owned(),
+            weights: vertices
+                .iter()
+                .map(|&vertex_id| AiVertexWeight {
+                    vertex_id,
+                    weight: 1.0,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn bone_vertices(mesh: &AiMesh) -> Vec<(&str, Vec<u32>)> {
+        mesh.bones
+            .iter()
+            .map(|b| (&*b.name, b.weights.iter().map(|w| w.vertex_id).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_to_triangle_list() {
+        // a quad, a triangle, a line and a point
+        let mesh = mesh("mesh", 0, 5, &[&[0, 1, 2, 3], &[1, 4, 2], &[0, 4], &[3]]);
+        let list = mesh.to_triangle_list();
+        assert_eq!(list.positions, mesh.vertices);
+        assert_eq!(list.indices, [0, 1, 2, 0, 2, 3, 1, 4, 2]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = mesh("a", 0, 4, &[&[0, 1, 2, 3]]);
+        a.normals = vec![Vec3::Z; 4];
+        a.bones = vec![bone("shared", &[0, 3]), bone("a", &[1])];
+        let mut b = mesh("b", 1, 3, &[&[0, 1, 2], &[2, 0]]);
+        b.bones = vec![bone("shared", &[2]), bone("b", &[0])];
+
+        let merged = AiMesh::merge(&[a.clone(), b.clone()]);
+        let triangles = |list: TriangleList| {
+            list.indices
+                .iter()
+                .map(|&i| list.positions[i as usize])
+                .collect::<Vec<_>>()
+        };
+        // the triangles of the merged mesh are those of the parts
+        assert_eq!(
+            triangles(merged.to_triangle_list()),
+            [
+                triangles(a.to_triangle_list()),
+                triangles(b.to_triangle_list())
+            ]
+            .concat()
+        );
+
+        assert_eq!(merged.name, "a.b");
+        assert_eq!(merged.material_index, 0);
+        assert_eq!(
+            merged.primitive_type,
+            PrimitiveTypeFlags::POLYGON | PrimitiveTypeFlags::TRIANGLE | PrimitiveTypeFlags::LINE
+        );
+        assert_eq!(merged.vertices, [a.vertices, b.vertices].concat());
+        // b has no normals, they are filled with zeros
+        assert_eq!(
+            merged.normals[3..],
+            [Vec3::Z, Vec3::ZERO, Vec3::ZERO, Vec3::ZERO]
+        );
+        let faces = merged
+            .faces
+            .iter()
+            .map(|f| f.indices.to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(faces, [vec![0, 1, 2, 3], vec![4, 5, 6], vec![6, 4]]);
+        assert_eq!(
+            bone_vertices(&merged),
+            [("shared", vec![0, 3, 6]), ("a", vec![1]), ("b", vec![4])]
+        );
+
+        assert!(AiMesh::merge(&[]).vertices.is_empty());
+    }
+
+    #[test]
+    fn test_merge_by_material() {
+        let meshes = [
+            mesh("a", 1, 3, &[&[0, 1, 2]]),
+            mesh("b", 0, 3, &[&[0, 1, 2]]),
+            mesh("c", 1, 4, &[&[0, 1, 2], &[2, 3, 0]]),
+        ];
+        let merged = AiMesh::merge_by_material(&meshes);
+        let names = merged
+            .iter()
+            .map(|m| (&*m.name, m.material_index))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [("b", 0), ("a.c", 1)]);
+
+        let merged = &merged[1];
+        assert_eq!(merged.vertices.len(), 7);
+        assert_eq!(merged.faces[1].indices[..], [3, 4, 5]);
+        assert_eq!(merged.faces[2].indices[..], [5, 6, 3]);
+        // every vertex of the parts is still referenced by its triangles
+        let list = merged.to_triangle_list();
+        let mut used = list.indices.clone();
+        used.sort_unstable();
+        used.dedup();
+        assert_eq!(used, (0..7).collect::<Vec<_>>());
+    }
+}
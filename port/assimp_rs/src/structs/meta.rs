@@ -2,6 +2,38 @@ use indexmap::IndexMap;
 
 use crate::{AiReal, utils::float_precision::Vec3};
 
+/// Scene metadata key of the name of the format the scene was imported from
+pub const AI_METADATA_SOURCE_FORMAT: &str = "SourceAsset_Format";
+/// Scene metadata key of the version of the source format
+pub const AI_METADATA_SOURCE_FORMAT_VERSION: &str = "SourceAsset_FormatVersion";
+/// Scene metadata key of the application which wrote the source file
+pub const AI_METADATA_SOURCE_GENERATOR: &str = "SourceAsset_Generator";
+/// Scene metadata key of the copyright notice of the source file
+pub const AI_METADATA_SOURCE_COPYRIGHT: &str = "SourceAsset_Copyright";
+/// Scene metadata key of the codepage a text file was converted from, set
+/// only for files which were not Unicode
+pub const AI_METADATA_SOURCE_ENCODING: &str = "SourceAsset_Encoding";
+/// Scene metadata key of the up axis, 0 for x, 1 for y and 2 for z
+pub const AI_METADATA_UP_AXIS: &str = "UpAxis";
+/// Scene metadata key of the direction of the up axis, 1 or -1
+pub const AI_METADATA_UP_AXIS_SIGN: &str = "UpAxisSign";
+/// Scene metadata key of the front axis, 0 for x, 1 for y and 2 for z
+pub const AI_METADATA_FRONT_AXIS: &str = "FrontAxis";
+/// Scene metadata key of the direction of the front axis, 1 or -1
+pub const AI_METADATA_FRONT_AXIS_SIGN: &str = "FrontAxisSign";
+/// Scene metadata key of the third axis, 0 for x, 1 for y and 2 for z
+pub const AI_METADATA_COORD_AXIS: &str = "CoordAxis";
+/// Scene metadata key of the direction of the third axis, 1 or -1
+pub const AI_METADATA_COORD_AXIS_SIGN: &str = "CoordAxisSign";
+/// Scene metadata key of the size of a unit in centimeters
+pub const AI_METADATA_UNIT_SCALE_FACTOR: &str = "UnitScaleFactor";
+/// Scene metadata key of the unit scale factor before it was changed by
+/// post-processing
+pub const AI_METADATA_ORIGINAL_UNIT_SCALE_FACTOR: &str = "OriginalUnitScaleFactor";
+/// Scene metadata key of the color space of the vertex, material and light
+/// colors, `sRGB` or `Linear`
+pub const AI_METADATA_COLOR_SPACE: &str = "ColorSpace";
+
 #[derive(Debug, Clone)]
 pub enum MetadataEntry {
     Bool(bool),
@@ -40,4 +72,189 @@ impl Default for MetadataEntry {
     }
 }
 
+impl MetadataEntry {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            Self::Int32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Self::UInt32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of any integer entry which fits into an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int32(value) => Some((*value).into()),
+            Self::UInt32(value) => Some((*value).into()),
+            Self::Int64(value) => Some(*value),
+            Self::UInt64(value) => (*value).try_into().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value of any non-negative integer entry
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Int32(value) => (*value).try_into().ok(),
+            Self::UInt32(value) => Some((*value).into()),
+            Self::Int64(value) => (*value).try_into().ok(),
+            Self::UInt64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<AiReal> {
+        match self {
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_vec3(&self) -> Option<Vec3> {
+        match self {
+            Self::Vector3(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_metadata(&self) -> Option<&Metadata> {
+        match self {
+            Self::Metadata(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_from_for_entry {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for MetadataEntry {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_for_entry!(
+    bool => Bool,
+    i32 => Int32,
+    u32 => UInt32,
+    i64 => Int64,
+    u64 => UInt64,
+    AiReal => Float,
+    Vec3 => Vector3,
+    &str => String,
+    String => String,
+    Box<str> => String,
+    Metadata => Metadata,
+);
+
 pub type Metadata = IndexMap<String, MetadataEntry>;
+
+/// Typed access to [`Metadata`].
+///
+/// The getters return `None` for missing keys and for entries of another
+/// type. Keys can be paths of nested [`MetadataEntry::Metadata`] entries,
+/// joined by dots, e.g. `"Asset.Author"`.
+pub trait MetadataExt {
+    /// The entry at a dotted path
+    fn get_path(&self, path: &str) -> Option<&MetadataEntry>;
+
+    /// Inserts `value` at a dotted path, creating the nested metadata on the
+    /// way. Entries on the way which are not metadata are replaced. Returns
+    /// the previous value.
+    fn set_path(&mut self, path: &str, value: impl Into<MetadataEntry>) -> Option<MetadataEntry>;
+
+    /// Inserts `value` under `key` and returns the previous value.
+    fn set(&mut self, key: &str, value: impl Into<MetadataEntry>) -> Option<MetadataEntry>;
+
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get_path(key)?.as_bool()
+    }
+
+    fn get_int32(&self, key: &str) -> Option<i32> {
+        self.get_path(key)?.as_i32()
+    }
+
+    fn get_uint32(&self, key: &str) -> Option<u32> {
+        self.get_path(key)?.as_u32()
+    }
+
+    /// Also converts the other integer types, see [`MetadataEntry::as_i64`]
+    fn get_int64(&self, key: &str) -> Option<i64> {
+        self.get_path(key)?.as_i64()
+    }
+
+    /// Also converts the other integer types, see [`MetadataEntry::as_u64`]
+    fn get_uint64(&self, key: &str) -> Option<u64> {
+        self.get_path(key)?.as_u64()
+    }
+
+    fn get_float(&self, key: &str) -> Option<AiReal> {
+        self.get_path(key)?.as_float()
+    }
+
+    fn get_str(&self, key: &str) -> Option<&str> {
+        self.get_path(key)?.as_str()
+    }
+
+    fn get_vec3(&self, key: &str) -> Option<Vec3> {
+        self.get_path(key)?.as_vec3()
+    }
+
+    fn get_metadata(&self, key: &str) -> Option<&Metadata> {
+        self.get_path(key)?.as_metadata()
+    }
+}
+
+impl MetadataExt for Metadata {
+    fn get_path(&self, path: &str) -> Option<&MetadataEntry> {
+        // keys containing dots are found directly
+        if let Some(entry) = self.get(path) {
+            return Some(entry);
+        }
+        let (first, rest) = path.split_once('.')?;
+        self.get(first)?.as_metadata()?.get_path(rest)
+    }
+
+    fn set_path(&mut self, path: &str, value: impl Into<MetadataEntry>) -> Option<MetadataEntry> {
+        let Some((first, rest)) = path.split_once('.') else {
+            return self.set(path, value);
+        };
+        match self.entry(first.to_owned()).or_default() {
+            MetadataEntry::Metadata(nested) => nested.set_path(rest, value),
+            entry => {
+                let mut nested = Metadata::default();
+                nested.set_path(rest, value);
+                *entry = MetadataEntry::Metadata(Box::new(nested));
+                None
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: impl Into<MetadataEntry>) -> Option<MetadataEntry> {
+        self.insert(key.to_owned(), value.into())
+    }
+}
@@ -4,17 +4,34 @@ pub mod blob;
 pub mod bone;
 pub mod camera;
 pub mod color;
+pub mod coordinate_system;
+pub mod diff;
 pub mod exporter;
+pub mod exporter_desc;
+pub mod extensions;
 pub mod face;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hierarchy;
+pub mod importer;
 pub mod importer_desc;
 pub mod key;
 pub mod light;
 pub mod material;
 pub mod mesh;
+pub mod names;
 pub mod meta;
 pub mod node;
 pub mod nodes;
+pub mod pbr;
 pub mod plane;
+pub mod precision;
+pub mod quantize;
 pub mod ray;
 pub mod scene;
+pub mod scene_builder;
+pub mod shared;
+pub mod skinning;
+pub mod stats;
 pub mod texture;
+pub mod vertex_layout;
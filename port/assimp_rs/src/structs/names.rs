@@ -0,0 +1,216 @@
+//! Sanitizing of names for exporters: length limits, control characters and
+//! characters reserved by a format, see [`NameRules`] and
+//! [`AiScene::sanitize_names`].
+
+use std::borrow::Cow;
+
+use super::{
+    meta::{Metadata, MetadataEntry},
+    scene::AiScene,
+};
+
+/// Size of the buffer of an `aiString` of assimp, including the terminating
+/// zero
+pub const AI_MAXLEN: usize = 1024;
+
+/// Scene metadata key of the original names of objects renamed by
+/// [`AiScene::sanitize_names`], a metadata with the new names as keys
+pub const AI_METADATA_ORIGINAL_NAMES: &str = "OriginalNames";
+
+/// How names are sanitized for a format
+#[derive(Debug, Clone, Copy)]
+pub struct NameRules {
+    /// Longest name in bytes, longer names are cut at a character boundary
+    pub max_len: usize,
+    /// Characters the format does not allow in names, control characters
+    /// are always replaced
+    pub is_reserved: fn(char) -> bool,
+    /// Replaces control and reserved characters
+    pub replacement: char,
+}
+
+impl NameRules {
+    /// Names which fit into an `aiString`, only control characters are
+    /// replaced
+    pub const AI_STRING: Self = Self {
+        max_len: AI_MAXLEN - 1,
+        is_reserved: |_| false,
+        replacement: '_',
+    };
+
+    /// Identifiers of X files, which consist of ASCII letters and digits
+    pub const X_FILE: Self = Self {
+        max_len: AI_MAXLEN - 1,
+        is_reserved: |c| !c.is_ascii_alphanumeric(),
+        replacement: '_',
+    };
+
+    /// The name following the rules
+    pub fn sanitize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let replace = |c: char| c.is_control() || (self.is_reserved)(c);
+        let mut len = name.len().min(self.max_len);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+        let name = &name[..len];
+        if !name.contains(replace) {
+            return Cow::Borrowed(name);
+        }
+        let mut sanitized = name.replace(replace, self.replacement.encode_utf8(&mut [0; 4]));
+        // the replacement may be longer than the replaced characters
+        while sanitized.len() > self.max_len {
+            sanitized.pop();
+        }
+        Cow::Owned(sanitized)
+    }
+}
+
+impl Default for NameRules {
+    fn default() -> Self {
+        Self::AI_STRING
+    }
+}
+
+impl AiScene {
+    /// Sanitizes the names of all nodes, meshes, bones, animations, lights
+    /// and cameras, and the names animation channels refer to them by.
+    /// Returns the number of renamed objects.
+    ///
+    /// Different names may become the same, run
+    /// [`UniqueNamesProcess`](crate::postprocess::unique_names::UniqueNamesProcess)
+    /// afterwards if names have to be unique. With `keep_originals` the
+    /// original names are stored under [`AI_METADATA_ORIGINAL_NAMES`] in the
+    /// scene metadata, the first original of a new name wins.
+    pub fn sanitize_names(&mut self, rules: &NameRules, keep_originals: bool) -> usize {
+        let mut originals = Metadata::default();
+        let mut renamed = 0;
+        let mut rename = |name: &mut dyn NameField| {
+            let sanitized = rules.sanitize(name.get());
+            if sanitized == name.get() {
+                return;
+            }
+            let sanitized = sanitized.into_owned();
+            if keep_originals {
+                originals
+                    .entry(sanitized.clone())
+                    .or_insert_with(|| MetadataEntry::String(name.get().into()));
+            }
+            name.set(sanitized);
+            renamed += 1;
+        };
+
+        for node in self.nodes.iter_mut() {
+            rename(&mut node.name);
+        }
+        for mesh in self.meshes.iter_mut() {
+            rename(&mut mesh.name);
+            for bone in mesh.bones.iter_mut() {
+                rename(&mut bone.name);
+            }
+        }
+        for animation in self.animations.iter_mut() {
+            rename(&mut animation.name);
+            for channel in animation.channels.iter_mut() {
+                rename(&mut channel.node_name);
+            }
+            for channel in animation.mesh_channels.iter_mut() {
+                rename(&mut channel.name);
+            }
+            for channel in animation.morph_mesh_channels.iter_mut() {
+                rename(&mut channel.name);
+            }
+        }
+        for light in self.lights.iter_mut() {
+            rename(&mut light.name);
+        }
+        for camera in self.cameras.iter_mut() {
+            rename(&mut camera.name);
+        }
+
+        if !originals.is_empty() {
+            match self
+                .metadata
+                .entry(AI_METADATA_ORIGINAL_NAMES.to_owned())
+                .or_insert_with(|| MetadataEntry::Metadata(Box::default()))
+            {
+                MetadataEntry::Metadata(existing) => {
+                    for (name, original) in originals {
+                        existing.entry(name).or_insert(original);
+                    }
+                }
+                entry => *entry = MetadataEntry::Metadata(Box::new(originals)),
+            }
+        }
+        renamed
+    }
+}
+
+/// The owned string types of names
+trait NameField {
+    fn get(&self) -> &str;
+
+    fn set(&mut self, name: String);
+}
+
+impl NameField for String {
+    fn get(&self) -> &str {
+        self
+    }
+
+    fn set(&mut self, name: String) {
+        *self = name;
+    }
+}
+
+impl NameField for Box<str> {
+    fn get(&self) -> &str {
+        self
+    }
+
+    fn set(&mut self, name: String) {
+        *self = name.into();
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::borrow::Cow;
+
+    use super::{AI_MAXLEN, AI_METADATA_ORIGINAL_NAMES, NameRules};
+    use crate::{
+        formats::x::importer::Importer, structs::meta::MetadataExt,
+        traits::importer::trait_define::Importer as _,
+    };
+
+    #[test]
+    fn test_sanitize_names() {
+        let rules = NameRules::AI_STRING;
+        assert!(matches!(rules.sanitize("Arm.L"), Cow::Borrowed("Arm.L")));
+        assert_eq!(rules.sanitize("Arm\tL\n"), "Arm_L_");
+        // names are cut at a character boundary
+        let long = "ä".repeat(AI_MAXLEN);
+        assert_eq!(rules.sanitize(&long).len(), AI_MAXLEN - 2);
+        assert_eq!(NameRules::X_FILE.sanitize("Arm.L-ä"), "Arm_L__");
+
+        let mut scene = *Importer::read_from_buf(
+            br#"xof 0303txt 0032
+Frame Root {
+Frame Arm.L { Mesh { 3; 0;0;0;, 1;0;0;, 0;1;0;; 1; 3;0,1,2;; } }
+}
+AnimationSet { Animation { {Arm.L} AnimationKey { 2; 1; 0;3;1,2,3;;; } } }
+"#,
+        )
+        .unwrap();
+        // the node, its mesh and the animation channel
+        assert_eq!(scene.sanitize_names(&NameRules::X_FILE, true), 3);
+        assert_eq!(scene.nodes[1].name, "Arm_L");
+        assert_eq!(&*scene.animations[0].channels[0].node_name, "Arm_L");
+        let originals = scene
+            .metadata
+            .get_metadata(AI_METADATA_ORIGINAL_NAMES)
+            .unwrap();
+        assert_eq!(originals.get_str("Arm_L"), Some("Arm.L"));
+        assert_eq!(scene.sanitize_names(&NameRules::X_FILE, true), 0);
+    }
+}
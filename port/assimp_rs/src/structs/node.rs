@@ -7,7 +7,7 @@ use crate::{
 pub struct Node {
     pub name: String,
     pub transformation_matrix: Mat4,
-    pub parent: Index<Node>,
+    pub parent: Option<Index<Node>>,
     pub children: Vec<Index<Node>>,
     pub meshes: Vec<u32>,
     pub metadata: Box<Metadata>,
@@ -2,21 +2,14 @@
 
 use core::{any, marker};
 
+/// Position of a `T` in a `Vec<T>`.
+///
+/// Every value is a valid position, including 0, so references which may be
+/// missing, like the parent of the root node, are `Option<Index<T>>`.
 #[derive(Debug)]
 pub struct Index<T>(u32, marker::PhantomData<fn() -> T>);
 
-impl<T> Default for Index<T> {
-    fn default() -> Self {
-        Self::new(0)
-    }
-}
-
 impl<T> Index<T> {
-    pub const GUARD_INDEX: Index<T> = Index::new(0);
-
-    pub fn is_exist(self) -> bool {
-        self.value() != 0
-    }
     pub fn push(vec: &mut Vec<T>, value: T) -> Index<T> {
         let len = vec.len();
         let Ok(index): Result<u32, _> = len.try_into() else {
@@ -30,9 +23,15 @@ impl<T> Index<T> {
         Index::new(index)
     }
 
+    #[cfg(feature = "paranoid")]
+    pub fn get(self, vec: &[T]) -> Option<&T> {
+        vec.get(self.value())
+    }
+
+    #[cfg(not(feature = "paranoid"))]
     pub fn get(self, vec: &[T]) -> Option<&T> {
         if self.value() < vec.len() {
-            // SAFETY: self is not guard index and self.value() is guaranteed to be less than vec.len()
+            // SAFETY: self.value() is guaranteed to be less than vec.len()
             Some(unsafe { self.get_unchecked(vec) })
         } else {
             None
@@ -43,9 +42,15 @@ impl<T> Index<T> {
         unsafe { vec.get_unchecked(self.value()) }
     }
 
+    #[cfg(feature = "paranoid")]
+    pub fn get_mut(self, vec: &mut [T]) -> Option<&mut T> {
+        vec.get_mut(self.value())
+    }
+
+    #[cfg(not(feature = "paranoid"))]
     pub fn get_mut(self, vec: &mut [T]) -> Option<&mut T> {
         if self.value() < vec.len() {
-            // SAFETY: self is not guard index and self.value() is guaranteed to be less than vec.len()
+            // SAFETY: self.value() is guaranteed to be less than vec.len()
             Some(unsafe { self.get_mut_unchecked(vec) })
         } else {
             None
@@ -68,6 +73,13 @@ impl<T> Index<T> {
     pub const fn value(&self) -> usize {
         self.0 as usize
     }
+
+    /// The same offset into an array of another type, e.g. when the
+    /// elements of the array are converted one by one.
+    #[inline(always)]
+    pub const fn cast<U>(self) -> Index<U> {
+        Index::new(self.0)
+    }
 }
 
 impl<T> Clone for Index<T> {
@@ -78,6 +90,32 @@ impl<T> Clone for Index<T> {
 
 impl<T> Copy for Index<T> {}
 
+impl<T> PartialEq for Index<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for Index<T> {}
+
+impl<T> PartialOrd for Index<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Index<T> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T> core::hash::Hash for Index<T> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
 // impl<T> core::ops::Index<Index<T>> for Vec<T> {
 //     type Output = T;
 
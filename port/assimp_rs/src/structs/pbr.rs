@@ -0,0 +1,250 @@
+//! Metallic-roughness view of materials, to exchange materials between
+//! PBR formats like glTF and formats using the Phong model like X or OBJ.
+
+use super::material::{
+    AI_MATKEY_BASE_COLOR, AI_MATKEY_CLEARCOAT_FACTOR, AI_MATKEY_CLEARCOAT_ROUGHNESS_FACTOR,
+    AI_MATKEY_COLOR_DIFFUSE, AI_MATKEY_COLOR_EMISSIVE, AI_MATKEY_EMISSIVE_INTENSITY,
+    AI_MATKEY_METALLIC_FACTOR, AI_MATKEY_OPACITY, AI_MATKEY_ROUGHNESS_FACTOR,
+    AI_MATKEY_SHADING_MODEL, AI_MATKEY_SHININESS, AI_MATKEY_TEXTURE_SCALE,
+    AI_MATKEY_TEXTURE_STRENGTH, AI_MATKEY_TRANSMISSION_FACTOR, AddProperty, AiMaterial, AiProperty,
+    AiShadingMode, AiTextureType, TextureSlot,
+};
+use crate::{
+    AiReal,
+    utils::float_precision::{Vec3, Vec4},
+};
+
+/// The PBR properties of a material in the glTF metallic-roughness model
+#[derive(Clone, Debug, PartialEq)]
+pub struct PbrMaterial {
+    /// Linear RGBA base color, the alpha channel is the opacity
+    pub base_color: Vec4,
+    pub base_color_texture: Option<TextureSlot>,
+    pub metallic: AiReal,
+    pub roughness: AiReal,
+    /// Metalness in the blue and roughness in the green channel
+    pub metallic_roughness_texture: Option<TextureSlot>,
+    pub emissive: Vec3,
+    /// Multiplier of the emissive color
+    pub emissive_intensity: AiReal,
+    pub emissive_texture: Option<TextureSlot>,
+    pub normal_texture: Option<TextureSlot>,
+    /// Scale of the x and y components of the normals of the normal map
+    pub normal_scale: AiReal,
+    pub occlusion_texture: Option<TextureSlot>,
+    /// How much the occlusion texture darkens the material
+    pub occlusion_strength: AiReal,
+    pub clearcoat: AiReal,
+    pub clearcoat_roughness: AiReal,
+    pub transmission: AiReal,
+}
+
+impl Default for PbrMaterial {
+    /// The defaults of glTF: a white, fully metallic and rough material
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            base_color_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            emissive: Vec3::ZERO,
+            emissive_intensity: 1.0,
+            emissive_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.0,
+            transmission: 0.0,
+        }
+    }
+}
+
+/// Roughness with a similar highlight as the Phong exponent `shininess`
+fn shininess_to_roughness(shininess: AiReal) -> AiReal {
+    (2.0 / (shininess.max(0.0) + 2.0)).sqrt()
+}
+
+/// Inverse of [`shininess_to_roughness`]
+fn roughness_to_shininess(roughness: AiReal) -> AiReal {
+    let roughness = roughness.clamp(0.01, 1.0);
+    2.0 / (roughness * roughness) - 2.0
+}
+
+impl PbrMaterial {
+    /// Approximates the PBR properties of a material using the Phong
+    /// model: the diffuse color becomes the base color, the shininess
+    /// the roughness, and the material is treated as a dielectric.
+    pub fn from_legacy_phong(material: &AiMaterial) -> Self {
+        let texture = |ty| material.get_texture(ty, 0);
+        let mut base_color = material
+            .get_color(AI_MATKEY_COLOR_DIFFUSE)
+            .unwrap_or(Vec4::ONE);
+        base_color.w *= material.get_float(AI_MATKEY_OPACITY).unwrap_or(1.0);
+        let emissive = material
+            .get_color(AI_MATKEY_COLOR_EMISSIVE)
+            .map_or(Vec3::ZERO, |color| color.truncate());
+        Self {
+            base_color,
+            base_color_texture: texture(AiTextureType::Diffuse),
+            metallic: 0.0,
+            roughness: material
+                .get_float(AI_MATKEY_SHININESS)
+                .map_or(1.0, shininess_to_roughness),
+            emissive,
+            emissive_texture: texture(AiTextureType::Emissive),
+            normal_texture: texture(AiTextureType::Normals),
+            occlusion_texture: texture(AiTextureType::Lightmap),
+            ..Self::default()
+        }
+    }
+
+    /// Writes the PBR properties to `material`, replacing existing ones.
+    ///
+    /// The diffuse color, shininess and diffuse texture are set as well,
+    /// so consumers which only know the Phong model still get a similar
+    /// material.
+    pub fn apply_to(&self, material: &mut AiMaterial) {
+        material.add_property(AI_MATKEY_SHADING_MODEL, AiShadingMode::PBR_BRDF, 0);
+        material.add_property(AI_MATKEY_BASE_COLOR, self.base_color, 0);
+        material.add_property(AI_MATKEY_METALLIC_FACTOR, self.metallic, 0);
+        material.add_property(AI_MATKEY_ROUGHNESS_FACTOR, self.roughness, 0);
+        material.add_property(AI_MATKEY_COLOR_EMISSIVE, self.emissive, 0);
+        material.add_property(AI_MATKEY_EMISSIVE_INTENSITY, self.emissive_intensity, 0);
+        material.add_property(AI_MATKEY_CLEARCOAT_FACTOR, self.clearcoat, 0);
+        material.add_property(
+            AI_MATKEY_CLEARCOAT_ROUGHNESS_FACTOR,
+            self.clearcoat_roughness,
+            0,
+        );
+        material.add_property(AI_MATKEY_TRANSMISSION_FACTOR, self.transmission, 0);
+
+        // fallbacks for the Phong model
+        material.add_property(AI_MATKEY_COLOR_DIFFUSE, self.base_color, 0);
+        material.add_property(
+            AI_MATKEY_SHININESS,
+            roughness_to_shininess(self.roughness),
+            0,
+        );
+
+        let textures = [
+            (AiTextureType::BaseColor, &self.base_color_texture),
+            (AiTextureType::Diffuse, &self.base_color_texture),
+            (AiTextureType::Metalness, &self.metallic_roughness_texture),
+            (AiTextureType::Emissive, &self.emissive_texture),
+            (AiTextureType::Normals, &self.normal_texture),
+            (AiTextureType::AmbientOcclusion, &self.occlusion_texture),
+        ];
+        for (ty, slot) in textures {
+            if let Some(slot) = slot {
+                material.set_texture(ty, 0, slot.clone());
+            }
+        }
+        if self.normal_texture.is_some() {
+            let scale = AiProperty::Float(self.normal_scale);
+            material.set_property(AI_MATKEY_TEXTURE_SCALE, AiTextureType::Normals, 0, scale);
+        }
+        if self.occlusion_texture.is_some() {
+            let strength = AiProperty::Float(self.occlusion_strength);
+            material.set_property(
+                AI_MATKEY_TEXTURE_STRENGTH,
+                AiTextureType::AmbientOcclusion,
+                0,
+                strength,
+            );
+        }
+    }
+}
+
+impl AiMaterial {
+    /// The PBR properties of the material. Properties which are not set
+    /// are derived from the Phong properties, see
+    /// [`PbrMaterial::from_legacy_phong`].
+    pub fn to_pbr(&self) -> PbrMaterial {
+        let legacy = PbrMaterial::from_legacy_phong(self);
+        let float = |key, default| self.get_float(key).unwrap_or(default);
+        let texture = |ty, fallback| self.get_texture(ty, 0).or(fallback);
+        let texture_float = |key, ty| {
+            self.property(key, ty, 0)
+                .and_then(AiProperty::as_float)
+                .unwrap_or(1.0)
+        };
+        PbrMaterial {
+            base_color: self
+                .get_color(AI_MATKEY_BASE_COLOR)
+                .unwrap_or(legacy.base_color),
+            base_color_texture: texture(AiTextureType::BaseColor, legacy.base_color_texture),
+            metallic: float(AI_MATKEY_METALLIC_FACTOR, legacy.metallic),
+            roughness: float(AI_MATKEY_ROUGHNESS_FACTOR, legacy.roughness),
+            metallic_roughness_texture: self.get_texture(AiTextureType::Metalness, 0),
+            emissive: legacy.emissive,
+            emissive_intensity: float(AI_MATKEY_EMISSIVE_INTENSITY, 1.0),
+            emissive_texture: legacy.emissive_texture,
+            normal_texture: texture(AiTextureType::NormalCamera, legacy.normal_texture),
+            normal_scale: texture_float(AI_MATKEY_TEXTURE_SCALE, AiTextureType::Normals),
+            occlusion_texture: texture(AiTextureType::AmbientOcclusion, legacy.occlusion_texture),
+            occlusion_strength: texture_float(
+                AI_MATKEY_TEXTURE_STRENGTH,
+                AiTextureType::AmbientOcclusion,
+            ),
+            clearcoat: float(AI_MATKEY_CLEARCOAT_FACTOR, 0.0),
+            clearcoat_roughness: float(AI_MATKEY_CLEARCOAT_ROUGHNESS_FACTOR, 0.0),
+            transmission: float(AI_MATKEY_TRANSMISSION_FACTOR, 0.0),
+        }
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::PbrMaterial;
+    use crate::{
+        AiReal,
+        formats::x::importer::Importer,
+        structs::material::{AI_MATKEY_COLOR_DIFFUSE, AiMaterial, TextureSlot},
+        traits::importer::trait_define::Importer as _,
+        utils::{float_precision::Vec4, get_model_path},
+    };
+
+    #[test]
+    fn test_pbr_from_phong() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = *Importer::read_from_buf(&source).unwrap();
+        let material = scene
+            .materials
+            .iter()
+            .find(|m| m.name() == Some("bottom"))
+            .unwrap();
+        let pbr = material.to_pbr();
+        assert_eq!(pbr, PbrMaterial::from_legacy_phong(material));
+        assert_eq!(pbr.base_color, Vec4::new(0.588, 0.588, 0.588, 1.0));
+        assert_eq!(pbr.metallic, 0.0);
+        assert!((pbr.roughness - (2.0 / 33.0 as AiReal).sqrt()).abs() < 1e-6);
+        assert!(
+            pbr.base_color_texture
+                .as_ref()
+                .unwrap()
+                .path
+                .ends_with("bottom.tga")
+        );
+
+        let mut converted = AiMaterial::default();
+        let pbr = PbrMaterial {
+            clearcoat: 0.25,
+            normal_texture: Some(TextureSlot::new("normals.png")),
+            normal_scale: 0.5,
+            ..pbr
+        };
+        pbr.apply_to(&mut converted);
+        assert_eq!(converted.to_pbr(), pbr);
+        // Phong consumers still see the base color
+        assert_eq!(
+            converted.get_color(AI_MATKEY_COLOR_DIFFUSE),
+            Some(pbr.base_color)
+        );
+    }
+}
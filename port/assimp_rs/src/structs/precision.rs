@@ -0,0 +1,301 @@
+//! Copies of the geometry of a scene in a fixed float precision.
+//!
+//! [`AiScene`] uses [`AiReal`], which depends on the `double_precision`
+//! feature. An application can import in double precision, e.g. for CAD data
+//! far from the origin, and convert to [`AiSceneF32`] for rendering in the
+//! same build, or the other way around with [`AiSceneF64`].
+//!
+//! The copies contain the node hierarchy, the meshes with their bones and
+//! the node animations. Materials, textures, cameras and lights stay in the
+//! [`AiScene`], meshes refer to its materials by index.
+
+use core::{fmt::Debug, ops::Range};
+
+use super::{
+    anim::AiAnimation,
+    color::Color4D,
+    face::AiFace,
+    mesh::AiMesh,
+    scene::{AiNode, AiScene},
+};
+use crate::{
+    AiReal,
+    utils::float_precision::{Mat4, Quat, Vec2, Vec3},
+};
+
+/// A float type with the matching vector and matrix types of glam
+pub trait Real: Copy + Debug + 'static {
+    type Vec2: Copy + Debug + PartialEq;
+    type Vec3: Copy + Debug + PartialEq;
+    type Quat: Copy + Debug + PartialEq;
+    type Mat4: Copy + Debug + PartialEq;
+
+    fn vec2(v: Vec2) -> Self::Vec2;
+    fn vec3(v: Vec3) -> Self::Vec3;
+    fn quat(q: Quat) -> Self::Quat;
+    fn mat4(m: Mat4) -> Self::Mat4;
+}
+
+// the casts are no-ops for the precision of the build
+#[allow(clippy::unnecessary_cast)]
+impl Real for f32 {
+    type Vec2 = glam::Vec2;
+    type Vec3 = glam::Vec3;
+    type Quat = glam::Quat;
+    type Mat4 = glam::Mat4;
+
+    fn vec2(v: Vec2) -> Self::Vec2 {
+        glam::Vec2::new(v.x as f32, v.y as f32)
+    }
+
+    fn vec3(v: Vec3) -> Self::Vec3 {
+        glam::Vec3::new(v.x as f32, v.y as f32, v.z as f32)
+    }
+
+    fn quat(q: Quat) -> Self::Quat {
+        glam::Quat::from_xyzw(q.x as f32, q.y as f32, q.z as f32, q.w as f32)
+    }
+
+    fn mat4(m: Mat4) -> Self::Mat4 {
+        glam::Mat4::from_cols_array(&m.to_cols_array().map(|x: AiReal| x as f32))
+    }
+}
+
+#[allow(clippy::unnecessary_cast)]
+impl Real for f64 {
+    type Vec2 = glam::DVec2;
+    type Vec3 = glam::DVec3;
+    type Quat = glam::DQuat;
+    type Mat4 = glam::DMat4;
+
+    fn vec2(v: Vec2) -> Self::Vec2 {
+        glam::DVec2::new(v.x as f64, v.y as f64)
+    }
+
+    fn vec3(v: Vec3) -> Self::Vec3 {
+        glam::DVec3::new(v.x as f64, v.y as f64, v.z as f64)
+    }
+
+    fn quat(q: Quat) -> Self::Quat {
+        glam::DQuat::from_xyzw(q.x as f64, q.y as f64, q.z as f64, q.w as f64)
+    }
+
+    fn mat4(m: Mat4) -> Self::Mat4 {
+        glam::DMat4::from_cols_array(&m.to_cols_array().map(|x: AiReal| x as f64))
+    }
+}
+
+/// The geometry of an [`AiScene`] with the float type `R`
+#[derive(Debug, Clone)]
+pub struct SceneData<R: Real> {
+    pub name: String,
+    /// Index of the root node in `nodes`
+    pub root: Option<u32>,
+    pub nodes: Vec<NodeData<R>>,
+    pub meshes: Vec<MeshData<R>>,
+    pub animations: Vec<AnimationData<R>>,
+}
+
+pub type AiSceneF32 = SceneData<f32>;
+pub type AiSceneF64 = SceneData<f64>;
+
+/// See [`AiNode`]
+#[derive(Debug, Clone)]
+pub struct NodeData<R: Real> {
+    pub name: String,
+    pub transformation: R::Mat4,
+    pub parent: Option<u32>,
+    pub children: Vec<u32>,
+    pub meshes: Range<u32>,
+}
+
+/// See [`AiMesh`], texture coordinates and colors are only stored for the
+/// used channels
+#[derive(Debug, Clone)]
+pub struct MeshData<R: Real> {
+    pub name: String,
+    pub vertices: Vec<R::Vec3>,
+    pub normals: Vec<R::Vec3>,
+    pub tangents: Vec<R::Vec3>,
+    pub bitangents: Vec<R::Vec3>,
+    pub texture_coords: Vec<Vec<R::Vec3>>,
+    pub colors: Vec<Vec<Color4D>>,
+    pub faces: Vec<AiFace>,
+    pub material_index: u32,
+    pub bones: Vec<BoneData<R>>,
+}
+
+/// See [`AiBone`](super::bone::AiBone), the weights are pairs of vertex
+/// index and weight
+#[derive(Debug, Clone)]
+pub struct BoneData<R: Real> {
+    pub name: String,
+    pub offset_matrix: R::Mat4,
+    pub weights: Vec<(u32, f32)>,
+}
+
+/// See [`AiAnimation`], only the node channels are copied
+#[derive(Debug, Clone)]
+pub struct AnimationData<R: Real> {
+    pub name: String,
+    pub duration: f64,
+    pub ticks_per_second: f64,
+    pub channels: Vec<ChannelData<R>>,
+}
+
+/// See [`AiNodeAnim`](super::anim::anim::AiNodeAnim), keys are pairs of
+/// time and value
+#[derive(Debug, Clone)]
+pub struct ChannelData<R: Real> {
+    pub node_name: String,
+    pub position_keys: Vec<(f64, R::Vec3)>,
+    pub rotation_keys: Vec<(f64, R::Quat)>,
+    pub scaling_keys: Vec<(f64, R::Vec3)>,
+}
+
+impl<R: Real> NodeData<R> {
+    fn new(node: &AiNode) -> Self {
+        Self {
+            name: node.name.clone(),
+            transformation: R::mat4(node.transformation),
+            parent: node.parent.map(|parent| parent.value() as u32),
+            children: node
+                .children
+                .iter()
+                .map(|child| child.value() as u32)
+                .collect(),
+            meshes: node.meshes.clone(),
+        }
+    }
+}
+
+impl<R: Real> MeshData<R> {
+    fn new(mesh: &AiMesh) -> Self {
+        let vec3s = |v: &[Vec3]| v.iter().map(|&v| R::vec3(v)).collect::<Vec<_>>();
+        Self {
+            name: mesh.name.clone(),
+            vertices: vec3s(&mesh.vertices),
+            normals: vec3s(&mesh.normals),
+            tangents: vec3s(&mesh.tangents),
+            bitangents: vec3s(&mesh.bitangents),
+            texture_coords: mesh.texture_coords[..mesh.num_of_uv_channels()]
+                .iter()
+                .map(|channel| vec3s(channel))
+                .collect(),
+            colors: mesh.colors[..mesh.num_of_color_channels()].to_vec(),
+            faces: mesh.faces.clone(),
+            material_index: mesh.material_index,
+            bones: mesh
+                .bones
+                .iter()
+                .map(|bone| BoneData {
+                    name: bone.name.clone(),
+                    offset_matrix: R::mat4(bone.offset_matrix),
+                    weights: bone
+                        .weights
+                        .iter()
+                        .map(|w| (w.vertex_id, w.weight))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<R: Real> AnimationData<R> {
+    fn new(animation: &AiAnimation) -> Self {
+        Self {
+            name: animation.name.clone(),
+            duration: animation.duration,
+            ticks_per_second: animation.ticks_per_second,
+            channels: animation
+                .channels
+                .iter()
+                .map(|channel| ChannelData {
+                    node_name: channel.node_name.to_string(),
+                    position_keys: channel
+                        .position_keys
+                        .iter()
+                        .map(|key| (key.time, R::vec3(key.value)))
+                        .collect(),
+                    rotation_keys: channel
+                        .rotation_keys
+                        .iter()
+                        .map(|key| (key.time, R::quat(key.value)))
+                        .collect(),
+                    scaling_keys: channel
+                        .scaling_keys
+                        .iter()
+                        .map(|key| (key.time, R::vec3(key.value)))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl AiScene {
+    /// Copies the geometry of the scene with the float type `R`, see
+    /// [`SceneData`]
+    pub fn to_precision<R: Real>(&self) -> SceneData<R> {
+        SceneData {
+            name: self.name.to_string(),
+            root: self.root.map(|root| root.value() as u32),
+            nodes: self.nodes.iter().map(NodeData::new).collect(),
+            meshes: self.meshes.iter().map(MeshData::new).collect(),
+            animations: self.animations.iter().map(AnimationData::new).collect(),
+        }
+    }
+
+    /// Copies the geometry in single precision, e.g. for rendering
+    pub fn to_f32(&self) -> AiSceneF32 {
+        self.to_precision()
+    }
+
+    /// Copies the geometry in double precision
+    pub fn to_f64(&self) -> AiSceneF64 {
+        self.to_precision()
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use crate::{
+        formats::x::importer::Importer, traits::importer::trait_define::Importer as _,
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_precision_conversion() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = *Importer::read_from_buf(&source).unwrap();
+        let single = scene.to_f32();
+        let double = scene.to_f64();
+        assert_eq!(single.nodes.len(), scene.nodes.len());
+        assert_eq!(double.root, Some(0));
+        for ((mesh, single), double) in scene.meshes.iter().zip(&single.meshes).zip(&double.meshes)
+        {
+            assert_eq!(single.faces, mesh.faces);
+            assert_eq!(single.texture_coords.len(), mesh.num_of_uv_channels());
+            assert_eq!(single.colors.len(), mesh.num_of_color_channels());
+            for ((v, s), d) in mesh
+                .vertices
+                .iter()
+                .zip(&single.vertices)
+                .zip(&double.vertices)
+            {
+                assert_eq!(d.to_array(), v.to_array().map(f64::from));
+                assert_eq!(d.as_vec3(), *s);
+            }
+        }
+        let node = &scene.nodes[0];
+        assert_eq!(
+            double.nodes[0].transformation.as_mat4(),
+            single.nodes[0].transformation
+        );
+        assert_eq!(single.nodes[0].meshes, node.meshes);
+    }
+}
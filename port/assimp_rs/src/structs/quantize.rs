@@ -0,0 +1,293 @@
+//! Quantized vertex attributes for compact exports, e.g. custom engine
+//! formats or glTF with `KHR_mesh_quantization`, see
+//! [`AiScene::quantize_meshes`].
+
+use super::{
+    aabb::AABB,
+    meta::{Metadata, MetadataExt},
+    scene::AiScene,
+};
+use crate::{
+    AiReal,
+    utils::float_precision::{Mat4, Quat, Vec2, Vec3, compose},
+};
+
+/// Scene metadata key of [`Dequantization::position_offset`]
+pub const AI_METADATA_QUANTIZATION_POSITION_OFFSET: &str = "Quantization.PositionOffset";
+/// Scene metadata key of [`Dequantization::position_scale`]
+pub const AI_METADATA_QUANTIZATION_POSITION_SCALE: &str = "Quantization.PositionScale";
+/// Scene metadata key of [`Dequantization::uv_offset`], z is 0
+pub const AI_METADATA_QUANTIZATION_UV_OFFSET: &str = "Quantization.UVOffset";
+/// Scene metadata key of [`Dequantization::uv_scale`], z is 0
+pub const AI_METADATA_QUANTIZATION_UV_SCALE: &str = "Quantization.UVScale";
+
+const UNORM16_MAX: AiReal = u16::MAX as AiReal;
+const SNORM16_MAX: AiReal = i16::MAX as AiReal;
+
+/// How the quantized attributes of all meshes of a scene are restored.
+/// Quantized values are normalized, 0 to 65535 stand for 0.0 to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dequantization {
+    /// The smallest position of all meshes
+    pub position_offset: Vec3,
+    /// The same for all axes, so normals stay valid if the dequantization
+    /// is a node transformation
+    pub position_scale: AiReal,
+    /// The smallest texture coordinate of the first channel
+    pub uv_offset: Vec2,
+    pub uv_scale: Vec2,
+}
+
+impl Default for Dequantization {
+    fn default() -> Self {
+        Self {
+            position_offset: Vec3::ZERO,
+            position_scale: 1.0,
+            uv_offset: Vec2::ZERO,
+            uv_scale: Vec2::ONE,
+        }
+    }
+}
+
+impl Dequantization {
+    pub fn position(&self, position: [u16; 3]) -> Vec3 {
+        let normalized = Vec3::from_array(position.map(|c| c as AiReal / UNORM16_MAX));
+        self.position_offset + normalized * self.position_scale
+    }
+
+    pub fn uv(&self, uv: [u16; 2]) -> Vec2 {
+        let normalized = Vec2::from_array(uv.map(|c| c as AiReal / UNORM16_MAX));
+        self.uv_offset + normalized * self.uv_scale
+    }
+
+    /// The dequantization of positions as a transformation in the layout of
+    /// [`AiNode::transformation`](super::scene::AiNode::transformation),
+    /// for normalized positions in `[0, 1]`
+    pub fn position_matrix(&self) -> Mat4 {
+        compose(
+            self.position_offset,
+            Quat::IDENTITY,
+            Vec3::splat(self.position_scale),
+        )
+    }
+
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        let uv_offset = metadata.get_vec3(AI_METADATA_QUANTIZATION_UV_OFFSET)?;
+        let uv_scale = metadata.get_vec3(AI_METADATA_QUANTIZATION_UV_SCALE)?;
+        Some(Self {
+            position_offset: metadata.get_vec3(AI_METADATA_QUANTIZATION_POSITION_OFFSET)?,
+            position_scale: metadata.get_float(AI_METADATA_QUANTIZATION_POSITION_SCALE)?,
+            uv_offset: uv_offset.truncate(),
+            uv_scale: uv_scale.truncate(),
+        })
+    }
+
+    pub fn write_metadata(&self, metadata: &mut Metadata) {
+        metadata.set_path(
+            AI_METADATA_QUANTIZATION_POSITION_OFFSET,
+            self.position_offset,
+        );
+        metadata.set_path(AI_METADATA_QUANTIZATION_POSITION_SCALE, self.position_scale);
+        metadata.set_path(
+            AI_METADATA_QUANTIZATION_UV_OFFSET,
+            self.uv_offset.extend(0.0),
+        );
+        metadata.set_path(AI_METADATA_QUANTIZATION_UV_SCALE, self.uv_scale.extend(0.0));
+    }
+}
+
+/// The quantized attributes of a mesh, empty if the mesh does not have
+/// them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuantizedMesh {
+    pub positions: Vec<[u16; 3]>,
+    /// Octahedral encoded normals, see [`oct_encode`]
+    pub normals: Vec<[i16; 2]>,
+    /// The first texture coordinate channel
+    pub texture_coords: Vec<[u16; 2]>,
+}
+
+/// The meshes of a scene quantized by [`AiScene::quantize_meshes`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuantizedScene {
+    pub dequantization: Dequantization,
+    /// Indexed like [`AiScene::meshes`]
+    pub meshes: Vec<QuantizedMesh>,
+}
+
+/// Maps `value` from `[offset, offset + scale]` to `[0, 65535]`
+fn unorm16(value: AiReal, offset: AiReal, scale: AiReal) -> u16 {
+    if scale > 0.0 {
+        ((value - offset) / scale * UNORM16_MAX).round() as u16
+    } else {
+        0
+    }
+}
+
+fn snorm16(value: AiReal) -> i16 {
+    (value.clamp(-1.0, 1.0) * SNORM16_MAX).round() as i16
+}
+
+fn sign(value: AiReal) -> AiReal {
+    if value >= 0.0 { 1.0 } else { -1.0 }
+}
+
+/// Encodes a unit vector as a point of the octahedron, unfolded into the
+/// unit square. Zero vectors become `[0, 0]`, which decodes to +z.
+pub fn oct_encode(normal: Vec3) -> [i16; 2] {
+    let length = normal.x.abs() + normal.y.abs() + normal.z.abs();
+    if length == 0.0 || !length.is_finite() {
+        return [0, 0];
+    }
+    let n = normal / length;
+    let (x, y) = if n.z >= 0.0 {
+        (n.x, n.y)
+    } else {
+        // the lower half is folded over the diagonals
+        ((1.0 - n.y.abs()) * sign(n.x), (1.0 - n.x.abs()) * sign(n.y))
+    };
+    [snorm16(x), snorm16(y)]
+}
+
+/// The unit vector of an [`oct_encode`]d normal
+pub fn oct_decode(encoded: [i16; 2]) -> Vec3 {
+    let [x, y] = encoded.map(|c| (c as AiReal / SNORM16_MAX).max(-1.0));
+    let z = 1.0 - x.abs() - y.abs();
+    let fold = (-z).max(0.0);
+    Vec3::new(x - fold * sign(x), y - fold * sign(y), z).normalize()
+}
+
+impl AiScene {
+    /// Quantizes the positions, normals and first texture coordinates of
+    /// all meshes to 16 bits.
+    ///
+    /// Positions and texture coordinates are normalized to the bounds of all
+    /// meshes, so the meshes share one [`Dequantization`]. The positions
+    /// are scaled uniformly by the largest extent, so a position is off by
+    /// at most `position_scale / 131070` on every axis. Normals are encoded
+    /// with [`oct_encode`].
+    pub fn quantize_meshes(&self) -> QuantizedScene {
+        let bounds = self.meshes.iter().fold(AABB::EMPTY, |bounds, mesh| {
+            bounds.union(&AABB::from_points(&mesh.vertices))
+        });
+        let (uv_min, uv_max) = self
+            .meshes
+            .iter()
+            .flat_map(|mesh| &mesh.texture_coords[0])
+            .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), uv| {
+                (min.min(uv.truncate()), max.max(uv.truncate()))
+            });
+
+        let mut dequantization = Dequantization::default();
+        if !bounds.is_empty() {
+            dequantization.position_offset = bounds.min;
+            dequantization.position_scale = bounds.extent().max_element();
+        }
+        if uv_min.cmple(uv_max).all() {
+            dequantization.uv_offset = uv_min;
+            dequantization.uv_scale = uv_max - uv_min;
+        }
+
+        let Dequantization {
+            position_offset,
+            position_scale,
+            uv_offset,
+            uv_scale,
+        } = dequantization;
+        let meshes = self
+            .meshes
+            .iter()
+            .map(|mesh| QuantizedMesh {
+                positions: mesh
+                    .vertices
+                    .iter()
+                    .map(|v| {
+                        (*v - position_offset)
+                            .to_array()
+                            .map(|c| unorm16(c, 0.0, position_scale))
+                    })
+                    .collect(),
+                normals: mesh.normals.iter().map(|n| oct_encode(*n)).collect(),
+                texture_coords: mesh.texture_coords[0]
+                    .iter()
+                    .map(|uv| {
+                        [
+                            unorm16(uv.x, uv_offset.x, uv_scale.x),
+                            unorm16(uv.y, uv_offset.y, uv_scale.y),
+                        ]
+                    })
+                    .collect(),
+            })
+            .collect();
+        QuantizedScene {
+            dequantization,
+            meshes,
+        }
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{Dequantization, oct_decode, oct_encode};
+    use crate::{
+        structs::{mesh::AiMesh, scene::AiScene},
+        utils::float_precision::{Vec2, Vec3},
+    };
+
+    #[test]
+    fn test_quantize_meshes() {
+        let mesh = |vertices: Vec<Vec3>, uvs: Vec<Vec3>| {
+            let mut mesh = AiMesh {
+                normals: vec![Vec3::new(0.6, 0.0, -0.8); vertices.len()],
+                vertices,
+                ..Default::default()
+            };
+            mesh.texture_coords[0] = uvs;
+            mesh
+        };
+        let mut scene = AiScene {
+            meshes: vec![
+                mesh(
+                    vec![Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.25, 0.5, 0.0)],
+                    vec![Vec3::new(0.0, 0.5, 0.0); 2],
+                ),
+                mesh(
+                    vec![Vec3::new(3.0, 1.0, 0.5)],
+                    vec![Vec3::new(2.0, 1.0, 0.0)],
+                ),
+            ],
+            ..Default::default()
+        };
+        let quantized = scene.quantize_meshes();
+        let dequantization = quantized.dequantization;
+        assert_eq!(dequantization.position_offset, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(dequantization.position_scale, 4.0);
+        assert_eq!(dequantization.uv_scale, Vec2::new(2.0, 0.5));
+        assert_eq!(quantized.meshes[0].positions[0], [0, 0, 0]);
+        assert_eq!(quantized.meshes[1].positions[0][0], u16::MAX);
+        for (mesh, q) in scene.meshes.iter().zip(&quantized.meshes) {
+            for (v, p) in mesh.vertices.iter().zip(&q.positions) {
+                assert!(dequantization.position(*p).abs_diff_eq(*v, 4.0 / 131070.0));
+            }
+            for (uv, t) in mesh.texture_coords[0].iter().zip(&q.texture_coords) {
+                assert!(dequantization.uv(*t).abs_diff_eq(uv.truncate(), 1e-4));
+            }
+            for (n, o) in mesh.normals.iter().zip(&q.normals) {
+                assert!(oct_decode(*o).abs_diff_eq(*n, 1e-4));
+            }
+        }
+        assert_eq!(oct_decode(oct_encode(Vec3::ZERO)), Vec3::Z);
+        assert!(oct_decode(oct_encode(-Vec3::Z)).abs_diff_eq(-Vec3::Z, 1e-6));
+
+        let corner = dequantization
+            .position_matrix()
+            .transpose()
+            .transform_point3(Vec3::ONE);
+        assert_eq!(corner, Vec3::new(3.0, 4.0, 4.0));
+        dequantization.write_metadata(&mut scene.metadata);
+        assert_eq!(
+            Dequantization::from_metadata(&scene.metadata),
+            Some(dequantization)
+        );
+    }
+}
@@ -1,14 +1,211 @@
-use crate::utils::float_precision::Vec3;
+use super::{
+    aabb::AABB,
+    nodes::Index,
+    scene::{AiNode, AiScene},
+};
+use crate::{
+    AiReal,
+    core::spatial::SceneBvh,
+    utils::float_precision::{Mat4, Vec3},
+};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AiRay {
     pub origin: Vec3,
     pub direction: Vec3,
 }
 
+/// Where a ray hits the scene, see [`AiScene::raycast`]
+#[derive(Debug, Clone)]
+pub struct RayHit {
+    /// The node instancing the mesh
+    pub node: Index<AiNode>,
+    pub mesh: u32,
+    pub face: u32,
+    /// The vertices of the triangle which was hit, polygons are split into
+    /// a fan around their first vertex
+    pub indices: [u32; 3],
+    /// Weights of the three vertices at the hit point, they sum up to 1
+    pub barycentrics: Vec3,
+    /// Ray parameter of the hit, the distance in units of the direction
+    pub t: AiReal,
+    /// The hit point in world space
+    pub point: Vec3,
+}
+
+/// Where a ray hits the faces of a single mesh, in the space of the mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriangleHit {
+    pub face: u32,
+    /// The vertices of the triangle which was hit, polygons are split into
+    /// a fan around their first vertex
+    pub indices: [u32; 3],
+    /// Weights of the three vertices at the hit point, they sum up to 1
+    pub barycentrics: Vec3,
+    pub t: AiReal,
+}
+
 impl AiRay {
     pub const fn new(origin: Vec3, direction: Vec3) -> Self {
         Self { origin, direction }
     }
+
+    pub fn at(&self, t: AiReal) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// The ray in the coordinate system of `transform`, the parameter of
+    /// every point stays the same.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        Self::new(
+            transform.transform_point3(self.origin),
+            transform.transform_vector3(self.direction),
+        )
+    }
+
+    /// Möller-Trumbore intersection with both sides of a triangle. Returns
+    /// the ray parameter and the barycentric weights of `b` and `c`.
+    pub fn intersect_triangle(
+        &self,
+        a: Vec3,
+        b: Vec3,
+        c: Vec3,
+    ) -> Option<(AiReal, AiReal, AiReal)> {
+        let (ab, ac) = (b - a, c - a);
+        let p = self.direction.cross(ac);
+        let det = ab.dot(p);
+        if det.abs() < AiReal::EPSILON * ab.length() * ac.length() * self.direction.length() {
+            // parallel to the triangle, or a degenerate triangle
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(ab);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = ac.dot(q) * inv_det;
+        (t >= 0.0).then_some((t, u, v))
+    }
+
+    /// Slab test, returns the ray parameter where the ray enters the box,
+    /// 0 if it starts inside.
+    pub fn intersect_aabb(&self, aabb: &AABB) -> Option<AiReal> {
+        let inv = self.direction.recip();
+        let t1 = (aabb.min - self.origin) * inv;
+        let t2 = (aabb.max - self.origin) * inv;
+        // NaN from 0 * inf (origin on a slab of a parallel ray) is ignored
+        // by min/max
+        let near = t1.min(t2).max_element().max(0.0);
+        // rays through an edge or a corner must not be missed because of
+        // rounding, so the far side is moved out a little
+        let far = t1.max(t2).min_element() * (1.0 + 6.0 * AiReal::EPSILON);
+        (near <= far).then_some(near)
+    }
+
+    /// Closest hit of the ray with the faces of a mesh in local space,
+    /// testing every triangle
+    fn cast_mesh(&self, scene: &AiScene, mesh_index: u32) -> Option<TriangleHit> {
+        let mesh = scene.meshes.get(mesh_index as usize)?;
+        // meshes built without bounding box have the default one
+        if mesh.aabb != AABB::default() {
+            self.intersect_aabb(&mesh.aabb)?;
+        }
+        let vertex = |i: u32| mesh.vertices.get(i as usize).copied();
+        let mut best: Option<TriangleHit> = None;
+        for (f, face) in mesh.faces.iter().enumerate() {
+            let Some((&first, rest)) = face.indices.split_first() else {
+                continue;
+            };
+            for pair in rest.windows(2) {
+                let indices = [first, pair[0], pair[1]];
+                let (Some(a), Some(b), Some(c)) =
+                    (vertex(indices[0]), vertex(indices[1]), vertex(indices[2]))
+                else {
+                    continue;
+                };
+                let Some((t, u, v)) = self.intersect_triangle(a, b, c) else {
+                    continue;
+                };
+                if best.is_none_or(|best| t < best.t) {
+                    best = Some(TriangleHit {
+                        face: f as u32,
+                        indices,
+                        barycentrics: Vec3::new(1.0 - u - v, u, v),
+                        t,
+                    });
+                }
+            }
+        }
+        best
+    }
+
+    /// Closest hit of the ray with the meshes of the scene, taking the node
+    /// transformations into account. Every triangle is tested, except for
+    /// meshes whose bounding box is missed.
+    pub fn cast(&self, scene: &AiScene) -> Option<RayHit> {
+        self.cast_with(scene, |ray, mesh| ray.cast_mesh(scene, mesh))
+    }
+
+    /// Like [`Self::cast`], but uses the hierarchies of `bvh` to find the
+    /// triangles, which pays off when casting many rays.
+    pub fn cast_cached(&self, scene: &AiScene, bvh: &SceneBvh) -> Option<RayHit> {
+        self.cast_with(scene, |ray, mesh| bvh.mesh(mesh)?.raycast(ray))
+    }
+
+    /// Walks the nodes and finds the closest hit of `cast_mesh`, which gets
+    /// the ray in the space of the node.
+    fn cast_with(
+        &self,
+        scene: &AiScene,
+        cast_mesh: impl Fn(&AiRay, u32) -> Option<TriangleHit>,
+    ) -> Option<RayHit> {
+        let root = scene.root?;
+        let mut visited = vec![false; scene.nodes.len()];
+        let mut stack = vec![(root, Mat4::IDENTITY)];
+        let mut best: Option<RayHit> = None;
+        while let Some((index, parent)) = stack.pop() {
+            let Some(node) = scene.get_node_by_index(index) else {
+                continue;
+            };
+            if std::mem::replace(&mut visited[index.value()], true) {
+                continue;
+            }
+            // row layout, so this is `parent * local`
+            let transform = node.transformation * parent;
+            stack.extend(node.children.iter().map(|&child| (child, transform)));
+            if node.meshes.is_empty() {
+                continue;
+            }
+            let inverse = transform.transpose().inverse();
+            if !inverse.is_finite() {
+                continue;
+            }
+            let local = self.transformed(&inverse);
+            for mesh in node.meshes.clone() {
+                let Some(hit) = cast_mesh(&local, mesh) else {
+                    continue;
+                };
+                if best.as_ref().is_none_or(|best| hit.t < best.t) {
+                    best = Some(RayHit {
+                        node: index,
+                        mesh,
+                        face: hit.face,
+                        indices: hit.indices,
+                        barycentrics: hit.barycentrics,
+                        t: hit.t,
+                        point: self.at(hit.t),
+                    });
+                }
+            }
+        }
+        best
+    }
 }
 
 impl Default for AiRay {
@@ -16,3 +213,80 @@ impl Default for AiRay {
         Self::new(Vec3::ZERO, Vec3::ZERO)
     }
 }
+
+#[allow(unused)]
+mod test {
+    use super::AiRay;
+    use crate::{
+        compose,
+        core::spatial::SceneBvh,
+        structs::{
+            face::AiFace, material::AiMaterial, mesh::AiMesh, scene::AiScene,
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Quat, Vec3},
+    };
+
+    /// A quad at z = -5 in the space of the root, split into a triangle fan
+    fn quad_scene(root_rotation: Quat) -> AiScene {
+        // the node transformations are stored row by row, like imported ones
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", compose(Vec3::ZERO, root_rotation, Vec3::ONE), None);
+        let translation = compose(-5.0 * Vec3::Z, Quat::IDENTITY, Vec3::ONE);
+        let node = builder.add_node("quad", translation, Some(root));
+        let mesh = builder.add_mesh(AiMesh {
+            vertices: vec![
+                Vec3::new(-1.0, -1.0, 0.0),
+                Vec3::new(1.0, -1.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(-1.0, 1.0, 0.0),
+            ],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2, 3]),
+            }],
+            ..Default::default()
+        });
+        builder.attach_mesh(node, mesh);
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_raycast() {
+        let scene = quad_scene(Quat::IDENTITY);
+        let hit = scene
+            .raycast(&AiRay::new(Vec3::new(-0.5, 0.5, 0.0), -Vec3::Z))
+            .unwrap();
+        assert_eq!((hit.mesh, hit.face), (0, 0));
+        assert_eq!(hit.indices, [0, 2, 3]);
+        assert_eq!(hit.t, 5.0);
+        assert_eq!(hit.point, Vec3::new(-0.5, 0.5, -5.0));
+        assert!(
+            hit.barycentrics
+                .abs_diff_eq(Vec3::new(0.25, 0.25, 0.5), 1e-6)
+        );
+        assert!(scene.raycast(&AiRay::new(Vec3::ZERO, Vec3::Z)).is_none());
+        assert!(
+            scene
+                .raycast(&AiRay::new(Vec3::new(2.0, 0.0, 0.0), -Vec3::Z))
+                .is_none()
+        );
+
+        // the rotated root moves the quad to x = -5, facing the x axis
+        let scene = quad_scene(Quat::from_rotation_y(core::f32::consts::FRAC_PI_2 as _));
+        let ray = AiRay::new(Vec3::new(0.0, 0.5, 0.5), -Vec3::X);
+        let bvh = SceneBvh::new(&scene);
+        for hit in [scene.raycast(&ray), scene.raycast_cached(&ray, &bvh)] {
+            let hit = hit.unwrap();
+            assert_eq!(hit.indices, [0, 2, 3]);
+            assert!((hit.t - 5.0).abs() < 1e-5);
+            assert!(hit.point.abs_diff_eq(Vec3::new(-5.0, 0.5, 0.5), 1e-5));
+            assert!(
+                hit.barycentrics
+                    .abs_diff_eq(Vec3::new(0.25, 0.25, 0.5), 1e-5)
+            );
+        }
+        let ray = AiRay::new(Vec3::new(-0.5, 0.5, 0.0), -Vec3::Z);
+        assert!(scene.raycast(&ray).is_none());
+    }
+}
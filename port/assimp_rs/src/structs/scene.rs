@@ -1,9 +1,20 @@
 use std::{fmt::Debug, ops::Range};
 
+use bitflags::bitflags;
+
 use crate::{
+    core::spatial::SceneBvh,
     structs::{
-        anim::AiAnimation, camera::AiCamera, light::AiLight, material::AiMaterial, mesh::AiMesh,
-        meta::Metadata, nodes::Index, texture::AiTexture,
+        anim::AiAnimation,
+        camera::AiCamera,
+        light::AiLight,
+        material::AiMaterial,
+        mesh::{AiMesh, Skeleton},
+        meta::Metadata,
+        nodes::Index,
+        ray::{AiRay, RayHit},
+        stats::AiSceneStats,
+        texture::AiTexture,
     },
     utils::float_precision::Mat4,
 };
@@ -11,13 +22,40 @@ use crate::{
 pub struct AiNode {
     pub name: String,
     pub transformation: Mat4,
-    pub parent: Index<AiNode>,
+    /// `None` for the root node
+    pub parent: Option<Index<AiNode>>,
     pub children: Vec<Index<AiNode>>,
     pub meshes: Range<u32>,
     pub metadata: Box<Metadata>,
 }
+bitflags! {
+    /// Flags describing the state of an [`AiScene`]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct AiSceneFlags: u32 {
+        /// The scene data structure is incomplete, e.g. because the file
+        /// was truncated and only the data read so far was kept.
+        const INCOMPLETE = 0x1;
+
+        /// The scene was validated.
+        const VALIDATED = 0x2;
+
+        /// The scene was validated, but there were warnings.
+        const VALIDATION_WARNING = 0x4;
+
+        /// Vertices are shared between faces.
+        const NON_VERBOSE_FORMAT = 0x8;
+
+        /// The scene is a terrain.
+        const TERRAIN = 0x10;
+
+        /// Meshes may be referenced by several nodes.
+        const ALLOW_SHARED = 0x20;
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct AiScene {
+    pub flags: AiSceneFlags,
     pub root: Option<Index<AiNode>>,
     pub nodes: Vec<AiNode>,
     pub meshes: Vec<AiMesh>,
@@ -26,6 +64,7 @@ pub struct AiScene {
     pub textures: Vec<AiTexture>,
     pub lights: Vec<AiLight>,
     pub cameras: Vec<AiCamera>,
+    pub skeletons: Vec<Skeleton>,
     pub metadata: Box<Metadata>,
     pub name: Box<str>,
 }
@@ -33,6 +72,7 @@ pub struct AiScene {
 impl AiScene {
     pub fn new() -> Self {
         Self {
+            flags: AiSceneFlags::empty(),
             root: None,
             nodes: Vec::new(),
             meshes: Vec::new(),
@@ -41,11 +81,30 @@ impl AiScene {
             textures: Vec::new(),
             lights: Vec::new(),
             cameras: Vec::new(),
+            skeletons: Vec::new(),
             metadata: Box::default(),
             name: Box::default(),
         }
     }
 
+    /// Counts the contents of the scene, the result can be printed for a
+    /// human readable summary.
+    pub fn stats(&self) -> AiSceneStats {
+        AiSceneStats::new(self)
+    }
+
+    /// The closest intersection of the ray with a mesh of the scene, see
+    /// [`AiRay::cast`].
+    pub fn raycast(&self, ray: &AiRay) -> Option<RayHit> {
+        ray.cast(self)
+    }
+
+    /// Like [`Self::raycast`], but uses the hierarchies of `bvh`, which has
+    /// to be built from this scene.
+    pub fn raycast_cached(&self, ray: &AiRay, bvh: &SceneBvh) -> Option<RayHit> {
+        ray.cast_cached(self, bvh)
+    }
+
     pub fn get_node_by_index(&self, index: Index<AiNode>) -> Option<&AiNode> {
         self.nodes.get(index.value())
     }
@@ -67,27 +126,40 @@ impl AiScene {
             None
         }
     }
+}
 
-    pub fn add_children(
-        &mut self,
-        parent: Index<AiNode>,
-        children: Vec<AiNode>,
-    ) -> Option<Vec<AiNode>> {
-        let index = parent.value();
-        if index == 0 || index >= self.nodes.len() {
-            return Some(children);
-        };
-        let len = children.len();
-        if len > 0 {
-            let current_len = self.nodes.len();
-            self.nodes.extend(children);
-            let parent_node = self.get_node_by_index_mut(parent)?;
-            parent_node.children.extend(
-                (current_len..current_len + len)
-                    .map(|i| Index::new(i as u32))
-                    .into_iter(),
-            );
-        }
-        None
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use crate::{
+        formats::x::importer::Importer, traits::importer::trait_define::Importer as _,
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_scene_stats() {
+        let source = fs::read(get_model_path("X", "kwxport_test_cubewithvcolors.x")).unwrap();
+        let scene = *Importer::read_from_buf(&source).unwrap();
+        let stats = scene.stats();
+        assert_eq!(stats.nodes, scene.nodes.len());
+        assert_eq!(stats.meshes, scene.meshes.len());
+        let vertices = scene.meshes.iter().map(|m| m.vertices.len()).sum::<usize>();
+        let faces = scene.meshes.iter().map(|m| m.faces.len()).sum::<usize>();
+        assert_eq!(stats.vertices, vertices);
+        assert_eq!(stats.faces, faces);
+        assert_eq!(stats.triangles.faces, 12);
+        assert_eq!(stats.triangles.indices, 36);
+        assert_eq!(stats.polygons.faces, 0);
+        assert_eq!(stats.materials, scene.materials.len());
+        let properties = scene.materials.iter().map(|m| m.properties.len()).sum();
+        assert_eq!(
+            stats.material_properties.values().sum::<usize>(),
+            properties
+        );
+        let display = stats.to_string();
+        assert!(display.contains("Triangles:        12 (36 indices)"));
+        println!("{display}");
     }
 }
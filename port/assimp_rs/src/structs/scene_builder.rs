@@ -0,0 +1,476 @@
+use thiserror::Error;
+
+use crate::{
+    structs::{
+        anim::AiAnimation,
+        camera::AiCamera,
+        light::AiLight,
+        material::AiMaterial,
+        mesh::{AiMesh, Skeleton, SkeletonBone},
+        meta::Metadata,
+        nodes::Index,
+        scene::{AiNode, AiScene, AiSceneFlags},
+        texture::AiTexture,
+    },
+    utils::float_precision::Mat4,
+};
+
+/// Errors detected when building a scene
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SceneBuildError {
+    #[error("No root node found")]
+    NoRootNode,
+
+    #[error("Node {0} has no parent, only the first node can be the root")]
+    MultipleRootNodes(usize),
+
+    #[error("Parent {parent} of node {node} does not exist")]
+    InvalidParent { node: usize, parent: usize },
+
+    #[error("Mesh {mesh} attached to node {node} does not exist")]
+    InvalidMesh { node: usize, mesh: usize },
+
+    #[error("Material {material} of mesh {mesh} does not exist")]
+    InvalidMaterial { mesh: usize, material: usize },
+
+    #[error("Face {face} of mesh {mesh} references vertex {vertex}, but there are only {len}")]
+    InvalidVertexIndex {
+        mesh: usize,
+        face: usize,
+        vertex: u32,
+        len: usize,
+    },
+
+    #[error("Bone {bone} of skeleton {skeleton} influences mesh {mesh}, which does not exist")]
+    InvalidSkeletonMesh {
+        skeleton: usize,
+        bone: usize,
+        mesh: usize,
+    },
+
+    #[error("Bone {bone} of skeleton {skeleton} references node {node}, which does not exist")]
+    InvalidSkeletonNode {
+        skeleton: usize,
+        bone: usize,
+        node: usize,
+    },
+
+    #[error("Mesh {mesh} has {len} {component}, but {vertices} vertices")]
+    ComponentCountMismatch {
+        mesh: usize,
+        component: &'static str,
+        len: usize,
+        vertices: usize,
+    },
+}
+
+struct NodeEntry {
+    node: AiNode,
+    parent: Option<Index<AiNode>>,
+    meshes: Vec<u32>,
+}
+
+/// Constructs an [`AiScene`] programmatically.
+///
+/// The first node added becomes the root node, every other node needs a
+/// parent which was added before. Meshes are added independently of nodes and
+/// attached afterwards, [`Self::build`] takes care of laying them out so every
+/// node references a contiguous span of [`AiScene::meshes`]. A mesh attached
+/// to several nodes is duplicated, meshes not attached to any node are stored
+/// after all others. Skeleton bones follow their meshes, a bone of a
+/// duplicated mesh is duplicated as well.
+///
+/// ```
+/// # use assimp_rs::structs::{material::AiMaterial, mesh::AiMesh, scene_builder::SceneBuilder};
+/// # use glam::Mat4;
+/// let mut builder = SceneBuilder::new();
+/// let material = builder.add_material(AiMaterial::default());
+/// let root = builder.add_node("root", Mat4::IDENTITY, None);
+/// let child = builder.add_node("child", Mat4::IDENTITY, Some(root));
+/// let mesh = builder.add_mesh(AiMesh {
+///     material_index: material,
+///     ..Default::default()
+/// });
+/// builder.attach_mesh(child, mesh);
+/// let scene = builder.build().unwrap();
+/// assert_eq!(scene.nodes[1].meshes, 0..1);
+/// ```
+#[derive(Default)]
+pub struct SceneBuilder {
+    name: Box<str>,
+    nodes: Vec<NodeEntry>,
+    meshes: Vec<AiMesh>,
+    materials: Vec<AiMaterial>,
+    animations: Vec<AiAnimation>,
+    textures: Vec<AiTexture>,
+    lights: Vec<AiLight>,
+    cameras: Vec<AiCamera>,
+    skeletons: Vec<Skeleton>,
+    metadata: Box<Metadata>,
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(&mut self, name: impl Into<Box<str>>) -> &mut Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Adds a node, `None` as parent marks the root node.
+    pub fn add_node(
+        &mut self,
+        name: impl Into<String>,
+        transformation: Mat4,
+        parent: Option<Index<AiNode>>,
+    ) -> Index<AiNode> {
+        let index = Index::new(self.nodes.len() as u32);
+        self.nodes.push(NodeEntry {
+            node: AiNode {
+                name: name.into(),
+                transformation,
+                ..Default::default()
+            },
+            parent,
+            meshes: Vec::new(),
+        });
+        index
+    }
+
+    /// Gives access to a node added before, e.g. to fill its metadata.
+    pub fn node_mut(&mut self, node: Index<AiNode>) -> Option<&mut AiNode> {
+        self.nodes
+            .get_mut(node.value())
+            .map(|entry| &mut entry.node)
+    }
+
+    /// Adds a mesh and returns its index in the order of adding. The final
+    /// index in the built scene may differ.
+    pub fn add_mesh(&mut self, mesh: AiMesh) -> u32 {
+        self.meshes.push(mesh);
+        (self.meshes.len() - 1) as u32
+    }
+
+    /// Makes the node reference the mesh. Invalid indices are reported by
+    /// [`Self::build`].
+    pub fn attach_mesh(&mut self, node: Index<AiNode>, mesh: u32) -> &mut Self {
+        if let Some(entry) = self.nodes.get_mut(node.value()) {
+            entry.meshes.push(mesh);
+        }
+        self
+    }
+
+    /// Adds a material, the returned index is used as `AiMesh::material_index`.
+    pub fn add_material(&mut self, material: AiMaterial) -> u32 {
+        self.materials.push(material);
+        (self.materials.len() - 1) as u32
+    }
+
+    pub fn add_animation(&mut self, animation: AiAnimation) -> &mut Self {
+        self.animations.push(animation);
+        self
+    }
+
+    pub fn add_texture(&mut self, texture: AiTexture) -> u32 {
+        self.textures.push(texture);
+        (self.textures.len() - 1) as u32
+    }
+
+    pub fn add_light(&mut self, light: AiLight) -> &mut Self {
+        self.lights.push(light);
+        self
+    }
+
+    pub fn add_camera(&mut self, camera: AiCamera) -> &mut Self {
+        self.cameras.push(camera);
+        self
+    }
+
+    /// Adds a skeleton, its node and mesh indices refer to the nodes and
+    /// meshes in the order of adding.
+    pub fn add_skeleton(&mut self, skeleton: Skeleton) -> &mut Self {
+        self.skeletons.push(skeleton);
+        self
+    }
+
+    /// Validates all indices and assembles the scene.
+    pub fn build(self) -> Result<AiScene, SceneBuildError> {
+        if self.nodes.is_empty() {
+            return Err(SceneBuildError::NoRootNode);
+        }
+        for (i, entry) in self.nodes.iter().enumerate() {
+            match entry.parent {
+                None if i != 0 => return Err(SceneBuildError::MultipleRootNodes(i)),
+                Some(_) if i == 0 => return Err(SceneBuildError::NoRootNode),
+                // Parents have to be added before their children, which also
+                // rules out cycles
+                Some(parent) if parent.value() >= i => {
+                    return Err(SceneBuildError::InvalidParent {
+                        node: i,
+                        parent: parent.value(),
+                    });
+                }
+                _ => {}
+            }
+            if let Some(&mesh) = entry
+                .meshes
+                .iter()
+                .find(|&&mesh| mesh as usize >= self.meshes.len())
+            {
+                return Err(SceneBuildError::InvalidMesh {
+                    node: i,
+                    mesh: mesh as usize,
+                });
+            }
+        }
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            Self::validate_mesh(i, mesh, self.materials.len())?;
+        }
+        for (i, skeleton) in self.skeletons.iter().enumerate() {
+            Self::validate_skeleton(i, skeleton, self.nodes.len(), self.meshes.len())?;
+        }
+
+        // Indices of every copy of each mesh in the scene, the first one is
+        // the mesh itself
+        let mut placed: Vec<Vec<u32>> = vec![Vec::new(); self.meshes.len()];
+        let mut source = self.meshes.into_iter().map(Some).collect::<Vec<_>>();
+        let mut meshes: Vec<AiMesh> = Vec::with_capacity(source.len());
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        let mut children = vec![Vec::new(); self.nodes.len()];
+        for (i, entry) in self.nodes.iter().enumerate() {
+            if let Some(parent) = entry.parent {
+                children[parent.value()].push(Index::new(i as u32));
+            }
+        }
+        for (entry, children) in self.nodes.into_iter().zip(children) {
+            let start = meshes.len() as u32;
+            for mesh in entry.meshes {
+                let mesh = mesh as usize;
+                let at = meshes.len() as u32;
+                match placed[mesh].first() {
+                    // Meshes are referenced by span, so shared ones are copied
+                    Some(&first) => meshes.push(meshes[first as usize].clone()),
+                    None => meshes.extend(source[mesh].take()),
+                }
+                placed[mesh].push(at);
+            }
+            nodes.push(AiNode {
+                parent: entry.parent,
+                children,
+                meshes: start..meshes.len() as u32,
+                ..entry.node
+            });
+        }
+        for (mesh, unattached) in source.into_iter().enumerate() {
+            if let Some(unattached) = unattached {
+                placed[mesh].push(meshes.len() as u32);
+                meshes.push(unattached);
+            }
+        }
+        // Nodes keep their order, only the meshes of the bones move
+        let skeletons = self
+            .skeletons
+            .into_iter()
+            .map(|skeleton| Self::place_skeleton(skeleton, &placed))
+            .collect();
+
+        Ok(AiScene {
+            flags: AiSceneFlags::empty(),
+            root: Some(Index::new(0)),
+            nodes,
+            meshes,
+            materials: self.materials,
+            animations: self.animations,
+            textures: self.textures,
+            lights: self.lights,
+            cameras: self.cameras,
+            skeletons,
+            metadata: self.metadata,
+            name: self.name,
+        })
+    }
+
+    /// Moves the bones to the placed meshes. Bones of copied meshes are
+    /// copied after all others, so the parent indices stay valid.
+    fn place_skeleton(mut skeleton: Skeleton, placed: &[Vec<u32>]) -> Skeleton {
+        let mut bones = skeleton.bones.into_vec();
+        let copies = bones
+            .iter()
+            .flat_map(|bone| {
+                placed[bone.mesh_id.value()][1..]
+                    .iter()
+                    .map(|&at| SkeletonBone {
+                        mesh_id: Index::new(at),
+                        ..bone.clone()
+                    })
+            })
+            .collect::<Vec<_>>();
+        for bone in &mut bones {
+            bone.mesh_id = Index::new(placed[bone.mesh_id.value()][0]);
+        }
+        bones.extend(copies);
+        skeleton.bones = bones.into_boxed_slice();
+        skeleton
+    }
+
+    fn validate_skeleton(
+        i: usize,
+        skeleton: &Skeleton,
+        nodes: usize,
+        meshes: usize,
+    ) -> Result<(), SceneBuildError> {
+        for (bone, b) in skeleton.bones.iter().enumerate() {
+            let mesh = b.mesh_id.value();
+            if mesh >= meshes {
+                return Err(SceneBuildError::InvalidSkeletonMesh {
+                    skeleton: i,
+                    bone,
+                    mesh,
+                });
+            }
+            if let Some(node) = [b.node, b.armature]
+                .into_iter()
+                .flatten()
+                .map(|node| node.value())
+                .find(|&node| node >= nodes)
+            {
+                return Err(SceneBuildError::InvalidSkeletonNode {
+                    skeleton: i,
+                    bone,
+                    node,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_mesh(i: usize, mesh: &AiMesh, materials: usize) -> Result<(), SceneBuildError> {
+        let material = mesh.material_index as usize;
+        if material >= materials {
+            return Err(SceneBuildError::InvalidMaterial { mesh: i, material });
+        }
+        let vertices = mesh.vertices.len();
+        for (face, f) in mesh.faces.iter().enumerate() {
+            if let Some(&vertex) = f.indices.iter().find(|&&v| v as usize >= vertices) {
+                return Err(SceneBuildError::InvalidVertexIndex {
+                    mesh: i,
+                    face,
+                    vertex,
+                    len: vertices,
+                });
+            }
+        }
+        let components = [
+            ("normals", mesh.normals.len()),
+            ("tangents", mesh.tangents.len()),
+            ("bitangents", mesh.bitangents.len()),
+        ]
+        .into_iter()
+        .chain(mesh.colors.iter().map(|c| ("colors", c.len())))
+        .chain(
+            mesh.texture_coords
+                .iter()
+                .map(|c| ("texture coordinates", c.len())),
+        );
+        for (component, len) in components {
+            if len != 0 && len != vertices {
+                return Err(SceneBuildError::ComponentCountMismatch {
+                    mesh: i,
+                    component,
+                    len,
+                    vertices,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{SceneBuildError, SceneBuilder};
+    use crate::{
+        structs::{
+            material::AiMaterial,
+            mesh::{AiMesh, Skeleton, SkeletonBone},
+            nodes::Index,
+        },
+        utils::float_precision::Mat4,
+    };
+
+    fn bone(parent: i32, node: u32, mesh: u32) -> SkeletonBone {
+        SkeletonBone {
+            parent,
+            armature: None,
+            node: Some(Index::new(node)),
+            mesh_id: Index::new(mesh),
+            weights: Box::default(),
+            offset_matrix: Mat4::IDENTITY,
+            local_matrix: Mat4::IDENTITY,
+        }
+    }
+
+    #[test]
+    fn test_skeleton_on_shared_mesh() {
+        let mut builder = SceneBuilder::new();
+        builder.add_material(AiMaterial::default());
+        let root = builder.add_node("root", Mat4::IDENTITY, None);
+        let a = builder.add_node("a", Mat4::IDENTITY, Some(root));
+        let b = builder.add_node("b", Mat4::IDENTITY, Some(root));
+        let mut add_mesh = |name: &str| {
+            builder.add_mesh(AiMesh {
+                name: name.to_owned(),
+                ..Default::default()
+            })
+        };
+        let unattached = add_mesh("unattached");
+        let shared = add_mesh("shared");
+        let single = add_mesh("single");
+        builder
+            .attach_mesh(root, single)
+            .attach_mesh(a, shared)
+            .attach_mesh(b, shared);
+        builder.add_skeleton(Skeleton {
+            name: "skeleton".into(),
+            bones: Box::new([
+                bone(-1, root.value() as u32, single),
+                bone(0, a.value() as u32, shared),
+                bone(0, root.value() as u32, unattached),
+            ]),
+        });
+        let scene = builder.build().unwrap();
+
+        let names = scene.meshes.iter().map(|m| &*m.name).collect::<Vec<_>>();
+        assert_eq!(names, ["single", "shared", "shared", "unattached"]);
+        let bones = &scene.skeletons[0].bones;
+        let mesh_ids = bones.iter().map(|b| b.mesh_id.value()).collect::<Vec<_>>();
+        // the bone of the shared mesh is copied for the second node
+        assert_eq!(mesh_ids, [0, 1, 3, 2]);
+        assert_eq!(bones[3].parent, 0);
+        assert_eq!(bones[3].node, Some(a));
+    }
+
+    #[test]
+    fn test_invalid_skeleton() {
+        let mut builder = SceneBuilder::new();
+        builder.add_node("root", Mat4::IDENTITY, None);
+        builder.add_skeleton(Skeleton {
+            name: "skeleton".into(),
+            bones: Box::new([bone(-1, 0, 1)]),
+        });
+        assert_eq!(
+            builder.build().err(),
+            Some(SceneBuildError::InvalidSkeletonMesh {
+                skeleton: 0,
+                bone: 0,
+                mesh: 1,
+            })
+        );
+    }
+}
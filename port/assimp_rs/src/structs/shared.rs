@@ -0,0 +1,153 @@
+//! Sharing imported scenes between threads without copying them.
+
+use std::{ops::Deref, sync::Arc};
+
+use crate::{
+    core::{profiler::Profiler, spatial::SceneBvh},
+    structs::{
+        anim::AiAnimation,
+        camera::AiCamera,
+        importer::{ImportProperties, report::ImportReport},
+        light::AiLight,
+        material::AiMaterial,
+        mesh::{AiMesh, Skeleton},
+        meta::Metadata,
+        scene::{AiNode, AiScene},
+        texture::AiTexture,
+    },
+};
+
+// Scenes are handed to worker threads, e.g. by asset servers. None of the
+// types may hold `Rc`, `Cell` or raw pointers.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<AiScene>();
+    assert_send_sync::<AiNode>();
+    assert_send_sync::<AiMesh>();
+    assert_send_sync::<Skeleton>();
+    assert_send_sync::<AiMaterial>();
+    assert_send_sync::<AiAnimation>();
+    assert_send_sync::<AiTexture>();
+    assert_send_sync::<AiLight>();
+    assert_send_sync::<AiCamera>();
+    assert_send_sync::<Metadata>();
+    assert_send_sync::<SceneBvh>();
+    assert_send_sync::<ImportProperties>();
+    assert_send_sync::<ImportReport>();
+    assert_send_sync::<Profiler>();
+    assert_send_sync::<SharedScene>();
+};
+
+/// A read-only scene which can be cloned cheaply and shared between
+/// threads. It dereferences to the [`AiScene`], changes go through
+/// [`Self::make_mut`], which copies the scene if it is still shared.
+#[derive(Debug, Clone, Default)]
+pub struct SharedScene(Arc<AiScene>);
+
+impl SharedScene {
+    pub fn new(scene: AiScene) -> Self {
+        Self(Arc::new(scene))
+    }
+
+    pub fn as_arc(&self) -> &Arc<AiScene> {
+        &self.0
+    }
+
+    pub fn into_arc(self) -> Arc<AiScene> {
+        self.0
+    }
+
+    /// Whether both refer to the same scene, not just equal ones
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// The number of handles to the scene, including this one
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    /// The scene for changes, copied first unless this is the only handle
+    pub fn make_mut(&mut self) -> &mut AiScene {
+        Arc::make_mut(&mut self.0)
+    }
+
+    /// The scene without copying it if this is the only handle, else the
+    /// handle itself
+    pub fn try_unwrap(self) -> Result<AiScene, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl Deref for SharedScene {
+    type Target = AiScene;
+
+    fn deref(&self) -> &AiScene {
+        &self.0
+    }
+}
+
+impl AsRef<AiScene> for SharedScene {
+    fn as_ref(&self) -> &AiScene {
+        &self.0
+    }
+}
+
+impl From<AiScene> for SharedScene {
+    fn from(scene: AiScene) -> Self {
+        Self::new(scene)
+    }
+}
+
+impl From<Box<AiScene>> for SharedScene {
+    /// Moves the scene out of the box returned by the `read_from_*`
+    /// functions
+    fn from(scene: Box<AiScene>) -> Self {
+        Self::new(*scene)
+    }
+}
+
+impl From<Arc<AiScene>> for SharedScene {
+    fn from(scene: Arc<AiScene>) -> Self {
+        Self(scene)
+    }
+}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use std::fs;
+
+    use super::SharedScene;
+    use crate::{
+        formats::x::importer::Importer, traits::importer::trait_define::Importer as _,
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_shared_scene() {
+        let source = fs::read(get_model_path("X", "test_cube_text.x")).unwrap();
+        let scene = SharedScene::from(Importer::read_from_buf(&source).unwrap());
+        let vertices = std::thread::scope(|s| {
+            let workers = (0..4)
+                .map(|_| {
+                    let scene = scene.clone();
+                    s.spawn(move || scene.meshes.iter().map(|m| m.vertices.len()).sum::<usize>())
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|w| w.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(vertices, [scene.meshes[0].vertices.len(); 4]);
+
+        // changes copy the scene while it is shared
+        let mut copy = scene.clone();
+        assert!(copy.ptr_eq(&scene) && copy.handle_count() == 2);
+        copy.make_mut().name = "copy".into();
+        assert!(!copy.ptr_eq(&scene) && scene.name.is_empty());
+        assert_eq!(copy.try_unwrap().unwrap().name.as_ref(), "copy");
+        assert!(scene.try_unwrap().is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+//! Bone influences per vertex for skinning on GPUs, see
+//! [`AiMesh::skinning_streams`].
+
+use thiserror::Error;
+
+use super::mesh::AiMesh;
+
+/// Number of bones which can influence a vertex in the streams, the limit
+/// of the `LimitBoneWeights` step by default
+pub const MAX_BONES_PER_VERTEX: usize = 4;
+
+/// How far the weights of a vertex may sum up from 1 before they are
+/// normalized
+pub const WEIGHT_SUM_EPSILON: f32 = 1e-3;
+
+/// Bone indices and weights of all vertices, a `Uint8x4` and a `Float32x4`
+/// vertex attribute. The influences of a vertex are sorted by decreasing
+/// weight, unused ones have bone 0 and weight 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkinningStreams {
+    /// Indices into [`AiMesh::bones`]
+    pub indices: Vec<[u8; MAX_BONES_PER_VERTEX]>,
+    /// The weights, which sum up to 1 for every vertex
+    pub weights: Vec<[f32; MAX_BONES_PER_VERTEX]>,
+}
+
+/// Errors of [`AiMesh::skinning_streams`]
+#[derive(Debug, Error, PartialEq)]
+pub enum SkinningError {
+    #[error("The mesh has {0} bones, but only 256 can be indexed by a byte")]
+    TooManyBones(usize),
+    #[error("Bone {bone} references vertex {vertex}, which does not exist")]
+    InvalidVertex { bone: String, vertex: u32 },
+    #[error(
+        "Vertex {vertex} is influenced by {count} bones, at most {MAX_BONES_PER_VERTEX} are supported"
+    )]
+    TooManyInfluences { vertex: u32, count: usize },
+    #[error("The weights of vertex {vertex} sum up to {sum} instead of 1")]
+    WeightSum { vertex: u32, sum: f32 },
+}
+
+impl AiMesh {
+    /// Converts the weights of [`bones`](Self::bones), which list the
+    /// vertices they influence, into the influences of every vertex.
+    ///
+    /// The mesh is expected to be processed by `LimitBoneWeights`: every
+    /// vertex has at most [`MAX_BONES_PER_VERTEX`] influences whose weights
+    /// sum up to 1 within [`WEIGHT_SUM_EPSILON`]. Vertices without any
+    /// influence fail this check. Influences with a weight of 0 are skipped.
+    pub fn skinning_streams(&self) -> Result<SkinningStreams, SkinningError> {
+        if self.bones.len() > u8::MAX as usize + 1 {
+            return Err(SkinningError::TooManyBones(self.bones.len()));
+        }
+        let num_vertices = self.vertices.len();
+        let mut influences = vec![Vec::new(); num_vertices];
+        for (index, bone) in self.bones.iter().enumerate() {
+            for weight in &bone.weights {
+                let Some(vertex) = influences.get_mut(weight.vertex_id as usize) else {
+                    return Err(SkinningError::InvalidVertex {
+                        bone: bone.name.clone(),
+                        vertex: weight.vertex_id,
+                    });
+                };
+                if weight.weight != 0.0 {
+                    vertex.push((index as u8, weight.weight));
+                }
+            }
+        }
+
+        let mut streams = SkinningStreams {
+            indices: Vec::with_capacity(num_vertices),
+            weights: Vec::with_capacity(num_vertices),
+        };
+        for (vertex, mut influences) in influences.into_iter().enumerate() {
+            let vertex = vertex as u32;
+            if influences.len() > MAX_BONES_PER_VERTEX {
+                return Err(SkinningError::TooManyInfluences {
+                    vertex,
+                    count: influences.len(),
+                });
+            }
+            let sum = influences.iter().map(|&(_, weight)| weight).sum::<f32>();
+            if (sum - 1.0).abs() > WEIGHT_SUM_EPSILON {
+                return Err(SkinningError::WeightSum { vertex, sum });
+            }
+            // the sort is stable, so equal weights keep the order of the bones
+            influences.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let mut indices = [0; MAX_BONES_PER_VERTEX];
+            let mut weights = [0.0; MAX_BONES_PER_VERTEX];
+            for (slot, (bone, weight)) in influences.into_iter().enumerate() {
+                indices[slot] = bone;
+                weights[slot] = weight / sum;
+            }
+            streams.indices.push(indices);
+            streams.weights.push(weights);
+        }
+        Ok(streams)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::SkinningError;
+    use crate::{
+        structs::{
+            bone::AiBone,
+            mesh::{AiMesh, AiVertexWeight},
+        },
+        utils::float_precision::Vec3,
+    };
+
+    #[test]
+    fn test_skinning_streams() {
+        let bone = |name: &str, weights: &[(u32, f32)]| AiBone {
+            name: name.to_owned(),
+            weights: weights
+                .iter()
+                .map(|&(vertex_id, weight)| AiVertexWeight { vertex_id, weight })
+                .collect(),
+            ..Default::default()
+        };
+        let mut mesh = AiMesh {
+            vertices: vec![Vec3::ZERO; 2],
+            bones: vec![
+                bone("Hip", &[(0, 1.0), (1, 0.25)]),
+                bone("Knee", &[(1, 0.7498)]),
+                bone("Foot", &[(1, 0.0)]),
+            ],
+            ..Default::default()
+        };
+        let streams = mesh.skinning_streams().unwrap();
+        assert_eq!(streams.indices, [[0, 0, 0, 0], [1, 0, 0, 0]]);
+        assert_eq!(streams.weights[0], [1.0, 0.0, 0.0, 0.0]);
+        // the weights are sorted and normalized
+        let [knee, hip, ..] = streams.weights[1];
+        assert!(knee > hip && (knee + hip - 1.0).abs() < 1e-6);
+
+        mesh.bones[2].weights[0].weight = 0.5;
+        assert!(matches!(
+            mesh.skinning_streams(),
+            Err(SkinningError::WeightSum { vertex: 1, sum }) if (sum - 1.5).abs() < 1e-3
+        ));
+        mesh.bones = (0..5).map(|_| bone("Bone", &[(0, 0.2)])).collect();
+        assert_eq!(
+            mesh.skinning_streams(),
+            Err(SkinningError::TooManyInfluences {
+                vertex: 0,
+                count: 5
+            })
+        );
+        mesh.bones = vec![bone("Hip", &[(2, 1.0)])];
+        assert!(matches!(
+            mesh.skinning_streams(),
+            Err(SkinningError::InvalidVertex { vertex: 2, .. })
+        ));
+        // vertices without any influence are not skinned
+        mesh.bones = vec![bone("Hip", &[(0, 1.0)])];
+        assert_eq!(
+            mesh.skinning_streams(),
+            Err(SkinningError::WeightSum {
+                vertex: 1,
+                sum: 0.0
+            })
+        );
+        mesh.bones = vec![AiBone::default(); 257];
+        assert_eq!(
+            mesh.skinning_streams(),
+            Err(SkinningError::TooManyBones(257))
+        );
+    }
+}
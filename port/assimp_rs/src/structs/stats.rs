@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use super::scene::AiScene;
+
+/// Number of faces and the vertices they reference, for one kind of
+/// primitive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrimitiveStats {
+    pub faces: usize,
+    pub indices: usize,
+}
+
+/// Summary of the contents of an [`AiScene`], see [`AiScene::stats`].
+///
+/// Faces are classified by their number of indices, so the counts do not
+/// depend on the primitive types reported by the meshes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AiSceneStats {
+    pub nodes: usize,
+    /// Depth of the node hierarchy, a lone root node has depth 1
+    pub max_depth: usize,
+    pub meshes: usize,
+    pub vertices: usize,
+    pub faces: usize,
+    pub points: PrimitiveStats,
+    pub lines: PrimitiveStats,
+    pub triangles: PrimitiveStats,
+    pub polygons: PrimitiveStats,
+    pub bones: usize,
+    pub vertex_weights: usize,
+    pub materials: usize,
+    pub embedded_textures: usize,
+    /// Number of texture file references in all materials
+    pub texture_references: usize,
+    pub animations: usize,
+    pub node_channels: usize,
+    pub mesh_channels: usize,
+    pub morph_mesh_channels: usize,
+    pub lights: usize,
+    pub cameras: usize,
+    /// How often each material property key occurs in all materials
+    pub material_properties: BTreeMap<String, usize>,
+}
+
+impl AiSceneStats {
+    pub fn new(scene: &AiScene) -> Self {
+        let mut stats = Self {
+            nodes: scene.nodes.len(),
+            meshes: scene.meshes.len(),
+            materials: scene.materials.len(),
+            embedded_textures: scene.textures.len(),
+            animations: scene.animations.len(),
+            lights: scene.lights.len(),
+            cameras: scene.cameras.len(),
+            ..Default::default()
+        };
+
+        if let Some(root) = scene.root {
+            let mut stack = vec![(root, 1)];
+            while let Some((index, depth)) = stack.pop() {
+                let Some(node) = scene.get_node_by_index(index) else {
+                    continue;
+                };
+                stats.max_depth = stats.max_depth.max(depth);
+                stack.extend(node.children.iter().map(|&child| (child, depth + 1)));
+            }
+        }
+
+        for mesh in &scene.meshes {
+            stats.vertices += mesh.vertices.len();
+            stats.faces += mesh.faces.len();
+            for face in &mesh.faces {
+                let primitive = match face.indices.len() {
+                    0 => continue,
+                    1 => &mut stats.points,
+                    2 => &mut stats.lines,
+                    3 => &mut stats.triangles,
+                    _ => &mut stats.polygons,
+                };
+                primitive.faces += 1;
+                primitive.indices += face.indices.len();
+            }
+            stats.bones += mesh.bones.len();
+            stats.vertex_weights += mesh.bones.iter().map(|b| b.weights.len()).sum::<usize>();
+        }
+
+        for material in &scene.materials {
+            for property in &material.properties {
+                if property.texture_path().is_some() {
+                    stats.texture_references += 1;
+                }
+                *stats
+                    .material_properties
+                    .entry(property.key.to_string())
+                    .or_default() += 1;
+            }
+        }
+
+        for animation in &scene.animations {
+            stats.node_channels += animation.channels.len();
+            stats.mesh_channels += animation.mesh_channels.len();
+            stats.morph_mesh_channels += animation.morph_mesh_channels.len();
+        }
+        stats
+    }
+}
+
+impl Display for AiSceneStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Nodes:              {} (depth {})",
+            self.nodes, self.max_depth
+        )?;
+        writeln!(f, "Meshes:             {}", self.meshes)?;
+        writeln!(f, "Vertices:           {}", self.vertices)?;
+        writeln!(f, "Faces:              {}", self.faces)?;
+        for (name, primitive) in [
+            ("Points", self.points),
+            ("Lines", self.lines),
+            ("Triangles", self.triangles),
+            ("Polygons", self.polygons),
+        ] {
+            if primitive.faces > 0 {
+                writeln!(
+                    f,
+                    "  {:<18}{} ({} indices)",
+                    format!("{name}:"),
+                    primitive.faces,
+                    primitive.indices
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "Bones:              {} ({} weights)",
+            self.bones, self.vertex_weights
+        )?;
+        writeln!(f, "Materials:          {}", self.materials)?;
+        writeln!(f, "Texture references: {}", self.texture_references)?;
+        writeln!(f, "Embedded textures:  {}", self.embedded_textures)?;
+        writeln!(f, "Animations:         {}", self.animations)?;
+        writeln!(
+            f,
+            "  Channels:         {} node, {} mesh, {} morph",
+            self.node_channels, self.mesh_channels, self.morph_mesh_channels
+        )?;
+        writeln!(f, "Lights:             {}", self.lights)?;
+        write!(f, "Cameras:            {}", self.cameras)?;
+        if !self.material_properties.is_empty() {
+            write!(f, "\nMaterial properties:")?;
+            for (key, count) in &self.material_properties {
+                write!(f, "\n  {key:<18}{count}")?;
+            }
+        }
+        Ok(())
+    }
+}
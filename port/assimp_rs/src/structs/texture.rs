@@ -1,6 +1,6 @@
 const HINT_MAX_TEXTURE_LEN: usize = 9;
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub struct AiTexel {
     pub b: u8,
     pub g: u8,
@@ -84,3 +84,91 @@ pub struct AiTexture {
      */
     pub filename: Box<str>,
 }
+
+impl AiTexture {
+    /// Creates a compressed texture from the bytes of an image file. The
+    /// bytes are packed into the texels in memory order, four per texel.
+    pub fn from_compressed(bytes: &[u8], hint: &str) -> Self {
+        let texels = bytes
+            .chunks(4)
+            .map(|c| {
+                let mut b = [0; 4];
+                b[..c.len()].copy_from_slice(c);
+                AiTexel::new(b[0], b[1], b[2], b[3])
+            })
+            .collect();
+        let mut texture = Self {
+            width: bytes.len() as u32,
+            height: 0,
+            data: Box::new([texels]),
+            ..Default::default()
+        };
+        texture.set_format_hint(hint);
+        texture
+    }
+
+    /// Whether the texture holds the bytes of an image file
+    pub fn is_compressed(&self) -> bool {
+        self.height == 0
+    }
+
+    /// The format hint up to the terminator, empty if not valid UTF-8
+    pub fn format_hint(&self) -> &str {
+        let end = self
+            .ash_format_hint
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(HINT_MAX_TEXTURE_LEN);
+        core::str::from_utf8(&self.ash_format_hint[..end]).unwrap_or_default()
+    }
+
+    /// Sets the format hint, it is truncated to 8 bytes
+    pub fn set_format_hint(&mut self, hint: &str) {
+        self.ash_format_hint = [0; HINT_MAX_TEXTURE_LEN];
+        let len = hint.len().min(HINT_MAX_TEXTURE_LEN - 1);
+        self.ash_format_hint[..len].copy_from_slice(&hint.as_bytes()[..len]);
+    }
+
+    /// The bytes of the image file of a compressed texture, `None` for
+    /// uncompressed textures
+    pub fn compressed_data(&self) -> Option<Vec<u8>> {
+        if !self.is_compressed() {
+            return None;
+        }
+        let mut bytes = self
+            .data
+            .iter()
+            .flatten()
+            .flat_map(|t| [t.b, t.g, t.r, t.a])
+            .collect::<Vec<_>>();
+        bytes.truncate(self.width as usize);
+        Some(bytes)
+    }
+
+    /// Decodes a compressed texture to an uncompressed ARGB8888 texture with
+    /// the same file name, uncompressed textures are cloned. See
+    /// [`image`](crate::utils::image) for the supported formats.
+    #[cfg(feature = "image")]
+    pub fn decode(&self) -> Result<AiTexture, crate::utils::image::ImageError> {
+        let Some(bytes) = self.compressed_data() else {
+            return Ok(self.clone());
+        };
+        let image = crate::utils::image::decode(&bytes, self.format_hint())?;
+        let data = if image.width == 0 {
+            Box::default()
+        } else {
+            image
+                .texels
+                .chunks(image.width as usize)
+                .map(Box::from)
+                .collect()
+        };
+        Ok(AiTexture {
+            width: image.width,
+            height: image.height,
+            ash_format_hint: [0; HINT_MAX_TEXTURE_LEN],
+            data,
+            filename: self.filename.clone(),
+        })
+    }
+}
@@ -0,0 +1,281 @@
+//! Interleaved vertex and index buffers for renderers, see
+//! [`AiMesh::interleave`] and [`AiMesh::index_buffer`].
+
+use thiserror::Error;
+
+use super::{
+    color::Color4D,
+    mesh::{AI_MAX_NUMBER_OF_COLOR_SETS, AI_MAX_NUMBER_OF_TEXTURECOORDS, AiMesh},
+};
+use crate::utils::float_precision::{Vec3, to_f32};
+
+/// A vertex attribute of a mesh as written to an interleaved buffer.
+/// Floats are written as `f32` in native byte order, also in double
+/// precision builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexAttribute {
+    /// Three floats
+    Position,
+    /// Three floats
+    Normal,
+    /// Three floats
+    Tangent,
+    /// Three floats
+    Bitangent,
+    /// The first two components of a texture coordinate channel
+    TexCoord(u8),
+    /// All three components of a texture coordinate channel
+    TexCoord3(u8),
+    /// Four floats of a vertex color set
+    Color(u8),
+    /// A vertex color set as four bytes, 0 to 255 for 0.0 to 1.0
+    ColorUnorm8(u8),
+}
+
+impl VertexAttribute {
+    /// Size of the attribute in bytes
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Position | Self::Normal | Self::Tangent | Self::Bitangent => 12,
+            Self::TexCoord(_) => 8,
+            Self::TexCoord3(_) => 12,
+            Self::Color(_) => 16,
+            Self::ColorUnorm8(_) => 4,
+        }
+    }
+}
+
+/// The data of an attribute in a mesh, see [`AiMesh::stream`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VertexStream<'a> {
+    Vec3(&'a [Vec3]),
+    Color(&'a [Color4D]),
+}
+
+impl VertexStream<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Vec3(v) => v.len(),
+            Self::Color(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The attributes of an interleaved vertex, in the order they are written
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(attributes: &[VertexAttribute]) -> Self {
+        Self {
+            attributes: attributes.to_vec(),
+        }
+    }
+
+    /// Appends `attribute` to the layout
+    pub fn with(mut self, attribute: VertexAttribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    /// Size of a vertex in bytes
+    pub fn stride(&self) -> usize {
+        self.attributes.iter().map(|a| a.size()).sum()
+    }
+
+    /// Byte offset of the first occurrence of `attribute` in a vertex
+    pub fn offset(&self, attribute: VertexAttribute) -> Option<usize> {
+        let index = self.attributes.iter().position(|&a| a == attribute)?;
+        Some(self.attributes[..index].iter().map(|a| a.size()).sum())
+    }
+}
+
+/// Errors of [`AiMesh::interleave`]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VertexLayoutError {
+    #[error("The mesh has no {0:?} for all vertices")]
+    MissingAttribute(VertexAttribute),
+}
+
+/// Triangle indices in the smallest type fitting all of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexBuffer {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl IndexBuffer {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U16(v) => v.len(),
+            Self::U32(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The indices widened to `u32`
+    pub fn to_u32(&self) -> Vec<u32> {
+        match self {
+            Self::U16(v) => v.iter().map(|&i| i as u32).collect(),
+            Self::U32(v) => v.clone(),
+        }
+    }
+}
+
+impl AiMesh {
+    /// The data of `attribute`, `None` if the mesh does not have it
+    pub fn stream(&self, attribute: VertexAttribute) -> Option<VertexStream<'_>> {
+        let stream = match attribute {
+            VertexAttribute::Position => VertexStream::Vec3(&self.vertices),
+            VertexAttribute::Normal => VertexStream::Vec3(&self.normals),
+            VertexAttribute::Tangent => VertexStream::Vec3(&self.tangents),
+            VertexAttribute::Bitangent => VertexStream::Vec3(&self.bitangents),
+            VertexAttribute::TexCoord(channel) | VertexAttribute::TexCoord3(channel) => {
+                let channel = channel as usize;
+                if channel >= AI_MAX_NUMBER_OF_TEXTURECOORDS {
+                    return None;
+                }
+                VertexStream::Vec3(&self.texture_coords[channel])
+            }
+            VertexAttribute::Color(set) | VertexAttribute::ColorUnorm8(set) => {
+                let set = set as usize;
+                if set >= AI_MAX_NUMBER_OF_COLOR_SETS {
+                    return None;
+                }
+                VertexStream::Color(&self.colors[set])
+            }
+        };
+        (!stream.is_empty()).then_some(stream)
+    }
+
+    /// Writes the vertices into one buffer with the attributes of `layout`
+    /// following each other, [`VertexLayout::stride`] bytes per vertex.
+    pub fn interleave(&self, layout: &VertexLayout) -> Result<Vec<u8>, VertexLayoutError> {
+        let num_vertices = self.vertices.len();
+        let streams = layout
+            .attributes()
+            .iter()
+            .map(|&attribute| match self.stream(attribute) {
+                Some(stream) if stream.len() == num_vertices => Ok((attribute, stream)),
+                _ => Err(VertexLayoutError::MissingAttribute(attribute)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut buffer = Vec::with_capacity(num_vertices * layout.stride());
+        for vertex in 0..num_vertices {
+            for &(attribute, stream) in &streams {
+                let floats = |buffer: &mut Vec<u8>, values: &[f32]| {
+                    for value in values {
+                        buffer.extend_from_slice(&value.to_ne_bytes());
+                    }
+                };
+                match (attribute, stream) {
+                    (VertexAttribute::TexCoord(_), VertexStream::Vec3(v)) => {
+                        let v = v[vertex];
+                        floats(&mut buffer, &[to_f32(v.x), to_f32(v.y)]);
+                    }
+                    (_, VertexStream::Vec3(v)) => {
+                        let v = v[vertex];
+                        floats(&mut buffer, &[to_f32(v.x), to_f32(v.y), to_f32(v.z)]);
+                    }
+                    (VertexAttribute::ColorUnorm8(_), VertexStream::Color(c)) => {
+                        let c = c[vertex].clamp(Color4D::ZERO, Color4D::ONE) * 255.0;
+                        buffer.extend(c.round().to_array().map(|c| c as u8));
+                    }
+                    (_, VertexStream::Color(c)) => floats(&mut buffer, &c[vertex].to_array()),
+                }
+            }
+        }
+        Ok(buffer)
+    }
+
+    /// The indices of all faces as triangles. Polygons are triangulated as
+    /// fans, points and lines are skipped.
+    pub fn triangle_indices(&self) -> Vec<u32> {
+        let mut indices = Vec::with_capacity(self.faces.len() * 3);
+        for face in &self.faces {
+            if let [first, rest @ ..] = face.indices.as_ref() {
+                for pair in rest.windows(2) {
+                    indices.extend([*first, pair[0], pair[1]]);
+                }
+            }
+        }
+        indices
+    }
+
+    /// The [`triangle_indices`](Self::triangle_indices) as `u16` if all
+    /// of them fit, as `u32` otherwise
+    pub fn index_buffer(&self) -> IndexBuffer {
+        let indices = self.triangle_indices();
+        if indices.iter().all(|&i| i <= u16::MAX as u32) {
+            IndexBuffer::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            IndexBuffer::U32(indices)
+        }
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{IndexBuffer, VertexAttribute as A, VertexLayout, VertexLayoutError};
+    use crate::{
+        structs::{color::Color4D, face::AiFace, mesh::AiMesh},
+        utils::float_precision::Vec3,
+    };
+
+    #[test]
+    fn test_interleave() {
+        let mut mesh = AiMesh {
+            vertices: vec![Vec3::X, Vec3::Y, Vec3::Z, Vec3::ONE],
+            normals: vec![Vec3::Z; 4],
+            faces: vec![AiFace {
+                indices: [0, 1, 2, 3].into(),
+            }],
+            ..Default::default()
+        };
+        mesh.texture_coords[0] = vec![Vec3::new(0.25, 0.5, 0.75); 4];
+        mesh.colors[1] = vec![Color4D::new(1.0, 0.5, 0.0, 2.0); 4];
+
+        let layout = VertexLayout::new(&[A::Position, A::Normal])
+            .with(A::TexCoord(0))
+            .with(A::ColorUnorm8(1));
+        assert_eq!(layout.stride(), 12 + 12 + 8 + 4);
+        assert_eq!(layout.offset(A::TexCoord(0)), Some(24));
+        let buffer = mesh.interleave(&layout).unwrap();
+        assert_eq!(buffer.len(), 4 * layout.stride());
+        let float =
+            |offset: usize| f32::from_ne_bytes(buffer[offset..offset + 4].try_into().unwrap());
+        let vertex = layout.stride();
+        assert_eq!(
+            [float(vertex), float(vertex + 4), float(vertex + 8)],
+            [0.0, 1.0, 0.0]
+        );
+        assert_eq!(float(vertex + 20), 1.0);
+        assert_eq!([float(vertex + 24), float(vertex + 28)], [0.25, 0.5]);
+        assert_eq!(buffer[vertex + 32..vertex + 36], [255, 128, 0, 255]);
+
+        assert_eq!(
+            mesh.interleave(&layout.with(A::Tangent)),
+            Err(VertexLayoutError::MissingAttribute(A::Tangent))
+        );
+        assert_eq!(
+            mesh.index_buffer(),
+            IndexBuffer::U16(vec![0, 1, 2, 0, 2, 3])
+        );
+        mesh.faces[0].indices = [0, 1, 70000].into();
+        assert_eq!(mesh.index_buffer(), IndexBuffer::U32(vec![0, 1, 70000]));
+    }
+}
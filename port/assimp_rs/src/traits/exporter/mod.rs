@@ -0,0 +1 @@
+pub mod trait_define;
@@ -0,0 +1,137 @@
+#[cfg(feature = "std")]
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use std::borrow::Cow;
+
+use crate::structs::{exporter::ExportProperties, exporter_desc::ExporterDesc, scene::AiScene};
+
+/// Exporter trait, the counterpart of
+/// [`InternalImporter`](crate::traits::importer::trait_define::InternalImporter)
+///
+/// Exporters write to any [`io::Write`] while they convert the scene, so the
+/// file does not have to fit into memory before the first bytes are written.
+pub trait Exporter<E> {
+    /// Description of the exported format
+    fn desc() -> &'static ExporterDesc;
+
+    /// Export the scene to a writer, honouring the export properties the
+    /// exporter supports. The writer is not buffered by the exporter.
+    #[cfg(feature = "std")]
+    fn export_to_writer<W: Write>(
+        scene: &AiScene,
+        properties: &ExportProperties,
+        writer: &mut W,
+    ) -> Result<(), E>;
+
+    /// Export the scene to a string, for text formats
+    fn export_to_string(scene: &AiScene, properties: &ExportProperties) -> Result<String, E>;
+
+    /// Export the scene to a file through a buffered writer
+    #[cfg(feature = "std")]
+    fn export_to_file<P: AsRef<Path>>(
+        scene: &AiScene,
+        properties: &ExportProperties,
+        file_path: P,
+    ) -> Result<(), E>
+    where
+        E: From<io::Error>,
+    {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        Self::export_to_writer(scene, properties, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Encodes text as ISO-8859-1 (Latin-1), characters outside of it become
+/// `?`. ASCII text is borrowed.
+pub(crate) fn latin1_bytes(s: &str) -> Cow<'_, [u8]> {
+    if s.is_ascii() {
+        Cow::Borrowed(s.as_bytes())
+    } else {
+        Cow::Owned(s.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect())
+    }
+}
+
+/// Size of the buffer of [`IoAdapter`]
+#[cfg(feature = "std")]
+pub(crate) const EXPORT_BUFFER_SIZE: usize = 1 << 16;
+
+/// Adapts an [`io::Write`] for exporters writing text through
+/// [`core::fmt::Write`]. The many small pieces of text are collected in a
+/// buffer of [`EXPORT_BUFFER_SIZE`] bytes, so the memory is bounded no matter
+/// the size of the scene and unbuffered writers like files are fine.
+///
+/// `fmt::Error` carries no details, the error of the writer is returned by
+/// [`finish`](Self::finish) instead.
+#[cfg(feature = "std")]
+pub(crate) struct IoAdapter<'a, W: Write> {
+    inner: &'a mut W,
+    buffer: Vec<u8>,
+    error: Option<io::Error>,
+    /// Write the text as Latin-1 instead of UTF-8
+    latin1: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'a, W: Write> IoAdapter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self {
+            inner,
+            buffer: Vec::with_capacity(EXPORT_BUFFER_SIZE),
+            error: None,
+            latin1: false,
+        }
+    }
+
+    /// Encodes the text as Latin-1, see [`latin1_bytes`]
+    pub(crate) fn with_latin1(mut self, latin1: bool) -> Self {
+        self.latin1 = latin1;
+        self
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let result = self.inner.write_all(&self.buffer);
+        self.buffer.clear();
+        result
+    }
+
+    /// Writes the rest of the buffer, returns the first error of the writer
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.flush_buffer(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> core::fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let bytes = if self.latin1 {
+            latin1_bytes(s)
+        } else {
+            Cow::Borrowed(s.as_bytes())
+        };
+        if self.buffer.len() + bytes.len() > EXPORT_BUFFER_SIZE {
+            let result = self.flush_buffer().and_then(|()| {
+                if bytes.len() >= EXPORT_BUFFER_SIZE {
+                    self.inner.write_all(&bytes)
+                } else {
+                    self.buffer.extend_from_slice(&bytes);
+                    Ok(())
+                }
+            });
+            return result.map_err(|e| {
+                self.error = Some(e);
+                core::fmt::Error
+            });
+        }
+        self.buffer.extend_from_slice(&bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Async imports on tokio. Files and readers are read asynchronously, the
+//! parsing runs on the blocking thread pool of the runtime, so neither
+//! blocks other tasks. The futures have to be polled within a tokio
+//! runtime.
+
+use core::future::Future;
+use std::panic;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::{error::ImportError, trait_define::Importer};
+use crate::structs::{importer::ImportProperties, scene::AiScene};
+
+/// Runs `f` on the blocking thread pool, panics of `f` are resumed in the
+/// calling task
+async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(error) => match error.try_into_panic() {
+            Ok(payload) => panic::resume_unwind(payload),
+            // the runtime shuts down
+            Err(error) => panic!("{error}"),
+        },
+    }
+}
+
+/// Async variants of the [`Importer`] functions, implemented for all
+/// importers
+pub trait AsyncImporter<E: Send + 'static>: Importer<E> + 'static {
+    /// Reads a file with `tokio::fs` and imports it like
+    /// [`Importer::read_file_with_report`]
+    fn import_from_file_async(
+        file_name: impl Into<String>,
+        properties: ImportProperties,
+    ) -> impl Future<Output = Result<Box<AiScene>, E>> + Send
+    where
+        E: From<ImportError>,
+    {
+        let file_name = file_name.into();
+        async move {
+            let buf = tokio::fs::read(&file_name)
+                .await
+                .map_err(ImportError::from)?;
+            spawn_blocking(move || {
+                #[cfg(feature = "compression")]
+                if let Some(unpacked) = super::trait_define::unpack(&buf, &properties)? {
+                    return Self::read_with_report(&unpacked, &properties).map(|(scene, _)| scene);
+                }
+                Self::read_with_report(&buf, &properties).map(|(scene, _)| scene)
+            })
+            .await
+        }
+    }
+
+    /// Reads `reader` to the end and imports its content like
+    /// [`Importer::read_with_report`]
+    fn import_from_reader_async(
+        mut reader: impl AsyncRead + Unpin + Send + 'static,
+        properties: ImportProperties,
+    ) -> impl Future<Output = Result<Box<AiScene>, E>> + Send
+    where
+        E: From<ImportError>,
+    {
+        async move {
+            let mut buf = Vec::new();
+            reader
+                .read_to_end(&mut buf)
+                .await
+                .map_err(ImportError::from)?;
+            spawn_blocking(move || {
+                Self::read_with_report(&buf, &properties).map(|(scene, _)| scene)
+            })
+            .await
+        }
+    }
+}
+
+impl<E: Send + 'static, T: Importer<E> + 'static> AsyncImporter<E> for T {}
+
+#[allow(unused)]
+#[cfg(feature = "x_file")]
+mod test {
+    use super::AsyncImporter;
+    use crate::{
+        formats::x::importer::Importer, structs::importer::ImportProperties,
+        traits::importer::trait_define::Importer as _, utils::get_model_path,
+    };
+
+    #[test]
+    fn test_import_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let path = get_model_path("X", "test_cube_text.x");
+        let expected = Importer::read_from_file(path.to_str().unwrap()).unwrap();
+        let scene = runtime
+            .block_on(Importer::import_from_file_async(
+                path.to_str().unwrap(),
+                ImportProperties::default(),
+            ))
+            .unwrap();
+        assert_eq!(scene.meshes[0].vertices, expected.meshes[0].vertices);
+        #[cfg(feature = "compression")]
+        {
+            let gz = get_model_path("X", "test_cube_text.x.gz");
+            let scene = runtime
+                .block_on(Importer::import_from_file_async(
+                    gz.to_str().unwrap(),
+                    ImportProperties::default(),
+                ))
+                .unwrap();
+            assert_eq!(scene.meshes[0].vertices, expected.meshes[0].vertices);
+        }
+        let scene = runtime
+            .block_on(async {
+                let reader = tokio::fs::File::open(&path).await.unwrap();
+                Importer::import_from_reader_async(reader, ImportProperties::default()).await
+            })
+            .unwrap();
+        assert_eq!(scene.nodes.len(), expected.nodes.len());
+        assert!(
+            runtime
+                .block_on(Importer::import_from_file_async(
+                    "missing.x",
+                    ImportProperties::default()
+                ))
+                .is_err()
+        );
+    }
+}
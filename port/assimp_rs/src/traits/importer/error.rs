@@ -2,6 +2,9 @@ use core::char::DecodeUtf16Error;
 
 use thiserror::Error;
 
+#[cfg(feature = "compression")]
+use crate::utils::compression::error::CompressionError;
+
 /// Encoding conversion errors
 #[derive(Debug, Error)]
 pub enum EncodingError {
@@ -32,6 +35,9 @@ pub enum EncodingError {
     #[error("Unknown encoding")]
     UnknownEncoding,
 
+    #[error("Unknown encoding label: {0}")]
+    UnknownLabel(String),
+
     #[error("UTF8 code {0} {1} can not be converted into ISA-8859-1.")]
     NotValidUtf8ToIso8859_1(u8, u8),
 
@@ -56,4 +62,11 @@ pub enum ImportError {
 
     #[error("Parse error")]
     ParseError,
+
+    #[cfg(feature = "compression")]
+    #[error("Decompression error: {0}")]
+    CompressionError(#[from] CompressionError),
+
+    #[error("Archive contains {0} files, the file to import has to be named")]
+    AmbiguousArchive(usize),
 }
@@ -1,2 +1,4 @@
+#[cfg(feature = "async")]
+pub mod blocking;
 pub mod error;
 pub mod trait_define;
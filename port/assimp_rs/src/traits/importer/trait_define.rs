@@ -2,7 +2,17 @@
 use std::{fs::File, io::Read, path::Path};
 
 use super::error::{EncodingError, ImportError};
-use crate::structs::scene::AiScene;
+#[cfg(all(feature = "std", feature = "compression"))]
+use crate::utils::compression::{gzip, unpack_budget, zip};
+#[cfg(feature = "mmap")]
+use crate::utils::mmap::Mmap;
+use crate::{
+    core::profiler::{ProfileScope, Profiler},
+    structs::{
+        importer::{ImportProperties, report::ImportReport},
+        scene::AiScene,
+    },
+};
 
 /// UTF encoding conversion utilities
 pub mod encoding {
@@ -94,6 +104,53 @@ pub mod encoding {
         result
     }
 
+    /// Guesses the codepage of text which is not valid UTF-8. Shift-JIS is
+    /// chosen if the text decodes without errors and has more double-byte
+    /// characters than half-width katakana, which are also the accented
+    /// capitals of Windows-1252. Everything else is Windows-1252.
+    #[cfg(feature = "encoding")]
+    pub fn detect_legacy_encoding(buf: &[u8]) -> &'static encoding_rs::Encoding {
+        let (text, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(buf);
+        if !had_errors {
+            let (mut wide, mut narrow) = (0, 0);
+            for c in text.chars() {
+                match c {
+                    '\u{FF61}'..='\u{FF9F}' => narrow += 1,
+                    c if !c.is_ascii() => wide += 1,
+                    _ => {}
+                }
+            }
+            if wide > 0 && wide >= narrow {
+                return encoding_rs::SHIFT_JIS;
+            }
+        }
+        encoding_rs::WINDOWS_1252
+    }
+
+    /// Converts text which is not valid UTF-8 from the codepage with the
+    /// WHATWG `label`, or the one found by [`detect_legacy_encoding`].
+    /// Returns `None` for valid UTF-8, else the text and the name of the
+    /// codepage.
+    #[cfg(feature = "encoding")]
+    pub fn convert_legacy_to_utf8(
+        buf: &[u8],
+        label: Option<&str>,
+    ) -> Result<Option<(String, &'static str)>, EncodingError> {
+        if core::str::from_utf8(buf).is_ok() {
+            return Ok(None);
+        }
+        let encoding = match label {
+            Some(label) => encoding_rs::Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| EncodingError::UnknownLabel(label.to_string()))?,
+            None => detect_legacy_encoding(buf),
+        };
+        let (text, had_errors) = encoding.decode_without_bom_handling(buf);
+        if had_errors {
+            log::warn!("Invalid {} characters replaced", encoding.name());
+        }
+        Ok(Some((text.into_owned(), encoding.name())))
+    }
+
     /// Convert UTF-8 to ISO-8859-1(Latin-1)
     pub fn convert_utf8_to_iso8859_1(buf: &mut Vec<u8>) -> Result<(), EncodingError> {
         let len = buf.len();
@@ -179,6 +236,84 @@ pub trait InternalImporter<E> {
     /// Import from file to scene
     #[cfg(feature = "std")]
     fn import_from_file(file_name: &str, scene: &mut AiScene) -> Result<(), E>;
+
+    /// Import from byte buffer to scene, honouring the import properties the
+    /// importer supports
+    fn import_from_buf_with_properties(
+        buf: &[u8],
+        scene: &mut AiScene,
+        _properties: &ImportProperties,
+    ) -> Result<(), E> {
+        Self::import_from_buf(buf, scene)
+    }
+
+    /// Import from byte buffer to scene like
+    /// [`Self::import_from_buf_with_properties`], recording the durations of
+    /// the import phases in `profiler`. Importers without separately timed
+    /// phases record the whole import as `import`.
+    fn import_from_buf_with_profiler(
+        buf: &[u8],
+        scene: &mut AiScene,
+        properties: &ImportProperties,
+        profiler: &mut Profiler,
+    ) -> Result<(), E> {
+        profiler.measure(ProfileScope::Import, "import", || {
+            Self::import_from_buf_with_properties(buf, scene, properties)
+        })
+    }
+
+    /// Import from byte buffer to scene like
+    /// [`Self::import_from_buf_with_properties`], returning the problems in
+    /// the file which were tolerated or repaired according to the
+    /// [`ErrorPolicy`](crate::structs::importer::report::ErrorPolicy), the
+    /// format and the durations of the import phases. Importers which do
+    /// not report problems return no issues.
+    fn import_from_buf_with_report(
+        buf: &[u8],
+        scene: &mut AiScene,
+        properties: &ImportProperties,
+    ) -> Result<ImportReport, E> {
+        let mut profiler = Profiler::new();
+        Self::import_from_buf_with_profiler(buf, scene, properties, &mut profiler)?;
+        let mut report = ImportReport::default();
+        report.finish(scene, &profiler);
+        Ok(report)
+    }
+
+    /// Import from file to scene, honouring the import properties the
+    /// importer supports
+    #[cfg(feature = "std")]
+    fn import_from_file_with_properties(
+        file_name: &str,
+        scene: &mut AiScene,
+        _properties: &ImportProperties,
+    ) -> Result<(), E> {
+        Self::import_from_file(file_name, scene)
+    }
+
+    /// Import from a memory mapped file to scene, honouring the import
+    /// properties like [`Self::import_from_file_with_properties`]. The
+    /// importer reads the mapped pages instead of a copy of the file.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is imported,
+    /// otherwise the importer reads changing data or the process crashes,
+    /// see [`Mmap::map`].
+    #[cfg(feature = "mmap")]
+    unsafe fn import_from_mmap(
+        file_name: &str,
+        scene: &mut AiScene,
+        properties: &ImportProperties,
+    ) -> Result<(), E>
+    where
+        E: From<std::io::Error>,
+    {
+        let file = File::open(file_name)?;
+        // SAFETY: forwarded to the caller
+        let map = unsafe { Mmap::map(&file)? };
+        Self::import_from_buf_with_properties(&map, scene, properties)
+    }
 }
 
 /// Public importer trait
@@ -186,19 +321,127 @@ pub trait InternalImporter<E> {
 /// Provide high-level import API, returning complete scene objects
 pub trait Importer<E>: InternalImporter<E> {
     /// Read from file and create scene
+    ///
+    /// With the `compression` feature gzip files and zip archives holding a
+    /// single file are decompressed transparently, up to
+    /// [`DEFAULT_MAX_UNPACKED_BYTES`](crate::utils::compression::DEFAULT_MAX_UNPACKED_BYTES).
     #[cfg(feature = "std")]
-    fn read_from_file(file_name: &str) -> Result<Box<AiScene>, E> {
+    fn read_from_file(file_name: &str) -> Result<Box<AiScene>, E>
+    where
+        E: From<ImportError>,
+    {
         let mut scene = Box::<AiScene>::default();
+        #[cfg(feature = "compression")]
+        if let Some(buf) = unpack_file(file_name, &ImportProperties::default())? {
+            Self::import_from_buf(&buf, &mut scene)?;
+            return Ok(scene);
+        }
         Self::import_from_file(file_name, &mut scene)?;
         Ok(scene)
     }
 
+    /// Read a file inside a zip archive and create scene
+    #[cfg(all(feature = "std", feature = "compression"))]
+    fn read_from_zip(archive_name: &str, entry_name: &str) -> Result<Box<AiScene>, E>
+    where
+        E: From<ImportError>,
+    {
+        let archive = std::fs::read(archive_name).map_err(ImportError::from)?;
+        let buf = zip::ZipArchive::new(&archive)
+            .and_then(|archive| {
+                archive.read_by_name(entry_name, &mut unpack_budget(&ImportProperties::default()))
+            })
+            .map_err(ImportError::from)?;
+        let mut scene = Box::<AiScene>::default();
+        Self::import_from_buf(&buf, &mut scene)?;
+        Ok(scene)
+    }
+
     /// Read from byte buffer and create scene
     fn read_from_buf(buf: &[u8]) -> Result<Box<AiScene>, E> {
         let mut scene = Box::<AiScene>::default();
         Self::import_from_buf(buf, &mut scene)?;
         Ok(scene)
     }
+
+    /// Read from byte buffer with `properties` and create scene, along with
+    /// the report of the import, see
+    /// [`InternalImporter::import_from_buf_with_report`]
+    fn read_with_report(
+        buf: &[u8],
+        properties: &ImportProperties,
+    ) -> Result<(Box<AiScene>, ImportReport), E> {
+        let mut scene = Box::<AiScene>::default();
+        let report = Self::import_from_buf_with_report(buf, &mut scene, properties)?;
+        Ok((scene, report))
+    }
+
+    /// Read from file like [`Self::read_from_file`] with `properties` and
+    /// create scene, along with the report of the import. Compressed files
+    /// are limited by [`ImportProperties::max_alloc_bytes`].
+    #[cfg(feature = "std")]
+    fn read_file_with_report(
+        file_name: &str,
+        properties: &ImportProperties,
+    ) -> Result<(Box<AiScene>, ImportReport), E>
+    where
+        E: From<ImportError>,
+    {
+        #[cfg(feature = "compression")]
+        if let Some(buf) = unpack_file(file_name, properties)? {
+            return Self::read_with_report(&buf, properties);
+        }
+        let buf = std::fs::read(file_name).map_err(ImportError::from)?;
+        Self::read_with_report(&buf, properties)
+    }
+}
+
+/// Returns the decompressed content if the file is a gzip file or a zip
+/// archive, `None` for all other files and for usdz packages. Content
+/// larger than the budget of [`unpack_budget`] fails.
+#[cfg(all(feature = "std", feature = "compression"))]
+fn unpack_file(
+    file_name: &str,
+    properties: &ImportProperties,
+) -> Result<Option<Vec<u8>>, ImportError> {
+    let mut magic = [0; 4];
+    let mut file = File::open(file_name)?;
+    if file.read_exact(&mut magic).is_err() {
+        // too small to be compressed, let the importer report it
+        return Ok(None);
+    }
+    if !gzip::is_gzip(&magic) && !zip::is_zip(&magic) {
+        return Ok(None);
+    }
+    unpack(&std::fs::read(file_name)?, properties)
+}
+
+/// Returns the decompressed content of a gzip file or a zip archive like
+/// [`unpack_file`], for files which are read already
+#[cfg(all(feature = "std", feature = "compression"))]
+pub(crate) fn unpack(
+    data: &[u8],
+    properties: &ImportProperties,
+) -> Result<Option<Vec<u8>>, ImportError> {
+    let mut budget = unpack_budget(properties);
+    if gzip::is_gzip(data) {
+        return Ok(Some(gzip::decompress_gzip(data, &mut budget)?));
+    }
+    // usdz packages are zip archives, their importer reads the files used
+    // by the layer from the archive
+    #[cfg(feature = "usd")]
+    if crate::formats::usd::importer::is_usdz(data) {
+        return Ok(None);
+    }
+    if !zip::is_zip(data) {
+        return Ok(None);
+    }
+    let archive = zip::ZipArchive::new(data)?;
+    let mut files = archive.files();
+    match (files.next(), files.next()) {
+        (Some(entry), None) => Ok(Some(archive.read(entry, &mut budget)?)),
+        _ => Err(ImportError::AmbiguousArchive(archive.files().count())),
+    }
 }
 
 // Automatically implement Importer for all types that implement InternalImporter
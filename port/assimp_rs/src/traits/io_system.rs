@@ -0,0 +1,126 @@
+#[cfg(feature = "std")]
+use std::fs;
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// File system abstraction used by importers and post-processing steps
+///
+/// Importers which reference external files (textures, material libraries)
+/// and steps which need to look up such files go through this trait, so
+/// applications can redirect all file access (archives, virtual file systems,
+/// in-memory assets).
+pub trait IoSystem {
+    /// Tests for the existence of a file at the given path
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Returns the path separator of the underlying file system
+    fn os_separator(&self) -> char {
+        '/'
+    }
+
+    /// Reads the whole content of the file at the given path
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+}
+
+/// Default [`IoSystem`] implementation using the local file system
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultIoSystem;
+
+#[cfg(feature = "std")]
+impl IoSystem for DefaultIoSystem {
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn os_separator(&self) -> char {
+        std::path::MAIN_SEPARATOR
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
+
+/// [`IoSystem`] keeping the content of every file read through it, so files
+/// shared by several models, like textures, are read once. It can be used
+/// from several threads if the wrapped io system can.
+#[derive(Debug, Default)]
+pub struct CachingIoSystem<I> {
+    inner: I,
+    cache: Mutex<HashMap<PathBuf, Arc<[u8]>>>,
+    hits: AtomicUsize,
+}
+
+impl<I: IoSystem> CachingIoSystem<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            cache: Mutex::default(),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// The content of the file, shared with all other reads of the same
+    /// path
+    pub fn read_shared(&self, path: &Path) -> io::Result<Arc<[u8]>> {
+        if let Some(data) = self.lock().get(path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(data.clone());
+        }
+        // read without the lock, if another thread was faster its copy wins
+        let data = Arc::<[u8]>::from(self.inner.read(path)?);
+        Ok(self
+            .lock()
+            .entry(path.to_path_buf())
+            .or_insert(data)
+            .clone())
+    }
+
+    /// The number of files in the cache
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// The number of reads answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Arc<[u8]>>> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<I: IoSystem> IoSystem for CachingIoSystem<I> {
+    fn exists(&self, path: &Path) -> bool {
+        self.lock().contains_key(path) || self.inner.exists(path)
+    }
+
+    fn os_separator(&self) -> char {
+        self.inner.os_separator()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.read_shared(path).map(|data| data.to_vec())
+    }
+}
@@ -1 +1,3 @@
+pub mod exporter;
 pub mod importer;
+pub mod io_system;
@@ -0,0 +1,52 @@
+//! Accounting of the memory parsers allocate on behalf of untrusted input.
+
+use core::mem::size_of;
+
+/// Number of bytes a parser may still allocate.
+///
+/// Allocations are charged with the element count claimed by the file, not
+/// with what is actually reserved, so a hostile count fails early even if
+/// the reservation itself would be cut down to the size of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocBudget {
+    remaining: usize,
+}
+
+impl AllocBudget {
+    /// A budget of `limit` bytes, `None` for no limit
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            remaining: limit.unwrap_or(usize::MAX),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    /// The bytes which may still be allocated
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Takes the memory of `count` values of `T` from the budget, returns
+    /// `false` and leaves the budget untouched if it is not enough.
+    pub fn charge<T>(&mut self, count: usize) -> bool {
+        match count
+            .checked_mul(size_of::<T>())
+            .and_then(|bytes| self.remaining.checked_sub(bytes))
+        {
+            Some(remaining) => {
+                self.remaining = remaining;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for AllocBudget {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
@@ -20,8 +20,23 @@ pub enum CompressionError {
     #[error("zlib: unknown error code: {0}")]
     Unknown(i32),
 
-    #[error("zlib: try to close a closed stream")]
-    TryToCloseClosedStream,
+    #[error("gzip: invalid header")]
+    InvalidGzipHeader,
+
+    #[error("zip: invalid archive, {0}")]
+    InvalidZipArchive(&'static str),
+
+    #[error("zip: entry {name} is not supported, {reason}")]
+    UnsupportedZipEntry { name: String, reason: &'static str },
+
+    #[error("zip: entry {0} not found")]
+    ZipEntryNotFound(String),
+
+    #[error("checksum of the decompressed data does not match")]
+    ChecksumMismatch,
+
+    #[error("decompressed data exceeds the limit of {0} bytes")]
+    OutputLimitExceeded(usize),
 }
 
 impl From<ReturnCode> for CompressionError {
@@ -0,0 +1,76 @@
+//! Reading of gzip (`.gz`) files as described in RFC 1952.
+
+use zlib_rs::MAX_WBITS;
+
+use super::{Compression, error::CompressionError};
+use crate::utils::alloc_budget::AllocBudget;
+
+/// Magic bytes at the start of every gzip member
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Adding 16 to the window bits makes zlib read the gzip header and verify
+/// the trailing checksum
+const GZIP_WINDOW_BITS: i32 = MAX_WBITS + 16;
+
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompresses a gzip file. Concatenated members are decompressed one
+/// after another, anything following the last member is ignored like
+/// `gunzip` does. The decompressed bytes are taken from `budget`, the
+/// decompression stops once it is used up.
+pub fn decompress_gzip(
+    mut data: &[u8],
+    budget: &mut AllocBudget,
+) -> Result<Vec<u8>, CompressionError> {
+    if !is_gzip(data) {
+        return Err(CompressionError::InvalidGzipHeader);
+    }
+    let mut output = Vec::new();
+    while is_gzip(data) {
+        let mut compression = Compression::builder()
+            .window_bits(GZIP_WINDOW_BITS)
+            .max_output(budget.remaining())
+            .open()?;
+        let size = compression.decompress(data, &mut output)?;
+        budget.charge::<u8>(size);
+        data = &data[data.len() - compression.remaining_input()..];
+    }
+    Ok(output)
+}
+
+#[allow(unused)]
+mod test {
+    use super::{GZIP_WINDOW_BITS, decompress_gzip};
+    use crate::utils::{
+        alloc_budget::AllocBudget,
+        compression::{DEFAULT_COMPRESSION_LEVEL, compress, error::CompressionError},
+    };
+
+    #[test]
+    fn test_decompress_gzip_budget() {
+        // two members of 1 MiB zeros each, a few kilobytes compressed
+        let data = vec![0; 1 << 20];
+        let mut gzip = Vec::new();
+        compress(
+            &data,
+            DEFAULT_COMPRESSION_LEVEL,
+            GZIP_WINDOW_BITS,
+            &mut gzip,
+        )
+        .unwrap();
+        gzip.extend_from_within(..);
+        assert!(gzip.len() < 1 << 14);
+
+        let mut budget = AllocBudget::new(Some(3 << 20));
+        assert_eq!(decompress_gzip(&gzip, &mut budget).unwrap().len(), 2 << 20);
+        assert_eq!(budget.remaining(), 1 << 20);
+        // the second member exceeds what is left
+        let mut budget = AllocBudget::new(Some(3 << 19));
+        assert!(matches!(
+            decompress_gzip(&gzip, &mut budget),
+            Err(CompressionError::OutputLimitExceeded(limit)) if limit == 1 << 19
+        ));
+    }
+}
@@ -1,15 +1,77 @@
 use zlib_rs::{
-    InflateFlush, ReturnCode,
+    InflateFlush, MAX_WBITS, ReturnCode,
     c_api::z_stream,
+    deflate::DeflateConfig,
     inflate::{InflateConfig, InflateStream},
 };
 
-pub(crate) mod error;
+pub mod error;
+pub mod gzip;
+pub mod zip;
+
 use error::CompressionError;
+
+use crate::{structs::importer::ImportProperties, utils::alloc_budget::AllocBudget};
 #[allow(unused)]
 const MYBLOCK: usize = 32786;
 
+/// Let zlib pick the compression level, currently 6
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = -1;
+
+/// Limit of the decompressed size of files the importers unpack
+/// transparently without a limit in
+/// [`AI_CONFIG_IMPORT_MAX_ALLOC_BYTES`](crate::structs::importer::AI_CONFIG_IMPORT_MAX_ALLOC_BYTES),
+/// which protects against decompression bombs
+pub const DEFAULT_MAX_UNPACKED_BYTES: usize = 1 << 30;
+
+/// Upper bound of the ratio between decompressed and compressed size of a
+/// deflate stream, used to bound reservations for untrusted size fields
+pub const MAX_DEFLATE_RATIO: usize = 1032;
+
+/// The budget of files the importers unpack transparently, the limit of
+/// [`AI_CONFIG_IMPORT_MAX_ALLOC_BYTES`](crate::structs::importer::AI_CONFIG_IMPORT_MAX_ALLOC_BYTES)
+/// or [`DEFAULT_MAX_UNPACKED_BYTES`]
+pub fn unpack_budget(properties: &ImportProperties) -> AllocBudget {
+    AllocBudget::new(Some(
+        properties
+            .max_alloc_bytes()
+            .unwrap_or(DEFAULT_MAX_UNPACKED_BYTES),
+    ))
+}
+
+/// Compresses `data` in one go and appends the result to `output`, returns
+/// the number of bytes written.
+///
+/// The window bits select the container like for inflating: negative values
+/// write a raw deflate stream, `MAX_WBITS` a zlib stream and `MAX_WBITS + 16`
+/// a gzip file.
+pub fn compress(
+    data: &[u8],
+    level: i32,
+    window_bits: i32,
+    output: &mut Vec<u8>,
+) -> Result<usize, CompressionError> {
+    let start = output.len();
+    // the bound covers the zlib wrapper, the gzip header and trailer are
+    // 12 bytes larger
+    output.resize(start + zlib_rs::deflate::bound(None, data.len()) + 12, 0);
+    let config = DeflateConfig {
+        level,
+        window_bits,
+        ..DeflateConfig::default()
+    };
+    let (compressed, ret) = zlib_rs::deflate::compress_slice(&mut output[start..], data, config);
+    let size = compressed.len();
+    if ret != ReturnCode::Ok {
+        output.truncate(start);
+        return Err(ret.into());
+    }
+    output.truncate(start + size);
+    Ok(size)
+}
+
 #[allow(unused)]
+#[derive(Debug, Clone, Copy)]
 pub enum Format {
     InvalidFormat = -1,
     Text = 0,
@@ -17,36 +79,94 @@ pub enum Format {
     Compressed = 2,
 }
 
-pub struct Compression {
-    is_open: bool,
-    stream: z_stream,
+/// Configuration of a decompression stream, see [`Compression::builder`]
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionBuilder {
+    format: Format,
     flush_mode: InflateFlush,
+    window_bits: i32,
+    max_output: usize,
 }
 
-#[allow(unused)]
-impl Compression {
-    pub fn new() -> Self {
+impl Default for CompressionBuilder {
+    fn default() -> Self {
         Self {
-            is_open: false,
-            stream: z_stream::default(),
+            format: Format::Binary,
             flush_mode: InflateFlush::NoFlush,
+            window_bits: MAX_WBITS,
+            max_output: usize::MAX,
         }
     }
+}
 
-    pub fn open(
-        &mut self,
-        format: Format,
-        flush_mode: InflateFlush,
-        window_bits: i32,
-    ) -> Result<(), CompressionError> {
-        self.stream.data_type = format as i32;
+impl CompressionBuilder {
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Flush mode of [`Compression::decompress`], `Finish` expects the
+    /// output buffer to be large enough for the whole stream
+    pub fn flush_mode(mut self, flush_mode: InflateFlush) -> Self {
         self.flush_mode = flush_mode;
-        let ret = zlib_rs::inflate::init(&mut self.stream, InflateConfig { window_bits });
+        self
+    }
+
+    /// Negative values read a raw deflate stream, `MAX_WBITS` a zlib stream
+    /// and `MAX_WBITS + 16` a gzip file
+    pub fn window_bits(mut self, window_bits: i32) -> Self {
+        self.window_bits = window_bits;
+        self
+    }
+
+    /// Upper bound of the bytes one call of [`Compression::decompress`]
+    /// appends, longer streams fail with
+    /// [`CompressionError::OutputLimitExceeded`] before the output grows
+    /// beyond it
+    pub fn max_output(mut self, max_output: usize) -> Self {
+        self.max_output = max_output;
+        self
+    }
+
+    /// Opens the stream, it is closed again when dropped.
+    pub fn open(self) -> Result<Compression, CompressionError> {
+        // the stream is boxed as zlib must not see it move once initialized
+        let mut stream = Box::new(z_stream {
+            data_type: self.format as i32,
+            ..z_stream::default()
+        });
+        let ret = zlib_rs::inflate::init(
+            &mut stream,
+            InflateConfig {
+                window_bits: self.window_bits,
+            },
+        );
         if ret != ReturnCode::Ok {
             return Err(ret.into());
         }
-        self.is_open = true;
-        Ok(())
+        Ok(Compression {
+            stream,
+            flush_mode: self.flush_mode,
+            max_output: self.max_output,
+        })
+    }
+}
+
+/// An open inflate stream
+pub struct Compression {
+    stream: Box<z_stream>,
+    flush_mode: InflateFlush,
+    max_output: usize,
+}
+
+impl Compression {
+    pub fn builder() -> CompressionBuilder {
+        CompressionBuilder::default()
+    }
+
+    fn inflate_stream(&mut self) -> &mut InflateStream<'_> {
+        // SAFETY: the stream was initialized for inflating when it was opened
+        unsafe { InflateStream::from_stream_mut(&mut *self.stream) }.expect("stream is initialized")
     }
 
     pub fn decompress(
@@ -60,8 +180,7 @@ impl Compression {
         if flush_mode == InflateFlush::Finish {
             self.stream.avail_out = output.len() as u32;
             self.stream.next_out = output.as_mut_ptr();
-            let stream = unsafe { InflateStream::from_stream_mut(&mut self.stream).unwrap() };
-            let ret = unsafe { zlib_rs::inflate::inflate(stream, self.flush_mode) };
+            let ret = unsafe { zlib_rs::inflate::inflate(self.inflate_stream(), flush_mode) };
             if ret != ReturnCode::StreamEnd && ret != ReturnCode::Ok {
                 return Err(ret.into());
             }
@@ -76,15 +195,17 @@ impl Compression {
                 }
                 s
             };
-            self.stream.next_out = block.as_mut_ptr();
             loop {
+                self.stream.next_out = block.as_mut_ptr();
                 self.stream.avail_out = MYBLOCK as u32;
-                let stream = unsafe { InflateStream::from_stream_mut(&mut self.stream).unwrap() };
-                let ret = unsafe { zlib_rs::inflate::inflate(stream, flush_mode) };
+                let ret = unsafe { zlib_rs::inflate::inflate(self.inflate_stream(), flush_mode) };
                 if ret != ReturnCode::StreamEnd && ret != ReturnCode::Ok {
                     return Err(ret.into());
                 }
                 let size = MYBLOCK - self.stream.avail_out as usize;
+                if size > self.max_output - total {
+                    return Err(CompressionError::OutputLimitExceeded(self.max_output));
+                }
                 total += size;
                 output.extend_from_slice(&block[..size]);
                 if ret == ReturnCode::StreamEnd {
@@ -104,16 +225,17 @@ impl Compression {
         let avail_out = output.len() as u32;
         self.stream.avail_out = avail_out;
         self.stream.next_out = output.as_mut_ptr();
-        let stream = unsafe { InflateStream::from_stream_mut(&mut self.stream).unwrap() };
-        let ret = unsafe { zlib_rs::inflate::inflate(stream, InflateFlush::SyncFlush) };
+        let ret =
+            unsafe { zlib_rs::inflate::inflate(self.inflate_stream(), InflateFlush::SyncFlush) };
         if ret != ReturnCode::StreamEnd && ret != ReturnCode::Ok {
             return Err(ret.into());
         }
+        let total = avail_out as usize - self.stream.avail_out as usize;
+        let stream = self.inflate_stream();
         let ret = zlib_rs::inflate::reset(stream);
         if ret != ReturnCode::Ok {
             return Err(ret.into());
         }
-        let total = avail_out as usize - self.stream.avail_out as usize;
         let ret = zlib_rs::inflate::set_dictionary(stream, &output[..total]);
         if ret != ReturnCode::Ok {
             return Err(ret.into());
@@ -121,13 +243,15 @@ impl Compression {
         Ok(total)
     }
 
-    pub fn close(&mut self) -> Result<(), CompressionError> {
-        if !self.is_open {
-            return Err(CompressionError::TryToCloseClosedStream);
-        }
-        let stream = unsafe { InflateStream::from_stream_mut(&mut self.stream).unwrap() };
-        zlib_rs::inflate::end(stream);
-        self.is_open = false;
-        return Ok(());
+    /// Number of input bytes which were not consumed by the last call, e.g.
+    /// the data following the end of the stream
+    pub fn remaining_input(&self) -> usize {
+        self.stream.avail_in as usize
+    }
+}
+
+impl Drop for Compression {
+    fn drop(&mut self) {
+        zlib_rs::inflate::end(self.inflate_stream());
     }
 }
@@ -0,0 +1,259 @@
+//! Reading of PKZIP archives, the container of e.g. 3MF, KMZ and zipped
+//! model collections.
+//!
+//! Stored and deflated entries are supported, encrypted entries and ZIP64
+//! archives are not.
+
+use zlib_rs::MAX_WBITS;
+
+use super::{Compression, MAX_DEFLATE_RATIO, error::CompressionError};
+use crate::utils::alloc_budget::AllocBudget;
+
+/// Signature of a local file header, every archive starts with one
+pub const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+const CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = *b"PK\x01\x02";
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = *b"PK\x05\x06";
+
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const FLAG_ENCRYPTED: u16 = 0x1;
+
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(&ZIP_MAGIC)
+}
+
+#[inline]
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+#[inline]
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// A file or directory in an archive, as listed in the central directory
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    /// Path inside the archive, directories end with `/`
+    pub name: String,
+    pub compression_method: u16,
+    pub flags: u16,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    local_header_offset: u32,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// A PKZIP archive in memory
+#[derive(Debug, Clone)]
+pub struct ZipArchive<'a> {
+    data: &'a [u8],
+    entries: Vec<ZipEntry>,
+}
+
+impl<'a> ZipArchive<'a> {
+    /// Reads the central directory of the archive.
+    pub fn new(data: &'a [u8]) -> Result<Self, CompressionError> {
+        let invalid = || CompressionError::InvalidZipArchive("truncated central directory");
+        // the end of central directory record is followed by a comment of at
+        // most 64k bytes, so search backwards
+        let last = data
+            .len()
+            .checked_sub(END_OF_CENTRAL_DIRECTORY_SIZE)
+            .ok_or(CompressionError::InvalidZipArchive("file is too small"))?;
+        let end = (last.saturating_sub(u16::MAX as usize)..=last)
+            .rev()
+            .find(|&i| data[i..].starts_with(&END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+            .ok_or(CompressionError::InvalidZipArchive(
+                "end of central directory not found",
+            ))?;
+
+        let num_entries = u16_at(data, end + 10).ok_or_else(invalid)?;
+        let mut offset = u32_at(data, end + 16).ok_or_else(invalid)?;
+        if num_entries == u16::MAX || offset == u32::MAX {
+            return Err(CompressionError::InvalidZipArchive(
+                "ZIP64 archives are not supported",
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(num_entries as usize);
+        for _ in 0..num_entries {
+            let header = data.get(offset as usize..).ok_or_else(invalid)?;
+            if !header.starts_with(&CENTRAL_DIRECTORY_SIGNATURE) {
+                return Err(CompressionError::InvalidZipArchive(
+                    "invalid central directory header",
+                ));
+            }
+            let name_len = u16_at(header, 28).ok_or_else(invalid)? as usize;
+            let extra_len = u16_at(header, 30).ok_or_else(invalid)? as usize;
+            let comment_len = u16_at(header, 32).ok_or_else(invalid)? as usize;
+            let name = header
+                .get(CENTRAL_DIRECTORY_HEADER_SIZE..CENTRAL_DIRECTORY_HEADER_SIZE + name_len)
+                .ok_or_else(invalid)?;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).replace('\\', "/"),
+                flags: u16_at(header, 8).ok_or_else(invalid)?,
+                compression_method: u16_at(header, 10).ok_or_else(invalid)?,
+                crc32: u32_at(header, 16).ok_or_else(invalid)?,
+                compressed_size: u32_at(header, 20).ok_or_else(invalid)?,
+                uncompressed_size: u32_at(header, 24).ok_or_else(invalid)?,
+                local_header_offset: u32_at(header, 42).ok_or_else(invalid)?,
+            });
+            offset += (CENTRAL_DIRECTORY_HEADER_SIZE + name_len + extra_len + comment_len) as u32;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// The entries which are files
+    pub fn files(&self) -> impl Iterator<Item = &ZipEntry> {
+        self.entries.iter().filter(|entry| !entry.is_dir())
+    }
+
+    /// Finds an entry by its path, `\` and `/` are both accepted as
+    /// separators.
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        let name = name.replace('\\', "/");
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Decompresses an entry and verifies its checksum. The size the entry
+    /// claims is taken from `budget` before anything is allocated, longer
+    /// data fails.
+    pub fn read(
+        &self,
+        entry: &ZipEntry,
+        budget: &mut AllocBudget,
+    ) -> Result<Vec<u8>, CompressionError> {
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(CompressionError::UnsupportedZipEntry {
+                name: entry.name.clone(),
+                reason: "encrypted",
+            });
+        }
+        let invalid = || CompressionError::InvalidZipArchive("truncated local file header");
+        let header = self
+            .data
+            .get(entry.local_header_offset as usize..)
+            .filter(|header| is_zip(header))
+            .ok_or_else(invalid)?;
+        // the lengths of the local header may differ from the central directory
+        let name_len = u16_at(header, 26).ok_or_else(invalid)? as usize;
+        let extra_len = u16_at(header, 28).ok_or_else(invalid)? as usize;
+        let start = LOCAL_HEADER_SIZE + name_len + extra_len;
+        let data = header
+            .get(start..start + entry.compressed_size as usize)
+            .ok_or(CompressionError::InvalidZipArchive("truncated file data"))?;
+
+        let size = entry.uncompressed_size as usize;
+        if !budget.charge::<u8>(size) {
+            return Err(CompressionError::OutputLimitExceeded(budget.remaining()));
+        }
+        let output = match entry.compression_method {
+            METHOD_STORED if data.len() == size => data.to_vec(),
+            METHOD_STORED => {
+                return Err(CompressionError::InvalidZipArchive(
+                    "sizes of a stored entry differ",
+                ));
+            }
+            METHOD_DEFLATED => {
+                // the size field is not trusted for the reservation
+                let mut output =
+                    Vec::with_capacity(size.min(data.len().saturating_mul(MAX_DEFLATE_RATIO)));
+                Compression::builder()
+                    .window_bits(-MAX_WBITS)
+                    .max_output(size)
+                    .open()?
+                    .decompress(data, &mut output)?;
+                output
+            }
+            _ => {
+                return Err(CompressionError::UnsupportedZipEntry {
+                    name: entry.name.clone(),
+                    reason: "unknown compression method",
+                });
+            }
+        };
+        if zlib_rs::crc32(0, &output) != entry.crc32 {
+            return Err(CompressionError::ChecksumMismatch);
+        }
+        Ok(output)
+    }
+
+    /// Decompresses the entry with the given path, see [`Self::read`].
+    pub fn read_by_name(
+        &self,
+        name: &str,
+        budget: &mut AllocBudget,
+    ) -> Result<Vec<u8>, CompressionError> {
+        let entry = self
+            .find(name)
+            .ok_or_else(|| CompressionError::ZipEntryNotFound(name.to_owned()))?;
+        self.read(entry, budget)
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::ZipArchive;
+    use crate::utils::{
+        alloc_budget::AllocBudget, compression::error::CompressionError, get_model_path,
+    };
+
+    #[test]
+    fn test_read_3mf_archive() {
+        let data = std::fs::read(get_model_path("3MF", "box.3mf")).unwrap();
+        let archive = ZipArchive::new(&data).unwrap();
+        assert_eq!(archive.files().count(), 3);
+        let mut budget = AllocBudget::unlimited();
+        let model = archive
+            .read_by_name("3D\\3dmodel.model", &mut budget)
+            .unwrap();
+        assert_eq!(model.len(), 1273);
+        assert!(model.starts_with(b"<?xml"));
+        assert!(
+            archive
+                .read_by_name("3D/missing.model", &mut budget)
+                .is_err()
+        );
+
+        // the claimed size is taken from the budget before inflating
+        let mut budget = AllocBudget::new(Some(1000));
+        assert!(matches!(
+            archive.read_by_name("3D/3dmodel.model", &mut budget),
+            Err(CompressionError::OutputLimitExceeded(1000))
+        ));
+        assert_eq!(budget.remaining(), 1000);
+    }
+
+    #[test]
+    fn test_read_understated_size() {
+        // claim 100 bytes for the 1273 of the model in the central directory
+        let mut data = std::fs::read(get_model_path("3MF", "box.3mf")).unwrap();
+        let name = b"3D/3dmodel.model";
+        let header = (0..data.len())
+            .find(|&i| data[i..].starts_with(b"PK\x01\x02") && data[i + 46..].starts_with(name))
+            .unwrap();
+        data[header + 24..header + 28].copy_from_slice(&100u32.to_le_bytes());
+        let archive = ZipArchive::new(&data).unwrap();
+        let mut budget = AllocBudget::unlimited();
+        assert!(matches!(
+            archive.read_by_name("3D/3dmodel.model", &mut budget),
+            Err(CompressionError::OutputLimitExceeded(100))
+        ));
+    }
+}
@@ -56,95 +56,115 @@ pub fn strtoul10_64(
     return Ok((src, value, cnt));
 }
 
+/// Values written by the MSVC runtime for special floats, e.g. `1.#QNAN0`
+/// or `-1.#IND00`. The digits after them are padding.
+const MSVC_SPECIALS: [(&[u8], AiReal); 4] = [
+    (b"1.#QNAN", AiReal::NAN),
+    (b"1.#SNAN", AiReal::NAN),
+    (b"1.#IND", AiReal::NAN),
+    (b"1.#INF", AiReal::INFINITY),
+];
+
+/// Reads a real number from the start of `src` and returns the rest.
+///
+/// Besides decimal and exponent forms like `1`, `-.5`, `2.` or `1.5E-03`
+/// this accepts `nan`, `inf` and `infinity` in any case, the MSVC forms
+/// like `1.#QNAN0`, `-1.#INF00` or `1.#IND00`, and with `check_comma` a
+/// comma as decimal separator if a digit follows it, as in `0,5`.
 #[allow(unused)]
 pub fn fast_atoreal_move(src: &[u8], check_comma: bool) -> Result<(&[u8], AiReal), FastAtofError> {
-    // let mut f = 0.0;
-    // let (&maybe_sign_byte, rest) = src
-    //     .split_first()
-    //     .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    // let inv = maybe_sign_byte == b'-';
-    // if inv || maybe_sign_byte == b'+' {
-    //     src = rest;
-    // }
-    // let (bytes, rest) = src
-    //     .split_at_checked(3)
-    //     .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    // let bytes: &[u8; 3] = bytes.try_into().unwrap();
-    // if bytes.eq_ignore_ascii_case(b"nan") {
-    //     return Ok((rest, f64::NAN));
-    // } else if bytes.eq_ignore_ascii_case(b"inf") {
-    //     if let Some((_, _rest)) = rest.split_at_checked(5) {
-    //         let rest = if rest.eq_ignore_ascii_case(b"inity") {
-    //             _rest
-    //         } else {
-    //             rest
-    //         };
-    //         return Ok((
-    //             rest,
-    //             if inv {
-    //                 f64::NEG_INFINITY
-    //             } else {
-    //                 f64::INFINITY
-    //             },
-    //         ));
-    //     }
-    // }
-    // let (&byte, _) = src
-    //     .split_first()
-    //     .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    // if !byte.is_ascii_digit() {
-    //     return Err(FastAtofError::InvalidRealNumber(
-    //         String::from_utf8_lossy(src).into_owned(),
-    //     ));
-    // }
-    // if byte != b'.' && (!check_comma || byte != b',') {
-    //     let (rest, value, _) = strtoul10_64(src, None)?;
-    //     src = rest;
-    //     f = value as f64;
-    // }
-    // let (a, rest) = src
-    //     .split_at_checked(1)
-    //     .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    // let a = a[0];
-    // let (&b, _) = rest
-    //     .split_first()
-    //     .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    // if a == b'.' || (check_comma && a == b',') && b.is_ascii_digit() {
-    //     src = rest;
-    //     let (rest, value, diff) = strtoul10_64(src, Some(AI_FAST_ATOF_RELAVANT_DECIMALS))?;
-    //     src = rest;
-    //     f += (value as f64) * FAST_ATOF_TABLE[diff];
-    // } else if a == b'.' {
-    //     src = rest;
-    // }
-    // let (&b, rest) = src
-    //     .split_first()
-    //     .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    // if b.eq_ignore_ascii_case(&b'e') {
-    //     src = rest;
-    //     let (&b, rest) = src
-    //         .split_first()
-    //         .ok_or(FastAtofError::UnexpectedEndOfFile)?;
-    //     let e_inv = b == b'-';
-    //     if e_inv || b == b'+' {
-    //         src = rest;
-    //     }
-    //     let (rest, exp, _) = strtoul10_64(src, None)?;
-    //     src = rest;
-    //     f *= 10.0f64.powf(exp as f64);
-    // }
-    // if inv {
-    //     f = -f;
-    // }
-    // Ok((src, f))
-    match AiReal::fast_path_partial::<STANDARD>(src, const { &Options::new() }) {
-        Ok((f, offs)) => {
-            Ok((
-                // SAFETY: offs is checked to be within src by fast_path_partial
-                unsafe { src.get_unchecked(offs..) },
-                f,
-            ))
+    let (negative, unsigned) = match src {
+        [] => return Err(FastAtofError::UnexpectedEndOfFile),
+        [b'-', rest @ ..] => (true, rest),
+        [b'+', rest @ ..] => (false, rest),
+        _ => (false, src),
+    };
+    let sign = |value: AiReal| if negative { -value } else { value };
+    for (word, value) in [
+        (&b"infinity"[..], AiReal::INFINITY),
+        (b"inf", AiReal::INFINITY),
+        (b"nan", AiReal::NAN),
+    ] {
+        if unsigned
+            .get(..word.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(word))
+        {
+            return Ok((&unsigned[word.len()..], sign(value)));
+        }
+    }
+    for (prefix, value) in MSVC_SPECIALS {
+        if let Some(rest) = unsigned.strip_prefix(prefix) {
+            let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+            return Ok((&rest[digits..], sign(value)));
         }
+    }
+
+    let digits = unsigned.iter().take_while(|b| b.is_ascii_digit()).count();
+    if check_comma && let [b',', b'0'..=b'9', ..] = unsigned[digits..] {
+        // the literal has the same length with a decimal point
+        let end = src
+            .iter()
+            .position(|b| !matches!(b, b'0'..=b'9' | b'+' | b'-' | b'.' | b',' | b'e' | b'E'))
+            .unwrap_or(src.len());
+        let mut literal = src[..end].to_vec();
+        literal[src.len() - unsigned.len() + digits] = b'.';
+        let (value, offs) =
+            AiReal::fast_path_partial::<STANDARD>(&literal, const { &Options::new() })?;
+        return Ok((&src[offs..], value));
+    }
+    match AiReal::fast_path_partial::<STANDARD>(src, const { &Options::new() }) {
+        Ok((f, offs)) => Ok((src.get(offs..).unwrap_or_default(), f)),
         Err(e) => Err(FastAtofError::from(e)),
     }
 }
+
+#[allow(unused)]
+mod test {
+    use super::fast_atoreal_move;
+    use crate::AiReal;
+
+    #[test]
+    fn test_fast_atoreal_move() {
+        for (source, check_comma, value, rest) in [
+            ("1", false, 1.0, ""),
+            ("-2.5;", false, -2.5, ";"),
+            ("+.5 ", false, 0.5, " "),
+            ("2.,", false, 2.0, ","),
+            ("1e3", false, 1000.0, ""),
+            ("1.5E-03;", false, 0.0015, ";"),
+            ("-2.5e+2,", false, -250.0, ","),
+            ("inf;", false, AiReal::INFINITY, ";"),
+            ("-Infinity", false, AiReal::NEG_INFINITY, ""),
+            ("1.#INF00;", false, AiReal::INFINITY, ";"),
+            ("-1.#INF", false, AiReal::NEG_INFINITY, ""),
+            ("0,5;", true, 0.5, ";"),
+            ("-1,25e1,", true, -12.5, ","),
+            ("0,5;", false, 0.0, ",5;"),
+            ("1.5,5", true, 1.5, ",5"),
+            ("3, 4", true, 3.0, ", 4"),
+            (",5", true, 0.5, ""),
+        ] {
+            assert_eq!(
+                fast_atoreal_move(source.as_bytes(), check_comma).unwrap(),
+                (rest.as_bytes(), value),
+                "{source}"
+            );
+        }
+        for (source, rest) in [
+            ("NaN", ""),
+            ("-nan;", ";"),
+            ("1.#QNAN0;", ";"),
+            ("1.#SNAN", ""),
+            ("-1.#IND00,", ","),
+        ] {
+            let (r, value) = fast_atoreal_move(source.as_bytes(), false).unwrap();
+            assert!(value.is_nan() && r == rest.as_bytes(), "{source}");
+        }
+        for source in ["", "-", "x1", ";", ",5"] {
+            assert!(
+                fast_atoreal_move(source.as_bytes(), false).is_err(),
+                "{source}"
+            );
+        }
+    }
+}
@@ -5,12 +5,86 @@ mod precision {
         DMat3 as Mat3, DMat4 as Mat4, DQuat as Quat, DVec2 as Vec2, DVec3 as Vec3, DVec4 as Vec4,
     };
     pub const PRECISION: usize = 17;
+
+    /// Converts a single precision vector, e.g. of a color, to [`AiReal`]
+    #[inline]
+    pub fn real3(v: glam::Vec3) -> Vec3 {
+        v.as_dvec3()
+    }
+
+    /// Converts to single precision, for values stored as `f32` in any
+    /// build like colors or light parameters
+    #[inline]
+    pub fn to_f32(x: AiReal) -> f32 {
+        x as f32
+    }
 }
 #[cfg(not(feature = "double_precision"))]
 mod precision {
     pub type AiReal = f32;
     pub use glam::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
     pub const PRECISION: usize = 9;
+
+    /// Converts a single precision vector, e.g. of a color, to [`AiReal`]
+    #[inline]
+    pub fn real3(v: Vec3) -> Vec3 {
+        v
+    }
+
+    /// Converts to single precision, for values stored as `f32` in any
+    /// build like colors or light parameters
+    #[inline]
+    pub fn to_f32(x: AiReal) -> f32 {
+        x
+    }
 }
 
 pub use precision::*;
+
+/// Splits a transformation stored row by row, like
+/// [`AiNode::transformation`](crate::structs::scene::AiNode::transformation),
+/// into translation, rotation and scale. A negative determinant is
+/// expressed by a negative x scale. Shear cannot be expressed and is lost,
+/// a warning is logged for sheared matrices.
+pub fn decompose(matrix: Mat4) -> (Vec3, Quat, Vec3) {
+    let matrix = matrix.transpose();
+    let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+    let axes = [
+        matrix.x_axis.truncate(),
+        matrix.y_axis.truncate(),
+        matrix.z_axis.truncate(),
+    ];
+    if scale.cmpne(Vec3::ZERO).all() {
+        let [x, y, z] = [axes[0] / scale.x, axes[1] / scale.y, axes[2] / scale.z];
+        let shear = x.dot(y).abs().max(x.dot(z).abs()).max(y.dot(z).abs());
+        if shear > 1e-4 {
+            log::warn!("Transformation has a shear of {shear}, it is lost in the decomposition");
+        }
+    }
+    (translation, rotation, scale)
+}
+
+/// Builds a transformation stored row by row from translation, rotation
+/// and scale, the inverse of [`decompose`]
+pub fn compose(translation: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+    Mat4::from_scale_rotation_translation(scale, rotation, translation).transpose()
+}
+
+#[allow(unused)]
+mod test {
+    use super::{Quat, Vec3, compose, decompose};
+
+    #[test]
+    fn test_decompose_transformation() {
+        let translation = Vec3::new(1.0, -2.0, 3.0);
+        let rotation = Quat::from_euler(glam::EulerRot::XYZ, 0.3, -0.7, 1.1);
+        let scale = Vec3::new(2.0, 0.5, 3.0);
+        let matrix = compose(translation, rotation, scale);
+        // stored row by row, the translation is in the last column
+        assert_eq!(matrix.x_axis.w, 1.0);
+        let (t, r, s) = decompose(matrix);
+        assert!(t.abs_diff_eq(translation, 1e-5));
+        assert!(r.abs_diff_eq(rotation, 1e-5) || r.abs_diff_eq(-rotation, 1e-5));
+        assert!(s.abs_diff_eq(scale, 1e-5));
+    }
+}
@@ -0,0 +1,514 @@
+//! Reader of the binary dumps written by `assimp dump -b`, see
+//! `AssbinFileWriter.cpp` of the C++ library.
+
+#[cfg(feature = "compression")]
+use zlib_rs::MAX_WBITS;
+
+use super::{
+    DumpAnimation, DumpBone, DumpCamera, DumpChannel, DumpLight, DumpMaterial, DumpMesh, DumpNode,
+    DumpProperty, DumpScene, DumpValue, Faces, GoldenError, Samples,
+};
+#[cfg(feature = "compression")]
+use crate::utils::compression::Compression;
+
+/// Start of the text at the beginning of the header
+pub(super) const MAGIC: &[u8] = b"ASSIMP.binary-dump.";
+
+const HEADER_LENGTH: usize = 512;
+/// `ASSIMP_CFLAGS_DOUBLE_SUPPORT`, `ai_real` values are doubles
+const CFLAGS_DOUBLE_SUPPORT: u32 = 0x20;
+
+const CHUNK_CAMERA: u32 = 0x1234;
+const CHUNK_LIGHT: u32 = 0x1235;
+const CHUNK_TEXTURE: u32 = 0x1236;
+const CHUNK_MESH: u32 = 0x1237;
+const CHUNK_NODE_ANIM: u32 = 0x1238;
+const CHUNK_SCENE: u32 = 0x1239;
+const CHUNK_BONE: u32 = 0x123a;
+const CHUNK_ANIMATION: u32 = 0x123b;
+const CHUNK_NODE: u32 = 0x123c;
+const CHUNK_MATERIAL: u32 = 0x123d;
+const CHUNK_MATERIAL_PROPERTY: u32 = 0x123e;
+
+const MESH_HAS_POSITIONS: u32 = 0x1;
+const MESH_HAS_NORMALS: u32 = 0x2;
+const MESH_HAS_TANGENTS_AND_BITANGENTS: u32 = 0x4;
+const MESH_HAS_TEXCOORD_BASE: u32 = 0x100;
+const MESH_HAS_COLOR_BASE: u32 = 0x10000;
+const MAX_CHANNELS: u32 = 8;
+
+const LIGHT_DIRECTIONAL: u32 = 1;
+const LIGHT_SPOT: u32 = 3;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    shortened: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], GoldenError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or(GoldenError::UnexpectedEnd(self.offset))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], GoldenError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> Result<u16, GoldenError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, GoldenError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn count(&mut self) -> Result<usize, GoldenError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn f32(&mut self) -> Result<f64, GoldenError> {
+        Ok(f32::from_le_bytes(self.array()?).into())
+    }
+
+    fn f64(&mut self) -> Result<f64, GoldenError> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn floats<const N: usize>(&mut self) -> Result<[f64; N], GoldenError> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = self.f32()?;
+        }
+        Ok(values)
+    }
+
+    /// An `aiString`, its length and the characters
+    fn string(&mut self) -> Result<String, GoldenError> {
+        let len = self.count()?;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn matrix(&mut self) -> Result<[f64; 16], GoldenError> {
+        self.floats()
+    }
+
+    /// Reads the header of a chunk and returns its end
+    fn chunk(&mut self, expected: u32) -> Result<usize, GoldenError> {
+        let offset = self.offset;
+        let found = self.u32()?;
+        if found != expected {
+            return Err(GoldenError::UnexpectedChunk {
+                expected,
+                found,
+                offset,
+            });
+        }
+        let size = self.count()?;
+        Ok(self.offset + size)
+    }
+
+    /// `count` values, or their bounds in shortened dumps. `read` reads one
+    /// value.
+    fn samples<const N: usize>(
+        &mut self,
+        count: usize,
+        read: fn(&mut Self) -> Result<[f64; N], GoldenError>,
+    ) -> Result<Samples<N>, GoldenError> {
+        if self.shortened {
+            Ok(Samples::Bounds {
+                count,
+                min: read(self)?,
+                max: read(self)?,
+            })
+        } else {
+            // the count is not trusted for the allocation
+            let mut values = Vec::with_capacity(count.min(self.data.len() / 4));
+            for _ in 0..count {
+                values.push(read(self)?);
+            }
+            Ok(Samples::All(values))
+        }
+    }
+
+    fn node(&mut self) -> Result<DumpNode, GoldenError> {
+        let end = self.chunk(CHUNK_NODE)?;
+        let name = self.string()?;
+        let transformation = self.matrix()?;
+        let num_children = self.count()?;
+        let num_meshes = self.count()?;
+        let _num_metadata = self.count()?;
+        let meshes = (0..num_meshes)
+            .map(|_| self.u32())
+            .collect::<Result<_, _>>()?;
+        let children = (0..num_children)
+            .map(|_| self.node())
+            .collect::<Result<_, _>>()?;
+        // the metadata is not compared
+        self.offset = end;
+        Ok(DumpNode {
+            name,
+            transformation,
+            meshes,
+            children,
+        })
+    }
+
+    fn bone(&mut self) -> Result<DumpBone, GoldenError> {
+        let end = self.chunk(CHUNK_BONE)?;
+        let name = self.string()?;
+        let num_weights = self.count()?;
+        let offset_matrix = self.matrix()?;
+        let weights = if self.shortened {
+            // `aiVertexWeight` has no meaningful order, the bounds are skipped
+            None
+        } else {
+            Some(
+                (0..num_weights)
+                    .map(|_| Ok((self.u32()?, self.f32()?)))
+                    .collect::<Result<_, GoldenError>>()?,
+            )
+        };
+        self.offset = end;
+        Ok(DumpBone {
+            name,
+            offset_matrix,
+            num_weights,
+            weights,
+        })
+    }
+
+    fn mesh(&mut self) -> Result<DumpMesh, GoldenError> {
+        let end = self.chunk(CHUNK_MESH)?;
+        let primitive_types = self.u32()?;
+        let num_vertices = self.count()?;
+        let num_faces = self.count()?;
+        let num_bones = self.count()?;
+        let material_index = self.u32()?;
+        let components = self.u32()?;
+
+        let mut stream = |flag: u32| -> Result<Option<Samples<3>>, GoldenError> {
+            (components & flag != 0)
+                .then(|| self.samples(num_vertices, Self::floats))
+                .transpose()
+        };
+        let positions = stream(MESH_HAS_POSITIONS)?;
+        let normals = stream(MESH_HAS_NORMALS)?;
+        let tangents = stream(MESH_HAS_TANGENTS_AND_BITANGENTS)?;
+        let bitangents = stream(MESH_HAS_TANGENTS_AND_BITANGENTS)?;
+        let mut colors = Vec::new();
+        for n in 0..MAX_CHANNELS {
+            if components & (MESH_HAS_COLOR_BASE << n) == 0 {
+                break;
+            }
+            colors.push(self.samples(num_vertices, Self::floats)?);
+        }
+        let mut texture_coords = Vec::new();
+        for n in 0..MAX_CHANNELS {
+            if components & (MESH_HAS_TEXCOORD_BASE << n) == 0 {
+                break;
+            }
+            let num_components = self.u32()?;
+            texture_coords.push((num_components, self.samples(num_vertices, Self::floats)?));
+        }
+
+        let faces = if self.shortened {
+            let blocks = num_faces.div_ceil(512);
+            Faces::Hashes((0..blocks).map(|_| self.u32()).collect::<Result<_, _>>()?)
+        } else {
+            let mut faces = Vec::with_capacity(num_faces.min(self.data.len() / 2));
+            for _ in 0..num_faces {
+                let len = self.u16()?;
+                let face = (0..len)
+                    .map(|_| {
+                        if num_vertices < 1 << 16 {
+                            self.u16().map(u32::from)
+                        } else {
+                            self.u32()
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                faces.push(face);
+            }
+            Faces::Indices(faces)
+        };
+        let bones = (0..num_bones)
+            .map(|_| self.bone())
+            .collect::<Result<_, _>>()?;
+        self.offset = end;
+        Ok(DumpMesh {
+            name: None,
+            primitive_types,
+            material_index,
+            num_vertices,
+            num_faces,
+            positions,
+            normals,
+            tangents,
+            bitangents,
+            colors,
+            texture_coords,
+            faces,
+            bones,
+        })
+    }
+
+    fn material(&mut self) -> Result<DumpMaterial, GoldenError> {
+        let end = self.chunk(CHUNK_MATERIAL)?;
+        let num_properties = self.count()?;
+        let mut properties = Vec::new();
+        for _ in 0..num_properties {
+            let property_end = self.chunk(CHUNK_MATERIAL_PROPERTY)?;
+            let key = self.string()?;
+            let semantic = self.u32()?;
+            let index = self.u32()?;
+            let len = self.count()?;
+            let ty = self.u32()?;
+            let data = self.bytes(len)?;
+            let value = match ty {
+                // aiPTI_Float, aiPTI_Double and aiPTI_Integer
+                1 => DumpValue::Numbers(
+                    data.chunks_exact(4)
+                        .map(|b| f32::from_le_bytes(b.try_into().unwrap()).into())
+                        .collect(),
+                ),
+                2 => DumpValue::Numbers(
+                    data.chunks_exact(8)
+                        .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                        .collect(),
+                ),
+                4 => DumpValue::Numbers(
+                    data.chunks_exact(4)
+                        .map(|b| i32::from_le_bytes(b.try_into().unwrap()).into())
+                        .collect(),
+                ),
+                // aiPTI_String, stored as length, characters and terminator
+                3 => {
+                    let text = data.get(4..).unwrap_or_default();
+                    let text = text.strip_suffix(b"\0").unwrap_or(text);
+                    DumpValue::String(String::from_utf8_lossy(text).into_owned())
+                }
+                _ => DumpValue::Buffer(data.to_vec()),
+            };
+            properties.push(DumpProperty {
+                key,
+                semantic,
+                index,
+                value,
+            });
+            self.offset = property_end;
+        }
+        self.offset = end;
+        Ok(DumpMaterial { properties })
+    }
+
+    fn vector_key(&mut self) -> Result<[f64; 4], GoldenError> {
+        let time = self.f64()?;
+        let [x, y, z] = self.floats()?;
+        Ok([time, x, y, z])
+    }
+
+    fn quat_key(&mut self) -> Result<[f64; 5], GoldenError> {
+        let time = self.f64()?;
+        let [w, x, y, z] = self.floats()?;
+        Ok([time, w, x, y, z])
+    }
+
+    fn keys<const N: usize>(
+        &mut self,
+        count: usize,
+        read: fn(&mut Self) -> Result<[f64; N], GoldenError>,
+    ) -> Result<Samples<N>, GoldenError> {
+        // arrays without keys are not written at all
+        if count == 0 {
+            Ok(Samples::All(Vec::new()))
+        } else {
+            self.samples(count, read)
+        }
+    }
+
+    fn animation(&mut self) -> Result<DumpAnimation, GoldenError> {
+        let end = self.chunk(CHUNK_ANIMATION)?;
+        let name = self.string()?;
+        let duration = self.f64()?;
+        let ticks_per_second = self.f64()?;
+        let num_channels = self.count()?;
+        let mut channels = Vec::new();
+        for _ in 0..num_channels {
+            let channel_end = self.chunk(CHUNK_NODE_ANIM)?;
+            let node_name = self.string()?;
+            let num_position_keys = self.count()?;
+            let num_rotation_keys = self.count()?;
+            let num_scaling_keys = self.count()?;
+            let pre_state = self.u32()?;
+            let post_state = self.u32()?;
+            channels.push(DumpChannel {
+                node_name,
+                pre_state,
+                post_state,
+                position_keys: self.keys(num_position_keys, Self::vector_key)?,
+                rotation_keys: self.keys(num_rotation_keys, Self::quat_key)?,
+                scaling_keys: self.keys(num_scaling_keys, Self::vector_key)?,
+            });
+            self.offset = channel_end;
+        }
+        self.offset = end;
+        Ok(DumpAnimation {
+            name,
+            duration,
+            ticks_per_second,
+            channels,
+        })
+    }
+
+    fn light(&mut self) -> Result<DumpLight, GoldenError> {
+        let end = self.chunk(CHUNK_LIGHT)?;
+        let name = self.string()?;
+        let light_type = self.u32()?;
+        let position = Some(self.floats()?);
+        let direction = Some(self.floats()?);
+        let up = Some(self.floats()?);
+        let attenuation = if light_type != LIGHT_DIRECTIONAL {
+            Some(self.floats()?)
+        } else {
+            None
+        };
+        let diffuse = self.floats()?;
+        let specular = self.floats()?;
+        let ambient = self.floats()?;
+        let cone = if light_type == LIGHT_SPOT {
+            Some(self.floats()?)
+        } else {
+            None
+        };
+        self.offset = end;
+        Ok(DumpLight {
+            name,
+            light_type,
+            position,
+            direction,
+            up,
+            attenuation,
+            diffuse,
+            specular,
+            ambient,
+            cone,
+        })
+    }
+
+    fn camera(&mut self) -> Result<DumpCamera, GoldenError> {
+        let end = self.chunk(CHUNK_CAMERA)?;
+        let camera = DumpCamera {
+            name: self.string()?,
+            position: Some(self.floats()?),
+            look_at: self.floats()?,
+            up: self.floats()?,
+            horizontal_fov: self.f32()?,
+            clip_plane_near: self.f32()?,
+            clip_plane_far: self.f32()?,
+            aspect: self.f32()?,
+        };
+        self.offset = end;
+        Ok(camera)
+    }
+
+    fn scene(&mut self) -> Result<DumpScene, GoldenError> {
+        self.chunk(CHUNK_SCENE)?;
+        let flags = self.u32()?;
+        let num_meshes = self.count()?;
+        let num_materials = self.count()?;
+        let num_animations = self.count()?;
+        let num_textures = self.count()?;
+        let num_lights = self.count()?;
+        let num_cameras = self.count()?;
+        let root = Some(self.node()?);
+        let meshes = (0..num_meshes)
+            .map(|_| self.mesh())
+            .collect::<Result<_, _>>()?;
+        let materials = (0..num_materials)
+            .map(|_| self.material())
+            .collect::<Result<_, _>>()?;
+        let animations = (0..num_animations)
+            .map(|_| self.animation())
+            .collect::<Result<_, _>>()?;
+        for _ in 0..num_textures {
+            // only the number of textures is compared
+            self.offset = self.chunk(CHUNK_TEXTURE)?;
+        }
+        let lights = (0..num_lights)
+            .map(|_| self.light())
+            .collect::<Result<_, _>>()?;
+        let cameras = (0..num_cameras)
+            .map(|_| self.camera())
+            .collect::<Result<_, _>>()?;
+        Ok(DumpScene {
+            flags,
+            root,
+            meshes,
+            materials,
+            animations,
+            num_textures,
+            lights,
+            cameras,
+        })
+    }
+}
+
+pub(super) fn read(data: &[u8]) -> Result<DumpScene, GoldenError> {
+    let mut header = Reader {
+        data,
+        offset: 44,
+        shortened: false,
+    };
+    let _major = header.u32()?;
+    let _minor = header.u32()?;
+    let _revision = header.u32()?;
+    let compile_flags = header.u32()?;
+    let shortened = header.u16()? != 0;
+    let compressed = header.u16()? != 0;
+    if compile_flags & CFLAGS_DOUBLE_SUPPORT != 0 {
+        return Err(GoldenError::DoublePrecision);
+    }
+    let body = data
+        .get(HEADER_LENGTH..)
+        .ok_or(GoldenError::UnexpectedEnd(data.len()))?;
+
+    let decompressed;
+    let body = if compressed {
+        // the uncompressed size followed by a zlib stream
+        let stream = body
+            .get(4..)
+            .ok_or(GoldenError::UnexpectedEnd(HEADER_LENGTH))?;
+        decompressed = inflate(stream)?;
+        &decompressed[..]
+    } else {
+        body
+    };
+    let mut reader = Reader {
+        data: body,
+        offset: 0,
+        shortened,
+    };
+    reader.scene()
+}
+
+#[cfg(feature = "compression")]
+fn inflate(stream: &[u8]) -> Result<Vec<u8>, GoldenError> {
+    let mut output = Vec::new();
+    Compression::builder()
+        .window_bits(MAX_WBITS)
+        .open()
+        .and_then(|mut compression| compression.decompress(stream, &mut output))
+        .map_err(|_| GoldenError::Decompression)?;
+    Ok(output)
+}
+
+#[cfg(not(feature = "compression"))]
+fn inflate(_stream: &[u8]) -> Result<Vec<u8>, GoldenError> {
+    Err(GoldenError::Decompression)
+}
@@ -0,0 +1,597 @@
+//! Reader of the JSON dumps written by `assimp export <model> <model>.assjson`,
+//! see `json_exporter.cpp` of the C++ library.
+
+use super::{
+    DumpAnimation, DumpBone, DumpCamera, DumpChannel, DumpLight, DumpMaterial, DumpMesh, DumpNode,
+    DumpProperty, DumpScene, DumpValue, Faces, GoldenError, Samples,
+};
+
+const LIGHT_DIRECTIONAL: u32 = 1;
+const LIGHT_POINT: u32 = 2;
+const LIGHT_SPOT: u32 = 3;
+/// `aiPTI_String` and `aiPTI_Buffer`
+const PROPERTY_STRING: u32 = 3;
+const PROPERTY_BUFFER: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn member(&self, key: &'static str) -> Result<&Value, GoldenError> {
+        self.get(key).ok_or(GoldenError::InvalidMember(key))
+    }
+
+    /// Numbers, special values are written as strings by the exporter
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::String(text) => match text.as_str() {
+                "NaN" => Some(f64::NAN),
+                "Infinity" => Some(f64::INFINITY),
+                "-Infinity" => Some(f64::NEG_INFINITY),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Accessors of members which name the member in their errors
+struct Members<'a>(&'a Value);
+
+impl<'a> Members<'a> {
+    fn string(&self, key: &'static str) -> Result<String, GoldenError> {
+        match self.0.member(key)? {
+            Value::String(text) => Ok(text.clone()),
+            _ => Err(GoldenError::InvalidMember(key)),
+        }
+    }
+
+    fn number(&self, key: &'static str) -> Result<f64, GoldenError> {
+        self.0
+            .member(key)?
+            .as_f64()
+            .ok_or(GoldenError::InvalidMember(key))
+    }
+
+    fn unsigned(&self, key: &'static str) -> Result<u32, GoldenError> {
+        Ok(self.number(key)? as u32)
+    }
+
+    fn array(&self, key: &'static str) -> Result<&'a [Value], GoldenError> {
+        self.optional_array(key)?
+            .ok_or(GoldenError::InvalidMember(key))
+    }
+
+    fn optional_array(&self, key: &'static str) -> Result<Option<&'a [Value]>, GoldenError> {
+        self.0
+            .get(key)
+            .map(|value| value.as_array().ok_or(GoldenError::InvalidMember(key)))
+            .transpose()
+    }
+
+    fn floats(&self, key: &'static str) -> Result<Vec<f64>, GoldenError> {
+        numbers(self.array(key)?, key)
+    }
+
+    fn optional_floats<const N: usize>(
+        &self,
+        key: &'static str,
+    ) -> Result<Option<[f64; N]>, GoldenError> {
+        self.0.get(key).map(|value| fixed(value, key)).transpose()
+    }
+
+    fn fixed<const N: usize>(&self, key: &'static str) -> Result<[f64; N], GoldenError> {
+        fixed(self.0.member(key)?, key)
+    }
+}
+
+fn numbers(values: &[Value], key: &'static str) -> Result<Vec<f64>, GoldenError> {
+    values
+        .iter()
+        .map(|value| value.as_f64().ok_or(GoldenError::InvalidMember(key)))
+        .collect()
+}
+
+fn fixed<const N: usize>(value: &Value, key: &'static str) -> Result<[f64; N], GoldenError> {
+    let values = numbers(value.as_array().unwrap_or_default(), key)?;
+    values
+        .try_into()
+        .map_err(|_| GoldenError::InvalidMember(key))
+}
+
+/// Groups a flat array into vectors of `stride` values, padded with zeros to
+/// `N` values
+fn vectors<const N: usize>(
+    values: &[f64],
+    stride: usize,
+    key: &'static str,
+) -> Result<Vec<[f64; N]>, GoldenError> {
+    if stride == 0 || stride > N || !values.len().is_multiple_of(stride) {
+        return Err(GoldenError::InvalidMember(key));
+    }
+    Ok(values
+        .chunks_exact(stride)
+        .map(|chunk| {
+            let mut vector = [0.0; N];
+            vector[..stride].copy_from_slice(chunk);
+            vector
+        })
+        .collect())
+}
+
+fn node(value: &Value) -> Result<DumpNode, GoldenError> {
+    let members = Members(value);
+    let meshes = numbers(
+        members.optional_array("meshes")?.unwrap_or_default(),
+        "meshes",
+    )?;
+    Ok(DumpNode {
+        name: members.string("name")?,
+        transformation: members.fixed("transformation")?,
+        meshes: meshes.into_iter().map(|index| index as u32).collect(),
+        children: members
+            .optional_array("children")?
+            .unwrap_or_default()
+            .iter()
+            .map(node)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn mesh(value: &Value) -> Result<DumpMesh, GoldenError> {
+    let members = Members(value);
+    let positions = vectors(&members.floats("vertices")?, 3, "vertices")?;
+    let num_vertices = positions.len();
+    let stream = |key: &'static str| -> Result<Option<Samples<3>>, GoldenError> {
+        members
+            .optional_array(key)?
+            .map(|values| Ok(Samples::All(vectors(&numbers(values, key)?, 3, key)?)))
+            .transpose()
+    };
+
+    let components = numbers(
+        members
+            .optional_array("numuvcomponents")?
+            .unwrap_or_default(),
+        "numuvcomponents",
+    )?;
+    let mut texture_coords = Vec::new();
+    for (n, channel) in members
+        .optional_array("texturecoords")?
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        let num_components = components.get(n).map_or(0, |&c| c as u32);
+        // the exporter writes two components for channels without a count
+        let stride = if num_components == 0 {
+            2
+        } else {
+            num_components
+        } as usize;
+        let values = numbers(channel.as_array().unwrap_or_default(), "texturecoords")?;
+        texture_coords.push((
+            num_components,
+            Samples::All(vectors(&values, stride, "texturecoords")?),
+        ));
+    }
+    let colors = members
+        .optional_array("colors")?
+        .unwrap_or_default()
+        .iter()
+        .map(|set| {
+            let values = numbers(set.as_array().unwrap_or_default(), "colors")?;
+            Ok(Samples::All(vectors(&values, 4, "colors")?))
+        })
+        .collect::<Result<_, GoldenError>>()?;
+
+    let faces: Vec<Vec<u32>> = members
+        .optional_array("faces")?
+        .unwrap_or_default()
+        .iter()
+        .map(|face| {
+            let indices = numbers(face.as_array().unwrap_or_default(), "faces")?;
+            Ok(indices.into_iter().map(|index| index as u32).collect())
+        })
+        .collect::<Result<_, GoldenError>>()?;
+
+    let bones = members
+        .optional_array("bones")?
+        .unwrap_or_default()
+        .iter()
+        .map(|bone| {
+            let members = Members(bone);
+            let weights: Vec<(u32, f64)> = members
+                .array("weights")?
+                .iter()
+                .map(|weight| {
+                    let [id, weight] = fixed(weight, "weights")?;
+                    Ok((id as u32, weight))
+                })
+                .collect::<Result<_, GoldenError>>()?;
+            Ok(DumpBone {
+                name: members.string("name")?,
+                offset_matrix: members.fixed("offsetmatrix")?,
+                num_weights: weights.len(),
+                weights: Some(weights),
+            })
+        })
+        .collect::<Result<_, GoldenError>>()?;
+
+    Ok(DumpMesh {
+        name: Some(members.string("name")?),
+        primitive_types: members.unsigned("primitivetypes")?,
+        material_index: members.unsigned("materialindex")?,
+        num_vertices,
+        num_faces: faces.len(),
+        positions: Some(Samples::All(positions)),
+        normals: stream("normals")?,
+        tangents: stream("tangents")?,
+        bitangents: stream("bitangents")?,
+        colors,
+        texture_coords,
+        faces: Faces::Indices(faces),
+        bones,
+    })
+}
+
+fn material(value: &Value) -> Result<DumpMaterial, GoldenError> {
+    let properties = Members(value)
+        .array("properties")?
+        .iter()
+        .map(|property| {
+            let members = Members(property);
+            let ty = members.unsigned("type")?;
+            let value = match (ty, members.0.member("value")?) {
+                (PROPERTY_STRING, Value::String(text)) => DumpValue::String(text.clone()),
+                (PROPERTY_BUFFER, Value::String(hex)) => DumpValue::Buffer(
+                    hex.as_bytes()
+                        .chunks(2)
+                        .map(|digits| {
+                            core::str::from_utf8(digits)
+                                .ok()
+                                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                                .ok_or(GoldenError::InvalidMember("value"))
+                        })
+                        .collect::<Result<_, _>>()?,
+                ),
+                (_, Value::Array(values)) => DumpValue::Numbers(numbers(values, "value")?),
+                (_, value) => DumpValue::Numbers(vec![
+                    value.as_f64().ok_or(GoldenError::InvalidMember("value"))?,
+                ]),
+            };
+            Ok(DumpProperty {
+                key: members.string("key")?,
+                semantic: members.unsigned("semantic")?,
+                index: members.unsigned("index")?,
+                value,
+            })
+        })
+        .collect::<Result<_, GoldenError>>()?;
+    Ok(DumpMaterial { properties })
+}
+
+/// Keys written as `[time, [values]]`
+fn keys<const N: usize>(members: &Members, key: &'static str) -> Result<Samples<N>, GoldenError> {
+    let keys = members
+        .optional_array(key)?
+        .unwrap_or_default()
+        .iter()
+        .map(|k| match k.as_array() {
+            Some([time, values]) => {
+                let values: [f64; N] = {
+                    let mut all = vec![time.as_f64().ok_or(GoldenError::InvalidMember(key))?];
+                    all.extend(numbers(values.as_array().unwrap_or_default(), key)?);
+                    all.try_into()
+                        .map_err(|_| GoldenError::InvalidMember(key))?
+                };
+                Ok(values)
+            }
+            _ => Err(GoldenError::InvalidMember(key)),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Samples::All(keys))
+}
+
+fn animation(value: &Value) -> Result<DumpAnimation, GoldenError> {
+    let members = Members(value);
+    let channels = members
+        .array("channels")?
+        .iter()
+        .map(|channel| {
+            let members = Members(channel);
+            Ok(DumpChannel {
+                node_name: members.string("name")?,
+                pre_state: members.unsigned("prestate")?,
+                post_state: members.unsigned("poststate")?,
+                position_keys: keys(&members, "positionkeys")?,
+                rotation_keys: keys(&members, "rotationkeys")?,
+                scaling_keys: keys(&members, "scalingkeys")?,
+            })
+        })
+        .collect::<Result<_, GoldenError>>()?;
+    Ok(DumpAnimation {
+        name: members.string("name")?,
+        duration: members.number("duration")?,
+        ticks_per_second: members.number("tickspersecond")?,
+        channels,
+    })
+}
+
+fn light(value: &Value) -> Result<DumpLight, GoldenError> {
+    let members = Members(value);
+    let light_type = members.unsigned("type")?;
+    let attenuation = if light_type != LIGHT_DIRECTIONAL {
+        Some([
+            members.number("attenuationconstant")?,
+            members.number("attenuationlinear")?,
+            members.number("attenuationquadratic")?,
+        ])
+    } else {
+        None
+    };
+    let cone = if light_type == LIGHT_SPOT {
+        Some([
+            members.number("angleinnercone")?,
+            members.number("angleoutercone")?,
+        ])
+    } else {
+        None
+    };
+    Ok(DumpLight {
+        name: members.string("name")?,
+        light_type,
+        position: if light_type != LIGHT_DIRECTIONAL {
+            members.optional_floats("position")?
+        } else {
+            None
+        },
+        direction: if light_type != LIGHT_POINT {
+            members.optional_floats("direction")?
+        } else {
+            None
+        },
+        up: members.optional_floats("up")?,
+        attenuation,
+        diffuse: members.fixed("diffusecolor")?,
+        specular: members.fixed("specularcolor")?,
+        ambient: members.fixed("ambientcolor")?,
+        cone,
+    })
+}
+
+fn camera(value: &Value) -> Result<DumpCamera, GoldenError> {
+    let members = Members(value);
+    Ok(DumpCamera {
+        name: members.string("name")?,
+        position: members.optional_floats("position")?,
+        look_at: members.fixed("lookat")?,
+        up: members.fixed("up")?,
+        horizontal_fov: members.number("horizontalfov")?,
+        clip_plane_near: members.number("clipplanenear")?,
+        clip_plane_far: members.number("clipplanefar")?,
+        aspect: members.number("aspect")?,
+    })
+}
+
+fn list<T>(
+    members: &Members,
+    key: &'static str,
+    read: fn(&Value) -> Result<T, GoldenError>,
+) -> Result<Vec<T>, GoldenError> {
+    members
+        .optional_array(key)?
+        .unwrap_or_default()
+        .iter()
+        .map(read)
+        .collect()
+}
+
+pub(super) fn read(data: &[u8]) -> Result<DumpScene, GoldenError> {
+    let value = JsonParser { data, offset: 0 }.document()?;
+    let members = Members(&value);
+    Ok(DumpScene {
+        flags: members.unsigned("flags")?,
+        root: Some(node(members.0.member("rootnode")?)?),
+        meshes: list(&members, "meshes", mesh)?,
+        materials: list(&members, "materials", material)?,
+        animations: list(&members, "animations", animation)?,
+        num_textures: members
+            .optional_array("textures")?
+            .map_or(0, |textures| textures.len()),
+        lights: list(&members, "lights", light)?,
+        cameras: list(&members, "cameras", camera)?,
+    })
+}
+
+/// Just enough JSON for the dumps
+struct JsonParser<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, reason: &'static str) -> GoldenError {
+        GoldenError::InvalidJson {
+            offset: self.offset,
+            reason,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .data
+            .get(self.offset)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.data.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, byte: u8, reason: &'static str) -> Result<(), GoldenError> {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.error(reason))
+        }
+    }
+
+    fn document(mut self) -> Result<Value, GoldenError> {
+        let value = self.value()?;
+        if self.peek().is_some() {
+            return Err(self.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, GoldenError> {
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.literal(b"true", Value::Bool(true)),
+            Some(b'f') => self.literal(b"false", Value::Bool(false)),
+            Some(b'n') => self.literal(b"null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn literal(&mut self, text: &[u8], value: Value) -> Result<Value, GoldenError> {
+        if self.data[self.offset..].starts_with(text) {
+            self.offset += text.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, GoldenError> {
+        let start = self.offset;
+        while self
+            .data
+            .get(self.offset)
+            .is_some_and(|&b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+        {
+            self.offset += 1;
+        }
+        core::str::from_utf8(&self.data[start..self.offset])
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .map(Value::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, GoldenError> {
+        self.expect(b'"', "expected a string")?;
+        let mut text = Vec::new();
+        loop {
+            let Some(&byte) = self.data.get(self.offset) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.offset += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.data.get(self.offset) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.offset += 1;
+                    match escape {
+                        b'n' => text.push(b'\n'),
+                        b't' => text.push(b'\t'),
+                        b'r' => text.push(b'\r'),
+                        b'b' => text.push(0x08),
+                        b'f' => text.push(0x0c),
+                        b'u' => {
+                            let code = self
+                                .data
+                                .get(self.offset..self.offset + 4)
+                                .and_then(|digits| core::str::from_utf8(digits).ok())
+                                .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                                .ok_or_else(|| self.error("invalid escape"))?;
+                            self.offset += 4;
+                            let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+                            text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => text.push(other),
+                    }
+                }
+                other => text.push(other),
+            }
+        }
+        Ok(String::from_utf8_lossy(&text).into_owned())
+    }
+
+    fn array(&mut self) -> Result<Value, GoldenError> {
+        self.expect(b'[', "expected an array")?;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => {
+                    self.offset += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("expected , or ]")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, GoldenError> {
+        self.expect(b'{', "expected an object")?;
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':', "expected :")?;
+            members.push((key, self.value()?));
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b'}') => {
+                    self.offset += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(self.error("expected , or }")),
+            }
+        }
+    }
+}
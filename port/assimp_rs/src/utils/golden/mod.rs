@@ -0,0 +1,1057 @@
+//! Regression tests against scene dumps of the C++ library.
+//!
+//! `assimp dump <model> <model>.assbin -b [-s] [-z]` and `assimp export <model>
+//! <model>.assjson` write the scene imported by assimp, these dumps are read
+//! into a [`DumpScene`] and compared structurally with the [`AiScene`] of
+//! this crate by [`compare`]. Floats are compared with a [`Tolerance`].
+//!
+//! Shortened assbin dumps, like the ones of the regression suite in
+//! `test/regression`, only store the bounds of vertex data and hashes of the
+//! faces, both are compared as well.
+
+mod assbin;
+mod assjson;
+
+use core::fmt;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+pub use crate::structs::diff::Tolerance;
+
+use crate::{
+    AiReal,
+    structs::{
+        diff::property_numbers,
+        material::{AiMaterialProperty, AiProperty},
+        nodes::Index,
+        scene::{AiNode, AiScene, AiSceneFlags},
+    },
+    utils::{float_precision::Mat4, get_model_path},
+};
+
+#[derive(Debug, Error)]
+pub enum GoldenError {
+    #[error("Failed to read the dump: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unknown dump format")]
+    UnknownFormat,
+
+    #[error("Unexpected end of the dump at offset {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Expected chunk {expected:#x} at offset {offset} but found {found:#x}")]
+    UnexpectedChunk {
+        expected: u32,
+        found: u32,
+        offset: usize,
+    },
+
+    #[error("Dumps of double precision builds are not supported")]
+    DoublePrecision,
+
+    #[error("Failed to decompress the dump")]
+    Decompression,
+
+    #[error("Invalid JSON at offset {offset}: {reason}")]
+    InvalidJson { offset: usize, reason: &'static str },
+
+    #[error("Missing or invalid member {0} in the JSON dump")]
+    InvalidMember(&'static str),
+}
+
+/// Data of a vertex or key array, shortened dumps only store its bounds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Samples<const N: usize> {
+    All(Vec<[f64; N]>),
+    /// The smallest and largest element by the `operator<` of assimp, which
+    /// orders vectors lexicographically and keys by time
+    Bounds {
+        count: usize,
+        min: [f64; N],
+        max: [f64; N],
+    },
+}
+
+impl<const N: usize> Samples<N> {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::All(values) => values.len(),
+            Self::Bounds { count, .. } => *count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Faces of a mesh, shortened dumps store a hash per block of 512 faces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Faces {
+    Indices(Vec<Vec<u32>>),
+    Hashes(Vec<u32>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DumpNode {
+    pub name: String,
+    /// Row by row like `aiMatrix4x4`
+    pub transformation: [f64; 16],
+    pub meshes: Vec<u32>,
+    pub children: Vec<DumpNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpBone {
+    pub name: String,
+    pub offset_matrix: [f64; 16],
+    pub num_weights: usize,
+    /// Vertex index and weight, not stored in shortened dumps
+    pub weights: Option<Vec<(u32, f64)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpMesh {
+    /// Not stored in assbin dumps
+    pub name: Option<String>,
+    pub primitive_types: u32,
+    pub material_index: u32,
+    pub num_vertices: usize,
+    pub num_faces: usize,
+    pub positions: Option<Samples<3>>,
+    pub normals: Option<Samples<3>>,
+    pub tangents: Option<Samples<3>>,
+    pub bitangents: Option<Samples<3>>,
+    pub colors: Vec<Samples<4>>,
+    /// Number of components and coordinates of each channel
+    pub texture_coords: Vec<(u32, Samples<3>)>,
+    pub faces: Faces,
+    pub bones: Vec<DumpBone>,
+}
+
+/// Value of a material property by its `aiPropertyTypeInfo`
+#[derive(Debug, Clone, PartialEq)]
+pub enum DumpValue {
+    Numbers(Vec<f64>),
+    String(String),
+    Buffer(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpProperty {
+    pub key: String,
+    pub semantic: u32,
+    pub index: u32,
+    pub value: DumpValue,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DumpMaterial {
+    pub properties: Vec<DumpProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpChannel {
+    pub node_name: String,
+    pub pre_state: u32,
+    pub post_state: u32,
+    /// Time and value of each key
+    pub position_keys: Samples<4>,
+    /// Time and `w`, `x`, `y`, `z` of each key
+    pub rotation_keys: Samples<5>,
+    pub scaling_keys: Samples<4>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpAnimation {
+    pub name: String,
+    pub duration: f64,
+    pub ticks_per_second: f64,
+    pub channels: Vec<DumpChannel>,
+}
+
+/// A light, the members left out by the dump format or for the type of the
+/// light are `None`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DumpLight {
+    pub name: String,
+    pub light_type: u32,
+    pub position: Option<[f64; 3]>,
+    pub direction: Option<[f64; 3]>,
+    pub up: Option<[f64; 3]>,
+    /// Constant, linear and quadratic
+    pub attenuation: Option<[f64; 3]>,
+    pub diffuse: [f64; 3],
+    pub specular: [f64; 3],
+    pub ambient: [f64; 3],
+    /// Inner and outer angle
+    pub cone: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DumpCamera {
+    pub name: String,
+    /// Not stored in assjson dumps
+    pub position: Option<[f64; 3]>,
+    pub look_at: [f64; 3],
+    pub up: [f64; 3],
+    pub horizontal_fov: f64,
+    pub clip_plane_near: f64,
+    pub clip_plane_far: f64,
+    pub aspect: f64,
+}
+
+/// A scene read from a dump of the C++ library
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DumpScene {
+    pub flags: u32,
+    pub root: Option<DumpNode>,
+    pub meshes: Vec<DumpMesh>,
+    pub materials: Vec<DumpMaterial>,
+    pub animations: Vec<DumpAnimation>,
+    pub num_textures: usize,
+    pub lights: Vec<DumpLight>,
+    pub cameras: Vec<DumpCamera>,
+}
+
+impl DumpScene {
+    /// Reads an assbin or assjson dump, the format is detected from the
+    /// content
+    pub fn from_bytes(data: &[u8]) -> Result<Self, GoldenError> {
+        if data.starts_with(assbin::MAGIC) {
+            assbin::read(data)
+        } else if data.trim_ascii_start().starts_with(b"{") {
+            assjson::read(data)
+        } else {
+            Err(GoldenError::UnknownFormat)
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, GoldenError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// The dump of a test model, `test/models/<format>/ref/golden/<model>` with
+/// the extension `.assbin` or `.assjson`
+pub fn golden_path(model_format: &str, model_name: &str) -> Option<PathBuf> {
+    let directory = get_model_path(model_format, "ref").join("golden");
+    ["assbin", "assjson"]
+        .into_iter()
+        .map(|extension| directory.join(format!("{model_name}.{extension}")))
+        .find(|path| path.is_file())
+}
+
+/// A difference between a scene and its dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Location in the scene like `meshes[0].normals[3]`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+struct Comparison<'a> {
+    tolerance: &'a Tolerance,
+    mismatches: Vec<Mismatch>,
+}
+
+impl Comparison<'_> {
+    fn report(&mut self, path: impl fmt::Display, message: String) {
+        self.mismatches.push(Mismatch {
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn exact<T: PartialEq + fmt::Debug>(
+        &mut self,
+        path: impl fmt::Display,
+        actual: T,
+        expected: T,
+    ) -> bool {
+        let equal = actual == expected;
+        if !equal {
+            self.report(path, format!("{actual:?} != {expected:?}"));
+        }
+        equal
+    }
+
+    fn floats(&mut self, path: impl fmt::Display, actual: &[f64], expected: &[f64]) {
+        if !self.tolerance.eq_all(actual, expected) {
+            self.report(path, format!("{actual:?} != {expected:?}"));
+        }
+    }
+
+    fn samples<const N: usize>(
+        &mut self,
+        path: impl fmt::Display,
+        actual: &[[f64; N]],
+        expected: &Samples<N>,
+        less: fn(&[f64; N], &[f64; N]) -> bool,
+    ) {
+        if !self.exact(format_args!("{path}.len"), actual.len(), expected.len()) {
+            return;
+        }
+        match expected {
+            Samples::All(values) => {
+                for (i, (actual, expected)) in actual.iter().zip(values).enumerate() {
+                    if !self.tolerance.eq_all(actual, expected) {
+                        self.report(
+                            format_args!("{path}[{i}]"),
+                            format!("{actual:?} != {expected:?}"),
+                        );
+                        // one difference often shifts all following values
+                        return;
+                    }
+                }
+            }
+            Samples::Bounds { count: 0, .. } => {}
+            Samples::Bounds { min, max, .. } => {
+                let (actual_min, actual_max) = bounds(actual, less);
+                self.floats(format_args!("{path}.min"), &actual_min, min);
+                self.floats(format_args!("{path}.max"), &actual_max, max);
+            }
+        }
+    }
+}
+
+/// `aiVector3t::operator<` and friends
+fn lexicographic_less<const N: usize>(a: &[f64; N], b: &[f64; N]) -> bool {
+    a.iter()
+        .zip(b)
+        .find(|(a, b)| a != b)
+        .is_some_and(|(a, b)| a < b)
+}
+
+/// `aiVectorKey::operator<` and `aiQuatKey::operator<`
+fn time_less<const N: usize>(a: &[f64; N], b: &[f64; N]) -> bool {
+    a[0] < b[0]
+}
+
+/// The bounds like `ArrayBounds` of assimp computes them, ties are resolved
+/// the same way
+fn bounds<const N: usize>(
+    values: &[[f64; N]],
+    less: fn(&[f64; N], &[f64; N]) -> bool,
+) -> ([f64; N], [f64; N]) {
+    let (mut min, mut max) = ([1e10; N], [-1e10; N]);
+    for value in values {
+        // std::min(value, min) and std::max(value, max)
+        if !less(&min, value) {
+            min = *value;
+        }
+        if !less(value, &max) {
+            max = *value;
+        }
+    }
+    (min, max)
+}
+
+/// `SuperFastHash` of assimp, used for the faces of shortened dumps
+pub(crate) fn super_fast_hash(data: &[u8], mut hash: u32) -> u32 {
+    let get16 = |data: &[u8]| u32::from(u16::from_le_bytes([data[0], data[1]]));
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        hash = hash.wrapping_add(get16(chunk));
+        let tmp = (get16(&chunk[2..]) << 11) ^ hash;
+        hash = (hash << 16) ^ tmp;
+        hash = hash.wrapping_add(hash >> 11);
+    }
+    // the bytes are signed chars in C++
+    let signed = |b: u8| b as i8 as i32 as u32;
+    match *chunks.remainder() {
+        [a, b, c] => {
+            hash = hash.wrapping_add(get16(&[a, b]));
+            hash ^= hash << 16;
+            hash ^= u32::from((c as i8).unsigned_abs()) << 18;
+            hash = hash.wrapping_add(hash >> 11);
+        }
+        [a, b] => {
+            hash = hash.wrapping_add(get16(&[a, b]));
+            hash ^= hash << 11;
+            hash = hash.wrapping_add(hash >> 17);
+        }
+        [a] => {
+            hash = hash.wrapping_add(signed(a));
+            hash ^= hash << 10;
+            hash = hash.wrapping_add(hash >> 1);
+        }
+        _ => {}
+    }
+    hash ^= hash << 3;
+    hash = hash.wrapping_add(hash >> 5);
+    hash ^= hash << 4;
+    hash = hash.wrapping_add(hash >> 17);
+    hash ^= hash << 25;
+    hash = hash.wrapping_add(hash >> 6);
+    hash
+}
+
+/// The hashes of a shortened dump, one per block of 512 faces
+fn face_hashes(faces: impl ExactSizeIterator<Item = impl AsRef<[u32]>>) -> Vec<u32> {
+    let faces: Vec<_> = faces.collect();
+    faces
+        .chunks(512)
+        .map(|block| {
+            block.iter().fold(0, |hash, face| {
+                let face = face.as_ref();
+                let hash = super_fast_hash(&(face.len() as u32).to_le_bytes(), hash);
+                face.iter().fold(hash, |hash, index| {
+                    super_fast_hash(&index.to_le_bytes(), hash)
+                })
+            })
+        })
+        .collect()
+}
+
+/// The matrix row by row like `aiMatrix4x4`
+fn matrix_rows(matrix: &Mat4) -> [f64; 16] {
+    let rows = matrix.transpose().to_cols_array();
+    rows.map(|value| value as f64)
+}
+
+fn f64s<T: Into<f64>, const N: usize>(values: [T; N]) -> [f64; N] {
+    values.map(Into::into)
+}
+
+/// Compares `scene` with the dump of the C++ library, an empty result means
+/// the scenes match
+pub fn compare(scene: &AiScene, dump: &DumpScene, tolerance: &Tolerance) -> Vec<Mismatch> {
+    let mut c = Comparison {
+        tolerance,
+        mismatches: Vec::new(),
+    };
+    c.exact(
+        "flags.incomplete",
+        scene.flags.contains(AiSceneFlags::INCOMPLETE),
+        dump.flags & AiSceneFlags::INCOMPLETE.bits() != 0,
+    );
+    match (scene.root, &dump.root) {
+        (Some(root), Some(expected)) => compare_node(&mut c, scene, root, expected, "root"),
+        (None, None) => {}
+        (actual, expected) => {
+            c.report(
+                "root",
+                format!("{} != {}", actual.is_some(), expected.is_some()),
+            );
+        }
+    }
+
+    if c.exact("meshes.len", scene.meshes.len(), dump.meshes.len()) {
+        for (i, (mesh, expected)) in scene.meshes.iter().zip(&dump.meshes).enumerate() {
+            compare_mesh(&mut c, mesh, expected, &format!("meshes[{i}]"));
+        }
+    }
+    if c.exact("materials.len", scene.materials.len(), dump.materials.len()) {
+        for (i, (material, expected)) in scene.materials.iter().zip(&dump.materials).enumerate() {
+            compare_material(
+                &mut c,
+                &material.properties,
+                expected,
+                &format!("materials[{i}]"),
+            );
+        }
+    }
+    if c.exact(
+        "animations.len",
+        scene.animations.len(),
+        dump.animations.len(),
+    ) {
+        for (i, (animation, expected)) in scene.animations.iter().zip(&dump.animations).enumerate()
+        {
+            let path = format!("animations[{i}]");
+            c.exact(
+                format_args!("{path}.name"),
+                &*animation.name,
+                &expected.name,
+            );
+            c.floats(
+                format_args!("{path}.duration"),
+                &[animation.duration, animation.ticks_per_second],
+                &[expected.duration, expected.ticks_per_second],
+            );
+            if !c.exact(
+                format_args!("{path}.channels.len"),
+                animation.channels.len(),
+                expected.channels.len(),
+            ) {
+                continue;
+            }
+            for (j, (channel, expected)) in animation
+                .channels
+                .iter()
+                .zip(&expected.channels)
+                .enumerate()
+            {
+                let path = format!("{path}.channels[{j}]");
+                c.exact(
+                    format_args!("{path}.node_name"),
+                    &*channel.node_name,
+                    &expected.node_name,
+                );
+                c.exact(
+                    format_args!("{path}.states"),
+                    [channel.pre_state as u32, channel.post_state as u32],
+                    [expected.pre_state, expected.post_state],
+                );
+                let vector_keys = |keys: &[crate::structs::key::AiVectorKey]| -> Vec<[f64; 4]> {
+                    keys.iter()
+                        .map(|key| {
+                            let [x, y, z] = f64s(key.value.to_array());
+                            [key.time, x, y, z]
+                        })
+                        .collect()
+                };
+                // `q` and `-q` are the same rotation, the sign of the keys
+                // depends on how they were converted
+                let rotation_keys: Vec<_> = channel
+                    .rotation_keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, key)| {
+                        let [x, y, z, w] = f64s(key.value.to_array());
+                        let flip = match &expected.rotation_keys {
+                            Samples::All(keys) => {
+                                keys.get(i).is_some_and(|&[_, ew, ex, ey, ez]| {
+                                    w * ew + x * ex + y * ey + z * ez < 0.0
+                                })
+                            }
+                            Samples::Bounds { .. } => false,
+                        };
+                        let sign = if flip { -1.0 } else { 1.0 };
+                        [key.time, sign * w, sign * x, sign * y, sign * z]
+                    })
+                    .collect();
+                c.samples(
+                    format_args!("{path}.position_keys"),
+                    &vector_keys(&channel.position_keys),
+                    &expected.position_keys,
+                    time_less,
+                );
+                c.samples(
+                    format_args!("{path}.rotation_keys"),
+                    &rotation_keys,
+                    &expected.rotation_keys,
+                    time_less,
+                );
+                c.samples(
+                    format_args!("{path}.scaling_keys"),
+                    &vector_keys(&channel.scaling_keys),
+                    &expected.scaling_keys,
+                    time_less,
+                );
+            }
+        }
+    }
+    c.exact("textures.len", scene.textures.len(), dump.num_textures);
+
+    if c.exact("lights.len", scene.lights.len(), dump.lights.len()) {
+        for (i, (light, expected)) in scene.lights.iter().zip(&dump.lights).enumerate() {
+            let path = format!("lights[{i}]");
+            c.exact(format_args!("{path}.name"), &*light.name, &expected.name);
+            c.exact(
+                format_args!("{path}.type"),
+                light.light_type.clone() as u32,
+                expected.light_type,
+            );
+            let vectors = [
+                ("position", light.position, expected.position),
+                ("direction", light.direction, expected.direction),
+                ("up", light.up, expected.up),
+            ];
+            for (name, actual, expected) in vectors {
+                if let Some(expected) = expected {
+                    c.floats(
+                        format_args!("{path}.{name}"),
+                        &f64s(actual.to_array()),
+                        &expected,
+                    );
+                }
+            }
+            if let Some(expected) = expected.attenuation {
+                c.floats(
+                    format_args!("{path}.attenuation"),
+                    &[
+                        light.attenuation_constant,
+                        light.attenuation_linear,
+                        light.attenuation_quadratic,
+                    ]
+                    .map(f64::from),
+                    &expected,
+                );
+            }
+            let colors = [
+                ("diffuse", light.color_diffuse, expected.diffuse),
+                ("specular", light.color_specular, expected.specular),
+                ("ambient", light.color_ambient, expected.ambient),
+            ];
+            for (name, actual, expected) in colors {
+                c.floats(
+                    format_args!("{path}.{name}"),
+                    &f64s(actual.to_array()),
+                    &expected,
+                );
+            }
+            if let Some(expected) = expected.cone {
+                c.floats(
+                    format_args!("{path}.cone"),
+                    &[light.angle_inner_cone, light.angle_outer_cone].map(f64::from),
+                    &expected,
+                );
+            }
+        }
+    }
+
+    if c.exact("cameras.len", scene.cameras.len(), dump.cameras.len()) {
+        for (i, (camera, expected)) in scene.cameras.iter().zip(&dump.cameras).enumerate() {
+            let path = format!("cameras[{i}]");
+            c.exact(format_args!("{path}.name"), &*camera.name, &expected.name);
+            if let Some(position) = expected.position {
+                c.floats(
+                    format_args!("{path}.position"),
+                    &f64s(camera.position.to_array()),
+                    &position,
+                );
+            }
+            c.floats(
+                format_args!("{path}.look_at"),
+                &f64s(camera.look_at.to_array()),
+                &expected.look_at,
+            );
+            c.floats(
+                format_args!("{path}.up"),
+                &f64s(camera.up.to_array()),
+                &expected.up,
+            );
+            c.floats(
+                format_args!("{path}.projection"),
+                &[
+                    camera.horizontal_fov,
+                    camera.clip_plane_near,
+                    camera.clip_plane_far,
+                    camera.aspect,
+                ]
+                .map(f64::from),
+                &[
+                    expected.horizontal_fov,
+                    expected.clip_plane_near,
+                    expected.clip_plane_far,
+                    expected.aspect,
+                ],
+            );
+        }
+    }
+    c.mismatches
+}
+
+fn compare_node(
+    c: &mut Comparison<'_>,
+    scene: &AiScene,
+    index: Index<AiNode>,
+    expected: &DumpNode,
+    path: &str,
+) {
+    let Some(node) = scene.get_node_by_index(index) else {
+        c.report(path, format!("invalid node index {}", index.value()));
+        return;
+    };
+    let path = format!("{path}({})", expected.name);
+    c.exact(format_args!("{path}.name"), &node.name, &expected.name);
+    c.floats(
+        format_args!("{path}.transformation"),
+        &matrix_rows(&node.transformation),
+        &expected.transformation,
+    );
+    c.exact(
+        format_args!("{path}.meshes"),
+        node.meshes.clone().collect::<Vec<_>>(),
+        expected.meshes.clone(),
+    );
+    if c.exact(
+        format_args!("{path}.children.len"),
+        node.children.len(),
+        expected.children.len(),
+    ) {
+        for (i, (&child, expected)) in node.children.iter().zip(&expected.children).enumerate() {
+            compare_node(c, scene, child, expected, &format!("{path}.children[{i}]"));
+        }
+    }
+}
+
+fn compare_mesh(
+    c: &mut Comparison<'_>,
+    mesh: &crate::structs::mesh::AiMesh,
+    expected: &DumpMesh,
+    path: &str,
+) {
+    if let Some(name) = &expected.name {
+        c.exact(format_args!("{path}.name"), &mesh.name, name);
+    }
+    c.exact(
+        format_args!("{path}.primitive_types"),
+        mesh.primitive_type.bits(),
+        expected.primitive_types,
+    );
+    c.exact(
+        format_args!("{path}.material_index"),
+        mesh.material_index,
+        expected.material_index,
+    );
+    c.exact(
+        format_args!("{path}.num_vertices"),
+        mesh.vertices.len(),
+        expected.num_vertices,
+    );
+
+    let vectors = |values: &[crate::utils::float_precision::Vec3]| -> Vec<[f64; 3]> {
+        values.iter().map(|v| f64s(v.to_array())).collect()
+    };
+    let streams = [
+        ("vertices", &mesh.vertices, &expected.positions),
+        ("normals", &mesh.normals, &expected.normals),
+        ("tangents", &mesh.tangents, &expected.tangents),
+        ("bitangents", &mesh.bitangents, &expected.bitangents),
+    ];
+    for (name, actual, expected) in streams {
+        match expected {
+            Some(expected) => c.samples(
+                format_args!("{path}.{name}"),
+                &vectors(actual),
+                expected,
+                lexicographic_less,
+            ),
+            None if !actual.is_empty() => {
+                c.report(format_args!("{path}.{name}"), "unexpected".to_owned());
+            }
+            None => {}
+        }
+    }
+
+    let colors = mesh.colors.iter().take_while(|set| !set.is_empty());
+    if c.exact(
+        format_args!("{path}.colors.len"),
+        colors.clone().count(),
+        expected.colors.len(),
+    ) {
+        for (i, (set, expected)) in colors.zip(&expected.colors).enumerate() {
+            let set: Vec<_> = set.iter().map(|color| f64s(color.to_array())).collect();
+            c.samples(
+                format_args!("{path}.colors[{i}]"),
+                &set,
+                expected,
+                lexicographic_less,
+            );
+        }
+    }
+    let channels = mesh.texture_coords.iter().take_while(|set| !set.is_empty());
+    if c.exact(
+        format_args!("{path}.texture_coords.len"),
+        channels.clone().count(),
+        expected.texture_coords.len(),
+    ) {
+        for (i, (coords, (components, expected))) in
+            channels.zip(&expected.texture_coords).enumerate()
+        {
+            c.exact(
+                format_args!("{path}.num_uv_components[{i}]"),
+                mesh.num_of_uv_components[i],
+                *components,
+            );
+            c.samples(
+                format_args!("{path}.texture_coords[{i}]"),
+                &vectors(coords),
+                expected,
+                lexicographic_less,
+            );
+        }
+    }
+
+    if c.exact(
+        format_args!("{path}.faces.len"),
+        mesh.faces.len(),
+        expected.num_faces,
+    ) {
+        match &expected.faces {
+            Faces::Indices(faces) => {
+                let first_difference = mesh
+                    .faces
+                    .iter()
+                    .zip(faces)
+                    .position(|(face, expected)| *face.indices != **expected);
+                if let Some(i) = first_difference {
+                    c.exact(
+                        format_args!("{path}.faces[{i}]"),
+                        &*mesh.faces[i].indices,
+                        &faces[i][..],
+                    );
+                }
+            }
+            Faces::Hashes(hashes) => {
+                let actual = face_hashes(mesh.faces.iter().map(|face| &face.indices[..]));
+                c.exact(format_args!("{path}.faces.hashes"), &actual, hashes);
+            }
+        }
+    }
+
+    if c.exact(
+        format_args!("{path}.bones.len"),
+        mesh.bones.len(),
+        expected.bones.len(),
+    ) {
+        for (i, (bone, expected)) in mesh.bones.iter().zip(&expected.bones).enumerate() {
+            let path = format!("{path}.bones[{i}]");
+            c.exact(format_args!("{path}.name"), &bone.name, &expected.name);
+            c.floats(
+                format_args!("{path}.offset_matrix"),
+                &matrix_rows(&bone.offset_matrix),
+                &expected.offset_matrix,
+            );
+            c.exact(
+                format_args!("{path}.weights.len"),
+                bone.weights.len(),
+                expected.num_weights,
+            );
+            if let Some(weights) = &expected.weights {
+                let difference = bone
+                    .weights
+                    .iter()
+                    .zip(weights)
+                    .position(|(weight, &(id, w))| {
+                        weight.vertex_id != id || !c.tolerance.eq(weight.weight.into(), w)
+                    });
+                if let Some(j) = difference {
+                    let weight = &bone.weights[j];
+                    c.report(
+                        format_args!("{path}.weights[{j}]"),
+                        format!(
+                            "({}, {}) != {:?}",
+                            weight.vertex_id, weight.weight, weights[j]
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The value of a property as it is stored by the C++ library
+fn property_value(property: &AiProperty) -> Option<DumpValue> {
+    // the shading modes are flags here and an enumeration starting at 1 in
+    // the C++ library
+    if let AiProperty::ShadingModel(mode) = property {
+        let value = mode.bits().trailing_zeros() + 1;
+        return Some(DumpValue::Numbers(vec![value.into()]));
+    }
+    if let Some(numbers) = property_numbers(property) {
+        return Some(DumpValue::Numbers(numbers));
+    }
+    match property {
+        AiProperty::String(value) => Some(DumpValue::String(value.clone())),
+        AiProperty::Buffer(data) => Some(DumpValue::Buffer(data.clone())),
+        _ => None,
+    }
+}
+
+fn compare_material(
+    c: &mut Comparison<'_>,
+    properties: &[AiMaterialProperty],
+    expected: &DumpMaterial,
+    path: &str,
+) {
+    for expected in &expected.properties {
+        let path = format!(
+            "{path}.{}[{},{}]",
+            expected.key, expected.semantic, expected.index
+        );
+        let property = properties.iter().find(|p| {
+            p.key == expected.key
+                && p.semantic as u32 == expected.semantic
+                && p.index == expected.index
+        });
+        let Some(property) = property else {
+            c.report(path, "missing".to_owned());
+            continue;
+        };
+        match (property_value(&property.property), &expected.value) {
+            (Some(DumpValue::Numbers(actual)), DumpValue::Numbers(expected)) => {
+                c.floats(path, &actual, expected);
+            }
+            (Some(actual), expected) => {
+                c.exact(path, &actual, expected);
+            }
+            (None, _) => {}
+        }
+    }
+    for property in properties {
+        let known = expected.properties.iter().any(|expected| {
+            property.key == expected.key
+                && property.semantic as u32 == expected.semantic
+                && property.index == expected.index
+        });
+        if !known && property_value(&property.property).is_some() {
+            c.report(
+                format_args!(
+                    "{path}.{}[{},{}]",
+                    property.key, property.semantic as u32, property.index
+                ),
+                "unexpected".to_owned(),
+            );
+        }
+    }
+}
+
+#[allow(unused)]
+mod test {
+    use super::{DumpScene, GoldenError, compare, super_fast_hash};
+    use crate::{
+        structs::{
+            diff::Tolerance,
+            face::AiFace,
+            material::{AI_MATKEY_NAME, AddProperty, AiMaterial},
+            mesh::{AiMesh, PrimitiveTypeFlags},
+            scene_builder::SceneBuilder,
+        },
+        utils::float_precision::{Mat4, Vec3},
+    };
+
+    #[test]
+    fn test_golden_files() {
+        let mut builder = SceneBuilder::new();
+        let root = builder.add_node(
+            "root",
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+            None,
+        );
+        let mesh = builder.add_mesh(AiMesh {
+            primitive_type: PrimitiveTypeFlags::TRIANGLE,
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            faces: vec![AiFace {
+                indices: Box::new([0, 1, 2]),
+            }],
+            ..Default::default()
+        });
+        builder.attach_mesh(root, mesh);
+        let mut material = AiMaterial::default();
+        material.add_property(AI_MATKEY_NAME, "red".to_owned(), 0);
+        builder.add_material(material);
+        let mut scene = builder.build().unwrap();
+
+        // the dump of the scene by `assimp dump -b [-s]`
+        let assbin = |shortened: bool| {
+            let chunk = |out: &mut Vec<u8>, magic: u32, body: Vec<u8>| {
+                out.extend(magic.to_le_bytes());
+                out.extend((body.len() as u32).to_le_bytes());
+                out.extend(body);
+            };
+            let u32s = |values: &[u32]| -> Vec<u8> {
+                values.iter().flat_map(|v| v.to_le_bytes()).collect()
+            };
+            let f32s = |values: &[f32]| -> Vec<u8> {
+                values.iter().flat_map(|v| v.to_le_bytes()).collect()
+            };
+            let mut node = u32s(&[4]);
+            node.extend(b"root");
+            node.extend(f32s(&[
+                1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 1.0, 3.0, 0.0, 0.0, 0.0, 1.0,
+            ]));
+            node.extend(u32s(&[0, 1, 0, 0]));
+            let mut mesh = u32s(&[4, 3, 1, 0, 0, 1]);
+            if shortened {
+                mesh.extend(f32s(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0]));
+                let hash = [3u32, 0, 1, 2]
+                    .iter()
+                    .fold(0, |hash, value| super_fast_hash(&value.to_le_bytes(), hash));
+                mesh.extend(hash.to_le_bytes());
+            } else {
+                mesh.extend(f32s(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]));
+                mesh.extend([3u16, 0, 1, 2].iter().flat_map(|v| v.to_le_bytes()));
+            }
+            let mut property = u32s(&[9]);
+            property.extend(b"?mat.name");
+            property.extend(u32s(&[0, 0, 8, 3, 3]));
+            property.extend(b"red\0");
+            let mut material = u32s(&[1]);
+            chunk(&mut material, 0x123e, property);
+
+            let mut body = u32s(&[0, 1, 1, 0, 0, 0, 0]);
+            chunk(&mut body, 0x123c, node);
+            chunk(&mut body, 0x1237, mesh);
+            chunk(&mut body, 0x123d, material);
+            let mut scene_chunk = Vec::new();
+            chunk(&mut scene_chunk, 0x1239, body);
+
+            let mut data = b"ASSIMP.binary-dump.Thu Jan  1 00:00:00 1970".to_vec();
+            data.resize(44, 0);
+            data.extend(u32s(&[6, 0, 0, 0]));
+            data.extend(
+                [u16::from(shortened), 0]
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes()),
+            );
+            data.resize(512, 0);
+            data.extend(scene_chunk);
+            data
+        };
+        let assjson = br#"{
+            "__metadata__": {"format": "Assimp2Json", "version": 100},
+            "rootnode": {
+                "name": "root",
+                "transformation": [1, 0, 0, 1, 0, 1, 0, 2, 0, 0, 1, 3, 0, 0, 0, 1],
+                "meshes": [0]
+            },
+            "flags": 0,
+            "meshes": [{
+                "name": "",
+                "materialindex": 0,
+                "primitivetypes": 4,
+                "vertices": [0, 0, 0, 1, 0, 0, 0, 1.0e0, 0],
+                "faces": [[0, 1, 2]]
+            }],
+            "materials": [{
+                "properties": [
+                    {"key": "?mat.name", "semantic": 0, "index": 0, "type": 3, "value": "red"}
+                ]
+            }]
+        }"#;
+
+        for data in [assbin(false), assbin(true), assjson.to_vec()] {
+            let dump = DumpScene::from_bytes(&data).unwrap();
+            assert_eq!(compare(&scene, &dump, &Tolerance::default()), []);
+        }
+
+        // differences are reported with their location
+        scene.meshes[0].vertices[1].x = 2.0;
+        scene.meshes[0].faces[0].indices[2] = 1;
+        for data in [assbin(false), assbin(true)] {
+            let dump = DumpScene::from_bytes(&data).unwrap();
+            let paths: Vec<_> = compare(&scene, &dump, &Tolerance::default())
+                .into_iter()
+                .map(|mismatch| mismatch.path)
+                .collect();
+            assert!(
+                paths.iter().any(|p| p.starts_with("meshes[0].vertices")),
+                "{paths:?}"
+            );
+            assert!(
+                paths.iter().any(|p| p.starts_with("meshes[0].faces")),
+                "{paths:?}"
+            );
+        }
+        assert!(matches!(
+            DumpScene::from_bytes(&assbin(false)[..600]),
+            Err(GoldenError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            DumpScene::from_bytes(b"{\"flags\": 0,}"),
+            Err(GoldenError::InvalidJson { .. })
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! Decoding of uncompressed DirectDraw Surface (DDS) files. Only the first
+//! mipmap level of the first surface is read, block compressed formats are
+//! not supported.
+
+use super::{Image, ImageError, scale_to_u8};
+use crate::structs::texture::AiTexel;
+
+/// Magic bytes at the start of every DDS file
+pub const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// Size of the magic and the header
+const HEADER_SIZE: usize = 128;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+pub fn is_dds(data: &[u8]) -> bool {
+    data.starts_with(&DDS_MAGIC)
+}
+
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Extracts a channel from a pixel and scales it to 8 bits, `None` for
+/// missing channels
+fn channel(pixel: u32, mask: u32) -> Option<u8> {
+    if mask == 0 {
+        return None;
+    }
+    let shift = mask.trailing_zeros();
+    Some(scale_to_u8((pixel & mask) >> shift, mask >> shift))
+}
+
+pub fn decode_dds(data: &[u8]) -> Result<Image, ImageError> {
+    if !is_dds(data) {
+        return Err(ImageError::Invalid("missing DDS signature"));
+    }
+    if data.len() < HEADER_SIZE {
+        return Err(ImageError::Invalid("truncated DDS header"));
+    }
+    let (height, width) = (le_u32(data, 12), le_u32(data, 16));
+    let flags = le_u32(data, 80);
+    if flags & DDPF_FOURCC != 0 {
+        let four_cc = String::from_utf8_lossy(&data[84..88]).into_owned();
+        return Err(ImageError::Unsupported(format!("DDS {four_cc}")));
+    }
+    if flags & (DDPF_RGB | DDPF_LUMINANCE | DDPF_ALPHA) == 0 {
+        return Err(ImageError::Unsupported("DDS pixel format".to_owned()));
+    }
+    let bits = le_u32(data, 88);
+    if !matches!(bits, 8 | 16 | 24 | 32) {
+        return Err(ImageError::Invalid("invalid bit count"));
+    }
+    let [red, green, blue, alpha] = [92, 96, 100, 104].map(|offset| le_u32(data, offset));
+    let alpha = if flags & (DDPF_ALPHAPIXELS | DDPF_ALPHA) != 0 {
+        alpha
+    } else {
+        0
+    };
+
+    let bytes = bits as usize / 8;
+    let size = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|count| count.checked_mul(bytes))
+        .ok_or(ImageError::Invalid("image too large"))?;
+    let pixels = data
+        .get(HEADER_SIZE..HEADER_SIZE + size)
+        .ok_or(ImageError::Invalid("not enough image data"))?;
+    let texels = pixels
+        .chunks_exact(bytes)
+        .map(|p| {
+            let pixel = p
+                .iter()
+                .rev()
+                .fold(0u32, |pixel, &b| (pixel << 8) | u32::from(b));
+            let a = channel(pixel, alpha).unwrap_or(255);
+            if flags & DDPF_RGB != 0 {
+                let [r, g, b] = [red, green, blue].map(|mask| channel(pixel, mask).unwrap_or(0));
+                AiTexel::new(b, g, r, a)
+            } else {
+                // luminance in the red mask, or alpha only
+                let l = channel(pixel, red).unwrap_or(255);
+                AiTexel::new(l, l, l, a)
+            }
+        })
+        .collect();
+    Ok(Image {
+        width,
+        height,
+        texels,
+    })
+}
@@ -0,0 +1,140 @@
+//! Decoding of the image files embedded as compressed textures, see
+//! [`AiTexture::decode`](crate::structs::texture::AiTexture::decode).
+//!
+//! PNG and uncompressed DDS files are supported, block compressed DDS and
+//! JPEG files are not.
+
+use thiserror::Error;
+
+use super::compression::error::CompressionError;
+use crate::structs::texture::AiTexel;
+
+pub mod dds;
+pub mod png;
+
+#[derive(Error, Debug)]
+pub enum ImageError {
+    #[error("image format {0} is not supported")]
+    Unsupported(String),
+    #[error("invalid image, {0}")]
+    Invalid(&'static str),
+    #[error("failed to decompress the image")]
+    Compression(#[from] CompressionError),
+}
+
+/// A decoded image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// `width * height` texels, row by row starting at the top
+    pub texels: Vec<AiTexel>,
+}
+
+/// Decodes an image file. The format is detected by the magic bytes, the
+/// hint, usually the file extension, is used for the error message only.
+pub fn decode(data: &[u8], hint: &str) -> Result<Image, ImageError> {
+    if png::is_png(data) {
+        png::decode_png(data)
+    } else if dds::is_dds(data) {
+        dds::decode_dds(data)
+    } else {
+        Err(ImageError::Unsupported(if hint.is_empty() {
+            "unknown".to_owned()
+        } else {
+            hint.to_owned()
+        }))
+    }
+}
+
+/// Scales a value in `[0, max]` to `[0, 255]`
+fn scale_to_u8(value: u32, max: u32) -> u8 {
+    (value * 255 + max / 2).checked_div(max).unwrap_or(0) as u8
+}
+
+#[allow(unused)]
+mod test {
+    use std::fs;
+
+    use super::ImageError;
+    use crate::{
+        structs::texture::{AiTexel, AiTexture},
+        utils::get_model_path,
+    };
+
+    #[test]
+    fn test_decode_texture() {
+        let sum = |texture: &AiTexture| -> u64 {
+            texture
+                .data
+                .iter()
+                .flatten()
+                .map(|t| u64::from(t.r) + u64::from(t.g) + u64::from(t.b) + u64::from(t.a))
+                .sum()
+        };
+        // RGB and palette images, the sums are taken from another decoder
+        for (name, size, first, expected) in [
+            (
+                "textureTransform/Error.png",
+                128,
+                AiTexel::new(0, 0, 192, 255),
+                8292868,
+            ),
+            (
+                "BoxTextured-glTF/CesiumLogoFlat.png",
+                211,
+                AiTexel::new(220, 220, 220, 255),
+                34379832,
+            ),
+        ] {
+            let bytes = fs::read(get_model_path("glTF2", name)).unwrap();
+            let texture = AiTexture::from_compressed(&bytes, "png");
+            assert!(texture.is_compressed());
+            assert_eq!(texture.format_hint(), "png");
+            assert_eq!(texture.compressed_data(), Some(bytes));
+            let decoded = texture.decode().unwrap();
+            assert_eq!((decoded.width, decoded.height), (size, size));
+            assert_eq!(decoded.format_hint(), "");
+            assert_eq!(decoded.data[0][0], first);
+            assert_eq!(sum(&decoded), expected);
+        }
+
+        // a 2x1 A8R8G8B8 surface
+        let mut dds = vec![0u8; 128];
+        dds[..4].copy_from_slice(b"DDS ");
+        for (offset, value) in [
+            (4, 124),
+            (12, 1),
+            (16, 2),
+            (76, 32),
+            (80, 0x41),
+            (88, 32),
+            (92, 0x00ff_0000),
+            (96, 0x0000_ff00),
+            (100, 0x0000_00ff),
+            (104, 0xff00_0000),
+        ] {
+            dds[offset..offset + 4].copy_from_slice(&u32::to_le_bytes(value));
+        }
+        dds.extend([1, 2, 3, 4, 5, 6, 7, 8]);
+        let decoded = AiTexture::from_compressed(&dds, "dds").decode().unwrap();
+        assert_eq!((decoded.width, decoded.height), (2, 1));
+        assert_eq!(
+            *decoded.data[0],
+            [AiTexel::new(1, 2, 3, 4), AiTexel::new(5, 6, 7, 8)]
+        );
+
+        // uncompressed textures are returned as they are
+        assert_eq!(decoded.decode().unwrap().data, decoded.data);
+        assert!(matches!(
+            AiTexture::from_compressed(&[0xff, 0xd8, 0xff], "jpg").decode(),
+            Err(ImageError::Unsupported(hint)) if hint == "jpg"
+        ));
+        dds[80] = 0x4;
+        dds[84..88].copy_from_slice(b"DXT1");
+        assert!(matches!(
+            AiTexture::from_compressed(&dds, "dds").decode(),
+            Err(ImageError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+//! Decoding of PNG files as described in the W3C PNG specification.
+//! Interlaced images are not supported.
+
+use super::{Image, ImageError, scale_to_u8};
+use crate::{structs::texture::AiTexel, utils::compression::Compression};
+
+/// Magic bytes at the start of every PNG file
+pub const PNG_MAGIC: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const COLOR_GRAY: u8 = 0;
+const COLOR_RGB: u8 = 2;
+const COLOR_PALETTE: u8 = 3;
+const COLOR_GRAY_ALPHA: u8 = 4;
+const COLOR_RGBA: u8 = 6;
+
+pub fn is_png(data: &[u8]) -> bool {
+    data.starts_with(&PNG_MAGIC)
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_RGB => 3,
+            COLOR_GRAY_ALPHA => 2,
+            COLOR_RGBA => 4,
+            _ => 1,
+        }
+    }
+
+    /// Bytes of one row without the filter type
+    fn row_size(&self) -> usize {
+        (self.width as usize * self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    /// Distance of the bytes compared by the filters
+    fn filter_distance(&self) -> usize {
+        (self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+}
+
+fn be_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+pub fn decode_png(data: &[u8]) -> Result<Image, ImageError> {
+    if !is_png(data) {
+        return Err(ImageError::Invalid("missing PNG signature"));
+    }
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    let mut rest = &data[PNG_MAGIC.len()..];
+    while rest.len() >= 12 {
+        let length = be_u32(rest) as usize;
+        let Some(chunk) = rest.get(8..8 + length) else {
+            return Err(ImageError::Invalid("truncated chunk"));
+        };
+        match &rest[4..8] {
+            b"IHDR" if chunk.len() >= 13 => {
+                if chunk[12] != 0 {
+                    return Err(ImageError::Unsupported("interlaced PNG".to_owned()));
+                }
+                header = Some(Header {
+                    width: be_u32(chunk),
+                    height: be_u32(&chunk[4..]),
+                    bit_depth: chunk[8],
+                    color_type: chunk[9],
+                });
+            }
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        // skip the checksum as well
+        rest = rest.get(12 + length..).unwrap_or_default();
+    }
+    let header = header.ok_or(ImageError::Invalid("missing IHDR chunk"))?;
+    let valid_depth = match header.color_type {
+        COLOR_GRAY => matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16),
+        COLOR_PALETTE => matches!(header.bit_depth, 1 | 2 | 4 | 8),
+        COLOR_RGB | COLOR_GRAY_ALPHA | COLOR_RGBA => matches!(header.bit_depth, 8 | 16),
+        _ => false,
+    };
+    if !valid_depth {
+        return Err(ImageError::Invalid("invalid color type or bit depth"));
+    }
+    if header.color_type == COLOR_PALETTE && palette.is_empty() {
+        return Err(ImageError::Invalid("missing PLTE chunk"));
+    }
+
+    let row_size = header.row_size();
+    let expected = (row_size + 1)
+        .checked_mul(header.height as usize)
+        .ok_or(ImageError::Invalid("image too large"))?;
+    let mut raw = Vec::with_capacity(expected);
+    Compression::builder()
+        .open()?
+        .decompress(&compressed, &mut raw)?;
+    if raw.len() < expected {
+        return Err(ImageError::Invalid("not enough image data"));
+    }
+
+    let distance = header.filter_distance();
+    let mut previous = vec![0u8; row_size];
+    let mut row = vec![0u8; row_size];
+    let mut texels = Vec::with_capacity(header.width as usize * header.height as usize);
+    for line in raw[..expected].chunks_exact(row_size + 1) {
+        row.copy_from_slice(&line[1..]);
+        unfilter(line[0], &mut row, &previous, distance)?;
+        read_row(&header, &row, palette, transparency, &mut texels);
+        core::mem::swap(&mut row, &mut previous);
+    }
+    Ok(Image {
+        width: header.width,
+        height: header.height,
+        texels,
+    })
+}
+
+/// Reverses the filter of a row in place
+fn unfilter(
+    filter: u8,
+    row: &mut [u8],
+    previous: &[u8],
+    distance: usize,
+) -> Result<(), ImageError> {
+    for i in 0..row.len() {
+        let left = if i >= distance { row[i - distance] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= distance {
+            previous[i - distance]
+        } else {
+            0
+        };
+        let predictor = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err(ImageError::Invalid("unknown filter type")),
+        };
+        row[i] = row[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Converts an unfiltered row to texels
+fn read_row(
+    header: &Header,
+    row: &[u8],
+    palette: &[u8],
+    transparency: &[u8],
+    texels: &mut Vec<AiTexel>,
+) {
+    let depth = u32::from(header.bit_depth);
+    let max = (1u32 << depth) - 1;
+    // the raw value of the n-th sample of the row
+    let sample = |n: usize| -> u32 {
+        match depth {
+            16 => u32::from(u16::from_be_bytes([row[n * 2], row[n * 2 + 1]])),
+            8 => u32::from(row[n]),
+            _ => {
+                let bit = n * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                (u32::from(row[bit / 8]) >> shift) & max
+            }
+        }
+    };
+    // the transparent color key of gray and RGB images
+    let key = |n: usize| -> Option<u32> {
+        transparency
+            .get(n * 2..n * 2 + 2)
+            .map(|v| u32::from(u16::from_be_bytes([v[0], v[1]])))
+    };
+    let channels = header.channels();
+    for x in 0..header.width as usize {
+        let s = |c: usize| sample(x * channels + c);
+        let v = |c: usize| scale_to_u8(s(c), max);
+        let texel = match header.color_type {
+            COLOR_GRAY => {
+                let a = if key(0) == Some(s(0)) { 0 } else { 255 };
+                AiTexel::new(v(0), v(0), v(0), a)
+            }
+            COLOR_RGB => {
+                let transparent = (0..3).all(|c| key(c) == Some(s(c)));
+                AiTexel::new(v(2), v(1), v(0), if transparent { 0 } else { 255 })
+            }
+            COLOR_PALETTE => {
+                let index = s(0) as usize;
+                let color = palette.get(index * 3..index * 3 + 3).unwrap_or(&[0; 3]);
+                let a = transparency.get(index).copied().unwrap_or(255);
+                AiTexel::new(color[2], color[1], color[0], a)
+            }
+            COLOR_GRAY_ALPHA => AiTexel::new(v(0), v(0), v(0), v(1)),
+            _ => AiTexel::new(v(2), v(1), v(0), v(3)),
+        };
+        texels.push(texel);
+    }
+}
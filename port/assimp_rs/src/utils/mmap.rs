@@ -0,0 +1,132 @@
+//! Read-only memory mapping of files, used to import large files without
+//! copying them into memory first.
+//!
+//! Only 64 bit unix targets map the file, everywhere else and with the
+//! `paranoid` feature the file is read into a buffer instead.
+
+use core::ops::Deref;
+use std::{fs::File, io};
+
+#[cfg(all(unix, target_pointer_width = "64", not(feature = "paranoid")))]
+mod sys {
+    use core::ffi::{c_int, c_void};
+    use std::{fs::File, io, os::fd::AsRawFd};
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 2;
+    const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    unsafe extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+
+    pub struct Map {
+        ptr: *mut c_void,
+        len: usize,
+    }
+
+    impl Map {
+        pub unsafe fn new(file: &File, len: usize) -> io::Result<Self> {
+            if len == 0 {
+                // mapping zero bytes is an error
+                return Ok(Self {
+                    ptr: core::ptr::null_mut(),
+                    len,
+                });
+            }
+            // SAFETY: a new private read-only mapping does not alias any memory
+            let ptr = unsafe {
+                mmap(
+                    core::ptr::null_mut(),
+                    len,
+                    PROT_READ,
+                    MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr == MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { ptr, len })
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            if self.len == 0 {
+                return &[];
+            }
+            // SAFETY: the mapping is valid for `len` bytes until it is dropped
+            unsafe { core::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Map {
+        fn drop(&mut self) {
+            if self.len != 0 {
+                // SAFETY: ptr and len are the values returned by and passed to mmap
+                unsafe { munmap(self.ptr, self.len) };
+            }
+        }
+    }
+
+    // SAFETY: the mapping is read-only
+    unsafe impl Send for Map {}
+    unsafe impl Sync for Map {}
+}
+
+#[cfg(not(all(unix, target_pointer_width = "64", not(feature = "paranoid"))))]
+mod sys {
+    use std::{fs::File, io, io::Read};
+
+    pub struct Map(Vec<u8>);
+
+    impl Map {
+        pub unsafe fn new(mut file: &File, len: usize) -> io::Result<Self> {
+            let mut data = Vec::with_capacity(len);
+            file.read_to_end(&mut data)?;
+            Ok(Self(data))
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            &self.0
+        }
+    }
+}
+
+/// A read-only view of a whole file
+pub struct Mmap {
+    map: sys::Map,
+}
+
+impl Mmap {
+    /// Maps the whole file into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the map is alive,
+    /// otherwise the contents of the slice change under our feet or reading
+    /// it crashes the process.
+    pub unsafe fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "file is too large to map"))?;
+        // SAFETY: forwarded to the caller
+        let map = unsafe { sys::Map::new(file, len)? };
+        Ok(Self { map })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.map.as_slice()
+    }
+}
@@ -1,38 +1,32 @@
+pub mod alloc_budget;
 #[cfg(feature = "compression")]
 pub mod compression;
 pub mod fast_atof;
 pub mod float_precision;
+#[cfg(test)]
+#[allow(unused)]
+pub mod golden;
+#[cfg(feature = "image")]
+pub mod image;
+#[cfg(feature = "mmap")]
+pub mod mmap;
 #[allow(unused)]
 pub mod read;
 
-use std::{env, ffi::OsString, fs::read_dir, io, io::ErrorKind, path::PathBuf};
+use std::path::{Path, PathBuf};
 
 pub use float_precision::AiReal;
 
-pub(crate) fn get_project_root() -> io::Result<PathBuf> {
-    let path = env::current_dir()?;
-    let mut path_ancestors = path.as_path().ancestors();
-
-    while let Some(p) = path_ancestors.next() {
-        let has_cargo = read_dir(p)?
-            .into_iter()
-            .any(|p| p.unwrap().file_name() == OsString::from("Cargo.toml"));
-        if has_cargo {
-            return Ok(PathBuf::from(p));
-        }
-    }
-    Err(io::Error::new(
-        ErrorKind::NotFound,
-        "Ran out of places to find Cargo.toml",
-    ))
-}
-
-pub(crate) fn get_model_path(model_format: &str, model_name: &str) -> PathBuf {
-    let project_root = get_project_root().unwrap();
-    let mut path_ancestors = project_root.as_path().ancestors();
-    path_ancestors
+/// Path of a model in the `test/models` directory of the assimp source
+/// tree, e.g. `get_model_path("X", "test.x")`, for tests and benchmarks.
+///
+/// The directory is found relative to the manifest of this crate, so the
+/// path does not depend on the working directory.
+pub fn get_model_path(model_format: &str, model_name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .ancestors()
         .nth(2)
-        .unwrap()
+        .expect("the crate is located in port/assimp_rs of the assimp tree")
         .join("test")
         .join("models")
         .join(model_format)
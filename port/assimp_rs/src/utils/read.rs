@@ -33,9 +33,10 @@ impl<'a> BinaryReader<'a> {
 #[inline]
 pub fn parse_4digits_decimal(mut v: u32) -> u32 {
     let radix = 10;
-    v -= 0x3030_3030;
+    // wrapping, so invalid digits give a wrong result instead of a panic
+    v = v.wrapping_sub(0x3030_3030);
     // Scale digits in `0 <= Nn <= 99`.
-    v = (v * radix) + (v >> 8);
+    v = v.wrapping_mul(radix).wrapping_add(v >> 8);
     // Scale digits in `0 <= Nnnn <= 9999`.
     v = ((v & 0x0000007f) * radix * radix) + ((v >> 16) & 0x0000007f);
 